@@ -0,0 +1,50 @@
+//! Records the git commit for `build_info::GIT_HASH`.
+//!
+//! Source tarballs have no `.git`, so packagers can set `RTP_OPUS_GIT_HASH`
+//! themselves; otherwise it is `unknown`.
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    // ---
+    println!("cargo:rerun-if-env-changed=RTP_OPUS_GIT_HASH");
+
+    let hash = std::env::var("RTP_OPUS_GIT_HASH")
+        .ok()
+        .filter(|hash| !hash.is_empty())
+        .or_else(git_hash)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RTP_OPUS_GIT_HASH={hash}");
+
+    // Rebuild when the checked-out commit moves. Only existing paths are
+    // listed: Cargo reruns every build for a missing one.
+    let git_dir = Path::new("../.git");
+    let head = git_dir.join("HEAD");
+    if head.exists() {
+        println!("cargo:rerun-if-changed={}", head.display());
+        if let Some(branch) = std::fs::read_to_string(&head)
+            .ok()
+            .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string()))
+        {
+            let branch = git_dir.join(branch);
+            if branch.exists() {
+                println!("cargo:rerun-if-changed={}", branch.display());
+            }
+        }
+    }
+}
+
+/// Returns the short hash of `HEAD`, or `None` outside a git checkout.
+fn git_hash() -> Option<String> {
+    // ---
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!hash.is_empty()).then_some(hash)
+}
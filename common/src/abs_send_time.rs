@@ -0,0 +1,100 @@
+//! abs-send-time RTP header extension.
+//!
+//! The sender stamps each packet with the time it went out, as the low
+//! 24 bits of an NTP timestamp in 6.18 fixed point: 6 bits of seconds and
+//! 18 of fraction, wrapping every 64 seconds. Sender and receiver clocks
+//! need not agree; differences between stamps and between arrival times
+//! give the change in one-way delay. Carried in a one-byte header
+//! extension element (see [`RtpPacket::set_extension_element`]) whose ID
+//! is negotiated in SDP against [`ABS_SEND_TIME_URI`].
+//!
+//! [`RtpPacket::set_extension_element`]: crate::RtpPacket::set_extension_element
+
+use anyhow::Result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// URI identifying the extension in an SDP `a=extmap` line
+pub const ABS_SEND_TIME_URI: &str = "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time";
+
+/// Size of the extension element data in bytes
+pub const ABS_SEND_TIME_LEN: usize = 3;
+
+/// Timestamp units per second (18 fractional bits)
+pub const ABS_SEND_TIME_UNITS_PER_SECOND: u32 = 1 << 18;
+
+/// Period after which the timestamp wraps
+pub const ABS_SEND_TIME_WRAP: Duration = Duration::from_secs(64);
+
+/// Mask of the 24 bits carried
+const ABS_SEND_TIME_MASK: u64 = 0xFF_FFFF;
+
+/// Encodes `time`, measured from the NTP or UNIX epoch (both whole
+/// multiples of 64 seconds apart, so either gives the same stamp).
+pub fn encode_abs_send_time(time: Duration) -> [u8; ABS_SEND_TIME_LEN] {
+    // ---
+    let units = (time.as_nanos() * ABS_SEND_TIME_UNITS_PER_SECOND as u128 / 1_000_000_000) as u64
+        & ABS_SEND_TIME_MASK;
+    let [_, _, _, _, _, high, mid, low] = units.to_be_bytes();
+    [high, mid, low]
+}
+
+/// Encodes the current wall-clock time.
+pub fn abs_send_time_now() -> [u8; ABS_SEND_TIME_LEN] {
+    // ---
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    encode_abs_send_time(now)
+}
+
+/// Decodes extension element data into the 24-bit timestamp, in
+/// [`ABS_SEND_TIME_UNITS_PER_SECOND`] units.
+///
+/// # Errors
+///
+/// Returns error if `data` is not [`ABS_SEND_TIME_LEN`] bytes long.
+pub fn decode_abs_send_time(data: &[u8]) -> Result<u32> {
+    // ---
+    let &[high, mid, low] = data else {
+        anyhow::bail!(
+            "abs-send-time of {} bytes (expected {})",
+            data.len(),
+            ABS_SEND_TIME_LEN
+        );
+    };
+    Ok(u32::from_be_bytes([0, high, mid, low]))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_encodes_six_dot_eighteen_fixed_point() {
+        // ---
+        assert_eq!(encode_abs_send_time(Duration::ZERO), [0, 0, 0]);
+        assert_eq!(encode_abs_send_time(Duration::from_secs(1)), [0x04, 0, 0]);
+        assert_eq!(
+            encode_abs_send_time(Duration::from_millis(500)),
+            [0x02, 0, 0]
+        );
+
+        // 63.999... seconds is the top of the range; 64 wraps to zero
+        let top = ABS_SEND_TIME_WRAP - Duration::from_nanos(1);
+        assert_eq!(encode_abs_send_time(top), [0xFF, 0xFF, 0xFF]);
+        assert_eq!(encode_abs_send_time(ABS_SEND_TIME_WRAP), [0, 0, 0]);
+        assert_eq!(
+            encode_abs_send_time(ABS_SEND_TIME_WRAP * 1000 + Duration::from_secs(3)),
+            [0x0C, 0, 0]
+        );
+
+        let stamp = encode_abs_send_time(Duration::from_micros(12_345_678));
+        let units = decode_abs_send_time(&stamp).unwrap();
+        let seconds = units as f64 / ABS_SEND_TIME_UNITS_PER_SECOND as f64;
+        assert!((seconds - 12.345_678).abs() < 1e-5, "{seconds}");
+
+        assert!(decode_abs_send_time(&[1, 2]).is_err());
+        assert!(decode_abs_send_time(&[1, 2, 3, 4]).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! What a binary was built from.
+//!
+//! Packagers build the crates with different feature sets, so a running
+//! binary has to be able to say which one it is. [`BuildInfo`] collects the
+//! crate versions, the git commit (recorded by the build script, or taken
+//! from `RTP_OPUS_GIT_HASH` when building outside a checkout), and the
+//! enabled Cargo features. Binaries print it in their startup banner and
+//! for `--version --verbose`, and export it as the `rtp_opus_build_info`
+//! metric.
+
+use std::ffi::OsString;
+use std::fmt;
+
+/// Version of this crate
+pub const COMMON_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Commit the workspace was built from, or `unknown`
+pub const GIT_HASH: &str = env!("RTP_OPUS_GIT_HASH");
+
+/// Name this crate is listed under
+const COMMON_CRATE: &str = "rtp-opus-common";
+
+/// Features of this crate, with whether each is enabled in this build.
+const COMMON_FEATURES: [(&str, bool); 2] = [
+    (
+        "rtp-opus-common/metrics-server",
+        cfg!(feature = "metrics-server"),
+    ),
+    ("rtp-opus-common/sim", cfg!(feature = "sim")),
+];
+
+/// Versions, commit, and enabled features of a binary and the crates
+/// linked into it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    // ---
+    /// Binary (or library) this describes
+    binary: &'static str,
+
+    /// Version of `binary`
+    version: &'static str,
+
+    /// Every crate linked in, with its version, `binary` first
+    crates: Vec<(&'static str, &'static str)>,
+
+    /// Enabled features as `crate/feature`, in order added
+    features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    // ---
+    /// Describes `binary` at `version`, linked against this crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `binary` - Crate or binary name
+    /// * `version` - Its version, normally `env!("CARGO_PKG_VERSION")`
+    pub fn new(binary: &'static str, version: &'static str) -> Self {
+        // ---
+        let mut info = Self {
+            binary,
+            version,
+            crates: vec![(binary, version)],
+            features: Vec::new(),
+        };
+        info.add_crate(COMMON_CRATE, COMMON_VERSION);
+        for (feature, enabled) in COMMON_FEATURES {
+            info = info.with_feature(feature, enabled);
+        }
+        info
+    }
+
+    /// Records `feature` if it is enabled.
+    ///
+    /// Crates pass `cfg!(feature = "...")` so the list reflects the build.
+    pub fn with_feature(mut self, feature: &'static str, enabled: bool) -> Self {
+        // ---
+        if enabled && !self.features.contains(&feature) {
+            self.features.push(feature);
+        }
+        self
+    }
+
+    /// Adds the crates and features of a library linked into this binary.
+    pub fn including(mut self, other: BuildInfo) -> Self {
+        // ---
+        for (name, version) in other.crates {
+            self.add_crate(name, version);
+        }
+        for feature in other.features {
+            self = self.with_feature(feature, true);
+        }
+        self
+    }
+
+    /// Returns the binary name.
+    pub fn binary(&self) -> &'static str {
+        // ---
+        self.binary
+    }
+
+    /// Returns the binary version.
+    pub fn version(&self) -> &'static str {
+        // ---
+        self.version
+    }
+
+    /// Returns the commit the workspace was built from.
+    pub fn git_hash(&self) -> &'static str {
+        // ---
+        GIT_HASH
+    }
+
+    /// Returns every crate linked in, with its version.
+    pub fn crates(&self) -> &[(&'static str, &'static str)] {
+        // ---
+        &self.crates
+    }
+
+    /// Returns the enabled features as `crate/feature`.
+    pub fn features(&self) -> &[&'static str] {
+        // ---
+        &self.features
+    }
+
+    /// Returns true if `feature` (as `crate/feature`) is enabled.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        // ---
+        self.features.contains(&feature)
+    }
+
+    /// Returns the multi-line report printed for `--version --verbose`.
+    pub fn verbose(&self) -> String {
+        // ---
+        let mut out = format!(
+            "{} {}\ngit: {}\ncrates:\n",
+            self.binary, self.version, GIT_HASH
+        );
+        for (name, version) in &self.crates {
+            out.push_str(&format!("  {name} {version}\n"));
+        }
+        out.push_str("features:\n");
+        if self.features.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        for feature in &self.features {
+            out.push_str(&format!("  {feature}\n"));
+        }
+        out
+    }
+
+    /// Records a linked crate unless already listed.
+    fn add_crate(&mut self, name: &'static str, version: &'static str) {
+        // ---
+        if !self.crates.iter().any(|(listed, _)| *listed == name) {
+            self.crates.push((name, version));
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    /// One-line form used in startup banners.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(f, "{} v{} ({}", self.binary, self.version, GIT_HASH)?;
+        if self.features.is_empty() {
+            write!(f, ", no optional features)")
+        } else {
+            write!(f, "; features: {})", self.features.join(", "))
+        }
+    }
+}
+
+/// Prints the verbose build report and exits if the command line asks
+/// for `--version --verbose`.
+///
+/// Call before parsing arguments: clap answers `--version` on its own and
+/// would exit before seeing `--verbose`. Binaries should also declare a
+/// `--verbose` flag so clap accepts it.
+pub fn handle_verbose_version(info: &BuildInfo) {
+    // ---
+    if wants_verbose_version(std::env::args_os()) {
+        print!("{}", info.verbose());
+        std::process::exit(0);
+    }
+}
+
+/// Returns true if `args` contain both `--version` (or `-V`) and `--verbose`.
+fn wants_verbose_version(args: impl IntoIterator<Item = OsString>) -> bool {
+    // ---
+    let (mut version, mut verbose) = (false, false);
+    for arg in args {
+        if arg == "--" {
+            break;
+        }
+        version |= arg == "--version" || arg == "-V";
+        verbose |= arg == "--verbose";
+    }
+    version && verbose
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_build_info_lists_crates_and_enabled_features() {
+        // ---
+        let info = BuildInfo::new("tool", "1.2.3")
+            .with_feature("tool/fast", true)
+            .with_feature("tool/slow", false)
+            .including(BuildInfo::new("lib", "0.1.0").with_feature("lib/extra", true));
+
+        assert_eq!(
+            info.crates(),
+            [
+                ("tool", "1.2.3"),
+                (COMMON_CRATE, COMMON_VERSION),
+                ("lib", "0.1.0")
+            ]
+        );
+        assert!(info.has_feature("tool/fast"));
+        assert!(!info.has_feature("tool/slow"));
+        assert!(info.has_feature("lib/extra"));
+        assert_eq!(
+            info.has_feature("rtp-opus-common/sim"),
+            cfg!(feature = "sim")
+        );
+
+        let verbose = info.verbose();
+        assert!(verbose.starts_with("tool 1.2.3\n"));
+        assert!(verbose.contains("  lib 0.1.0\n"));
+        assert!(verbose.contains("  tool/fast\n"));
+        assert!(info.to_string().starts_with("tool v1.2.3 ("));
+        assert!(!GIT_HASH.is_empty());
+    }
+
+    #[test]
+    fn test_wants_verbose_version() {
+        // ---
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+        assert!(wants_verbose_version(args(&[
+            "bin",
+            "--version",
+            "--verbose"
+        ])));
+        assert!(wants_verbose_version(args(&["bin", "--verbose", "-V"])));
+        assert!(!wants_verbose_version(args(&["bin", "--version"])));
+        assert!(!wants_verbose_version(args(&["bin", "--verbose"])));
+        assert!(!wants_verbose_version(args(&[
+            "bin",
+            "--",
+            "--version",
+            "--verbose"
+        ])));
+    }
+
+    /// Smoke test of the feature-gated parts of this crate in whatever
+    /// feature combination it was built with.
+    #[test]
+    fn test_feature_gated_constructors() {
+        // ---
+        let metrics = crate::MetricsContext::new("features").expect("metrics");
+        metrics.set_build_info(&BuildInfo::new("features", "0.0.0"));
+        assert!(metrics
+            .gather()
+            .iter()
+            .any(|family| family.get_name().ends_with("rtp_opus_build_info")));
+
+        #[cfg(feature = "metrics-server")]
+        {
+            let config = crate::MetricsServerConfig::new("127.0.0.1:0".parse().unwrap());
+            assert_eq!(config.bind.ip().to_string(), "127.0.0.1");
+        }
+
+        #[cfg(feature = "sim")]
+        {
+            let mut sim = crate::NetworkSimulator::new(crate::NetworkSimulatorConfig::default());
+            sim.send(crate::RtpPacket::new(0, 0, 1, vec![0; 4]));
+            assert!(sim.receive().is_some());
+        }
+    }
+}
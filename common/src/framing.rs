@@ -0,0 +1,94 @@
+//! RTP over TCP framing.
+//!
+//! Implements the framing of RFC 4571: on a connection-oriented transport,
+//! each RTP packet is preceded by its length as a 16-bit big-endian
+//! integer, so the receiver can split the byte stream back into packets.
+
+use anyhow::Result;
+
+/// Size of the length prefix before each framed packet
+pub const FRAME_HEADER_LEN: usize = 2;
+
+/// Largest packet a frame can carry (the length is 16 bits)
+pub const MAX_FRAME_LEN: usize = u16::MAX as usize;
+
+/// How RTP packets travel between sender and receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transport {
+    /// One packet per UDP datagram (RFC 3550).
+    #[default]
+    Udp,
+
+    /// Length-prefixed packets over a TCP connection (RFC 4571), for
+    /// networks that block UDP.
+    Tcp,
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ---
+        f.write_str(match self {
+            Transport::Udp => "UDP",
+            Transport::Tcp => "TCP",
+        })
+    }
+}
+
+/// Appends `packet` to `out` as one RFC 4571 frame.
+///
+/// # Errors
+///
+/// Returns error if the packet is longer than [`MAX_FRAME_LEN`].
+pub fn append_frame(packet: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    // ---
+    let Ok(len) = u16::try_from(packet.len()) else {
+        anyhow::bail!(
+            "{} byte packet does not fit in a frame (at most {})",
+            packet.len(),
+            MAX_FRAME_LEN
+        );
+    };
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(packet);
+    Ok(())
+}
+
+/// Returns the length of the packet in the first complete frame of
+/// `buf`, or `None` until all of it has arrived.
+///
+/// The packet starts [`FRAME_HEADER_LEN`] bytes into `buf`; the frame
+/// takes `FRAME_HEADER_LEN` plus the returned length.
+pub fn complete_frame(buf: &[u8]) -> Option<usize> {
+    // ---
+    let header: [u8; FRAME_HEADER_LEN] = buf.get(..FRAME_HEADER_LEN)?.try_into().ok()?;
+    let len = u16::from_be_bytes(header) as usize;
+    (buf.len() >= FRAME_HEADER_LEN + len).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_frames_round_trip_in_pieces() {
+        // ---
+        let mut stream = Vec::new();
+        append_frame(&[1, 2, 3], &mut stream).unwrap();
+        append_frame(&[], &mut stream).unwrap();
+        append_frame(&[4; 300], &mut stream).unwrap();
+        assert_eq!(&stream[..5], [0, 3, 1, 2, 3]);
+        assert_eq!(&stream[5..9], [0, 0, 1, 44]);
+
+        // Nothing is complete until the whole frame is there
+        assert_eq!(complete_frame(&stream[..1]), None);
+        assert_eq!(complete_frame(&stream[..4]), None);
+        assert_eq!(complete_frame(&stream[..5]), Some(3));
+        assert_eq!(complete_frame(&stream[5..]), Some(0));
+        assert_eq!(complete_frame(&stream[7..stream.len() - 1]), None);
+        assert_eq!(complete_frame(&stream[7..]), Some(300));
+
+        assert!(append_frame(&vec![0; MAX_FRAME_LEN], &mut Vec::new()).is_ok());
+        assert!(append_frame(&vec![0; MAX_FRAME_LEN + 1], &mut Vec::new()).is_err());
+    }
+}
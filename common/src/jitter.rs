@@ -0,0 +1,176 @@
+//! RFC 3550 interarrival jitter.
+//!
+//! The canonical RTP quality metric, and the jitter field of an RTCP
+//! receiver report: the smoothed mean deviation of the spacing of packets
+//! at the receiver from their spacing at the sender, as given by their RTP
+//! timestamps (RFC 3550 §6.4.1). Measured in timestamp units, so it needs
+//! the stream's clock rate.
+
+use std::time::{Duration, Instant};
+
+/// Gain of the running average: each packet moves the estimate 1/16 of
+/// the way to its deviation (RFC 3550 §6.4.1)
+const GAIN: f64 = 1.0 / 16.0;
+
+/// Estimates the interarrival jitter of an RTP stream.
+///
+/// Timestamps are compared as wrapping differences, and an arrival before
+/// the previous one counts as a negative spacing, so timestamp wraparound
+/// and reordered packets are measured rather than mistaken for huge
+/// deviations.
+#[derive(Debug, Clone)]
+pub struct JitterEstimator {
+    // ---
+    /// RTP clock rate in Hz
+    clock_rate: u32,
+
+    /// Arrival time and RTP timestamp of the previous packet
+    last: Option<(Instant, u32)>,
+
+    /// Smoothed jitter in timestamp units
+    jitter: f64,
+}
+
+impl JitterEstimator {
+    // ---
+    /// Creates an estimator for a stream with `clock_rate` (Hz) timestamps.
+    pub fn new(clock_rate: u32) -> Self {
+        // ---
+        Self {
+            clock_rate: clock_rate.max(1),
+            last: None,
+            jitter: 0.0,
+        }
+    }
+
+    /// Updates the estimate with a packet stamped `timestamp` arriving at
+    /// `arrival`.
+    pub fn update(&mut self, timestamp: u32, arrival: Instant) {
+        // ---
+        if let Some((prev_arrival, prev_timestamp)) = self.last {
+            let arrival_delta = if arrival >= prev_arrival {
+                arrival.duration_since(prev_arrival).as_secs_f64()
+            } else {
+                -prev_arrival.duration_since(arrival).as_secs_f64()
+            };
+
+            // D(i-1, i): the change in relative transit time (RFC 3550 A.8)
+            let sent_delta = timestamp.wrapping_sub(prev_timestamp) as i32 as f64;
+            let d = arrival_delta * self.clock_rate as f64 - sent_delta;
+            self.jitter += (d.abs() - self.jitter) * GAIN;
+        }
+        self.last = Some((arrival, timestamp));
+    }
+
+    /// Forgets the previous packet and the estimate, for a new stream.
+    pub fn reset(&mut self) {
+        // ---
+        self.last = None;
+        self.jitter = 0.0;
+    }
+
+    /// Returns the clock rate the estimate is measured in.
+    pub fn clock_rate(&self) -> u32 {
+        // ---
+        self.clock_rate
+    }
+
+    /// Returns the current estimate in RTP timestamp units, as an RTCP
+    /// receiver report carries it.
+    pub fn jitter_units(&self) -> f64 {
+        // ---
+        self.jitter
+    }
+
+    /// Returns the current estimate in milliseconds.
+    pub fn jitter_ms(&self) -> f64 {
+        // ---
+        self.jitter * 1000.0 / self.clock_rate as f64
+    }
+
+    /// Returns the current estimate.
+    pub fn jitter(&self) -> Duration {
+        // ---
+        Duration::from_secs_f64(self.jitter / self.clock_rate as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    const CLOCK_RATE: u32 = 48_000;
+
+    /// Timestamp units in 20ms at [`CLOCK_RATE`]
+    const FRAME: u32 = 960;
+
+    /// Feeds `count` packets, 20ms apart from timestamp `first`, each
+    /// arriving `delay_ms(index)` after it was sent
+    fn feed(first: u32, count: u32, delay_ms: impl Fn(u32) -> u64) -> JitterEstimator {
+        // ---
+        let mut estimator = JitterEstimator::new(CLOCK_RATE);
+        let start = Instant::now();
+        for index in 0..count {
+            let sent = start + Duration::from_millis(20 * index as u64);
+            let timestamp = first.wrapping_add(index * FRAME);
+            estimator.update(timestamp, sent + Duration::from_millis(delay_ms(index)));
+        }
+        estimator
+    }
+
+    #[test]
+    fn test_constant_delay_has_no_jitter() {
+        // ---
+        let estimator = feed(0, 500, |_| 35);
+        assert!(estimator.jitter_units() < 0.01);
+        assert!(estimator.jitter_ms() < 0.001);
+        assert_eq!(estimator.clock_rate(), CLOCK_RATE);
+    }
+
+    #[test]
+    fn test_converges_on_alternating_and_sawtooth_delay() {
+        // ---
+        // Every other packet 10ms late: each deviation is 10ms, which the
+        // estimate approaches geometrically
+        let estimator = feed(0, 500, |index| 10 * (index % 2) as u64);
+        assert!((estimator.jitter_ms() - 10.0).abs() < 0.01);
+        assert!((estimator.jitter_units() - 480.0).abs() < 0.5);
+
+        // Delay ramps 0, 5, 10, 15ms then drops back: deviations of 5, 5,
+        // 5 and 15ms, whose mean (7.5ms) the estimate settles around
+        let estimator = feed(0, 2000, |index| 5 * (index % 4) as u64);
+        let jitter = estimator.jitter_ms();
+        assert!((6.5..8.5).contains(&jitter), "{jitter}ms");
+        assert!((estimator.jitter().as_secs_f64() * 1000.0 - jitter).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_timestamp_wraparound_is_not_jitter() {
+        // ---
+        let delay = |index: u32| 10 * (index % 2) as u64;
+        let wrapped = feed(u32::MAX - 10 * FRAME, 100, delay);
+        let unwrapped = feed(0, 100, delay);
+        assert!((wrapped.jitter_units() - unwrapped.jitter_units()).abs() < 0.01);
+        assert!(feed(u32::MAX - 10 * FRAME, 100, |_| 20).jitter_units() < 0.01);
+    }
+
+    #[test]
+    fn test_arrival_before_previous_packet() {
+        // ---
+        let mut estimator = JitterEstimator::new(16_000);
+        let start = Instant::now();
+
+        // Sent 20ms apart, the later one arriving 20ms before the earlier:
+        // a 40ms deviation, a sixteenth of which is taken
+        estimator.update(0, start + Duration::from_millis(20));
+        estimator.update(320, start);
+        assert!((estimator.jitter_ms() - 2.5).abs() < 1e-9);
+        assert!((estimator.jitter_units() - 40.0).abs() < 1e-6);
+
+        estimator.reset();
+        assert_eq!(estimator.jitter_units(), 0.0);
+        estimator.update(10_000, start);
+        assert_eq!(estimator.jitter_units(), 0.0);
+    }
+}
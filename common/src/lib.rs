@@ -4,10 +4,74 @@
 //! downstream crates should import through `common::*` exports and should not
 //! drill into internal module structure.
 
+mod abs_send_time;
+mod build_info;
 mod cli;
+mod framing;
+mod jitter;
 mod observability;
+mod rtcp;
+mod rtcp_socket;
+mod rtcp_xr;
 mod rtp;
+mod rtpdump;
+mod seq;
+mod session;
+#[cfg(feature = "sim")]
+mod sim;
+mod srtp;
+mod trace;
+mod transport_cc;
 
+pub use abs_send_time::{
+    abs_send_time_now, decode_abs_send_time, encode_abs_send_time, ABS_SEND_TIME_LEN,
+    ABS_SEND_TIME_UNITS_PER_SECOND, ABS_SEND_TIME_URI, ABS_SEND_TIME_WRAP,
+};
+pub use build_info::{handle_verbose_version, BuildInfo, COMMON_VERSION, GIT_HASH};
 pub use cli::ColorWhen;
-pub use observability::{init_tracing, MetricsContext, MetricsServerConfig};
-pub use rtp::RtpPacket;
+pub use framing::{append_frame, complete_frame, Transport, FRAME_HEADER_LEN, MAX_FRAME_LEN};
+pub use jitter::JitterEstimator;
+pub use observability::{
+    init_tracing, AnomalyClass, AnomalyLogConfig, AnomalyLogger, AnomalyOutcome, AnomalySummary,
+    MetricsContext, DEFAULT_ANOMALY_LOG_INTERVAL,
+};
+#[cfg(feature = "metrics-server")]
+pub use observability::{MetricsServerConfig, StatsRenderer};
+pub use rtcp::{
+    compound_packets, default_cname, dlsr_units, is_rtcp, ntp_timestamp, ntp_to_system_time, Bye,
+    CompoundPacket, DecoderRefreshRequest, Keepalive, Nack, ReceiverReport, ReportBlock,
+    RtcpInterval, Sdes, SdesChunk, SdesItem, SenderReport, DEFAULT_SESSION_BANDWIDTH,
+    KEEPALIVE_LEN, MAX_REPORT_BLOCKS, MAX_SDES_TEXT_LEN, PLI_LEN, PSFB_FMT_PLI, REPORT_BLOCK_LEN,
+    RTCP_BANDWIDTH_FRACTION, RTCP_PT_BYE, RTCP_PT_PSFB, RTCP_PT_RR, RTCP_PT_RTPFB, RTCP_PT_SDES,
+    RTCP_PT_SR, RTPFB_FMT_NACK, SDES_CNAME, SENDER_REPORT_LEN,
+};
+pub use rtcp_socket::{RtcpHandler, RtcpSocket};
+pub use rtcp_xr::{
+    ExtendedReport, LossRle, VoipMetrics, XrBlock, RTCP_PT_XR, VOIP_METRICS_LEN, XR_BT_LOSS_RLE,
+    XR_BT_VOIP_METRICS, XR_DEFAULT_GMIN, XR_UNAVAILABLE,
+};
+pub use rtp::{
+    payload_crc32, ExtensionElement, RtpHeaderExtension, RtpPacket, RtpParseError,
+    RtpParseErrorKind, RtpParseMode, MAX_CSRC, MAX_ONE_BYTE_ELEMENT_LEN, MAX_PADDING,
+    MAX_PAYLOAD_TYPE, ONE_BYTE_EXTENSION_PROFILE, PAYLOAD_CRC_LEN, PAYLOAD_TYPE_OPUS,
+    RTP_HEADER_LEN,
+};
+pub use rtpdump::{
+    parse_rtpdump, read_rtpdump, RtpdumpFile, RtpdumpPacket, RtpdumpWriter,
+    RTPDUMP_FILE_HEADER_LEN, RTPDUMP_MAGIC, RTPDUMP_RECORD_HEADER_LEN,
+};
+pub use seq::{SeqTracker, SeqUpdate, MAX_DROPOUT, MAX_MISORDER, MIN_SEQUENTIAL};
+pub use session::{
+    SessionConfig, SessionEvent, SessionState, SessionTracker, Transition,
+    DEFAULT_CONCEALMENT_THRESHOLD, DEFAULT_CONCEALMENT_WINDOW, DEFAULT_DEGRADED_HOLD,
+    DEFAULT_PAUSE_AFTER,
+};
+#[cfg(feature = "sim")]
+pub use sim::{NetworkSimulator, NetworkSimulatorConfig, NetworkSimulatorStats};
+pub use srtp::{
+    SrtpContext, SrtpKey, SRTP_AUTH_TAG_LEN, SRTP_MASTER_KEY_LEN, SRTP_MASTER_SALT_LEN,
+};
+pub use trace::TraceRecord;
+pub use transport_cc::{
+    decode_transport_sequence, encode_transport_sequence, TRANSPORT_CC_LEN, TRANSPORT_CC_URI,
+};
@@ -0,0 +1,476 @@
+//! Rate-limited logging of packet anomalies.
+//!
+//! A lossy link produces late, evicted, and invalid packets continuously;
+//! one log line each drowns everything else. [`AnomalyLogger`] logs the
+//! first occurrence of each class in full, counts the ones that follow
+//! without logging them, and emits one aggregate line per class on a
+//! configurable cadence ("discarded 47 late packets in the last 10 s, seq
+//! range 1023–1305"). Suppressed occurrences are exported per class as
+//! `anomalies_suppressed_total`.
+
+use prometheus::IntCounterVec;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+use super::metrics::MetricsContext;
+
+/// Default interval between aggregate lines for one anomaly class
+pub const DEFAULT_ANOMALY_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Kind of packet anomaly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyClass {
+    // ---
+    /// Packet arrived behind the playout point and was discarded
+    LatePacket,
+
+    /// Sequence jump too large for the jitter buffer, which restarted
+    BufferResync,
+
+    /// Jitter buffer was full and evicted its oldest packet
+    BufferOverflow,
+
+    /// Datagram did not parse as RTP
+    InvalidPacket,
+
+    /// Payload failed its CRC trailer check in verification mode
+    PayloadCrcMismatch,
+
+    /// Packet carried a payload type other than the one expected
+    PayloadTypeMismatch,
+
+    /// Packet could not be sent
+    SendFailed,
+
+    /// Destination answered with ICMP port unreachable: nothing listening
+    DestinationUnreachable,
+
+    /// SRTP packet failed authentication and was dropped
+    SrtpAuthFailed,
+}
+
+impl AnomalyClass {
+    // ---
+    /// Every class.
+    pub const ALL: [AnomalyClass; 9] = [
+        AnomalyClass::LatePacket,
+        AnomalyClass::BufferResync,
+        AnomalyClass::BufferOverflow,
+        AnomalyClass::InvalidPacket,
+        AnomalyClass::PayloadCrcMismatch,
+        AnomalyClass::PayloadTypeMismatch,
+        AnomalyClass::SendFailed,
+        AnomalyClass::DestinationUnreachable,
+        AnomalyClass::SrtpAuthFailed,
+    ];
+
+    /// Returns the class's metric label.
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            AnomalyClass::LatePacket => "late_packet",
+            AnomalyClass::BufferResync => "buffer_resync",
+            AnomalyClass::BufferOverflow => "buffer_overflow",
+            AnomalyClass::InvalidPacket => "invalid_packet",
+            AnomalyClass::PayloadCrcMismatch => "payload_crc_mismatch",
+            AnomalyClass::PayloadTypeMismatch => "payload_type_mismatch",
+            AnomalyClass::SendFailed => "send_failed",
+            AnomalyClass::DestinationUnreachable => "destination_unreachable",
+            AnomalyClass::SrtpAuthFailed => "srtp_auth_failed",
+        }
+    }
+
+    /// Returns true if the class is logged at error rather than warn level.
+    ///
+    /// Failing to send affects every receiver; the other classes are
+    /// expected on an impaired network.
+    pub fn is_error(&self) -> bool {
+        // ---
+        matches!(self, AnomalyClass::SendFailed)
+    }
+
+    /// Describes `count` occurrences, for the aggregate line.
+    fn describe(&self, count: u64) -> String {
+        // ---
+        match self {
+            AnomalyClass::LatePacket => format!("discarded {} late packets", count),
+            AnomalyClass::BufferResync => format!("resynced the jitter buffer {} times", count),
+            AnomalyClass::BufferOverflow => {
+                format!("evicted {} packets on jitter buffer overflow", count)
+            }
+            AnomalyClass::InvalidPacket => format!("dropped {} invalid packets", count),
+            AnomalyClass::PayloadCrcMismatch => {
+                format!("dropped {} packets failing the payload CRC", count)
+            }
+            AnomalyClass::PayloadTypeMismatch => {
+                format!("dropped {} packets with an unexpected payload type", count)
+            }
+            AnomalyClass::SendFailed => format!("failed to send {} packets", count),
+            AnomalyClass::DestinationUnreachable => {
+                format!("had {} packets refused by the destination", count)
+            }
+            AnomalyClass::SrtpAuthFailed => {
+                format!("dropped {} packets failing SRTP authentication", count)
+            }
+        }
+    }
+}
+
+impl fmt::Display for AnomalyClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// Anomaly logging cadence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnomalyLogConfig {
+    // ---
+    /// Minimum time between two lines for the same class
+    pub interval: Duration,
+}
+
+impl Default for AnomalyLogConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            interval: DEFAULT_ANOMALY_LOG_INTERVAL,
+        }
+    }
+}
+
+/// Occurrences of one class that were counted instead of logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnomalySummary {
+    // ---
+    pub class: AnomalyClass,
+
+    /// Occurrences since the previous line for this class
+    pub count: u64,
+
+    /// Time since the previous line for this class
+    pub window: Duration,
+
+    /// Lowest and highest sequence number involved, when known
+    pub sequences: Option<(u16, u16)>,
+}
+
+impl fmt::Display for AnomalySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(
+            f,
+            "{} in the last {} s",
+            self.class.describe(self.count),
+            self.window.as_secs()
+        )?;
+        if let Some((low, high)) = self.sequences {
+            write!(f, ", seq range {}–{}", low, high)?;
+        }
+        Ok(())
+    }
+}
+
+/// What [`AnomalyLogger::record`] did with an occurrence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnomalyOutcome {
+    // ---
+    /// Logged in full: the first occurrence after a quiet interval
+    Logged,
+
+    /// Counted toward the next aggregate line
+    Suppressed,
+
+    /// Counted and, the interval having passed, logged as this aggregate
+    Aggregated(AnomalySummary),
+}
+
+/// Per-class logging state.
+#[derive(Debug, Clone, Default)]
+struct ClassLog {
+    // ---
+    /// When the last line for this class was logged
+    last_logged: Option<Instant>,
+
+    /// Occurrences counted since then
+    suppressed: u64,
+
+    /// Lowest and highest sequence among them (by wrapping order)
+    sequences: Option<(u16, u16)>,
+
+    /// Lines logged for this class
+    lines: u64,
+}
+
+/// Logs packet anomalies at most once per interval and class.
+///
+/// Owned by each component that detects anomalies. Attach a metrics
+/// registry with [`with_metrics`](Self::with_metrics) to export the
+/// suppression counters.
+#[derive(Debug, Clone)]
+pub struct AnomalyLogger {
+    // ---
+    config: AnomalyLogConfig,
+    classes: [ClassLog; AnomalyClass::ALL.len()],
+    suppressed_total: Option<IntCounterVec>,
+}
+
+impl Default for AnomalyLogger {
+    fn default() -> Self {
+        // ---
+        Self::new(AnomalyLogConfig::default())
+    }
+}
+
+impl AnomalyLogger {
+    // ---
+    /// Creates a logger with no metrics attached.
+    pub fn new(config: AnomalyLogConfig) -> Self {
+        // ---
+        Self {
+            config,
+            classes: Default::default(),
+            suppressed_total: None,
+        }
+    }
+
+    /// Exports suppressed occurrences to `metrics`.
+    pub fn with_metrics(mut self, metrics: &MetricsContext) -> Self {
+        // ---
+        self.suppressed_total = Some(metrics.anomalies_suppressed_total.clone());
+        self
+    }
+
+    /// Records one occurrence of `class` at `now`.
+    ///
+    /// The first occurrence after a quiet interval is logged as `detail`;
+    /// later ones are only counted until the interval has passed, when one
+    /// aggregate line covers them all.
+    ///
+    /// # Arguments
+    ///
+    /// * `class` - Kind of anomaly
+    /// * `sequence` - RTP sequence number involved, if any
+    /// * `detail` - Full description, logged for the first occurrence only
+    /// * `now` - When the anomaly happened
+    pub fn record(
+        &mut self,
+        class: AnomalyClass,
+        sequence: Option<u16>,
+        detail: fmt::Arguments<'_>,
+        now: Instant,
+    ) -> AnomalyOutcome {
+        // ---
+        let interval = self.config.interval;
+        let log = &mut self.classes[class as usize];
+        let quiet = log
+            .last_logged
+            .is_none_or(|at| now.saturating_duration_since(at) >= interval);
+
+        if quiet && log.suppressed == 0 {
+            log.last_logged = Some(now);
+            log.lines += 1;
+            emit(class, detail);
+            return AnomalyOutcome::Logged;
+        }
+
+        log.suppressed += 1;
+        if let Some(sequence) = sequence {
+            log.sequences = Some(match log.sequences {
+                None => (sequence, sequence),
+                Some((low, high)) => (
+                    if is_before(sequence, low) {
+                        sequence
+                    } else {
+                        low
+                    },
+                    if is_before(high, sequence) {
+                        sequence
+                    } else {
+                        high
+                    },
+                ),
+            });
+        }
+        if let Some(counter) = &self.suppressed_total {
+            counter.with_label_values(&[class.as_str()]).inc();
+        }
+
+        if !quiet {
+            return AnomalyOutcome::Suppressed;
+        }
+        AnomalyOutcome::Aggregated(self.aggregate(class, now))
+    }
+
+    /// Logs an aggregate line for every class with occurrences not yet
+    /// reported, regardless of the interval.
+    ///
+    /// Called when a stream ends so the last window is not lost.
+    pub fn flush(&mut self, now: Instant) -> Vec<AnomalySummary> {
+        // ---
+        let mut summaries = Vec::new();
+        for class in AnomalyClass::ALL {
+            if self.classes[class as usize].suppressed > 0 {
+                summaries.push(self.aggregate(class, now));
+            }
+        }
+        summaries
+    }
+
+    /// Returns the number of lines logged for `class`.
+    pub fn lines_logged(&self, class: AnomalyClass) -> u64 {
+        // ---
+        self.classes[class as usize].lines
+    }
+
+    /// Logs and resets the pending occurrences of `class`.
+    fn aggregate(&mut self, class: AnomalyClass, now: Instant) -> AnomalySummary {
+        // ---
+        let log = &mut self.classes[class as usize];
+        let summary = AnomalySummary {
+            class,
+            count: log.suppressed,
+            window: log
+                .last_logged
+                .map_or(Duration::ZERO, |at| now.saturating_duration_since(at)),
+            sequences: log.sequences.take(),
+        };
+        log.suppressed = 0;
+        log.last_logged = Some(now);
+        log.lines += 1;
+        emit(class, format_args!("{}", summary));
+        summary
+    }
+}
+
+/// Logs `message` at the level for `class`.
+fn emit(class: AnomalyClass, message: fmt::Arguments<'_>) {
+    // ---
+    if class.is_error() {
+        error!("{}", message);
+    } else {
+        warn!("{}", message);
+    }
+}
+
+/// Returns true if sequence `a` comes before `b`, allowing for wraparound.
+fn is_before(a: u16, b: u16) -> bool {
+    // ---
+    a != b && b.wrapping_sub(a) < 0x8000
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    fn late(logger: &mut AnomalyLogger, seq: u16, now: Instant) -> AnomalyOutcome {
+        logger.record(
+            AnomalyClass::LatePacket,
+            Some(seq),
+            format_args!("Discarding late packet: seq={}", seq),
+            now,
+        )
+    }
+
+    #[test]
+    fn test_first_occurrence_then_aggregate() {
+        // ---
+        let mut logger = AnomalyLogger::default();
+        let start = Instant::now();
+
+        assert_eq!(late(&mut logger, 1000, start), AnomalyOutcome::Logged);
+
+        // 47 more inside the interval are only counted
+        for i in 0..47u16 {
+            let at = start + Duration::from_millis(100 + i as u64 * 100);
+            assert_eq!(
+                late(&mut logger, 1023 + i * 6, at),
+                AnomalyOutcome::Suppressed
+            );
+        }
+
+        // The first one past the interval reports them all
+        let at = start + Duration::from_secs(10);
+        let AnomalyOutcome::Aggregated(summary) = late(&mut logger, 1305, at) else {
+            panic!("expected an aggregate line");
+        };
+        assert_eq!(
+            summary.to_string(),
+            "discarded 48 late packets in the last 10 s, seq range 1023–1305"
+        );
+        assert_eq!(logger.lines_logged(AnomalyClass::LatePacket), 2);
+
+        // After a quiet interval the next one is logged in full again
+        let at = start + Duration::from_secs(30);
+        assert_eq!(late(&mut logger, 2000, at), AnomalyOutcome::Logged);
+    }
+
+    #[test]
+    fn test_high_rate_logs_once_per_interval() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut logger = AnomalyLogger::new(AnomalyLogConfig {
+            interval: Duration::from_secs(5),
+        })
+        .with_metrics(&metrics);
+        let start = Instant::now();
+
+        // 1000 anomalies a second for 20 seconds, across two classes
+        let mut lines = 0;
+        for i in 0..20_000u32 {
+            let at = start + Duration::from_millis(i as u64);
+            let outcomes = [
+                late(&mut logger, i as u16, at),
+                logger.record(
+                    AnomalyClass::InvalidPacket,
+                    None,
+                    format_args!("Dropped invalid packet: {}", i),
+                    at,
+                ),
+            ];
+            lines += outcomes
+                .iter()
+                .filter(|o| **o != AnomalyOutcome::Suppressed)
+                .count();
+        }
+
+        // One first line and three aggregates (5, 10, 15 s) per class
+        assert_eq!(lines, 8);
+        assert_eq!(logger.lines_logged(AnomalyClass::LatePacket), 4);
+
+        let suppressed = |class: AnomalyClass| {
+            metrics
+                .anomalies_suppressed_total
+                .with_label_values(&[class.as_str()])
+                .get()
+        };
+        assert_eq!(suppressed(AnomalyClass::LatePacket), 19_999);
+        assert_eq!(suppressed(AnomalyClass::InvalidPacket), 19_999);
+
+        // The tail after the last aggregate is reported on flush
+        let flushed = logger.flush(start + Duration::from_secs(20));
+        assert_eq!(flushed.len(), 2);
+        assert_eq!(flushed[0].count, 4_999);
+        assert_eq!(flushed[1].sequences, None);
+        assert!(logger.flush(start + Duration::from_secs(21)).is_empty());
+    }
+
+    #[test]
+    fn test_sequence_range_across_wraparound() {
+        // ---
+        let mut logger = AnomalyLogger::default();
+        let start = Instant::now();
+
+        late(&mut logger, 1, start);
+        for seq in [65534, 2, 65535, 0] {
+            late(&mut logger, seq, start);
+        }
+
+        let summary = &logger.flush(start + Duration::from_secs(3))[0];
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.sequences, Some((65534, 2)));
+    }
+}
@@ -4,28 +4,66 @@
 //! and controls which metrics it reports.
 
 use anyhow::Result;
+#[cfg(feature = "metrics-server")]
 use hyper::service::{make_service_fn, service_fn};
+#[cfg(feature = "metrics-server")]
 use hyper::{Body, Method, Request, Response, Server, StatusCode};
 use prometheus::{
-    Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Opts, Registry, TextEncoder,
+    Counter, CounterVec, Gauge, GaugeVec, Histogram, HistogramOpts, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec, Opts, Registry,
 };
+#[cfg(feature = "metrics-server")]
+use prometheus::{Encoder, TextEncoder};
+#[cfg(feature = "metrics-server")]
 use std::net::SocketAddr;
+#[cfg(feature = "metrics-server")]
 use std::sync::Arc;
+#[cfg(feature = "metrics-server")]
 use tokio::task::JoinHandle;
 
+use crate::build_info::BuildInfo;
+
+/// Renders the plain-text body of `GET /stats`.
+#[cfg(feature = "metrics-server")]
+pub type StatsRenderer = Arc<dyn Fn() -> String + Send + Sync>;
+
 /// Configuration for the built-in Prometheus scrape endpoint.
-#[derive(Debug, Clone)]
+#[cfg(feature = "metrics-server")]
+#[derive(Clone)]
 pub struct MetricsServerConfig {
     // ---
     /// Address to bind, e.g. `127.0.0.1:9100`.
     pub bind: SocketAddr,
+
+    /// Serves `GET /stats` alongside `/metrics` if set.
+    pub stats: Option<StatsRenderer>,
 }
 
+#[cfg(feature = "metrics-server")]
 impl MetricsServerConfig {
     // ---
     pub fn new(bind: SocketAddr) -> Self {
         // ---
-        Self { bind }
+        Self { bind, stats: None }
+    }
+
+    /// Also serves `GET /stats`, with the text `render` returns at each
+    /// request.
+    pub fn with_stats(mut self, render: impl Fn() -> String + Send + Sync + 'static) -> Self {
+        // ---
+        self.stats = Some(Arc::new(render));
+        self
+    }
+}
+
+#[cfg(feature = "metrics-server")]
+impl std::fmt::Debug for MetricsServerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ---
+        f.debug_struct("MetricsServerConfig")
+            .field("bind", &self.bind)
+            .field("stats", &self.stats.is_some())
+            .finish()
     }
 }
 
@@ -40,21 +78,130 @@ pub struct MetricsContext {
 
     // Network counters
     pub packets_sent_total: IntCounter,
+    pub packets_failed_total: IntCounter,
+
+    // Sender fan-out, labelled by destination address
+    pub destination_packets_sent_total: IntCounterVec,
+    pub destination_packets_failed_total: IntCounterVec,
+
+    // Sender bandwidth cap
+    pub packets_rate_limited_total: IntCounter,
+    pub rate_limit_wait_seconds_total: Counter,
+
     pub packets_received_total: IntCounter,
     pub packets_lost_total: IntCounter,
     pub packets_reordered_total: IntCounter,
     pub packets_late_total: IntCounter,
+    pub packets_duplicate_total: IntCounter,
+    pub pacing_catchup_events_total: IntCounter,
+    pub frames_skipped_total: IntCounter,
+    pub frames_dtx_suppressed_total: IntCounter,
+    pub frames_fec_recovered_total: IntCounter,
+    pub frames_concealed_total: IntCounter,
+
+    // Feedback counters
+    pub decoder_refresh_requests_sent_total: IntCounter,
+    pub decoder_refresh_requests_suppressed_total: IntCounter,
+    pub decoder_refresh_requests_received_total: IntCounter,
+    pub encoder_resets_total: IntCounter,
+
+    // Retransmission on NACK (RFC 4585 generic NACK)
+    pub nack_packets_requested_total: IntCounter,
+    pub nack_requests_received_total: IntCounter,
+    pub packets_retransmitted_total: IntCounter,
+
+    // NAT keepalives (empty datagrams) during silence
+    pub keepalives_sent_total: IntCounter,
+    pub keepalives_received_total: IntCounter,
+
+    // RTCP sender reports
+    pub sender_reports_sent_total: IntCounter,
+    pub sender_reports_received_total: IntCounter,
+
+    // RTCP receiver reports, and the reception they report at the sender
+    pub receiver_reports_sent_total: IntCounter,
+    pub receiver_reports_received_total: IntCounter,
+    pub remote_fraction_lost: Gauge,
+    pub remote_jitter_seconds: Gauge,
+
+    // RTCP extended reports, and the listening quality they report at the
+    // sender
+    pub extended_reports_sent_total: IntCounter,
+    pub extended_reports_received_total: IntCounter,
+    pub remote_mos_lq: Gauge,
+
+    // Anomaly log lines withheld by rate limiting, labelled by class
+    pub anomalies_suppressed_total: IntCounterVec,
+
+    // Payloads dropped for failing the CRC trailer check
+    pub payload_crc_mismatch_total: IntCounter,
+
+    // Packets dropped for carrying a payload type other than the expected one
+    pub payload_type_mismatch_total: IntCounter,
+
+    // SSRC changes, and packets dropped around them
+    pub ssrc_changes_total: IntCounter,
+    pub ssrc_stale_packets_total: IntCounter,
+    pub ssrc_unconfirmed_packets_total: IntCounter,
+
+    // Datagrams dropped for not parsing as RTP, labelled by reason
+    pub parse_errors_total: IntCounterVec,
+
+    // SRTP packets dropped for failing authentication
+    pub srtp_auth_failures_total: IntCounter,
+
+    // Datagrams dropped for being larger than the receive buffer takes
+    pub datagrams_oversized_total: IntCounter,
 
     pub bytes_sent_total: IntCounter,
+    pub padding_bytes_sent_total: IntCounter,
     pub bytes_received_total: IntCounter,
+    pub bytes_received_gross_total: IntCounter,
 
-    // Buffer gauges
+    // Buffer gauges and events
     pub jitter_buffer_occupancy_packets: IntGauge,
+    pub jitter_buffer_overflow_total: IntCounter,
+    pub jitter_buffer_resync_total: IntCounter,
+    pub jitter_buffer_priming_overflow_total: IntCounter,
+    pub jitter_buffer_primed: IntGauge,
+    pub jitter_buffer_target_depth_seconds: Gauge,
+    pub jitter_buffer_lost_total: IntCounter,
+    pub jitter_buffer_gaps_skipped_total: IntCounter,
+    pub jitter_buffer_underruns_total: IntCounter,
+    pub jitter_buffer_catch_up_drops_total: IntCounter,
+    pub jitter_buffer_accelerated_total: IntCounter,
+    pub jitter_buffer_residence_seconds: GaugeVec,
+
+    // Stream clock gauges
+    pub clock_rate_hz: IntGauge,
+    pub frame_duration_seconds: Gauge,
+    pub interarrival_jitter_seconds: Gauge,
+
+    // Startup gauges
+    pub sink_setup_seconds: Gauge,
+    pub sink_setup_buffered_packets: IntGauge,
+
+    // What the process was built from (see `set_build_info`)
+    pub build_info: IntGaugeVec,
+
+    // Encoder settings, labelled by mode
+    pub encoder_vbr_mode: IntGaugeVec,
+    pub encoder_target_bitrate_bps: IntGauge,
+
+    // Bitrate chosen by adaptation to receiver feedback
+    pub current_bitrate_bps: IntGauge,
+
+    // Session lifecycle, labelled by state
+    pub session_state: IntGaugeVec,
+    pub session_state_seconds_total: CounterVec,
 
     // Latency histograms (seconds)
     pub encode_seconds: Histogram,
+    pub pacing_deviation_seconds: Histogram,
     pub decode_seconds: Histogram,
     pub jitter_buffer_delay_seconds: Histogram,
+    pub priming_duration_seconds: Histogram,
+    pub priming_buffered_delay_seconds: Histogram,
     pub network_transit_seconds: Histogram,
     pub receiver_pipeline_seconds: Histogram,
 }
@@ -75,6 +222,32 @@ impl MetricsContext {
             "rtp_packets_sent_total",
             "Total RTP packets sent",
         ))?;
+        let packets_failed_total = IntCounter::with_opts(Opts::new(
+            "rtp_packets_failed_total",
+            "Total RTP packets the socket failed to send",
+        ))?;
+        let destination_packets_sent_total = IntCounterVec::new(
+            Opts::new(
+                "rtp_destination_packets_sent_total",
+                "Total RTP packets sent to each destination",
+            ),
+            &["destination"],
+        )?;
+        let destination_packets_failed_total = IntCounterVec::new(
+            Opts::new(
+                "rtp_destination_packets_failed_total",
+                "Total RTP packets the socket failed to send to each destination",
+            ),
+            &["destination"],
+        )?;
+        let packets_rate_limited_total = IntCounter::with_opts(Opts::new(
+            "rtp_packets_rate_limited_total",
+            "Total RTP packets held back by the sender's bandwidth cap",
+        ))?;
+        let rate_limit_wait_seconds_total = Counter::with_opts(Opts::new(
+            "rtp_rate_limit_wait_seconds_total",
+            "Total seconds the sender waited on its bandwidth cap",
+        ))?;
         let packets_received_total = IntCounter::with_opts(Opts::new(
             "rtp_packets_received_total",
             "Total RTP packets received",
@@ -91,25 +264,295 @@ impl MetricsContext {
             "rtp_packets_late_total",
             "Total RTP packets that arrived too late for playout",
         ))?;
+        let packets_duplicate_total = IntCounter::with_opts(Opts::new(
+            "rtp_packets_duplicate_total",
+            "Total duplicate RTP packets discarded",
+        ))?;
+
+        let pacing_catchup_events_total = IntCounter::with_opts(Opts::new(
+            "pacing_catchup_events_total",
+            "Total sender pacing stalls that left frames overdue",
+        ))?;
+        let frames_skipped_total = IntCounter::with_opts(Opts::new(
+            "frames_skipped_total",
+            "Total frames dropped by the sender to stay real-time after a stall",
+        ))?;
+        let frames_dtx_suppressed_total = IntCounter::with_opts(Opts::new(
+            "frames_dtx_suppressed_total",
+            "Total silent DTX frames the sender did not transmit",
+        ))?;
+        let frames_fec_recovered_total = IntCounter::with_opts(Opts::new(
+            "frames_fec_recovered_total",
+            "Total lost frames rebuilt from in-band FEC in the following packet",
+        ))?;
+        let frames_concealed_total = IntCounter::with_opts(Opts::new(
+            "frames_concealed_total",
+            "Total lost or undecodable frames filled by packet loss concealment",
+        ))?;
+
+        let decoder_refresh_requests_sent_total = IntCounter::with_opts(Opts::new(
+            "decoder_refresh_requests_sent_total",
+            "Total decoder refresh requests sent by the receiver after long outages",
+        ))?;
+        let decoder_refresh_requests_suppressed_total = IntCounter::with_opts(Opts::new(
+            "decoder_refresh_requests_suppressed_total",
+            "Total decoder refresh requests withheld by the receiver's rate limit",
+        ))?;
+        let decoder_refresh_requests_received_total = IntCounter::with_opts(Opts::new(
+            "decoder_refresh_requests_received_total",
+            "Total decoder refresh requests received by the sender",
+        ))?;
+        let encoder_resets_total = IntCounter::with_opts(Opts::new(
+            "encoder_resets_total",
+            "Total sender encoder resets in response to refresh requests",
+        ))?;
+        let nack_packets_requested_total = IntCounter::with_opts(Opts::new(
+            "rtp_nack_packets_requested_total",
+            "Total lost packets the receiver asked the sender to retransmit",
+        ))?;
+        let nack_requests_received_total = IntCounter::with_opts(Opts::new(
+            "rtp_nack_requests_received_total",
+            "Total packets the sender was asked to retransmit",
+        ))?;
+        let packets_retransmitted_total = IntCounter::with_opts(Opts::new(
+            "rtp_packets_retransmitted_total",
+            "Total RTP packets the sender retransmitted on request",
+        ))?;
+        let keepalives_sent_total = IntCounter::with_opts(Opts::new(
+            "rtp_keepalives_sent_total",
+            "Total keepalives the sender sent while the stream was silent",
+        ))?;
+        let keepalives_received_total = IntCounter::with_opts(Opts::new(
+            "rtp_keepalives_received_total",
+            "Total keepalives the receiver received and discarded",
+        ))?;
+        let sender_reports_sent_total = IntCounter::with_opts(Opts::new(
+            "rtcp_sender_reports_sent_total",
+            "Total RTCP sender reports the sender sent",
+        ))?;
+        let sender_reports_received_total = IntCounter::with_opts(Opts::new(
+            "rtcp_sender_reports_received_total",
+            "Total RTCP sender reports the receiver received",
+        ))?;
+        let receiver_reports_sent_total = IntCounter::with_opts(Opts::new(
+            "rtcp_receiver_reports_sent_total",
+            "Total RTCP receiver reports the receiver sent",
+        ))?;
+        let receiver_reports_received_total = IntCounter::with_opts(Opts::new(
+            "rtcp_receiver_reports_received_total",
+            "Total RTCP receiver reports the sender received",
+        ))?;
+        let extended_reports_sent_total = IntCounter::with_opts(Opts::new(
+            "rtcp_extended_reports_sent_total",
+            "Total RTCP extended reports the receiver sent",
+        ))?;
+        let extended_reports_received_total = IntCounter::with_opts(Opts::new(
+            "rtcp_extended_reports_received_total",
+            "Total RTCP extended reports the sender received",
+        ))?;
+        let remote_mos_lq = Gauge::with_opts(Opts::new(
+            "rtcp_remote_mos_lq",
+            "Listening quality MOS estimated by the receiver in its last VoIP Metrics report",
+        ))?;
+        let remote_fraction_lost = Gauge::with_opts(Opts::new(
+            "rtcp_remote_fraction_lost",
+            "Fraction of packets lost over the last receiver report interval, as reported by the receiver",
+        ))?;
+        let remote_jitter_seconds = Gauge::with_opts(Opts::new(
+            "rtcp_remote_jitter_seconds",
+            "Interarrival jitter as reported by the receiver (seconds)",
+        ))?;
+
+        let anomalies_suppressed_total = IntCounterVec::new(
+            Opts::new(
+                "anomalies_suppressed_total",
+                "Total packet anomalies counted into aggregate log lines instead of logged",
+            ),
+            &["class"],
+        )?;
+
+        let payload_crc_mismatch_total = IntCounter::with_opts(Opts::new(
+            "payload_crc_mismatch_total",
+            "Total packets dropped because the payload failed its CRC trailer check",
+        ))?;
+        let payload_type_mismatch_total = IntCounter::with_opts(Opts::new(
+            "rtp_payload_type_mismatch_total",
+            "Total packets dropped because their payload type was not the expected one",
+        ))?;
+        let ssrc_changes_total = IntCounter::with_opts(Opts::new(
+            "rtp_ssrc_changes_total",
+            "Total times a new SSRC replaced the stream being played",
+        ))?;
+        let ssrc_stale_packets_total = IntCounter::with_opts(Opts::new(
+            "rtp_ssrc_stale_packets_total",
+            "Total packets dropped because their SSRC had been replaced",
+        ))?;
+        let ssrc_unconfirmed_packets_total = IntCounter::with_opts(Opts::new(
+            "rtp_ssrc_unconfirmed_packets_total",
+            "Total packets dropped from a new SSRC before it replaced the stream being played",
+        ))?;
+        let parse_errors_total = IntCounterVec::new(
+            Opts::new(
+                "rtp_parse_errors_total",
+                "Total datagrams dropped because they did not parse as RTP",
+            ),
+            &["reason"],
+        )?;
+
+        let srtp_auth_failures_total = IntCounter::with_opts(Opts::new(
+            "srtp_auth_failures_total",
+            "Total SRTP packets dropped because they failed authentication",
+        ))?;
+        let datagrams_oversized_total = IntCounter::with_opts(Opts::new(
+            "rtp_datagrams_oversized_total",
+            "Total datagrams dropped because they were larger than the max datagram size",
+        ))?;
 
         let bytes_sent_total = IntCounter::with_opts(Opts::new(
             "rtp_bytes_sent_total",
             "Total RTP payload bytes sent",
         ))?;
+        let padding_bytes_sent_total = IntCounter::with_opts(Opts::new(
+            "rtp_padding_bytes_sent_total",
+            "Total RTP padding bytes sent to keep packet sizes uniform",
+        ))?;
         let bytes_received_total = IntCounter::with_opts(Opts::new(
             "rtp_bytes_received_total",
-            "Total RTP payload bytes received",
+            "Total RTP payload bytes received, excluding padding (net)",
+        ))?;
+        let bytes_received_gross_total = IntCounter::with_opts(Opts::new(
+            "rtp_bytes_received_gross_total",
+            "Total RTP payload bytes received, including padding (gross)",
         ))?;
 
         let jitter_buffer_occupancy_packets = IntGauge::with_opts(Opts::new(
             "jitter_buffer_occupancy_packets",
             "Current jitter buffer occupancy in packets",
         ))?;
+        let jitter_buffer_overflow_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_overflow_total",
+            "Total packets evicted because the jitter buffer was full",
+        ))?;
+        let jitter_buffer_resync_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_resync_total",
+            "Total jitter buffer resyncs after a sequence discontinuity, confirmed by two sequential packets",
+        ))?;
+
+        let jitter_buffer_priming_overflow_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_priming_overflow_total",
+            "Packets evicted because the jitter buffer was full while priming \
+             (also counted in jitter_buffer_overflow_total)",
+        ))?;
+        let jitter_buffer_primed = IntGauge::with_opts(Opts::new(
+            "jitter_buffer_primed",
+            "1 while the jitter buffer is primed and playing out, 0 while priming",
+        ))?;
+        let jitter_buffer_target_depth_seconds = Gauge::with_opts(Opts::new(
+            "jitter_buffer_target_depth_seconds",
+            "Depth the jitter buffer primes to (fixed, or where an adaptive depth has moved)",
+        ))?;
+        let jitter_buffer_lost_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_lost_total",
+            "Total packets the jitter buffer gave up waiting for and signalled for concealment",
+        ))?;
+        let jitter_buffer_gaps_skipped_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_gaps_skipped_total",
+            "Total gaps jitter buffer playout moved past without the missing packets",
+        ))?;
+        let jitter_buffer_underruns_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_underruns_total",
+            "Total times jitter buffer playout ran dry for longer than the target depth",
+        ))?;
+        let jitter_buffer_catch_up_drops_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_catch_up_drops_total",
+            "Total packets dropped to bring jitter buffer occupancy back to the target depth",
+        ))?;
+        let jitter_buffer_accelerated_total = IntCounter::with_opts(Opts::new(
+            "jitter_buffer_accelerated_total",
+            "Total packets played faster than real time to bring jitter buffer occupancy back to the target depth",
+        ))?;
+        let jitter_buffer_residence_seconds = GaugeVec::new(
+            Opts::new(
+                "jitter_buffer_residence_seconds",
+                "Shortest, mean, and longest hold of the packets released since the last update",
+            ),
+            &["stat"],
+        )?;
+
+        let clock_rate_hz = IntGauge::with_opts(Opts::new(
+            "rtp_clock_rate_hz",
+            "RTP clock rate in use (configured or detected)",
+        ))?;
+        let frame_duration_seconds = Gauge::with_opts(Opts::new(
+            "rtp_frame_duration_seconds",
+            "Media duration of one RTP packet (configured or detected)",
+        ))?;
+        let interarrival_jitter_seconds = Gauge::with_opts(Opts::new(
+            "rtp_interarrival_jitter_seconds",
+            "RFC 3550 interarrival jitter estimate of the received stream",
+        ))?;
+
+        let sink_setup_seconds = Gauge::with_opts(Opts::new(
+            "sink_setup_seconds",
+            "Time taken to open the audio sink at startup (seconds)",
+        ))?;
+        let sink_setup_buffered_packets = IntGauge::with_opts(Opts::new(
+            "sink_setup_buffered_packets",
+            "Packets received and buffered while the audio sink was opening",
+        ))?;
+
+        let build_info = IntGaugeVec::new(
+            Opts::new(
+                "rtp_opus_build_info",
+                "Build of the running process: always 1, with the details as labels",
+            ),
+            &["binary", "version", "git_hash", "features"],
+        )?;
+
+        let encoder_vbr_mode = IntGaugeVec::new(
+            Opts::new(
+                "encoder_vbr_mode",
+                "Encoder rate control mode: 1 for the active mode, 0 for the others",
+            ),
+            &["mode"],
+        )?;
+        let encoder_target_bitrate_bps = IntGauge::with_opts(Opts::new(
+            "encoder_target_bitrate_bps",
+            "Bitrate the encoder is currently asked for (bits per second)",
+        ))?;
+        let current_bitrate_bps = IntGauge::with_opts(Opts::new(
+            "current_bitrate_bps",
+            "Bitrate adaptation to receiver feedback last chose (bits per second)",
+        ))?;
+
+        let session_state = IntGaugeVec::new(
+            Opts::new(
+                "session_state",
+                "Current session state: 1 for the active state, 0 for the others",
+            ),
+            &["state"],
+        )?;
+        let session_state_seconds_total = CounterVec::new(
+            Opts::new(
+                "session_state_seconds_total",
+                "Total seconds the session spent in each state",
+            ),
+            &["state"],
+        )?;
 
         let encode_seconds = Histogram::with_opts(HistogramOpts::new(
             "opus_encode_seconds",
             "Opus encode duration in seconds",
         ))?;
+        let pacing_deviation_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "pacing_deviation_seconds",
+                "How far after its ideal send time each packet went out (seconds)",
+            )
+            .buckets(vec![
+                0.0005, 0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.25, 0.5, 1.0,
+            ]),
+        )?;
         let decode_seconds = Histogram::with_opts(HistogramOpts::new(
             "opus_decode_seconds",
             "Opus decode duration in seconds",
@@ -118,6 +561,14 @@ impl MetricsContext {
             "jitter_buffer_delay_seconds",
             "Time a packet spent waiting in the jitter buffer (seconds)",
         ))?;
+        let priming_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+            "priming_duration_seconds",
+            "Time from the first packet until playout started, per prime or re-prime (seconds)",
+        ))?;
+        let priming_buffered_delay_seconds = Histogram::with_opts(HistogramOpts::new(
+            "priming_buffered_delay_seconds",
+            "How long each packet buffered during priming had been held when playout started (seconds)",
+        ))?;
         let network_transit_seconds = Histogram::with_opts(HistogramOpts::new(
             "network_transit_seconds",
             "Estimated network transit time (seconds)",
@@ -129,32 +580,162 @@ impl MetricsContext {
 
         // Register all metrics
         registry.register(Box::new(packets_sent_total.clone()))?;
+        registry.register(Box::new(packets_failed_total.clone()))?;
+        registry.register(Box::new(destination_packets_sent_total.clone()))?;
+        registry.register(Box::new(destination_packets_failed_total.clone()))?;
+        registry.register(Box::new(packets_rate_limited_total.clone()))?;
+        registry.register(Box::new(rate_limit_wait_seconds_total.clone()))?;
         registry.register(Box::new(packets_received_total.clone()))?;
         registry.register(Box::new(packets_lost_total.clone()))?;
         registry.register(Box::new(packets_reordered_total.clone()))?;
         registry.register(Box::new(packets_late_total.clone()))?;
+        registry.register(Box::new(packets_duplicate_total.clone()))?;
+        registry.register(Box::new(pacing_catchup_events_total.clone()))?;
+        registry.register(Box::new(frames_skipped_total.clone()))?;
+        registry.register(Box::new(frames_dtx_suppressed_total.clone()))?;
+        registry.register(Box::new(frames_fec_recovered_total.clone()))?;
+        registry.register(Box::new(frames_concealed_total.clone()))?;
+        registry.register(Box::new(decoder_refresh_requests_sent_total.clone()))?;
+        registry.register(Box::new(decoder_refresh_requests_suppressed_total.clone()))?;
+        registry.register(Box::new(decoder_refresh_requests_received_total.clone()))?;
+        registry.register(Box::new(encoder_resets_total.clone()))?;
+        registry.register(Box::new(nack_packets_requested_total.clone()))?;
+        registry.register(Box::new(nack_requests_received_total.clone()))?;
+        registry.register(Box::new(packets_retransmitted_total.clone()))?;
+        registry.register(Box::new(keepalives_sent_total.clone()))?;
+        registry.register(Box::new(keepalives_received_total.clone()))?;
+        registry.register(Box::new(sender_reports_sent_total.clone()))?;
+        registry.register(Box::new(sender_reports_received_total.clone()))?;
+        registry.register(Box::new(receiver_reports_sent_total.clone()))?;
+        registry.register(Box::new(receiver_reports_received_total.clone()))?;
+        registry.register(Box::new(remote_fraction_lost.clone()))?;
+        registry.register(Box::new(remote_jitter_seconds.clone()))?;
+        registry.register(Box::new(extended_reports_sent_total.clone()))?;
+        registry.register(Box::new(extended_reports_received_total.clone()))?;
+        registry.register(Box::new(remote_mos_lq.clone()))?;
+        registry.register(Box::new(anomalies_suppressed_total.clone()))?;
+        registry.register(Box::new(payload_crc_mismatch_total.clone()))?;
+        registry.register(Box::new(payload_type_mismatch_total.clone()))?;
+        registry.register(Box::new(ssrc_changes_total.clone()))?;
+        registry.register(Box::new(ssrc_stale_packets_total.clone()))?;
+        registry.register(Box::new(ssrc_unconfirmed_packets_total.clone()))?;
+        registry.register(Box::new(parse_errors_total.clone()))?;
+        registry.register(Box::new(srtp_auth_failures_total.clone()))?;
+        registry.register(Box::new(datagrams_oversized_total.clone()))?;
         registry.register(Box::new(bytes_sent_total.clone()))?;
+        registry.register(Box::new(padding_bytes_sent_total.clone()))?;
         registry.register(Box::new(bytes_received_total.clone()))?;
+        registry.register(Box::new(bytes_received_gross_total.clone()))?;
         registry.register(Box::new(jitter_buffer_occupancy_packets.clone()))?;
+        registry.register(Box::new(jitter_buffer_overflow_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_resync_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_priming_overflow_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_primed.clone()))?;
+        registry.register(Box::new(jitter_buffer_target_depth_seconds.clone()))?;
+        registry.register(Box::new(jitter_buffer_lost_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_gaps_skipped_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_underruns_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_catch_up_drops_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_accelerated_total.clone()))?;
+        registry.register(Box::new(jitter_buffer_residence_seconds.clone()))?;
+        registry.register(Box::new(clock_rate_hz.clone()))?;
+        registry.register(Box::new(frame_duration_seconds.clone()))?;
+        registry.register(Box::new(interarrival_jitter_seconds.clone()))?;
+        registry.register(Box::new(sink_setup_seconds.clone()))?;
+        registry.register(Box::new(sink_setup_buffered_packets.clone()))?;
+        registry.register(Box::new(build_info.clone()))?;
+        registry.register(Box::new(encoder_vbr_mode.clone()))?;
+        registry.register(Box::new(encoder_target_bitrate_bps.clone()))?;
+        registry.register(Box::new(current_bitrate_bps.clone()))?;
+        registry.register(Box::new(session_state.clone()))?;
+        registry.register(Box::new(session_state_seconds_total.clone()))?;
         registry.register(Box::new(encode_seconds.clone()))?;
+        registry.register(Box::new(pacing_deviation_seconds.clone()))?;
         registry.register(Box::new(decode_seconds.clone()))?;
         registry.register(Box::new(jitter_buffer_delay_seconds.clone()))?;
+        registry.register(Box::new(priming_duration_seconds.clone()))?;
+        registry.register(Box::new(priming_buffered_delay_seconds.clone()))?;
         registry.register(Box::new(network_transit_seconds.clone()))?;
         registry.register(Box::new(receiver_pipeline_seconds.clone()))?;
 
         Ok(Self {
             registry,
             packets_sent_total,
+            packets_failed_total,
+            destination_packets_sent_total,
+            destination_packets_failed_total,
+            packets_rate_limited_total,
+            rate_limit_wait_seconds_total,
             packets_received_total,
             packets_lost_total,
             packets_reordered_total,
             packets_late_total,
+            packets_duplicate_total,
+            pacing_catchup_events_total,
+            frames_skipped_total,
+            frames_dtx_suppressed_total,
+            frames_fec_recovered_total,
+            frames_concealed_total,
+            decoder_refresh_requests_sent_total,
+            decoder_refresh_requests_suppressed_total,
+            decoder_refresh_requests_received_total,
+            encoder_resets_total,
+            nack_packets_requested_total,
+            nack_requests_received_total,
+            packets_retransmitted_total,
+            keepalives_sent_total,
+            keepalives_received_total,
+            sender_reports_sent_total,
+            sender_reports_received_total,
+            receiver_reports_sent_total,
+            receiver_reports_received_total,
+            remote_fraction_lost,
+            remote_jitter_seconds,
+            extended_reports_sent_total,
+            extended_reports_received_total,
+            remote_mos_lq,
+            anomalies_suppressed_total,
+            payload_crc_mismatch_total,
+            payload_type_mismatch_total,
+            ssrc_changes_total,
+            ssrc_stale_packets_total,
+            ssrc_unconfirmed_packets_total,
+            parse_errors_total,
+            srtp_auth_failures_total,
+            datagrams_oversized_total,
             bytes_sent_total,
+            padding_bytes_sent_total,
             bytes_received_total,
+            bytes_received_gross_total,
             jitter_buffer_occupancy_packets,
+            jitter_buffer_overflow_total,
+            jitter_buffer_resync_total,
+            jitter_buffer_priming_overflow_total,
+            jitter_buffer_primed,
+            jitter_buffer_target_depth_seconds,
+            jitter_buffer_lost_total,
+            jitter_buffer_gaps_skipped_total,
+            jitter_buffer_underruns_total,
+            jitter_buffer_catch_up_drops_total,
+            jitter_buffer_accelerated_total,
+            jitter_buffer_residence_seconds,
+            clock_rate_hz,
+            frame_duration_seconds,
+            interarrival_jitter_seconds,
+            sink_setup_seconds,
+            sink_setup_buffered_packets,
+            build_info,
+            encoder_vbr_mode,
+            encoder_target_bitrate_bps,
+            current_bitrate_bps,
+            session_state,
+            session_state_seconds_total,
             encode_seconds,
+            pacing_deviation_seconds,
             decode_seconds,
             jitter_buffer_delay_seconds,
+            priming_duration_seconds,
+            priming_buffered_delay_seconds,
             network_transit_seconds,
             receiver_pipeline_seconds,
         })
@@ -166,20 +747,41 @@ impl MetricsContext {
         self.registry.gather()
     }
 
-    /// Spawns a minimal HTTP server that serves `GET /metrics`.
+    /// Publishes what this process was built from as the
+    /// `rtp_opus_build_info` gauge, set to 1 and labelled with the binary,
+    /// version, git hash, and enabled features.
+    pub fn set_build_info(&self, info: &BuildInfo) {
+        // ---
+        self.build_info
+            .with_label_values(&[
+                info.binary(),
+                info.version(),
+                info.git_hash(),
+                &info.features().join(","),
+            ])
+            .set(1);
+    }
+
+    /// Spawns a minimal HTTP server that serves `GET /metrics`, and
+    /// `GET /stats` if [`MetricsServerConfig::stats`] is set.
     ///
     /// This is intentionally explicit (callers decide whether to run it).
+    /// Requires the `metrics-server` feature.
+    #[cfg(feature = "metrics-server")]
     pub fn spawn_metrics_server(&self, cfg: MetricsServerConfig) -> JoinHandle<Result<()>> {
         // ---
         let registry = Arc::new(self.registry.clone());
+        let stats = cfg.stats.clone();
         tokio::spawn(async move {
             // ---
             let make_svc = make_service_fn(move |_conn| {
                 let registry = Arc::clone(&registry);
+                let stats = stats.clone();
                 async move {
                     Ok::<_, hyper::Error>(service_fn(move |req| {
                         let registry = Arc::clone(&registry);
-                        async move { handle_metrics_request(req, registry).await }
+                        let stats = stats.clone();
+                        async move { handle_metrics_request(req, registry, stats).await }
                     }))
                 }
             });
@@ -191,12 +793,22 @@ impl MetricsContext {
     }
 }
 
+#[cfg(feature = "metrics-server")]
 async fn handle_metrics_request(
     req: Request<Body>,
     registry: Arc<Registry>,
+    stats: Option<StatsRenderer>,
 ) -> Result<Response<Body>, hyper::Error> {
-    match (req.method(), req.uri().path()) {
-        (&Method::GET, "/metrics") => {
+    match (req.method(), req.uri().path(), stats) {
+        (&Method::GET, "/stats", Some(render)) => {
+            let mut resp = Response::new(Body::from(render()));
+            resp.headers_mut().insert(
+                hyper::header::CONTENT_TYPE,
+                hyper::header::HeaderValue::from_static("text/plain; charset=utf-8"),
+            );
+            Ok(resp)
+        }
+        (&Method::GET, "/metrics", _) => {
             let encoder = TextEncoder::new();
             let metric_families = registry.gather();
             let mut buffer = Vec::new();
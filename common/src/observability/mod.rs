@@ -1,7 +1,14 @@
 //! Observability utilities (metrics + tracing).
 
+mod anomaly;
 mod metrics;
 mod tracing;
 
-pub use metrics::{MetricsContext, MetricsServerConfig};
+pub use anomaly::{
+    AnomalyClass, AnomalyLogConfig, AnomalyLogger, AnomalyOutcome, AnomalySummary,
+    DEFAULT_ANOMALY_LOG_INTERVAL,
+};
+pub use metrics::MetricsContext;
+#[cfg(feature = "metrics-server")]
+pub use metrics::{MetricsServerConfig, StatsRenderer};
 pub use tracing::init_tracing;
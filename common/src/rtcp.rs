@@ -0,0 +1,1788 @@
+//! RTCP feedback messages.
+//!
+//! Implements the payload-specific feedback format of RFC 4585 §6.1 for
+//! a decoder refresh request, carried as a Picture Loss Indication (PLI,
+//! §6.3.1). Audio has no pictures, but PLI has exactly the semantics
+//! needed — "my decoder state is unusable, send something decodable on
+//! its own" — and standard RTCP tooling already understands it.
+//!
+//! Lost packets are asked for again with a Generic NACK (RFC 4585
+//! §6.2.1), which the sender answers by retransmitting them.
+//!
+//! A receiver behind NAT also sends a [`Keepalive`] toward the sender, an
+//! empty receiver report, to open and hold the path the stream comes back
+//! on (symmetric RTP).
+//!
+//! The media sender reports what it has sent in a [`SenderReport`]
+//! (RFC 3550 §6.4.1), tying its RTP timestamps to wallclock time, and the
+//! receiver how reception is going in a [`ReceiverReport`] (§6.4.2).
+//! Sender reports go out compound with a source description ([`Sdes`],
+//! §6.5) naming the stream by CNAME, and a sender leaving says so with a
+//! [`Bye`] (§6.6); see [`compound_packets`] to take a compound datagram
+//! apart.
+//!
+//! All of these share the RTP socket (RTCP multiplexing, RFC 5761); see
+//! [`is_rtcp`] to tell them from RTP packets.
+
+use anyhow::Result;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::rtcp_xr::ExtendedReport;
+
+/// RTP/RTCP version 2
+pub(crate) const RTCP_VERSION: u8 = 2;
+
+/// RTCP packet type for payload-specific feedback (PSFB)
+pub const RTCP_PT_PSFB: u8 = 206;
+
+/// PSFB feedback message type for Picture Loss Indication
+pub const PSFB_FMT_PLI: u8 = 1;
+
+/// Size of a serialized PLI in bytes (common header plus two SSRCs)
+pub const PLI_LEN: usize = 12;
+
+/// RTCP packet type for transport-layer feedback (RTPFB, RFC 4585)
+pub const RTCP_PT_RTPFB: u8 = 205;
+
+/// Feedback message type of a Generic NACK within RTPFB
+pub const RTPFB_FMT_NACK: u8 = 1;
+
+/// Size of a NACK without feedback control information (FCI) entries
+const NACK_HEADER_LEN: usize = 12;
+
+/// RTCP packet type for a receiver report (RR)
+pub const RTCP_PT_RR: u8 = 201;
+
+/// Size of a serialized keepalive in bytes (common header plus one SSRC)
+pub const KEEPALIVE_LEN: usize = 8;
+
+/// RTCP packet type for a sender report (SR)
+pub const RTCP_PT_SR: u8 = 200;
+
+/// Size of a serialized sender report without report blocks
+pub const SENDER_REPORT_LEN: usize = 28;
+
+/// Size of a serialized report block
+pub const REPORT_BLOCK_LEN: usize = 24;
+
+/// Most report blocks one receiver report carries (the 5-bit RC field)
+pub const MAX_REPORT_BLOCKS: usize = 31;
+
+/// RTCP packet type for a source description (SDES)
+pub const RTCP_PT_SDES: u8 = 202;
+
+/// SDES item type of the canonical name (CNAME)
+pub const SDES_CNAME: u8 = 1;
+
+/// Longest SDES item text in bytes (the 8-bit length field)
+pub const MAX_SDES_TEXT_LEN: usize = 255;
+
+/// RTCP packet type for a goodbye (BYE)
+pub const RTCP_PT_BYE: u8 = 203;
+
+/// Seconds from the NTP epoch (1900) to the Unix epoch (1970)
+const NTP_UNIX_OFFSET_SECS: u64 = 2_208_988_800;
+
+/// Share of the session bandwidth RTCP may use (RFC 3550 §6.2)
+pub const RTCP_BANDWIDTH_FRACTION: f64 = 0.05;
+
+/// Session bandwidth in bits per second assumed when none is known: a
+/// 32 kb/s Opus stream with its RTP, UDP, and IP headers, and room to spare
+pub const DEFAULT_SESSION_BANDWIDTH: u64 = 64_000;
+
+/// Bytes of UDP and IPv4 header counted into the size of every RTCP
+/// datagram for the report interval (RFC 3550 §6.2)
+const RTCP_LOWER_LAYER_OVERHEAD: usize = 28;
+
+/// Average RTCP datagram size assumed before any has been seen: a report
+/// with one block and a short SDES, with its UDP and IP headers
+const INITIAL_AVG_RTCP_SIZE: f64 = 100.0;
+
+/// Returns whether a datagram on a multiplexed socket is RTCP rather than
+/// RTP: RTCP packet types 192-223 fall where RTP's marker bit and payload
+/// type would be 64-95, which RTP avoids (RFC 5761 §4).
+pub fn is_rtcp(data: &[u8]) -> bool {
+    // ---
+    data.len() >= 4 && data[0] >> 6 == RTCP_VERSION && (192..=223).contains(&data[1])
+}
+
+/// Splits a compound RTCP datagram (RFC 3550 §6.1) into its packets by
+/// their length fields.
+///
+/// Stops at the first packet that is not RTCP or whose length runs past
+/// the datagram; a single packet comes back whole.
+pub fn compound_packets(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    // ---
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if !is_rtcp(rest) {
+            return None;
+        }
+        let len = (u16::from_be_bytes([rest[2], rest[3]]) as usize + 1) * 4;
+        if len > rest.len() {
+            return None;
+        }
+        let (packet, tail) = rest.split_at(len);
+        rest = tail;
+        Some(packet)
+    })
+}
+
+/// Returns the 64-bit NTP timestamp of `time`: seconds since 1900 in the
+/// upper 32 bits, the fraction in the lower.
+pub fn ntp_timestamp(time: SystemTime) -> u64 {
+    // ---
+    let since_unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_unix.as_secs() + NTP_UNIX_OFFSET_SECS;
+    let fraction = ((since_unix.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    seconds << 32 | fraction
+}
+
+/// Returns the wallclock time of a 64-bit NTP timestamp.
+pub fn ntp_to_system_time(ntp: u64) -> SystemTime {
+    // ---
+    let seconds = (ntp >> 32).saturating_sub(NTP_UNIX_OFFSET_SECS);
+    let nanos = ((ntp & 0xFFFF_FFFF) * 1_000_000_000) >> 32;
+    UNIX_EPOCH + Duration::new(seconds, nanos as u32)
+}
+
+/// Request that the media sender refresh its encoder state.
+///
+/// Sent by a receiver after an outage long enough that its decoder
+/// state no longer matches the encoder's prediction state.
+///
+/// # Wire Format
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P| FMT=1   |    PT=206     |          length=2             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of packet sender                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of media source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderRefreshRequest {
+    // ---
+    /// SSRC of the receiver sending the request
+    pub sender_ssrc: u32,
+
+    /// SSRC of the stream whose encoder should be refreshed
+    pub media_ssrc: u32,
+}
+
+impl DecoderRefreshRequest {
+    // ---
+    /// Serializes the request as an RTCP PLI.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        let mut buf = Vec::with_capacity(PLI_LEN);
+        buf.push(RTCP_VERSION << 6 | PSFB_FMT_PLI);
+        buf.push(RTCP_PT_PSFB);
+
+        // Length in 32-bit words minus one
+        let words = (PLI_LEN / 4 - 1) as u16;
+        buf.extend_from_slice(&words.to_be_bytes());
+        buf.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.media_ssrc.to_be_bytes());
+        buf
+    }
+
+    /// Parses an RTCP PLI.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Raw datagram received from the network
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is not a version 2 PSFB packet with
+    /// the PLI message type and a complete body.
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.len() < PLI_LEN {
+            anyhow::bail!("RTCP feedback too small: {} bytes", data.len());
+        }
+
+        let version = data[0] >> 6;
+        if version != RTCP_VERSION {
+            anyhow::bail!("invalid RTCP version: {}", version);
+        }
+        if data[1] != RTCP_PT_PSFB || data[0] & 0x1F != PSFB_FMT_PLI {
+            anyhow::bail!(
+                "not a PLI: packet type {}, feedback type {}",
+                data[1],
+                data[0] & 0x1F
+            );
+        }
+
+        Ok(Self {
+            sender_ssrc: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            media_ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+        })
+    }
+}
+
+/// Generic NACK: sequence numbers a receiver asks to be sent again.
+///
+/// # Wire Format
+///
+/// The RTPFB common header with FMT=1 and the two SSRCs, followed by one
+/// or more FCI entries. Each entry names a lost packet (PID) and, in the
+/// bitmask of following lost packets (BLP), which of the next 16 were lost
+/// too: bit `i` set means PID + i + 1 was.
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|  FMT=1  |    PT=205     |          length=2+N           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of packet sender                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of media source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            PID                |             BLP               |  x N
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nack {
+    // ---
+    /// SSRC of the receiver sending the request (0 if it sends no media)
+    pub sender_ssrc: u32,
+
+    /// SSRC of the stream the packets belong to
+    pub media_ssrc: u32,
+
+    /// Sequence numbers of the lost packets, in the order they were lost
+    pub lost: Vec<u16>,
+}
+
+impl Nack {
+    // ---
+    /// Serializes the NACK, packing runs of nearby sequence numbers into
+    /// shared FCI entries.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        let mut entries: Vec<(u16, u16)> = Vec::new();
+        for &sequence in &self.lost {
+            if let Some((pid, blp)) = entries.last_mut() {
+                let offset = sequence.wrapping_sub(*pid);
+                if offset == 0 {
+                    continue;
+                }
+                if offset <= 16 {
+                    *blp |= 1 << (offset - 1);
+                    continue;
+                }
+            }
+            entries.push((sequence, 0));
+        }
+
+        let mut buf = Vec::with_capacity(NACK_HEADER_LEN + 4 * entries.len());
+        buf.push((RTCP_VERSION << 6) | RTPFB_FMT_NACK);
+        buf.push(RTCP_PT_RTPFB);
+
+        // Length in 32-bit words minus one
+        let words = (2 + entries.len()) as u16;
+        buf.extend_from_slice(&words.to_be_bytes());
+        buf.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.media_ssrc.to_be_bytes());
+        for (pid, blp) in entries {
+            buf.extend_from_slice(&pid.to_be_bytes());
+            buf.extend_from_slice(&blp.to_be_bytes());
+        }
+        buf
+    }
+
+    /// Parses a Generic NACK.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is too short, is not a version 2
+    /// Generic NACK, or its length field runs past the data.
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.len() < NACK_HEADER_LEN + 4 {
+            anyhow::bail!("RTCP NACK too small: {} bytes", data.len());
+        }
+        let version = data[0] >> 6;
+        let fmt = data[0] & 0x1F;
+        if version != RTCP_VERSION || data[1] != RTCP_PT_RTPFB || fmt != RTPFB_FMT_NACK {
+            anyhow::bail!(
+                "not a generic NACK: version {}, PT {}, FMT {}",
+                version,
+                data[1],
+                fmt
+            );
+        }
+        let len = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        if len > data.len() {
+            anyhow::bail!("RTCP NACK length {} exceeds {} bytes", len, data.len());
+        }
+
+        let mut lost = Vec::new();
+        for entry in data[NACK_HEADER_LEN..len].chunks_exact(4) {
+            let pid = u16::from_be_bytes([entry[0], entry[1]]);
+            let blp = u16::from_be_bytes([entry[2], entry[3]]);
+            lost.push(pid);
+            lost.extend(
+                (0..16)
+                    .filter(|bit| blp & (1 << bit) != 0)
+                    .map(|bit| pid.wrapping_add(bit + 1)),
+            );
+        }
+
+        Ok(Self {
+            sender_ssrc: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            media_ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+            lost,
+        })
+    }
+}
+
+/// Datagram a receiver sends to the media sender to be found behind NAT.
+///
+/// A sender in listen mode streams to wherever the first one comes from;
+/// sent again periodically, it keeps the NAT binding open. It is an RTCP
+/// receiver report with no report blocks (RFC 3550 §6.4.2), so RTP stacks
+/// that do not expect it discard it as ordinary RTCP.
+///
+/// # Wire Format
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|  RC=0   |    PT=201     |          length=1             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of packet sender                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keepalive {
+    // ---
+    /// SSRC of the receiver sending the keepalive
+    pub sender_ssrc: u32,
+}
+
+impl Keepalive {
+    // ---
+    /// Serializes the keepalive as an empty RTCP receiver report.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        let mut buf = Vec::with_capacity(KEEPALIVE_LEN);
+        buf.push(RTCP_VERSION << 6);
+        buf.push(RTCP_PT_RR);
+
+        // Length in 32-bit words minus one
+        let words = (KEEPALIVE_LEN / 4 - 1) as u16;
+        buf.extend_from_slice(&words.to_be_bytes());
+        buf.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        buf
+    }
+
+    /// Parses an empty RTCP receiver report.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is not a version 2 receiver report
+    /// without report blocks.
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.len() < KEEPALIVE_LEN {
+            anyhow::bail!("RTCP report too small: {} bytes", data.len());
+        }
+        if data[0] != RTCP_VERSION << 6 || data[1] != RTCP_PT_RR {
+            anyhow::bail!("not an empty receiver report: {:02X} {}", data[0], data[1]);
+        }
+
+        Ok(Self {
+            sender_ssrc: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        })
+    }
+}
+
+/// Returns `delay` in the units of the DLSR field: 1/65536 seconds,
+/// saturating.
+pub fn dlsr_units(delay: Duration) -> u32 {
+    // ---
+    (delay.as_secs_f64() * 65_536.0).min(u32::MAX as f64) as u32
+}
+
+/// Reception statistics for one stream, as a receiver report carries
+/// them (RFC 3550 §6.4.1).
+///
+/// # Wire Format
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 SSRC of the stream reported on                |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | fraction lost |       cumulative number of packets lost       |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           extended highest sequence number received           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      interarrival jitter                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         last SR (LSR)                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                   delay since last SR (DLSR)                  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReportBlock {
+    // ---
+    /// SSRC of the stream reported on
+    pub ssrc: u32,
+
+    /// Packets lost since the previous report, as a fraction of those
+    /// expected, in 256ths
+    pub fraction_lost: u8,
+
+    /// Packets lost since reception started; negative with duplicates.
+    /// Carried in 24 bits, so clamped to ±2^23.
+    pub cumulative_lost: i32,
+
+    /// Highest sequence number received, with the wrap count in the
+    /// upper 16 bits
+    pub extended_highest_sequence: u32,
+
+    /// Interarrival jitter in RTP timestamp units
+    pub jitter: u32,
+
+    /// Middle 32 bits of the NTP timestamp of the last sender report
+    /// received ([`SenderReport::compact_ntp`]), 0 if none
+    pub last_sr: u32,
+
+    /// Time from receiving that sender report to sending this report, in
+    /// 1/65536 seconds (see [`dlsr_units`]), 0 if none
+    pub delay_since_last_sr: u32,
+}
+
+impl ReportBlock {
+    // ---
+    /// Returns the fraction lost as a ratio from 0 to 1.
+    pub fn fraction_lost_ratio(&self) -> f64 {
+        // ---
+        self.fraction_lost as f64 / 256.0
+    }
+
+    /// Appends the block to `buf`.
+    fn write(&self, buf: &mut Vec<u8>) {
+        // ---
+        let cumulative_lost = self.cumulative_lost.clamp(-0x80_0000, 0x7F_FFFF);
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        buf.push(self.fraction_lost);
+        buf.extend_from_slice(&cumulative_lost.to_be_bytes()[1..]);
+        buf.extend_from_slice(&self.extended_highest_sequence.to_be_bytes());
+        buf.extend_from_slice(&self.jitter.to_be_bytes());
+        buf.extend_from_slice(&self.last_sr.to_be_bytes());
+        buf.extend_from_slice(&self.delay_since_last_sr.to_be_bytes());
+    }
+
+    /// Parses a block from the first [`REPORT_BLOCK_LEN`] bytes of `data`.
+    fn read(data: &[u8]) -> Self {
+        // ---
+        let word =
+            |at: usize| u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+        Self {
+            ssrc: word(0),
+            fraction_lost: data[4],
+            // Sign-extends the 24-bit count
+            cumulative_lost: (word(4) << 8) as i32 >> 8,
+            extended_highest_sequence: word(8),
+            jitter: word(12),
+            last_sr: word(16),
+            delay_since_last_sr: word(20),
+        }
+    }
+}
+
+/// How reception is going: an RTCP receiver report (RFC 3550 §6.4.2),
+/// sent back to the media sender.
+///
+/// # Wire Format
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|    RC   |    PT=201     |         length=1+6*RC         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of packet sender                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  report block (see ReportBlock)               |  x RC
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// With no blocks it is a [`Keepalive`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiverReport {
+    // ---
+    /// SSRC of the receiver sending the report (0 if it sends no media)
+    pub sender_ssrc: u32,
+
+    /// One block per stream reported on (at most [`MAX_REPORT_BLOCKS`];
+    /// more are not sent)
+    pub blocks: Vec<ReportBlock>,
+}
+
+impl ReceiverReport {
+    // ---
+    /// Serializes the report as an RTCP RR.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        let blocks = &self.blocks[..self.blocks.len().min(MAX_REPORT_BLOCKS)];
+        let len = KEEPALIVE_LEN + REPORT_BLOCK_LEN * blocks.len();
+        let mut buf = Vec::with_capacity(len);
+        buf.push(RTCP_VERSION << 6 | blocks.len() as u8);
+        buf.push(RTCP_PT_RR);
+
+        // Length in 32-bit words minus one
+        let words = (len / 4 - 1) as u16;
+        buf.extend_from_slice(&words.to_be_bytes());
+        buf.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        for block in blocks {
+            block.write(&mut buf);
+        }
+        buf
+    }
+
+    /// Parses an RTCP RR, skipping profile-specific extensions after the
+    /// report blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is not a version 2 receiver report,
+    /// or its blocks or length field run past the data.
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.len() < KEEPALIVE_LEN {
+            anyhow::bail!("RTCP receiver report too small: {} bytes", data.len());
+        }
+        let version = data[0] >> 6;
+        if version != RTCP_VERSION || data[1] != RTCP_PT_RR {
+            anyhow::bail!("not a receiver report: version {}, PT {}", version, data[1]);
+        }
+        let count = (data[0] & 0x1F) as usize;
+        let len = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        if len < KEEPALIVE_LEN + REPORT_BLOCK_LEN * count || len > data.len() {
+            anyhow::bail!(
+                "RTCP receiver report length {} invalid for {} blocks in {} bytes",
+                len,
+                count,
+                data.len()
+            );
+        }
+
+        Ok(Self {
+            sender_ssrc: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            blocks: data[KEEPALIVE_LEN..]
+                .chunks_exact(REPORT_BLOCK_LEN)
+                .take(count)
+                .map(ReportBlock::read)
+                .collect(),
+        })
+    }
+}
+
+/// What a media sender has sent, and when: an RTCP sender report
+/// (RFC 3550 §6.4.1) without report blocks.
+///
+/// The NTP and RTP timestamps are the same instant, so a receiver can map
+/// the stream's RTP timestamps to the sender's wallclock. The middle 32
+/// bits of the NTP timestamp ([`compact_ntp`](Self::compact_ntp)) come
+/// back in receiver reports as the LSR field.
+///
+/// # Wire Format
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|  RC=0   |    PT=200     |          length=6             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         SSRC of sender                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |              NTP timestamp, most significant word             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |             NTP timestamp, least significant word             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         RTP timestamp                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                     sender's packet count                     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                      sender's octet count                     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SenderReport {
+    // ---
+    /// SSRC of the stream reported on
+    pub ssrc: u32,
+
+    /// Wallclock time of the report (see [`ntp_timestamp`])
+    pub ntp_timestamp: u64,
+
+    /// RTP timestamp of the same instant
+    pub rtp_timestamp: u32,
+
+    /// RTP packets sent since the stream started (wraps)
+    pub packet_count: u32,
+
+    /// Payload octets sent since the stream started, headers and padding
+    /// excluded (wraps)
+    pub octet_count: u32,
+}
+
+impl SenderReport {
+    // ---
+    /// Returns the middle 32 bits of the NTP timestamp, as a receiver
+    /// report echoes them (LSR).
+    pub fn compact_ntp(&self) -> u32 {
+        // ---
+        (self.ntp_timestamp >> 16) as u32
+    }
+
+    /// Serializes the report as an RTCP SR.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        let mut buf = Vec::with_capacity(SENDER_REPORT_LEN);
+        buf.push(RTCP_VERSION << 6);
+        buf.push(RTCP_PT_SR);
+
+        // Length in 32-bit words minus one
+        let words = (SENDER_REPORT_LEN / 4 - 1) as u16;
+        buf.extend_from_slice(&words.to_be_bytes());
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.ntp_timestamp.to_be_bytes());
+        buf.extend_from_slice(&self.rtp_timestamp.to_be_bytes());
+        buf.extend_from_slice(&self.packet_count.to_be_bytes());
+        buf.extend_from_slice(&self.octet_count.to_be_bytes());
+        buf
+    }
+
+    /// Parses an RTCP SR. Report blocks, and packets following the SR in
+    /// a compound packet, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is not a version 2 sender report, or
+    /// its length field runs past the data.
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.len() < SENDER_REPORT_LEN {
+            anyhow::bail!("RTCP sender report too small: {} bytes", data.len());
+        }
+        let version = data[0] >> 6;
+        if version != RTCP_VERSION || data[1] != RTCP_PT_SR {
+            anyhow::bail!("not a sender report: version {}, PT {}", version, data[1]);
+        }
+        let len = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        if len < SENDER_REPORT_LEN || len > data.len() {
+            anyhow::bail!(
+                "RTCP sender report length {} invalid for {} bytes",
+                len,
+                data.len()
+            );
+        }
+
+        let word =
+            |at: usize| u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+        Ok(Self {
+            ssrc: word(4),
+            ntp_timestamp: (word(8) as u64) << 32 | word(12) as u64,
+            rtp_timestamp: word(16),
+            packet_count: word(20),
+            octet_count: word(24),
+        })
+    }
+}
+
+/// Returns a CNAME for this host in the `user@host` form RFC 3550
+/// §6.5.1 suggests, from the environment; the host alone if there is no
+/// user.
+pub fn default_cname() -> String {
+    // ---
+    let non_empty = |name: String| {
+        let name = name.trim().to_string();
+        (!name.is_empty()).then_some(name)
+    };
+    let host = ["HOSTNAME", "COMPUTERNAME"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().and_then(non_empty))
+        .or_else(|| {
+            std::fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .and_then(non_empty)
+        })
+        .unwrap_or_else(|| "localhost".to_string());
+    match ["USER", "USERNAME"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().and_then(non_empty))
+    {
+        Some(user) => format!("{user}@{host}"),
+        None => host,
+    }
+}
+
+/// One item of an SDES chunk: a type ([`SDES_CNAME`], ...) and its text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdesItem {
+    // ---
+    /// Item type
+    pub kind: u8,
+
+    /// Item text, truncated to [`MAX_SDES_TEXT_LEN`] bytes when sent
+    pub text: String,
+}
+
+/// The items describing one source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdesChunk {
+    // ---
+    /// SSRC of the source described
+    pub ssrc: u32,
+
+    /// Its items, in the order sent
+    pub items: Vec<SdesItem>,
+}
+
+impl SdesChunk {
+    // ---
+    /// Creates a chunk naming `ssrc` by `cname` alone.
+    pub fn with_cname(ssrc: u32, cname: impl Into<String>) -> Self {
+        // ---
+        Self {
+            ssrc,
+            items: vec![SdesItem {
+                kind: SDES_CNAME,
+                text: cname.into(),
+            }],
+        }
+    }
+
+    /// Returns the chunk's CNAME, if it has one.
+    pub fn cname(&self) -> Option<&str> {
+        // ---
+        self.items
+            .iter()
+            .find(|item| item.kind == SDES_CNAME)
+            .map(|item| item.text.as_str())
+    }
+}
+
+/// A source description: an RTCP SDES packet (RFC 3550 §6.5).
+///
+/// # Wire Format
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|    SC   |    PT=202     |             length            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          SSRC/CSRC_1                          |  chunk
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+  x SC
+/// |     type      |    length     |  text ...                     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  ... more items, then a zero type, zero-padded to 32 bits     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sdes {
+    // ---
+    /// One chunk per source (at most 31, the 5-bit SC field; more are not
+    /// sent)
+    pub chunks: Vec<SdesChunk>,
+}
+
+impl Sdes {
+    // ---
+    /// Serializes the description as an RTCP SDES packet.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        let chunks = &self.chunks[..self.chunks.len().min(MAX_REPORT_BLOCKS)];
+        let mut buf = vec![RTCP_VERSION << 6 | chunks.len() as u8, RTCP_PT_SDES, 0, 0];
+        for chunk in chunks {
+            buf.extend_from_slice(&chunk.ssrc.to_be_bytes());
+            for item in &chunk.items {
+                let text = truncate_utf8(&item.text, MAX_SDES_TEXT_LEN);
+                buf.extend_from_slice(&[item.kind, text.len() as u8]);
+                buf.extend_from_slice(text.as_bytes());
+            }
+
+            // The end of the items, and padding to the next word
+            buf.push(0);
+            buf.resize(buf.len().next_multiple_of(4), 0);
+        }
+
+        // Length in 32-bit words minus one
+        let words = (buf.len() / 4 - 1) as u16;
+        buf[2..4].copy_from_slice(&words.to_be_bytes());
+        buf
+    }
+
+    /// Parses an RTCP SDES packet. Item text that is not UTF-8 is taken
+    /// lossily.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is not a version 2 SDES packet, or a
+    /// chunk runs past its length field.
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.len() < 4 {
+            anyhow::bail!("RTCP SDES too small: {} bytes", data.len());
+        }
+        let version = data[0] >> 6;
+        if version != RTCP_VERSION || data[1] != RTCP_PT_SDES {
+            anyhow::bail!("not an SDES packet: version {}, PT {}", version, data[1]);
+        }
+        let count = (data[0] & 0x1F) as usize;
+        let end = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        if end > data.len() {
+            anyhow::bail!("RTCP SDES length {} invalid for {} bytes", end, data.len());
+        }
+
+        let mut chunks = Vec::with_capacity(count);
+        let mut at = 4;
+        for index in 0..count {
+            let truncated = || anyhow::anyhow!("RTCP SDES chunk {} truncated", index);
+            let ssrc = data[..end].get(at..at + 4).ok_or_else(truncated)?;
+            let ssrc = u32::from_be_bytes([ssrc[0], ssrc[1], ssrc[2], ssrc[3]]);
+            at += 4;
+
+            let mut items = Vec::new();
+            loop {
+                let kind = *data[..end].get(at).ok_or_else(truncated)?;
+                if kind == 0 {
+                    at = (at + 1).next_multiple_of(4);
+                    break;
+                }
+                let len = *data[..end].get(at + 1).ok_or_else(truncated)? as usize;
+                let text = data[..end]
+                    .get(at + 2..at + 2 + len)
+                    .ok_or_else(truncated)?;
+                items.push(SdesItem {
+                    kind,
+                    text: String::from_utf8_lossy(text).into_owned(),
+                });
+                at += 2 + len;
+            }
+            chunks.push(SdesChunk { ssrc, items });
+        }
+
+        Ok(Self { chunks })
+    }
+}
+
+/// Sources leaving the session: an RTCP BYE packet (RFC 3550 §6.6).
+///
+/// # Wire Format
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|    SC   |    PT=203     |             length            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           SSRC/CSRC                           |  x SC
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     length    |  reason for leaving, zero-padded to 32 bits ... (opt)
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bye {
+    // ---
+    /// Sources leaving (at most 31, the 5-bit SC field; more are not
+    /// sent)
+    pub ssrcs: Vec<u32>,
+
+    /// Why, if said; truncated to [`MAX_SDES_TEXT_LEN`] bytes when sent
+    pub reason: Option<String>,
+}
+
+impl Bye {
+    // ---
+    /// Serializes the goodbye as an RTCP BYE.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        let ssrcs = &self.ssrcs[..self.ssrcs.len().min(MAX_REPORT_BLOCKS)];
+        let mut buf = vec![RTCP_VERSION << 6 | ssrcs.len() as u8, RTCP_PT_BYE, 0, 0];
+        for ssrc in ssrcs {
+            buf.extend_from_slice(&ssrc.to_be_bytes());
+        }
+        if let Some(reason) = &self.reason {
+            let reason = truncate_utf8(reason, MAX_SDES_TEXT_LEN);
+            buf.push(reason.len() as u8);
+            buf.extend_from_slice(reason.as_bytes());
+            buf.resize(buf.len().next_multiple_of(4), 0);
+        }
+
+        // Length in 32-bit words minus one
+        let words = (buf.len() / 4 - 1) as u16;
+        buf[2..4].copy_from_slice(&words.to_be_bytes());
+        buf
+    }
+
+    /// Parses an RTCP BYE. A reason that is not UTF-8 is taken lossily.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is not a version 2 BYE, or its
+    /// sources or reason run past its length field.
+    pub fn deserialize(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.len() < 4 {
+            anyhow::bail!("RTCP BYE too small: {} bytes", data.len());
+        }
+        let version = data[0] >> 6;
+        if version != RTCP_VERSION || data[1] != RTCP_PT_BYE {
+            anyhow::bail!("not a BYE packet: version {}, PT {}", version, data[1]);
+        }
+        let count = (data[0] & 0x1F) as usize;
+        let end = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        let reason_at = 4 + 4 * count;
+        if end > data.len() || reason_at > end {
+            anyhow::bail!(
+                "RTCP BYE length {} invalid for {} sources in {} bytes",
+                end,
+                count,
+                data.len()
+            );
+        }
+
+        let ssrcs = data[4..reason_at]
+            .chunks_exact(4)
+            .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        let reason = match data[reason_at..end].split_first() {
+            Some((&len, rest)) => {
+                let text = rest
+                    .get(..len as usize)
+                    .ok_or_else(|| anyhow::anyhow!("RTCP BYE reason truncated"))?;
+                Some(String::from_utf8_lossy(text).into_owned())
+            }
+            None => None,
+        };
+
+        Ok(Self { ssrcs, reason })
+    }
+}
+
+/// A compound RTCP packet (RFC 3550 §6.1): the RTCP packets sent together
+/// in one datagram.
+///
+/// A compound packet starts with a sender or receiver report, followed by
+/// any further receiver reports, then the source description, then other
+/// packets such as feedback, with a BYE last. The constructors start one
+/// with its report, and each `with_*` method places its packet where the
+/// order says, so a built packet is always valid. [`parse`](Self::parse)
+/// holds a received datagram to the same rules.
+///
+/// Padding (e.g. to an encryption block size) goes on the last packet
+/// only, with its P bit set and the padding count in its last octet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompoundPacket {
+    // ---
+    /// Serialized packets in wire order, without padding
+    packets: Vec<Vec<u8>>,
+}
+
+impl CompoundPacket {
+    // ---
+    /// Starts a compound packet with a sender report.
+    pub fn sender_report(report: &SenderReport) -> Self {
+        // ---
+        Self {
+            packets: vec![report.serialize()],
+        }
+    }
+
+    /// Starts a compound packet with a receiver report.
+    pub fn receiver_report(report: &ReceiverReport) -> Self {
+        // ---
+        Self {
+            packets: vec![report.serialize()],
+        }
+    }
+
+    /// Adds a source description after the reports.
+    pub fn with_sdes(self, sdes: &Sdes) -> Self {
+        // ---
+        self.with_packet(sdes.serialize())
+    }
+
+    /// Adds a NACK after the source description.
+    pub fn with_nack(self, nack: &Nack) -> Self {
+        // ---
+        self.with_packet(nack.serialize())
+    }
+
+    /// Adds a decoder refresh request (PLI) after the source description.
+    pub fn with_refresh_request(self, request: &DecoderRefreshRequest) -> Self {
+        // ---
+        self.with_packet(request.serialize())
+    }
+
+    /// Adds an extended report (RFC 3611) after the source description.
+    pub fn with_extended_report(self, report: &ExtendedReport) -> Self {
+        // ---
+        self.with_packet(report.serialize())
+    }
+
+    /// Adds a BYE, which always goes last.
+    pub fn with_bye(self, bye: &Bye) -> Self {
+        // ---
+        self.with_packet(bye.serialize())
+    }
+
+    /// Inserts `packet` after every packet that goes before it.
+    fn with_packet(mut self, packet: Vec<u8>) -> Self {
+        // ---
+        let rank = compound_rank(packet[1]);
+        let at = self
+            .packets
+            .iter()
+            .position(|other| compound_rank(other[1]) > rank)
+            .unwrap_or(self.packets.len());
+        self.packets.insert(at, packet);
+        self
+    }
+
+    /// Returns the packets in wire order, each without padding.
+    pub fn packets(&self) -> impl Iterator<Item = &[u8]> {
+        // ---
+        self.packets.iter().map(Vec::as_slice)
+    }
+
+    /// Returns the packet types in wire order.
+    pub fn packet_types(&self) -> Vec<u8> {
+        // ---
+        self.packets.iter().map(|packet| packet[1]).collect()
+    }
+
+    /// Serializes the compound packet without padding.
+    pub fn serialize(&self) -> Vec<u8> {
+        // ---
+        self.packets.concat()
+    }
+
+    /// Serializes the compound packet padded to a multiple of `block`
+    /// bytes, e.g. for an encryption block size. Nothing is added if it
+    /// already is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - Size to pad to a multiple of: a multiple of 4, at most
+    ///   256 so the count fits its octet
+    pub fn serialize_padded(&self, block: usize) -> Vec<u8> {
+        // ---
+        debug_assert!(block.is_multiple_of(4) && (4..=256).contains(&block));
+        let mut buf = self.serialize();
+        let padding = buf.len().next_multiple_of(block) - buf.len();
+        if padding == 0 {
+            return buf;
+        }
+
+        let last = buf.len() - self.packets.last().map_or(0, Vec::len);
+        buf[last] |= 0x20;
+        let words = u16::from_be_bytes([buf[last + 2], buf[last + 3]]) + (padding / 4) as u16;
+        buf[last + 2..last + 4].copy_from_slice(&words.to_be_bytes());
+        buf.resize(buf.len() + padding - 1, 0);
+        buf.push(padding as u8);
+        buf
+    }
+
+    /// Parses a compound RTCP datagram and checks it is framed and ordered
+    /// as RFC 3550 §6.1 says. Padding is removed from the last packet.
+    ///
+    /// A lone feedback packet (NACK or PLI) is accepted as a reduced-size
+    /// RTCP packet (RFC 5506), as receivers send them between reports.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the datagram is empty, not a whole number of
+    /// 32-bit words, or holds a packet that is not version 2 RTCP, runs
+    /// past the datagram, or is padded without being last or with an
+    /// invalid count; or if the packets are out of order: not starting
+    /// with a report, or anything after a BYE.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        // ---
+        if data.is_empty() || !data.len().is_multiple_of(4) {
+            anyhow::bail!("compound RTCP length {} not a multiple of 4", data.len());
+        }
+
+        let mut packets = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            if !is_rtcp(rest) {
+                anyhow::bail!(
+                    "packet {} of compound RTCP is not RTCP: version {}, PT {}",
+                    packets.len(),
+                    rest[0] >> 6,
+                    rest.get(1).copied().unwrap_or_default()
+                );
+            }
+            let len = (u16::from_be_bytes([rest[2], rest[3]]) as usize + 1) * 4;
+            if len > rest.len() {
+                anyhow::bail!(
+                    "packet {} of compound RTCP is {} bytes, only {} left",
+                    packets.len(),
+                    len,
+                    rest.len()
+                );
+            }
+            let (packet, tail) = rest.split_at(len);
+            rest = tail;
+
+            let mut packet = packet.to_vec();
+            if packet[0] & 0x20 != 0 {
+                if !rest.is_empty() {
+                    anyhow::bail!(
+                        "packet {} of compound RTCP padded but not last",
+                        packets.len()
+                    );
+                }
+                let padding = packet[len - 1] as usize;
+                if padding == 0 || !padding.is_multiple_of(4) || padding > len - 4 {
+                    anyhow::bail!("invalid RTCP padding count {} in {} bytes", padding, len);
+                }
+                packet.truncate(len - padding);
+                packet[0] &= !0x20;
+                let words = ((len - padding) / 4 - 1) as u16;
+                packet[2..4].copy_from_slice(&words.to_be_bytes());
+            }
+            packets.push(packet);
+        }
+
+        let types: Vec<u8> = packets.iter().map(|packet| packet[1]).collect();
+        let reduced_size = matches!(types.as_slice(), [RTCP_PT_RTPFB | RTCP_PT_PSFB]);
+        if !matches!(types[0], RTCP_PT_SR | RTCP_PT_RR) && !reduced_size {
+            anyhow::bail!("compound RTCP starts with PT {}, not a report", types[0]);
+        }
+        if let Some(pair) = types
+            .windows(2)
+            .find(|pair| pair[0] == RTCP_PT_BYE || compound_rank(pair[0]) > compound_rank(pair[1]))
+        {
+            anyhow::bail!("compound RTCP has PT {} after PT {}", pair[1], pair[0]);
+        }
+
+        Ok(Self { packets })
+    }
+}
+
+/// Returns where a packet of type `packet_type` goes in a compound packet:
+/// reports first, then the source description, then anything else, with
+/// the BYE last.
+fn compound_rank(packet_type: u8) -> u8 {
+    // ---
+    match packet_type {
+        RTCP_PT_SR | RTCP_PT_RR => 0,
+        RTCP_PT_SDES => 1,
+        RTCP_PT_BYE => 3,
+        _ => 2,
+    }
+}
+
+/// How often a participant sends RTCP reports, so RTCP stays within 5% of
+/// the session bandwidth however many take part (RFC 3550 §6.3.1 and
+/// Appendix A.7).
+///
+/// The interval is the deterministic one of the RFC: the time the
+/// participants' reports, at the average RTCP datagram size seen so far,
+/// take to use up their share of [`RTCP_BANDWIDTH_FRACTION`], and never
+/// less than a minimum interval. Senders share a quarter of the RTCP
+/// bandwidth and receivers the rest while senders are a quarter of the
+/// session or fewer. The RFC's randomization, which keeps the reports of
+/// a large session from synchronizing, is left out: a stream has one
+/// sender and few receivers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtcpInterval {
+    // ---
+    /// Session bandwidth in bits per second
+    session_bandwidth: u64,
+
+    /// Shortest interval, however much bandwidth there is
+    min_interval: Duration,
+
+    /// Average size of the RTCP datagrams sent and received, in bytes
+    /// with their UDP and IP headers
+    avg_rtcp_size: f64,
+}
+
+impl RtcpInterval {
+    // ---
+    /// Creates the interval computation for a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_bandwidth` - Bandwidth of the session in bits per second,
+    ///   headers included (see [`DEFAULT_SESSION_BANDWIDTH`])
+    /// * `min_interval` - Shortest interval between reports (RFC 3550
+    ///   suggests 5 seconds)
+    pub fn new(session_bandwidth: u64, min_interval: Duration) -> Self {
+        // ---
+        Self {
+            session_bandwidth: session_bandwidth.max(1),
+            min_interval,
+            avg_rtcp_size: INITIAL_AVG_RTCP_SIZE,
+        }
+    }
+
+    /// Returns the shortest interval between reports.
+    pub fn min_interval(&self) -> Duration {
+        // ---
+        self.min_interval
+    }
+
+    /// Returns the average RTCP datagram size, in bytes with UDP and IP
+    /// headers.
+    pub fn avg_rtcp_size(&self) -> f64 {
+        // ---
+        self.avg_rtcp_size
+    }
+
+    /// Counts an RTCP datagram of `len` bytes sent or received into the
+    /// average size.
+    pub fn record_packet(&mut self, len: usize) {
+        // ---
+        let size = (len + RTCP_LOWER_LAYER_OVERHEAD) as f64;
+        self.avg_rtcp_size += (size - self.avg_rtcp_size) / 16.0;
+    }
+
+    /// Returns the interval until this participant's next report.
+    ///
+    /// # Arguments
+    ///
+    /// * `members` - Participants in the session, this one included
+    /// * `senders` - Participants that sent media recently
+    /// * `we_sent` - Whether this participant is one of the senders
+    pub fn interval(&self, members: usize, senders: usize, we_sent: bool) -> Duration {
+        // ---
+        let mut rtcp_bandwidth = self.session_bandwidth as f64 / 8.0 * RTCP_BANDWIDTH_FRACTION;
+        let mut participants = members.max(1);
+        if senders > 0 && senders as f64 <= members as f64 * 0.25 {
+            if we_sent {
+                rtcp_bandwidth *= 0.25;
+                participants = senders;
+            } else {
+                rtcp_bandwidth *= 0.75;
+                participants = members - senders;
+            }
+        }
+        let interval = self.avg_rtcp_size * participants as f64 / rtcp_bandwidth;
+        Duration::from_secs_f64(interval).max(self.min_interval)
+    }
+}
+
+/// Returns the longest prefix of `text` of at most `max` bytes that ends
+/// on a character boundary.
+fn truncate_utf8(text: &str, max: usize) -> &str {
+    // ---
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_refresh_request_roundtrip() {
+        // ---
+        let request = DecoderRefreshRequest {
+            sender_ssrc: 0x0102_0304,
+            media_ssrc: 0xCAFE_F00D,
+        };
+        let serialized = request.serialize();
+
+        assert_eq!(serialized.len(), PLI_LEN);
+        assert_eq!(&serialized[..4], &[0x81, 206, 0, 2]);
+        assert_eq!(
+            DecoderRefreshRequest::deserialize(&serialized).expect("parse"),
+            request
+        );
+    }
+
+    #[test]
+    fn test_rejects_other_packets() {
+        // ---
+        let mut data = DecoderRefreshRequest {
+            sender_ssrc: 1,
+            media_ssrc: 2,
+        }
+        .serialize();
+        assert!(DecoderRefreshRequest::deserialize(&data[..8]).is_err());
+
+        // Generic NACK (RTPFB) and a plain RTP packet are not refresh requests
+        data[1] = 205;
+        assert!(DecoderRefreshRequest::deserialize(&data).is_err());
+        let rtp = crate::RtpPacket::new(1, 320, 2, vec![0; 20]);
+        assert!(DecoderRefreshRequest::deserialize(&rtp.serialize().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_keepalive_roundtrip() {
+        // ---
+        let keepalive = Keepalive { sender_ssrc: 7 };
+        let serialized = keepalive.serialize();
+
+        assert_eq!(serialized, [0x80, 201, 0, 1, 0, 0, 0, 7]);
+        assert_eq!(Keepalive::deserialize(&serialized).unwrap(), keepalive);
+
+        // A refresh request is not a keepalive
+        let request = DecoderRefreshRequest {
+            sender_ssrc: 7,
+            media_ssrc: 8,
+        };
+        assert!(Keepalive::deserialize(&request.serialize()).is_err());
+    }
+
+    #[test]
+    fn test_nack_roundtrip_packs_nearby_losses() {
+        // ---
+        let nack = Nack {
+            sender_ssrc: 0,
+            media_ssrc: 0x2057,
+            lost: vec![65530, 65535, 3, 40],
+        };
+        let serialized = nack.serialize();
+
+        // 65535 and 3 share the entry of 65530; 40 is too far from it
+        assert_eq!(serialized.len(), NACK_HEADER_LEN + 8);
+        assert_eq!(&serialized[..4], [0x81, 205, 0, 4]);
+        assert_eq!(&serialized[12..16], [0xFF, 0xFA, 0x01, 0x10]);
+        assert_eq!(Nack::deserialize(&serialized).unwrap(), nack);
+
+        // A refresh request is not a NACK, nor a NACK a refresh request
+        let request = DecoderRefreshRequest {
+            sender_ssrc: 0,
+            media_ssrc: 0x2057,
+        };
+        assert!(Nack::deserialize(&request.serialize()).is_err());
+        assert!(DecoderRefreshRequest::deserialize(&serialized).is_err());
+        assert!(Nack::deserialize(&serialized[..NACK_HEADER_LEN + 4]).is_err());
+    }
+
+    #[test]
+    fn test_nack_scattered_losses() {
+        // ---
+        let nack = Nack {
+            sender_ssrc: 0,
+            media_ssrc: 0x2075,
+            lost: vec![100, 101, 116, 117, 300],
+        };
+        let serialized = nack.serialize();
+
+        // 116 is the last a BLP can reach from 100; 117 and 300 start anew
+        assert_eq!(serialized.len(), NACK_HEADER_LEN + 12);
+        assert_eq!(&serialized[..4], [0x81, 205, 0, 5]);
+        assert_eq!(&serialized[12..16], [0x00, 100, 0x80, 0x01]);
+        assert_eq!(&serialized[16..20], [0x00, 117, 0x00, 0x00]);
+        assert_eq!(&serialized[20..24], [0x01, 0x2C, 0x00, 0x00]);
+        assert_eq!(Nack::deserialize(&serialized).unwrap(), nack);
+    }
+
+    #[test]
+    fn test_sender_report_matches_hand_built_packet() {
+        // ---
+        let report = SenderReport {
+            ssrc: 0x1234_5678,
+            ntp_timestamp: 0xE8F0_1A2B_8000_0000,
+            rtp_timestamp: 0x0001_F400,
+            packet_count: 250,
+            octet_count: 20_000,
+        };
+        #[rustfmt::skip]
+        let expected = [
+            0x80, 200, 0x00, 0x06,
+            0x12, 0x34, 0x56, 0x78,
+            0xE8, 0xF0, 0x1A, 0x2B,
+            0x80, 0x00, 0x00, 0x00,
+            0x00, 0x01, 0xF4, 0x00,
+            0x00, 0x00, 0x00, 0xFA,
+            0x00, 0x00, 0x4E, 0x20,
+        ];
+        assert_eq!(report.serialize(), expected);
+        assert_eq!(SenderReport::deserialize(&expected).unwrap(), report);
+        assert_eq!(report.compact_ntp(), 0x1A2B_8000);
+        assert!(is_rtcp(&expected));
+
+        // Report blocks are skipped; a short length field or datagram is not
+        let mut with_block = expected.to_vec();
+        with_block[0] |= 1;
+        with_block[3] = 12;
+        with_block.extend_from_slice(&[0; 24]);
+        assert_eq!(SenderReport::deserialize(&with_block).unwrap(), report);
+        assert!(SenderReport::deserialize(&with_block[..40]).is_err());
+        let mut short = expected;
+        short[3] = 5;
+        assert!(SenderReport::deserialize(&short).is_err());
+
+        // Nor is a receiver report or RTP a sender report
+        assert!(SenderReport::deserialize(&Keepalive { sender_ssrc: 1 }.serialize()).is_err());
+        let rtp = crate::RtpPacket::new(1, 320, 2, vec![0; 40])
+            .serialize()
+            .unwrap();
+        assert!(SenderReport::deserialize(&rtp).is_err());
+        assert!(!is_rtcp(&rtp));
+    }
+
+    #[test]
+    fn test_ntp_timestamp_conversion() {
+        // ---
+        // 2024-01-01T00:00:00.5Z
+        let time = UNIX_EPOCH + Duration::from_millis(1_704_067_200_500);
+        let ntp = ntp_timestamp(time);
+        assert_eq!(ntp >> 32, 1_704_067_200 + NTP_UNIX_OFFSET_SECS);
+        assert_eq!(ntp & 0xFFFF_FFFF, 0x8000_0000);
+
+        let back = ntp_to_system_time(ntp);
+        assert_eq!(back, time);
+        let now = SystemTime::now();
+        let error = now
+            .duration_since(ntp_to_system_time(ntp_timestamp(now)))
+            .unwrap();
+        assert!(error < Duration::from_nanos(2));
+    }
+
+    #[test]
+    fn test_receiver_report_roundtrip() {
+        // ---
+        let block = ReportBlock {
+            ssrc: 0x1234_5678,
+            fraction_lost: 26,
+            cumulative_lost: 513,
+            extended_highest_sequence: 0x0001_0005,
+            jitter: 160,
+            last_sr: 0x1A2B_8000,
+            delay_since_last_sr: dlsr_units(Duration::from_millis(1500)),
+        };
+        let report = ReceiverReport {
+            sender_ssrc: 0,
+            blocks: vec![block],
+        };
+        let serialized = report.serialize();
+        assert_eq!(serialized.len(), KEEPALIVE_LEN + REPORT_BLOCK_LEN);
+        assert_eq!(&serialized[..4], [0x81, 201, 0, 7]);
+        assert_eq!(&serialized[12..16], [26, 0x00, 0x02, 0x01]);
+        assert_eq!(&serialized[28..32], [0x00, 0x01, 0x80, 0x00]);
+        assert_eq!(ReceiverReport::deserialize(&serialized).unwrap(), report);
+        assert!((block.fraction_lost_ratio() - 0.1).abs() < 0.002);
+        assert!(is_rtcp(&serialized));
+
+        // Negative and out-of-range counts keep their sign in 24 bits
+        for (lost, carried) in [(-3, -3), (-0x100_0000, -0x80_0000), (i32::MAX, 0x7F_FFFF)] {
+            let report = ReceiverReport {
+                sender_ssrc: 9,
+                blocks: vec![ReportBlock {
+                    cumulative_lost: lost,
+                    ..block
+                }],
+            };
+            let parsed = ReceiverReport::deserialize(&report.serialize()).unwrap();
+            assert_eq!(parsed.blocks[0].cumulative_lost, carried);
+        }
+
+        // A keepalive is a report without blocks; a truncated block is
+        // refused
+        let keepalive = Keepalive { sender_ssrc: 7 }.serialize();
+        assert_eq!(ReceiverReport::deserialize(&keepalive).unwrap().blocks, []);
+        assert!(Keepalive::deserialize(&serialized).is_err());
+        assert!(ReceiverReport::deserialize(&serialized[..30]).is_err());
+        assert!(ReceiverReport::deserialize(&SenderReport::default().serialize()).is_err());
+    }
+
+    #[test]
+    fn test_sdes_roundtrip_with_several_chunks() {
+        // ---
+        let sdes = Sdes {
+            chunks: vec![
+                SdesChunk::with_cname(0x1111_1111, "alice@studio"),
+                SdesChunk {
+                    ssrc: 0x2222_2222,
+                    items: vec![
+                        SdesItem {
+                            kind: SDES_CNAME,
+                            text: "bob@10.0.0.2".to_string(),
+                        },
+                        SdesItem {
+                            kind: 2,
+                            text: "Bob".to_string(),
+                        },
+                    ],
+                },
+                SdesChunk {
+                    ssrc: 0x3333_3333,
+                    items: Vec::new(),
+                },
+            ],
+        };
+        let serialized = sdes.serialize();
+        assert_eq!(serialized.len() % 4, 0);
+        assert_eq!(&serialized[..2], [0x83, 202]);
+        let words = u16::from_be_bytes([serialized[2], serialized[3]]) as usize;
+        assert_eq!((words + 1) * 4, serialized.len());
+
+        // "alice@studio" is 12 bytes: 2 + 12 + the terminator pads to 16
+        assert_eq!(&serialized[4..8], [0x11; 4]);
+        assert_eq!(&serialized[8..10], [SDES_CNAME, 12]);
+        assert_eq!(&serialized[10..22], b"alice@studio");
+        assert_eq!(&serialized[22..24], [0, 0]);
+
+        let parsed = Sdes::deserialize(&serialized).unwrap();
+        assert_eq!(parsed, sdes);
+        assert_eq!(parsed.chunks[1].cname(), Some("bob@10.0.0.2"));
+        assert_eq!(parsed.chunks[2].cname(), None);
+
+        // Over-long text is cut at a character boundary
+        let long = "é".repeat(200);
+        let parsed = Sdes::deserialize(
+            &Sdes {
+                chunks: vec![SdesChunk::with_cname(1, long)],
+            }
+            .serialize(),
+        )
+        .unwrap();
+        assert_eq!(parsed.chunks[0].cname().unwrap().len(), 254);
+
+        // A chunk running past the length field is refused
+        let mut cut = serialized.clone();
+        cut[3] -= 1;
+        assert!(Sdes::deserialize(&cut).is_err());
+        assert!(Sdes::deserialize(&serialized[..8]).is_err());
+    }
+
+    #[test]
+    fn test_compound_packets_split_on_length() {
+        // ---
+        let report = SenderReport {
+            ssrc: 7,
+            ..Default::default()
+        }
+        .serialize();
+        let sdes = Sdes {
+            chunks: vec![SdesChunk::with_cname(7, "a@b")],
+        }
+        .serialize();
+        let compound = [report.clone(), sdes.clone()].concat();
+
+        let packets: Vec<&[u8]> = compound_packets(&compound).collect();
+        assert_eq!(packets, [&report[..], &sdes[..]]);
+        assert_eq!(compound_packets(&report).count(), 1);
+
+        // Trailing garbage, or a length past the end, ends the split
+        let mut garbled = compound.clone();
+        garbled.extend_from_slice(&[0x80, 202, 0, 9]);
+        assert_eq!(compound_packets(&garbled).count(), 2);
+        assert_eq!(compound_packets(&[0x80, 0x60, 0, 0]).count(), 0);
+    }
+
+    #[test]
+    fn test_default_cname_names_the_host() {
+        // ---
+        let cname = default_cname();
+        assert!(!cname.is_empty());
+        assert!(!cname.starts_with('@') && !cname.ends_with('@'));
+    }
+
+    #[test]
+    fn test_bye_roundtrip() {
+        // ---
+        let bye = Bye {
+            ssrcs: vec![0x2072_2072],
+            reason: Some("end of file".to_string()),
+        };
+        let serialized = bye.serialize();
+        assert_eq!(serialized.len(), 20);
+        assert_eq!(&serialized[..4], [0x81, 203, 0, 4]);
+        assert_eq!(&serialized[4..8], [0x20, 0x72, 0x20, 0x72]);
+        assert_eq!(serialized[8], 11);
+        assert_eq!(&serialized[9..20], b"end of file");
+        assert_eq!(Bye::deserialize(&serialized).unwrap(), bye);
+        assert!(is_rtcp(&serialized));
+
+        // Without a reason, and with several sources
+        let bye = Bye {
+            ssrcs: vec![1, 2, 3],
+            reason: None,
+        };
+        let serialized = bye.serialize();
+        assert_eq!(serialized.len(), 16);
+        assert_eq!(Bye::deserialize(&serialized).unwrap(), bye);
+
+        // A reason running past the length field is refused, as is
+        // another packet type
+        let mut cut = Bye {
+            ssrcs: vec![1],
+            reason: Some("abcdefgh".to_string()),
+        }
+        .serialize();
+        cut[3] -= 1;
+        assert!(Bye::deserialize(&cut).is_err());
+        assert!(Bye::deserialize(&Keepalive { sender_ssrc: 1 }.serialize()).is_err());
+    }
+
+    /// A sender's goodbye as a compound packet, built out of order
+    fn goodbye() -> CompoundPacket {
+        // ---
+        CompoundPacket::sender_report(&SenderReport {
+            ssrc: 7,
+            ..Default::default()
+        })
+        .with_bye(&Bye {
+            ssrcs: vec![7],
+            reason: None,
+        })
+        .with_nack(&Nack {
+            sender_ssrc: 7,
+            media_ssrc: 9,
+            lost: vec![1],
+        })
+        .with_sdes(&Sdes {
+            chunks: vec![SdesChunk::with_cname(7, "a@b")],
+        })
+    }
+
+    #[test]
+    fn test_compound_packet_orders_and_parses() {
+        // ---
+        let compound = goodbye();
+        assert_eq!(
+            compound.packet_types(),
+            [RTCP_PT_SR, RTCP_PT_SDES, RTCP_PT_RTPFB, RTCP_PT_BYE]
+        );
+        let serialized = compound.serialize();
+        assert_eq!(compound_packets(&serialized).count(), 4);
+        assert_eq!(CompoundPacket::parse(&serialized).unwrap(), compound);
+
+        // A receiver report alone, a keepalive, and a lone NACK or PLI
+        // (reduced-size) are all valid
+        let report = ReceiverReport {
+            sender_ssrc: 1,
+            blocks: Vec::new(),
+        };
+        let alone = CompoundPacket::receiver_report(&report).serialize();
+        assert_eq!(alone, report.serialize());
+        assert!(CompoundPacket::parse(&alone).is_ok());
+        assert!(CompoundPacket::parse(&Keepalive { sender_ssrc: 1 }.serialize()).is_ok());
+        let nack = Nack {
+            sender_ssrc: 1,
+            media_ssrc: 2,
+            lost: vec![3],
+        };
+        assert!(CompoundPacket::parse(&nack.serialize()).is_ok());
+        let pli = DecoderRefreshRequest {
+            sender_ssrc: 1,
+            media_ssrc: 2,
+        };
+        assert!(CompoundPacket::parse(&pli.serialize()).is_ok());
+    }
+
+    #[test]
+    fn test_compound_packet_padding() {
+        // ---
+        let compound = goodbye();
+        let plain = compound.serialize();
+        let padded = compound.serialize_padded(16);
+        assert_eq!(padded.len() % 16, 0);
+        assert!(padded.len() > plain.len());
+
+        // Only the last packet is marked, its length covers the padding,
+        // and the last octet counts it
+        let packets: Vec<&[u8]> = compound_packets(&padded).collect();
+        assert_eq!(packets.len(), 4);
+        assert!(packets[..3].iter().all(|packet| packet[0] & 0x20 == 0));
+        assert_ne!(packets[3][0] & 0x20, 0);
+        assert_eq!(*padded.last().unwrap() as usize, padded.len() - plain.len());
+        assert_eq!(CompoundPacket::parse(&padded).unwrap(), compound);
+
+        // Already a multiple: left alone
+        assert_eq!(compound.serialize_padded(4), plain);
+    }
+
+    #[test]
+    fn test_compound_packet_rejects_malformed() {
+        // ---
+        let serialized = goodbye().serialize();
+        let sr_len = SENDER_REPORT_LEN;
+        let sdes = Sdes {
+            chunks: vec![SdesChunk::with_cname(7, "a@b")],
+        }
+        .serialize();
+        let bye = Bye {
+            ssrcs: vec![7],
+            reason: None,
+        }
+        .serialize();
+        let report = &serialized[..sr_len];
+
+        // Framing: empty, not whole words, not RTCP, or running past the end
+        assert!(CompoundPacket::parse(&[]).is_err());
+        assert!(CompoundPacket::parse(&serialized[..serialized.len() - 2]).is_err());
+        assert!(CompoundPacket::parse(&[report, &[0x80, 0x60, 0, 0]].concat()).is_err());
+        let mut long = serialized.clone();
+        long[sr_len + 3] += 1;
+        assert!(CompoundPacket::parse(&long).is_err());
+
+        // Order: a report first, and nothing after the BYE
+        assert!(CompoundPacket::parse(&sdes).is_err());
+        assert!(CompoundPacket::parse(&[&sdes[..], report].concat()).is_err());
+        assert!(CompoundPacket::parse(&[report, &bye, &sdes].concat()).is_err());
+        assert!(CompoundPacket::parse(&[report, &bye, &bye].concat()).is_err());
+        assert!(CompoundPacket::parse(&[&bye[..], report].concat()).is_err());
+
+        // Padding: only on the last packet, with a count that fits
+        let mut early = [report, &sdes].concat();
+        early[0] |= 0x20;
+        assert!(CompoundPacket::parse(&early).is_err());
+        let padded = goodbye().serialize_padded(16);
+        let mut zero = padded.clone();
+        *zero.last_mut().unwrap() = 0;
+        assert!(CompoundPacket::parse(&zero).is_err());
+        let mut excessive = padded.clone();
+        *excessive.last_mut().unwrap() = 252;
+        assert!(CompoundPacket::parse(&excessive).is_err());
+    }
+
+    #[test]
+    fn test_rtcp_interval_follows_session_bandwidth() {
+        // ---
+        let min = Duration::from_millis(500);
+
+        // Plenty of bandwidth: the minimum holds
+        let wide = RtcpInterval::new(1_000_000, Duration::from_secs(5));
+        assert_eq!(wide.interval(2, 1, true), Duration::from_secs(5));
+
+        // 8 kb/s leaves 50 B/s of RTCP; two members at 100 bytes each
+        let narrow = RtcpInterval::new(8_000, min);
+        assert_eq!(narrow.interval(2, 1, true), Duration::from_secs(4));
+        assert_eq!(narrow.interval(2, 1, false), Duration::from_secs(4));
+
+        // 64 kb/s leaves 400 B/s: with one sender among 40, the sender
+        // gets a quarter of it to itself and the 39 receivers the rest
+        let conference = RtcpInterval::new(DEFAULT_SESSION_BANDWIDTH, min);
+        assert_eq!(conference.interval(40, 1, true), Duration::from_secs(1));
+        assert_eq!(conference.interval(40, 1, false), Duration::from_secs(13));
+
+        // The average follows the datagrams seen
+        let mut growing = RtcpInterval::new(8_000, min);
+        for _ in 0..200 {
+            growing.record_packet(172);
+        }
+        assert!((growing.avg_rtcp_size() - 200.0).abs() < 0.1);
+        assert!(growing.interval(2, 1, true) > Duration::from_millis(7_990));
+    }
+}
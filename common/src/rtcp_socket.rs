@@ -0,0 +1,435 @@
+//! RTCP transport shared by the sender and the receiver.
+//!
+//! [`RtcpSocket`] carries a participant's RTCP: on the RTP socket
+//! (multiplexing, RFC 5761), or on a socket of its own on the RTP port
+//! plus one (RFC 3550 §11). It parses every compound packet that arrives
+//! and hands each packet in it to the handler registered for its type,
+//! and it schedules the participant's reports within the RTCP share of the
+//! session bandwidth (see [`RtcpInterval`]).
+
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::rtcp::{CompoundPacket, RtcpInterval};
+
+/// Handler for one RTCP packet type: takes the packet, without padding,
+/// and the address it came from, into its context `C`
+pub type RtcpHandler<C> = fn(&mut C, &[u8], SocketAddr);
+
+/// Largest RTCP datagram read from a socket of its own
+const MAX_RTCP_DATAGRAM: usize = 1500;
+
+/// Members of a session with one sender and one receiver
+const POINT_TO_POINT_MEMBERS: usize = 2;
+
+/// RTCP transport of one participant.
+///
+/// Handlers are plain functions over a context `C` the owner passes to
+/// [`dispatch`](Self::dispatch), [`recv`](Self::recv), and
+/// [`try_recv`](Self::try_recv), so they can record what arrives into the
+/// owner's state without sharing it.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut rtcp = RtcpSocket::muxed().with_handler(RTCP_PT_BYE, |byes: &mut Vec<Bye>, packet, _| {
+///     byes.extend(Bye::deserialize(packet).ok());
+/// });
+/// rtcp.dispatch(&datagram, from, &mut byes);
+/// ```
+pub struct RtcpSocket<C> {
+    // ---
+    /// Socket of its own, or `None` to share the RTP socket
+    socket: Option<UdpSocket>,
+
+    /// Handlers by packet type, in registration order
+    handlers: Vec<(u8, RtcpHandler<C>)>,
+
+    /// Report interval computation, if this participant reports
+    schedule: Option<RtcpInterval>,
+
+    /// Whether this participant sends media, so its reports are sender
+    /// reports
+    we_send: bool,
+
+    /// When the next report is due, once scheduled
+    next_report: Option<Instant>,
+
+    /// Datagrams refused by [`CompoundPacket::parse`]
+    malformed: u64,
+}
+
+impl<C> RtcpSocket<C> {
+    // ---
+    /// Creates an RTCP transport sharing the RTP socket (RFC 5761).
+    ///
+    /// The owner reads the socket, passes the datagrams that are RTCP to
+    /// [`dispatch`](Self::dispatch), and sends through
+    /// [`send`](Self::send) with the RTP socket.
+    pub fn muxed() -> Self {
+        // ---
+        Self {
+            socket: None,
+            handlers: Vec::new(),
+            schedule: None,
+            we_send: false,
+            next_report: None,
+            malformed: 0,
+        }
+    }
+
+    /// Creates an RTCP transport on a socket of its own, bound to the
+    /// port after `rtp_addr`'s.
+    ///
+    /// # Arguments
+    ///
+    /// * `rtp_addr` - Local address of the RTP socket
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the RTP port is the last one or binding fails.
+    pub async fn bind(rtp_addr: SocketAddr) -> Result<Self> {
+        // ---
+        let addr = rtcp_addr(rtp_addr).context("no port after the RTP port for RTCP")?;
+        let socket = UdpSocket::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind RTCP socket to {}", addr))?;
+        Ok(Self::from_socket(socket))
+    }
+
+    /// Creates an RTCP transport on `socket`, e.g. one joined to a
+    /// multicast group.
+    pub fn from_socket(socket: UdpSocket) -> Self {
+        // ---
+        Self {
+            socket: Some(socket),
+            ..Self::muxed()
+        }
+    }
+
+    /// Registers `handler` for packets of type `packet_type`. Several
+    /// handlers for one type are called in registration order.
+    pub fn with_handler(mut self, packet_type: u8, handler: RtcpHandler<C>) -> Self {
+        // ---
+        self.handlers.push((packet_type, handler));
+        self
+    }
+
+    /// Returns whether RTCP shares the RTP socket.
+    pub fn is_muxed(&self) -> bool {
+        // ---
+        self.socket.is_none()
+    }
+
+    /// Returns the local address of the socket of its own, if any.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        // ---
+        self.socket
+            .as_ref()
+            .and_then(|socket| socket.local_addr().ok())
+    }
+
+    /// Returns where RTCP for a peer receiving RTP at `rtp_target` goes:
+    /// the same address when muxed, else the port after it.
+    pub fn target(&self, rtp_target: SocketAddr) -> SocketAddr {
+        // ---
+        match self.socket {
+            Some(_) => rtcp_addr(rtp_target).unwrap_or(rtp_target),
+            None => rtp_target,
+        }
+    }
+
+    /// Returns how many datagrams were refused as malformed.
+    pub fn malformed(&self) -> u64 {
+        // ---
+        self.malformed
+    }
+
+    /// Parses a compound RTCP datagram from `from` and passes each packet
+    /// in it to the handlers of its type; other types are skipped.
+    /// Malformed datagrams are logged and counted (see
+    /// [`malformed`](Self::malformed)).
+    ///
+    /// # Returns
+    ///
+    /// Whether the datagram was well-formed.
+    pub fn dispatch(&mut self, datagram: &[u8], from: SocketAddr, context: &mut C) -> bool {
+        // ---
+        let compound = match CompoundPacket::parse(datagram) {
+            Ok(compound) => compound,
+            Err(e) => {
+                self.malformed += 1;
+                debug!(
+                    "Ignoring {}-byte RTCP datagram from {}: {}",
+                    datagram.len(),
+                    from,
+                    e
+                );
+                return false;
+            }
+        };
+        if let Some(schedule) = &mut self.schedule {
+            schedule.record_packet(datagram.len());
+        }
+
+        for packet in compound.packets() {
+            let mut handled = false;
+            for (_, handler) in self.handlers.iter().filter(|(kind, _)| *kind == packet[1]) {
+                handler(context, packet, from);
+                handled = true;
+            }
+            if !handled {
+                debug!(
+                    "Ignoring {}-byte RTCP packet (PT {}) from {}",
+                    packet.len(),
+                    packet[1],
+                    from
+                );
+            }
+        }
+        true
+    }
+
+    /// Waits for a datagram on the socket of its own and dispatches it.
+    /// Never completes when muxed, so it can sit in a `select!` beside
+    /// the RTP socket either way.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if receiving fails.
+    pub async fn recv(&mut self, context: &mut C) -> Result<()> {
+        // ---
+        let Some(socket) = &self.socket else {
+            return std::future::pending().await;
+        };
+        let mut buf = [0u8; MAX_RTCP_DATAGRAM];
+        let (len, from) = socket
+            .recv_from(&mut buf)
+            .await
+            .context("failed to receive RTCP")?;
+        self.dispatch(&buf[..len], from, context);
+        Ok(())
+    }
+
+    /// Dispatches every datagram already waiting on the socket of its own,
+    /// without blocking; nothing when muxed.
+    ///
+    /// # Returns
+    ///
+    /// How many datagrams were read.
+    pub fn try_recv(&mut self, context: &mut C) -> usize {
+        // ---
+        let mut buf = [0u8; MAX_RTCP_DATAGRAM];
+        let mut received = 0;
+        while let Some(socket) = &self.socket {
+            match socket.try_recv_from(&mut buf) {
+                Ok((len, from)) => {
+                    received += 1;
+                    self.dispatch(&buf[..len], from, context);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    // ICMP errors from an unreachable peer surface here
+                    debug!("RTCP receive failed: {}", e);
+                    break;
+                }
+            }
+        }
+        received
+    }
+
+    /// Sends an RTCP datagram to the peer receiving RTP at `rtp_target`:
+    /// from the socket of its own to the port after it, or when muxed
+    /// from `rtp_socket`, connected or not.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if sending fails.
+    pub async fn send(
+        &mut self,
+        rtp_socket: &UdpSocket,
+        datagram: &[u8],
+        rtp_target: SocketAddr,
+    ) -> std::io::Result<()> {
+        // ---
+        let target = self.target(rtp_target);
+        match &self.socket {
+            Some(socket) => socket.send_to(datagram, target).await?,
+            None if rtp_socket.peer_addr().is_ok_and(|peer| peer == target) => {
+                rtp_socket.send(datagram).await?
+            }
+            None => rtp_socket.send_to(datagram, target).await?,
+        };
+        if let Some(schedule) = &mut self.schedule {
+            schedule.record_packet(datagram.len());
+        }
+        Ok(())
+    }
+
+    /// Schedules this participant's reports by `interval`, or stops them
+    /// with `None`, and starts the schedule over.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Report interval computation for the session
+    /// * `we_send` - Whether this participant sends media: a sender's
+    ///   first report is due at once, tying its RTP timestamps to
+    ///   wallclock time from the start, and a receiver's one interval in,
+    ///   once there is reception to report
+    pub fn schedule_reports(&mut self, interval: Option<RtcpInterval>, we_send: bool) {
+        // ---
+        self.schedule = interval;
+        self.we_send = we_send;
+        self.next_report = None;
+    }
+
+    /// Returns whether a report is due as of `now`, and if so, or on the
+    /// first call, schedules the next one interval after `now`. Always
+    /// `false` without a schedule.
+    pub fn report_due(&mut self, now: Instant) -> bool {
+        // ---
+        let Some(schedule) = &self.schedule else {
+            return false;
+        };
+        let due = match self.next_report {
+            Some(next) => now >= next,
+            None => self.we_send,
+        };
+        if due || self.next_report.is_none() {
+            let senders = 1;
+            let interval = schedule.interval(POINT_TO_POINT_MEMBERS, senders, self.we_send);
+            self.next_report = Some(now + interval);
+        }
+        due
+    }
+}
+
+/// Returns the address on the port after `rtp_addr`'s, where RTCP goes by
+/// convention when not muxed, or `None` if there is none.
+fn rtcp_addr(rtp_addr: SocketAddr) -> Option<SocketAddr> {
+    // ---
+    let port = rtp_addr.port().checked_add(1)?;
+    Some(SocketAddr::new(rtp_addr.ip(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::rtcp::{Bye, SenderReport, RTCP_PT_BYE, RTCP_PT_SR};
+    use std::time::Duration;
+
+    /// What the test handlers took in
+    #[derive(Default)]
+    struct Seen {
+        reports: Vec<SenderReport>,
+        byes: Vec<Bye>,
+    }
+
+    fn on_report(seen: &mut Seen, packet: &[u8], _: SocketAddr) {
+        // ---
+        seen.reports.extend(SenderReport::deserialize(packet).ok());
+    }
+
+    fn on_bye(seen: &mut Seen, packet: &[u8], _: SocketAddr) {
+        // ---
+        seen.byes.extend(Bye::deserialize(packet).ok());
+    }
+
+    fn goodbye() -> Vec<u8> {
+        // ---
+        CompoundPacket::sender_report(&SenderReport {
+            ssrc: 7,
+            ..Default::default()
+        })
+        .with_bye(&Bye {
+            ssrcs: vec![7],
+            reason: None,
+        })
+        .serialize_padded(16)
+    }
+
+    #[test]
+    fn test_dispatch_by_packet_type() {
+        // ---
+        let mut rtcp = RtcpSocket::muxed()
+            .with_handler(RTCP_PT_SR, on_report)
+            .with_handler(RTCP_PT_BYE, on_bye);
+        let from = SocketAddr::from(([127, 0, 0, 1], 5004));
+        let mut seen = Seen::default();
+
+        assert!(rtcp.dispatch(&goodbye(), from, &mut seen));
+        assert_eq!(seen.reports.len(), 1);
+        assert_eq!(seen.byes[0].ssrcs, [7]);
+
+        // A BYE first is no compound packet: nothing is handled
+        let bye = Bye {
+            ssrcs: vec![7],
+            reason: None,
+        }
+        .serialize();
+        assert!(!rtcp.dispatch(&bye, from, &mut seen));
+        assert_eq!((seen.reports.len(), seen.byes.len()), (1, 1));
+        assert_eq!(rtcp.malformed(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_separate_socket_on_next_port() {
+        // ---
+        let rtp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let rtp_addr = rtp.local_addr().unwrap();
+        let Ok(receiving) = RtcpSocket::bind(rtp_addr).await else {
+            // The next port is taken: nothing to test here
+            return;
+        };
+        let receiving_addr = receiving.local_addr().unwrap();
+        assert_eq!(receiving_addr.port(), rtp_addr.port() + 1);
+        let mut receiving = receiving.with_handler(RTCP_PT_BYE, on_bye);
+
+        // Sent muxed from an RTP socket straight to the RTCP socket
+        let mut sending: RtcpSocket<Seen> = RtcpSocket::muxed();
+        let sender_rtp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        sending
+            .send(&sender_rtp, &goodbye(), receiving_addr)
+            .await
+            .unwrap();
+
+        let mut seen = Seen::default();
+        tokio::time::timeout(Duration::from_secs(2), receiving.recv(&mut seen))
+            .await
+            .expect("RTCP datagram")
+            .unwrap();
+        assert_eq!(seen.byes.len(), 1);
+        assert_eq!(receiving.try_recv(&mut seen), 0);
+        assert_eq!(receiving.target(rtp_addr), receiving_addr);
+    }
+
+    #[test]
+    fn test_report_schedule() {
+        // ---
+        let interval = RtcpInterval::new(1_000_000, Duration::from_secs(1));
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+
+        // A sender reports at once, then every interval
+        let mut sender: RtcpSocket<Seen> = RtcpSocket::muxed();
+        assert!(!sender.report_due(start));
+        sender.schedule_reports(Some(interval.clone()), true);
+        assert!(sender.report_due(start));
+        assert!(!sender.report_due(start + second / 2));
+        assert!(sender.report_due(start + second));
+
+        // A receiver waits an interval first
+        let mut receiver: RtcpSocket<Seen> = RtcpSocket::muxed();
+        receiver.schedule_reports(Some(interval), false);
+        assert!(!receiver.report_due(start));
+        assert!(receiver.report_due(start + second));
+        assert!(!receiver.report_due(start + second * 3 / 2));
+
+        receiver.schedule_reports(None, false);
+        assert!(!receiver.report_due(start + second * 10));
+    }
+}
@@ -1,16 +1,300 @@
 //! RTP packet structure and serialization.
 //!
-//! Implements basic RTP packet format according to RFC 3550.
-//! This implementation focuses on the minimum required fields for
-//! audio streaming without optional extensions.
+//! Implements the RTP packet format according to RFC 3550: the fixed
+//! header, CSRC list, header extension (§5.3.1), and padding (§5.1).
+//! This sender emits only the fixed header and padding, but packets from
+//! other RTP stacks may carry all of them.
 
 use anyhow::Result;
+use bytes::Bytes;
+use std::fmt;
+use std::ops::Range;
 
 /// RTP packet version 2 (as per RFC 3550)
 const RTP_VERSION: u8 = 2;
 
 /// Payload type for dynamic Opus codec
-const PAYLOAD_TYPE_OPUS: u8 = 96;
+pub const PAYLOAD_TYPE_OPUS: u8 = 96;
+
+/// Highest RTP payload type (the field is seven bits)
+pub const MAX_PAYLOAD_TYPE: u8 = 127;
+
+/// Size of the fixed RTP header in bytes
+pub const RTP_HEADER_LEN: usize = 12;
+
+/// Most padding octets one packet can carry (the count is a single octet)
+pub const MAX_PADDING: usize = 255;
+
+/// Size of the payload checksum trailer added in verification mode
+pub const PAYLOAD_CRC_LEN: usize = 4;
+
+/// Most CSRC identifiers one packet can carry (the count is four bits)
+pub const MAX_CSRC: usize = 15;
+
+/// Padding (P) bit in the first header byte
+const PADDING_BIT: u8 = 0x20;
+
+/// Extension (X) bit in the first header byte
+const EXTENSION_BIT: u8 = 0x10;
+
+/// CSRC count (CC) in the first header byte
+const CSRC_COUNT_MASK: u8 = 0x0F;
+
+/// Size of the header extension's profile and length fields
+const EXTENSION_HEADER_LEN: usize = 4;
+
+/// Header extension profile of RFC 8285 one-byte extension elements
+pub const ONE_BYTE_EXTENSION_PROFILE: u16 = 0xBEDE;
+
+/// Most data bytes one one-byte extension element can carry
+pub const MAX_ONE_BYTE_ELEMENT_LEN: usize = 16;
+
+/// Extension element ID that ends a one-byte extension block
+const ONE_BYTE_STOP_ID: u8 = 15;
+
+/// Marker (M) bit in the second header byte
+const MARKER_BIT: u8 = 0x80;
+
+/// RTP header extension (RFC 3550 §5.3.1).
+///
+/// Carried as received and sent as given; the contents are defined by the
+/// profile, e.g. `0xBEDE` for RFC 8285 one-byte extension elements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RtpHeaderExtension {
+    // ---
+    /// Profile-defined identifier of the extension format
+    pub profile: u16,
+
+    /// Extension data, a whole number of 32-bit words
+    #[cfg_attr(feature = "serde", serde(with = "crate::trace::base64_bytes"))]
+    pub data: Vec<u8>,
+}
+
+/// One element of a one-byte header extension (RFC 8285 §4.2): an ID
+/// negotiated in SDP (`a=extmap`) and its data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionElement {
+    // ---
+    /// Element ID, 1 to 14
+    pub id: u8,
+
+    /// Element data, 1 to [`MAX_ONE_BYTE_ELEMENT_LEN`] bytes
+    pub data: Vec<u8>,
+}
+
+impl RtpHeaderExtension {
+    // ---
+    /// Builds a one-byte extension block ([`ONE_BYTE_EXTENSION_PROFILE`])
+    /// holding `elements` in order, padded with zeros to a whole word.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if an element ID is outside 1 to 14, or its data is
+    /// empty or longer than [`MAX_ONE_BYTE_ELEMENT_LEN`].
+    pub fn one_byte(elements: &[ExtensionElement]) -> Result<Self> {
+        // ---
+        let mut data = Vec::new();
+        for element in elements {
+            if !(1..ONE_BYTE_STOP_ID).contains(&element.id) {
+                anyhow::bail!(
+                    "one-byte extension ID {} is out of range (1 to 14)",
+                    element.id
+                );
+            }
+            if !(1..=MAX_ONE_BYTE_ELEMENT_LEN).contains(&element.data.len()) {
+                anyhow::bail!(
+                    "one-byte extension element of {} bytes (1 to {})",
+                    element.data.len(),
+                    MAX_ONE_BYTE_ELEMENT_LEN
+                );
+            }
+            data.push(element.id << 4 | (element.data.len() - 1) as u8);
+            data.extend_from_slice(&element.data);
+        }
+        data.resize(data.len().next_multiple_of(4), 0);
+        Ok(Self {
+            profile: ONE_BYTE_EXTENSION_PROFILE,
+            data,
+        })
+    }
+
+    /// Returns the elements of a one-byte extension block, in order.
+    /// Padding bytes are skipped; ID 15 ends the block.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the profile is not [`ONE_BYTE_EXTENSION_PROFILE`]
+    /// or an element runs past the end of the block.
+    pub fn elements(&self) -> Result<Vec<ExtensionElement>> {
+        // ---
+        if self.profile != ONE_BYTE_EXTENSION_PROFILE {
+            anyhow::bail!(
+                "header extension profile 0x{:04X} is not one-byte elements",
+                self.profile
+            );
+        }
+        let mut elements = Vec::new();
+        walk_one_byte_elements(&self.data, |id, data| {
+            elements.push(ExtensionElement {
+                id,
+                data: data.to_vec(),
+            })
+        })?;
+        Ok(elements)
+    }
+}
+
+/// Calls `element` with the ID and data of each element of a one-byte
+/// extension block, in order. Padding bytes are skipped; ID 15 ends the
+/// block.
+fn walk_one_byte_elements(
+    mut rest: &[u8],
+    mut element: impl FnMut(u8, &[u8]),
+) -> Result<(), RtpParseError> {
+    // ---
+    while let Some((&header, tail)) = rest.split_first() {
+        let id = header >> 4;
+        if header == 0 {
+            rest = tail;
+            continue;
+        }
+        if id == ONE_BYTE_STOP_ID {
+            break;
+        }
+        let len = (header & 0x0F) as usize + 1;
+        if tail.len() < len {
+            return Err(RtpParseError::BadExtension {
+                len,
+                available: tail.len(),
+            });
+        }
+        let (data, tail) = tail.split_at(len);
+        element(id, data);
+        rest = tail;
+    }
+    Ok(())
+}
+
+/// How strictly [`RtpPacket::try_deserialize`] checks a packet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RtpParseMode {
+    // ---
+    /// Anything well-formed under RFC 3550 with version 2
+    #[default]
+    Lenient,
+
+    /// Also refuses what this receiver would not understand: a header
+    /// extension must hold well-formed RFC 8285 one-byte elements
+    Strict,
+}
+
+/// Why a datagram is not an RTP packet, from [`RtpPacket::try_deserialize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtpParseError {
+    // ---
+    /// Shorter than the fixed header
+    TooShort { len: usize },
+
+    /// Version other than 2: not RTP at all (e.g. STUN or DTLS sharing
+    /// the port)
+    BadVersion { version: u8 },
+
+    /// The CSRC list runs past the end of the packet
+    TruncatedCsrc { count: usize, available: usize },
+
+    /// The header extension, or one of its elements, runs past its end
+    BadExtension { len: usize, available: usize },
+
+    /// The padding count is zero or longer than the payload
+    BadPadding { count: u8, available: usize },
+
+    /// Valid RTP using something strict parsing refuses
+    UnsupportedFeature { feature: &'static str },
+}
+
+/// Kind of [`RtpParseError`], without its details: a label for counting
+/// dropped packets by reason.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RtpParseErrorKind {
+    // ---
+    TooShort,
+    BadVersion,
+    TruncatedCsrc,
+    BadExtension,
+    BadPadding,
+    UnsupportedFeature,
+}
+
+impl RtpParseErrorKind {
+    // ---
+    /// Every kind, in declaration order
+    pub const ALL: [Self; 6] = [
+        Self::TooShort,
+        Self::BadVersion,
+        Self::TruncatedCsrc,
+        Self::BadExtension,
+        Self::BadPadding,
+        Self::UnsupportedFeature,
+    ];
+
+    /// Returns the snake-case label for metrics and logs.
+    pub fn label(self) -> &'static str {
+        // ---
+        match self {
+            Self::TooShort => "too_short",
+            Self::BadVersion => "bad_version",
+            Self::TruncatedCsrc => "truncated_csrc",
+            Self::BadExtension => "bad_extension",
+            Self::BadPadding => "bad_padding",
+            Self::UnsupportedFeature => "unsupported_feature",
+        }
+    }
+}
+
+impl RtpParseError {
+    // ---
+    /// Returns the kind of error, without its details.
+    pub fn kind(&self) -> RtpParseErrorKind {
+        // ---
+        match self {
+            Self::TooShort { .. } => RtpParseErrorKind::TooShort,
+            Self::BadVersion { .. } => RtpParseErrorKind::BadVersion,
+            Self::TruncatedCsrc { .. } => RtpParseErrorKind::TruncatedCsrc,
+            Self::BadExtension { .. } => RtpParseErrorKind::BadExtension,
+            Self::BadPadding { .. } => RtpParseErrorKind::BadPadding,
+            Self::UnsupportedFeature { .. } => RtpParseErrorKind::UnsupportedFeature,
+        }
+    }
+}
+
+impl fmt::Display for RtpParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        match self {
+            Self::TooShort { len } => write!(f, "packet too small: {} bytes", len),
+            Self::BadVersion { version } => write!(f, "invalid RTP version: {}", version),
+            Self::TruncatedCsrc { count, available } => write!(
+                f,
+                "{} CSRCs do not fit in {} bytes after the header",
+                count, available
+            ),
+            Self::BadExtension { len, available } => write!(
+                f,
+                "header extension of {} bytes exceeds the {} bytes left",
+                len, available
+            ),
+            Self::BadPadding { count, available } => write!(
+                f,
+                "invalid RTP padding count {} in {}-byte payload",
+                count, available
+            ),
+            Self::UnsupportedFeature { feature } => write!(f, "unsupported: {}", feature),
+        }
+    }
+}
+
+impl std::error::Error for RtpParseError {}
 
 /// RTP packet structure for audio transmission.
 ///
@@ -21,13 +305,21 @@ const PAYLOAD_TYPE_OPUS: u8 = 96;
 /// # Protocol Details
 ///
 /// - Version: Always 2 (RFC 3550)
-/// - Payload Type: 96 (dynamic assignment for Opus)
+/// - Payload Type: 96 (dynamic assignment for Opus) unless set otherwise
 /// - Sequence: Increments by 1 for each packet
 /// - Timestamp: Increments by 320 samples for 20ms @ 16kHz
 /// - SSRC: Synchronization source identifier (random per session)
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RtpPacket {
     // ---
+    /// RTP payload type (7 bits)
+    pub payload_type: u8,
+
+    /// Marker bit; set on a packet the receiver should treat as a fresh
+    /// start (e.g. after an encoder reset)
+    pub marker: bool,
+
     /// Packet sequence number (wraps at 65535)
     pub sequence: u16,
 
@@ -37,34 +329,209 @@ pub struct RtpPacket {
     /// Synchronization source identifier
     pub ssrc: u32,
 
-    /// Encoded audio payload
-    pub payload: Vec<u8>,
+    /// Contributing source identifiers, as set by a mixer (at most
+    /// [`MAX_CSRC`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub csrc: Vec<u32>,
+
+    /// Header extension, if the X bit is set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extension: Option<RtpHeaderExtension>,
+
+    /// Encoded audio payload; a received packet's is a view of the
+    /// datagram it arrived in (see [`deserialize_bytes`](Self::deserialize_bytes)),
+    /// so cloning the packet does not copy it
+    #[cfg_attr(feature = "serde", serde(with = "crate::trace::base64_bytes"))]
+    pub payload: Bytes,
+
+    /// Padding octets following the payload on the wire, including the
+    /// trailing count octet (0 = no padding, P bit clear)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub padding: u8,
 }
 
 impl RtpPacket {
     // ---
     /// Creates a new RTP packet with the given parameters.
     ///
+    /// The payload type defaults to [`PAYLOAD_TYPE_OPUS`].
+    ///
     /// # Arguments
     ///
     /// * `sequence` - Packet sequence number
     /// * `timestamp` - RTP timestamp (in sample units)
     /// * `ssrc` - Synchronization source identifier
-    /// * `payload` - Encoded audio data
-    pub fn new(sequence: u16, timestamp: u32, ssrc: u32, payload: Vec<u8>) -> Self {
+    /// * `payload` - Encoded audio data, e.g. a `Vec<u8>`
+    pub fn new(sequence: u16, timestamp: u32, ssrc: u32, payload: impl Into<Bytes>) -> Self {
         // ---
         Self {
+            payload_type: PAYLOAD_TYPE_OPUS,
+            marker: false,
             sequence,
             timestamp,
             ssrc,
-            payload,
+            csrc: Vec::new(),
+            extension: None,
+            payload: payload.into(),
+            padding: 0,
+        }
+    }
+
+    /// Sets the payload type, e.g. the one negotiated for Opus in SDP.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `payload_type` is above [`MAX_PAYLOAD_TYPE`].
+    pub fn with_payload_type(mut self, payload_type: u8) -> Result<Self> {
+        // ---
+        if payload_type > MAX_PAYLOAD_TYPE {
+            anyhow::bail!(
+                "payload type {} is out of range (0 to {})",
+                payload_type,
+                MAX_PAYLOAD_TYPE
+            );
+        }
+        self.payload_type = payload_type;
+        Ok(self)
+    }
+
+    /// Returns the data of one-byte extension element `id`, if the packet
+    /// carries a well-formed one-byte extension block with it.
+    pub fn extension_element(&self, id: u8) -> Option<Vec<u8>> {
+        // ---
+        self.extension
+            .as_ref()?
+            .elements()
+            .ok()?
+            .into_iter()
+            .find(|element| element.id == id)
+            .map(|element| element.data)
+    }
+
+    /// Sets one-byte extension element `id` to `data`, replacing any
+    /// element with the same ID and keeping the others.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the packet carries a header extension that is not
+    /// a well-formed one-byte block, or the element is invalid (see
+    /// [`RtpHeaderExtension::one_byte`]).
+    pub fn set_extension_element(&mut self, id: u8, data: &[u8]) -> Result<()> {
+        // ---
+        let mut elements = match &self.extension {
+            Some(extension) => extension.elements()?,
+            None => Vec::new(),
+        };
+        let element = ExtensionElement {
+            id,
+            data: data.to_vec(),
+        };
+        match elements.iter_mut().find(|element| element.id == id) {
+            Some(existing) => *existing = element,
+            None => elements.push(element),
+        }
+        self.extension = Some(RtpHeaderExtension::one_byte(&elements)?);
+        Ok(())
+    }
+
+    /// Returns the serialized size of the header in bytes: the fixed
+    /// header, the CSRC list, and the header extension.
+    pub fn header_len(&self) -> usize {
+        // ---
+        let extension = self
+            .extension
+            .as_ref()
+            .map_or(0, |extension| EXTENSION_HEADER_LEN + extension.data.len());
+        RTP_HEADER_LEN + 4 * self.csrc.len() + extension
+    }
+
+    /// Returns the serialized size of this packet in bytes.
+    pub fn wire_len(&self) -> usize {
+        // ---
+        self.header_len() + self.payload.len() + self.padding as usize
+    }
+
+    /// Sets padding so the serialized packet is exactly `packet_len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the header and payload alone exceed `packet_len`, or
+    /// if reaching it would need more than [`MAX_PADDING`] padding octets.
+    pub fn pad_to(&mut self, packet_len: usize) -> Result<()> {
+        // ---
+        let unpadded = self.header_len() + self.payload.len();
+        let Some(padding) = packet_len.checked_sub(unpadded) else {
+            anyhow::bail!(
+                "packet of {} bytes exceeds padded size {}",
+                unpadded,
+                packet_len
+            );
+        };
+        if padding > MAX_PADDING {
+            anyhow::bail!(
+                "padding {} bytes to {} needs {} padding octets (max {})",
+                unpadded,
+                packet_len,
+                padding,
+                MAX_PADDING
+            );
         }
+
+        self.padding = padding as u8;
+        Ok(())
+    }
+
+    /// Appends a CRC32 of the payload as a 4-byte big-endian trailer.
+    ///
+    /// Used in payload verification mode, which both ends must enable: a
+    /// peer that does not expect the trailer would hand it to the decoder.
+    /// Call before [`pad_to`](Self::pad_to), since the trailer is part of
+    /// the payload on the wire.
+    pub fn append_payload_crc(&mut self) {
+        // ---
+        let crc = payload_crc32(&self.payload);
+        let mut payload = Vec::from(std::mem::take(&mut self.payload));
+        payload.extend_from_slice(&crc.to_be_bytes());
+        self.payload = payload.into();
+    }
+
+    /// Checks and removes the trailer added by
+    /// [`append_payload_crc`](Self::append_payload_crc).
+    ///
+    /// On success the payload is the original codec payload again. On
+    /// failure it is left as received.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the payload is shorter than the trailer or the
+    /// checksum does not match.
+    pub fn strip_payload_crc(&mut self) -> Result<()> {
+        // ---
+        let Some(split) = self.payload.len().checked_sub(PAYLOAD_CRC_LEN) else {
+            anyhow::bail!("payload of {} bytes has no CRC trailer", self.payload.len());
+        };
+
+        let (body, trailer) = self.payload.split_at(split);
+        let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let actual = payload_crc32(body);
+        if actual != expected {
+            anyhow::bail!(
+                "payload CRC mismatch: trailer 0x{:08X}, computed 0x{:08X}",
+                expected,
+                actual
+            );
+        }
+
+        self.payload.truncate(split);
+        Ok(())
     }
 
     /// Serializes the RTP packet into wire format.
     ///
     /// Returns a byte vector ready for UDP transmission. The format follows
-    /// RFC 3550 fixed header (12 bytes) followed by the payload.
+    /// RFC 3550: the fixed header (12 bytes), the CSRC list, the header
+    /// extension if any, then the payload and, if `padding` is non-zero,
+    /// zero octets ending in the padding count.
     ///
     /// # Wire Format
     ///
@@ -78,25 +545,79 @@ impl RtpPacket {
     /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
     /// |           synchronization source (SSRC) identifier            |
     /// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+    /// |            contributing source (CSRC) identifiers             |
+    /// |                             ....                              |
+    /// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+    /// |      defined by profile       |           length              |
+    /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    /// |                        header extension                       |
+    /// |                             ....                              |
+    /// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
     /// |                           payload...                          |
     /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns error if serialization fails (currently infallible but returns
-    /// Result for future extensibility).
+    /// Returns error if the payload type is above [`MAX_PAYLOAD_TYPE`],
+    /// there are more than [`MAX_CSRC`] CSRCs, or the header extension is
+    /// not a whole number of 32-bit words or longer than its 16-bit length
+    /// field can describe.
     pub fn serialize(&self) -> Result<Vec<u8>> {
         // ---
-        let mut buf = Vec::with_capacity(12 + self.payload.len());
+        let mut buf = Vec::with_capacity(self.wire_len());
+        self.serialize_into(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Serializes the packet into `buf`, replacing its contents, as
+    /// [`serialize`](Self::serialize) does into a new `Vec`.
+    ///
+    /// Reusing one buffer for every packet sent avoids allocating per
+    /// packet once it has grown to the largest packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`serialize`](Self::serialize), leaving
+    /// `buf` empty.
+    pub fn serialize_into(&self, buf: &mut Vec<u8>) -> Result<()> {
+        // ---
+        buf.clear();
+        if self.payload_type > MAX_PAYLOAD_TYPE {
+            anyhow::bail!("payload type {} does not fit in 7 bits", self.payload_type);
+        }
+        if self.csrc.len() > MAX_CSRC {
+            anyhow::bail!("{} CSRCs (at most {})", self.csrc.len(), MAX_CSRC);
+        }
+        let extension_words = match &self.extension {
+            Some(extension) => {
+                let words = extension.data.len() / 4;
+                if extension.data.len() % 4 != 0 || words > u16::MAX as usize {
+                    anyhow::bail!(
+                        "header extension of {} bytes is not a whole number of 32-bit words up to {}",
+                        extension.data.len(),
+                        u16::MAX
+                    );
+                }
+                words as u16
+            }
+            None => 0,
+        };
+        buf.reserve(self.wire_len());
 
         // Byte 0: V(2) | P(1) | X(1) | CC(4)
-        // V=2, P=0 (no padding), X=0 (no extension), CC=0 (no CSRC)
-        buf.push(RTP_VERSION << 6);
+        // V=2, P set only when padded, X only with an extension
+        let padding_bit = if self.padding > 0 { PADDING_BIT } else { 0 };
+        let extension_bit = if self.extension.is_some() {
+            EXTENSION_BIT
+        } else {
+            0
+        };
+        buf.push(RTP_VERSION << 6 | padding_bit | extension_bit | self.csrc.len() as u8);
 
         // Byte 1: M(1) | PT(7)
-        // M=0 (not marker), PT=96 (dynamic Opus)
-        buf.push(PAYLOAD_TYPE_OPUS);
+        let marker_bit = if self.marker { MARKER_BIT } else { 0 };
+        buf.push(marker_bit | self.payload_type);
 
         // Bytes 2-3: Sequence number (big-endian)
         buf.extend_from_slice(&self.sequence.to_be_bytes());
@@ -107,17 +628,34 @@ impl RtpPacket {
         // Bytes 8-11: SSRC (big-endian)
         buf.extend_from_slice(&self.ssrc.to_be_bytes());
 
+        // CSRC list, then the extension with its length in 32-bit words
+        for csrc in &self.csrc {
+            buf.extend_from_slice(&csrc.to_be_bytes());
+        }
+        if let Some(extension) = &self.extension {
+            buf.extend_from_slice(&extension.profile.to_be_bytes());
+            buf.extend_from_slice(&extension_words.to_be_bytes());
+            buf.extend_from_slice(&extension.data);
+        }
+
         // Payload
         buf.extend_from_slice(&self.payload);
 
-        Ok(buf)
+        // Padding: zeros, with the last octet holding the padding count
+        if self.padding > 0 {
+            buf.resize(buf.len() + self.padding as usize - 1, 0);
+            buf.push(self.padding);
+        }
+
+        Ok(())
     }
 
     /// Deserializes an RTP packet from wire format.
     ///
-    /// Parses the fixed 12-byte header and extracts the payload.
-    /// Validates version field but does not validate payload type
-    /// to allow for future codec flexibility.
+    /// Parses the fixed 12-byte header, the CSRC list, and any header
+    /// extension, and extracts the payload, stripping any RTP padding.
+    /// Validates version field but does not validate payload type to allow
+    /// for future codec flexibility.
     ///
     /// # Arguments
     ///
@@ -128,35 +666,172 @@ impl RtpPacket {
     /// Returns error if:
     /// - Packet is smaller than minimum header size (12 bytes)
     /// - RTP version is not 2
+    /// - The CSRC list or header extension runs past the end of the packet
+    /// - The padding count is zero or longer than the payload
     pub fn deserialize(data: &[u8]) -> Result<Self> {
         // ---
-        if data.len() < 12 {
-            anyhow::bail!("packet too small: {} bytes", data.len());
+        Ok(Self::try_deserialize(data, RtpParseMode::Lenient)?)
+    }
+
+    /// Deserializes an RTP packet as [`deserialize`](Self::deserialize)
+    /// does, but without copying the payload: it stays a view of `data`,
+    /// which is kept alive by the packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`deserialize`](Self::deserialize).
+    pub fn deserialize_bytes(data: Bytes) -> Result<Self> {
+        // ---
+        Ok(Self::try_deserialize_bytes(data, RtpParseMode::Lenient)?)
+    }
+
+    /// Deserializes an RTP packet as [`deserialize`](Self::deserialize)
+    /// does, checked as `mode` asks, with an error saying why a datagram
+    /// was refused.
+    ///
+    /// No input makes it panic.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`RtpParseError`] for the first problem found.
+    pub fn try_deserialize(data: &[u8], mode: RtpParseMode) -> Result<Self, RtpParseError> {
+        // ---
+        Self::parse(data, mode, |payload| Bytes::copy_from_slice(&data[payload]))
+    }
+
+    /// Deserializes an RTP packet as [`try_deserialize`](Self::try_deserialize)
+    /// does, leaving the payload a view of `data` as
+    /// [`deserialize_bytes`](Self::deserialize_bytes) does.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`try_deserialize`](Self::try_deserialize).
+    pub fn try_deserialize_bytes(data: Bytes, mode: RtpParseMode) -> Result<Self, RtpParseError> {
+        // ---
+        Self::parse(&data, mode, |payload| data.slice(payload))
+    }
+
+    /// Parses the header of `data`, taking the payload bytes in the given
+    /// range from `payload`.
+    fn parse(
+        data: &[u8],
+        mode: RtpParseMode,
+        payload: impl FnOnce(Range<usize>) -> Bytes,
+    ) -> Result<Self, RtpParseError> {
+        // ---
+        if data.len() < RTP_HEADER_LEN {
+            return Err(RtpParseError::TooShort { len: data.len() });
         }
 
         // Validate version
         let version = (data[0] >> 6) & 0x03;
         if version != RTP_VERSION {
-            anyhow::bail!("invalid RTP version: {}", version);
+            return Err(RtpParseError::BadVersion { version });
         }
 
         // Extract fields (big-endian)
+        let marker = data[1] & MARKER_BIT != 0;
+        let payload_type = data[1] & 0x7F;
         let sequence = u16::from_be_bytes([data[2], data[3]]);
         let timestamp = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
         let ssrc = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
 
-        // Payload is everything after header
-        let payload = data[12..].to_vec();
+        // CSRC list
+        let csrc_count = (data[0] & CSRC_COUNT_MASK) as usize;
+        let mut body = &data[RTP_HEADER_LEN..];
+        if body.len() < 4 * csrc_count {
+            return Err(RtpParseError::TruncatedCsrc {
+                count: csrc_count,
+                available: body.len(),
+            });
+        }
+        let (list, rest) = body.split_at(4 * csrc_count);
+        let csrc = list
+            .chunks_exact(4)
+            .map(|id| u32::from_be_bytes([id[0], id[1], id[2], id[3]]))
+            .collect();
+        body = rest;
+
+        // Header extension: profile, length in 32-bit words, data
+        let extension = if data[0] & EXTENSION_BIT != 0 {
+            if body.len() < EXTENSION_HEADER_LEN {
+                return Err(RtpParseError::BadExtension {
+                    len: EXTENSION_HEADER_LEN,
+                    available: body.len(),
+                });
+            }
+            let profile = u16::from_be_bytes([body[0], body[1]]);
+            let len = 4 * u16::from_be_bytes([body[2], body[3]]) as usize;
+            let rest = &body[EXTENSION_HEADER_LEN..];
+            if rest.len() < len {
+                return Err(RtpParseError::BadExtension {
+                    len,
+                    available: rest.len(),
+                });
+            }
+            let (extension, rest) = rest.split_at(len);
+            body = rest;
+            if mode == RtpParseMode::Strict {
+                if profile != ONE_BYTE_EXTENSION_PROFILE {
+                    return Err(RtpParseError::UnsupportedFeature {
+                        feature: "header extension profile other than one-byte elements",
+                    });
+                }
+                walk_one_byte_elements(extension, |_, _| {})?;
+            }
+            Some(RtpHeaderExtension {
+                profile,
+                data: extension.to_vec(),
+            })
+        } else {
+            None
+        };
+
+        // Payload is everything after the header, minus any padding
+        let padding = if data[0] & PADDING_BIT != 0 {
+            match body.last() {
+                Some(&count) if count > 0 && count as usize <= body.len() => count,
+                last => {
+                    return Err(RtpParseError::BadPadding {
+                        count: last.copied().unwrap_or_default(),
+                        available: body.len(),
+                    })
+                }
+            }
+        } else {
+            0
+        };
+        let start = data.len() - body.len();
+        let payload = payload(start..data.len() - padding as usize);
 
         Ok(Self {
+            payload_type,
+            marker,
             sequence,
             timestamp,
             ssrc,
+            csrc,
+            extension,
             payload,
+            padding,
         })
     }
 }
 
+/// Computes the CRC-32 (IEEE 802.3, as used by zlib and Ethernet) of `data`.
+pub fn payload_crc32(data: &[u8]) -> u32 {
+    // ---
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
 #[cfg(test)]
 mod tests {
     // ---
@@ -220,6 +895,564 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_payload_type_roundtrip() {
+        // ---
+        let mut packet = RtpPacket::new(1, 160, 0x12345678, vec![0xFF; 160]);
+        packet.payload_type = 0; // PCMU
+        let serialized = packet.serialize().expect("serialization failed");
+
+        assert_eq!(serialized[1] & 0x7F, 0);
+
+        let deserialized = RtpPacket::deserialize(&serialized).expect("deserialization failed");
+        assert_eq!(deserialized.payload_type, 0);
+
+        // A negotiated dynamic type, set through the builder
+        let packet = RtpPacket::new(2, 320, 0x12345678, vec![0x78])
+            .with_payload_type(111)
+            .expect("valid payload type");
+        let deserialized = RtpPacket::deserialize(&packet.serialize().unwrap()).unwrap();
+        assert_eq!(deserialized.payload_type, 111);
+        assert_eq!(deserialized, packet);
+    }
+
+    #[test]
+    fn test_payload_type_out_of_range_rejected() {
+        // ---
+        let packet = RtpPacket::new(1, 0, 0, vec![]);
+        assert!(packet.clone().with_payload_type(MAX_PAYLOAD_TYPE).is_ok());
+        assert!(packet.clone().with_payload_type(128).is_err());
+
+        // Set directly, it is caught on the way out rather than masked
+        let mut packet = packet;
+        packet.payload_type = 0xEF;
+        assert!(packet.serialize().is_err());
+    }
+
+    #[test]
+    fn test_marker_roundtrip() {
+        // ---
+        let mut packet = RtpPacket::new(1, 320, 0x12345678, vec![1, 2, 3]);
+        packet.marker = true;
+        let serialized = packet.serialize().expect("serialization failed");
+
+        assert_eq!(serialized[1], 0x80 | PAYLOAD_TYPE_OPUS);
+
+        let deserialized = RtpPacket::deserialize(&serialized).expect("deserialization failed");
+        assert!(deserialized.marker);
+        assert_eq!(deserialized.payload_type, PAYLOAD_TYPE_OPUS);
+    }
+
+    #[test]
+    fn test_padding_roundtrip() {
+        // ---
+        let mut packet = RtpPacket::new(7, 320, 0x12345678, vec![1, 2, 3]);
+        packet.pad_to(32).expect("pad");
+        let serialized = packet.serialize().expect("serialization failed");
+
+        assert_eq!(serialized.len(), 32);
+        assert_eq!(packet.wire_len(), 32);
+        assert_ne!(serialized[0] & PADDING_BIT, 0);
+        assert_eq!(*serialized.last().unwrap(), 17);
+
+        let deserialized = RtpPacket::deserialize(&serialized).expect("deserialization failed");
+        assert_eq!(deserialized.payload, vec![1, 2, 3]);
+        assert_eq!(deserialized.padding, 17);
+    }
+
+    #[test]
+    fn test_pad_to_exact_size_needs_no_padding() {
+        // ---
+        let mut packet = RtpPacket::new(1, 0, 0, vec![0xAA; 20]);
+        packet.pad_to(32).expect("pad");
+
+        assert_eq!(packet.padding, 0);
+        assert_eq!(packet.serialize().unwrap()[0] & PADDING_BIT, 0);
+    }
+
+    #[test]
+    fn test_pad_to_rejects_impossible_sizes() {
+        // ---
+        let mut packet = RtpPacket::new(1, 0, 0, vec![0xAA; 20]);
+
+        assert!(packet.pad_to(31).is_err()); // Payload too large
+        assert!(packet.pad_to(32 + MAX_PADDING + 1).is_err()); // Too much padding
+        assert!(packet.pad_to(32 + MAX_PADDING).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_padding_rejected() {
+        // ---
+        let mut data = RtpPacket::new(1, 0, 0, vec![1, 2, 3]).serialize().unwrap();
+        data[0] |= PADDING_BIT;
+
+        *data.last_mut().unwrap() = 0; // Zero count
+        assert!(RtpPacket::deserialize(&data).is_err());
+
+        *data.last_mut().unwrap() = 4; // Longer than the payload
+        assert!(RtpPacket::deserialize(&data).is_err());
+    }
+
+    #[test]
+    fn test_csrc_and_extension_roundtrip() {
+        // ---
+        let mut packet = RtpPacket::new(9, 960, 0x1111_2222, vec![0x78, 1, 2]);
+        packet.marker = true;
+        packet.csrc = vec![0xAAAA_0001, 0xAAAA_0002];
+        packet.extension = Some(RtpHeaderExtension {
+            profile: 0xBEDE,
+            data: vec![0x10, 0x8A, 0, 0],
+        });
+        packet.pad_to(40).expect("pad");
+        assert_eq!(packet.header_len(), 12 + 8 + 8);
+
+        let serialized = packet.serialize().expect("serialization failed");
+        assert_eq!(serialized.len(), 40);
+        assert_eq!(serialized[0], 0x80 | PADDING_BIT | EXTENSION_BIT | 2);
+        assert_eq!(serialized[20..24], [0xBE, 0xDE, 0x00, 0x01]);
+
+        let deserialized = RtpPacket::deserialize(&serialized).expect("deserialization failed");
+        assert_eq!(deserialized, packet);
+    }
+
+    /// Lays a packet out field by field, as `serialize` did before it
+    /// wrote into a caller's buffer.
+    fn reference_encoding(packet: &RtpPacket) -> Vec<u8> {
+        // ---
+        let mut data = vec![
+            0x80 | ((packet.padding > 0) as u8 * PADDING_BIT)
+                | (packet.extension.is_some() as u8 * EXTENSION_BIT)
+                | packet.csrc.len() as u8,
+            (packet.marker as u8 * MARKER_BIT) | packet.payload_type,
+        ];
+        data.extend(packet.sequence.to_be_bytes());
+        data.extend(packet.timestamp.to_be_bytes());
+        data.extend(packet.ssrc.to_be_bytes());
+        data.extend(packet.csrc.iter().flat_map(|csrc| csrc.to_be_bytes()));
+        if let Some(extension) = &packet.extension {
+            data.extend(extension.profile.to_be_bytes());
+            data.extend((extension.data.len() as u16 / 4).to_be_bytes());
+            data.extend(&extension.data);
+        }
+        data.extend(&packet.payload);
+        if packet.padding > 0 {
+            data.extend(vec![0; packet.padding as usize - 1]);
+            data.push(packet.padding);
+        }
+        data
+    }
+
+    #[test]
+    fn test_serialize_into_reused_buffer_matches_reference() {
+        // ---
+        let plain = RtpPacket::new(1, 960, 0x1234_5678, vec![0x78; 80]);
+        let mut marked = RtpPacket::new(u16::MAX, u32::MAX, 1, vec![1, 2, 3])
+            .with_payload_type(0)
+            .unwrap();
+        marked.marker = true;
+        let mut padded = RtpPacket::new(2, 0, 2, vec![9; 7]);
+        padded.pad_to(60).unwrap();
+        let mut full = RtpPacket::new(3, 1920, 3, vec![0xFC, 0xFF, 0xFE]);
+        full.csrc = vec![0xAAAA_0001, 0xAAAA_0002, 0xAAAA_0003];
+        full.set_extension_element(3, &[1, 2, 3]).unwrap();
+        full.set_extension_element(5, &[4, 5]).unwrap();
+        full.pad_to(64).unwrap();
+        let empty = RtpPacket::new(4, 0, 4, Vec::new());
+
+        // Largest first, so later packets land in a longer, dirty buffer
+        let mut buf = vec![0xEE; 1500];
+        for packet in [&plain, &full, &marked, &padded, &empty, &plain] {
+            packet.serialize_into(&mut buf).expect("serialize_into");
+            assert_eq!(buf, reference_encoding(packet), "{packet:?}");
+            assert_eq!(buf, packet.serialize().unwrap());
+            assert_eq!(buf.len(), packet.wire_len());
+        }
+        assert!(buf.capacity() >= 1500, "capacity reused");
+
+        // A packet that cannot be serialized leaves the buffer empty
+        let mut invalid = plain.clone();
+        invalid.payload_type = MAX_PAYLOAD_TYPE + 1;
+        assert!(invalid.serialize_into(&mut buf).is_err());
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_bytes_matches_deserialize_without_copying() {
+        // ---
+        let mut padded = RtpPacket::new(2, 0, 2, vec![9; 7]);
+        padded.pad_to(60).unwrap();
+        let mut full = RtpPacket::new(3, 1920, 3, vec![0xFC, 0xFF, 0xFE]);
+        full.csrc = vec![0xAAAA_0001];
+        full.set_extension_element(3, &[1, 2, 3]).unwrap();
+        let empty = RtpPacket::new(4, 0, 4, Vec::new());
+
+        for packet in [padded, full, empty] {
+            let data = Bytes::from(packet.serialize().unwrap());
+            let parsed = RtpPacket::deserialize_bytes(data.clone()).expect("parse");
+            assert_eq!(parsed, packet);
+            assert_eq!(parsed, RtpPacket::deserialize(&data).unwrap());
+
+            // The payload is the datagram's own bytes
+            if !packet.payload.is_empty() {
+                let offset = parsed.payload.as_ptr() as usize - data.as_ptr() as usize;
+                assert_eq!(offset, packet.header_len());
+            }
+        }
+
+        // Malformed datagrams fail the same way
+        for bad in [
+            &[0x80, 0][..],
+            &[0x40; 12],
+            &[0xA0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        ] {
+            let copied = RtpPacket::deserialize(bad).unwrap_err().to_string();
+            let sliced = RtpPacket::deserialize_bytes(Bytes::copy_from_slice(bad));
+            assert_eq!(sliced.unwrap_err().to_string(), copied);
+        }
+    }
+
+    #[test]
+    fn test_one_byte_elements_roundtrip_at_every_length() {
+        // ---
+        for len in 1..=MAX_ONE_BYTE_ELEMENT_LEN {
+            let mut packet = RtpPacket::new(1, 320, 7, vec![0x78, 1]);
+            let data: Vec<u8> = (1..=len as u8).collect();
+            packet
+                .set_extension_element(5, &data)
+                .expect("valid element");
+            packet
+                .set_extension_element(14, &[0xAB])
+                .expect("valid element");
+
+            let extension = packet.extension.as_ref().unwrap();
+            assert_eq!(extension.profile, ONE_BYTE_EXTENSION_PROFILE);
+            assert_eq!(extension.data.len() % 4, 0, "{len} bytes: not word padded");
+            assert_eq!(extension.data.len(), (len + 3).next_multiple_of(4));
+
+            let serialized = packet.serialize().unwrap();
+            let parsed = RtpPacket::deserialize(&serialized).unwrap();
+            assert_eq!(parsed.extension_element(5), Some(data), "{len} bytes");
+            assert_eq!(parsed.extension_element(14), Some(vec![0xAB]));
+            assert_eq!(parsed.extension_element(6), None);
+            assert_eq!(parsed.payload, [0x78, 1][..]);
+        }
+
+        // Setting an ID again replaces its element in place
+        let mut packet = RtpPacket::new(1, 320, 7, vec![]);
+        packet.set_extension_element(3, &[1, 2, 3]).unwrap();
+        packet.set_extension_element(4, &[9]).unwrap();
+        packet.set_extension_element(3, &[4, 5, 6]).unwrap();
+        let elements = packet.extension.as_ref().unwrap().elements().unwrap();
+        let ids: Vec<u8> = elements.iter().map(|element| element.id).collect();
+        assert_eq!(ids, [3, 4]);
+        assert_eq!(elements[0].data, [4, 5, 6]);
+
+        // Packets without an extension have no elements
+        let plain = RtpPacket::deserialize(&RtpPacket::new(1, 0, 0, vec![1]).serialize().unwrap());
+        assert_eq!(plain.unwrap().extension_element(3), None);
+    }
+
+    #[test]
+    fn test_invalid_one_byte_elements_rejected() {
+        // ---
+        let mut packet = RtpPacket::new(1, 0, 0, vec![]);
+        assert!(packet.set_extension_element(0, &[1]).is_err());
+        assert!(packet.set_extension_element(15, &[1]).is_err());
+        assert!(packet.set_extension_element(1, &[]).is_err());
+        assert!(packet
+            .set_extension_element(1, &[0; MAX_ONE_BYTE_ELEMENT_LEN + 1])
+            .is_err());
+        assert!(packet.extension.is_none());
+
+        // Another profile is left alone
+        packet.extension = Some(RtpHeaderExtension {
+            profile: 0x1000,
+            data: vec![0x10, 0xFF, 0, 0],
+        });
+        assert!(packet.set_extension_element(1, &[1]).is_err());
+        assert_eq!(packet.extension_element(1), None);
+
+        // An element running past the block, and one after the stop ID
+        let extension = RtpHeaderExtension {
+            profile: ONE_BYTE_EXTENSION_PROFILE,
+            data: vec![0x00, 0x13, 1, 2],
+        };
+        assert!(extension.elements().is_err());
+        let extension = RtpHeaderExtension {
+            profile: ONE_BYTE_EXTENSION_PROFILE,
+            data: vec![0x20, 7, 0xF0, 0x30],
+        };
+        assert_eq!(
+            extension.elements().unwrap(),
+            [ExtensionElement {
+                id: 2,
+                data: vec![7]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parses_packets_from_other_stacks() {
+        // ---
+        // Opus as libwebrtc sends it: PT 111, an RFC 8285 one-byte
+        // extension with the audio level (ID 1) and transport-wide
+        // sequence number (ID 3), payload starting with the TOC byte
+        let webrtc = [
+            0x90, 0x6F, 0x4B, 0x21, 0x00, 0x03, 0x8E, 0x40, 0x5C, 0x1A, 0x0F, 0x33, // header
+            0xBE, 0xDE, 0x00, 0x02, // extension profile and length
+            0x10, 0x8A, 0x31, 0x02, 0x9C, 0x00, 0x00, 0x00, // elements, padded
+            0x78, 0x0B, 0xE4, 0xC1, 0x36, // payload
+        ];
+        let packet = RtpPacket::deserialize(&webrtc).expect("libwebrtc packet");
+        assert_eq!(packet.payload_type, 111);
+        assert!(!packet.marker);
+        assert_eq!(packet.sequence, 0x4B21);
+        assert_eq!(packet.timestamp, 0x0003_8E40);
+        assert_eq!(packet.ssrc, 0x5C1A_0F33);
+        assert!(packet.csrc.is_empty());
+        let extension = packet.extension.as_ref().expect("extension");
+        assert_eq!(extension.profile, 0xBEDE);
+        assert_eq!(extension.data, [0x10, 0x8A, 0x31, 0x02, 0x9C, 0, 0, 0]);
+        assert_eq!(packet.payload, [0x78, 0x0B, 0xE4, 0xC1, 0x36][..]);
+        assert_eq!(packet.serialize().unwrap(), webrtc);
+
+        // A conference mixer's PCMU output: marker set, the two talkers
+        // mixed in as CSRCs
+        let mixer = [
+            0x82, 0x80, 0x01, 0x00, 0x00, 0x00, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x2A, // header
+            0x00, 0x00, 0x10, 0x01, 0x00, 0x00, 0x10, 0x02, // CSRCs
+            0xFF, 0xFE, 0x7F, 0x7E, // payload
+        ];
+        let packet = RtpPacket::deserialize(&mixer).expect("mixer packet");
+        assert_eq!(packet.payload_type, 0);
+        assert!(packet.marker);
+        assert_eq!(packet.csrc, [0x1001, 0x1002]);
+        assert_eq!(packet.payload, [0xFF, 0xFE, 0x7F, 0x7E][..]);
+        assert_eq!(packet.serialize().unwrap(), mixer);
+
+        // A bandwidth probe: padding only, after an extension
+        let probe = [
+            0xB0, 0x6F, 0x4B, 0x22, 0x00, 0x03, 0x8E, 0x40, 0x5C, 0x1A, 0x0F, 0x33, // header
+            0xBE, 0xDE, 0x00, 0x01, 0x30, 0x02, 0x9D, 0x00, // extension
+            0x00, 0x00, 0x00, 0x04, // padding
+        ];
+        let packet = RtpPacket::deserialize(&probe).expect("probe packet");
+        assert!(packet.payload.is_empty());
+        assert_eq!(packet.padding, 4);
+        assert_eq!(packet.serialize().unwrap(), probe);
+    }
+
+    #[test]
+    fn test_malformed_headers_rejected() {
+        // ---
+        let mut packet = RtpPacket::new(1, 0, 0, vec![1, 2, 3]);
+        packet.csrc = vec![7];
+        packet.extension = Some(RtpHeaderExtension {
+            profile: 0x1000,
+            data: vec![0; 4],
+        });
+        let valid = packet.serialize().unwrap();
+
+        // A CSRC count past the end of the packet
+        let mut data = RtpPacket::new(1, 0, 0, vec![1, 2, 3]).serialize().unwrap();
+        data[0] |= 1;
+        assert!(RtpPacket::deserialize(&data).is_err());
+
+        // An extension header cut off, or a length past the end
+        let mut data = RtpPacket::new(1, 0, 0, vec![1, 2, 3]).serialize().unwrap();
+        data[0] |= EXTENSION_BIT;
+        assert!(RtpPacket::deserialize(&data).is_err());
+        let mut data = valid.clone();
+        data[18] = 0x00;
+        data[19] = 0x02;
+        assert!(RtpPacket::deserialize(&data).is_err());
+
+        // Padding reaching back into the extension
+        let mut data = valid.clone();
+        data[0] |= PADDING_BIT;
+        *data.last_mut().unwrap() = 4;
+        assert!(RtpPacket::deserialize(&data).is_err());
+
+        // No truncation of a packet using every field panics; cut inside
+        // the header, it fails. Cut later, the last byte left may happen
+        // to be a valid padding count.
+        packet.pad_to(40).unwrap();
+        let full = packet.serialize().unwrap();
+        for len in 0..full.len() {
+            let result = RtpPacket::deserialize(&full[..len]);
+            if len < packet.header_len() {
+                assert!(result.is_err(), "{len} bytes");
+            }
+        }
+
+        // Fields that cannot be put on the wire
+        let mut packet = RtpPacket::new(1, 0, 0, vec![]);
+        packet.csrc = vec![0; MAX_CSRC + 1];
+        assert!(packet.serialize().is_err());
+        packet.csrc.clear();
+        packet.extension = Some(RtpHeaderExtension {
+            profile: 0,
+            data: vec![0; 3],
+        });
+        assert!(packet.serialize().is_err());
+    }
+
+    #[test]
+    fn test_parse_errors_say_why() {
+        // ---
+        let lenient = |data: &[u8]| RtpPacket::try_deserialize(data, RtpParseMode::Lenient);
+        let kind = |data: &[u8]| lenient(data).unwrap_err().kind();
+
+        assert_eq!(
+            lenient(&[0x80; 11]),
+            Err(RtpParseError::TooShort { len: 11 })
+        );
+        assert_eq!(
+            lenient(&[0x40; 12]),
+            Err(RtpParseError::BadVersion { version: 1 })
+        );
+
+        let plain = RtpPacket::new(1, 0, 0, vec![1, 2, 3]).serialize().unwrap();
+        let mut data = plain.clone();
+        data[0] |= 1;
+        assert_eq!(
+            lenient(&data),
+            Err(RtpParseError::TruncatedCsrc {
+                count: 1,
+                available: 3
+            })
+        );
+        let mut data = plain.clone();
+        data[0] |= EXTENSION_BIT;
+        assert_eq!(kind(&data), RtpParseErrorKind::BadExtension);
+        let mut data = plain.clone();
+        data[0] |= PADDING_BIT;
+        *data.last_mut().unwrap() = 4;
+        assert_eq!(
+            lenient(&data),
+            Err(RtpParseError::BadPadding {
+                count: 4,
+                available: 3
+            })
+        );
+        *data.last_mut().unwrap() = 0;
+        assert_eq!(
+            lenient(&data),
+            Err(RtpParseError::BadPadding {
+                count: 0,
+                available: 3
+            })
+        );
+
+        // The anyhow wrapper keeps the message
+        let error = RtpPacket::deserialize(&[0x80; 11]).unwrap_err();
+        assert_eq!(error.to_string(), "packet too small: 11 bytes");
+        assert_eq!(
+            error
+                .downcast_ref::<RtpParseError>()
+                .map(RtpParseError::kind),
+            Some(RtpParseErrorKind::TooShort)
+        );
+        let labels: Vec<&str> = RtpParseErrorKind::ALL.iter().map(|k| k.label()).collect();
+        assert_eq!(labels[0], "too_short");
+        assert_eq!(labels.len(), 6);
+    }
+
+    #[test]
+    fn test_strict_mode_refuses_unknown_extensions() {
+        // ---
+        let strict = |data: &[u8]| RtpPacket::try_deserialize(data, RtpParseMode::Strict);
+
+        let mut packet = RtpPacket::new(1, 0, 0, vec![1, 2, 3]);
+        packet.set_extension_element(3, &[0, 7]).unwrap();
+        let one_byte = packet.serialize().unwrap();
+        assert_eq!(strict(&one_byte).unwrap(), packet);
+
+        // A two-byte (RFC 8285 §4.3) block is valid RTP, but not understood
+        packet.extension = Some(RtpHeaderExtension {
+            profile: 0x1000,
+            data: vec![3, 2, 0, 7],
+        });
+        let two_byte = packet.serialize().unwrap();
+        assert!(RtpPacket::try_deserialize(&two_byte, RtpParseMode::Lenient).is_ok());
+        assert_eq!(
+            strict(&two_byte).unwrap_err().kind(),
+            RtpParseErrorKind::UnsupportedFeature
+        );
+
+        // An element running past the block
+        packet.extension = Some(RtpHeaderExtension {
+            profile: ONE_BYTE_EXTENSION_PROFILE,
+            data: vec![0x00, 0x13, 1, 2],
+        });
+        let overrun = packet.serialize().unwrap();
+        assert!(RtpPacket::deserialize(&overrun).is_ok());
+        assert_eq!(
+            strict(&overrun),
+            Err(RtpParseError::BadExtension {
+                len: 4,
+                available: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_input_panics_or_reads_out_of_bounds() {
+        // ---
+        // xorshift64: arbitrary, reproducible bytes without a dependency
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let check = |data: &[u8]| {
+            for mode in [RtpParseMode::Lenient, RtpParseMode::Strict] {
+                let bytes = Bytes::copy_from_slice(data);
+                let Ok(packet) = RtpPacket::try_deserialize_bytes(bytes.clone(), mode) else {
+                    continue;
+                };
+                let end = packet.header_len() + packet.payload.len() + packet.padding as usize;
+                assert_eq!(end, data.len(), "{data:02X?}");
+                let offset = packet.payload.as_ptr() as usize - bytes.as_ptr() as usize;
+                assert!(packet.payload.is_empty() || offset == packet.header_len());
+            }
+        };
+
+        // Random datagrams, mostly claiming version 2 so parsing gets past
+        // the first byte
+        for _ in 0..100_000 {
+            let len = (next() % 80) as usize;
+            let mut data: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            if let Some(first) = data.first_mut() {
+                if next() % 8 != 0 {
+                    *first = 0x80 | (*first & 0x3F);
+                }
+            }
+            check(&data);
+        }
+
+        // Every value of every header byte, and every truncation, of a
+        // packet using every field
+        let mut packet = RtpPacket::new(1, 2, 3, vec![0x78; 8]);
+        packet.csrc = vec![7, 8];
+        packet.set_extension_element(1, &[1, 2, 3]).unwrap();
+        packet.pad_to(64).unwrap();
+        let full = packet.serialize().unwrap();
+        for index in 0..packet.header_len() + 1 {
+            for value in 0..=u8::MAX {
+                let mut data = full.clone();
+                data[index] = value;
+                check(&data);
+            }
+        }
+        for len in 0..=full.len() {
+            check(&full[..len]);
+        }
+    }
+
     #[test]
     fn test_sequence_wraparound() {
         // ---
@@ -254,4 +1487,32 @@ mod tests {
         let deserialized = RtpPacket::deserialize(&serialized).expect("deserialization failed");
         assert_eq!(deserialized.payload, large_payload);
     }
+
+    #[test]
+    fn test_payload_crc_round_trip_and_mismatch() {
+        // ---
+        // Standard CRC-32 check value
+        assert_eq!(payload_crc32(b"123456789"), 0xCBF4_3926);
+
+        let mut packet = RtpPacket::new(7, 960, 0xABCD, vec![0x78, 1, 2, 3]);
+        packet.append_payload_crc();
+        packet.pad_to(40).unwrap();
+        let mut received = RtpPacket::deserialize(&packet.serialize().unwrap()).unwrap();
+        assert_eq!(received.payload.len(), 4 + PAYLOAD_CRC_LEN);
+        received.strip_payload_crc().expect("intact payload");
+        assert_eq!(received.payload, vec![0x78, 1, 2, 3]);
+
+        // A single flipped bit is caught and the payload left alone
+        let mut corrupted = packet.clone();
+        let mut payload = corrupted.payload.to_vec();
+        payload[1] ^= 0x10;
+        corrupted.payload = payload.into();
+        assert!(corrupted.strip_payload_crc().is_err());
+        assert_eq!(corrupted.payload.len(), 4 + PAYLOAD_CRC_LEN);
+
+        // Too short to carry a trailer
+        assert!(RtpPacket::new(1, 0, 0, vec![1, 2])
+            .strip_payload_crc()
+            .is_err());
+    }
 }
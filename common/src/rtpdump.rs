@@ -0,0 +1,404 @@
+//! The rtpdump capture format of rtptools.
+//!
+//! An rtpdump file starts with a text line naming the address the packets
+//! were captured on (`#!rtpplay1.0 address/port`), then a binary header
+//! holding when the capture started. Each packet follows as a record
+//! giving its length and its arrival time in milliseconds since the
+//! start, then its bytes as they came off the wire. All binary fields are
+//! in network byte order.
+//!
+//! [`RtpdumpWriter`] appends packets as they arrive; [`parse_rtpdump`]
+//! and [`read_rtpdump`] read a capture back, e.g. to replay it with its
+//! original timing. Files are compatible with `rtpplay` and Wireshark.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Start of the text line opening every rtpdump file
+pub const RTPDUMP_MAGIC: &str = "#!rtpplay1.0";
+
+/// Size of the binary file header after the text line
+pub const RTPDUMP_FILE_HEADER_LEN: usize = 16;
+
+/// Size of the header before each recorded packet
+pub const RTPDUMP_RECORD_HEADER_LEN: usize = 8;
+
+/// Longest text line accepted when parsing
+const MAX_TEXT_LINE: usize = 256;
+
+/// One packet read from an rtpdump file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpdumpPacket {
+    // ---
+    /// Arrival time since the start of the capture, in whole milliseconds
+    pub offset: Duration,
+
+    /// Packet bytes as received; shorter than `wire_len` if the capture
+    /// kept only the start of the packet
+    pub data: Vec<u8>,
+
+    /// Length of the packet on the wire, 0 for RTCP
+    pub wire_len: usize,
+}
+
+impl RtpdumpPacket {
+    // ---
+    /// Returns whether the capture holds the whole RTP packet.
+    pub fn is_complete(&self) -> bool {
+        // ---
+        self.wire_len > 0 && self.data.len() == self.wire_len
+    }
+}
+
+/// The packets of an rtpdump file, in the order they were captured.
+#[derive(Debug, Clone)]
+pub struct RtpdumpFile {
+    // ---
+    /// Address the packets were captured on
+    pub source: SocketAddrV4,
+
+    /// Wall-clock time the capture started
+    pub start: SystemTime,
+
+    /// Recorded packets
+    pub packets: Vec<RtpdumpPacket>,
+}
+
+impl RtpdumpFile {
+    // ---
+    /// Returns the time from the first packet to the last.
+    pub fn duration(&self) -> Duration {
+        // ---
+        match (self.packets.first(), self.packets.last()) {
+            (Some(first), Some(last)) => last.offset.saturating_sub(first.offset),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Appends received packets to an rtpdump file.
+///
+/// Arrival times are recorded relative to the creation of the writer,
+/// which is the start time written in the file header.
+pub struct RtpdumpWriter<W: Write> {
+    // ---
+    out: W,
+
+    /// When the capture started, for record offsets
+    started: Instant,
+
+    packets_written: u64,
+    bytes_written: u64,
+}
+
+impl RtpdumpWriter<File> {
+    // ---
+    /// Creates the rtpdump file at `path`, replacing any file there.
+    ///
+    /// The file is not buffered: each record goes out in one write, so
+    /// the capture is complete up to the last packet even if the process
+    /// is killed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be created or its header written.
+    pub fn create(path: impl AsRef<Path>, source: SocketAddrV4) -> Result<Self> {
+        // ---
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("failed to create rtpdump file {}", path.display()))?;
+        Self::new(file, source)
+            .with_context(|| format!("failed to write rtpdump file {}", path.display()))
+    }
+}
+
+impl<W: Write> RtpdumpWriter<W> {
+    // ---
+    /// Starts a capture on `out` by writing the file header.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - Where the capture goes
+    /// * `source` - Address the packets are captured on
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the header cannot be written.
+    pub fn new(mut out: W, source: SocketAddrV4) -> io::Result<Self> {
+        // ---
+        let started = Instant::now();
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO);
+
+        let mut header =
+            format!("{} {}/{}\n", RTPDUMP_MAGIC, source.ip(), source.port()).into_bytes();
+        header.extend_from_slice(&(since_epoch.as_secs() as u32).to_be_bytes());
+        header.extend_from_slice(&since_epoch.subsec_micros().to_be_bytes());
+        header.extend_from_slice(&source.ip().octets());
+        header.extend_from_slice(&source.port().to_be_bytes());
+        header.extend_from_slice(&[0, 0]);
+        out.write_all(&header)?;
+
+        Ok(Self {
+            out,
+            started,
+            packets_written: 0,
+            bytes_written: 0,
+        })
+    }
+
+    /// Records one RTP packet as received, arriving at `at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the packet is too long for a record or the write
+    /// fails.
+    pub fn write_packet(&mut self, data: &[u8], at: Instant) -> io::Result<()> {
+        // ---
+        let wire_len = u16::try_from(data.len())
+            .ok()
+            .filter(|&len| usize::from(len) + RTPDUMP_RECORD_HEADER_LEN <= u16::MAX as usize)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} byte packet does not fit in a record", data.len()),
+                )
+            })?;
+        let offset = at.saturating_duration_since(self.started).as_millis() as u32;
+
+        let mut record = Vec::with_capacity(RTPDUMP_RECORD_HEADER_LEN + data.len());
+        record.extend_from_slice(&(wire_len + RTPDUMP_RECORD_HEADER_LEN as u16).to_be_bytes());
+        record.extend_from_slice(&wire_len.to_be_bytes());
+        record.extend_from_slice(&offset.to_be_bytes());
+        record.extend_from_slice(data);
+        self.out.write_all(&record)?;
+
+        self.packets_written += 1;
+        self.bytes_written += data.len() as u64;
+        Ok(())
+    }
+
+    /// Returns how many packets have been recorded.
+    pub fn packets_written(&self) -> u64 {
+        // ---
+        self.packets_written
+    }
+
+    /// Returns the packet bytes recorded, not counting record headers.
+    pub fn bytes_written(&self) -> u64 {
+        // ---
+        self.bytes_written
+    }
+
+    /// Returns the same capture writing through a boxed writer, so
+    /// captures to different kinds of output have one type.
+    pub fn boxed(self) -> RtpdumpWriter<Box<dyn Write + Send>>
+    where
+        W: Send + 'static,
+    {
+        // ---
+        RtpdumpWriter {
+            out: Box::new(self.out),
+            started: self.started,
+            packets_written: self.packets_written,
+            bytes_written: self.bytes_written,
+        }
+    }
+
+    /// Flushes the capture and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the flush fails.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        // ---
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Parses an rtpdump file held in memory.
+///
+/// A final record cut short, as when the capturing process was killed
+/// mid-write, is left out.
+///
+/// # Errors
+///
+/// Returns error if the file does not start with the rtpdump text line and
+/// header, or if a record's lengths are inconsistent.
+pub fn parse_rtpdump(data: &[u8]) -> Result<RtpdumpFile> {
+    // ---
+    let line_end = data
+        .iter()
+        .take(MAX_TEXT_LINE)
+        .position(|&b| b == b'\n')
+        .context("no rtpdump text line")?;
+    let line = std::str::from_utf8(&data[..line_end]).context("rtpdump text line is not text")?;
+    if !line.starts_with(RTPDUMP_MAGIC) {
+        anyhow::bail!("not an rtpdump file (starts with {:?})", line);
+    }
+
+    let header = data
+        .get(line_end + 1..line_end + 1 + RTPDUMP_FILE_HEADER_LEN)
+        .context("rtpdump file header cut short")?;
+    let word = |at: usize| u32::from_be_bytes(header[at..at + 4].try_into().unwrap());
+    let start = UNIX_EPOCH + Duration::new(word(0) as u64, word(4).min(999_999) * 1000);
+    let source = SocketAddrV4::new(
+        Ipv4Addr::from(word(8)),
+        u16::from_be_bytes([header[12], header[13]]),
+    );
+
+    let mut packets = Vec::new();
+    let mut rest = &data[line_end + 1 + RTPDUMP_FILE_HEADER_LEN..];
+    while rest.len() >= RTPDUMP_RECORD_HEADER_LEN {
+        let length = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        let wire_len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+        let offset = u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]);
+        if length < RTPDUMP_RECORD_HEADER_LEN {
+            anyhow::bail!(
+                "record {} is {} bytes, shorter than its header",
+                packets.len(),
+                length
+            );
+        }
+        if length > rest.len() {
+            break;
+        }
+        let data = rest[RTPDUMP_RECORD_HEADER_LEN..length].to_vec();
+        if wire_len > 0 && data.len() > wire_len {
+            anyhow::bail!(
+                "record {} holds {} bytes of a {} byte packet",
+                packets.len(),
+                data.len(),
+                wire_len
+            );
+        }
+        packets.push(RtpdumpPacket {
+            offset: Duration::from_millis(offset as u64),
+            data,
+            wire_len,
+        });
+        rest = &rest[length..];
+    }
+
+    Ok(RtpdumpFile {
+        source,
+        start,
+        packets,
+    })
+}
+
+/// Reads and parses the rtpdump file at `path`.
+///
+/// # Errors
+///
+/// Returns error if the file cannot be read or is not a valid rtpdump
+/// file (see [`parse_rtpdump`]).
+pub fn read_rtpdump<P: AsRef<Path>>(path: P) -> Result<RtpdumpFile> {
+    // ---
+    let path = path.as_ref();
+    let data = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    parse_rtpdump(&data).with_context(|| format!("invalid rtpdump file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::RtpPacket;
+
+    fn capture(packets: &[(u16, u64)]) -> Vec<u8> {
+        // ---
+        let source = SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 7), 5004);
+        let mut writer = RtpdumpWriter::new(Vec::new(), source).unwrap();
+        let started = writer.started;
+        for &(sequence, ms) in packets {
+            let packet = RtpPacket::new(
+                sequence,
+                sequence as u32 * 320,
+                0xD0D0,
+                vec![sequence as u8; 40],
+            );
+            writer
+                .write_packet(
+                    &packet.serialize().unwrap(),
+                    started + Duration::from_millis(ms),
+                )
+                .unwrap();
+        }
+        assert_eq!(writer.packets_written(), packets.len() as u64);
+        assert_eq!(writer.bytes_written(), packets.len() as u64 * 52);
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_capture_reads_back() {
+        // ---
+        let data = capture(&[(0, 0), (1, 20), (3, 61), (4, 80)]);
+        assert!(data.starts_with(b"#!rtpplay1.0 192.0.2.7/5004\n"));
+
+        let file = parse_rtpdump(&data).unwrap();
+        assert_eq!(
+            file.source,
+            SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 7), 5004)
+        );
+        let age = SystemTime::now().duration_since(file.start).unwrap();
+        assert!(age < Duration::from_secs(5), "start {:?} ago", age);
+
+        let offsets: Vec<u64> = file
+            .packets
+            .iter()
+            .map(|p| p.offset.as_millis() as u64)
+            .collect();
+        assert_eq!(offsets, [0, 20, 61, 80]);
+        assert_eq!(file.duration(), Duration::from_millis(80));
+
+        let sequences: Vec<u16> = file
+            .packets
+            .iter()
+            .map(|p| {
+                assert!(p.is_complete());
+                RtpPacket::deserialize(&p.data).unwrap().sequence
+            })
+            .collect();
+        assert_eq!(sequences, [0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn test_record_layout() {
+        // ---
+        let data = capture(&[(7, 1234)]);
+        let record = &data[data.len() - 52 - RTPDUMP_RECORD_HEADER_LEN..];
+        assert_eq!(u16::from_be_bytes([record[0], record[1]]), 60);
+        assert_eq!(u16::from_be_bytes([record[2], record[3]]), 52);
+        assert_eq!(
+            u32::from_be_bytes([record[4], record[5], record[6], record[7]]),
+            1234
+        );
+    }
+
+    #[test]
+    fn test_truncated_last_record_is_dropped() {
+        // ---
+        let data = capture(&[(0, 0), (1, 20)]);
+        let file = parse_rtpdump(&data[..data.len() - 10]).unwrap();
+        assert_eq!(file.packets.len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        // ---
+        assert!(parse_rtpdump(b"RIFF....WAVEfmt ").is_err());
+        assert!(parse_rtpdump(b"#!rtpplay1.0 1.2.3.4/5\n\0\0").is_err());
+
+        let mut data = capture(&[(0, 0)]);
+        let record = data.len() - 52 - RTPDUMP_RECORD_HEADER_LEN;
+        data[record..record + 2].copy_from_slice(&4u16.to_be_bytes());
+        assert!(parse_rtpdump(&data).is_err());
+    }
+}
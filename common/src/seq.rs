@@ -0,0 +1,350 @@
+//! Extended RTP sequence numbers.
+//!
+//! The 16-bit sequence number wraps every 65536 packets, under 22 minutes
+//! of 20ms frames. [`SeqTracker`] counts the wraps, after RFC 3550
+//! Appendix A.1, so each packet has a 64-bit extended sequence number that
+//! orders it within the whole stream. It also tells a sender restart (a
+//! jump, confirmed by the next packet following it) from stray packets,
+//! and holds a new stream on probation until its first packets arrive in
+//! order.
+
+/// Largest forward step taken as the same stream, gaps included
+pub const MAX_DROPOUT: u16 = 3000;
+
+/// Largest backward step taken as a reordered or duplicate packet
+pub const MAX_MISORDER: u16 = 100;
+
+/// Packets in order that take a new stream off probation
+pub const MIN_SEQUENTIAL: u32 = 2;
+
+/// Size of the sequence number space
+const SEQ_MOD: u32 = 1 << 16;
+
+/// How a packet's sequence number relates to the stream, from
+/// [`SeqTracker::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeqUpdate {
+    // ---
+    /// The first packet, or one confirming a jump as a restart of the
+    /// stream: a new baseline, with no loss before it
+    Restart { extended: u64 },
+
+    /// Ahead of the highest sequence number by `gap` (1 when in order)
+    Ahead { extended: u64, gap: u16 },
+
+    /// At or behind the highest sequence number: a duplicate or a
+    /// reordered packet
+    Behind { extended: u64 },
+
+    /// Too far from the highest sequence number to belong to the stream.
+    /// Ignored, unless the next packet follows it, which restarts the
+    /// stream there.
+    Jump,
+}
+
+/// Maps 16-bit sequence numbers to extended ones (RFC 3550 Appendix A.1).
+///
+/// Unlike the RFC's sample code, a packet behind the highest during
+/// probation is taken as reordered rather than restarting probation from
+/// it, and packets on probation are still given extended numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeqTracker {
+    // ---
+    /// Highest sequence number seen, once a packet has been
+    max_seq: Option<u16>,
+
+    /// Times the sequence number has wrapped since the baseline
+    cycles: u64,
+
+    /// Sequence number that would confirm the last jump as a restart
+    bad_seq: Option<u16>,
+
+    /// Packets in order still needed to leave probation
+    probation: u32,
+}
+
+impl Default for SeqTracker {
+    fn default() -> Self {
+        // ---
+        Self::new()
+    }
+}
+
+impl SeqTracker {
+    // ---
+    /// Creates a tracker that has seen no packets yet.
+    pub fn new() -> Self {
+        // ---
+        Self {
+            max_seq: None,
+            cycles: 0,
+            bad_seq: None,
+            probation: MIN_SEQUENTIAL,
+        }
+    }
+
+    /// Observes the packet with `sequence`.
+    pub fn update(&mut self, sequence: u16) -> SeqUpdate {
+        // ---
+        let Some(max_seq) = self.max_seq else {
+            self.restart(sequence);
+            self.probation = MIN_SEQUENTIAL - 1;
+            return SeqUpdate::Restart {
+                extended: sequence as u64,
+            };
+        };
+
+        let udelta = sequence.wrapping_sub(max_seq);
+        let behind = udelta == 0 || udelta as u32 >= SEQ_MOD - MAX_MISORDER as u32;
+        if self.probation > 0 {
+            if behind {
+                return self.behind(sequence);
+            }
+            if udelta >= MAX_DROPOUT {
+                // Not yet a stream to keep: start over from here
+                self.max_seq = Some(sequence);
+                self.probation = MIN_SEQUENTIAL - 1;
+                return SeqUpdate::Restart {
+                    extended: self.highest_extended().unwrap_or_default(),
+                };
+            }
+            self.probation = if udelta == 1 {
+                self.probation - 1
+            } else {
+                MIN_SEQUENTIAL - 1
+            };
+            return self.advance(sequence, udelta);
+        }
+
+        if behind {
+            self.behind(sequence)
+        } else if udelta < MAX_DROPOUT {
+            self.advance(sequence, udelta)
+        } else if self.bad_seq == Some(sequence) {
+            // Two packets in order after a jump: the sender restarted
+            self.restart(sequence);
+            SeqUpdate::Restart {
+                extended: sequence as u64,
+            }
+        } else {
+            self.bad_seq = Some(sequence.wrapping_add(1));
+            SeqUpdate::Jump
+        }
+    }
+
+    /// Returns the extended sequence number `sequence` would have: the one
+    /// nearest the highest seen. `None` before the first packet, or if it
+    /// would fall before the first wrap.
+    pub fn extend(&self, sequence: u16) -> Option<u64> {
+        // ---
+        let max_seq = self.max_seq?;
+        let delta = sequence.wrapping_sub(max_seq) as i16 as i64;
+        (self.cycles << 16 | max_seq as u64).checked_add_signed(delta)
+    }
+
+    /// Returns the highest extended sequence number seen.
+    pub fn highest_extended(&self) -> Option<u64> {
+        // ---
+        self.extend(self.max_seq?)
+    }
+
+    /// Returns how many times the sequence number has wrapped since the
+    /// stream (re)started.
+    pub fn cycles(&self) -> u64 {
+        // ---
+        self.cycles
+    }
+
+    /// Returns how many more packets in order the stream needs to leave
+    /// probation; zero once it has.
+    pub fn probation(&self) -> u32 {
+        // ---
+        self.probation
+    }
+
+    /// Takes `sequence` as the new baseline.
+    fn restart(&mut self, sequence: u16) {
+        // ---
+        self.max_seq = Some(sequence);
+        self.cycles = 0;
+        self.bad_seq = None;
+    }
+
+    /// Moves the highest sequence number `udelta` ahead to `sequence`.
+    fn advance(&mut self, sequence: u16, udelta: u16) -> SeqUpdate {
+        // ---
+        if self.max_seq.is_some_and(|max_seq| sequence < max_seq) {
+            self.cycles += 1;
+        }
+        self.max_seq = Some(sequence);
+        SeqUpdate::Ahead {
+            extended: self.highest_extended().unwrap_or_default(),
+            gap: udelta,
+        }
+    }
+
+    /// Reports `sequence`, at or behind the highest, leaving it in place.
+    fn behind(&self, sequence: u16) -> SeqUpdate {
+        // ---
+        match self.extend(sequence) {
+            Some(extended) => SeqUpdate::Behind { extended },
+            // Before the first packet seen: there is nothing to order it by
+            None => SeqUpdate::Jump,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    /// Feeds `sequences` in, returning the extended number of each
+    fn extended(tracker: &mut SeqTracker, sequences: &[u16]) -> Vec<Option<u64>> {
+        // ---
+        sequences
+            .iter()
+            .map(|&sequence| match tracker.update(sequence) {
+                SeqUpdate::Restart { extended }
+                | SeqUpdate::Ahead { extended, .. }
+                | SeqUpdate::Behind { extended } => Some(extended),
+                SeqUpdate::Jump => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_counts_cycles_across_wraparound() {
+        // ---
+        let mut tracker = SeqTracker::new();
+        assert_eq!(tracker.highest_extended(), None);
+        assert_eq!(tracker.probation(), MIN_SEQUENTIAL);
+
+        assert_eq!(
+            tracker.update(65_534),
+            SeqUpdate::Restart { extended: 65_534 }
+        );
+        assert_eq!(tracker.probation(), 1);
+        assert_eq!(
+            tracker.update(65_535),
+            SeqUpdate::Ahead {
+                extended: 65_535,
+                gap: 1
+            }
+        );
+        assert_eq!(tracker.probation(), 0);
+
+        // Two full wraps, in order: packet 70,000 and beyond
+        let mut sequence = 65_535u16;
+        for _ in 0..2 * SEQ_MOD {
+            sequence = sequence.wrapping_add(1);
+            assert!(matches!(
+                tracker.update(sequence),
+                SeqUpdate::Ahead { gap: 1, .. }
+            ));
+        }
+        assert_eq!(tracker.cycles(), 2);
+        assert_eq!(tracker.highest_extended(), Some(65_535 + 2 * 65_536));
+
+        // A gap across the next wrap
+        assert_eq!(
+            tracker.update(4),
+            SeqUpdate::Ahead {
+                extended: 3 * 65_536 + 4,
+                gap: 5
+            }
+        );
+        assert_eq!(tracker.cycles(), 3);
+    }
+
+    #[test]
+    fn test_reordering_straddling_the_wrap() {
+        // ---
+        let mut tracker = SeqTracker::new();
+        let sequences = [65_532, 65_533, 65_535, 0, 65_534, 2, 1, 65_535, 3];
+        let cycle = 65_536;
+        assert_eq!(
+            extended(&mut tracker, &sequences),
+            [
+                Some(65_532),
+                Some(65_533),
+                Some(65_535),
+                Some(cycle),
+                Some(65_534), // Reordered from before the wrap
+                Some(cycle + 2),
+                Some(cycle + 1),
+                Some(65_535), // Duplicate from before the wrap
+                Some(cycle + 3),
+            ]
+        );
+        assert_eq!(tracker.cycles(), 1);
+        assert_eq!(
+            tracker.update(2),
+            SeqUpdate::Behind {
+                extended: cycle + 2
+            }
+        );
+        assert_eq!(tracker.extend(65_000), Some(65_000));
+        assert_eq!(tracker.extend(1_000), Some(cycle + 1_000));
+    }
+
+    #[test]
+    fn test_sender_restart_with_a_jump() {
+        // ---
+        let mut tracker = SeqTracker::new();
+        for sequence in 0..=65_535u16 {
+            tracker.update(sequence);
+        }
+        tracker.update(0);
+        tracker.update(1);
+        assert_eq!(tracker.highest_extended(), Some(65_536 + 1));
+
+        // A stray packet far away is ignored
+        assert_eq!(tracker.update(40_000), SeqUpdate::Jump);
+        assert!(matches!(tracker.update(2), SeqUpdate::Ahead { gap: 1, .. }));
+
+        // A restart: the jump, then the packet after it
+        assert_eq!(tracker.update(20_000), SeqUpdate::Jump);
+        assert_eq!(
+            tracker.update(20_001),
+            SeqUpdate::Restart { extended: 20_001 }
+        );
+        assert_eq!(tracker.cycles(), 0);
+        assert_eq!(
+            tracker.update(20_002),
+            SeqUpdate::Ahead {
+                extended: 20_002,
+                gap: 1
+            }
+        );
+
+        // Far behind, beyond reordering, is a jump too
+        assert_eq!(tracker.update(20_002 - 500), SeqUpdate::Jump);
+    }
+
+    #[test]
+    fn test_probation_restarts_on_jumps_and_gaps() {
+        // ---
+        let mut tracker = SeqTracker::new();
+        tracker.update(100);
+
+        // A jump on probation is not held back for confirmation
+        assert_eq!(
+            tracker.update(30_000),
+            SeqUpdate::Restart { extended: 30_000 }
+        );
+        assert_eq!(tracker.probation(), 1);
+
+        // Nor does a gap or a reordered packet end probation
+        tracker.update(30_002);
+        tracker.update(30_001);
+        assert_eq!(tracker.probation(), 1);
+        tracker.update(30_003);
+        assert_eq!(tracker.probation(), 0);
+
+        // Behind the first packet of all, there is no extended number
+        let mut tracker = SeqTracker::new();
+        tracker.update(3);
+        assert_eq!(tracker.update(65_535), SeqUpdate::Jump);
+    }
+}
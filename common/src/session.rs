@@ -0,0 +1,612 @@
+//! Session lifecycle tracking.
+//!
+//! Both ends of a stream move through the same coarse states: waiting for
+//! media, priming, streaming, paused, and so on. [`SessionTracker`] is a
+//! pure state machine fed timestamped events by the send and receive loops.
+//! It derives the current state from those events, accumulates the time
+//! spent in each state, and keeps a timeline of transitions for the
+//! shutdown summary.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::MetricsContext;
+
+/// Default gap between packets after which the session counts as paused
+pub const DEFAULT_PAUSE_AFTER: Duration = Duration::from_millis(250);
+
+/// Default window over which the concealed share of frames is measured
+pub const DEFAULT_CONCEALMENT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Default concealed share of frames that makes the session concealment-heavy
+pub const DEFAULT_CONCEALMENT_THRESHOLD: f64 = 0.05;
+
+/// Default time the session stays degraded after the last disruption
+pub const DEFAULT_DEGRADED_HOLD: Duration = Duration::from_secs(5);
+
+/// Frames the window must hold before its concealed share counts
+const MIN_WINDOW_FRAMES: usize = 25;
+
+/// Transitions kept for the timeline; older ones are dropped first
+const MAX_TIMELINE: usize = 1024;
+
+/// Interval between metric updates while the state is unchanged
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Coarse state of a send or receive session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionState {
+    // ---
+    /// No media sent or received yet
+    Connecting,
+
+    /// Media arriving, but playout waits for the jitter buffer to fill
+    Priming,
+
+    /// Media flowing normally
+    Streaming,
+
+    /// No packet for longer than [`SessionConfig::pause_after`] (silence
+    /// suppression, a paused source, or an outage)
+    Paused,
+
+    /// More than [`SessionConfig::concealment_threshold`] of recent frames
+    /// were concealed
+    Concealing,
+
+    /// Recently disrupted (pacing stall, buffer resync, stale decoder)
+    Degraded,
+
+    /// The stream ended and what is left is being flushed
+    Draining,
+}
+
+impl SessionState {
+    // ---
+    /// Every state, in lifecycle order.
+    pub const ALL: [SessionState; 7] = [
+        SessionState::Connecting,
+        SessionState::Priming,
+        SessionState::Streaming,
+        SessionState::Paused,
+        SessionState::Concealing,
+        SessionState::Degraded,
+        SessionState::Draining,
+    ];
+
+    /// Returns the state's metric label.
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            SessionState::Connecting => "connecting",
+            SessionState::Priming => "priming",
+            SessionState::Streaming => "streaming",
+            SessionState::Paused => "paused",
+            SessionState::Concealing => "concealing",
+            SessionState::Degraded => "degraded",
+            SessionState::Draining => "draining",
+        }
+    }
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// Signal from a send or receive loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    // ---
+    /// A media packet was sent or received
+    Packet,
+
+    /// Playout is waiting for the jitter buffer to fill
+    Priming,
+
+    /// The jitter buffer primed and playout started
+    Primed,
+
+    /// A frame was played out; `concealed` if loss concealment produced it
+    Frame { concealed: bool },
+
+    /// Something disrupted the stream: a pacing stall, a jitter buffer
+    /// resync, or a loss run long enough to leave the decoder stale
+    Disrupted,
+
+    /// The stream ended and what is left is being flushed
+    Draining,
+}
+
+/// Thresholds for deriving the session state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionConfig {
+    // ---
+    /// Gap between packets after which the session counts as paused
+    pub pause_after: Duration,
+
+    /// Window over which the concealed share of frames is measured
+    pub concealment_window: Duration,
+
+    /// Concealed share of frames (0.0-1.0) above which the session is
+    /// concealment-heavy
+    pub concealment_threshold: f64,
+
+    /// Time the session stays degraded after the last disruption
+    pub degraded_hold: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            pause_after: DEFAULT_PAUSE_AFTER,
+            concealment_window: DEFAULT_CONCEALMENT_WINDOW,
+            concealment_threshold: DEFAULT_CONCEALMENT_THRESHOLD,
+            degraded_hold: DEFAULT_DEGRADED_HOLD,
+        }
+    }
+}
+
+/// One state change in the session timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    // ---
+    /// Time since the session started
+    pub at: Duration,
+
+    /// State left
+    pub from: SessionState,
+
+    /// State entered
+    pub to: SessionState,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(
+            f,
+            "+{:.3}s {} -> {}",
+            self.at.as_secs_f64(),
+            self.from,
+            self.to
+        )
+    }
+}
+
+/// Derives the session state from loop events and tracks time in each.
+///
+/// The state follows from what the events established, in priority
+/// order: draining, connecting (no packet yet), paused (packet gap),
+/// priming, degraded (within the hold after a disruption), concealing,
+/// and otherwise streaming. Pauses and the end of a degraded period are
+/// placed at the instant they happened, not when the next event arrives,
+/// so no timer is needed.
+///
+/// # Example
+///
+/// ```
+/// use rtp_opus_common::{SessionConfig, SessionEvent, SessionState, SessionTracker};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let mut session = SessionTracker::new(SessionConfig::default(), start);
+/// session.record(SessionEvent::Packet, start + Duration::from_millis(100));
+/// assert_eq!(session.state(), SessionState::Streaming);
+/// assert_eq!(session.time_in(SessionState::Connecting), Duration::from_millis(100));
+/// ```
+#[derive(Debug)]
+pub struct SessionTracker {
+    // ---
+    config: SessionConfig,
+
+    /// When the session started
+    start: Instant,
+
+    /// Current state, and the instant its time was last accounted
+    state: SessionState,
+    since: Instant,
+
+    /// Time spent in each state, indexed like [`SessionState::ALL`]
+    totals: [Duration; 7],
+
+    /// Most recent transitions, oldest first
+    timeline: VecDeque<Transition>,
+
+    /// Transitions dropped from the front of `timeline`
+    timeline_dropped: usize,
+
+    /// Conditions the state is derived from
+    last_packet: Option<Instant>,
+    primed: bool,
+    draining: bool,
+    degraded_until: Option<Instant>,
+
+    /// Frames played within the concealment window, and whether each was
+    /// concealed
+    frames: VecDeque<(Instant, bool)>,
+
+    /// State and time of the last metric update
+    published: Option<(SessionState, Instant)>,
+}
+
+impl SessionTracker {
+    // ---
+    /// Creates a tracker for a session starting at `now`, in
+    /// [`SessionState::Connecting`].
+    pub fn new(config: SessionConfig, now: Instant) -> Self {
+        // ---
+        Self {
+            config,
+            start: now,
+            state: SessionState::Connecting,
+            since: now,
+            totals: [Duration::ZERO; 7],
+            timeline: VecDeque::new(),
+            timeline_dropped: 0,
+            last_packet: None,
+            primed: true,
+            draining: false,
+            degraded_until: None,
+            frames: VecDeque::new(),
+            published: None,
+        }
+    }
+
+    /// Records `event`, which happened at `now`.
+    pub fn record(&mut self, event: SessionEvent, now: Instant) {
+        // ---
+        self.advance(now);
+        match event {
+            SessionEvent::Packet => self.last_packet = Some(now),
+            SessionEvent::Priming => self.primed = false,
+            SessionEvent::Primed => self.primed = true,
+            SessionEvent::Frame { concealed } => self.frames.push_back((now, concealed)),
+            SessionEvent::Disrupted => {
+                self.degraded_until = Some(now + self.config.degraded_hold);
+            }
+            SessionEvent::Draining => self.draining = true,
+        }
+        self.enter(self.derive(now), now);
+    }
+
+    /// Brings the state up to date at `now` without a new event.
+    pub fn advance(&mut self, now: Instant) {
+        // ---
+        // Conditions that lapsed on their own since the last update
+        let mut lapsed = [
+            self.last_packet.map(|at| at + self.config.pause_after),
+            self.degraded_until,
+        ];
+        lapsed.sort();
+        for at in lapsed.into_iter().flatten() {
+            if at > self.since && at <= now {
+                self.enter(self.derive(at), at);
+            }
+        }
+        self.enter(self.derive(now), now);
+
+        if let Some(horizon) = now.checked_sub(self.config.concealment_window) {
+            while self.frames.front().is_some_and(|&(at, _)| at <= horizon) {
+                self.frames.pop_front();
+            }
+        }
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> SessionState {
+        // ---
+        self.state
+    }
+
+    /// Returns the time spent in `state` up to the last update.
+    pub fn time_in(&self, state: SessionState) -> Duration {
+        // ---
+        self.totals[state as usize]
+    }
+
+    /// Returns the recorded transitions, oldest first.
+    ///
+    /// Only the most recent transitions are kept; see
+    /// [`timeline_dropped`](Self::timeline_dropped).
+    pub fn timeline(&self) -> impl Iterator<Item = &Transition> {
+        // ---
+        self.timeline.iter()
+    }
+
+    /// Returns the number of early transitions no longer in the timeline.
+    pub fn timeline_dropped(&self) -> usize {
+        // ---
+        self.timeline_dropped
+    }
+
+    /// Exports the state and time in each state to `metrics`.
+    ///
+    /// Cheap to call on every loop iteration: metrics are only touched when
+    /// the state changed or a second has passed since the last update.
+    pub fn publish(&mut self, metrics: &MetricsContext, now: Instant) {
+        // ---
+        let due = self.published.is_none_or(|(state, at)| {
+            state != self.state || now.saturating_duration_since(at) >= PUBLISH_INTERVAL
+        });
+        if !due {
+            return;
+        }
+
+        for state in SessionState::ALL {
+            let label = [state.as_str()];
+            metrics
+                .session_state
+                .with_label_values(&label)
+                .set((state == self.state) as i64);
+
+            let seconds = metrics
+                .session_state_seconds_total
+                .with_label_values(&label);
+            let unpublished = self.time_in(state).as_secs_f64() - seconds.get();
+            if unpublished > 0.0 {
+                seconds.inc_by(unpublished);
+            }
+        }
+        self.published = Some((self.state, now));
+    }
+
+    /// Derives the state from the conditions as they stood at `at`.
+    fn derive(&self, at: Instant) -> SessionState {
+        // ---
+        let Some(last_packet) = self.last_packet else {
+            return if self.draining {
+                SessionState::Draining
+            } else {
+                SessionState::Connecting
+            };
+        };
+
+        if self.draining {
+            SessionState::Draining
+        } else if at >= last_packet + self.config.pause_after {
+            SessionState::Paused
+        } else if !self.primed {
+            SessionState::Priming
+        } else if self.degraded_until.is_some_and(|until| at < until) {
+            SessionState::Degraded
+        } else if self.concealed_share(at) > self.config.concealment_threshold {
+            SessionState::Concealing
+        } else {
+            SessionState::Streaming
+        }
+    }
+
+    /// Returns the concealed share of frames in the window ending at `at`.
+    fn concealed_share(&self, at: Instant) -> f64 {
+        // ---
+        let horizon = at.checked_sub(self.config.concealment_window);
+        let (frames, concealed) = self
+            .frames
+            .iter()
+            .filter(|&&(t, _)| t <= at && horizon.is_none_or(|h| t > h))
+            .fold((0usize, 0usize), |(frames, concealed), &(_, c)| {
+                (frames + 1, concealed + c as usize)
+            });
+
+        if frames < MIN_WINDOW_FRAMES {
+            return 0.0;
+        }
+        concealed as f64 / frames as f64
+    }
+
+    /// Accounts time up to `at` to the current state, then enters `state`.
+    fn enter(&mut self, state: SessionState, at: Instant) {
+        // ---
+        let at = at.max(self.since);
+        self.totals[self.state as usize] += at - self.since;
+        self.since = at;
+        if state == self.state {
+            return;
+        }
+
+        // Several changes at one instant collapse into one transition
+        let offset = at - self.start;
+        match self.timeline.back_mut() {
+            Some(last) if last.at == offset => {
+                last.to = state;
+                if last.from == state {
+                    self.timeline.pop_back();
+                }
+            }
+            _ => {
+                if self.timeline.len() == MAX_TIMELINE {
+                    self.timeline.pop_front();
+                    self.timeline_dropped += 1;
+                }
+                self.timeline.push_back(Transition {
+                    at: offset,
+                    from: self.state,
+                    to: state,
+                });
+            }
+        }
+        self.state = state;
+    }
+}
+
+impl fmt::Display for SessionTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        let mut first = true;
+        for state in SessionState::ALL {
+            let time = self.time_in(state);
+            if time.is_zero() {
+                continue;
+            }
+            if !first {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} {:.1}s", state, time.as_secs_f64())?;
+            first = false;
+        }
+        if first {
+            f.write_str("no time recorded")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Records one packet and one frame every 20ms over `[from, to)`.
+    fn stream(
+        session: &mut SessionTracker,
+        start: Instant,
+        from: u64,
+        to: u64,
+        concealed: impl Fn(u64) -> bool,
+    ) {
+        // ---
+        for t in (from..to).step_by(20) {
+            session.record(SessionEvent::Packet, start + ms(t));
+            session.record(
+                SessionEvent::Frame {
+                    concealed: concealed(t),
+                },
+                start + ms(t),
+            );
+        }
+    }
+
+    fn states(session: &SessionTracker) -> Vec<(u64, SessionState)> {
+        session
+            .timeline()
+            .map(|t| (t.at.as_millis() as u64, t.to))
+            .collect()
+    }
+
+    #[test]
+    fn test_receiver_lifecycle_with_pause() {
+        // ---
+        let start = Instant::now();
+        let mut session = SessionTracker::new(SessionConfig::default(), start);
+
+        // First packet at 100ms; the buffer primes 60ms later
+        session.record(SessionEvent::Packet, start + ms(100));
+        session.record(SessionEvent::Priming, start + ms(100));
+        session.record(SessionEvent::Packet, start + ms(120));
+        session.record(SessionEvent::Primed, start + ms(160));
+        stream(&mut session, start, 160, 1000, |_| false);
+
+        // One second of silence, then the stream resumes and ends
+        stream(&mut session, start, 2000, 3000, |_| false);
+        session.record(SessionEvent::Draining, start + ms(3500));
+
+        use SessionState::*;
+        assert_eq!(
+            states(&session),
+            vec![
+                (100, Priming),
+                (160, Streaming),
+                (1230, Paused),
+                (2000, Streaming),
+                (3230, Paused),
+                (3500, Draining),
+            ]
+        );
+        assert_eq!(session.time_in(Connecting), ms(100));
+        assert_eq!(session.time_in(Priming), ms(60));
+        assert_eq!(session.time_in(Paused), ms(770 + 270));
+        assert_eq!(session.time_in(Streaming), ms(1070 + 1230));
+        assert_eq!(session.state(), Draining);
+        assert_eq!(
+            session.to_string(),
+            "connecting 0.1s, priming 0.1s, streaming 2.3s, paused 1.0s"
+        );
+    }
+
+    #[test]
+    fn test_concealment_heavy_over_window() {
+        // ---
+        let start = Instant::now();
+        let mut session = SessionTracker::new(SessionConfig::default(), start);
+
+        // 2% concealed is fine, 10% is concealment-heavy
+        stream(&mut session, start, 0, 4000, |t| t % 1000 == 0);
+        assert_eq!(session.state(), SessionState::Streaming);
+        stream(&mut session, start, 4000, 10_000, |t| t % 200 == 0);
+        assert_eq!(session.state(), SessionState::Concealing);
+
+        // Clean audio brings the share back down as the window slides
+        stream(&mut session, start, 10_000, 30_000, |_| false);
+        assert_eq!(session.state(), SessionState::Streaming);
+        assert!(session.time_in(SessionState::Concealing) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_disruption_holds_degraded() {
+        // ---
+        let start = Instant::now();
+        let mut session = SessionTracker::new(SessionConfig::default(), start);
+
+        stream(&mut session, start, 0, 1000, |_| false);
+        session.record(SessionEvent::Disrupted, start + ms(1000));
+        stream(&mut session, start, 1000, 8000, |_| false);
+
+        use SessionState::*;
+        assert_eq!(
+            states(&session),
+            vec![(0, Streaming), (1000, Degraded), (6000, Streaming)]
+        );
+        assert_eq!(session.time_in(Degraded), DEFAULT_DEGRADED_HOLD);
+    }
+
+    #[test]
+    fn test_same_instant_changes_collapse() {
+        // ---
+        let start = Instant::now();
+        let mut session = SessionTracker::new(SessionConfig::default(), start);
+
+        // Packet then Priming at one instant never shows Streaming
+        session.record(SessionEvent::Packet, start + ms(50));
+        session.record(SessionEvent::Priming, start + ms(50));
+        assert_eq!(states(&session), vec![(50, SessionState::Priming)]);
+
+        // Going and coming back at one instant leaves no trace
+        session.record(SessionEvent::Primed, start + ms(80));
+        session.record(SessionEvent::Priming, start + ms(80));
+        assert_eq!(states(&session), vec![(50, SessionState::Priming)]);
+    }
+
+    #[test]
+    fn test_publish_exports_state_and_seconds() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let start = Instant::now();
+        let mut session = SessionTracker::new(SessionConfig::default(), start);
+
+        session.record(SessionEvent::Packet, start + ms(500));
+        session.publish(&metrics, start + ms(500));
+        stream(&mut session, start, 500, 2500, |_| false);
+        session.publish(&metrics, start + ms(2500));
+
+        let gauge = |state: &str| metrics.session_state.with_label_values(&[state]).get();
+        let seconds = |state: &str| {
+            metrics
+                .session_state_seconds_total
+                .with_label_values(&[state])
+                .get()
+        };
+        assert_eq!(gauge("streaming"), 1);
+        assert_eq!(gauge("connecting"), 0);
+        assert!((seconds("connecting") - 0.5).abs() < 1e-9);
+        assert!((seconds("streaming") - 1.98).abs() < 1e-9);
+    }
+}
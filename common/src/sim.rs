@@ -1,13 +1,17 @@
 //! Network simulator for testing resilience.
 //!
 //! Provides in-process network condition simulation including packet loss,
-//! jitter, and reordering for integration testing.
+//! jitter, reordering, and payload corruption for integration testing and demos. Enabled by the
+//! `sim` feature.
 
 use rand::Rng;
-use rtp_opus_common::RtpPacket;
 use std::collections::VecDeque;
+use std::ops::Range;
 use std::time::{Duration, Instant};
 
+use crate::rtp::RtpPacket;
+use crate::trace::TraceRecord;
+
 /// Network simulator configuration.
 #[derive(Debug, Clone)]
 pub struct NetworkSimulatorConfig {
@@ -21,6 +25,9 @@ pub struct NetworkSimulatorConfig {
     /// Packet reordering rate (0.0 to 1.0)
     pub reorder_rate: f64,
 
+    /// Rate at which a delivered packet has one payload bit flipped (0.0 to 1.0)
+    pub corrupt_rate: f64,
+
     /// Random seed for deterministic testing
     pub seed: Option<u64>,
 }
@@ -32,6 +39,7 @@ impl Default for NetworkSimulatorConfig {
             loss_rate: 0.0,
             jitter_ms: 0,
             reorder_rate: 0.0,
+            corrupt_rate: 0.0,
             seed: None,
         }
     }
@@ -46,25 +54,26 @@ struct DelayedPacket {
 
 /// Simulates network conditions for testing.
 ///
-/// Applies configurable packet loss, jitter, and reordering to packets
-/// passing through it. Useful for testing receiver resilience.
+/// Applies configurable packet loss, jitter, reordering, and corruption to
+/// packets passing through it. Useful for testing receiver resilience.
 ///
 /// # Example
 ///
 /// ```no_run
-/// use tests::network_simulator::{NetworkSimulator, NetworkSimulatorConfig};
+/// use rtp_opus_common::{NetworkSimulator, NetworkSimulatorConfig, RtpPacket};
 ///
 /// let config = NetworkSimulatorConfig {
 ///     loss_rate: 0.1,    // 10% loss
 ///     jitter_ms: 20,      // ±20ms jitter
 ///     reorder_rate: 0.05, // 5% reordering
+///     corrupt_rate: 0.0,  // No bit errors
 ///     seed: Some(42),     // Deterministic
 /// };
 ///
 /// let mut sim = NetworkSimulator::new(config);
 ///
 /// // Process packet
-/// sim.send(packet);
+/// sim.send(RtpPacket::new(0, 0, 0x12345678, vec![1, 2, 3]));
 ///
 /// // Retrieve ready packets
 /// while let Some(p) = sim.receive() {
@@ -80,6 +89,8 @@ pub struct NetworkSimulator {
     packets_lost: u64,
     packets_delayed: u64,
     packets_reordered: u64,
+    packets_corrupted: u64,
+    outages: Vec<Range<u64>>,
 }
 
 impl NetworkSimulator {
@@ -103,26 +114,68 @@ impl NetworkSimulator {
             packets_lost: 0,
             packets_delayed: 0,
             packets_reordered: 0,
+            packets_corrupted: 0,
+            outages: Vec::new(),
         }
     }
 
+    /// Takes the link down for a span of packets.
+    ///
+    /// Every packet whose zero-based send index falls in `packets` is
+    /// dropped, on top of the random loss. At 20ms per packet, `100..200`
+    /// is a two-second outage starting two seconds into the stream.
+    pub fn add_outage(&mut self, packets: Range<u64>) {
+        // ---
+        self.outages.push(packets);
+    }
+
     /// Sends a packet through the simulator.
     ///
-    /// Applies loss, jitter, and reordering based on configuration.
-    /// Packet may be delayed or dropped.
+    /// Applies loss, jitter, reordering, and corruption based on
+    /// configuration. Packet may be delayed, dropped, or damaged.
     pub fn send(&mut self, packet: RtpPacket) {
         // ---
+        self.send_at(packet, Instant::now());
+    }
+
+    /// Replays a trace record: sends its packet as of `start` plus its
+    /// arrival time, so a captured trace keeps its original timing.
+    pub fn send_record(&mut self, record: TraceRecord, start: Instant) {
+        // ---
+        let (packet, arrival) = RtpPacket::from_trace_record(record);
+        self.send_at(packet, start + arrival);
+    }
+
+    /// Sends a packet as if at `now`, which [`receive_at`](Self::receive_at)
+    /// measures delivery against. Lets tests and replay tooling drive the
+    /// simulator on their own clock.
+    pub fn send_at(&mut self, mut packet: RtpPacket, now: Instant) {
+        // ---
+        let index = self.packets_sent;
         self.packets_sent += 1;
 
-        // Packet loss
+        // Scheduled outage, then random loss
+        if self.outages.iter().any(|outage| outage.contains(&index)) {
+            self.packets_lost += 1;
+            return;
+        }
         if self.should_drop() {
             self.packets_lost += 1;
             return;
         }
 
+        // Bit error in the payload, as a faulty link or middlebox would cause
+        if !packet.payload.is_empty() && self.should_corrupt() {
+            self.packets_corrupted += 1;
+            let bit = self.rng.gen_range(0..packet.payload.len() * 8);
+            let mut payload = Vec::from(std::mem::take(&mut packet.payload));
+            payload[bit / 8] ^= 1 << (bit % 8);
+            packet.payload = payload.into();
+        }
+
         // Calculate delivery time with jitter
         let delay = self.calculate_delay();
-        let delivery_time = Instant::now() + delay;
+        let delivery_time = now + delay;
 
         // Reordering: sometimes hold packet back
         if self.should_reorder() && !self.delayed_queue.is_empty() {
@@ -153,8 +206,12 @@ impl NetworkSimulator {
     /// Returns `None` if no packets are ready yet.
     pub fn receive(&mut self) -> Option<RtpPacket> {
         // ---
-        let now = Instant::now();
+        self.receive_at(Instant::now())
+    }
 
+    /// Retrieves the next packet ready for delivery as of `now`.
+    pub fn receive_at(&mut self, now: Instant) -> Option<RtpPacket> {
+        // ---
         // Check if front packet is ready
         if let Some(delayed) = self.delayed_queue.front() {
             if delayed.delivery_time <= now {
@@ -179,6 +236,7 @@ impl NetworkSimulator {
             packets_lost: self.packets_lost,
             packets_delayed: self.packets_delayed,
             packets_reordered: self.packets_reordered,
+            packets_corrupted: self.packets_corrupted,
             loss_rate: if self.packets_sent > 0 {
                 self.packets_lost as f64 / self.packets_sent as f64
             } else {
@@ -199,6 +257,12 @@ impl NetworkSimulator {
         self.rng.gen_bool(self.config.reorder_rate)
     }
 
+    /// Determines if packet should be corrupted.
+    fn should_corrupt(&mut self) -> bool {
+        // ---
+        self.rng.gen_bool(self.config.corrupt_rate)
+    }
+
     /// Calculates random delay for jitter.
     fn calculate_delay(&mut self) -> Duration {
         // ---
@@ -219,6 +283,7 @@ pub struct NetworkSimulatorStats {
     pub packets_lost: u64,
     pub packets_delayed: u64,
     pub packets_reordered: u64,
+    pub packets_corrupted: u64,
     pub loss_rate: f64,
 }
 
@@ -269,6 +334,49 @@ mod tests {
         assert_eq!(stats.loss_rate, 1.0);
     }
 
+    #[test]
+    fn test_outage_drops_packet_span() {
+        // ---
+        let mut sim = NetworkSimulator::new(NetworkSimulatorConfig::default());
+        sim.add_outage(3..6);
+
+        for i in 0..8 {
+            sim.send(make_packet(i));
+        }
+
+        let delivered: Vec<u16> = std::iter::from_fn(|| sim.receive())
+            .map(|p| p.sequence)
+            .collect();
+        assert_eq!(delivered, vec![0, 1, 2, 6, 7]);
+        assert_eq!(sim.stats().packets_lost, 3);
+    }
+
+    #[test]
+    fn test_replays_records_on_their_timing() {
+        // ---
+        let mut sim = NetworkSimulator::new(NetworkSimulatorConfig::default());
+        let start = Instant::now();
+        for seq in 0..3 {
+            let arrival = Duration::from_millis(20 * seq as u64);
+            sim.send_record(make_packet(seq).to_trace_record(arrival), start);
+        }
+
+        assert_eq!(sim.receive_at(start).unwrap().sequence, 0);
+        assert!(sim.receive_at(start + Duration::from_millis(19)).is_none());
+        assert_eq!(
+            sim.receive_at(start + Duration::from_millis(40))
+                .unwrap()
+                .sequence,
+            1
+        );
+        assert_eq!(
+            sim.receive_at(start + Duration::from_millis(40))
+                .unwrap()
+                .sequence,
+            2
+        );
+    }
+
     #[test]
     fn test_jitter() {
         // ---
@@ -314,4 +422,32 @@ mod tests {
         // Same seed should give same results
         assert_eq!(stats1.packets_lost, stats2.packets_lost);
     }
+
+    #[test]
+    fn test_corruption_flips_one_payload_bit() {
+        // ---
+        let config = NetworkSimulatorConfig {
+            corrupt_rate: 1.0,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let mut sim = NetworkSimulator::new(config);
+
+        for i in 0..20 {
+            sim.send(make_packet(i));
+        }
+
+        let original = make_packet(0).payload;
+        while let Some(packet) = sim.receive() {
+            let flipped: u32 = packet
+                .payload
+                .iter()
+                .zip(&original)
+                .map(|(a, b)| (a ^ b).count_ones())
+                .sum();
+            assert_eq!(flipped, 1, "seq {}", packet.sequence);
+        }
+        assert_eq!(sim.stats().packets_corrupted, 20);
+        assert_eq!(sim.stats().packets_lost, 0);
+    }
 }
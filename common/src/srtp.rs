@@ -0,0 +1,466 @@
+//! SRTP protection of RTP packets (RFC 3711).
+//!
+//! Implements the default SRTP transform, AES_CM_128_HMAC_SHA1_80: the
+//! payload is encrypted with AES-128 in counter mode, and the header and
+//! encrypted payload are authenticated with HMAC-SHA1, truncated to an
+//! 80-bit tag appended to the packet. Both ends share a master key and
+//! salt up front (no key exchange); the session keys are derived from them
+//! with a key derivation rate of zero.
+//!
+//! The 16-bit RTP sequence number is extended to a 48-bit packet index by
+//! a rollover counter (ROC) kept per SSRC, which counts sequence
+//! wraparounds. The receiver estimates the index of each packet from its
+//! sequence number (RFC 3711 Appendix A) and only moves its counter on
+//! once the packet has authenticated, so forged packets cannot desync it.
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use aes::Aes128;
+use anyhow::{Context, Result};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::collections::HashMap;
+
+use crate::rtp::RTP_HEADER_LEN;
+
+/// Master key length for AES-128
+pub const SRTP_MASTER_KEY_LEN: usize = 16;
+
+/// Master salt length
+pub const SRTP_MASTER_SALT_LEN: usize = 14;
+
+/// Authentication tag appended to each packet (HMAC-SHA1 truncated to 80
+/// bits)
+pub const SRTP_AUTH_TAG_LEN: usize = 10;
+
+/// Session authentication key length (HMAC-SHA1)
+const AUTH_KEY_LEN: usize = 20;
+
+/// Key derivation labels (RFC 3711 §4.3.2)
+const LABEL_CIPHER_KEY: u8 = 0;
+const LABEL_AUTH_KEY: u8 = 1;
+const LABEL_SALT: u8 = 2;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type HmacSha1 = Hmac<Sha1>;
+
+/// Pre-shared SRTP master key and salt.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SrtpKey {
+    // ---
+    pub key: [u8; SRTP_MASTER_KEY_LEN],
+    pub salt: [u8; SRTP_MASTER_SALT_LEN],
+}
+
+impl SrtpKey {
+    // ---
+    /// Parses the key and salt from base64, the form SDES (RFC 4568) uses
+    /// for the `inline:` key parameter: the 16 key bytes followed by the
+    /// 14 salt bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `value` is not base64 or does not decode to 30
+    /// bytes.
+    pub fn from_base64(value: &str) -> Result<Self> {
+        // ---
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(value.trim())
+            .context("SRTP key is not valid base64")?;
+        if bytes.len() != SRTP_MASTER_KEY_LEN + SRTP_MASTER_SALT_LEN {
+            anyhow::bail!(
+                "SRTP key decodes to {} bytes, expected {} (16-byte key and 14-byte salt)",
+                bytes.len(),
+                SRTP_MASTER_KEY_LEN + SRTP_MASTER_SALT_LEN
+            );
+        }
+        let (key, salt) = bytes.split_at(SRTP_MASTER_KEY_LEN);
+        Ok(Self {
+            key: key.try_into().expect("key length"),
+            salt: salt.try_into().expect("salt length"),
+        })
+    }
+}
+
+impl std::fmt::Debug for SrtpKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ---
+        // Keep key material out of logs
+        f.write_str("SrtpKey(..)")
+    }
+}
+
+/// Rollover state for one SSRC.
+#[derive(Debug, Clone, Copy)]
+struct SsrcState {
+    // ---
+    /// Sequence number wraparounds so far
+    roc: u32,
+
+    /// Highest sequence number seen in the current rollover
+    highest: u16,
+}
+
+/// SRTP session keys and per-SSRC rollover counters for one direction of
+/// a stream.
+///
+/// The sender and receiver each keep their own context, created from the
+/// same [`SrtpKey`].
+pub struct SrtpContext {
+    // ---
+    cipher_key: [u8; SRTP_MASTER_KEY_LEN],
+    cipher_salt: [u8; SRTP_MASTER_SALT_LEN],
+    auth_key: [u8; AUTH_KEY_LEN],
+    streams: HashMap<u32, SsrcState>,
+}
+
+impl SrtpContext {
+    // ---
+    /// Derives the session keys from `master`.
+    pub fn new(master: &SrtpKey) -> Self {
+        // ---
+        let mut cipher_key = [0u8; SRTP_MASTER_KEY_LEN];
+        let mut cipher_salt = [0u8; SRTP_MASTER_SALT_LEN];
+        let mut auth_key = [0u8; AUTH_KEY_LEN];
+        derive(master, LABEL_CIPHER_KEY, &mut cipher_key);
+        derive(master, LABEL_AUTH_KEY, &mut auth_key);
+        derive(master, LABEL_SALT, &mut cipher_salt);
+        Self {
+            cipher_key,
+            cipher_salt,
+            auth_key,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Encrypts the payload of the serialized RTP packet in `packet` and
+    /// appends the authentication tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `packet` is shorter than its RTP header.
+    pub fn protect(&mut self, packet: &mut Vec<u8>) -> Result<()> {
+        // ---
+        let header_len = header_len(packet)?;
+        let (ssrc, sequence) = ids(packet);
+        let (roc, state) = self.estimate(ssrc, sequence);
+        self.streams.insert(ssrc, state);
+
+        let index = (u64::from(roc) << 16) | u64::from(sequence);
+        self.apply_keystream(ssrc, index, &mut packet[header_len..]);
+        let tag = self.mac(packet, roc).finalize().into_bytes();
+        packet.extend_from_slice(&tag[..SRTP_AUTH_TAG_LEN]);
+        Ok(())
+    }
+
+    /// Authenticates the SRTP packet in `packet` and decrypts its payload
+    /// in place. Returns the length of the RTP packet left at the start of
+    /// `packet`, without the tag.
+    ///
+    /// The rollover counter is only updated for packets that
+    /// authenticate.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the packet is too short or its tag does not match;
+    /// it is then left as it was.
+    pub fn unprotect(&mut self, packet: &mut [u8]) -> Result<usize> {
+        // ---
+        let Some(len) = packet.len().checked_sub(SRTP_AUTH_TAG_LEN) else {
+            anyhow::bail!("{} byte packet is too short for SRTP", packet.len());
+        };
+        let header_len = header_len(&packet[..len])?;
+        let (ssrc, sequence) = ids(packet);
+        let (roc, state) = self.estimate(ssrc, sequence);
+
+        self.mac(&packet[..len], roc)
+            .verify_truncated_left(&packet[len..])
+            .map_err(|_| anyhow::anyhow!("SRTP authentication failed"))?;
+
+        self.streams.insert(ssrc, state);
+        let index = (u64::from(roc) << 16) | u64::from(sequence);
+        self.apply_keystream(ssrc, index, &mut packet[header_len..len]);
+        Ok(len)
+    }
+
+    /// Estimates the rollover counter of `sequence` for `ssrc` (RFC 3711
+    /// Appendix A). Returns it with the state to keep if the packet is
+    /// accepted.
+    fn estimate(&self, ssrc: u32, sequence: u16) -> (u32, SsrcState) {
+        // ---
+        let Some(state) = self.streams.get(&ssrc).copied() else {
+            // The first packet of a stream starts the first rollover
+            let state = SsrcState {
+                roc: 0,
+                highest: sequence,
+            };
+            return (0, state);
+        };
+
+        let (seq, highest) = (i32::from(sequence), i32::from(state.highest));
+        let roc = if highest < 0x8000 {
+            if seq - highest > 0x8000 {
+                // A late packet from before the last wrap
+                state.roc.wrapping_sub(1)
+            } else {
+                state.roc
+            }
+        } else if highest - 0x8000 > seq {
+            // The sequence number wrapped
+            state.roc.wrapping_add(1)
+        } else {
+            state.roc
+        };
+
+        let newest =
+            roc == state.roc.wrapping_add(1) || (roc == state.roc && sequence > state.highest);
+        let next = if newest {
+            SsrcState {
+                roc,
+                highest: sequence,
+            }
+        } else {
+            state
+        };
+        (roc, next)
+    }
+
+    /// XORs `data` with the AES-CM keystream for packet `index` of `ssrc`
+    /// (RFC 3711 §4.1.1).
+    fn apply_keystream(&self, ssrc: u32, index: u64, data: &mut [u8]) {
+        // ---
+        let mut iv = [0u8; 16];
+        iv[..SRTP_MASTER_SALT_LEN].copy_from_slice(&self.cipher_salt);
+        for (byte, id) in iv[4..8].iter_mut().zip(ssrc.to_be_bytes()) {
+            *byte ^= id;
+        }
+        for (byte, id) in iv[8..14].iter_mut().zip(&index.to_be_bytes()[2..]) {
+            *byte ^= id;
+        }
+        Aes128Ctr::new(&self.cipher_key.into(), &iv.into()).apply_keystream(data);
+    }
+
+    /// Returns the HMAC over the authenticated portion of a packet and
+    /// its rollover counter (RFC 3711 §4.2).
+    fn mac(&self, authenticated: &[u8], roc: u32) -> HmacSha1 {
+        // ---
+        let mut mac = HmacSha1::new_from_slice(&self.auth_key).expect("any key length");
+        mac.update(authenticated);
+        mac.update(&roc.to_be_bytes());
+        mac
+    }
+}
+
+/// Fills `out` with the session key for `label`: the AES-CM keystream of
+/// the master key at IV (master salt XOR label) * 2^16 (RFC 3711 §4.3.1,
+/// key derivation rate zero).
+fn derive(master: &SrtpKey, label: u8, out: &mut [u8]) {
+    // ---
+    let mut iv = [0u8; 16];
+    iv[..SRTP_MASTER_SALT_LEN].copy_from_slice(&master.salt);
+    iv[7] ^= label;
+    out.fill(0);
+    Aes128Ctr::new(&master.key.into(), &iv.into()).apply_keystream(out);
+}
+
+/// Returns the RTP header length of `packet`, with its CSRC list and
+/// header extension: the part SRTP leaves unencrypted.
+fn header_len(packet: &[u8]) -> Result<usize> {
+    // ---
+    if packet.len() < RTP_HEADER_LEN {
+        anyhow::bail!("{} byte packet is too short for RTP", packet.len());
+    }
+    let csrc_count = usize::from(packet[0] & 0x0F);
+    let mut len = RTP_HEADER_LEN + 4 * csrc_count;
+    if packet[0] & 0x10 != 0 {
+        let words = packet
+            .get(len + 2..len + 4)
+            .map(|w| usize::from(u16::from_be_bytes([w[0], w[1]])))
+            .context("RTP header extension is truncated")?;
+        len += 4 + 4 * words;
+    }
+    if len > packet.len() {
+        anyhow::bail!("RTP header runs past the {} byte packet", packet.len());
+    }
+    Ok(len)
+}
+
+/// Returns the SSRC and sequence number of an RTP packet whose header has
+/// been checked.
+fn ids(packet: &[u8]) -> (u32, u16) {
+    // ---
+    let sequence = u16::from_be_bytes([packet[2], packet[3]]);
+    let ssrc = u32::from_be_bytes([packet[8], packet[9], packet[10], packet[11]]);
+    (ssrc, sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        // ---
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Master key and salt of the RFC 3711 and libsrtp test vectors
+    fn test_key() -> SrtpKey {
+        // ---
+        SrtpKey {
+            key: hex("E1F97A0D3E018BE0D64FA32C06DE4139").try_into().unwrap(),
+            salt: hex("0EC675AD498AFEEBB6960B3AABE6").try_into().unwrap(),
+        }
+    }
+
+    fn rtp(sequence: u16, ssrc: u32, payload: &[u8]) -> Vec<u8> {
+        // ---
+        let mut packet = vec![0x80, 0x0F];
+        packet.extend_from_slice(&sequence.to_be_bytes());
+        packet.extend_from_slice(&0xDECA_FBADu32.to_be_bytes());
+        packet.extend_from_slice(&ssrc.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn test_key_derivation_vectors() {
+        // ---
+        // RFC 3711 Appendix B.3
+        let context = SrtpContext::new(&test_key());
+        assert_eq!(
+            context.cipher_key.to_vec(),
+            hex("C61E7A93744F39EE10734AFE3FF7A087")
+        );
+        assert_eq!(
+            context.cipher_salt.to_vec(),
+            hex("30CBBC08863D8C85D49DB34A9AE1")
+        );
+        assert_eq!(
+            context.auth_key.to_vec(),
+            hex("CEBE321F6FF7716B6FD4AB49AF256A156D38BAA4")
+        );
+    }
+
+    #[test]
+    fn test_keystream_vector() {
+        // ---
+        // RFC 3711 Appendix B.2: session salt alone as the IV (SSRC and
+        // index zero)
+        let mut context = SrtpContext::new(&test_key());
+        context.cipher_key = hex("2B7E151628AED2A6ABF7158809CF4F3C").try_into().unwrap();
+        context.cipher_salt = hex("F0F1F2F3F4F5F6F7F8F9FAFBFCFD").try_into().unwrap();
+        let mut keystream = [0u8; 48];
+        context.apply_keystream(0, 0, &mut keystream);
+        assert_eq!(
+            keystream.to_vec(),
+            hex(concat!(
+                "E03EAD0935C95E80E166B16DD92B4EB4",
+                "D23513162B02D0F72A43A2FE4A5F97AB",
+                "41E95B3BB0A2E8DD477901E4FCA894C0"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_protect_matches_reference_and_round_trips() {
+        // ---
+        // libsrtp's AES_CM_128_HMAC_SHA1_80 test packet
+        let plaintext = rtp(0x1234, 0xCAFE_BABE, &[0xAB; 16]);
+        let mut packet = plaintext.clone();
+        SrtpContext::new(&test_key()).protect(&mut packet).unwrap();
+        assert_eq!(
+            packet,
+            hex(concat!(
+                "800f1234decafbadcafebabe",
+                "4e55dc4ce79978d88ca4d215949d2402",
+                "b78d6acc99ea179b8dbb"
+            ))
+        );
+
+        let len = SrtpContext::new(&test_key())
+            .unprotect(&mut packet)
+            .unwrap();
+        assert_eq!(packet[..len], plaintext[..]);
+    }
+
+    #[test]
+    fn test_tampered_packet_is_rejected() {
+        // ---
+        let mut sender = SrtpContext::new(&test_key());
+        let mut receiver = SrtpContext::new(&test_key());
+        let mut packet = rtp(7, 1, b"opus frame");
+        sender.protect(&mut packet).unwrap();
+
+        // Any flipped bit, in the header, payload, or tag, fails
+        for position in [3, RTP_HEADER_LEN + 2, packet.len() - 1] {
+            let mut tampered = packet.clone();
+            tampered[position] ^= 0x01;
+            assert!(receiver.unprotect(&mut tampered).is_err());
+        }
+
+        // So does a packet under another key
+        let other = SrtpKey {
+            key: [1; SRTP_MASTER_KEY_LEN],
+            salt: [2; SRTP_MASTER_SALT_LEN],
+        };
+        assert!(SrtpContext::new(&other)
+            .unprotect(&mut packet.clone())
+            .is_err());
+        assert!(receiver.unprotect(&mut [0u8; SRTP_AUTH_TAG_LEN]).is_err());
+
+        // The failures left no state behind
+        let len = receiver.unprotect(&mut packet).unwrap();
+        assert_eq!(&packet[RTP_HEADER_LEN..len], b"opus frame");
+    }
+
+    #[test]
+    fn test_rollover_counter_follows_sequence_wrap() {
+        // ---
+        let mut sender = SrtpContext::new(&test_key());
+        let mut receiver = SrtpContext::new(&test_key());
+        let mut late = None;
+        for sequence in (0xFFF0..=0xFFFF).chain(0..0x10u16) {
+            let mut packet = rtp(sequence, 5, &sequence.to_be_bytes());
+            sender.protect(&mut packet).unwrap();
+            if sequence == 0xFFFE {
+                // Arrives after the wrap
+                late = Some(packet);
+                continue;
+            }
+            let len = receiver.unprotect(&mut packet).unwrap();
+            assert_eq!(packet[RTP_HEADER_LEN..len], sequence.to_be_bytes());
+        }
+        assert_eq!(sender.streams[&5].roc, 1);
+        assert_eq!(receiver.streams[&5].roc, 1);
+
+        // A packet from before the wrap still authenticates with the old
+        // counter, and does not move the current one back
+        let mut late = late.unwrap();
+        let len = receiver.unprotect(&mut late).unwrap();
+        assert_eq!(late[RTP_HEADER_LEN..len], [0xFF, 0xFE]);
+        assert_eq!(receiver.streams[&5].roc, 1);
+        assert_eq!(receiver.streams[&5].highest, 0x0F);
+
+        // A receiver that joins after the wrap cannot know the counter:
+        // the packet does not authenticate, as RFC 3711 expects
+        let mut packet = rtp(0x20, 5, b"x");
+        sender.protect(&mut packet).unwrap();
+        assert!(SrtpContext::new(&test_key())
+            .unprotect(&mut packet)
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_from_base64() {
+        // ---
+        let key = SrtpKey::from_base64("4fl6DT4Bi+DWT6MsBt5BOQ7Gda1Jiv7rtpYLOqvm").unwrap();
+        assert_eq!(key, test_key());
+        assert_eq!(format!("{:?}", key), "SrtpKey(..)");
+        assert!(SrtpKey::from_base64("not base64!").is_err());
+        assert!(SrtpKey::from_base64("AAAA").is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Packet trace records for capture and replay tooling.
+//!
+//! A trace is a sequence of [`TraceRecord`]s: each packet with when it
+//! arrived (or was sent), counted from the start of the trace. With the
+//! `serde` feature, records and packets serialize to any serde format.
+//! Human-readable formats such as JSON carry payloads and header extension
+//! data as base64 strings, and arrival times as whole microseconds
+//! (`arrival_us`); binary formats such as CBOR carry raw bytes.
+//!
+//! Traces replay through the [`NetworkSimulator`](crate::NetworkSimulator)
+//! (`send_record`), and an rtpdump capture converts to one record per
+//! packet.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+
+use crate::rtp::RtpPacket;
+use crate::rtpdump::RtpdumpPacket;
+
+/// One packet of a trace and when it arrived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceRecord {
+    // ---
+    /// Time since the start of the trace
+    #[cfg_attr(feature = "serde", serde(rename = "arrival_us", with = "micros"))]
+    pub arrival: Duration,
+
+    /// The packet
+    pub packet: RtpPacket,
+}
+
+impl RtpPacket {
+    // ---
+    /// Returns a trace record of this packet arriving `arrival` into the
+    /// trace. The payload is shared, not copied.
+    pub fn to_trace_record(&self, arrival: Duration) -> TraceRecord {
+        // ---
+        TraceRecord {
+            arrival,
+            packet: self.clone(),
+        }
+    }
+
+    /// Returns the packet of a trace record and when it arrived.
+    pub fn from_trace_record(record: TraceRecord) -> (Self, Duration) {
+        // ---
+        (record.packet, record.arrival)
+    }
+}
+
+impl TryFrom<&RtpdumpPacket> for TraceRecord {
+    type Error = anyhow::Error;
+
+    /// Parses a captured packet, arriving at its offset into the capture.
+    fn try_from(captured: &RtpdumpPacket) -> Result<Self> {
+        // ---
+        let packet = RtpPacket::deserialize(&captured.data).with_context(|| {
+            format!("captured packet at {:?} is not whole RTP", captured.offset)
+        })?;
+        Ok(packet.to_trace_record(captured.offset))
+    }
+}
+
+/// Serde adapter for a [`Duration`] as whole microseconds.
+#[cfg(feature = "serde")]
+mod micros {
+    // ---
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        // ---
+        serializer.serialize_u64(duration.as_micros().try_into().unwrap_or(u64::MAX))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        // ---
+        u64::deserialize(deserializer).map(Duration::from_micros)
+    }
+}
+
+/// Serde adapter for bytes: a base64 string in human-readable formats,
+/// raw bytes in binary ones.
+#[cfg(feature = "serde")]
+pub(crate) mod base64_bytes {
+    // ---
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::de::{self, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(
+        data: &impl AsRef<[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        // ---
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(data))
+        } else {
+            serializer.serialize_bytes(data.as_ref())
+        }
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: From<Vec<u8>>,
+    {
+        // ---
+        let data = if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BytesVisitor)?
+        } else {
+            deserializer.deserialize_byte_buf(BytesVisitor)?
+        };
+        Ok(T::from(data))
+    }
+
+    /// Accepts base64 text, raw bytes, or a sequence of byte values.
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            // ---
+            f.write_str("base64 text or bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, text: &str) -> Result<Vec<u8>, E> {
+            // ---
+            STANDARD.decode(text).map_err(E::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, data: &[u8]) -> Result<Vec<u8>, E> {
+            // ---
+            Ok(data.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, data: Vec<u8>) -> Result<Vec<u8>, E> {
+            // ---
+            Ok(data)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+            // ---
+            let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                data.push(byte);
+            }
+            Ok(data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    #[cfg(feature = "serde")]
+    use crate::rtp::RtpHeaderExtension;
+
+    #[test]
+    fn test_trace_record_helpers() {
+        // ---
+        let packet = RtpPacket::new(7, 960, 0x1234_5678, vec![0x78; 40]);
+        let record = packet.to_trace_record(Duration::from_millis(140));
+        assert_eq!(record.packet.payload.as_ptr(), packet.payload.as_ptr());
+        assert_eq!(
+            RtpPacket::from_trace_record(record),
+            (packet.clone(), Duration::from_millis(140))
+        );
+
+        let captured = RtpdumpPacket {
+            offset: Duration::from_millis(20),
+            wire_len: 52,
+            data: packet.serialize().unwrap(),
+        };
+        let record = TraceRecord::try_from(&captured).unwrap();
+        assert_eq!((record.arrival, record.packet), (captured.offset, packet));
+        let truncated = RtpdumpPacket {
+            data: captured.data[..8].to_vec(),
+            ..captured
+        };
+        assert!(TraceRecord::try_from(&truncated).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    fn packet_using_every_field() -> RtpPacket {
+        // ---
+        let mut packet = RtpPacket::new(0xFFFF, 0xDEAD_BEEF, 0x5C1A_0F33, vec![0x78, 0x0B, 0xE4])
+            .with_payload_type(111)
+            .unwrap();
+        packet.marker = true;
+        packet.csrc = vec![0x1001, 0x1002];
+        packet.extension = Some(RtpHeaderExtension {
+            profile: 0xBEDE,
+            data: vec![0x10, 0x8A, 0, 0],
+        });
+        packet.padding = 4;
+        packet
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_roundtrip_with_empty_and_large_payloads() {
+        // ---
+        let large: Vec<u8> = (0..65_000u32).map(|i| (i * 7) as u8).collect();
+        let packets = [
+            RtpPacket::new(1, 0, 7, Vec::new()),
+            RtpPacket::new(2, 960, 7, large),
+            packet_using_every_field(),
+        ];
+        for (index, packet) in packets.into_iter().enumerate() {
+            let record = packet.to_trace_record(Duration::from_micros(20_000 * index as u64 + 3));
+            let json = serde_json::to_string(&record).unwrap();
+            let parsed: TraceRecord = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, record, "{json:.200}");
+
+            // The packet alone, too
+            let json = serde_json::to_value(&record.packet).unwrap();
+            assert!(json["payload"].is_string());
+            assert_eq!(
+                serde_json::from_value::<RtpPacket>(json).unwrap(),
+                record.packet
+            );
+        }
+
+        // Optional fields may be left out of hand-written traces
+        let parsed: TraceRecord = serde_json::from_str(
+            r#"{"arrival_us": 5, "packet": {"payload_type": 96, "marker": false,
+                "sequence": 1, "timestamp": 2, "ssrc": 3, "payload": "eA=="}}"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.packet, RtpPacket::new(1, 2, 3, vec![0x78]));
+        assert!(serde_json::from_str::<TraceRecord>(
+            r#"{"arrival_us": 5, "packet": {"payload_type": 96, "marker": false,
+                "sequence": 1, "timestamp": 2, "ssrc": 3, "payload": "not base64!"}}"#,
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_schema_matches_committed_fixture() {
+        // ---
+        // Changing this file is changing the trace format: existing traces
+        // must still load
+        let fixture: serde_json::Value =
+            serde_json::from_str(include_str!("../tests/fixtures/trace_records.json")).unwrap();
+
+        let records = [
+            RtpPacket::new(100, 32_000, 0x1234_5678, vec![1, 2, 3, 4])
+                .to_trace_record(Duration::from_micros(20_250)),
+            packet_using_every_field().to_trace_record(Duration::from_secs(3)),
+        ];
+        assert_eq!(serde_json::to_value(&records).unwrap(), fixture);
+        let parsed: Vec<TraceRecord> = serde_json::from_value(fixture).unwrap();
+        assert_eq!(parsed, records);
+    }
+}
@@ -0,0 +1,59 @@
+//! Transport-wide sequence number RTP header extension.
+//!
+//! The sender numbers every packet it sends with a 16-bit counter of its
+//! own, independent of the RTP sequence number (draft-holmer-rmcat-
+//! transport-wide-cc-extensions-01 §2). The receiver records when each
+//! number arrived, which is the raw material of transport-wide congestion
+//! control feedback and bandwidth estimation. Carried in a one-byte header
+//! extension element whose ID is negotiated in SDP against
+//! [`TRANSPORT_CC_URI`].
+
+use anyhow::Result;
+
+/// URI identifying the extension in an SDP `a=extmap` line
+pub const TRANSPORT_CC_URI: &str =
+    "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
+
+/// Size of the extension element data in bytes
+pub const TRANSPORT_CC_LEN: usize = 2;
+
+/// Encodes a transport-wide sequence number as extension element data.
+pub fn encode_transport_sequence(sequence: u16) -> [u8; TRANSPORT_CC_LEN] {
+    // ---
+    sequence.to_be_bytes()
+}
+
+/// Decodes extension element data into the transport-wide sequence number.
+///
+/// # Errors
+///
+/// Returns error if `data` is not [`TRANSPORT_CC_LEN`] bytes long.
+pub fn decode_transport_sequence(data: &[u8]) -> Result<u16> {
+    // ---
+    let &[high, low] = data else {
+        anyhow::bail!(
+            "transport-wide sequence number of {} bytes (expected {})",
+            data.len(),
+            TRANSPORT_CC_LEN
+        );
+    };
+    Ok(u16::from_be_bytes([high, low]))
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_sequence_roundtrip() {
+        // ---
+        for sequence in [0, 1, 0x1234, u16::MAX] {
+            let data = encode_transport_sequence(sequence);
+            assert_eq!(decode_transport_sequence(&data).unwrap(), sequence);
+        }
+        assert_eq!(encode_transport_sequence(0x1234), [0x12, 0x34]);
+        assert!(decode_transport_sequence(&[1]).is_err());
+        assert!(decode_transport_sequence(&[1, 2, 3]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Micro-benchmark for jitter buffer inserts.
+//!
+//! Run with `cargo bench -p receiver --bench jitter_insert`. After the
+//! first packet, packets arrive in blocks of [`HELD`], the first of each
+//! block last, so up to [`HELD`] wait behind it before the block plays
+//! out. Compares the storage the [`JitterBuffer`] used to have, a
+//! `VecDeque` scanned once for duplicates and once for the position, with
+//! the `BTreeMap` keyed by extended sequence number it has now, then
+//! times the [`JitterBuffer`] itself, which also reads the clock and
+//! tracks sequence numbers on each call.
+
+use receiver::{JitterBuffer, JitterBufferConfig, PrimeMode, RtpPacket};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::hint::black_box;
+use std::time::Instant;
+
+const BLOCKS: u64 = 1_000;
+
+/// Most packets held at once, as for a high-jitter link
+const HELD: u64 = 200;
+
+/// The former storage: packets in a `VecDeque`, scanned on every insert.
+struct LinearBuffer {
+    // ---
+    buffer: VecDeque<(u64, RtpPacket)>,
+    next: u64,
+}
+
+impl LinearBuffer {
+    // ---
+    fn insert(&mut self, extended: u64, packet: RtpPacket) {
+        // ---
+        if self
+            .buffer
+            .iter()
+            .any(|(_, buffered)| buffered.sequence == packet.sequence)
+        {
+            return;
+        }
+        let position = self
+            .buffer
+            .iter()
+            .position(|&(buffered, _)| buffered > extended)
+            .unwrap_or(self.buffer.len());
+        self.buffer.insert(position, (extended, packet));
+    }
+
+    fn get_next(&mut self) -> Option<RtpPacket> {
+        // ---
+        let position = self
+            .buffer
+            .iter()
+            .position(|&(extended, _)| extended == self.next)?;
+        self.next += 1;
+        self.buffer.remove(position).map(|(_, packet)| packet)
+    }
+}
+
+/// The current storage: packets in a `BTreeMap` by extended sequence.
+struct OrderedBuffer {
+    // ---
+    buffer: BTreeMap<u64, RtpPacket>,
+    next: u64,
+}
+
+impl OrderedBuffer {
+    // ---
+    fn insert(&mut self, extended: u64, packet: RtpPacket) {
+        // ---
+        self.buffer.entry(extended).or_insert(packet);
+    }
+
+    fn get_next(&mut self) -> Option<RtpPacket> {
+        // ---
+        let entry = self.buffer.first_entry()?;
+        if *entry.key() != self.next {
+            return None;
+        }
+        self.next += 1;
+        Some(entry.remove())
+    }
+}
+
+/// Returns the packet with extended sequence number `extended`.
+fn packet(extended: u64) -> RtpPacket {
+    // ---
+    RtpPacket::new(
+        extended as u16,
+        extended as u32 * 960,
+        0x1234_5678,
+        vec![0x78; 80],
+    )
+}
+
+/// Returns the packets in arrival order: the first, where playout starts,
+/// then blocks of [`HELD`], each one's first packet arriving after the
+/// rest.
+fn arrivals() -> Vec<(u64, RtpPacket)> {
+    // ---
+    let blocks = (0..BLOCKS).flat_map(|block| {
+        let first = 1 + block * HELD;
+        (first + 1..first + HELD).chain([first])
+    });
+    std::iter::once(0)
+        .chain(blocks)
+        .map(|extended| (extended, packet(extended)))
+        .collect()
+}
+
+/// Inserts every packet with `insert`, playing out with `get_next` after
+/// each, and returns nanoseconds per packet.
+fn measure(
+    name: &str,
+    mut insert: impl FnMut(u64, RtpPacket),
+    mut get_next: impl FnMut() -> Option<RtpPacket>,
+) -> f64 {
+    // ---
+    let arrivals = arrivals();
+    let count = arrivals.len();
+    let mut played = 0;
+
+    let start = Instant::now();
+    for (extended, packet) in arrivals {
+        insert(black_box(extended), black_box(packet));
+        while let Some(packet) = get_next() {
+            black_box(packet);
+            played += 1;
+        }
+    }
+    let per_packet = start.elapsed().as_nanos() as f64 / count as f64;
+
+    assert_eq!(played, count);
+    println!(
+        "{}: {:.1} ns/packet with up to {} held",
+        name, per_packet, HELD
+    );
+    per_packet
+}
+
+fn main() {
+    // ---
+    let linear = RefCell::new(LinearBuffer {
+        buffer: VecDeque::with_capacity(HELD as usize),
+        next: 0,
+    });
+    let linear_ns = measure(
+        "VecDeque with linear scans",
+        |extended, packet| linear.borrow_mut().insert(extended, packet),
+        || linear.borrow_mut().get_next(),
+    );
+
+    // Gaps are waited for as long as the benchmark runs
+    let ordered = RefCell::new(OrderedBuffer {
+        buffer: BTreeMap::new(),
+        next: 0,
+    });
+    let ordered_ns = measure(
+        "BTreeMap by extended sequence",
+        |extended, packet| ordered.borrow_mut().insert(extended, packet),
+        || ordered.borrow_mut().get_next(),
+    );
+    println!("BTreeMap/VecDeque: {:.2}", ordered_ns / linear_ns);
+
+    // Gaps are waited for as long as the benchmark runs
+    let buffer = RefCell::new(JitterBuffer::new(JitterBufferConfig {
+        depth_ms: 3_600_000,
+        max_packets: HELD as usize,
+        prime_mode: PrimeMode::Immediate,
+        ..Default::default()
+    }));
+    measure(
+        "JitterBuffer",
+        |_, packet| {
+            buffer.borrow_mut().insert(packet);
+        },
+        || buffer.borrow_mut().get_next(),
+    );
+}
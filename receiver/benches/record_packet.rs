@@ -0,0 +1,45 @@
+//! Micro-benchmark for the receiver stats hot path.
+//!
+//! Run with `cargo bench -p receiver`. Fails if `record_packet` averages
+//! more than the budget per call, so regressions that reintroduce logging
+//! or formatting on the record path are caught.
+
+use receiver::ReceiverStats;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 1_000_000;
+const BUDGET_NS: f64 = 100.0;
+
+fn main() {
+    // ---
+    let mut stats = ReceiverStats::new(Duration::from_millis(1));
+
+    // Warm up
+    for seq in 0..10_000u32 {
+        stats.record_packet(black_box(seq as u16), false);
+    }
+
+    let start = Instant::now();
+    for seq in 0..ITERATIONS {
+        // Every 50th packet skips one sequence to exercise the loss path
+        let seq = (seq + seq / 50) as u16;
+        stats.record_packet(black_box(seq), black_box(false));
+    }
+    let per_call = start.elapsed().as_nanos() as f64 / ITERATIONS as f64;
+
+    println!(
+        "record_packet: {:.1} ns/call over {} calls",
+        per_call, ITERATIONS
+    );
+    black_box(stats.snapshot());
+
+    if !cfg!(debug_assertions) {
+        assert!(
+            per_call < BUDGET_NS,
+            "record_packet took {:.1} ns/call, budget is {} ns",
+            per_call,
+            BUDGET_NS
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Micro-benchmark for the receive buffer.
+//!
+//! Run with `cargo bench -p receiver --bench recv_buffer`. Counts heap
+//! allocations with a wrapping global allocator while parsing datagrams
+//! the way the receiver does, holding the last few packets as a jitter
+//! buffer would, against copying each payload out of a scratch buffer.
+//! Fails if slicing payloads out of the [`RecvBuffer`] does not allocate
+//! far less than once per packet.
+
+use receiver::{RecvBuffer, RtpPacket, DEFAULT_RECV_BUFFER_DATAGRAMS};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+const ITERATIONS: u32 = 1_000_000;
+const MAX_DATAGRAM_SIZE: usize = 2048;
+
+/// Packets held at once, as in a jitter buffer
+const HELD: usize = 8;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // ---
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // ---
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Runs `receive` for every packet, keeping the last [`HELD`] results, and
+/// returns allocations per packet.
+fn measure(name: &str, datagram: &[u8], mut receive: impl FnMut(&[u8]) -> RtpPacket) -> f64 {
+    // ---
+    let mut held = VecDeque::with_capacity(HELD + 1);
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        held.push_back(black_box(receive(datagram)));
+        if held.len() > HELD {
+            held.pop_front();
+        }
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+
+    let per_packet = allocations as f64 / ITERATIONS as f64;
+    println!(
+        "{}: {:.1} ns/packet, {:.4} allocations/packet",
+        name,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+        per_packet
+    );
+    per_packet
+}
+
+fn main() {
+    // ---
+    let datagram = RtpPacket::new(1, 960, 0x1234_5678, vec![0x78; 80])
+        .serialize()
+        .expect("serialize");
+
+    let mut scratch = vec![0u8; MAX_DATAGRAM_SIZE];
+    let copied = measure("copy payload", &datagram, |data| {
+        scratch[..data.len()].copy_from_slice(data);
+        RtpPacket::deserialize(&scratch[..data.len()]).expect("parse")
+    });
+
+    let mut recv = RecvBuffer::new(MAX_DATAGRAM_SIZE, DEFAULT_RECV_BUFFER_DATAGRAMS);
+    let sliced = measure("slice payload", &datagram, |data| {
+        recv.space()[..data.len()].copy_from_slice(data);
+        RtpPacket::deserialize_bytes(recv.split(data.len())).expect("parse")
+    });
+
+    assert!(
+        sliced < copied && sliced < 0.01,
+        "receive path allocated {sliced} times per packet"
+    );
+}
@@ -0,0 +1,85 @@
+//! Embedding the receiver library in another tokio application.
+//!
+//! Receives one RTP stream into an in-memory sink and prints a summary
+//! once the stream has been quiet for two seconds. Pair it with the
+//! `embed_sender` example or the `sender` binary.
+//!
+//! ```bash
+//! cargo run -p receiver --example embed_receiver -- 5004
+//! ```
+
+use anyhow::Result;
+use receiver::codec::SAMPLE_RATE;
+use receiver::{
+    receive_loop, AudioSink, DecoderSet, MetricsContext, PrimeMode, ReceiveOptions, ReceiverStats,
+    RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use std::time::Duration;
+
+/// Sink that keeps every decoded sample in memory.
+#[derive(Default)]
+struct MemorySink {
+    // ---
+    samples: Vec<i16>,
+}
+
+impl AudioSink for MemorySink {
+    // ---
+    fn play(&mut self, samples: &[i16]) {
+        // ---
+        self.samples.extend_from_slice(samples);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // ---
+    let port = match std::env::args().nth(1) {
+        Some(port) => port.parse()?,
+        None => 5004,
+    };
+
+    let metrics = MetricsContext::new("embed-receiver")?;
+    let mut receiver = RtpReceiver::new(port).await?;
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = MemorySink::default();
+
+    // The caller owns the stats; a handle could be polled from another task
+    let mut stats = ReceiverStats::new(Duration::from_secs(5));
+    let mut options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(2)),
+        ..Default::default()
+    };
+    options.jitter.prime_mode = PrimeMode::Immediate;
+
+    println!("Waiting for a stream on {}", receiver.local_addr()?);
+    receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &metrics,
+    )
+    .await?;
+
+    let snapshot = stats.snapshot();
+    println!("{}", snapshot.summary());
+    println!(
+        "Captured {:.2}s of audio, mean jitter buffer delay {:.1}ms",
+        sink.samples.len() as f64 / SAMPLE_RATE as f64,
+        metrics.jitter_buffer_delay_seconds.get_sample_sum() * 1000.0
+            / metrics
+                .jitter_buffer_delay_seconds
+                .get_sample_count()
+                .max(1) as f64
+    );
+    for (payload_type, pt_stats) in decoders.stats() {
+        println!(
+            "PT {payload_type}: {} decoded, {} failed, {} unsupported",
+            pt_stats.decoded, pt_stats.failed, pt_stats.unsupported
+        );
+    }
+
+    Ok(())
+}
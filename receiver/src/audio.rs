@@ -1,15 +1,103 @@
 //! Audio playback using cpal.
 //!
 //! Provides real-time audio output through the system's default
-//! audio device using callback-based streaming.
+//! audio device using callback-based streaming, plus the `AudioSink`
+//! abstraction the receive loop plays into. [`AudioPlayer`] needs the
+//! `playback` feature; the sinks do not.
 
+#[cfg(feature = "playback")]
 use anyhow::{Context, Result};
+#[cfg(feature = "playback")]
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+#[cfg(feature = "playback")]
 use cpal::{Device, Stream, StreamConfig};
+#[cfg(feature = "playback")]
 use std::sync::mpsc::{self, Receiver, Sender};
+#[cfg(feature = "playback")]
+use std::sync::Arc;
+#[cfg(feature = "playback")]
 use tracing::{debug, info, warn};
 
+#[cfg(feature = "playback")]
 use crate::codec::SAMPLE_RATE;
+#[cfg(feature = "playback")]
+use crate::stats::{PlayoutEventKind, PlayoutHistory};
+
+/// Destination for decoded PCM frames.
+///
+/// The receive loop is generic over its sink so the same pipeline can drive
+/// a real output device, discard audio entirely, or capture it for tests.
+pub trait AudioSink {
+    // ---
+    /// Queues a frame of PCM samples for playback.
+    fn play(&mut self, samples: &[i16]);
+}
+
+impl<S: AudioSink + ?Sized> AudioSink for &mut S {
+    // ---
+    fn play(&mut self, samples: &[i16]) {
+        // ---
+        (**self).play(samples);
+    }
+}
+
+/// Sink that discards all audio.
+///
+/// Used by modes that exercise the receive pipeline without an output
+/// device (stream validation, headless tests). Keeps a running count of
+/// what it was given so callers can still reason about playout volume.
+#[derive(Debug, Default, Clone)]
+pub struct NullSink {
+    // ---
+    /// Number of frames received
+    pub frames: u64,
+
+    /// Number of samples received
+    pub samples: u64,
+}
+
+impl NullSink {
+    // ---
+    pub fn new() -> Self {
+        // ---
+        Self::default()
+    }
+}
+
+impl AudioSink for NullSink {
+    // ---
+    fn play(&mut self, samples: &[i16]) {
+        // ---
+        self.frames += 1;
+        self.samples += samples.len() as u64;
+    }
+}
+
+/// Shortens a frame by `fraction` of its samples, to play it faster than
+/// real time.
+///
+/// The samples cut from the middle are overlapped with those before them
+/// and crossfaded, so the frame stays continuous rather than clicking at
+/// the cut. Frames too short to cut are returned whole.
+pub fn shorten_frame(samples: &[i16], fraction: f64) -> Vec<i16> {
+    // ---
+    let cut = (samples.len() as f64 * fraction.clamp(0.0, 0.5)) as usize;
+    let middle = samples.len() / 2;
+    if cut == 0 || middle < cut {
+        return samples.to_vec();
+    }
+
+    let mut shortened = Vec::with_capacity(samples.len() - cut);
+    shortened.extend_from_slice(&samples[..middle - cut]);
+    for i in 0..cut {
+        let weight = (i + 1) as f64 / (cut + 1) as f64;
+        let before = samples[middle - cut + i] as f64;
+        let after = samples[middle + i] as f64;
+        shortened.push((before * (1.0 - weight) + after * weight).round() as i16);
+    }
+    shortened.extend_from_slice(&samples[middle + cut..]);
+    shortened
+}
 
 /// Audio player for real-time PCM playback.
 ///
@@ -19,13 +107,20 @@ use crate::codec::SAMPLE_RATE;
 /// # Thread Safety
 ///
 /// The player uses an MPSC channel to safely transfer audio samples
-/// from the network thread to the audio callback thread.
+/// from the network thread to the audio callback thread. The cpal stream
+/// itself is not `Send`, so it lives on a dedicated device thread for the
+/// lifetime of the player; this makes the player `Send`, and it can be
+/// opened with `spawn_blocking` while the receiver is already buffering.
+#[cfg(feature = "playback")]
 pub struct AudioPlayer {
     // ---
-    _stream: Stream,
     sample_tx: Sender<i16>,
+
+    /// Dropping this ends the device thread, which closes the stream
+    _shutdown_tx: Sender<()>,
 }
 
+#[cfg(feature = "playback")]
 impl AudioPlayer {
     // ---
     /// Creates a new audio player using the default output device.
@@ -41,26 +136,62 @@ impl AudioPlayer {
     /// - Stream creation fails
     pub fn new() -> Result<Self> {
         // ---
-        info!("Initializing audio playback");
+        Self::open(None)
+    }
 
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .context("no output device available")?;
+    /// Creates an audio player that records device underruns into
+    /// `history`.
+    ///
+    /// An underrun is a device callback that finds the sample queue empty
+    /// after playout had started; it is recorded once per callback with
+    /// the number of silent samples inserted.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`new`](Self::new).
+    pub fn with_history(history: Arc<PlayoutHistory>) -> Result<Self> {
+        // ---
+        Self::open(Some(history))
+    }
 
-        info!("Using audio device: {}", device.name()?);
+    /// Opens the default output device on a dedicated thread.
+    fn open(history: Option<Arc<PlayoutHistory>>) -> Result<Self> {
+        // ---
+        info!("Initializing audio playback");
 
         // Create channel for passing samples to audio callback
         let (sample_tx, sample_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = mpsc::channel::<()>();
+
+        // Build the stream on the device thread and keep it there
+        std::thread::Builder::new()
+            .name("audio-output".into())
+            .spawn(move || {
+                // ---
+                let _stream = match Self::open_default_stream(sample_rx, history) {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let _ = ready_tx.send(Ok(()));
+
+                // Blocks until the player is dropped, then closes the stream
+                let _ = shutdown_rx.recv();
+            })
+            .context("failed to spawn audio thread")?;
 
-        // Build stream with our configuration
-        let stream = Self::build_stream(&device, sample_rx)?;
+        ready_rx
+            .recv()
+            .context("audio thread exited during setup")??;
 
         info!("Audio stream created successfully");
 
         Ok(Self {
-            _stream: stream,
             sample_tx,
+            _shutdown_tx: shutdown_tx,
         })
     }
 
@@ -82,8 +213,28 @@ impl AudioPlayer {
         }
     }
 
+    /// Opens the default output device and builds its stream.
+    fn open_default_stream(
+        sample_rx: Receiver<i16>,
+        history: Option<Arc<PlayoutHistory>>,
+    ) -> Result<Stream> {
+        // ---
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .context("no output device available")?;
+
+        info!("Using audio device: {}", device.name()?);
+
+        Self::build_stream(&device, sample_rx, history)
+    }
+
     /// Builds the audio output stream.
-    fn build_stream(device: &Device, sample_rx: Receiver<i16>) -> Result<Stream> {
+    fn build_stream(
+        device: &Device,
+        sample_rx: Receiver<i16>,
+        history: Option<Arc<PlayoutHistory>>,
+    ) -> Result<Stream> {
         // ---
         let config = StreamConfig {
             channels: 1,
@@ -94,11 +245,21 @@ impl AudioPlayer {
         debug!("Stream config: {:?}", config);
 
         // Create the output stream with a callback
+        let mut playing = false;
         let stream = device
             .build_output_stream(
                 &config,
                 move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
-                    Self::audio_callback(data, &sample_rx);
+                    let filled = Self::audio_callback(data, &sample_rx);
+                    if let Some(history) = &history {
+                        if playing && filled < data.len() {
+                            let silent = (data.len() - filled) as u32;
+                            history.record(PlayoutEventKind::Underrun, None, silent);
+                        }
+                    }
+                    // Only a full callback counts as playing, so one dry
+                    // spell is one underrun rather than one per callback
+                    playing = filled == data.len();
                 },
                 |err| {
                     warn!("Audio stream error: {}", err);
@@ -120,11 +281,30 @@ impl AudioPlayer {
     /// Called by cpal when the audio device needs more samples.
     /// Pulls samples from the queue and fills the output buffer,
     /// using silence if the queue is empty.
-    fn audio_callback(data: &mut [i16], sample_rx: &Receiver<i16>) {
+    ///
+    /// Returns the number of samples taken from the queue.
+    fn audio_callback(data: &mut [i16], sample_rx: &Receiver<i16>) -> usize {
         // ---
+        let mut filled = 0;
         for sample in data.iter_mut() {
-            *sample = sample_rx.try_recv().unwrap_or(0);
+            *sample = match sample_rx.try_recv() {
+                Ok(queued) => {
+                    filled += 1;
+                    queued
+                }
+                Err(_) => 0,
+            };
         }
+        filled
+    }
+}
+
+#[cfg(feature = "playback")]
+impl AudioSink for AudioPlayer {
+    // ---
+    fn play(&mut self, samples: &[i16]) {
+        // ---
+        AudioPlayer::play(self, samples);
     }
 }
 
@@ -133,6 +313,7 @@ mod tests {
     // ---
     use super::*;
 
+    #[cfg(feature = "playback")]
     #[test]
     fn test_audio_player_creation() {
         // ---
@@ -149,6 +330,7 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[cfg(feature = "playback")]
     #[test]
     fn test_audio_player_play() {
         // ---
@@ -158,4 +340,33 @@ mod tests {
             // Should not panic
         }
     }
+
+    #[test]
+    fn test_null_sink_counts_frames() {
+        // ---
+        let mut sink = NullSink::new();
+        sink.play(&[0i16; 320]);
+        sink.play(&[0i16; 320]);
+
+        assert_eq!(sink.frames, 2);
+        assert_eq!(sink.samples, 640);
+    }
+
+    #[test]
+    fn test_shorten_frame_crossfades_cut() {
+        // ---
+        let ramp: Vec<i16> = (0..320).collect();
+        let shortened = shorten_frame(&ramp, 0.1);
+
+        // 32 samples fewer, the ends untouched
+        assert_eq!(shortened.len(), 288);
+        assert_eq!(shortened[..128], ramp[..128]);
+        assert_eq!(shortened[160..], ramp[192..]);
+
+        // The overlap moves from the samples before the cut to those after
+        assert!(shortened[128..160].windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(shortened[128] > 128 && shortened[159] < 192);
+
+        assert_eq!(shorten_frame(&ramp[..4], 0.1), ramp[..4]);
+    }
 }
@@ -7,8 +7,26 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use tracing::info;
 
-use receiver::{receive_loop, AudioPlayer, JitterBufferConfig, OpusDecoderWrapper, RtpReceiver};
-use rtp_opus_common::{init_tracing, ColorWhen, MetricsContext, MetricsServerConfig};
+use receiver::jitter_buffer::{DEFAULT_ADAPTIVE_MAX_MS, DEFAULT_ADAPTIVE_MIN_MS};
+use receiver::stats::DEFAULT_HISTORY_CAPACITY;
+#[cfg(feature = "playback")]
+use receiver::AudioPlayer;
+use receiver::{
+    receive_loop_with_setup, spawn_underrun_monitor, validate_loop, AdaptiveDepthConfig,
+    ClockRateConfig, ControlServer, DecoderSet, HistoryDumpConfig, JitterBufferConfig, NackConfig,
+    PlayoutHistory, PrimeMode, ReceiveOptions, ReceiverNetworkConfig, ReceiverStats, RefreshConfig,
+    RtpParseMode, RtpReceiver,
+};
+#[cfg(feature = "metrics-server")]
+use rtp_opus_common::MetricsServerConfig;
+use rtp_opus_common::{
+    handle_verbose_version, init_tracing, AnomalyLogConfig, ColorWhen, MetricsContext,
+    RtpdumpWriter, SrtpKey, Transport, DEFAULT_SESSION_BANDWIDTH, PAYLOAD_TYPE_OPUS,
+};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(clap::ValueEnum, Debug, Clone, Copy)]
 enum ColorArg {
@@ -27,6 +45,44 @@ impl From<ColorArg> for ColorWhen {
     }
 }
 
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum TransportArg {
+    Udp,
+    Tcp,
+}
+
+impl From<TransportArg> for Transport {
+    fn from(v: TransportArg) -> Self {
+        match v {
+            TransportArg::Udp => Transport::Udp,
+            TransportArg::Tcp => Transport::Tcp,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum PrimeModeArg {
+    WaitForDepth,
+    Immediate,
+    FirstTalkspurt,
+}
+
+impl From<PrimeModeArg> for PrimeMode {
+    fn from(v: PrimeModeArg) -> Self {
+        match v {
+            PrimeModeArg::WaitForDepth => PrimeMode::WaitForDepth,
+            PrimeModeArg::Immediate => PrimeMode::Immediate,
+            PrimeModeArg::FirstTalkspurt => PrimeMode::FirstTalkspurt,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum JitterModeArg {
+    Fixed,
+    Adaptive,
+}
+
 /// RTP Opus Receiver - Receive and play audio streams
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -38,10 +94,93 @@ struct Args {
         long,
         default_value_t = 5004,
         help = "Port to listen on",
-        long_help = "UDP port to listen on for incoming RTP packets."
+        long_help = "UDP port to listen on for incoming RTP packets (TCP port with\n\
+                     --transport tcp)."
     )]
     port: u16,
 
+    /// Transport to receive over
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = TransportArg::Udp,
+        help = "Receive over UDP or TCP",
+        long_help = "Transport for the RTP packets.\n\n\
+                     udp: One packet per datagram (the default).\n\
+                     tcp: Listen on --port for the sender's TCP connection and read\n\
+                     length-prefixed packets (RFC 4571), for networks that block UDP.\n\
+                     A new connection replaces the current one, so the stream resumes\n\
+                     when the sender reconnects. No multicast, and no decoder refresh\n\
+                     requests back to the sender."
+    )]
+    transport: TransportArg,
+
+    /// SRTP master key and salt, base64
+    #[arg(
+        long,
+        value_name = "BASE64",
+        value_parser = SrtpKey::from_base64,
+        help = "Accept only SRTP packets protected with this key",
+        long_help = "Authenticate and decrypt every packet with SRTP (RFC 3711,
+                     AES_CM_128_HMAC_SHA1_80), using the key the sender was given:
+                     the 16-byte master key followed by the 14-byte master salt,
+                     base64 encoded. Packets that fail authentication are dropped and
+                     counted in srtp_auth_failures_total. No decoder refresh requests
+                     back to the sender, since they would not be authenticated."
+    )]
+    srtp_key: Option<SrtpKey>,
+
+    /// Sender in listen mode to contact
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        conflicts_with = "multicast_group",
+        help = "Say hello to a sender started with --listen, and keep it alive",
+        long_help = "Send a hello datagram from the RTP socket to a sender running with\n\
+                     --listen, which then streams back to the address it came from;\n\
+                     through a NAT, that is the public address the NAT opened for this\n\
+                     socket. Keepalives follow every 5s while receiving so the binding\n\
+                     stays open. The hello is an empty RTCP receiver report. UDP\n\
+                     unicast only."
+    )]
+    punch: Option<String>,
+
+    /// Multicast group to join
+    #[arg(
+        long,
+        value_name = "ADDR",
+        help = "Join this IPv4 multicast group and receive what is sent to it",
+        long_help = "Receive a multicast stream: join the IPv4 group (e.g. 239.1.2.3)\n\
+                     before receiving on --port. The port is shared, so several\n\
+                     receivers on one host can listen to the same group."
+    )]
+    multicast_group: Option<Ipv4Addr>,
+
+    /// Interface to join the multicast group on
+    #[arg(
+        long,
+        value_name = "ADDR",
+        requires = "multicast_group",
+        help = "Join --multicast-group on the interface with this IPv4 address",
+        long_help = "Interface to join --multicast-group on, given by its IPv4 address.\n\
+                     By default the system chooses."
+    )]
+    mcast_if: Option<Ipv4Addr>,
+
+    /// Socket receive buffer size
+    #[arg(
+        long,
+        value_name = "BYTES",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Request a socket receive buffer (SO_RCVBUF) of this many bytes",
+        long_help = "Size of the socket receive buffer to request. A larger buffer holds\n\
+                     bursts that arrive while the receiver is not scheduled, which the\n\
+                     default may drop (seen as loss). The kernel may clamp the size\n\
+                     (net.core.rmem_max on Linux); the size granted is logged. By\n\
+                     default the system default is kept."
+    )]
+    so_rcvbuf: Option<u32>,
+
     /// Jitter buffer depth in milliseconds
     #[arg(
         short = 'b',
@@ -54,16 +193,351 @@ struct Args {
     )]
     buffer_depth_ms: u32,
 
-    /// Prometheus metrics bind address (serves `GET /metrics`).
+    /// Whether the jitter buffer depth is fixed or adapts
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = JitterModeArg::Fixed,
+        help = "Whether the jitter buffer depth is fixed or adapts to the network",
+        long_help = "Whether the jitter buffer depth is fixed or adapts to the network.\n\n\
+                     fixed: Prime to --buffer-depth-ms throughout.\n\
+                     adaptive: Start at --buffer-depth-ms, then grow or shrink between\n\
+                     --jitter-min and --jitter-max by a frame a second at most, following\n\
+                     the interarrival jitter and late packets. A new depth applies when\n\
+                     playout next primes."
+    )]
+    jitter_mode: JitterModeArg,
+
+    /// Shallowest adaptive jitter buffer depth in milliseconds
+    #[arg(
+        long,
+        default_value_t = DEFAULT_ADAPTIVE_MIN_MS,
+        help = "Shallowest adaptive jitter buffer depth in milliseconds"
+    )]
+    jitter_min: u32,
+
+    /// Deepest adaptive jitter buffer depth in milliseconds
+    #[arg(
+        long,
+        default_value_t = DEFAULT_ADAPTIVE_MAX_MS,
+        help = "Deepest adaptive jitter buffer depth in milliseconds"
+    )]
+    jitter_max: u32,
+
+    /// How long the packet after a gap waits for the missing one
+    #[arg(
+        long,
+        help = "How long in milliseconds the packet after a gap waits for the missing one",
+        long_help = "How long in milliseconds a packet that arrived after a gap is held\n\
+                     waiting for the missing one before playout gives up on it, conceals\n\
+                     it, and moves on. A missing packet that arrives afterwards is\n\
+                     counted late. Defaults to the jitter buffer depth plus one frame."
+    )]
+    jitter_max_wait: Option<u32>,
+
+    /// When playout starts relative to jitter buffer priming
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = PrimeModeArg::WaitForDepth,
+        help = "When playout starts relative to jitter buffer priming",
+        long_help = "Controls when the jitter buffer starts releasing packets.\n\n\
+                     wait-for-depth: Hold packets until the buffer depth is reached.\n\
+                     immediate: Start on the first packet (lowest latency).\n\
+                     first-talkspurt: Start immediately for the first talkspurt, then\n\
+                     use depth-based priming when playout has to re-prime."
+    )]
+    prime_mode: PrimeModeArg,
+
+    /// Dynamic RTP payload type carrying Opus
+    #[arg(
+        long,
+        default_value_t = PAYLOAD_TYPE_OPUS,
+        value_parser = clap::value_parser!(u8).range(96..=127),
+        help = "Dynamic RTP payload type carrying Opus",
+        long_help = "Dynamic RTP payload type (96-127) decoded as Opus.\n\n\
+                     Packets with payload type 0 (PCMU) or 8 (PCMA) are always decoded\n\
+                     as G.711. Packets with any other payload type are dropped."
+    )]
+    opus_payload_type: u8,
+
+    /// Only payload type accepted
+    #[arg(
+        long,
+        value_name = "PT",
+        value_parser = clap::value_parser!(u8).range(0..=127),
+        help = "Drop packets with any other RTP payload type",
+        long_help = "Accept only packets with this payload type (0-127), e.g. the one\n\
+                     negotiated in SDP; usually the same as --opus-payload-type. Other\n\
+                     packets are dropped on arrival, before the jitter buffer, logged,\n\
+                     and counted in rtp_payload_type_mismatch_total. By default every\n\
+                     payload type is accepted and the decoder drops unsupported ones."
+    )]
+    expect_payload_type: Option<u8>,
+
+    /// Refuse RTP the receiver would not understand
+    #[arg(
+        long,
+        help = "Drop packets whose header extension is not well-formed one-byte elements",
+        long_help = "Strict parsing. Besides malformed RTP, drop packets whose header\n\
+                     extension uses another profile than RFC 8285 one-byte elements, or\n\
+                     whose elements run past the extension. Every datagram that does not\n\
+                     parse is logged and counted by reason in rtp_parse_errors_total\n\
+                     (too_short, bad_version, truncated_csrc, bad_extension, bad_padding,\n\
+                     unsupported_feature)."
+    )]
+    strict_rtp: bool,
+
+    /// Header extension ID carrying abs-send-time
+    #[arg(
+        long,
+        value_name = "ID",
+        value_parser = clap::value_parser!(u8).range(1..=14),
+        help = "Measure transit time from abs-send-time in this header extension ID",
+        long_help = "Read the sender's abs-send-time stamp from the one-byte header\n\
+                     extension element with this ID (sender --abs-send-time-id) and\n\
+                     feed network_transit_seconds from it: how much longer each packet\n\
+                     took than the fastest so far, unaffected by DTX or pauses. Packets\n\
+                     without the stamp, or all packets by default, are estimated from\n\
+                     their RTP timestamps instead."
+    )]
+    abs_send_time_id: Option<u8>,
+
+    /// Header extension ID carrying transport-wide sequence numbers
+    #[arg(
+        long,
+        value_name = "ID",
+        value_parser = clap::value_parser!(u8).range(1..=14),
+        help = "Record transport-wide sequence numbers from this header extension ID",
+        long_help = "Read the sender's transport-wide sequence number from the one-byte\n\
+                     header extension element with this ID (sender --transport-cc-id)\n\
+                     and keep when each of the last 1024 arrived, the groundwork of\n\
+                     transport-wide congestion control feedback."
+    )]
+    transport_cc_id: Option<u8>,
+
+    /// RTP clock rate in Hz
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1000..=192000),
+        help = "RTP clock rate in Hz (detected when omitted)",
+        long_help = "RTP timestamp clock rate of the incoming stream in Hz.\n\n\
+                     When omitted, the clock rate and frame duration are inferred from\n\
+                     the timestamp step over the first 50 packets (e.g. 960 ticks per\n\
+                     packet is 48 kHz with 20ms frames, as RFC 7587 senders use)."
+    )]
+    clock_rate: Option<u32>,
+
+    /// Largest accepted datagram in bytes
+    #[arg(
+        long,
+        default_value_t = 2048,
+        value_parser = clap::value_parser!(u16).range(64..),
+        help = "Largest accepted datagram in bytes",
+        long_help = "Largest datagram the receive buffer takes, in bytes.\n\n\
+                     Set this from the path MTU (e.g. 1500 for Ethernet, larger for\n\
+                     jumbo frames). Datagrams larger than this are dropped and\n\
+                     counted in rtp_datagrams_oversized_total."
+    )]
+    max_datagram_size: u16,
+
+    /// Lost packets in a row before asking the sender for a decoder refresh
+    #[arg(
+        long,
+        default_value_t = receiver::refresh::DEFAULT_REFRESH_LOST_PACKETS,
+        help = "Lost packets in a row before requesting a decoder refresh (0 = never)",
+        long_help = "Consecutive lost packets after which the receiver asks the sender to\n\
+                     reset its encoder (an RTCP PLI sent back to the packet source).\n\n\
+                     After a long outage the decoder state no longer matches the encoder's\n\
+                     and audio recovers with artifacts; a reset gives both a clean start.\n\
+                     Requests are limited to one every 5 seconds. 0 disables them."
+    )]
+    refresh_after_packets: u16,
+
+    /// Ask the sender to retransmit lost packets
+    #[arg(
+        long,
+        help = "Ask the sender to retransmit lost packets (RTCP NACK)",
+        long_help = "Send a Generic NACK (RFC 4585) back to the packet source for each\n\
+                     packet still missing one frame (20ms) after a later one arrived,\n\
+                     while it could yet be played: gaps older than --buffer-depth-ms\n\
+                     are not asked for. The sender retransmits the packet unchanged if\n\
+                     it is still in its --rtx-cache. Each packet is asked for once.\n\
+                     NACKs are spaced out to keep within 5% of --session-bw, so losses\n\
+                     close together go out in one NACK. UDP only, and not with SRTP."
+    )]
+    nack: bool,
+
+    /// Session bandwidth that RTCP keeps within 5% of
+    #[arg(
+        long,
+        value_name = "BPS",
+        default_value_t = DEFAULT_SESSION_BANDWIDTH,
+        help = "Session bandwidth in bits per second, for spacing RTCP out",
+        long_help = "Bandwidth of the stream in bits per second, headers included. The\n\
+                     RTCP this receiver sends, receiver reports and NACKs, keeps within\n\
+                     5% of it (RFC 3550 §6.2, RFC 4585 §3.5): reports come no more\n\
+                     often than that allows, and a NACK only a report interval after\n\
+                     the last."
+    )]
+    session_bw: u64,
+
+    /// Time between RTCP receiver reports
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = receiver::DEFAULT_RECEIVER_REPORT_INTERVAL.as_secs(),
+        help = "Send an RTCP receiver report every SECS seconds (0 = off)",
+        long_help = "While receiving, send the packet source an RTCP receiver report\n\
+                     (RFC 3550) every SECS seconds, with the fraction of packets lost\n\
+                     since the last report, the cumulative loss, the highest sequence\n\
+                     number, the interarrival jitter, and the delay since the last\n\
+                     sender report. UDP only, and not with SRTP. 0 sends none."
+    )]
+    rtcp_rr_interval: u64,
+
+    /// Extended reports with the receiver reports
+    #[arg(
+        long,
+        help = "Send RTCP XR loss and VoIP metrics with receiver reports",
+        long_help = "With each RTCP receiver report, send an extended report (RFC 3611)\n\
+                     with a Loss RLE block marking each packet since the last report\n\
+                     received or lost, and a VoIP Metrics block with the loss and\n\
+                     discard rates, burst and gap figures, jitter buffer delays, and\n\
+                     an E-model MOS estimate. Needs receiver reports on."
+    )]
+    rtcp_xr: bool,
+
+    /// Exit once the sender ends the stream
+    #[arg(
+        long,
+        help = "Exit once the sender ends the stream with an RTCP BYE",
+        long_help = "When the sender ends the stream it is playing with an RTCP BYE\n\
+                     (RFC 3550), play out what is still buffered, log the final stats,\n\
+                     and exit. Without this, the receiver logs the stream's stats and\n\
+                     waits for the next stream. UDP only, and not with SRTP."
+    )]
+    exit_on_bye: bool,
+
+    /// RTCP on a port of its own
+    #[arg(
+        long,
+        conflicts_with = "punch",
+        help = "Take RTCP on PORT+1 rather than on the RTP socket",
+        long_help = "Bind a second UDP socket on the port after --port for RTCP, as\n\
+                     RFC 3550 has it, and send receiver reports and feedback from it\n\
+                     to the port after the sender's, rather than sharing the RTP socket\n\
+                     (rtcp-mux, RFC 5761). The sender must run with --no-rtcp-mux too.\n\
+                     UDP only, and not with SRTP or --punch."
+    )]
+    no_rtcp_mux: bool,
+
+    /// Interval between aggregated anomaly log lines
+    #[arg(
+        long,
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds between aggregated log lines for one kind of packet anomaly",
+        long_help = "Rate limit for packet anomaly logging.\n\n\
+                     The first late, evicted, or invalid packet of each kind is logged in full; later ones are counted and\n\
+                     summarized at most once per interval (count and sequence range).\n\
+                     Suppressed occurrences are exported as anomalies_suppressed_total."
+    )]
+    anomaly_log_interval_secs: u64,
+
+    /// Expect and check a CRC32 trailer on every payload
+    #[arg(
+        long,
+        help = "Check the CRC32 trailer the sender adds with --verify-payloads",
+        long_help = "Payload verification mode; the sender must also run with\n\
+                     --verify-payloads.\n\n\
+                     Every payload is expected to end in a CRC32 of the codec payload.\n\
+                     Packets that fail the check are dropped before decoding (and\n\
+                     concealed like a loss), logged with their sequence number, and\n\
+                     counted in payload_crc_mismatch_total. With --validate any mismatch\n\
+                     fails validation."
+    )]
+    verify_payloads: bool,
+
+    /// Directory for playout history dumps
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Keep a playout history and write dumps to this directory",
+        long_help = "Keep an in-memory history of playout events (packet arrivals,\n\
+                     jitter buffer releases, concealments, device underruns, and\n\
+                     occupancy samples) for diagnosing glitches after the fact.\n\n\
+                     The last --history-secs are written here as compact JSON when a\n\
+                     burst of underruns is detected, or on a dump command sent to\n\
+                     --control-bind."
+    )]
+    history_dir: Option<PathBuf>,
+
+    /// Capture file for received packets
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Record every received packet to FILE in rtpdump format",
+        long_help = "Append each datagram to FILE as it arrives, before SRTP, payload\n\
+                     checks, and the jitter buffer, in the rtpdump format of rtptools\n\
+                     (wire bytes and arrival time in ms). The capture can be replayed\n\
+                     with its original timing by sender --replay, or with rtpplay, and\n\
+                     opened in Wireshark. With --srtp-key, the packets are recorded\n\
+                     still protected."
+    )]
+    rtpdump_out: Option<PathBuf>,
+
+    /// Span of playout history written per dump
+    #[arg(
+        long,
+        default_value_t = 30,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds of playout history written per dump",
+        long_help = "Seconds of playout history written per dump (default 30).\n\n\
+                     The ring holds a fixed number of events, so at high packet rates\n\
+                     a dump may cover less than this."
+    )]
+    history_secs: u64,
+
+    /// Control socket address for on-demand history dumps
+    #[arg(
+        long,
+        requires = "history_dir",
+        help = "TCP address for the control socket (requires --history-dir)",
+        long_help = "Bind a line-based TCP control socket at this address.\n\n\
+                     Send \"dump\" (or \"dump <seconds>\") to write the playout history\n\
+                     to --history-dir; the reply is \"ok <path>\" or \"error <reason>\".\n\
+                     Example: echo dump | nc 127.0.0.1 9201"
+    )]
+    control_bind: Option<String>,
+
+    /// Prometheus metrics bind address (serves `GET /metrics` and
+    /// `GET /stats`).
     #[arg(
         long,
         default_value = "127.0.0.1:9200",
         help = "Prometheus metrics bind address",
         long_help = "Bind address for the Prometheus metrics endpoint.\n\n\
-                     Metrics are exposed via HTTP at GET /metrics."
+                     Metrics are exposed via HTTP at GET /metrics. GET /stats returns\n\
+                     the reception stats as text, with the RTP clock rate and frame\n\
+                     duration (and whether they were detected or configured) and the\n\
+                     CNAME of each sender seen in RTCP SDES."
     )]
     metrics_bind: String,
 
+    /// Validate stream correctness instead of playing audio
+    #[arg(
+        long,
+        help = "Validate stream correctness instead of playing audio",
+        long_help = "Run in stream validation mode.\n\n\
+                     No audio is played. Packets are checked for timestamp monotonicity,\n\
+                     a constant timestamp step, SSRC and payload type constancy, marker\n\
+                     bits only on the first packet or after a timestamp gap, and\n\
+                     decodability. When the stream goes idle a pass/fail report is logged\n\
+                     and the receiver exits (exit status 1 on failure)."
+    )]
+    validate: bool,
+
     /// Coloring
     #[arg(
         long,
@@ -76,52 +550,406 @@ struct Args {
                      never: Disable colors."
     )]
     color: ColorArg,
+
+    /// With --version, print the full build report
+    #[arg(
+        long,
+        help = "With --version, also print crate versions, git commit, and features",
+        long_help = "Use as --version --verbose to print the versions of all linked crates,\n\
+                     the git commit, and the Cargo features this binary was built with."
+    )]
+    verbose: bool,
 }
 
-/// Capture version number from Cargo.toml
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// How long the stream must be silent before validation mode reports
+const VALIDATE_IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often reception stats are logged
+const STATS_LOG_INTERVAL: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // ---
+    let build = receiver::build_info();
+    handle_verbose_version(&build);
     let args = Args::parse();
     init_tracing(args.color.into())?;
-    info!("Starting RTP Opus receiver v{VERSION}");
+    info!("Starting {build}");
     info!("Listening on port: {}", args.port);
-    info!("Output device: {}", "default");
-    info!("Jitter buffer depth: {}ms", args.buffer_depth_ms);
+    info!("Transport: {}", Transport::from(args.transport));
+    info!(
+        "SRTP: {}",
+        if args.srtp_key.is_some() {
+            "AES_CM_128_HMAC_SHA1_80"
+        } else {
+            "off"
+        }
+    );
+    if let Some(group) = args.multicast_group {
+        info!(
+            "Multicast group: {} on {}",
+            group,
+            args.mcast_if
+                .map_or("the default interface".to_string(), |i| i.to_string())
+        );
+    }
+    info!(
+        "Output device: {}",
+        if cfg!(feature = "playback") {
+            "default"
+        } else {
+            "none (built without playback)"
+        }
+    );
+    if args.jitter_mode == JitterModeArg::Adaptive && args.jitter_min > args.jitter_max {
+        anyhow::bail!(
+            "--jitter-min ({}ms) is above --jitter-max ({}ms)",
+            args.jitter_min,
+            args.jitter_max
+        );
+    }
+    match args.jitter_mode {
+        JitterModeArg::Fixed => info!("Jitter buffer depth: {}ms", args.buffer_depth_ms),
+        JitterModeArg::Adaptive => info!(
+            "Jitter buffer depth: {}ms, adaptive within {}-{}ms",
+            args.buffer_depth_ms, args.jitter_min, args.jitter_max
+        ),
+    }
+    info!("Prime mode: {:?}", args.prime_mode);
+    info!("Opus payload type: {}", args.opus_payload_type);
+    if let Some(pt) = args.expect_payload_type {
+        info!("Accepting payload type {} only", pt);
+    }
+    if args.strict_rtp {
+        info!("Strict RTP parsing");
+    }
+    match args.clock_rate {
+        Some(rate) => info!("Clock rate: {rate}Hz"),
+        None => info!("Clock rate: auto-detect"),
+    }
+    info!("Max datagram size: {} bytes", args.max_datagram_size);
+    // Replies go back over UDP only, and are not protected by SRTP
+    let refresh_after_packets = match (args.transport, args.refresh_after_packets) {
+        (_, 0) => {
+            info!("Decoder refresh requests: off");
+            0
+        }
+        (TransportArg::Tcp, _) => {
+            info!("Decoder refresh requests: off (not supported over TCP)");
+            0
+        }
+        (TransportArg::Udp, _) if args.srtp_key.is_some() => {
+            info!("Decoder refresh requests: off (not supported with SRTP)");
+            0
+        }
+        (TransportArg::Udp, n) => {
+            info!("Decoder refresh requests: after {n} lost packets");
+            n
+        }
+    };
+    let nack = match args.transport {
+        _ if !args.nack => false,
+        TransportArg::Tcp => {
+            info!("NACK: off (not supported over TCP)");
+            false
+        }
+        TransportArg::Udp if args.srtp_key.is_some() => {
+            info!("NACK: off (not supported with SRTP)");
+            false
+        }
+        TransportArg::Udp => {
+            info!("NACK: lost packets younger than {}ms", args.buffer_depth_ms);
+            true
+        }
+    };
+    let receiver_report_interval = match args.transport {
+        _ if args.rtcp_rr_interval == 0 => None,
+        TransportArg::Tcp => {
+            info!("Receiver reports: off (not supported over TCP)");
+            None
+        }
+        TransportArg::Udp if args.srtp_key.is_some() => {
+            info!("Receiver reports: off (not supported with SRTP)");
+            None
+        }
+        TransportArg::Udp => {
+            info!("Receiver reports: every {}s", args.rtcp_rr_interval);
+            Some(Duration::from_secs(args.rtcp_rr_interval))
+        }
+    };
+    let extended_reports = match receiver_report_interval {
+        _ if !args.rtcp_xr => false,
+        None => {
+            info!("Extended reports: off (receiver reports are off)");
+            false
+        }
+        Some(_) => {
+            info!("Extended reports: Loss RLE and VoIP Metrics");
+            true
+        }
+    };
+    let exit_on_bye = match args.transport {
+        _ if !args.exit_on_bye => false,
+        TransportArg::Tcp => {
+            info!("Exit on BYE: off (not supported over TCP)");
+            false
+        }
+        TransportArg::Udp if args.srtp_key.is_some() => {
+            info!("Exit on BYE: off (not supported with SRTP)");
+            false
+        }
+        TransportArg::Udp => {
+            info!("Exit on BYE: on");
+            true
+        }
+    };
+    let rtcp_mux = match args.transport {
+        _ if !args.no_rtcp_mux => true,
+        TransportArg::Tcp => {
+            info!("RTCP port: off (not supported over TCP)");
+            true
+        }
+        TransportArg::Udp if args.srtp_key.is_some() => {
+            info!("RTCP port: off (not supported with SRTP)");
+            true
+        }
+        TransportArg::Udp => false,
+    };
+    info!("Anomaly log interval: {}s", args.anomaly_log_interval_secs);
+    info!("Payload verification: {}", args.verify_payloads);
+    match &args.history_dir {
+        Some(dir) => info!(
+            "Playout history: last {}s dumped to {}",
+            args.history_secs,
+            dir.display()
+        ),
+        None => info!("Playout history: off"),
+    }
+    if let Some(path) = &args.rtpdump_out {
+        info!("Packet capture: {}", path.display());
+    }
     info!("Metrics bind: {}", args.metrics_bind);
 
     let metrics = MetricsContext::new("receiver")?;
-    let metrics_bind = args.metrics_bind.parse().context("invalid metrics bind")?;
-    let _metrics_task = metrics.spawn_metrics_server(MetricsServerConfig::new(metrics_bind));
+    metrics.set_build_info(&build);
+    let mut stats = ReceiverStats::new(STATS_LOG_INTERVAL);
+    #[cfg(feature = "metrics-server")]
+    {
+        let metrics_bind = args.metrics_bind.parse().context("invalid metrics bind")?;
+        let handle = stats.handle();
+        let server =
+            MetricsServerConfig::new(metrics_bind).with_stats(move || handle.snapshot().details());
+        let _metrics_task = metrics.spawn_metrics_server(server);
+    }
+    #[cfg(not(feature = "metrics-server"))]
+    tracing::warn!("Built without the metrics-server feature, not serving metrics");
 
-    // Create decoder and network receiver
-    let mut decoder = OpusDecoderWrapper::new().context("failed to create decoder")?;
-    let mut receiver = RtpReceiver::new(args.port)
+    // Create decoders and network receiver
+    let mut decoders = DecoderSet::new(args.opus_payload_type);
+    let punch = match &args.punch {
+        Some(target) => Some(
+            tokio::net::lookup_host(target.as_str())
+                .await
+                .with_context(|| format!("failed to resolve {}", target))?
+                .find(SocketAddr::is_ipv4)
+                .with_context(|| format!("{} has no IPv4 address", target))?,
+        ),
+        None => None,
+    };
+    let network_config = ReceiverNetworkConfig {
+        max_datagram_size: args.max_datagram_size as usize,
+        multicast_group: args.multicast_group,
+        multicast_interface: args.mcast_if.unwrap_or(Ipv4Addr::UNSPECIFIED),
+        recv_buffer_bytes: args.so_rcvbuf.map(|bytes| bytes as usize),
+        transport: args.transport.into(),
+        srtp_key: args.srtp_key,
+        punch,
+        rtcp_mux,
+        session_bandwidth: args.session_bw,
+        ..Default::default()
+    };
+    let mut receiver = RtpReceiver::with_config(args.port, network_config)
         .await
         .context("failed to create receiver")?;
-
-    // Create audio player
-    let mut player = AudioPlayer::new().context("failed to create audio player")?;
+    if let Some(path) = &args.rtpdump_out {
+        let source = match receiver.local_addr()? {
+            SocketAddr::V4(addr) => addr,
+            SocketAddr::V6(addr) => SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, addr.port()),
+        };
+        let dump = RtpdumpWriter::create(path, source)?;
+        receiver.set_rtpdump(dump.boxed());
+    }
 
     // Configure jitter buffer
     let jitter_config = JitterBufferConfig {
         depth_ms: args.buffer_depth_ms,
         max_packets: 100,
+        prime_mode: args.prime_mode.into(),
+        adaptive: (args.jitter_mode == JitterModeArg::Adaptive).then_some(AdaptiveDepthConfig {
+            min_ms: args.jitter_min,
+            max_ms: args.jitter_max,
+        }),
+        max_wait_ms: args.jitter_max_wait,
+        ..Default::default()
     };
 
+    if args.validate {
+        info!("Validation mode: waiting for stream...");
+        receiver.set_verify_payloads(args.verify_payloads);
+        receiver.set_expected_payload_type(args.expect_payload_type);
+        receiver.set_parse_mode(parse_mode(args.strict_rtp));
+        let report = validate_loop(
+            &mut receiver,
+            &mut decoders,
+            jitter_config,
+            VALIDATE_IDLE_TIMEOUT,
+        )
+        .await?;
+
+        info!(
+            "Checked {} packets, timestamp step {:?} ({:?} ms frames)",
+            report.packets, report.timestamp_step, report.frame_duration_ms
+        );
+        if let (Some(min), Some(mean), Some(max)) = (
+            report.arrivals.min,
+            report.arrivals.mean,
+            report.arrivals.max,
+        ) {
+            info!(
+                "Inter-arrival: min {:.1}ms, mean {:.1}ms, max {:.1}ms",
+                min.as_secs_f64() * 1000.0,
+                mean.as_secs_f64() * 1000.0,
+                max.as_secs_f64() * 1000.0
+            );
+        }
+        for violation in &report.violations {
+            tracing::error!("{violation}");
+        }
+        finish_rtpdump(&mut receiver, args.rtpdump_out.as_deref())?;
+        let crc_mismatches = receiver.payload_crc_mismatches();
+        if crc_mismatches > 0 {
+            tracing::error!("{crc_mismatches} packets failed the payload CRC check");
+        }
+
+        if !report.passed() || crc_mismatches > 0 {
+            tracing::error!("Validation FAILED: {} violations", report.violations.len());
+            std::process::exit(1);
+        }
+        info!("Validation PASSED");
+        return Ok(());
+    }
+
     info!("Ready to receive audio...");
 
+    let _stats_ticker = stats.spawn_ticker();
+
+    // Playout history with its automatic and on-demand dump triggers
+    let history = args
+        .history_dir
+        .as_ref()
+        .map(|_| Arc::new(PlayoutHistory::new(DEFAULT_HISTORY_CAPACITY)));
+    if let (Some(history), Some(dir)) = (&history, &args.history_dir) {
+        let dump_config = HistoryDumpConfig {
+            dir: dir.clone(),
+            window: Duration::from_secs(args.history_secs),
+            ..Default::default()
+        };
+        spawn_underrun_monitor(history.clone(), dump_config.clone());
+        if let Some(bind) = &args.control_bind {
+            let bind = bind.parse().context("invalid control bind")?;
+            let server = ControlServer::bind(bind).await?;
+            info!("Control socket: {}", server.local_addr()?);
+            server.spawn(history.clone(), dump_config);
+        }
+    }
+
+    let options = ReceiveOptions {
+        jitter: jitter_config,
+        clock_rate: args
+            .clock_rate
+            .map_or(ClockRateConfig::Auto, ClockRateConfig::Fixed),
+        idle_timeout: None,
+        refresh: (refresh_after_packets > 0).then(|| RefreshConfig {
+            lost_packets: refresh_after_packets,
+            ..Default::default()
+        }),
+        nack: nack.then(|| NackConfig {
+            max_age: Duration::from_millis(args.buffer_depth_ms as u64),
+            session_bandwidth: args.session_bw,
+            ..Default::default()
+        }),
+        receiver_report_interval,
+        extended_reports,
+        ssrc_confirm_packets: None,
+        exit_on_bye,
+        anomaly_log: AnomalyLogConfig {
+            interval: Duration::from_secs(args.anomaly_log_interval_secs),
+        },
+        verify_payloads: args.verify_payloads,
+        expect_payload_type: args.expect_payload_type,
+        parse_mode: parse_mode(args.strict_rtp),
+        abs_send_time_id: args.abs_send_time_id,
+        transport_cc_id: args.transport_cc_id,
+        history: history.clone(),
+    };
+
+    // Open the audio device while packets are already being buffered
+    #[cfg(feature = "playback")]
+    let player_setup = async {
+        tokio::task::spawn_blocking(move || match history {
+            Some(history) => AudioPlayer::with_history(history),
+            None => AudioPlayer::new(),
+        })
+        .await
+        .context("audio setup task failed")?
+        .context("failed to create audio player")
+    };
+    #[cfg(not(feature = "playback"))]
+    let player_setup = {
+        drop(history);
+        tracing::warn!("Built without the playback feature, discarding decoded audio");
+        async { Ok(receiver::NullSink::new()) }
+    };
+
     // Run receiver loop
-    receive_loop(
+    receive_loop_with_setup(
         &mut receiver,
-        &mut decoder,
-        &mut player,
-        jitter_config,
+        &mut decoders,
+        player_setup,
+        &mut stats,
+        &options,
         &metrics,
     )
     .await?;
+    finish_rtpdump(&mut receiver, args.rtpdump_out.as_deref())?;
+
+    Ok(())
+}
 
+/// Returns the parse mode `--strict-rtp` asks for.
+fn parse_mode(strict: bool) -> RtpParseMode {
+    // ---
+    if strict {
+        RtpParseMode::Strict
+    } else {
+        RtpParseMode::Lenient
+    }
+}
+
+/// Closes the `--rtpdump-out` capture, if one is still being written, and
+/// logs what it holds.
+fn finish_rtpdump(receiver: &mut RtpReceiver, path: Option<&Path>) -> Result<()> {
+    // ---
+    let (Some(dump), Some(path)) = (receiver.take_rtpdump(), path) else {
+        return Ok(());
+    };
+    info!(
+        "Captured {} packets, {} bytes to {}",
+        dump.packets_written(),
+        dump.bytes_written(),
+        path.display()
+    );
+    dump.into_inner()
+        .with_context(|| format!("failed to write rtpdump file {}", path.display()))?;
     Ok(())
 }
@@ -0,0 +1,312 @@
+//! RTP clock rate detection.
+//!
+//! Without SDP the receiver cannot know the RTP clock rate of a stream.
+//! RFC 7587 mandates a 48 kHz clock for Opus, while this project's sender
+//! uses the 16 kHz decode rate, and G.711 sources use 8 kHz. The detector
+//! infers the rate from the timestamp advance per sequence step over the
+//! first packets of a session, so transit and media-time math stay correct.
+
+use std::time::Duration;
+
+use crate::codec::{FRAME_DURATION_MS, SAMPLE_RATE};
+
+/// Packets observed before the clock rate is locked in.
+pub const DETECTION_PACKETS: usize = 50;
+
+/// Largest sequence step used for a delta (larger gaps are ignored).
+const MAX_SEQUENCE_STEP: u16 = 10;
+
+/// Candidate clock rates, in order of preference.
+const CLOCK_RATES: [u32; 3] = [48000, 16000, 8000];
+
+/// Candidate frame durations in microseconds, in order of preference.
+///
+/// 20ms is by far the most common, so it wins any ambiguity (e.g. 320
+/// ticks is 16 kHz × 20ms rather than 8 kHz × 40ms).
+const FRAME_DURATIONS_US: [u64; 6] = [20_000, 10_000, 40_000, 60_000, 5_000, 2_500];
+
+/// How the receiver learns the RTP clock rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClockRateConfig {
+    /// Infer the clock rate from the first packets of the stream.
+    #[default]
+    Auto,
+
+    /// Use the given clock rate in Hz; detection is skipped.
+    Fixed(u32),
+}
+
+/// RTP clock parameters of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamClock {
+    // ---
+    /// RTP timestamp ticks per second
+    pub clock_rate: u32,
+
+    /// Media duration of one packet
+    pub frame_duration: Duration,
+
+    /// Whether the values were inferred from the stream
+    pub detected: bool,
+}
+
+impl StreamClock {
+    // ---
+    /// Returns the RTP timestamp advance of one frame.
+    pub fn ticks_per_frame(&self) -> u32 {
+        // ---
+        (self.clock_rate as u128 * self.frame_duration.as_micros() / 1_000_000) as u32
+    }
+
+    /// Converts a number of RTP timestamp ticks to media time.
+    pub fn media_duration(&self, ticks: u32) -> Duration {
+        // ---
+        Duration::from_nanos(ticks as u64 * 1_000_000_000 / self.clock_rate.max(1) as u64)
+    }
+
+    /// Matches a per-packet timestamp advance against known
+    /// clock rate and frame duration combinations.
+    ///
+    /// Returns `None` if no combination produces `ticks`.
+    pub fn infer(ticks: u32) -> Option<Self> {
+        // ---
+        FRAME_DURATIONS_US.iter().find_map(|&frame_us| {
+            CLOCK_RATES
+                .iter()
+                .find(|&&rate| rate as u64 * frame_us / 1_000_000 == ticks as u64)
+                .map(|&clock_rate| Self {
+                    clock_rate,
+                    frame_duration: Duration::from_micros(frame_us),
+                    detected: true,
+                })
+        })
+    }
+}
+
+/// Infers the RTP clock rate from timestamp deltas.
+///
+/// Collects the timestamp advance per sequence step over the first
+/// [`DETECTION_PACKETS`] packets, then locks in the combination matching the
+/// median step. Reordered packets and large gaps are skipped; the median
+/// tolerates occasional DTX timestamp jumps.
+#[derive(Debug)]
+pub struct ClockRateDetector {
+    // ---
+    /// Packets to observe before locking
+    window: usize,
+
+    /// Packets observed so far
+    observed: usize,
+
+    /// Sequence number and timestamp of the previous packet
+    last: Option<(u16, u32)>,
+
+    /// Timestamp advance per sequence step
+    deltas: Vec<u32>,
+
+    /// Locked-in clock, once known
+    clock: Option<StreamClock>,
+}
+
+impl ClockRateDetector {
+    // ---
+    /// Creates a detector. A fixed clock rate is locked in immediately.
+    pub fn new(config: ClockRateConfig) -> Self {
+        // ---
+        Self::with_window(config, DETECTION_PACKETS)
+    }
+
+    /// Creates a detector that locks after `window` packets.
+    pub fn with_window(config: ClockRateConfig, window: usize) -> Self {
+        // ---
+        let clock = match config {
+            ClockRateConfig::Auto => None,
+            ClockRateConfig::Fixed(clock_rate) => Some(StreamClock {
+                clock_rate,
+                frame_duration: Duration::from_millis(FRAME_DURATION_MS as u64),
+                detected: false,
+            }),
+        };
+
+        Self {
+            window: window.max(2),
+            observed: 0,
+            last: None,
+            deltas: Vec::with_capacity(window),
+            clock,
+        }
+    }
+
+    /// Returns the stream clock once it is known.
+    pub fn clock(&self) -> Option<StreamClock> {
+        // ---
+        self.clock
+    }
+
+    /// Observes a received packet.
+    ///
+    /// Returns the clock on the packet that locks it in, and `None`
+    /// otherwise (including every packet after locking).
+    pub fn observe(&mut self, sequence: u16, timestamp: u32) -> Option<StreamClock> {
+        // ---
+        if self.clock.is_some() {
+            return None;
+        }
+
+        if let Some((last_seq, last_ts)) = self.last {
+            let step = sequence.wrapping_sub(last_seq);
+            let advance = timestamp.wrapping_sub(last_ts);
+            if (1..=MAX_SEQUENCE_STEP).contains(&step) && advance > 0 && advance < 0x8000_0000 {
+                self.deltas.push(advance / step as u32);
+            }
+        }
+        if self
+            .last
+            .is_none_or(|(last_seq, _)| sequence.wrapping_sub(last_seq) < 0x8000)
+        {
+            self.last = Some((sequence, timestamp));
+        }
+
+        self.observed += 1;
+        if self.observed < self.window || self.deltas.is_empty() {
+            return None;
+        }
+
+        self.deltas.sort_unstable();
+        let median = self.deltas[self.deltas.len() / 2];
+        let clock = StreamClock::infer(median).unwrap_or_else(|| {
+            // Unknown framing: keep the default rate so media time stays sane
+            tracing::warn!(
+                "Timestamp step {} matches no known clock rate, assuming {}Hz",
+                median,
+                SAMPLE_RATE
+            );
+            StreamClock {
+                clock_rate: SAMPLE_RATE,
+                frame_duration: Duration::from_secs_f64(median as f64 / SAMPLE_RATE as f64),
+                detected: false,
+            }
+        });
+
+        self.deltas = Vec::new();
+        self.clock = Some(clock);
+        Some(clock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    /// Feeds `count` in-order packets with the given timestamp step.
+    fn feed(detector: &mut ClockRateDetector, step: u32, count: u16) -> Option<StreamClock> {
+        // ---
+        let mut locked = None;
+        for seq in 0..count {
+            if let Some(clock) = detector.observe(seq.wrapping_add(1000), seq as u32 * step) {
+                locked = Some(clock);
+            }
+        }
+        locked
+    }
+
+    #[test]
+    fn test_infers_16khz_20ms() {
+        // ---
+        let mut detector = ClockRateDetector::new(ClockRateConfig::Auto);
+        let clock = feed(&mut detector, 320, 50).expect("locked");
+
+        assert_eq!(clock.clock_rate, 16000);
+        assert_eq!(clock.frame_duration, Duration::from_millis(20));
+        assert!(clock.detected);
+    }
+
+    #[test]
+    fn test_infers_48khz_20ms() {
+        // ---
+        let mut detector = ClockRateDetector::new(ClockRateConfig::Auto);
+        let clock = feed(&mut detector, 960, 50).expect("locked");
+
+        assert_eq!(clock.clock_rate, 48000);
+        assert_eq!(clock.frame_duration, Duration::from_millis(20));
+        assert_eq!(clock.ticks_per_frame(), 960);
+    }
+
+    #[test]
+    fn test_known_steps() {
+        // ---
+        let cases = [
+            (160, 8000, 20),
+            (320, 16000, 20),
+            (480, 48000, 10),
+            (960, 48000, 20),
+            (2880, 48000, 60),
+        ];
+        for (ticks, rate, frame_ms) in cases {
+            let clock = StreamClock::infer(ticks).expect("known step");
+            assert_eq!(clock.clock_rate, rate, "ticks={ticks}");
+            assert_eq!(clock.frame_duration, Duration::from_millis(frame_ms));
+        }
+        assert!(StreamClock::infer(321).is_none());
+    }
+
+    #[test]
+    fn test_waits_for_window_and_locks_once() {
+        // ---
+        let mut detector = ClockRateDetector::new(ClockRateConfig::Auto);
+
+        assert!(feed(&mut detector, 960, 49).is_none());
+        assert!(detector.clock().is_none());
+        assert!(detector.observe(1049, 49 * 960).is_some());
+        assert!(detector.observe(1050, 50 * 960).is_none());
+        assert_eq!(detector.clock().unwrap().clock_rate, 48000);
+    }
+
+    #[test]
+    fn test_tolerates_loss_reordering_and_dtx() {
+        // ---
+        let mut detector = ClockRateDetector::with_window(ClockRateConfig::Auto, 10);
+        let packets = [
+            (0, 0),
+            (1, 960),
+            (3, 2880), // One packet lost
+            (2, 1920), // Late arrival
+            (4, 3840),
+            (5, 96000), // DTX gap
+            (6, 96960),
+            (7, 97920),
+            (8, 98880),
+            (9, 99840),
+        ];
+
+        let mut locked = None;
+        for (seq, ts) in packets {
+            locked = locked.or(detector.observe(seq, ts));
+        }
+
+        assert_eq!(locked.expect("locked").clock_rate, 48000);
+    }
+
+    #[test]
+    fn test_fixed_rate_overrides_detection() {
+        // ---
+        let mut detector = ClockRateDetector::new(ClockRateConfig::Fixed(8000));
+
+        assert_eq!(detector.clock().unwrap().clock_rate, 8000);
+        assert!(feed(&mut detector, 960, 60).is_none());
+        assert_eq!(detector.clock().unwrap().clock_rate, 8000);
+        assert!(!detector.clock().unwrap().detected);
+    }
+
+    #[test]
+    fn test_media_duration_uses_detected_rate() {
+        // ---
+        let mut detector = ClockRateDetector::new(ClockRateConfig::Auto);
+        let clock = feed(&mut detector, 960, 50).expect("locked");
+
+        // One second of 48 kHz ticks is one second, not three
+        assert_eq!(clock.media_duration(48000), Duration::from_secs(1));
+        assert_eq!(clock.media_duration(960), Duration::from_millis(20));
+    }
+}
@@ -1,7 +1,8 @@
 //! Opus audio codec decoding.
 //!
 //! Provides a wrapper around the Opus decoder for decompressing
-//! Opus-encoded audio back to PCM samples.
+//! Opus-encoded audio back to PCM samples, and the `AudioDecoder` trait
+//! that lets the receive pipeline work with any payload codec.
 
 use anyhow::{Context, Result};
 use opus::{Channels, Decoder};
@@ -13,12 +14,46 @@ pub const SAMPLE_RATE: u32 = 16000;
 #[allow(dead_code)]
 pub const CHANNELS: usize = 1;
 
-/// Frame duration in milliseconds
+/// Default frame duration in milliseconds
 pub const FRAME_DURATION_MS: usize = 20;
 
-/// Samples per frame (20ms at 16kHz)
+/// Samples per frame at the default duration (20ms at 16kHz)
 pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as usize * FRAME_DURATION_MS) / 1000;
 
+/// Samples in the longest Opus packet (120ms at 16kHz)
+pub const MAX_FRAME_SAMPLES: usize = SAMPLE_RATE as usize * 120 / 1000;
+
+/// Payload decoder used by the receive pipeline.
+///
+/// Implementations produce mono PCM at [`SAMPLE_RATE`] so every codec feeds
+/// the same playout path regardless of its native clock rate.
+pub trait AudioDecoder {
+    // ---
+    /// Decodes one RTP payload to PCM samples.
+    fn decode(&mut self, data: &[u8]) -> Result<Vec<i16>>;
+
+    /// Synthesizes a frame to cover a lost packet.
+    fn conceal_loss(&mut self) -> Result<Vec<i16>>;
+
+    /// Rebuilds the frame lost just before `next` from redundancy carried
+    /// in `next`.
+    ///
+    /// Returns `Ok(None)` when `next` carries no such redundancy; the caller
+    /// then falls back to [`conceal_loss`](Self::conceal_loss).
+    fn recover_from_next(&mut self, next: &[u8]) -> Result<Option<Vec<i16>>> {
+        // ---
+        let _ = next;
+        Ok(None)
+    }
+
+    /// Forgets the state carried from one frame to the next, for a new
+    /// stream.
+    fn reset(&mut self) -> Result<()> {
+        // ---
+        Ok(())
+    }
+}
+
 /// Opus decoder wrapper for audio decompression.
 ///
 /// Decodes Opus-compressed audio frames back to PCM samples (16-bit signed integers).
@@ -28,7 +63,8 @@ pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as usize * FRAME_DURATION_MS)
 ///
 /// - Sample Rate: 16kHz (wideband)
 /// - Channels: Mono
-/// - Frame Size: 20ms (320 samples)
+/// - Frame Size: whatever the sender used (20ms, 320 samples, by default);
+///   each packet decodes to its own duration
 ///
 /// # Example
 ///
@@ -42,6 +78,9 @@ pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as usize * FRAME_DURATION_MS)
 pub struct OpusDecoderWrapper {
     // ---
     decoder: Decoder,
+
+    /// Length of the most recent decoded frame, used for concealment
+    last_frame_len: usize,
 }
 
 impl OpusDecoderWrapper {
@@ -56,13 +95,17 @@ impl OpusDecoderWrapper {
         let decoder =
             Decoder::new(SAMPLE_RATE, Channels::Mono).context("failed to create Opus decoder")?;
 
-        Ok(Self { decoder })
+        Ok(Self {
+            decoder,
+            last_frame_len: SAMPLES_PER_FRAME,
+        })
     }
 
     /// Decodes an Opus frame to PCM samples.
     ///
-    /// Outputs exactly SAMPLES_PER_FRAME (320) samples regardless of
-    /// input size, as Opus is a constant frame size codec.
+    /// Outputs as many samples as the packet carries: 320 for a 20ms
+    /// frame, 160 for 10ms, 640 for 40ms, and 960 for 60ms. The sender's
+    /// frame duration therefore needs no configuration on this side.
     ///
     /// # Arguments
     ///
@@ -70,28 +113,26 @@ impl OpusDecoderWrapper {
     ///
     /// # Returns
     ///
-    /// Vector of 320 PCM samples (16-bit signed integers).
+    /// Vector of PCM samples (16-bit signed integers).
     ///
     /// # Errors
     ///
     /// Returns error if Opus decoding fails (corrupted data, invalid format).
     pub fn decode(&mut self, data: &[u8]) -> Result<Vec<i16>> {
         // ---
-        let mut output = vec![0i16; SAMPLES_PER_FRAME];
+        let mut output = vec![0i16; MAX_FRAME_SAMPLES];
 
         let decoded = self
             .decoder
             .decode(data, &mut output, false)
             .context("Opus decoding failed")?;
 
-        if decoded != SAMPLES_PER_FRAME {
-            anyhow::bail!(
-                "unexpected decoded frame size: expected {}, got {}",
-                SAMPLES_PER_FRAME,
-                decoded
-            );
+        if decoded == 0 {
+            anyhow::bail!("Opus packet decoded to no samples");
         }
 
+        output.truncate(decoded);
+        self.last_frame_len = decoded;
         Ok(output)
     }
 
@@ -103,30 +144,131 @@ impl OpusDecoderWrapper {
     ///
     /// # Returns
     ///
-    /// Vector of 320 concealed PCM samples.
+    /// Vector of concealed PCM samples, as long as the last decoded frame.
     ///
     /// # Errors
     ///
     /// Returns error if PLC generation fails.
     pub fn conceal_loss(&mut self) -> Result<Vec<i16>> {
         // ---
-        let mut output = vec![0i16; SAMPLES_PER_FRAME];
+        let mut output = vec![0i16; self.last_frame_len];
 
         let decoded = self
             .decoder
             .decode(&[], &mut output, true) // fec=true triggers PLC
             .context("Opus PLC failed")?;
 
-        if decoded != SAMPLES_PER_FRAME {
+        if decoded != self.last_frame_len {
             anyhow::bail!(
                 "unexpected PLC frame size: expected {}, got {}",
-                SAMPLES_PER_FRAME,
+                self.last_frame_len,
                 decoded
             );
         }
 
         Ok(output)
     }
+
+    /// Recovers the frame lost before `next` from its in-band FEC (LBRR) data.
+    ///
+    /// Only frames whose successor arrived can be recovered this way, so
+    /// FEC helps with isolated losses; longer gaps still need PLC.
+    ///
+    /// # Returns
+    ///
+    /// The recovered samples (as long as the last decoded frame), or `None`
+    /// if `next` was encoded without FEC.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if Opus FEC decoding fails.
+    pub fn recover_from_next(&mut self, next: &[u8]) -> Result<Option<Vec<i16>>> {
+        // ---
+        if !has_lbrr(next) {
+            return Ok(None);
+        }
+
+        let mut output = vec![0i16; self.last_frame_len];
+        let decoded = self
+            .decoder
+            .decode(next, &mut output, true)
+            .context("Opus FEC decoding failed")?;
+
+        output.truncate(decoded);
+        Ok(Some(output))
+    }
+
+    /// Resets the decoder to its freshly created state, for a new stream:
+    /// concealment no longer continues the previous stream's audio.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if Opus fails to reset its state.
+    pub fn reset(&mut self) -> Result<()> {
+        // ---
+        self.decoder
+            .reset_state()
+            .context("failed to reset Opus decoder")?;
+        self.last_frame_len = SAMPLES_PER_FRAME;
+        Ok(())
+    }
+}
+
+/// Returns whether an Opus packet carries in-band FEC (LBRR) for the
+/// previous frame.
+///
+/// Follows `opus_packet_has_lbrr` from libopus 1.5: the LBRR flag is the
+/// bit after the per-frame VAD flags at the start of the first SILK frame.
+/// CELT-only packets never carry LBRR. Code 3 packets (multiple frames of
+/// arbitrary size) are reported as having none.
+fn has_lbrr(packet: &[u8]) -> bool {
+    // ---
+    let Some(&toc) = packet.first() else {
+        return false;
+    };
+
+    let config = toc >> 3;
+    let frame_ms = match config {
+        0..=11 => [10, 20, 40, 60][config as usize % 4],
+        12..=15 => [10, 20][config as usize % 2],
+        _ => return false, // CELT-only
+    };
+    let silk_frames = (frame_ms / 20).max(1);
+
+    let first_frame = match toc & 0x03 {
+        0 | 1 => packet.get(1),
+        2 => match packet.get(1) {
+            Some(&len) if len < 252 => packet.get(2),
+            Some(_) => packet.get(3),
+            None => None,
+        },
+        _ => None,
+    };
+
+    first_frame.is_some_and(|&byte| (byte >> (7 - silk_frames)) & 1 == 1)
+}
+
+impl AudioDecoder for OpusDecoderWrapper {
+    // ---
+    fn decode(&mut self, data: &[u8]) -> Result<Vec<i16>> {
+        // ---
+        OpusDecoderWrapper::decode(self, data)
+    }
+
+    fn conceal_loss(&mut self) -> Result<Vec<i16>> {
+        // ---
+        OpusDecoderWrapper::conceal_loss(self)
+    }
+
+    fn recover_from_next(&mut self, next: &[u8]) -> Result<Option<Vec<i16>>> {
+        // ---
+        OpusDecoderWrapper::recover_from_next(self, next)
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        // ---
+        OpusDecoderWrapper::reset(self)
+    }
 }
 
 #[cfg(test)]
@@ -166,6 +308,62 @@ mod tests {
         assert_eq!(decoded.len(), SAMPLES_PER_FRAME);
     }
 
+    #[test]
+    fn test_decodes_every_frame_duration() {
+        // ---
+        use opus::{Application, Encoder};
+
+        let mut encoder = Encoder::new(SAMPLE_RATE, Channels::Mono, Application::Voip)
+            .expect("encoder creation failed");
+        let mut decoder = OpusDecoderWrapper::new().expect("decoder creation failed");
+
+        for samples in [160, 640, 960, 320] {
+            let mut encoded = vec![0u8; 4000];
+            let len = encoder
+                .encode(&vec![0i16; samples], &mut encoded)
+                .expect("encoding failed");
+
+            let decoded = decoder.decode(&encoded[..len]).expect("decode");
+            assert_eq!(decoded.len(), samples);
+
+            // Concealment covers the same duration as the last frame
+            let concealed = decoder.conceal_loss().expect("plc");
+            assert_eq!(concealed.len(), samples);
+        }
+    }
+
+    #[test]
+    fn test_fec_recovery_needs_lbrr() {
+        // ---
+        use opus::{Application, Encoder};
+
+        let voiced: Vec<i16> = (0..SAMPLES_PER_FRAME * 10)
+            .map(|i| ((i as f32 * 0.07).sin() * 8000.0) as i16)
+            .collect();
+        let encode_all = |fec: bool| -> Vec<Vec<u8>> {
+            let mut encoder = Encoder::new(SAMPLE_RATE, Channels::Mono, Application::Voip)
+                .expect("encoder creation failed");
+            encoder.set_inband_fec(fec).expect("fec");
+            encoder.set_packet_loss_perc(10).expect("loss");
+            voiced
+                .chunks_exact(SAMPLES_PER_FRAME)
+                .map(|frame| encoder.encode_vec(frame, 4000).expect("encode"))
+                .collect()
+        };
+
+        let mut decoder = OpusDecoderWrapper::new().expect("decoder creation failed");
+        let plain = encode_all(false);
+        decoder.decode(&plain[0]).expect("decode");
+        assert!(plain.iter().all(|p| !has_lbrr(p)));
+        assert!(decoder.recover_from_next(&plain[2]).expect("fec").is_none());
+
+        // LBRR starts with the second frame, once there is history to protect
+        let protected = encode_all(true);
+        assert!(protected[2..].iter().all(|p| has_lbrr(p)));
+        let recovered = decoder.recover_from_next(&protected[2]).expect("fec");
+        assert_eq!(recovered.map(|r| r.len()), Some(SAMPLES_PER_FRAME));
+    }
+
     #[test]
     fn test_packet_loss_concealment() {
         // ---
@@ -178,6 +376,29 @@ mod tests {
         assert_eq!(concealed.len(), SAMPLES_PER_FRAME);
     }
 
+    #[test]
+    fn test_reset_forgets_frame_duration() {
+        // ---
+        use opus::{Application, Encoder};
+
+        let mut encoder = Encoder::new(SAMPLE_RATE, Channels::Mono, Application::Voip)
+            .expect("encoder creation failed");
+        let mut encoded = vec![0u8; 4000];
+        let len = encoder
+            .encode(&[0i16; 640], &mut encoded)
+            .expect("encoding failed");
+
+        let mut decoder = OpusDecoderWrapper::new().expect("decoder creation failed");
+        decoder.decode(&encoded[..len]).expect("decode");
+        assert_eq!(decoder.conceal_loss().expect("PLC").len(), 640);
+
+        decoder.reset().expect("reset");
+        assert_eq!(
+            decoder.conceal_loss().expect("PLC").len(),
+            SAMPLES_PER_FRAME
+        );
+    }
+
     #[test]
     fn test_decode_invalid_data() {
         // ---
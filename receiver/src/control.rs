@@ -0,0 +1,298 @@
+//! Playout history dump triggers.
+//!
+//! A [`PlayoutHistory`] is only useful if it can be written out when
+//! something went wrong. Two triggers are provided: a line-based TCP
+//! control socket ([`ControlServer`]) for dumping on demand, and an
+//! underrun monitor ([`spawn_underrun_monitor`]) that dumps automatically
+//! when the output device runs dry repeatedly.
+//!
+//! # Control protocol
+//!
+//! One command per line; each gets a one-line reply:
+//!
+//! ```text
+//! dump            -> ok /path/to/playout-history-1760000000123-command.json
+//! dump 10         -> (same, last 10 seconds only)
+//! anything else   -> error unknown command: ...
+//! ```
+
+use anyhow::{Context, Result};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::stats::{DumpReason, PlayoutHistory, UnderrunBurstDetector, DEFAULT_HISTORY_WINDOW};
+
+/// Default number of underruns that make a burst
+pub const DEFAULT_UNDERRUN_BURST: usize = 5;
+
+/// Default span within which the burst underruns must fall
+pub const DEFAULT_UNDERRUN_BURST_WINDOW: Duration = Duration::from_secs(2);
+
+/// Default minimum time between automatic dumps
+pub const DEFAULT_DUMP_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// How often the underrun monitor checks the history
+const MONITOR_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Where and how much playout history is dumped, and when dumps happen
+/// automatically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryDumpConfig {
+    // ---
+    /// Directory snapshots are written to
+    pub dir: PathBuf,
+
+    /// Span of history written when a command gives none
+    pub window: Duration,
+
+    /// Underruns within `underrun_burst_window` that trigger a dump
+    pub underrun_burst: usize,
+
+    /// Span within which the burst underruns must fall
+    pub underrun_burst_window: Duration,
+
+    /// Minimum time between automatic dumps
+    pub cooldown: Duration,
+}
+
+impl Default for HistoryDumpConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            dir: PathBuf::from("."),
+            window: DEFAULT_HISTORY_WINDOW,
+            underrun_burst: DEFAULT_UNDERRUN_BURST,
+            underrun_burst_window: DEFAULT_UNDERRUN_BURST_WINDOW,
+            cooldown: DEFAULT_DUMP_COOLDOWN,
+        }
+    }
+}
+
+/// Snapshots the last `window` of `history` and writes it to `config.dir`.
+///
+/// File I/O runs on the blocking pool.
+///
+/// # Errors
+///
+/// Returns error if the snapshot cannot be written.
+pub async fn dump_history(
+    history: &PlayoutHistory,
+    config: &HistoryDumpConfig,
+    window: Duration,
+    reason: DumpReason,
+) -> Result<PathBuf> {
+    // ---
+    let snapshot = history.snapshot(window, reason);
+    let events = snapshot.events.len();
+    let dir = config.dir.clone();
+    let path = tokio::task::spawn_blocking(move || snapshot.write_to(&dir))
+        .await
+        .context("history dump task failed")??;
+
+    info!(
+        "Wrote playout history ({}, {} events) to {}",
+        reason.as_str(),
+        events,
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Watches `history` for underrun bursts and dumps it when one occurs.
+///
+/// Runs until aborted.
+pub fn spawn_underrun_monitor(
+    history: Arc<PlayoutHistory>,
+    config: HistoryDumpConfig,
+) -> JoinHandle<()> {
+    // ---
+    tokio::spawn(async move {
+        // ---
+        let mut detector = UnderrunBurstDetector::new(
+            config.underrun_burst,
+            config.underrun_burst_window,
+            config.cooldown,
+        );
+        let mut seen = history.underruns();
+        let mut ticker = tokio::time::interval(MONITOR_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+            let underruns = history.underruns();
+            let fired = detector.observe(underruns - seen, Instant::now());
+            seen = underruns;
+
+            if fired {
+                warn!("Underrun burst detected, dumping playout history");
+                let reason = DumpReason::UnderrunBurst;
+                if let Err(e) = dump_history(&history, &config, config.window, reason).await {
+                    warn!("Failed to dump playout history: {e:#}");
+                }
+            }
+        }
+    })
+}
+
+/// TCP control socket accepting playout history dump commands.
+pub struct ControlServer {
+    // ---
+    listener: TcpListener,
+}
+
+impl ControlServer {
+    // ---
+    /// Binds the control socket.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the address cannot be bound.
+    pub async fn bind(addr: SocketAddr) -> Result<Self> {
+        // ---
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind control socket to {}", addr))?;
+        Ok(Self { listener })
+    }
+
+    /// Returns the address the socket is bound to.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the socket address cannot be queried.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        // ---
+        self.listener
+            .local_addr()
+            .context("failed to query control socket address")
+    }
+
+    /// Serves commands until aborted, one task per connection.
+    pub fn spawn(self, history: Arc<PlayoutHistory>, config: HistoryDumpConfig) -> JoinHandle<()> {
+        // ---
+        tokio::spawn(async move {
+            // ---
+            loop {
+                let (stream, peer) = match self.listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Control socket accept failed: {e}");
+                        continue;
+                    }
+                };
+                let history = history.clone();
+                let config = config.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(stream, &history, &config).await {
+                        warn!("Control connection from {peer} failed: {e:#}");
+                    }
+                });
+            }
+        })
+    }
+}
+
+/// Answers commands on one control connection until it closes.
+async fn serve_connection(
+    stream: TcpStream,
+    history: &PlayoutHistory,
+    config: &HistoryDumpConfig,
+) -> Result<()> {
+    // ---
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let reply = match parse_command(&line, config.window) {
+            Ok(window) => match dump_history(history, config, window, DumpReason::Command).await {
+                Ok(path) => format!("ok {}\n", path.display()),
+                Err(e) => format!("error {e:#}\n"),
+            },
+            Err(e) => format!("error {e}\n"),
+        };
+        writer.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Parses a `dump [seconds]` command into the window to dump.
+fn parse_command(line: &str, default_window: Duration) -> Result<Duration> {
+    // ---
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (Some("dump"), None, None) => Ok(default_window),
+        (Some("dump"), Some(secs), None) => {
+            let secs: u64 = secs
+                .parse()
+                .with_context(|| format!("invalid dump window: {secs}"))?;
+            Ok(Duration::from_secs(secs))
+        }
+        _ => anyhow::bail!("unknown command: {}", line.trim()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::stats::PlayoutEventKind;
+
+    #[test]
+    fn test_parse_command() {
+        // ---
+        let default = Duration::from_secs(30);
+        assert_eq!(parse_command("dump", default).unwrap(), default);
+        assert_eq!(
+            parse_command(" dump 5 ", default).unwrap(),
+            Duration::from_secs(5)
+        );
+        assert!(parse_command("dump soon", default).is_err());
+        assert!(parse_command("dump 5 6", default).is_err());
+        assert!(parse_command("status", default).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dump_command_writes_snapshot() {
+        // ---
+        let dir = std::env::temp_dir().join(format!("playout-history-test-{}", std::process::id()));
+        let config = HistoryDumpConfig {
+            dir: dir.clone(),
+            ..Default::default()
+        };
+        let history = Arc::new(PlayoutHistory::new(64));
+        for seq in 0..10 {
+            history.record(PlayoutEventKind::Arrival, Some(seq), 60);
+        }
+
+        let server = ControlServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let task = server.spawn(history, config);
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        writer.write_all(b"dump\nbogus\n").await.unwrap();
+        let reply = lines.next_line().await.unwrap().unwrap();
+        let path = PathBuf::from(reply.strip_prefix("ok ").expect(&reply));
+        assert!(lines
+            .next_line()
+            .await
+            .unwrap()
+            .unwrap()
+            .starts_with("error"));
+        task.abort();
+
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert!(json.starts_with("{\"reason\":\"command\""));
+        assert_eq!(json.matches("\"arrival\"").count(), 10);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
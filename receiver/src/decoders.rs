@@ -0,0 +1,453 @@
+//! Per-payload-type decoder selection.
+//!
+//! Routes each packet to a decoder chosen by its RTP payload type, so one
+//! receiver can play Opus from our sender as well as G.711 from third-party
+//! tools without configuration. G.711 needs the `formats` feature; without
+//! it those payload types are dropped as unsupported.
+
+use anyhow::{Context, Result};
+use rtp_opus_common::RtpPacket;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+
+use crate::codec::{AudioDecoder, OpusDecoderWrapper};
+#[cfg(feature = "formats")]
+use crate::g711::{G711Decoder, G711Law, PAYLOAD_TYPE_PCMA, PAYLOAD_TYPE_PCMU};
+
+/// Decode counters for a single payload type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PayloadTypeStats {
+    // ---
+    /// Payloads decoded successfully
+    pub decoded: u64,
+
+    /// Payloads the decoder rejected
+    pub failed: u64,
+
+    /// Packets dropped because no decoder handles this payload type
+    pub unsupported: u64,
+}
+
+/// Longest sequence gap filled frame by frame; larger jumps are treated
+/// as a resync and not concealed.
+pub const MAX_RECOVERED_GAP: u16 = 5;
+
+/// How a frame missing from the playout sequence was filled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    // ---
+    /// Rebuilt from in-band FEC carried by the following packet
+    Fec,
+
+    /// Synthesized by packet loss concealment
+    Concealed,
+}
+
+/// A frame synthesized for a packet that never arrived.
+#[derive(Debug, Clone)]
+pub struct RecoveredFrame {
+    // ---
+    /// How the frame was produced
+    pub recovery: Recovery,
+
+    /// Decoded PCM samples
+    pub samples: Vec<i16>,
+}
+
+/// Set of decoders keyed by RTP payload type.
+///
+/// PT 0 maps to PCMU, PT 8 to PCMA, and the configured dynamic payload
+/// type to Opus. One decoder instance is created lazily per payload type
+/// seen so codec state (e.g. Opus PLC history) stays per-stream. Unknown
+/// payload types are dropped with a single warning each.
+pub struct DecoderSet {
+    // ---
+    opus_payload_type: u8,
+    decoders: HashMap<u8, Box<dyn AudioDecoder>>,
+    stats: BTreeMap<u8, PayloadTypeStats>,
+    last_payload_type: Option<u8>,
+    last_sequence: Option<u16>,
+}
+
+impl DecoderSet {
+    // ---
+    /// Creates an empty decoder set.
+    ///
+    /// # Arguments
+    ///
+    /// * `opus_payload_type` - Dynamic payload type carrying Opus
+    pub fn new(opus_payload_type: u8) -> Self {
+        // ---
+        Self {
+            opus_payload_type,
+            decoders: HashMap::new(),
+            stats: BTreeMap::new(),
+            last_payload_type: None,
+            last_sequence: None,
+        }
+    }
+
+    /// Decodes a packet with the decoder for its payload type.
+    ///
+    /// Returns `Ok(None)` if the payload type is not supported; the packet
+    /// should be dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the decoder cannot be created or rejects the payload.
+    pub fn decode(&mut self, packet: &RtpPacket) -> Result<Option<Vec<i16>>> {
+        // ---
+        let pt = packet.payload_type;
+        self.last_sequence = Some(packet.sequence);
+        if !self.ensure_decoder(pt)? {
+            let stats = self.stats.entry(pt).or_default();
+            if stats.unsupported == 0 {
+                warn!("Dropping packets with unsupported payload type {}", pt);
+            }
+            stats.unsupported += 1;
+            return Ok(None);
+        }
+
+        let Some(decoder) = self.decoders.get_mut(&pt) else {
+            return Ok(None);
+        };
+        let result = decoder.decode(&packet.payload);
+        let stats = self.stats.entry(pt).or_default();
+        match result {
+            Ok(samples) => {
+                stats.decoded += 1;
+                self.last_payload_type = Some(pt);
+                Ok(Some(samples))
+            }
+            Err(e) => {
+                stats.failed += 1;
+                self.last_payload_type = Some(pt);
+                Err(e)
+            }
+        }
+    }
+
+    /// Conceals a lost frame using the decoder of the most recent payload type.
+    ///
+    /// Returns `Ok(None)` if nothing has been decoded yet.
+    pub fn conceal_loss(&mut self) -> Result<Option<Vec<i16>>> {
+        // ---
+        let Some(pt) = self.last_payload_type else {
+            return Ok(None);
+        };
+        match self.decoders.get_mut(&pt) {
+            Some(decoder) => decoder.conceal_loss().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Fills the frames missing between the last decoded packet and `next`.
+    ///
+    /// Call before [`decode`](Self::decode) with the packet about to be
+    /// played. The frame immediately before `next` is rebuilt from its
+    /// in-band FEC when the codec carries it; every other missing frame is
+    /// concealed. Nothing is returned for gaps longer than
+    /// [`MAX_RECOVERED_GAP`], for reordered packets, or before the first
+    /// decode.
+    pub fn recover_gap(&mut self, next: &RtpPacket) -> Vec<RecoveredFrame> {
+        // ---
+        let missing = self.missing_before(next);
+        if missing == 0 || missing > MAX_RECOVERED_GAP {
+            return Vec::new();
+        }
+
+        let mut frames = Vec::with_capacity(missing as usize);
+        for _ in 1..missing {
+            match self.conceal_loss() {
+                Ok(Some(samples)) => frames.push(RecoveredFrame {
+                    recovery: Recovery::Concealed,
+                    samples,
+                }),
+                Ok(None) => return frames,
+                Err(e) => {
+                    warn!("PLC failed before seq={}: {}", next.sequence, e);
+                    return frames;
+                }
+            }
+        }
+
+        // FEC only applies within one payload type's decoder state
+        let last_frame = match self.recover_from(next) {
+            Some(samples) => Some(RecoveredFrame {
+                recovery: Recovery::Fec,
+                samples,
+            }),
+            None => self
+                .conceal_loss()
+                .ok()
+                .flatten()
+                .map(|samples| RecoveredFrame {
+                    recovery: Recovery::Concealed,
+                    samples,
+                }),
+        };
+        frames.extend(last_frame);
+        frames
+    }
+
+    /// Fills in the frame of `sequence`, a packet that never arrived.
+    ///
+    /// The frame is rebuilt from the in-band FEC of `next`, the packet
+    /// following it, when given and the codec carries it; it is concealed
+    /// otherwise. The frame then counts as played, so
+    /// [`recover_gap`](Self::recover_gap) does not fill it again. Returns
+    /// `None` before the first decode or if concealment fails.
+    pub fn recover_lost(
+        &mut self,
+        sequence: u16,
+        next: Option<&RtpPacket>,
+    ) -> Option<RecoveredFrame> {
+        // ---
+        self.last_sequence = Some(sequence);
+        if let Some(samples) = next.and_then(|next| self.recover_from(next)) {
+            return Some(RecoveredFrame {
+                recovery: Recovery::Fec,
+                samples,
+            });
+        }
+        match self.conceal_loss() {
+            Ok(samples) => samples.map(|samples| RecoveredFrame {
+                recovery: Recovery::Concealed,
+                samples,
+            }),
+            Err(e) => {
+                warn!("PLC failed for seq={}: {}", sequence, e);
+                None
+            }
+        }
+    }
+
+    /// Rebuilds the frame before `next` from its in-band FEC, if the codec
+    /// carries it and the frame before was of the same payload type.
+    fn recover_from(&mut self, next: &RtpPacket) -> Option<Vec<i16>> {
+        // ---
+        match self.decoders.get_mut(&next.payload_type) {
+            Some(decoder) if self.last_payload_type == Some(next.payload_type) => decoder
+                .recover_from_next(&next.payload)
+                .unwrap_or_else(|e| {
+                    warn!("FEC recovery from seq={} failed: {}", next.sequence, e);
+                    None
+                }),
+            _ => None,
+        }
+    }
+
+    /// Resets every decoder for a new stream, and forgets the last packet
+    /// decoded. Decode counters are kept.
+    pub fn reset(&mut self) {
+        // ---
+        for (pt, decoder) in &mut self.decoders {
+            if let Err(e) = decoder.reset() {
+                warn!("Failed to reset decoder for payload type {}: {}", pt, e);
+            }
+        }
+        self.last_payload_type = None;
+        self.last_sequence = None;
+    }
+
+    /// Returns how many packets are missing between the last decoded
+    /// packet and `next`.
+    ///
+    /// Returns 0 before the first decode and for packets that are not
+    /// ahead of the last one.
+    pub fn missing_before(&self, next: &RtpPacket) -> u16 {
+        // ---
+        let Some(last) = self.last_sequence else {
+            return 0;
+        };
+        let missing = next.sequence.wrapping_sub(last).wrapping_sub(1);
+        if missing < 0x8000 {
+            missing
+        } else {
+            0
+        }
+    }
+
+    /// Returns decode counters for every payload type seen.
+    pub fn stats(&self) -> &BTreeMap<u8, PayloadTypeStats> {
+        // ---
+        &self.stats
+    }
+
+    /// Creates the decoder for `pt` if needed; returns `false` if unsupported.
+    fn ensure_decoder(&mut self, pt: u8) -> Result<bool> {
+        // ---
+        if !self.decoders.contains_key(&pt) {
+            let decoder: Box<dyn AudioDecoder> = match pt {
+                #[cfg(feature = "formats")]
+                PAYLOAD_TYPE_PCMU => Box::new(G711Decoder::new(G711Law::MuLaw)),
+                #[cfg(feature = "formats")]
+                PAYLOAD_TYPE_PCMA => Box::new(G711Decoder::new(G711Law::ALaw)),
+                pt if pt == self.opus_payload_type => {
+                    Box::new(OpusDecoderWrapper::new().context("failed to create Opus decoder")?)
+                }
+                _ => return Ok(false),
+            };
+            info!("Payload type {}: created decoder", pt);
+            self.decoders.insert(pt, decoder);
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::codec::SAMPLES_PER_FRAME;
+    use rtp_opus_common::PAYLOAD_TYPE_OPUS;
+
+    fn opus_frame() -> Vec<u8> {
+        // ---
+        use opus::{Application, Channels, Encoder};
+
+        let mut encoder = Encoder::new(16000, Channels::Mono, Application::Voip)
+            .expect("encoder creation failed");
+        let mut encoded = vec![0u8; 4000];
+        let len = encoder
+            .encode(&[0i16; SAMPLES_PER_FRAME], &mut encoded)
+            .expect("encoding failed");
+        encoded.truncate(len);
+        encoded
+    }
+
+    fn packet(seq: u16, payload_type: u8, payload: Vec<u8>) -> RtpPacket {
+        // ---
+        let mut packet = RtpPacket::new(seq, seq as u32 * 320, 0x12345678, payload);
+        packet.payload_type = payload_type;
+        packet
+    }
+
+    #[cfg(feature = "formats")]
+    #[test]
+    fn test_interleaved_pcmu_and_opus() {
+        // ---
+        let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+        let opus = opus_frame();
+
+        for seq in 0..10 {
+            let p = if seq % 2 == 0 {
+                packet(seq, PAYLOAD_TYPE_PCMU, vec![0xFF; 160])
+            } else {
+                packet(seq, PAYLOAD_TYPE_OPUS, opus.clone())
+            };
+            let samples = decoders
+                .decode(&p)
+                .expect("decode failed")
+                .expect("payload type should be supported");
+            assert_eq!(samples.len(), SAMPLES_PER_FRAME);
+        }
+
+        let stats = decoders.stats();
+        assert_eq!(stats[&PAYLOAD_TYPE_PCMU].decoded, 5);
+        assert_eq!(stats[&PAYLOAD_TYPE_OPUS].decoded, 5);
+    }
+
+    #[cfg(feature = "formats")]
+    #[test]
+    fn test_pcma_and_custom_opus_payload_type() {
+        // ---
+        let mut decoders = DecoderSet::new(111);
+
+        let pcma = decoders
+            .decode(&packet(0, PAYLOAD_TYPE_PCMA, vec![0xD5; 160]))
+            .expect("decode failed");
+        let opus = decoders
+            .decode(&packet(1, 111, opus_frame()))
+            .expect("decode failed");
+
+        assert_eq!(pcma.map(|s| s.len()), Some(SAMPLES_PER_FRAME));
+        assert_eq!(opus.map(|s| s.len()), Some(SAMPLES_PER_FRAME));
+    }
+
+    #[test]
+    fn test_unknown_payload_type_dropped_and_counted() {
+        // ---
+        let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+
+        for seq in 0..3 {
+            let result = decoders.decode(&packet(seq, 18, vec![0; 20]));
+            assert!(matches!(result, Ok(None)));
+        }
+
+        assert_eq!(decoders.stats()[&18].unsupported, 3);
+        assert!(decoders.conceal_loss().expect("PLC failed").is_none());
+    }
+
+    #[test]
+    fn test_recover_gap_conceals_missing_frames() {
+        // ---
+        let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+        let opus = opus_frame();
+
+        // Nothing to recover before the first decode or without a gap
+        assert!(decoders
+            .recover_gap(&packet(5, PAYLOAD_TYPE_OPUS, opus.clone()))
+            .is_empty());
+        decoders
+            .decode(&packet(5, PAYLOAD_TYPE_OPUS, opus.clone()))
+            .unwrap();
+        assert!(decoders
+            .recover_gap(&packet(6, PAYLOAD_TYPE_OPUS, opus.clone()))
+            .is_empty());
+
+        // Three frames missing; no FEC in the payload, so all are concealed
+        let frames = decoders.recover_gap(&packet(9, PAYLOAD_TYPE_OPUS, opus.clone()));
+        assert_eq!(frames.len(), 3);
+        assert!(frames
+            .iter()
+            .all(|f| f.recovery == Recovery::Concealed && f.samples.len() == SAMPLES_PER_FRAME));
+
+        // A resync-sized jump and a late packet are left alone
+        assert!(decoders
+            .recover_gap(&packet(
+                5 + MAX_RECOVERED_GAP + 2,
+                PAYLOAD_TYPE_OPUS,
+                opus.clone()
+            ))
+            .is_empty());
+        assert!(decoders
+            .recover_gap(&packet(4, PAYLOAD_TYPE_OPUS, opus))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_recover_lost_counts_as_played() {
+        // ---
+        let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+        let opus = opus_frame();
+        assert!(decoders.recover_lost(4, None).is_none());
+
+        decoders
+            .decode(&packet(5, PAYLOAD_TYPE_OPUS, opus.clone()))
+            .unwrap();
+        let next = packet(7, PAYLOAD_TYPE_OPUS, opus);
+        let frame = decoders.recover_lost(6, Some(&next)).expect("recovered");
+        assert_eq!(frame.recovery, Recovery::Concealed);
+        assert_eq!(frame.samples.len(), SAMPLES_PER_FRAME);
+
+        // Nothing left missing before the next packet
+        assert_eq!(decoders.missing_before(&next), 0);
+        assert!(decoders.recover_gap(&next).is_empty());
+    }
+
+    #[cfg(feature = "formats")]
+    #[test]
+    fn test_conceal_uses_last_payload_type() {
+        // ---
+        let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+        decoders
+            .decode(&packet(0, PAYLOAD_TYPE_PCMU, vec![0xFF; 80]))
+            .expect("decode failed");
+
+        // Concealment matches the G.711 frame length, not the Opus one
+        let concealed = decoders.conceal_loss().expect("PLC failed");
+        assert_eq!(concealed.map(|s| s.len()), Some(160));
+    }
+}
@@ -0,0 +1,171 @@
+//! G.711 (PCMU / PCMA) decoding.
+//!
+//! Lets the receiver play streams from simple SIP test tools, which
+//! default to the static G.711 payload types. G.711 runs at 8kHz, so
+//! decoded audio is upsampled 2x to the playout rate.
+
+use anyhow::Result;
+
+use crate::codec::{AudioDecoder, SAMPLES_PER_FRAME};
+
+/// Static RTP payload type for G.711 μ-law (RFC 3551)
+pub const PAYLOAD_TYPE_PCMU: u8 = 0;
+
+/// Static RTP payload type for G.711 A-law (RFC 3551)
+pub const PAYLOAD_TYPE_PCMA: u8 = 8;
+
+/// G.711 companding law.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum G711Law {
+    /// μ-law (PCMU), used in North America and Japan
+    MuLaw,
+
+    /// A-law (PCMA), used elsewhere
+    ALaw,
+}
+
+/// G.711 decoder producing 16kHz PCM.
+///
+/// Each payload byte is one 8kHz sample, so a 20ms packet (160 bytes)
+/// decodes to a full 320-sample playout frame.
+#[derive(Debug)]
+pub struct G711Decoder {
+    // ---
+    law: G711Law,
+
+    /// Last decoded 8kHz sample, carried over for interpolation
+    last_sample: i16,
+
+    /// Length of the most recent decoded frame, used for concealment
+    last_frame_len: usize,
+}
+
+impl G711Decoder {
+    // ---
+    /// Creates a decoder for the given companding law.
+    pub fn new(law: G711Law) -> Self {
+        // ---
+        Self {
+            law,
+            last_sample: 0,
+            last_frame_len: SAMPLES_PER_FRAME,
+        }
+    }
+
+    fn expand(&self, byte: u8) -> i16 {
+        // ---
+        match self.law {
+            G711Law::MuLaw => ulaw_to_linear(byte),
+            G711Law::ALaw => alaw_to_linear(byte),
+        }
+    }
+}
+
+impl AudioDecoder for G711Decoder {
+    // ---
+    fn decode(&mut self, data: &[u8]) -> Result<Vec<i16>> {
+        // ---
+        if data.is_empty() {
+            anyhow::bail!("empty G.711 payload");
+        }
+
+        // 2x linear interpolation from 8kHz to 16kHz
+        let mut output = Vec::with_capacity(data.len() * 2);
+        for &byte in data {
+            let sample = self.expand(byte);
+            let mid = ((self.last_sample as i32 + sample as i32) / 2) as i16;
+            output.push(mid);
+            output.push(sample);
+            self.last_sample = sample;
+        }
+
+        self.last_frame_len = output.len();
+        Ok(output)
+    }
+
+    fn conceal_loss(&mut self) -> Result<Vec<i16>> {
+        // ---
+        // G.711 has no built-in PLC; fill the gap with silence
+        self.last_sample = 0;
+        Ok(vec![0i16; self.last_frame_len])
+    }
+
+    fn reset(&mut self) -> Result<()> {
+        // ---
+        self.last_sample = 0;
+        self.last_frame_len = SAMPLES_PER_FRAME;
+        Ok(())
+    }
+}
+
+/// Expands a μ-law byte to 16-bit linear PCM.
+fn ulaw_to_linear(byte: u8) -> i16 {
+    // ---
+    let u = !byte;
+    let exponent = (u >> 4) & 0x07;
+    let mantissa = (u & 0x0F) as i32;
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+
+    if u & 0x80 != 0 {
+        -magnitude as i16
+    } else {
+        magnitude as i16
+    }
+}
+
+/// Expands an A-law byte to 16-bit linear PCM.
+fn alaw_to_linear(byte: u8) -> i16 {
+    // ---
+    let a = byte ^ 0x55;
+    let exponent = (a >> 4) & 0x07;
+    let mantissa = (a & 0x0F) as i32;
+    let magnitude = if exponent == 0 {
+        (mantissa << 4) + 8
+    } else {
+        ((mantissa << 4) + 0x108) << (exponent - 1)
+    };
+
+    // A-law sets the sign bit for positive values
+    if a & 0x80 != 0 {
+        magnitude as i16
+    } else {
+        -magnitude as i16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_ulaw_reference_values() {
+        // ---
+        assert_eq!(ulaw_to_linear(0xFF), 0);
+        assert_eq!(ulaw_to_linear(0x7F), 0);
+        assert_eq!(ulaw_to_linear(0x80), 32124);
+        assert_eq!(ulaw_to_linear(0x00), -32124);
+    }
+
+    #[test]
+    fn test_alaw_reference_values() {
+        // ---
+        assert_eq!(alaw_to_linear(0xD5), 8);
+        assert_eq!(alaw_to_linear(0x55), -8);
+        assert_eq!(alaw_to_linear(0xAA), 32256);
+        assert_eq!(alaw_to_linear(0x2A), -32256);
+    }
+
+    #[test]
+    fn test_decode_upsamples_to_playout_frame() {
+        // ---
+        let mut decoder = G711Decoder::new(G711Law::MuLaw);
+        let pcm = decoder.decode(&[0xFF; 160]).expect("decode failed");
+
+        assert_eq!(pcm.len(), SAMPLES_PER_FRAME);
+        assert!(pcm.iter().all(|&s| s == 0));
+
+        let concealed = decoder.conceal_loss().expect("PLC failed");
+        assert_eq!(concealed.len(), SAMPLES_PER_FRAME);
+    }
+}
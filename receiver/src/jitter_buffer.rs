@@ -1,12 +1,202 @@
 //! Jitter buffer for RTP packet reordering and delay compensation.
 //!
-//! Implements a fixed-depth jitter buffer that compensates for network
-//! variance by buffering packets and playing them out in sequence order.
+//! Implements a jitter buffer that compensates for network variance by
+//! buffering packets and playing them out in sequence order. Its depth is
+//! fixed, or adapts to the interarrival jitter and late packets seen (see
+//! [`AdaptiveDepthConfig`]).
 
-use rtp_opus_common::RtpPacket;
-use std::collections::VecDeque;
+use rtp_opus_common::{
+    AnomalyClass, AnomalyLogger, JitterEstimator, RtpPacket, SeqTracker, SeqUpdate, MAX_MISORDER,
+};
+use std::collections::BTreeMap;
 use std::time::{Duration, Instant};
-use tracing::{debug, warn};
+use tracing::debug;
+
+use crate::clock::StreamClock;
+
+/// Default shallowest depth an adaptive jitter buffer shrinks to
+pub const DEFAULT_ADAPTIVE_MIN_MS: u32 = 20;
+
+/// Default deepest depth an adaptive jitter buffer grows to
+pub const DEFAULT_ADAPTIVE_MAX_MS: u32 = 200;
+
+/// Time between adjustments of an adaptive depth, each by one frame at
+/// most, so it cannot oscillate faster
+pub const ADAPTIVE_ADJUST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Depth wanted, in multiples of the interarrival jitter
+const JITTER_MULTIPLE: u32 = 4;
+
+/// Share of packets arriving late above which the depth grows whatever
+/// the jitter
+const LATE_RATE_GROW: f64 = 0.01;
+
+/// Frame duration assumed until the stream clock is known
+const DEFAULT_FRAME: Duration = Duration::from_millis(20);
+
+/// Default longest run of lost packets signalled for concealment
+pub const DEFAULT_MAX_CONCEALED: u16 = 5;
+
+/// Default occupancy beyond the target depth tolerated before catching up
+pub const DEFAULT_CATCH_UP_MARGIN_MS: u32 = 100;
+
+/// Default time occupancy stays beyond the margin before catching up
+pub const DEFAULT_CATCH_UP_GRACE_MS: u32 = 1000;
+
+/// Share of each frame skipped when playout accelerates to catch up
+pub const ACCELERATE_FRACTION: f64 = 0.1;
+
+/// What playout does when the buffer holds well beyond its target depth,
+/// as after a burst of packets delayed by a network stall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowStrategy {
+    /// Keep the excess, as added latency until the stream ends
+    #[default]
+    Keep,
+
+    /// Drop the oldest packets down to the target depth
+    DropOldest,
+
+    /// Release packets as [`PlayoutAction::Accelerate`], to play
+    /// [`ACCELERATE_FRACTION`] faster than real time, until back to the
+    /// target depth
+    Accelerate,
+}
+
+/// Packets buffered that end priming before a fixed depth has elapsed,
+/// unless [`JitterBufferConfig::prime_by`] says otherwise
+pub const DEFAULT_PRIME_PACKETS: usize = 3;
+
+/// What a priming jitter buffer waits for before playout starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimeBy {
+    /// This many milliseconds since the first packet of the priming phase
+    Duration(u32),
+
+    /// This many packets buffered
+    Packets(usize),
+
+    /// The milliseconds or the packets, whichever comes first
+    Either(u32, usize),
+}
+
+/// Policy deciding when a jitter buffer may begin playout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrimeMode {
+    /// Hold packets until priming is done, as
+    /// [`JitterBufferConfig::prime_by`] defines it, before releasing the
+    /// first one.
+    #[default]
+    WaitForDepth,
+
+    /// Release packets as soon as they are in order. Lowest startup latency,
+    /// at the cost of no protection against jitter early in the session.
+    Immediate,
+
+    /// Start immediately for the first talkspurt of the session, then fall
+    /// back to depth-based priming whenever playout has to re-prime.
+    FirstTalkspurt,
+}
+
+/// Result of inserting a packet into the jitter buffer.
+///
+/// `Inserted`, `Overflowed`, and `Resync` all mean the packet was buffered;
+/// `Duplicate` and `Late` mean it was discarded, and `Probation` that it
+/// was held back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// Packet was buffered for playout.
+    Inserted,
+
+    /// A packet with the same sequence number is already buffered.
+    Duplicate,
+
+    /// Packet's sequence is behind the next playout sequence.
+    Late {
+        /// How many sequence numbers behind playout the packet was
+        behind_by: u16,
+    },
+
+    /// Packet was buffered, but the buffer was full and the oldest
+    /// buffered packet was evicted to make room.
+    Overflowed {
+        /// Sequence number of the evicted packet
+        evicted_seq: u16,
+    },
+
+    /// Packet's sequence is too far from playout's to be late or to fit
+    /// in the buffer (e.g. a sender restart). It is held back until the
+    /// next packet shows whether the stream really moved there.
+    Probation,
+
+    /// Packet follows the one held on [`Probation`](Self::Probation): the
+    /// stream moved. The buffer was flushed, playout restarted at the held
+    /// packet, both packets were buffered, and priming begins again.
+    Resync,
+}
+
+impl InsertOutcome {
+    // ---
+    /// Returns `true` if the packet was buffered for playout.
+    pub fn is_accepted(&self) -> bool {
+        // ---
+        matches!(
+            self,
+            InsertOutcome::Inserted | InsertOutcome::Overflowed { .. } | InsertOutcome::Resync
+        )
+    }
+}
+
+/// What playout should do next, from [`JitterBuffer::next_action`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayoutAction {
+    // ---
+    /// Play this packet
+    Packet {
+        /// Packet due for playout
+        packet: RtpPacket,
+
+        /// How long it was held in the buffer
+        delay: Duration,
+    },
+
+    /// Play this packet faster than real time, shortened by
+    /// [`ACCELERATE_FRACTION`], to drain the buffer back to its target
+    /// depth
+    Accelerate {
+        /// Packet due for playout
+        packet: RtpPacket,
+
+        /// How long it was held in the buffer
+        delay: Duration,
+    },
+
+    /// The packet due never arrived in time; conceal it
+    Lost {
+        /// Sequence number given up on
+        sequence: u16,
+    },
+
+    /// Nothing is due for this long, unless a packet arrives meanwhile
+    /// (`Duration::MAX` when only an arrival can change that)
+    Wait(Duration),
+}
+
+/// Summary of one completed priming phase.
+///
+/// Produced each time the buffer primes (the initial prime and every
+/// re-prime after a resync or after playout ran dry) and collected with
+/// [`JitterBuffer::take_priming_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrimingReport {
+    // ---
+    /// Time from the first packet of the phase until playout started
+    pub duration: Duration,
+
+    /// How long each packet still buffered when playout started had been
+    /// held, oldest sequence first
+    pub held_delays: Vec<Duration>,
+}
 
 /// Jitter buffer configuration.
 #[derive(Debug, Clone)]
@@ -15,8 +205,41 @@ pub struct JitterBufferConfig {
     /// Buffer depth in milliseconds
     pub depth_ms: u32,
 
-    /// Maximum packets to buffer
+    /// Maximum packets to buffer. A packet more than this many sequence
+    /// numbers ahead of playout triggers a resync.
     pub max_packets: usize,
+
+    /// When playout is allowed to start
+    pub prime_mode: PrimeMode,
+
+    /// What priming waits for, at the start and whenever playout ran dry;
+    /// `None` waits the target depth or [`DEFAULT_PRIME_PACKETS`] packets,
+    /// whichever comes first, or for an adaptive depth the packets that
+    /// span it
+    pub prime_by: Option<PrimeBy>,
+
+    /// Adapt the depth to the network, starting from `depth_ms`; `None`
+    /// keeps `depth_ms` fixed
+    pub adaptive: Option<AdaptiveDepthConfig>,
+
+    /// Most packets in a row signalled as [`PlayoutAction::Lost`]; the rest
+    /// of a longer gap is skipped
+    pub max_concealed: u16,
+
+    /// How long in milliseconds the packet after a gap waits for the
+    /// missing one before playout gives up on it; `None` waits the target
+    /// depth plus one frame
+    pub max_wait_ms: Option<u32>,
+
+    /// What playout does when it falls behind the target depth by more
+    /// than `catch_up_margin_ms` for longer than `catch_up_grace_ms`
+    pub overflow_strategy: OverflowStrategy,
+
+    /// Occupancy beyond the target depth tolerated, in milliseconds
+    pub catch_up_margin_ms: u32,
+
+    /// How long occupancy may stay beyond the margin, in milliseconds
+    pub catch_up_grace_ms: u32,
 }
 
 impl Default for JitterBufferConfig {
@@ -25,32 +248,207 @@ impl Default for JitterBufferConfig {
         Self {
             depth_ms: 60,     // 60ms default (3 frames @ 20ms)
             max_packets: 100, // Safety limit
+            prime_mode: PrimeMode::WaitForDepth,
+            prime_by: None,
+            adaptive: None,
+            max_concealed: DEFAULT_MAX_CONCEALED,
+            max_wait_ms: None,
+            overflow_strategy: OverflowStrategy::Keep,
+            catch_up_margin_ms: DEFAULT_CATCH_UP_MARGIN_MS,
+            catch_up_grace_ms: DEFAULT_CATCH_UP_GRACE_MS,
+        }
+    }
+}
+
+/// Bounds of an adaptive jitter buffer depth.
+///
+/// The target depth follows a multiple of the RFC 3550 interarrival
+/// jitter, and grows while more than 1% of packets arrive too late to
+/// play. It moves by one frame at most every
+/// [`ADAPTIVE_ADJUST_INTERVAL`], and shrinks only once the jitter leaves
+/// a frame to spare and nothing arrived late. A deeper or shallower
+/// target applies when playout next primes: at the start, after a
+/// resync, or after playout ran dry for longer than the target (a stall,
+/// or silence between talkspurts).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveDepthConfig {
+    // ---
+    /// Shallowest depth in milliseconds
+    pub min_ms: u32,
+
+    /// Deepest depth in milliseconds
+    pub max_ms: u32,
+}
+
+impl Default for AdaptiveDepthConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            min_ms: DEFAULT_ADAPTIVE_MIN_MS,
+            max_ms: DEFAULT_ADAPTIVE_MAX_MS,
+        }
+    }
+}
+
+/// Target depth of an adaptive jitter buffer, and what drives it.
+#[derive(Debug, Clone)]
+struct DepthAdapter {
+    // ---
+    /// Bounds of the target
+    min: Duration,
+    max: Duration,
+
+    /// Depth playout primes to
+    target: Duration,
+
+    /// Step the target moves by: one frame
+    frame: Duration,
+
+    /// Interarrival jitter, once the clock rate is known
+    jitter: Option<JitterEstimator>,
+
+    /// Packets, and of them late ones, since the last adjustment
+    packets: u64,
+    late: u64,
+
+    /// When the target was last considered for adjustment
+    last_adjust: Option<Instant>,
+}
+
+impl DepthAdapter {
+    // ---
+    /// Creates an adapter within `config`, starting from `depth_ms`.
+    fn new(config: &AdaptiveDepthConfig, depth_ms: u32) -> Self {
+        // ---
+        let min = Duration::from_millis(config.min_ms as u64);
+        let max = Duration::from_millis(config.max_ms.max(config.min_ms) as u64);
+        Self {
+            min,
+            max,
+            target: Duration::from_millis(depth_ms as u64).clamp(min, max),
+            frame: DEFAULT_FRAME,
+            jitter: None,
+            packets: 0,
+            late: 0,
+            last_adjust: None,
+        }
+    }
+
+    /// Takes the frame duration and clock rate of the stream; a change of
+    /// clock rate restarts the jitter estimate.
+    fn set_clock(&mut self, clock: &StreamClock) {
+        // ---
+        self.frame = clock.frame_duration;
+        if self
+            .jitter
+            .as_ref()
+            .is_none_or(|jitter| jitter.clock_rate() != clock.clock_rate)
+        {
+            self.jitter = Some(JitterEstimator::new(clock.clock_rate));
+        }
+    }
+
+    /// Counts a packet stamped `timestamp` buffered at `arrival`.
+    fn observe(&mut self, timestamp: u32, arrival: Instant) {
+        // ---
+        if let Some(jitter) = &mut self.jitter {
+            jitter.update(timestamp, arrival);
         }
+        self.packets += 1;
+        self.adjust(arrival);
+    }
+
+    /// Counts a packet that arrived too late to play.
+    fn observe_late(&mut self, arrival: Instant) {
+        // ---
+        self.packets += 1;
+        self.late += 1;
+        self.adjust(arrival);
+    }
+
+    /// Starts the jitter estimate over, for a new stream.
+    fn reset(&mut self) {
+        // ---
+        if let Some(jitter) = &mut self.jitter {
+            jitter.reset();
+        }
+    }
+
+    /// Moves the target a frame towards what the network calls for, if
+    /// an adjustment interval has passed.
+    fn adjust(&mut self, now: Instant) {
+        // ---
+        let since = *self.last_adjust.get_or_insert(now);
+        if now.saturating_duration_since(since) < ADAPTIVE_ADJUST_INTERVAL {
+            return;
+        }
+        self.last_adjust = Some(now);
+
+        let late_rate = match self.packets {
+            0 => 0.0,
+            packets => self.late as f64 / packets as f64,
+        };
+        let wanted = self
+            .jitter
+            .as_ref()
+            .map_or(self.target, |jitter| jitter.jitter() * JITTER_MULTIPLE);
+        let target = if late_rate > LATE_RATE_GROW || wanted > self.target {
+            (self.target + self.frame).min(self.max)
+        } else if self.late == 0 && wanted + self.frame <= self.target {
+            self.target.saturating_sub(self.frame).max(self.min)
+        } else {
+            self.target
+        };
+        if target != self.target {
+            debug!(
+                "Jitter buffer target depth {:?} -> {:?} (jitter target {:?}, {} of {} late)",
+                self.target, target, wanted, self.late, self.packets
+            );
+            self.target = target;
+        }
+        self.packets = 0;
+        self.late = 0;
     }
 }
 
 /// Buffered packet with arrival timestamp.
 #[derive(Debug, Clone)]
-struct BufferedPacket {
-    packet: RtpPacket,
-    arrival: Instant,
+pub struct BufferedPacket {
+    // ---
+    /// The buffered RTP packet
+    pub packet: RtpPacket,
+
+    /// When the packet arrived at the receiver
+    pub arrival: Instant,
+
+    /// Sequence number extended past the 16-bit range since the last
+    /// (re)start, which orders packets across a wrap
+    pub extended_sequence: u64,
 }
 
 /// Jitter buffer for packet reordering and playout smoothing.
 ///
 /// Buffers incoming packets and releases them in sequence order
-/// after a fixed delay to compensate for network jitter.
+/// after a delay to compensate for network jitter.
 ///
 /// # Design
 ///
-/// - **Fixed depth**: Simpler than adaptive, good enough for Phase 2
-/// - **Sequence-based ordering**: Packets sorted by sequence number
+/// - **Fixed or adaptive depth**: Fixed by default; adaptive within bounds
+///   with [`JitterBufferConfig::adaptive`]
+/// - **Sequence-based ordering**: Packets sorted by extended sequence number
 /// - **Late packet handling**: Discard packets arriving after playout deadline
+/// - **Loss signalling**: Give up on a missing packet once a later one has
+///   waited [`max_wait`](JitterBuffer::max_wait), so playout can conceal
+///   it ([`PlayoutAction::Lost`]); a packet given up on that arrives
+///   afterwards is late
+/// - **Catch-up**: Optionally drain a buffer that stays well beyond its
+///   target depth, for a consumer that pulls a frame at a time
+///   ([`JitterBufferConfig::overflow_strategy`])
 ///
 /// # Example
 ///
 /// ```
-/// use receiver::jitter_buffer::{JitterBuffer, JitterBufferConfig};
+/// use receiver::jitter_buffer::{InsertOutcome, JitterBuffer, JitterBufferConfig};
 /// use rtp_opus_common::RtpPacket;
 ///
 /// let mut buffer = JitterBuffer::new(JitterBufferConfig::default());
@@ -58,8 +456,8 @@ struct BufferedPacket {
 /// // Insert packets as they arrive (possibly out of order)
 /// let packet1 = RtpPacket::new(0, 0, 0x12345678, vec![1, 2, 3]);
 /// let packet2 = RtpPacket::new(1, 320, 0x12345678, vec![4, 5, 6]);
-/// buffer.insert(packet1);
-/// buffer.insert(packet2);
+/// assert_eq!(buffer.insert(packet1), InsertOutcome::Inserted);
+/// assert_eq!(buffer.insert(packet2), InsertOutcome::Inserted);
 ///
 /// // Retrieve packets in sequence order when ready
 /// if let Some(packet) = buffer.get_next() {
@@ -72,17 +470,60 @@ pub struct JitterBuffer {
     /// Buffer configuration
     config: JitterBufferConfig,
 
-    /// Buffered packets sorted by sequence number
-    buffer: VecDeque<BufferedPacket>,
+    /// Buffered packets keyed by extended sequence number, so they stay in
+    /// playout order across a wrap of the 16-bit one
+    buffer: BTreeMap<u64, BufferedPacket>,
 
     /// Next expected sequence number for playout
     next_sequence: Option<u16>,
 
+    /// Extended sequence numbers of the packets buffered since the last
+    /// (re)start
+    sequence: SeqTracker,
+
     /// Time when buffer started (for playout timing)
     start_time: Option<Instant>,
 
     /// Whether buffer has been primed (filled to depth)
     is_primed: bool,
+
+    /// Whether playout has ever started (used by `PrimeMode::FirstTalkspurt`)
+    primed_once: bool,
+
+    /// Report for the most recent priming phase, until collected
+    priming_report: Option<PrimingReport>,
+
+    /// Whether the stream has ended and playout skips gaps rather than
+    /// waiting for them to fill (see [`JitterBuffer::flush`])
+    flushing: bool,
+
+    /// Rate limits logging of late packets, resyncs, and overflows
+    anomalies: AnomalyLogger,
+
+    /// Target depth, when it adapts
+    adapter: Option<DepthAdapter>,
+
+    /// When a packet was last released for playout
+    last_release: Option<Instant>,
+
+    /// Packets signalled lost since the last one released
+    lost_run: u16,
+
+    /// Duration of a frame of the stream
+    frame: Duration,
+
+    /// Counters since creation or the last [`take_stats`](Self::take_stats)
+    stats: JitterBufferStats,
+
+    /// Since when occupancy has been beyond the catch-up margin
+    above_margin_since: Option<Instant>,
+
+    /// Whether playout is draining the buffer back to the target depth
+    catching_up: bool,
+
+    /// Packet far from playout, with its arrival, held until the next one
+    /// confirms a new stream position
+    probation: Option<(RtpPacket, Instant)>,
 }
 
 impl JitterBuffer {
@@ -90,107 +531,488 @@ impl JitterBuffer {
     /// Creates a new jitter buffer with the given configuration.
     pub fn new(config: JitterBufferConfig) -> Self {
         // ---
+        let adapter = config
+            .adaptive
+            .as_ref()
+            .map(|adaptive| DepthAdapter::new(adaptive, config.depth_ms));
         Self {
             config,
-            buffer: VecDeque::new(),
+            buffer: BTreeMap::new(),
             next_sequence: None,
+            sequence: SeqTracker::new(),
             start_time: None,
             is_primed: false,
+            primed_once: false,
+            priming_report: None,
+            flushing: false,
+            anomalies: AnomalyLogger::default(),
+            adapter,
+            last_release: None,
+            lost_run: 0,
+            frame: DEFAULT_FRAME,
+            stats: JitterBufferStats::default(),
+            above_margin_since: None,
+            catching_up: false,
+            probation: None,
+        }
+    }
+
+    /// Takes the clock of the stream, which an adaptive depth needs to
+    /// measure jitter and step by frames, and the default
+    /// [`max_wait`](Self::max_wait) to add a frame.
+    pub fn set_clock(&mut self, clock: &StreamClock) {
+        // ---
+        self.frame = clock.frame_duration;
+        if let Some(adapter) = &mut self.adapter {
+            adapter.set_clock(clock);
         }
     }
 
+    /// Returns the depth playout primes to: the configured one, or where
+    /// an adaptive depth has moved.
+    pub fn target_depth(&self) -> Duration {
+        // ---
+        self.adapter.as_ref().map_or(
+            Duration::from_millis(self.config.depth_ms as u64),
+            |adapter| adapter.target,
+        )
+    }
+
+    /// Returns the counters since the buffer was created or they were
+    /// last taken.
+    pub fn stats(&self) -> &JitterBufferStats {
+        // ---
+        &self.stats
+    }
+
+    /// Takes the counters, starting them over, e.g. to publish what
+    /// happened since the last call.
+    pub fn take_stats(&mut self) -> JitterBufferStats {
+        // ---
+        std::mem::take(&mut self.stats)
+    }
+
+    /// Returns how long the packet after a gap waits for the missing one:
+    /// [`JitterBufferConfig::max_wait_ms`], or the target depth plus one
+    /// frame.
+    pub fn max_wait(&self) -> Duration {
+        // ---
+        self.config.max_wait_ms.map_or_else(
+            || self.target_depth() + self.frame,
+            |max_wait_ms| Duration::from_millis(max_wait_ms as u64),
+        )
+    }
+
+    /// Replaces the logger for late packets, resyncs, and overflows, e.g.
+    /// with one that exports suppression metrics.
+    pub fn set_anomaly_logger(&mut self, logger: AnomalyLogger) {
+        // ---
+        self.anomalies = logger;
+    }
+
+    /// Returns the anomaly logger.
+    pub fn anomalies(&self) -> &AnomalyLogger {
+        // ---
+        &self.anomalies
+    }
+
+    /// Logs anomalies counted since the last aggregate line.
+    pub fn flush_anomalies(&mut self) {
+        // ---
+        self.anomalies.flush(Instant::now());
+    }
+
     /// Inserts a packet into the buffer.
     ///
     /// Packets are stored in sequence order. Late packets (arriving after
-    /// their playout deadline) are discarded.
-    ///
-    /// Returns `true` if packet was inserted, `false` if discarded (late or duplicate).
-    pub fn insert(&mut self, packet: RtpPacket) -> bool {
+    /// their playout deadline) and duplicates are discarded; see
+    /// [`InsertOutcome`] for every possible result.
+    pub fn insert(&mut self, packet: RtpPacket) -> InsertOutcome {
         // ---
         self.insert_with_arrival(packet, Instant::now())
     }
 
+    /// Inserts a packet, returning `true` if it was buffered.
+    ///
+    /// Compatibility shim for callers written against the old `bool`
+    /// return; new code should match on [`InsertOutcome`].
+    #[deprecated(note = "use `insert` and match on `InsertOutcome`")]
+    pub fn insert_accepted(&mut self, packet: RtpPacket) -> bool {
+        // ---
+        self.insert(packet).is_accepted()
+    }
+
     /// Inserts a packet with an explicit arrival timestamp.
     ///
     /// This enables receiver-side latency measurements without depending on
     /// wall-clock synchronization between sender and receiver.
-    pub fn insert_with_arrival(&mut self, packet: RtpPacket, arrival: Instant) -> bool {
+    pub fn insert_with_arrival(&mut self, packet: RtpPacket, arrival: Instant) -> InsertOutcome {
         // ---
-        // Initialize on first packet
-        if self.next_sequence.is_none() {
-            self.next_sequence = Some(packet.sequence);
-            self.start_time = Some(Instant::now());
+        let outcome = self.insert_packet(packet, arrival);
+        match outcome {
+            InsertOutcome::Inserted | InsertOutcome::Resync => self.stats.inserted += 1,
+            InsertOutcome::Overflowed { .. } => {
+                self.stats.inserted += 1;
+                self.stats.overflow_drops += 1;
+            }
+            InsertOutcome::Duplicate => self.stats.duplicates += 1,
+            InsertOutcome::Late { .. } => self.stats.late += 1,
+            InsertOutcome::Probation => {}
         }
+        outcome
+    }
 
+    /// Inserts a packet for [`insert_with_arrival`](Self::insert_with_arrival),
+    /// which counts the outcome.
+    fn insert_packet(&mut self, packet: RtpPacket, arrival: Instant) -> InsertOutcome {
+        // ---
         let packet_sequence = packet.sequence;
 
-        // Check if packet is too late
-        if self.is_late(&packet) {
-            warn!(
-                "Discarding late packet: seq={} (expected={})",
-                packet_sequence,
-                self.next_sequence.unwrap_or(0)
-            );
-            return false;
+        // Playout ran dry for longer than the depth: prime again
+        let stalled = self.last_release.is_some_and(|released| {
+            arrival.saturating_duration_since(released) > self.target_depth()
+        });
+        if self.is_primed && !self.flushing && self.buffer.is_empty() && stalled {
+            if !packet.marker {
+                self.stats.underruns += 1;
+            }
+            self.is_primed = false;
+            self.start_time = Some(arrival);
+        }
+
+        let mut outcome = InsertOutcome::Inserted;
+        match self.next_sequence {
+            // Initialize on first packet
+            None => self.restart_at(packet_sequence, arrival),
+
+            Some(next_seq) => {
+                // More than half the sequence space ahead means behind
+                let ahead = packet_sequence.wrapping_sub(next_seq);
+                let behind = next_seq.wrapping_sub(packet_sequence);
+                let far = if ahead > 32768 {
+                    behind > MAX_MISORDER
+                } else {
+                    ahead as usize > self.config.max_packets
+                };
+                let candidate = self.probation.take();
+
+                // Far from playout either way, as after a sender restart:
+                // a new stream position once the packet after it follows
+                // (RFC 3550 Appendix A.1), until then held back
+                if far {
+                    let Some((candidate, candidate_arrival)) = candidate
+                        .filter(|(held, _)| held.sequence.wrapping_add(1) == packet_sequence)
+                    else {
+                        debug!(
+                            "Holding seq={} far from expected={} until the next packet confirms it",
+                            packet_sequence, next_seq
+                        );
+                        self.probation = Some((packet, arrival));
+                        return InsertOutcome::Probation;
+                    };
+                    self.anomalies.record(
+                        AnomalyClass::BufferResync,
+                        Some(candidate.sequence),
+                        format_args!(
+                            "Resyncing jitter buffer: seq={} too far from expected={}",
+                            candidate.sequence, next_seq
+                        ),
+                        arrival,
+                    );
+                    self.buffer.clear();
+                    self.restart_at(candidate.sequence, candidate_arrival);
+                    if let Some(adapter) = &mut self.adapter {
+                        adapter.reset();
+                    }
+                    let extended_sequence = candidate.sequence as u64;
+                    self.sequence.update(candidate.sequence);
+                    self.buffer.insert(
+                        extended_sequence,
+                        BufferedPacket {
+                            packet: candidate,
+                            arrival: candidate_arrival,
+                            extended_sequence,
+                        },
+                    );
+                    outcome = InsertOutcome::Resync;
+                } else if ahead > 32768 {
+                    self.anomalies.record(
+                        AnomalyClass::LatePacket,
+                        Some(packet_sequence),
+                        format_args!(
+                            "Discarding late packet: seq={} (expected={})",
+                            packet_sequence, next_seq
+                        ),
+                        arrival,
+                    );
+                    if let Some(adapter) = &mut self.adapter {
+                        adapter.observe_late(arrival);
+                    }
+                    return InsertOutcome::Late { behind_by: behind };
+                }
+            }
         }
 
         // Check for duplicates
         if self
-            .buffer
-            .iter()
-            .any(|bp| bp.packet.sequence == packet_sequence)
+            .sequence
+            .extend(packet_sequence)
+            .is_some_and(|extended| self.buffer.contains_key(&extended))
         {
             debug!("Discarding duplicate packet: seq={}", packet_sequence);
-            return false;
+            return InsertOutcome::Duplicate;
         }
 
-        // Insert in sequence order
-        let buffered = BufferedPacket { packet, arrival };
-
-        let insert_pos = self
-            .buffer
-            .iter()
-            .position(|bp| sequence_compare(packet_sequence, bp.packet.sequence))
-            .unwrap_or(self.buffer.len());
+        // Insert in extended sequence order
+        let extended_sequence = match self.sequence.update(packet_sequence) {
+            SeqUpdate::Restart { extended }
+            | SeqUpdate::Ahead { extended, .. }
+            | SeqUpdate::Behind { extended } => extended,
+            // Too far behind the highest to be reordering, yet not late:
+            // only possible with a buffer deeper than the misorder window
+            SeqUpdate::Jump => self.sequence.extend(packet_sequence).unwrap_or_default(),
+        };
+        if let Some(adapter) = &mut self.adapter {
+            adapter.observe(packet.timestamp, arrival);
+        }
+        let buffered = BufferedPacket {
+            packet,
+            arrival,
+            extended_sequence,
+        };
 
-        self.buffer.insert(insert_pos, buffered);
+        self.buffer.insert(extended_sequence, buffered);
 
         // Enforce max buffer size
         if self.buffer.len() > self.config.max_packets {
-            warn!("Buffer overflow, dropping oldest packet");
-            self.buffer.pop_front();
+            if let Some((_, evicted)) = self.buffer.pop_first() {
+                self.anomalies.record(
+                    AnomalyClass::BufferOverflow,
+                    Some(evicted.packet.sequence),
+                    format_args!(
+                        "Buffer overflow, dropped oldest packet seq={}",
+                        evicted.packet.sequence
+                    ),
+                    arrival,
+                );
+                outcome = InsertOutcome::Overflowed {
+                    evicted_seq: evicted.packet.sequence,
+                };
+            }
         }
 
-        true
+        outcome
+    }
+
+    /// Restarts playout at `sequence` and re-primes the buffer.
+    ///
+    /// The priming phase is timed from `arrival`.
+    fn restart_at(&mut self, sequence: u16, arrival: Instant) {
+        // ---
+        self.next_sequence = Some(sequence);
+        self.sequence = SeqTracker::new();
+        self.start_time = Some(arrival);
+        self.is_primed = false;
+        self.flushing = false;
+        self.lost_run = 0;
+        self.above_margin_since = None;
+        self.catching_up = false;
+    }
+
+    /// Releases everything still buffered, for a stream that has ended.
+    ///
+    /// Playout starts even if the buffer has not primed, and the following
+    /// calls to [`next_action`](Self::next_action) give up on gaps at once
+    /// instead of waiting for packets that will never arrive, so they
+    /// return every buffered packet in sequence order.
+    pub fn flush(&mut self) {
+        // ---
+        self.is_primed = true;
+        self.flushing = true;
     }
 
     /// Retrieves the next packet along with its buffer delay.
+    ///
+    /// Packets given up on are skipped without a word; playout that
+    /// conceals them should use [`next_action`](Self::next_action).
     pub fn get_next_with_delay(&mut self) -> Option<(RtpPacket, Duration)> {
         // ---
+        self.get_next_at(Instant::now())
+    }
+
+    /// Retrieves the next packet as of `now`, along with its buffer delay.
+    ///
+    /// Same as [`get_next_with_delay`](Self::get_next_with_delay) with an
+    /// explicit clock, so priming and delays can be measured against
+    /// arrival times supplied to [`insert_with_arrival`](Self::insert_with_arrival).
+    pub fn get_next_at(&mut self, now: Instant) -> Option<(RtpPacket, Duration)> {
+        // ---
+        loop {
+            match self.next_action_at(now)? {
+                PlayoutAction::Packet { packet, delay }
+                | PlayoutAction::Accelerate { packet, delay } => return Some((packet, delay)),
+                PlayoutAction::Lost { .. } => {}
+                PlayoutAction::Wait(_) => return None,
+            }
+        }
+    }
+
+    /// Returns what playout should do next.
+    ///
+    /// Returns `None` while nothing is buffered.
+    pub fn next_action(&mut self) -> Option<PlayoutAction> {
+        // ---
+        self.next_action_at(Instant::now())
+    }
+
+    /// Returns what playout should do next as of `now`.
+    ///
+    /// Same as [`next_action`](Self::next_action) with an explicit clock.
+    /// The next sequence is given up on as [`PlayoutAction::Lost`] once a
+    /// later packet has been held [`max_wait`](Self::max_wait) without it
+    /// arriving, or straight away while flushing. After
+    /// [`JitterBufferConfig::max_concealed`] losses in a row, the rest of
+    /// a gap is skipped silently and playout resumes at the packet after
+    /// it.
+    pub fn next_action_at(&mut self, now: Instant) -> Option<PlayoutAction> {
+        // ---
+        let front_arrival = self.buffer.first_key_value()?.1.arrival;
+
         // Wait for buffer to prime (fill to target depth)
         if !self.is_primed {
-            if self.should_start_playout() {
+            if self.should_start_playout(now) {
                 self.is_primed = true;
-                debug!("Jitter buffer primed, starting playout");
+                self.primed_once = true;
+                let report = PrimingReport {
+                    duration: now.saturating_duration_since(self.start_time.unwrap_or(now)),
+                    held_delays: self
+                        .buffer
+                        .values()
+                        .map(|bp| now.saturating_duration_since(bp.arrival))
+                        .collect(),
+                };
+                debug!(
+                    "Jitter buffer primed after {:?} with {} packets, starting playout",
+                    report.duration,
+                    report.held_delays.len()
+                );
+                self.priming_report = Some(report);
             } else {
-                return None;
+                let start = self.start_time.unwrap_or(now);
+                let elapsed = now.saturating_duration_since(start);
+                let wait = match self.prime_by() {
+                    PrimeBy::Duration(ms) | PrimeBy::Either(ms, _) => {
+                        Duration::from_millis(ms as u64).saturating_sub(elapsed)
+                    }
+                    PrimeBy::Packets(_) => Duration::MAX,
+                };
+                return Some(PlayoutAction::Wait(wait));
             }
         }
 
+        self.catch_up(now);
         let next_seq = self.next_sequence?;
 
-        if let Some(pos) = self
+        // Nothing buffered is behind playout, so the packet due is first
+        if let Some(entry) = self
             .buffer
-            .iter()
-            .position(|bp| bp.packet.sequence == next_seq)
+            .first_entry()
+            .filter(|entry| entry.get().packet.sequence == next_seq)
         {
-            let buffered = self.buffer.remove(pos).unwrap();
+            let buffered = entry.remove();
+            self.next_sequence = Some(next_seq.wrapping_add(1));
+            self.last_release = Some(now);
+            self.lost_run = 0;
+            let delay = now.saturating_duration_since(buffered.arrival);
+            self.stats.residence.record(delay);
+            let packet = buffered.packet;
+            if self.catching_up && self.config.overflow_strategy == OverflowStrategy::Accelerate {
+                self.stats.accelerated += 1;
+                return Some(PlayoutAction::Accelerate { packet, delay });
+            }
+            return Some(PlayoutAction::Packet { packet, delay });
+        }
+
+        // The packet after the gap has waited as long as allowed, or
+        // nothing more will arrive to fill the gap
+        let held = now.saturating_duration_since(front_arrival);
+        let max_wait = self.max_wait();
+        if !self.flushing && held < max_wait {
+            return Some(PlayoutAction::Wait(max_wait - held));
+        }
+        if self.lost_run == 0 {
+            self.stats.gaps_skipped += 1;
+        }
+        if self.lost_run < self.config.max_concealed {
+            self.lost_run += 1;
+            self.stats.lost += 1;
             self.next_sequence = Some(next_seq.wrapping_add(1));
-            let delay = buffered.arrival.elapsed();
-            return Some((buffered.packet, delay));
+            return Some(PlayoutAction::Lost { sequence: next_seq });
+        }
+
+        // Conceal no further: move on past the gap
+        if let Some((_, front)) = self.buffer.first_key_value() {
+            debug!(
+                "Skipping seq={}..{} after {} concealed in a row",
+                next_seq,
+                front.packet.sequence.wrapping_sub(1),
+                self.lost_run
+            );
+            self.next_sequence = Some(front.packet.sequence);
+        }
+        self.next_action_at(now)
+    }
+
+    /// Starts catching up once occupancy has stayed beyond the target
+    /// depth by more than the margin for the grace period, and stops once
+    /// back to the target. Dropping the oldest packets gets there at once.
+    fn catch_up(&mut self, now: Instant) {
+        // ---
+        let target_depth = self.target_depth();
+        if self.flushing
+            || self.config.overflow_strategy == OverflowStrategy::Keep
+            || self.occupancy() <= target_depth
+        {
+            self.above_margin_since = None;
+            self.catching_up = false;
+            return;
+        }
+        if !self.catching_up {
+            let margin = Duration::from_millis(self.config.catch_up_margin_ms as u64);
+            if self.occupancy() <= target_depth + margin {
+                self.above_margin_since = None;
+                return;
+            }
+            let since = *self.above_margin_since.get_or_insert(now);
+            let grace = Duration::from_millis(self.config.catch_up_grace_ms as u64);
+            if now.saturating_duration_since(since) < grace {
+                return;
+            }
+            debug!(
+                "Jitter buffer {:?} beyond target {:?}, catching up",
+                self.occupancy(),
+                target_depth
+            );
+            self.catching_up = true;
         }
 
-        None
+        if self.config.overflow_strategy == OverflowStrategy::DropOldest {
+            while self.occupancy() > target_depth && self.buffer.pop_first().is_some() {
+                self.stats.catch_up_drops += 1;
+            }
+            if let Some((_, front)) = self.buffer.first_key_value() {
+                self.next_sequence = Some(front.packet.sequence);
+            }
+            self.lost_run = 0;
+            self.above_margin_since = None;
+            self.catching_up = false;
+        }
+    }
+
+    /// Returns how much audio the buffered packets hold.
+    fn occupancy(&self) -> Duration {
+        // ---
+        self.frame * self.buffer.len() as u32
     }
 
     /// Retrieves the next packet ready for playout.
@@ -205,37 +1027,58 @@ impl JitterBuffer {
         self.get_next_with_delay().map(|(p, _)| p)
     }
 
+    /// Takes the report of the most recently completed priming phase.
+    ///
+    /// Returns `None` if playout has not primed since the last call.
+    pub fn take_priming_report(&mut self) -> Option<PrimingReport> {
+        // ---
+        self.priming_report.take()
+    }
+
     /// Checks if we should start playout (buffer priming complete).
-    fn should_start_playout(&self) -> bool {
+    fn should_start_playout(&self, now: Instant) -> bool {
         // ---
         if self.buffer.is_empty() {
             return false;
         }
 
+        match self.config.prime_mode {
+            PrimeMode::Immediate => return true,
+            PrimeMode::FirstTalkspurt if !self.primed_once => return true,
+            PrimeMode::FirstTalkspurt | PrimeMode::WaitForDepth => {}
+        }
+
         let start = match self.start_time {
             Some(s) => s,
             None => return false,
         };
 
-        let elapsed = start.elapsed();
-        let target_depth = Duration::from_millis(self.config.depth_ms as u64);
-
-        // Start playout after target depth or if buffer has enough packets
-        elapsed >= target_depth || self.buffer.len() >= 3
+        let elapsed = now.saturating_duration_since(start);
+        let waited = |ms: u32| elapsed >= Duration::from_millis(ms as u64);
+        let buffered = |packets: usize| self.buffer.len() >= packets;
+        match self.prime_by() {
+            PrimeBy::Duration(ms) => waited(ms),
+            PrimeBy::Packets(packets) => buffered(packets),
+            PrimeBy::Either(ms, packets) => waited(ms) || buffered(packets),
+        }
     }
 
-    /// Checks if a packet is too late for playout.
-    fn is_late(&self, packet: &RtpPacket) -> bool {
+    /// Returns what priming waits for: [`JitterBufferConfig::prime_by`],
+    /// or the target depth and the packets described there.
+    fn prime_by(&self) -> PrimeBy {
         // ---
-        let next_seq = match self.next_sequence {
-            Some(seq) => seq,
-            None => return false, // First packet can't be late
+        if let Some(prime_by) = self.config.prime_by {
+            return prime_by;
+        }
+        let target_depth = self.target_depth();
+        let packets = match &self.adapter {
+            Some(adapter) => {
+                let frame = adapter.frame.max(Duration::from_millis(1));
+                (target_depth.as_micros() / frame.as_micros()) as usize + 1
+            }
+            None => DEFAULT_PRIME_PACKETS,
         };
-
-        // Packet is late if its sequence is behind next expected
-        // (accounting for wraparound)
-        let distance = packet.sequence.wrapping_sub(next_seq);
-        distance > 32768 // More than half the sequence space behind
+        PrimeBy::Either(target_depth.as_millis() as u32, packets)
     }
 
     /// Returns current buffer status for debugging.
@@ -245,43 +1088,135 @@ impl JitterBuffer {
             buffered_packets: self.buffer.len(),
             is_primed: self.is_primed,
             next_sequence: self.next_sequence,
+            target_depth: self.target_depth(),
+            occupancy: self.occupancy(),
         }
     }
 
+    /// Returns the packets waiting for playout, in sequence order.
+    pub fn buffered(&self) -> impl Iterator<Item = &BufferedPacket> {
+        // ---
+        self.buffer.values()
+    }
+
     /// Returns whether the given sequence was reordered.
     ///
-    /// A packet is reordered if it arrived out of sequence but was still buffered.
+    /// A packet is reordered if it arrives behind a higher sequence number
+    /// that was already buffered. Call this before inserting the packet.
     pub fn was_reordered(&self, sequence: u16) -> bool {
         // ---
-        if let Some(next_seq) = self.next_sequence {
-            sequence != next_seq
-        } else {
-            false
-        }
+        // Behind the first packet buffered, there is no extended number
+        self.sequence.highest_extended().is_some_and(|highest| {
+            self.sequence
+                .extend(sequence)
+                .is_none_or(|extended| extended < highest)
+        })
     }
 }
 
-/// Jitter buffer status for observability.
-#[derive(Debug, Clone)]
-pub struct JitterBufferStatus {
-    pub buffered_packets: usize,
-    pub is_primed: bool,
-    pub next_sequence: Option<u16>,
-}
-
-/// Compares two sequence numbers accounting for wraparound.
-///
-/// Returns `true` if `a` comes before `b` in sequence space.
-fn sequence_compare(a: u16, b: u16) -> bool {
+/// Minimum, mean, and maximum of how long released packets were held.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResidenceSummary {
     // ---
-    let diff = a.wrapping_sub(b);
-    diff < 32768
-}
+    /// Packets released
+    pub count: u64,
+
+    /// Shortest hold
+    pub min: Duration,
+
+    /// Longest hold
+    pub max: Duration,
+
+    /// Sum of the holds
+    pub total: Duration,
+}
+
+impl ResidenceSummary {
+    // ---
+    /// Adds the hold of a released packet.
+    pub fn record(&mut self, delay: Duration) {
+        // ---
+        self.min = match self.count {
+            0 => delay,
+            _ => self.min.min(delay),
+        };
+        self.max = self.max.max(delay);
+        self.total += delay;
+        self.count += 1;
+    }
+
+    /// Returns the mean hold, if any packet was released.
+    pub fn mean(&self) -> Option<Duration> {
+        // ---
+        (self.count > 0).then(|| self.total.div_f64(self.count as f64))
+    }
+}
+
+/// Counters of what happened to the packets through a jitter buffer.
+///
+/// Accumulates from the creation of the buffer, or from the last
+/// [`JitterBuffer::take_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JitterBufferStats {
+    // ---
+    /// Packets accepted for playout
+    pub inserted: u64,
+
+    /// Packets discarded as already buffered
+    pub duplicates: u64,
+
+    /// Packets discarded as behind playout
+    pub late: u64,
+
+    /// Buffered packets evicted because the buffer was full
+    pub overflow_drops: u64,
+
+    /// Gaps playout moved past without the missing packets
+    pub gaps_skipped: u64,
+
+    /// Sequence numbers signalled as [`PlayoutAction::Lost`]
+    pub lost: u64,
+
+    /// Times a packet found playout run dry: the buffer empty for longer
+    /// than the target depth since the last release, other than at the
+    /// start of a talkspurt
+    pub underruns: u64,
+
+    /// Packets dropped to catch up with [`OverflowStrategy::DropOldest`]
+    pub catch_up_drops: u64,
+
+    /// Packets released as [`PlayoutAction::Accelerate`]
+    pub accelerated: u64,
+
+    /// How long released packets were held
+    pub residence: ResidenceSummary,
+}
+
+/// Jitter buffer status for observability.
+#[derive(Debug, Clone)]
+pub struct JitterBufferStatus {
+    // ---
+    /// Packets waiting for playout
+    pub buffered_packets: usize,
+
+    /// Whether playout has started
+    pub is_primed: bool,
+
+    /// Next sequence number due for playout
+    pub next_sequence: Option<u16>,
+
+    /// Depth playout primes to (see [`JitterBuffer::target_depth`])
+    pub target_depth: Duration,
+
+    /// Audio the buffered packets hold, a frame each
+    pub occupancy: Duration,
+}
 
 #[cfg(test)]
 mod tests {
     // ---
     use super::*;
+    use rtp_opus_common::MetricsContext;
 
     fn make_packet(seq: u16) -> RtpPacket {
         RtpPacket::new(seq, seq as u32 * 320, 0x12345678, vec![1, 2, 3])
@@ -293,6 +1228,7 @@ mod tests {
         let mut buffer = JitterBuffer::new(JitterBufferConfig {
             depth_ms: 0, // No delay for testing
             max_packets: 10,
+            ..Default::default()
         });
 
         buffer.insert(make_packet(0));
@@ -310,6 +1246,7 @@ mod tests {
         let mut buffer = JitterBuffer::new(JitterBufferConfig {
             depth_ms: 0,
             max_packets: 10,
+            ..Default::default()
         });
 
         // Insert out of order
@@ -323,12 +1260,57 @@ mod tests {
         assert_eq!(buffer.get_next().unwrap().sequence, 2);
     }
 
+    #[test]
+    fn test_flush_releases_past_gaps() {
+        // ---
+        // Playout starts at once, but gaps are waited for a second
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 1000,
+            max_packets: 10,
+            prime_mode: PrimeMode::Immediate,
+            ..Default::default()
+        });
+
+        buffer.insert(make_packet(0));
+        buffer.insert(make_packet(3));
+        buffer.insert(make_packet(1));
+        buffer.insert(make_packet(6));
+        assert_eq!(buffer.get_next().unwrap().sequence, 0);
+        assert_eq!(buffer.get_next().unwrap().sequence, 1);
+        assert!(buffer.get_next().is_none()); // Waiting for 2
+
+        buffer.flush();
+        let released: Vec<u16> = std::iter::from_fn(|| buffer.get_next())
+            .map(|packet| packet.sequence)
+            .collect();
+        assert_eq!(released, vec![3, 6]);
+        assert_eq!(buffer.status().buffered_packets, 0);
+    }
+
+    #[test]
+    fn test_was_reordered_while_priming() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig::default());
+
+        // In-order packets held during priming are not reordered
+        for seq in 0..3 {
+            assert!(!buffer.was_reordered(seq));
+            buffer.insert(make_packet(seq));
+        }
+        buffer.insert(make_packet(5));
+
+        assert!(buffer.was_reordered(4));
+        assert!(!buffer.was_reordered(6));
+        assert!(!buffer.status().is_primed);
+    }
+
     #[test]
     fn test_jitter_buffer_late_packet() {
         // ---
         let mut buffer = JitterBuffer::new(JitterBufferConfig {
             depth_ms: 0,
             max_packets: 10,
+            ..Default::default()
         });
 
         buffer.insert(make_packet(0));
@@ -337,8 +1319,38 @@ mod tests {
         buffer.get_next(); // Play packet 1, next expected is 2
 
         // Packet 0 arrives again - should be discarded as late
-        let inserted = buffer.insert(make_packet(0));
-        assert!(!inserted);
+        let outcome = buffer.insert(make_packet(0));
+        assert_eq!(outcome, InsertOutcome::Late { behind_by: 2 });
+    }
+
+    #[test]
+    fn test_late_packet_flood_logs_once() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 0,
+            max_packets: 10,
+            ..Default::default()
+        });
+        buffer.set_anomaly_logger(AnomalyLogger::default().with_metrics(&metrics));
+
+        let start = Instant::now();
+        buffer.insert_with_arrival(make_packet(500), start);
+        buffer.get_next_at(start);
+
+        // 500 late packets within a second: one line, the rest counted
+        for i in 0..500u16 {
+            let arrival = start + Duration::from_millis(i as u64 * 2);
+            let outcome = buffer.insert_with_arrival(make_packet(401 + i % 100), arrival);
+            assert!(matches!(outcome, InsertOutcome::Late { .. }));
+        }
+
+        assert_eq!(buffer.anomalies().lines_logged(AnomalyClass::LatePacket), 1);
+        let suppressed = metrics
+            .anomalies_suppressed_total
+            .with_label_values(&["late_packet"])
+            .get();
+        assert_eq!(suppressed, 499);
     }
 
     #[test]
@@ -347,6 +1359,7 @@ mod tests {
         let mut buffer = JitterBuffer::new(JitterBufferConfig {
             depth_ms: 0,
             max_packets: 10,
+            ..Default::default()
         });
 
         buffer.insert(make_packet(65534));
@@ -359,26 +1372,211 @@ mod tests {
     }
 
     #[test]
-    fn test_duplicate_packets() {
+    fn test_reordering_straddling_wraparound() {
         // ---
         let mut buffer = JitterBuffer::new(JitterBufferConfig {
             depth_ms: 0,
             max_packets: 10,
+            ..Default::default()
         });
 
+        buffer.insert(make_packet(65533));
         buffer.insert(make_packet(0));
-        let inserted = buffer.insert(make_packet(0)); // Duplicate
+        assert!(buffer.was_reordered(65535));
+        buffer.insert(make_packet(65535));
+        buffer.insert(make_packet(2));
+        assert!(buffer.was_reordered(65534));
+        buffer.insert(make_packet(65534));
+        assert!(!buffer.was_reordered(3));
+
+        let extended: Vec<u64> = buffer.buffered().map(|bp| bp.extended_sequence).collect();
+        assert_eq!(extended, [65533, 65534, 65535, 65536, 65538]);
+        for expected in [65533, 65534, 65535, 0] {
+            assert_eq!(buffer.get_next().unwrap().sequence, expected);
+        }
+    }
 
-        assert!(!inserted);
+    #[test]
+    fn test_duplicate_packets() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 0,
+            max_packets: 10,
+            ..Default::default()
+        });
+
+        assert_eq!(buffer.insert(make_packet(0)), InsertOutcome::Inserted);
+        let outcome = buffer.insert(make_packet(0)); // Duplicate
+
+        assert_eq!(outcome, InsertOutcome::Duplicate);
         assert_eq!(buffer.buffer.len(), 1);
     }
 
+    #[test]
+    fn test_duplicate_is_not_late() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 0,
+            max_packets: 10,
+            ..Default::default()
+        });
+
+        buffer.insert(make_packet(0));
+        buffer.insert(make_packet(1));
+        buffer.get_next(); // Next expected is 1, still buffered
+
+        assert_eq!(buffer.insert(make_packet(1)), InsertOutcome::Duplicate);
+        assert_eq!(
+            buffer.insert(make_packet(0)),
+            InsertOutcome::Late { behind_by: 1 }
+        );
+    }
+
+    #[test]
+    fn test_overflow_reports_evicted_sequence() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 1000,
+            max_packets: 3,
+            ..Default::default()
+        });
+
+        for seq in 0..3 {
+            assert_eq!(buffer.insert(make_packet(seq)), InsertOutcome::Inserted);
+        }
+        let outcome = buffer.insert(make_packet(3));
+
+        assert_eq!(outcome, InsertOutcome::Overflowed { evicted_seq: 0 });
+        assert!(outcome.is_accepted());
+        assert_eq!(buffer.buffer.len(), 3);
+    }
+
+    #[test]
+    fn test_forward_jump_resyncs_and_reprimes() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 1000,
+            max_packets: 10,
+            prime_mode: PrimeMode::FirstTalkspurt,
+            ..Default::default()
+        });
+
+        buffer.insert(make_packet(0));
+        buffer.insert(make_packet(1));
+        assert_eq!(buffer.get_next().unwrap().sequence, 0);
+
+        // Sender restarted far ahead: held back until the next packet
+        // confirms it, then buffered packet 1 is flushed
+        let outcome = buffer.insert(make_packet(5000));
+        assert_eq!(outcome, InsertOutcome::Probation);
+        assert!(!outcome.is_accepted());
+        assert_eq!(buffer.status().next_sequence, Some(1));
+        let outcome = buffer.insert(make_packet(5001));
+        assert_eq!(outcome, InsertOutcome::Resync);
+        assert!(outcome.is_accepted());
+        assert_eq!(buffer.status().next_sequence, Some(5000));
+        assert_eq!(buffer.status().buffered_packets, 2);
+
+        // Re-prime falls back to depth-based priming
+        assert!(buffer.get_next().is_none());
+        buffer.insert(make_packet(5002));
+        assert_eq!(buffer.get_next().unwrap().sequence, 5000);
+    }
+
+    #[test]
+    fn test_resync_threshold_is_buffer_capacity() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 0,
+            max_packets: 10,
+            ..Default::default()
+        });
+
+        buffer.insert(make_packet(0));
+        assert_eq!(buffer.insert(make_packet(10)), InsertOutcome::Inserted);
+        assert_eq!(buffer.insert(make_packet(11)), InsertOutcome::Probation);
+        assert_eq!(buffer.insert(make_packet(12)), InsertOutcome::Resync);
+    }
+
+    #[test]
+    fn test_priming_report_records_duration_and_held_delays() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 200,
+            max_packets: 10,
+            ..Default::default()
+        });
+        let t0 = Instant::now();
+        let at = |ms: u64| t0 + Duration::from_millis(ms);
+
+        // One packet every 100ms: not primed until the depth elapses
+        buffer.insert_with_arrival(make_packet(0), at(0));
+        assert!(buffer.get_next_at(at(0)).is_none());
+        buffer.insert_with_arrival(make_packet(1), at(100));
+        assert!(buffer.get_next_at(at(199)).is_none());
+        assert!(buffer.take_priming_report().is_none());
+
+        let (packet, delay) = buffer.get_next_at(at(200)).expect("primed");
+        assert_eq!(packet.sequence, 0);
+        assert_eq!(delay, Duration::from_millis(200));
+
+        let report = buffer.take_priming_report().expect("report");
+        assert_eq!(report.duration, Duration::from_millis(200));
+        assert_eq!(
+            report.held_delays,
+            vec![Duration::from_millis(200), Duration::from_millis(100)]
+        );
+
+        // Reported once per prime
+        assert!(buffer.take_priming_report().is_none());
+    }
+
+    #[test]
+    fn test_resync_produces_new_priming_report() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 200,
+            max_packets: 10,
+            ..Default::default()
+        });
+        let t0 = Instant::now();
+        let at = |ms: u64| t0 + Duration::from_millis(ms);
+
+        buffer.insert_with_arrival(make_packet(0), at(0));
+        buffer.get_next_at(at(200)).expect("primed");
+        buffer.take_priming_report().expect("first prime");
+
+        buffer.insert_with_arrival(make_packet(900), at(1000));
+        buffer.insert_with_arrival(make_packet(901), at(1020));
+        assert!(buffer.get_next_at(at(1100)).is_none());
+        buffer.get_next_at(at(1250)).expect("re-primed");
+
+        // Timed from the packet that moved the stream
+        let report = buffer.take_priming_report().expect("re-prime");
+        assert_eq!(report.duration, Duration::from_millis(250));
+        assert_eq!(
+            report.held_delays,
+            vec![Duration::from_millis(250), Duration::from_millis(230)]
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_insert_accepted_shim() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig::default());
+
+        assert!(buffer.insert_accepted(make_packet(0)));
+        assert!(!buffer.insert_accepted(make_packet(0)));
+    }
+
     #[test]
     fn test_buffer_priming() {
         // ---
         let mut buffer = JitterBuffer::new(JitterBufferConfig {
             depth_ms: 100, // 100ms depth
             max_packets: 10,
+            ..Default::default()
         });
 
         buffer.insert(make_packet(0));
@@ -391,4 +1589,715 @@ mod tests {
         std::thread::sleep(Duration::from_millis(110));
         assert!(buffer.get_next().is_some());
     }
+
+    /// Measures time from the first packet arrival to the first playout
+    /// under a clean network (in-order packets every 20ms).
+    fn first_audio_latency(prime_mode: PrimeMode) -> Duration {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            max_packets: 10,
+            prime_mode,
+            ..Default::default()
+        });
+
+        let start = Instant::now();
+        for seq in 0..10 {
+            buffer.insert(make_packet(seq));
+            if buffer.get_next().is_some() {
+                return start.elapsed();
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        panic!("no packet released for {:?}", prime_mode);
+    }
+
+    #[test]
+    fn test_prime_mode_first_audio_latency() {
+        // ---
+        let wait = first_audio_latency(PrimeMode::WaitForDepth);
+        let immediate = first_audio_latency(PrimeMode::Immediate);
+        let first_talkspurt = first_audio_latency(PrimeMode::FirstTalkspurt);
+
+        // Immediate starts on the first packet; WaitForDepth holds for
+        // roughly the configured depth (3 packets at 20ms pacing).
+        assert!(immediate < Duration::from_millis(10), "{:?}", immediate);
+        assert!(first_talkspurt < Duration::from_millis(10));
+        assert!(
+            wait >= immediate + Duration::from_millis(35),
+            "wait={:?} immediate={:?}",
+            wait,
+            immediate
+        );
+    }
+
+    #[test]
+    fn test_prime_mode_immediate_releases_in_order() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 1000,
+            max_packets: 10,
+            prime_mode: PrimeMode::Immediate,
+            ..Default::default()
+        });
+
+        buffer.insert(make_packet(0));
+        assert_eq!(buffer.get_next().unwrap().sequence, 0);
+
+        // Out-of-order arrival waits for the gap to fill
+        buffer.insert(make_packet(2));
+        assert!(buffer.get_next().is_none());
+        buffer.insert(make_packet(1));
+        assert_eq!(buffer.get_next().unwrap().sequence, 1);
+        assert_eq!(buffer.get_next().unwrap().sequence, 2);
+    }
+
+    /// Inserts packet `n` at `arrivals[n]` ms and returns the first ms at
+    /// which playout releases a packet, if within a second.
+    fn primes_at(config: JitterBufferConfig, arrivals: &[u64]) -> Option<u64> {
+        // ---
+        let mut buffer = JitterBuffer::new(config);
+        let start = Instant::now();
+        (0..1000).find(|&ms| {
+            let now = start + Duration::from_millis(ms);
+            for (seq, _) in arrivals.iter().enumerate().filter(|&(_, &at)| at == ms) {
+                buffer.insert_with_arrival(make_packet(seq as u16), now);
+            }
+            buffer.get_next_at(now).is_some()
+        })
+    }
+
+    #[test]
+    fn test_prime_by_default_is_depth_or_three_packets() {
+        // ---
+        let config = JitterBufferConfig {
+            depth_ms: 200,
+            ..Default::default()
+        };
+        assert_eq!(primes_at(config.clone(), &[0, 20, 40]), Some(40));
+        assert_eq!(primes_at(config, &[0, 150]), Some(200));
+    }
+
+    #[test]
+    fn test_prime_by_duration_ignores_bursts() {
+        // ---
+        let config = JitterBufferConfig {
+            depth_ms: 200,
+            prime_by: Some(PrimeBy::Duration(200)),
+            ..Default::default()
+        };
+        assert_eq!(primes_at(config, &[0, 0, 0, 0, 0, 10, 10, 10]), Some(200));
+    }
+
+    #[test]
+    fn test_prime_by_packets_waits_for_count() {
+        // ---
+        let config = JitterBufferConfig {
+            depth_ms: 60,
+            prime_by: Some(PrimeBy::Packets(4)),
+            ..Default::default()
+        };
+        assert_eq!(primes_at(config.clone(), &[0, 100, 300, 500]), Some(500));
+        assert_eq!(primes_at(config.clone(), &[0, 100, 300]), None);
+
+        let mut buffer = JitterBuffer::new(config);
+        buffer.insert(make_packet(0));
+        assert_eq!(
+            buffer.next_action(),
+            Some(PlayoutAction::Wait(Duration::MAX))
+        );
+    }
+
+    #[test]
+    fn test_prime_by_either_takes_first() {
+        // ---
+        let config = JitterBufferConfig {
+            depth_ms: 200,
+            prime_by: Some(PrimeBy::Either(100, 5)),
+            ..Default::default()
+        };
+        assert_eq!(primes_at(config.clone(), &[0, 10, 20, 30, 40]), Some(40));
+        assert_eq!(primes_at(config, &[0, 10]), Some(100));
+    }
+
+    #[test]
+    fn test_underrun_reprimes_by_policy() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            prime_by: Some(PrimeBy::Packets(2)),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        buffer.insert_with_arrival(make_packet(0), at(0));
+        buffer.insert_with_arrival(make_packet(1), at(20));
+        assert_eq!(buffer.get_next_at(at(20)).unwrap().0.sequence, 0);
+        assert_eq!(buffer.get_next_at(at(20)).unwrap().0.sequence, 1);
+
+        // Drained for longer than the depth: 2 waits for a second packet
+        // rather than playing at once
+        buffer.insert_with_arrival(make_packet(2), at(200));
+        assert!(!buffer.status().is_primed);
+        assert!(buffer.get_next_at(at(500)).is_none());
+        buffer.insert_with_arrival(make_packet(3), at(520));
+        assert_eq!(buffer.get_next_at(at(520)).unwrap().0.sequence, 2);
+        assert_eq!(buffer.stats().underruns, 1);
+
+        let report = buffer.take_priming_report().expect("re-prime");
+        assert_eq!(report.duration, Duration::from_millis(320));
+    }
+
+    /// Feeds `count` packets from `first`, 20ms apart at 16kHz, each
+    /// delayed by `delay(seq)`, and plays them out as they arrive.
+    /// Returns the largest step of the target depth seen.
+    fn feed_adaptive(
+        buffer: &mut JitterBuffer,
+        start: Instant,
+        first: u16,
+        count: u16,
+        delay: impl Fn(u16) -> Duration,
+    ) -> Duration {
+        // ---
+        let mut step = Duration::ZERO;
+        for seq in first..first + count {
+            let arrival = start + Duration::from_millis(seq as u64 * 20) + delay(seq);
+            let before = buffer.target_depth();
+            buffer.insert_with_arrival(make_packet(seq), arrival);
+            while buffer.get_next_at(arrival).is_some() {}
+            step = step.max(before.abs_diff(buffer.target_depth()));
+        }
+        step
+    }
+
+    fn adaptive_buffer() -> JitterBuffer {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            adaptive: Some(AdaptiveDepthConfig {
+                min_ms: 20,
+                max_ms: 200,
+            }),
+            ..Default::default()
+        });
+        buffer.set_clock(&StreamClock {
+            clock_rate: 16000,
+            frame_duration: Duration::from_millis(20),
+            detected: false,
+        });
+        buffer
+    }
+
+    #[test]
+    fn test_adaptive_depth_follows_jitter_spike() {
+        // ---
+        let mut buffer = adaptive_buffer();
+        let start = Instant::now();
+        let frame = Duration::from_millis(20);
+
+        // A steady network lets the depth shrink to its minimum
+        let step = feed_adaptive(&mut buffer, start, 0, 250, |_| Duration::ZERO);
+        assert_eq!(buffer.target_depth(), Duration::from_millis(20));
+        assert!(step <= frame, "step {:?}", step);
+
+        // Every other packet 60ms late: the depth grows, a frame a second
+        let step = feed_adaptive(&mut buffer, start, 250, 250, |seq| {
+            Duration::from_millis(if seq % 2 == 0 { 60 } else { 0 })
+        });
+        let grown = buffer.status().target_depth;
+        assert!(grown >= Duration::from_millis(80), "grown {:?}", grown);
+        assert!(grown <= Duration::from_millis(200));
+        assert!(step <= frame, "step {:?}", step);
+
+        // The spike passes: back down to the minimum
+        let step = feed_adaptive(&mut buffer, start, 500, 750, |_| Duration::ZERO);
+        assert_eq!(buffer.status().target_depth, Duration::from_millis(20));
+        assert!(step <= frame, "step {:?}", step);
+    }
+
+    #[test]
+    fn test_adaptive_depth_grows_on_late_packets() {
+        // ---
+        let mut buffer = adaptive_buffer();
+        let start = Instant::now();
+
+        // Every tenth packet a copy of an earlier one arrives after it played
+        let before = buffer.target_depth();
+        for seq in 0..200u16 {
+            let arrival = start + Duration::from_millis(seq as u64 * 20);
+            buffer.insert_with_arrival(make_packet(seq), arrival);
+            while buffer.get_next_at(arrival).is_some() {}
+            if seq % 10 == 9 {
+                let late = make_packet(seq - 5);
+                assert!(matches!(
+                    buffer.insert_with_arrival(late, arrival),
+                    InsertOutcome::Late { .. }
+                ));
+            }
+        }
+        assert!(buffer.target_depth() > before);
+    }
+
+    #[test]
+    fn test_fixed_depth_does_not_adapt() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig::default());
+        let start = Instant::now();
+        feed_adaptive(&mut buffer, start, 0, 250, |seq| {
+            Duration::from_millis(if seq % 2 == 0 { 60 } else { 0 })
+        });
+        assert_eq!(buffer.status().target_depth, Duration::from_millis(60));
+    }
+
+    /// Feeds packets `0..10` but those in `dropped` 20ms apart, playing
+    /// out as each arrives and once more well after the last, and returns
+    /// what playout was told to do, leaving out waits.
+    fn play_with_losses(config: JitterBufferConfig, dropped: &[u16]) -> Vec<PlayoutAction> {
+        // ---
+        let mut buffer = JitterBuffer::new(config);
+        let start = Instant::now();
+        let mut actions = Vec::new();
+        let mut drain = |buffer: &mut JitterBuffer, now: Instant| {
+            while let Some(action) = buffer.next_action_at(now) {
+                match action {
+                    PlayoutAction::Wait(_) => break,
+                    action => actions.push(action),
+                }
+            }
+        };
+        for seq in (0..10u16).filter(|seq| !dropped.contains(seq)) {
+            let arrival = start + Duration::from_millis(seq as u64 * 20);
+            buffer.insert_with_arrival(make_packet(seq), arrival);
+            drain(&mut buffer, arrival);
+        }
+        drain(&mut buffer, start + Duration::from_secs(1));
+        actions
+    }
+
+    /// Describes playout actions as played sequence numbers, and lost
+    /// ones negated.
+    fn describe(actions: &[PlayoutAction]) -> Vec<i32> {
+        // ---
+        actions
+            .iter()
+            .map(|action| match action {
+                PlayoutAction::Packet { packet, .. } | PlayoutAction::Accelerate { packet, .. } => {
+                    packet.sequence as i32
+                }
+                PlayoutAction::Lost { sequence } => -(*sequence as i32),
+                PlayoutAction::Wait(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lost_packet_signalled_between_neighbours() {
+        // ---
+        let config = JitterBufferConfig {
+            depth_ms: 60,
+            ..Default::default()
+        };
+        let actions = play_with_losses(config, &[4]);
+        assert_eq!(describe(&actions), [0, 1, 2, 3, -4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_lost_waits_max_wait_after_next_arrival() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            prime_mode: PrimeMode::Immediate,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        buffer.insert_with_arrival(make_packet(0), start);
+        assert!(matches!(
+            buffer.next_action_at(start),
+            Some(PlayoutAction::Packet { .. })
+        ));
+
+        // Packet 1 is missing; packet 2 is held the depth and a frame
+        // before giving up
+        let arrival = start + Duration::from_millis(40);
+        buffer.insert_with_arrival(make_packet(2), arrival);
+        assert_eq!(buffer.max_wait(), Duration::from_millis(80));
+        assert_eq!(
+            buffer.next_action_at(arrival + Duration::from_millis(20)),
+            Some(PlayoutAction::Wait(Duration::from_millis(60)))
+        );
+        assert_eq!(
+            buffer.next_action_at(arrival + Duration::from_millis(80)),
+            Some(PlayoutAction::Lost { sequence: 1 })
+        );
+        assert_eq!(
+            buffer
+                .get_next_at(arrival + Duration::from_millis(80))
+                .unwrap()
+                .0
+                .sequence,
+            2
+        );
+        assert_eq!(buffer.next_action_at(arrival), None);
+
+        // Too late to play now
+        assert!(matches!(
+            buffer.insert_with_arrival(make_packet(1), arrival + Duration::from_millis(80)),
+            InsertOutcome::Late { .. }
+        ));
+    }
+
+    #[test]
+    fn test_gap_skipped_after_max_wait() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            prime_mode: PrimeMode::Immediate,
+            max_wait_ms: Some(100),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        for seq in 0..5u16 {
+            let arrival = start + Duration::from_millis(seq as u64 * 20);
+            buffer.insert_with_arrival(make_packet(seq), arrival);
+            assert!(buffer.get_next_at(arrival).is_some());
+        }
+
+        // Packets 5-7 never arrive; 8 waits the configured time for them
+        let arrival = start + Duration::from_millis(160);
+        buffer.insert_with_arrival(make_packet(8), arrival);
+        assert_eq!(
+            buffer.next_action_at(arrival + Duration::from_millis(99)),
+            Some(PlayoutAction::Wait(Duration::from_millis(1)))
+        );
+        let now = arrival + Duration::from_millis(100);
+        let mut actions = Vec::new();
+        while let Some(action) = buffer.next_action_at(now) {
+            actions.push(action);
+        }
+        assert_eq!(describe(&actions), [-5, -6, -7, 8]);
+
+        // Skipped, so too late now rather than buffered again
+        assert_eq!(
+            buffer.insert_with_arrival(make_packet(6), now + Duration::from_millis(20)),
+            InsertOutcome::Late { behind_by: 3 }
+        );
+        assert_eq!(buffer.status().buffered_packets, 0);
+    }
+
+    /// Plays 20 packets sent every 20ms through a 100ms buffer, packet 10
+    /// arriving `delay_ms` late, and returns the actions with the stats.
+    fn play_with_late_arrival(delay_ms: u64) -> (Vec<PlayoutAction>, JitterBufferStats) {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 100,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let mut arrivals: Vec<(u64, u16)> = (0..20u16)
+            .map(|seq| {
+                let late = if seq == 10 { delay_ms } else { 0 };
+                (seq as u64 * 20 + late, seq)
+            })
+            .collect();
+        arrivals.sort();
+
+        // Playout polls every 5ms, past the last arrival
+        let mut actions = Vec::new();
+        let mut pending = arrivals.into_iter().peekable();
+        for tick in (0..=800).step_by(5) {
+            let now = start + Duration::from_millis(tick);
+            while let Some((_, seq)) = pending.next_if(|&(at, _)| at <= tick) {
+                buffer.insert_with_arrival(make_packet(seq), now);
+            }
+            while let Some(action) = buffer.next_action_at(now) {
+                if matches!(action, PlayoutAction::Wait(_)) {
+                    break;
+                }
+                actions.push(action);
+            }
+        }
+        (actions, buffer.take_stats())
+    }
+
+    #[test]
+    fn test_late_arrival_within_max_wait_still_plays() {
+        // ---
+        // 30ms behind the packet after it: within the wait, so it plays in
+        // its place
+        let (actions, stats) = play_with_late_arrival(30);
+        assert_eq!(describe(&actions), (0..20).collect::<Vec<_>>());
+        assert_eq!((stats.lost, stats.late), (0, 0));
+
+        // 150ms behind: concealed, then counted late when it turns up
+        let (actions, stats) = play_with_late_arrival(150);
+        let expected: Vec<i32> = (0..20)
+            .map(|seq| if seq == 10 { -10 } else { seq })
+            .collect();
+        assert_eq!(describe(&actions), expected);
+        assert_eq!((stats.lost, stats.late), (1, 1));
+    }
+
+    #[test]
+    fn test_stats_count_insert_outcomes() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 0,
+            max_packets: 4,
+            prime_mode: PrimeMode::Immediate,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        buffer.insert_with_arrival(make_packet(0), start);
+        assert!(buffer.get_next_at(start).is_some());
+
+        // Unplayed, 0 is late, 2 and 3 come twice, and 5 evicts 1
+        for seq in [0, 1, 2, 2, 3, 3, 4, 5] {
+            buffer.insert_with_arrival(make_packet(seq), start);
+        }
+
+        let stats = buffer.take_stats();
+        assert_eq!(
+            stats,
+            JitterBufferStats {
+                inserted: 6,
+                duplicates: 2,
+                late: 1,
+                overflow_drops: 1,
+                residence: ResidenceSummary {
+                    count: 1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        );
+
+        // Taking the stats starts them over
+        assert_eq!(*buffer.stats(), JitterBufferStats::default());
+        buffer.insert_with_arrival(make_packet(4), start);
+        assert_eq!(buffer.stats().duplicates, 1);
+        assert_eq!(buffer.stats().inserted, 0);
+    }
+
+    #[test]
+    fn test_stats_count_gaps_and_residence() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            prime_mode: PrimeMode::Immediate,
+            max_concealed: 2,
+            max_wait_ms: Some(100),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Held 0ms, then 10ms; 2 is lost alone; 4-6 exceed the concealment
+        // cap, and 7 waits 100ms for them
+        buffer.insert_with_arrival(make_packet(0), at(0));
+        buffer.get_next_at(at(0));
+        buffer.insert_with_arrival(make_packet(1), at(20));
+        buffer.get_next_at(at(30));
+        buffer.insert_with_arrival(make_packet(3), at(60));
+        buffer.get_next_at(at(160));
+        buffer.insert_with_arrival(make_packet(7), at(140));
+        buffer.get_next_at(at(240));
+
+        let stats = buffer.stats();
+        assert_eq!(stats.gaps_skipped, 2);
+        assert_eq!(stats.lost, 3);
+        assert_eq!(stats.underruns, 0);
+        assert_eq!(
+            stats.residence,
+            ResidenceSummary {
+                count: 4,
+                min: Duration::ZERO,
+                max: Duration::from_millis(100),
+                total: Duration::from_millis(210),
+            }
+        );
+        assert_eq!(stats.residence.mean(), Some(Duration::from_micros(52_500)));
+    }
+
+    #[test]
+    fn test_stats_count_underruns() {
+        // ---
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            prime_mode: PrimeMode::Immediate,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        for seq in 0..3u16 {
+            buffer.insert_with_arrival(make_packet(seq), at(seq as u64 * 20));
+            buffer.get_next_at(at(seq as u64 * 20));
+        }
+
+        // Within the depth of the last release, then beyond it
+        buffer.insert_with_arrival(make_packet(3), at(100));
+        buffer.get_next_at(at(100));
+        buffer.insert_with_arrival(make_packet(4), at(200));
+        buffer.get_next_at(at(200));
+        assert_eq!(buffer.stats().underruns, 1);
+
+        // The first packet of a talkspurt ends a silence, not an underrun
+        let mut talkspurt = make_packet(5);
+        talkspurt.marker = true;
+        buffer.insert_with_arrival(talkspurt, at(1000));
+        assert_eq!(buffer.stats().underruns, 1);
+    }
+
+    /// Plays a stream 20ms a packet for 10s with a consumer pulling one
+    /// frame at a time, as an audio device would, where a 500ms stall at
+    /// 2s delivers its packets in a burst. Returns the occupancy 2s into
+    /// the stream, half a second after the burst, and at the end, and the
+    /// buffer.
+    fn play_through_stall(strategy: OverflowStrategy) -> ([Duration; 3], JitterBuffer) {
+        // ---
+        const FRAME: Duration = Duration::from_millis(20);
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60,
+            overflow_strategy: strategy,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let arrival = |seq: u64| {
+            let sent = seq * 20;
+            let delayed = (2000..2500).contains(&sent);
+            start + Duration::from_millis(if delayed { 2500 } else { sent })
+        };
+
+        let mut occupancy = Vec::new();
+        let mut next_pull = start;
+        let mut seq = 0;
+        for ms in 0..10_000 {
+            let now = start + Duration::from_millis(ms);
+            while arrival(seq) <= now {
+                buffer.insert_with_arrival(make_packet(seq as u16), arrival(seq));
+                seq += 1;
+            }
+            if now >= next_pull {
+                // Silence, concealment, or a frame, played for a frame or
+                // a shortened one
+                let played = match buffer.next_action_at(now) {
+                    Some(PlayoutAction::Accelerate { .. }) => {
+                        FRAME.mul_f64(1.0 - ACCELERATE_FRACTION)
+                    }
+                    _ => FRAME,
+                };
+                next_pull = now + played;
+            }
+            if [1999, 3000, 9999].contains(&ms) {
+                occupancy.push(buffer.status().occupancy);
+            }
+        }
+        (occupancy.try_into().unwrap(), buffer)
+    }
+
+    #[test]
+    fn test_stall_burst_kept_as_latency() {
+        // ---
+        let ([before, after, end], _) = play_through_stall(OverflowStrategy::Keep);
+        assert!(before <= Duration::from_millis(60));
+        assert!(after >= Duration::from_millis(500));
+        assert!(end >= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_stall_burst_dropped_after_grace() {
+        // ---
+        let ([_, after, end], buffer) = play_through_stall(OverflowStrategy::DropOldest);
+        assert!(after >= Duration::from_millis(500));
+        assert!(end <= Duration::from_millis(80), "occupancy {end:?}");
+        assert!(buffer.stats().catch_up_drops >= 20);
+        assert_eq!(buffer.stats().accelerated, 0);
+    }
+
+    #[test]
+    fn test_stall_burst_drained_by_acceleration() {
+        // ---
+        let ([_, after, end], buffer) = play_through_stall(OverflowStrategy::Accelerate);
+        assert!(after >= Duration::from_millis(500));
+        assert!(end <= Duration::from_millis(80), "occupancy {end:?}");
+        assert_eq!(buffer.stats().catch_up_drops, 0);
+        assert!(buffer.stats().accelerated >= 200);
+    }
+
+    #[test]
+    fn test_lost_run_capped_at_max_concealed() {
+        // ---
+        let config = JitterBufferConfig {
+            depth_ms: 60,
+            max_concealed: 3,
+            ..Default::default()
+        };
+        let actions = play_with_losses(config, &[3, 4, 5, 6, 7]);
+        assert_eq!(describe(&actions), [0, 1, 2, -3, -4, -5, 8, 9]);
+
+        // The count starts over after a packet plays
+        let config = JitterBufferConfig {
+            depth_ms: 60,
+            max_concealed: 2,
+            ..Default::default()
+        };
+        let actions = play_with_losses(config, &[2, 3, 5, 6]);
+        assert_eq!(describe(&actions), [0, 1, -2, -3, 4, -5, -6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_random_arrival_order_plays_in_order() {
+        // ---
+        // xorshift64: arbitrary, reproducible order without a dependency
+        let mut state = 0x2081_9E37_79B9_7F4Au64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        // 10k packets wrapping the sequence number, each but the first
+        // (where playout starts) displaced by up to 32 places, and about
+        // one in ten sent twice
+        const PACKETS: u64 = 10_000;
+        const FIRST: u16 = 60_000;
+        let mut arrivals: Vec<(u64, u16)> = (0..PACKETS)
+            .map(|i| match i {
+                0 => (0, FIRST),
+                _ => (i + next() % 32, FIRST.wrapping_add(i as u16)),
+            })
+            .collect();
+        let copies: Vec<(u64, u16)> = arrivals
+            .iter()
+            .filter_map(|&(slot, seq)| match next() % 10 {
+                0 => Some((slot + next() % 64, seq)),
+                _ => None,
+            })
+            .collect();
+        arrivals.extend(copies);
+        arrivals.sort_by_key(|&(slot, _)| slot);
+
+        // Deep enough that no gap is given up on
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 60_000,
+            max_packets: 200,
+            prime_mode: PrimeMode::Immediate,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let mut played = Vec::with_capacity(PACKETS as usize);
+        for (i, &(_, seq)) in arrivals.iter().enumerate() {
+            let arrival = start + Duration::from_millis(i as u64);
+            let packet = RtpPacket::new(seq, 0, 0x12345678, vec![1, 2, 3]);
+            buffer.insert_with_arrival(packet, arrival);
+            while let Some((packet, _)) = buffer.get_next_at(arrival) {
+                played.push(packet.sequence);
+            }
+        }
+
+        let expected: Vec<u16> = (0..PACKETS).map(|i| FIRST.wrapping_add(i as u16)).collect();
+        assert_eq!(played, expected);
+        assert_eq!(buffer.status().buffered_packets, 0);
+    }
 }
@@ -4,146 +4,1536 @@
 //! This library can be used to build custom receivers or for integration testing.
 
 pub mod audio;
+pub mod clock;
 pub mod codec;
+pub mod control;
+pub mod decoders;
+#[cfg(feature = "formats")]
+pub mod g711;
 pub mod jitter_buffer;
+pub mod nack;
 pub mod network;
+pub mod refresh;
+pub mod source;
+pub mod ssrc;
 pub mod stats;
+pub mod tcp;
+pub mod transit;
+pub mod transport_cc;
+pub mod validator;
+pub mod xr;
 
+#[cfg(feature = "playback")]
 pub use audio::AudioPlayer;
-pub use codec::OpusDecoderWrapper;
-pub use jitter_buffer::{JitterBuffer, JitterBufferConfig};
-pub use network::RtpReceiver;
-pub use rtp_opus_common::RtpPacket;
-pub use stats::ReceiverStats;
+pub use audio::{shorten_frame, AudioSink, NullSink};
+pub use clock::{ClockRateConfig, ClockRateDetector, StreamClock};
+pub use codec::{AudioDecoder, OpusDecoderWrapper};
+pub use control::{dump_history, spawn_underrun_monitor, ControlServer, HistoryDumpConfig};
+pub use decoders::{DecoderSet, PayloadTypeStats, RecoveredFrame, Recovery};
+#[cfg(feature = "formats")]
+pub use g711::{G711Decoder, G711Law};
+pub use jitter_buffer::{
+    AdaptiveDepthConfig, BufferedPacket, InsertOutcome, JitterBuffer, JitterBufferConfig,
+    JitterBufferStats, JitterBufferStatus, OverflowStrategy, PlayoutAction, PrimeBy, PrimeMode,
+    PrimingReport, ResidenceSummary, ACCELERATE_FRACTION,
+};
+pub use nack::{NackConfig, NackRequester};
+pub use network::{
+    ParseErrorCounts, ReceiverNetworkConfig, RecvBuffer, RtpReceiver, RtpReceiverStats,
+    DEFAULT_KEEPALIVE_INTERVAL, DEFAULT_RECV_BUFFER_DATAGRAMS,
+};
+pub use refresh::{RefreshConfig, RefreshDecision, RefreshRequester};
+pub use rtp_opus_common::{
+    BuildInfo, CompoundPacket, DecoderRefreshRequest, MetricsContext, Nack, ReceiverReport,
+    ReportBlock, RtpPacket, RtpParseErrorKind, RtpParseMode, PAYLOAD_TYPE_OPUS,
+};
+pub use source::{ObservedSource, SourceTracker};
+pub use ssrc::{SsrcTracker, SsrcUpdate};
+pub use stats::{
+    DumpReason, HistorySnapshot, PlayoutEvent, PlayoutEventKind, PlayoutHistory, ReceiverStats,
+    ReceptionReport, StatsHandle, StatsSnapshot, StatsTicker, UnderrunBurstDetector,
+};
+pub use transit::TransitEstimator;
+pub use transport_cc::{TransportArrival, TransportHistory, DEFAULT_TRANSPORT_HISTORY_PACKETS};
+pub use validator::{ArrivalStats, StreamValidator, ValidationReport, Violation};
+pub use xr::{BurstGapSummary, BurstGapTracker, PlayoutDelays, Reception, ReceptionHistory};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use rtp_opus_common::{
+    decode_abs_send_time, decode_transport_sequence, dlsr_units, AnomalyLogConfig, AnomalyLogger,
+    SessionConfig, SessionEvent, SessionTracker,
+};
+use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::warn;
+use tracing::{debug, info, warn};
+
+/// Returns the version, commit, and enabled features of this crate and
+/// the crates it links.
+pub fn build_info() -> BuildInfo {
+    // ---
+    BuildInfo::new("receiver", env!("CARGO_PKG_VERSION"))
+        .with_feature("receiver/playback", cfg!(feature = "playback"))
+        .with_feature("receiver/formats", cfg!(feature = "formats"))
+        .with_feature("receiver/metrics-server", cfg!(feature = "metrics-server"))
+}
+
+/// Default time between RTCP receiver reports, as RFC 3550 suggests for
+/// a small session
+pub const DEFAULT_RECEIVER_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time between exports of the jitter buffer counters
+const JITTER_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Options for [`receive_loop`] and [`receive_loop_with_setup`].
+#[derive(Debug, Clone, Default)]
+pub struct ReceiveOptions {
+    // ---
+    /// Jitter buffer configuration
+    pub jitter: JitterBufferConfig,
+
+    /// RTP clock rate, or auto-detection from the stream
+    pub clock_rate: ClockRateConfig,
+
+    /// Return once the stream has started and then been quiet this long;
+    /// `None` receives until an error occurs. The sender's keepalives
+    /// count as activity, so a timeout longer than their interval outlasts
+    /// a silent gap in the stream.
+    pub idle_timeout: Option<Duration>,
+
+    /// Ask the sender to reset its encoder after long outages; `None`
+    /// never sends feedback
+    pub refresh: Option<RefreshConfig>,
+
+    /// Ask the sender to retransmit lost packets while the jitter buffer
+    /// could still play them; `None` never NACKs
+    pub nack: Option<NackConfig>,
+
+    /// Send the sender an RTCP receiver report this often, on the stream
+    /// last received, or less often if the session bandwidth calls for
+    /// it (see [`RtpReceiver::schedule_receiver_reports`]); `None` sends
+    /// none. The first goes out one interval after the first packet.
+    pub receiver_report_interval: Option<Duration>,
+
+    /// Send an RTCP extended report (RFC 3611) with each receiver report:
+    /// a Loss RLE block over the packets since the previous one and a
+    /// VoIP Metrics block with the jitter buffer delays and a MOS estimate
+    /// (see [`ReceiverStats::take_extended_report`])
+    pub extended_reports: bool,
+
+    /// Consecutive packets from a new SSRC that confirm it replaced the
+    /// stream being played (a sender restart), which then starts over;
+    /// `None` takes [`ssrc::DEFAULT_SSRC_CONFIRM_PACKETS`]
+    pub ssrc_confirm_packets: Option<u32>,
+
+    /// Return once the stream being received ends with an RTCP BYE, after
+    /// playing out whatever is still buffered. Otherwise the loop plays it
+    /// out, logs the stream's stats, and waits for the next stream.
+    pub exit_on_bye: bool,
+
+    /// How often late, evicted, and invalid packets are summarized in the
+    /// log after the first of each kind
+    pub anomaly_log: AnomalyLogConfig,
+
+    /// Expect a CRC32 trailer on every payload and drop packets that fail
+    /// it (see [`RtpReceiver::set_verify_payloads`]); the sender must be
+    /// appending trailers
+    pub verify_payloads: bool,
+
+    /// Drop packets with any other payload type before they reach the
+    /// jitter buffer (see [`RtpReceiver::set_expected_payload_type`]);
+    /// `None` accepts any
+    pub expect_payload_type: Option<u8>,
+
+    /// How strictly datagrams are parsed (see
+    /// [`RtpReceiver::set_parse_mode`]); those refused are counted by
+    /// reason in `rtp_parse_errors_total`
+    pub parse_mode: RtpParseMode,
+
+    /// One-byte header extension ID the sender stamps abs-send-time with;
+    /// stamped packets feed `network_transit_seconds` from their send
+    /// times (see [`TransitEstimator`]). `None`, or a packet without the
+    /// stamp, falls back to an estimate from the RTP timestamps.
+    pub abs_send_time_id: Option<u8>,
+
+    /// One-byte header extension ID the sender puts transport-wide
+    /// sequence numbers in; their arrivals are recorded for
+    /// [`ReceiverStats::transport_feedback`]. `None` records none.
+    pub transport_cc_id: Option<u8>,
+
+    /// Ring the loop records arrivals, releases, concealments, and
+    /// occupancy samples into for post-hoc glitch diagnosis; `None` keeps
+    /// no history
+    pub history: Option<Arc<PlayoutHistory>>,
+}
 
 /// Runs the receiver loop with jitter buffer and stats tracking.
 ///
 /// This is the main reception function that integrates all receiver components:
 /// network reception, jitter buffering, packet loss concealment, decoding, and playback.
 ///
+/// Stats are recorded into the caller's `stats`, so they can be read through
+/// a [`StatsHandle`] while the loop runs and inspected after it returns.
+/// With an idle timeout set, the loop returns once the stream has gone quiet,
+/// after playing out whatever is still buffered; with
+/// [`ReceiveOptions::exit_on_bye`] set, it also returns once the sender ends
+/// the stream with an RTCP BYE. With
+/// [`ReceiveOptions::history`] set, pipeline events are recorded into the
+/// [`PlayoutHistory`] for later dumps.
+///
 /// # Arguments
 ///
 /// * `receiver` - Network receiver for incoming RTP packets
-/// * `decoder` - Opus decoder instance
-/// * `player` - Audio playback device
-/// * `jitter_config` - Jitter buffer configuration
+/// * `decoders` - Decoders selected per packet by payload type
+/// * `player` - Audio sink receiving decoded frames
+/// * `stats` - Stats tracker to record reception into
+/// * `options` - Jitter buffer, clock rate, idle timeout, and refresh options
+/// * `metrics` - Metrics registry to report into
 ///
 /// # Errors
 ///
 /// Returns error if network or audio system fails critically.
 pub async fn receive_loop(
     receiver: &mut RtpReceiver,
-    decoder: &mut OpusDecoderWrapper,
-    player: &mut AudioPlayer,
-    jitter_config: JitterBufferConfig,
-    metrics: &rtp_opus_common::MetricsContext,
+    decoders: &mut DecoderSet,
+    player: &mut impl AudioSink,
+    stats: &mut ReceiverStats,
+    options: &ReceiveOptions,
+    metrics: &MetricsContext,
 ) -> Result<()> {
     // ---
-    let mut jitter_buffer = JitterBuffer::new(jitter_config);
-    let mut stats = ReceiverStats::new(Duration::from_secs(5));
+    receive_loop_with_setup(
+        receiver,
+        decoders,
+        async { Ok(player) },
+        stats,
+        options,
+        metrics,
+    )
+    .await?;
+    Ok(())
+}
 
-    // Used for estimating network transit time using RTP timestamp deltas.
-    let mut first_ts: Option<u32> = None;
-    let mut first_arrival: Option<std::time::Instant> = None;
+/// Runs the receiver loop while the audio sink is still being opened.
+///
+/// Same as [`receive_loop`], except the sink comes from `sink_setup`, which
+/// runs concurrently with reception. Packets that arrive before the sink is
+/// ready are buffered in the jitter buffer with their real arrival times, and
+/// playout begins with the usual priming logic once it is ready. This keeps
+/// slow device startup (hundreds of ms on some backends) from turning the
+/// first burst of packets into late or lost ones. The setup time and the
+/// number of packets buffered meanwhile are exported as metrics.
+///
+/// Returns the sink once the stream goes idle (see
+/// [`ReceiveOptions::idle_timeout`]) or ends with a BYE (see
+/// [`ReceiveOptions::exit_on_bye`]).
+///
+/// # Arguments
+///
+/// * `receiver` - Network receiver for incoming RTP packets
+/// * `decoders` - Decoders selected per packet by payload type
+/// * `sink_setup` - Future resolving to the audio sink
+/// * `stats` - Stats tracker to record reception into
+/// * `options` - Jitter buffer, clock rate, idle timeout, and refresh options
+/// * `metrics` - Metrics registry to report into
+///
+/// # Errors
+///
+/// Returns error if sink setup fails, or if network or audio system fails
+/// critically.
+pub async fn receive_loop_with_setup<S: AudioSink>(
+    receiver: &mut RtpReceiver,
+    decoders: &mut DecoderSet,
+    sink_setup: impl Future<Output = Result<S>>,
+    stats: &mut ReceiverStats,
+    options: &ReceiveOptions,
+    metrics: &MetricsContext,
+) -> Result<S> {
+    // ---
+    let setup_start = std::time::Instant::now();
+    tokio::pin!(sink_setup);
+    let mut player: Option<S> = None;
+    let mut setup_packets: i64 = 0;
 
-    loop {
-        // Receive packet from network
-        match receiver.receive().await? {
-            Some(packet) => {
-                let arrival = std::time::Instant::now();
-                let sequence = packet.sequence;
-                let was_reordered = jitter_buffer.was_reordered(sequence);
-
-                metrics.packets_received_total.inc();
-                metrics
-                    .bytes_received_total
-                    .inc_by(packet.payload.len() as u64);
+    let anomalies = AnomalyLogger::new(options.anomaly_log.clone()).with_metrics(metrics);
+    let mut stream = StreamState::new(options, anomalies.clone(), setup_start);
+    receiver.set_anomaly_logger(anomalies);
+    receiver.set_verify_payloads(options.verify_payloads);
+    receiver.set_expected_payload_type(options.expect_payload_type);
+    receiver.set_parse_mode(options.parse_mode);
+    receiver.schedule_receiver_reports(options.receiver_report_interval);
+    let mut parse_errors = receiver.stats().parse_errors;
+    let mut crc_mismatches = receiver.payload_crc_mismatches();
+    let mut pt_mismatches = receiver.payload_type_mismatches();
+    let mut srtp_failures = receiver.srtp_auth_failures();
+    let mut oversized = receiver.datagrams_oversized();
+    let mut keepalives = receiver.keepalives_received();
+    let mut sender_reports = receiver.sender_reports_received();
+    let mut transit = TransitEstimator::new();
 
-                // Baseline for RTP timestamp -> media time.
-                if first_ts.is_none() {
-                    first_ts = Some(packet.timestamp);
-                    first_arrival = Some(arrival);
-                }
+    if let Some(clock) = stream.clock_detector.clock() {
+        info!("RTP clock: {}Hz (configured)", clock.clock_rate);
+        publish_clock(&clock, stats, metrics);
+    }
 
-                // Estimate network transit variation (no wall-clock sync required).
-                if let (Some(t0), Some(a0)) = (first_ts, first_arrival) {
-                    let dt_samples = packet.timestamp.wrapping_sub(t0) as u64;
-                    let media_secs = dt_samples as f64 / codec::SAMPLE_RATE as f64;
-                    let expected_arrival = a0 + std::time::Duration::from_secs_f64(media_secs);
-                    if arrival >= expected_arrival {
-                        metrics
-                            .network_transit_seconds
-                            .observe(arrival.duration_since(expected_arrival).as_secs_f64());
+    // SSRC of the stream being played, for matching an RTCP BYE and
+    // noticing a sender restart
+    let mut ssrcs = SsrcTracker::new(
+        options
+            .ssrc_confirm_packets
+            .unwrap_or(ssrc::DEFAULT_SSRC_CONFIRM_PACKETS),
+    );
+    let mut ended_by_bye = false;
+    let mut jitter_stats_published = std::time::Instant::now();
+
+    loop {
+        // Receive packet from network, buffering while the sink opens
+        let received = if player.is_none() {
+            tokio::select! {
+                sink = &mut sink_setup => {
+                    let setup_time = setup_start.elapsed();
+                    player = Some(sink.context("audio sink setup failed")?);
+                    info!(
+                        "Audio sink ready after {:?}, {} packets buffered during setup",
+                        setup_time, setup_packets
+                    );
+                    metrics.sink_setup_seconds.set(setup_time.as_secs_f64());
+                    metrics.sink_setup_buffered_packets.set(setup_packets);
+                    None
+                }
+                result = receiver.receive() => result?,
+            }
+        } else {
+            match options.idle_timeout {
+                Some(idle_timeout) if stream.started => {
+                    match tokio::time::timeout(idle_timeout, receiver.receive()).await {
+                        Ok(result) => result?,
+                        Err(_) => break,
                     }
                 }
+                _ => receiver.receive().await?,
+            }
+        };
+
+        // `None` is an invalid, corrupted, forged, or unexpected packet
+        // (already logged by the receiver), a keepalive, or the sink
+        // becoming ready
+        let mismatches = receiver.payload_crc_mismatches();
+        metrics
+            .payload_crc_mismatch_total
+            .inc_by(mismatches - crc_mismatches);
+        crc_mismatches = mismatches;
+        let mismatches = receiver.payload_type_mismatches();
+        metrics
+            .payload_type_mismatch_total
+            .inc_by(mismatches - pt_mismatches);
+        pt_mismatches = mismatches;
+        let failures = receiver.srtp_auth_failures();
+        metrics
+            .srtp_auth_failures_total
+            .inc_by(failures - srtp_failures);
+        srtp_failures = failures;
+        let dropped_oversized = receiver.datagrams_oversized();
+        metrics
+            .datagrams_oversized_total
+            .inc_by(dropped_oversized - oversized);
+        oversized = dropped_oversized;
+        let received_keepalives = receiver.keepalives_received();
+        metrics
+            .keepalives_received_total
+            .inc_by(received_keepalives - keepalives);
+        keepalives = received_keepalives;
+        let received_reports = receiver.sender_reports_received();
+        metrics
+            .sender_reports_received_total
+            .inc_by(received_reports - sender_reports);
+        sender_reports = received_reports;
+        for chunk in receiver.take_source_descriptions() {
+            if let Some(cname) = chunk.cname() {
+                stats.record_cname(chunk.ssrc, cname);
+            }
+        }
+        let errors = receiver.stats().parse_errors;
+        for ((kind, count), (_, before)) in errors.iter().zip(parse_errors.iter()) {
+            if count > before {
+                metrics
+                    .parse_errors_total
+                    .with_label_values(&[kind.label()])
+                    .inc_by(count - before);
+            }
+        }
+        parse_errors = errors;
+
+        let byes = receiver.take_byes();
+        if let Some(bye) = byes
+            .iter()
+            .find(|bye| ssrcs.active().is_some_and(|ssrc| bye.ssrcs.contains(&ssrc)))
+        {
+            info!(
+                "RTCP BYE from SSRC 0x{:08X}: {}",
+                ssrcs.active().unwrap_or_default(),
+                bye.reason.as_deref().unwrap_or("no reason given")
+            );
+            stream.jitter_buffer.flush();
+            if options.exit_on_bye {
+                ended_by_bye = true;
+                break;
+            }
 
-                // Insert into jitter buffer
-                if !jitter_buffer.insert_with_arrival(packet, arrival) {
-                    // Packet was late or duplicate
-                    stats.record_late_packet();
-                    metrics.packets_late_total.inc();
+            // Play out the ended stream and start over for the next one
+            restart_stream(
+                &mut stream,
+                decoders,
+                player.as_mut(),
+                stats,
+                receiver,
+                options,
+                metrics,
+            );
+            ssrcs.reset();
+            info!("Waiting for the next stream");
+            continue;
+        }
+
+        if let Some(packet) = received {
+            match ssrcs.observe(packet.ssrc) {
+                SsrcUpdate::Active => {}
+                SsrcUpdate::Unconfirmed => {
+                    metrics.ssrc_unconfirmed_packets_total.inc();
                     continue;
                 }
+                SsrcUpdate::Stale => {
+                    metrics.ssrc_stale_packets_total.inc();
+                    continue;
+                }
+                SsrcUpdate::Switched { .. } => {
+                    // The sender restarted: play out what is left of the
+                    // old stream and start over with this packet
+                    metrics.ssrc_changes_total.inc();
+                    restart_stream(
+                        &mut stream,
+                        decoders,
+                        player.as_mut(),
+                        stats,
+                        receiver,
+                        options,
+                        metrics,
+                    );
+                }
+            }
+            let arrival = std::time::Instant::now();
+            let sequence = packet.sequence;
+            if let Some(history) = &options.history {
+                let bytes = packet.payload.len() as u32;
+                history.record(PlayoutEventKind::Arrival, Some(sequence), bytes);
+            }
+            let was_reordered = stream.jitter_buffer.was_reordered(sequence);
+            stream.started = true;
+            stream
+                .session
+                .record(priming_event(&stream.jitter_buffer), arrival);
+            stream.session.record(SessionEvent::Packet, arrival);
+
+            metrics.packets_received_total.inc();
+            record_bytes_received(&packet, metrics);
+
+            // Baseline for RTP timestamp -> media time.
+            if stream.first_ts.is_none() {
+                stream.first_ts = Some(packet.timestamp);
+                stream.first_arrival = Some(arrival);
+            }
 
+            if let Some(clock) = stream.clock_detector.observe(sequence, packet.timestamp) {
+                info!(
+                    "RTP clock: {}Hz, {:?} frames (detected)",
+                    clock.clock_rate, clock.frame_duration
+                );
+                publish_clock(&clock, stats, metrics);
+            }
+            if let Some(clock) = stream.clock_detector.clock() {
+                stream.jitter_buffer.set_clock(&clock);
+                let jitter = stats.record_jitter(packet.timestamp, arrival, clock.clock_rate);
                 metrics
-                    .jitter_buffer_occupancy_packets
-                    .set(jitter_buffer.status().buffered_packets as i64);
+                    .interarrival_jitter_seconds
+                    .set(jitter.as_secs_f64());
+            }
 
-                // Record in stats
-                let lost_gap = stats.record_packet_and_get_loss(sequence, was_reordered);
-                if lost_gap > 0 {
-                    metrics.packets_lost_total.inc_by(lost_gap);
+            // Estimate network transit variation (no wall-clock sync required),
+            // from the send time if stamped, else from the RTP timestamp once
+            // the clock rate is known
+            let send_time = options
+                .abs_send_time_id
+                .and_then(|id| packet.extension_element(id))
+                .and_then(|data| decode_abs_send_time(&data).ok());
+            if let Some(send_time) = send_time {
+                let transit = transit.observe(send_time, arrival);
+                metrics
+                    .network_transit_seconds
+                    .observe(transit.as_secs_f64());
+            } else if let (Some(clock), Some(t0), Some(a0)) = (
+                stream.clock_detector.clock(),
+                stream.first_ts,
+                stream.first_arrival,
+            ) {
+                let expected_arrival = a0 + clock.media_duration(packet.timestamp.wrapping_sub(t0));
+                if arrival >= expected_arrival {
+                    metrics
+                        .network_transit_seconds
+                        .observe(arrival.duration_since(expected_arrival).as_secs_f64());
                 }
-                if was_reordered {
-                    metrics.packets_reordered_total.inc();
+            }
+
+            let transport_sequence = options
+                .transport_cc_id
+                .and_then(|id| packet.extension_element(id))
+                .and_then(|data| decode_transport_sequence(&data).ok());
+            if let Some(transport_sequence) = transport_sequence {
+                stats.record_transport_sequence(transport_sequence, arrival);
+            }
+
+            // Ask for packets lost long enough ago not to be just reordered
+            if let Some(nack) = &mut stream.nack {
+                nack.observe(sequence, arrival);
+                let lost = nack.due(arrival);
+                if !lost.is_empty() {
+                    request_retransmission(receiver, packet.ssrc, lost, metrics).await;
                 }
             }
-            None => {
-                // Invalid packet, already logged by receiver
+
+            // Insert into jitter buffer
+            let priming = !stream.jitter_buffer.status().is_primed;
+            let marker = packet.marker;
+            let media_ssrc = packet.ssrc;
+            let outcome = stream.jitter_buffer.insert_with_arrival(packet, arrival);
+            if outcome == InsertOutcome::Resync {
+                stream.session.record(SessionEvent::Disrupted, arrival);
+            }
+            if !record_insert_outcome(outcome, sequence, was_reordered, priming, stats, metrics) {
                 continue;
             }
-        }
+            if marker {
+                stats.record_talkspurt();
+                debug!("Talkspurt starts at seq={}", sequence);
+            }
 
-        // Try to get packets ready for playout
-        while let Some((packet, buffer_delay)) = jitter_buffer.get_next_with_delay() {
-            metrics
-                .jitter_buffer_delay_seconds
-                .observe(buffer_delay.as_secs_f64());
+            if receiver.receiver_report_due() {
+                let frame = stream.clock_detector.clock().map_or(
+                    Duration::from_millis(codec::FRAME_DURATION_MS as u64),
+                    |clock| clock.frame_duration,
+                );
+                let delays = options.extended_reports.then(|| PlayoutDelays {
+                    jitter_nominal: stream.jitter_buffer.target_depth(),
+                    ..PlayoutDelays::new(&options.jitter, frame)
+                });
+                send_receiver_report(receiver, media_ssrc, stats, delays, metrics).await;
+            }
+
+            if player.is_none() {
+                setup_packets += 1;
+            }
             metrics
                 .jitter_buffer_occupancy_packets
-                .set(jitter_buffer.status().buffered_packets as i64);
+                .set(stream.jitter_buffer.status().buffered_packets as i64);
+            metrics
+                .jitter_buffer_target_depth_seconds
+                .set(stream.jitter_buffer.target_depth().as_secs_f64());
+            if let Some(history) = &options.history {
+                history.sample_occupancy(stream.jitter_buffer.status().buffered_packets);
+            }
+        }
 
-            let pipeline_start = std::time::Instant::now();
-            let decode_start = std::time::Instant::now();
+        // Try to get packets ready for playout
+        if let Some(player) = player.as_mut() {
+            let stale = play_out(
+                &mut stream.jitter_buffer,
+                decoders,
+                player,
+                stream.refresh.as_mut(),
+                &mut stream.session,
+                options.history.as_deref(),
+                metrics,
+            );
+            if let Some(media_ssrc) = stale {
+                request_refresh(receiver, media_ssrc, metrics).await;
+            }
+        }
+        let now = std::time::Instant::now();
+        if now.saturating_duration_since(jitter_stats_published) >= JITTER_STATS_INTERVAL {
+            publish_jitter_stats(&mut stream.jitter_buffer, metrics);
+            jitter_stats_published = now;
+        }
+        stream.session.publish(metrics, now);
+    }
+
+    // Stream went idle or ended: play out whatever is still buffered,
+    // past any gaps
+    let mut player = player.context("stream ended before the audio sink was ready")?;
+    stream.jitter_buffer.flush();
+    stream
+        .session
+        .record(SessionEvent::Draining, std::time::Instant::now());
+    play_out(
+        &mut stream.jitter_buffer,
+        decoders,
+        &mut player,
+        None,
+        &mut stream.session,
+        options.history.as_deref(),
+        metrics,
+    );
+    if ended_by_bye {
+        info!("Stream ended, receive loop finished");
+    } else {
+        info!("Stream idle, receive loop finished");
+    }
+    stream.jitter_buffer.flush_anomalies();
+    receiver.flush_anomalies();
+    publish_jitter_stats(&mut stream.jitter_buffer, metrics);
+    log_stream_end(stats, stream.nack.as_ref(), &mut stream.session, metrics);
 
-            match decoder.decode(&packet.payload) {
-                Ok(samples) => {
+    Ok(player)
+}
+
+/// What the receive loop keeps about the stream being played, started
+/// over when another stream replaces it.
+struct StreamState {
+    // ---
+    /// Packets waiting for playout
+    jitter_buffer: JitterBuffer,
+
+    /// A packet of the stream arrived, arming the idle timeout
+    started: bool,
+
+    /// Decoder refresh requests after long loss runs, if enabled
+    refresh: Option<RefreshRequester>,
+
+    /// Retransmission requests for lost packets, if enabled
+    nack: Option<NackRequester>,
+
+    /// Timeline of the stream
+    session: SessionTracker,
+
+    /// RTP clock, configured or being detected from the stream
+    clock_detector: ClockRateDetector,
+
+    /// RTP timestamp and arrival of the first packet, the baseline for
+    /// estimating transit from RTP timestamp deltas
+    first_ts: Option<u32>,
+    first_arrival: Option<std::time::Instant>,
+}
+
+impl StreamState {
+    // ---
+    /// Creates the state for a stream starting at `start`, with the jitter
+    /// buffer logging to `anomalies`.
+    fn new(options: &ReceiveOptions, anomalies: AnomalyLogger, start: std::time::Instant) -> Self {
+        // ---
+        let mut jitter_buffer = JitterBuffer::new(options.jitter.clone());
+        jitter_buffer.set_anomaly_logger(anomalies);
+        Self {
+            jitter_buffer,
+            started: false,
+            refresh: options.refresh.clone().map(RefreshRequester::new),
+            nack: options.nack.clone().map(NackRequester::new),
+            session: SessionTracker::new(SessionConfig::default(), start),
+            clock_detector: ClockRateDetector::new(options.clock_rate),
+            first_ts: None,
+            first_arrival: None,
+        }
+    }
+}
+
+/// Plays out what is left of the stream being played, logs its end, and
+/// starts over for the next one: a new jitter buffer, decoders, clock
+/// detection, and reception baseline. The caller resets the SSRC tracker
+/// if the next stream's source is not already known.
+fn restart_stream<S: AudioSink>(
+    stream: &mut StreamState,
+    decoders: &mut DecoderSet,
+    player: Option<&mut S>,
+    stats: &mut ReceiverStats,
+    receiver: &mut RtpReceiver,
+    options: &ReceiveOptions,
+    metrics: &MetricsContext,
+) {
+    // ---
+    stream.jitter_buffer.flush();
+    if let Some(player) = player {
+        stream
+            .session
+            .record(SessionEvent::Draining, std::time::Instant::now());
+        play_out(
+            &mut stream.jitter_buffer,
+            decoders,
+            player,
+            None,
+            &mut stream.session,
+            options.history.as_deref(),
+            metrics,
+        );
+    }
+    stream.jitter_buffer.flush_anomalies();
+    publish_jitter_stats(&mut stream.jitter_buffer, metrics);
+    log_stream_end(stats, stream.nack.as_ref(), &mut stream.session, metrics);
+
+    let anomalies = stream.jitter_buffer.anomalies().clone();
+    *stream = StreamState::new(options, anomalies, std::time::Instant::now());
+    decoders.reset();
+    stats.resync();
+    stats.record_clock(stream.clock_detector.clock());
+    receiver.schedule_receiver_reports(options.receiver_report_interval);
+}
+
+/// Logs the final stats, NACK counts, and session timeline of a stream
+/// that went idle or ended.
+fn log_stream_end(
+    stats: &ReceiverStats,
+    nack: Option<&NackRequester>,
+    session: &mut SessionTracker,
+    metrics: &MetricsContext,
+) {
+    // ---
+    stats.log();
+    if let Some(nack) = nack {
+        info!(
+            "NACK: {} packets requested, {} arrived afterwards",
+            nack.requested(),
+            nack.recovered()
+        );
+    }
+    log_session(session, metrics);
+}
+
+/// Decodes and plays every packet the jitter buffer releases, and fills
+/// in the ones it gives up on from FEC or concealment.
+///
+/// Returns the SSRC of a stream whose decoder state went stale during a
+/// loss run, when `refresh` decides the sender should be asked to reset.
+/// Releases and concealments are recorded into `history` if given.
+fn play_out(
+    jitter_buffer: &mut JitterBuffer,
+    decoders: &mut DecoderSet,
+    player: &mut impl AudioSink,
+    mut refresh: Option<&mut RefreshRequester>,
+    session: &mut SessionTracker,
+    history: Option<&PlayoutHistory>,
+    metrics: &MetricsContext,
+) -> Option<u32> {
+    // ---
+    let mut stale = None;
+    let mut lost_run = 0;
+    while let Some(event) = jitter_buffer.next_action() {
+        let (packet, buffer_delay, accelerate) = match event {
+            PlayoutAction::Packet { packet, delay } => (packet, delay, false),
+            PlayoutAction::Accelerate { packet, delay } => (packet, delay, true),
+            PlayoutAction::Lost { sequence } => {
+                lost_run += 1;
+                let next = jitter_buffer
+                    .buffered()
+                    .next()
+                    .map(|buffered| &buffered.packet)
+                    .filter(|next| next.sequence == sequence.wrapping_add(1));
+                let Some(frame) = decoders.recover_lost(sequence, next) else {
+                    continue;
+                };
+                match frame.recovery {
+                    Recovery::Fec => metrics.frames_fec_recovered_total.inc(),
+                    Recovery::Concealed => metrics.frames_concealed_total.inc(),
+                }
+                let concealed = frame.recovery == Recovery::Concealed;
+                if let Some(history) = history.filter(|_| concealed) {
+                    history.record(PlayoutEventKind::Concealment, Some(sequence), 0);
+                }
+                session.record(SessionEvent::Frame { concealed }, std::time::Instant::now());
+                player.play(&frame.samples);
+                continue;
+            }
+            PlayoutAction::Wait(_) => break,
+        };
+        if let Some(history) = history {
+            let delay_us = buffer_delay.as_micros().min(u32::MAX as u128) as u32;
+            history.record(PlayoutEventKind::Release, Some(packet.sequence), delay_us);
+        }
+        metrics
+            .jitter_buffer_delay_seconds
+            .observe(buffer_delay.as_secs_f64());
+        metrics
+            .jitter_buffer_occupancy_packets
+            .set(jitter_buffer.status().buffered_packets as i64);
+
+        if let Some(refresh) = refresh.as_deref_mut() {
+            let lost = lost_run + decoders.missing_before(&packet);
+            let now = std::time::Instant::now();
+            match refresh.observe_loss_run(lost, now) {
+                RefreshDecision::Request => {
+                    stale = Some(packet.ssrc);
+                    session.record(SessionEvent::Disrupted, now);
+                }
+                RefreshDecision::RateLimited => {
+                    metrics.decoder_refresh_requests_suppressed_total.inc();
+                    session.record(SessionEvent::Disrupted, now);
+                }
+                RefreshDecision::NotNeeded => {}
+            }
+        }
+
+        lost_run = 0;
+
+        let pipeline_start = std::time::Instant::now();
+        let decode_start = std::time::Instant::now();
+
+        match decoders.decode(&packet) {
+            Ok(None) => {
+                // Unsupported payload type, already logged by the decoder set
+            }
+            Ok(Some(samples)) => {
+                metrics
+                    .decode_seconds
+                    .observe(decode_start.elapsed().as_secs_f64());
+                if accelerate {
+                    player.play(&shorten_frame(&samples, ACCELERATE_FRACTION));
+                } else {
+                    player.play(&samples);
+                }
+                metrics
+                    .receiver_pipeline_seconds
+                    .observe(pipeline_start.elapsed().as_secs_f64());
+                session.record(
+                    SessionEvent::Frame { concealed: false },
+                    std::time::Instant::now(),
+                );
+            }
+            Err(e) => {
+                warn!("Failed to decode packet seq={}: {}", packet.sequence, e);
+                // Use PLC for decode errors
+                if let Ok(Some(concealed)) = decoders.conceal_loss() {
+                    metrics.frames_concealed_total.inc();
+                    if let Some(history) = history {
+                        history.record(PlayoutEventKind::Concealment, Some(packet.sequence), 0);
+                    }
                     metrics
                         .decode_seconds
                         .observe(decode_start.elapsed().as_secs_f64());
-                    player.play(&samples);
+                    player.play(&concealed);
                     metrics
                         .receiver_pipeline_seconds
                         .observe(pipeline_start.elapsed().as_secs_f64());
+                    session.record(
+                        SessionEvent::Frame { concealed: true },
+                        std::time::Instant::now(),
+                    );
                 }
-                Err(e) => {
-                    warn!("Failed to decode packet seq={}: {}", packet.sequence, e);
-                    // Use PLC for decode errors
-                    if let Ok(concealed) = decoder.conceal_loss() {
-                        metrics
-                            .decode_seconds
-                            .observe(decode_start.elapsed().as_secs_f64());
-                        player.play(&concealed);
-                        metrics
-                            .receiver_pipeline_seconds
-                            .observe(pipeline_start.elapsed().as_secs_f64());
-                    }
+            }
+        }
+    }
+
+    if let Some(report) = jitter_buffer.take_priming_report() {
+        publish_priming(&report, metrics);
+    }
+    metrics
+        .jitter_buffer_primed
+        .set(jitter_buffer.status().is_primed as i64);
+    session.record(priming_event(jitter_buffer), std::time::Instant::now());
+    stale
+}
+
+/// Returns the session event matching the jitter buffer's priming state.
+fn priming_event(jitter_buffer: &JitterBuffer) -> SessionEvent {
+    // ---
+    if jitter_buffer.status().is_primed {
+        SessionEvent::Primed
+    } else {
+        SessionEvent::Priming
+    }
+}
+
+/// Exports and logs the final time in each session state and the timeline.
+fn log_session(session: &mut SessionTracker, metrics: &MetricsContext) {
+    // ---
+    let now = std::time::Instant::now();
+    session.advance(now);
+    session.publish(metrics, now);
+
+    info!("Session states: {}", session);
+    if session.timeline_dropped() > 0 {
+        info!(
+            "  ({} earlier transitions not kept)",
+            session.timeline_dropped()
+        );
+    }
+    for transition in session.timeline() {
+        info!("  {}", transition);
+    }
+}
+
+/// Sends a decoder refresh request for `media_ssrc` back to the sender.
+///
+/// The receiver sends no media of its own, so the request's sender SSRC
+/// is 0. Failures are logged; the next long outage asks again.
+async fn request_refresh(receiver: &mut RtpReceiver, media_ssrc: u32, metrics: &MetricsContext) {
+    // ---
+    let request = DecoderRefreshRequest {
+        sender_ssrc: 0,
+        media_ssrc,
+    };
+    match receiver.send_rtcp(&request.serialize()).await {
+        Ok(()) => metrics.decoder_refresh_requests_sent_total.inc(),
+        Err(e) => warn!("Failed to send decoder refresh request: {e:#}"),
+    }
+}
+
+/// Sends a NACK for the `lost` packets of `media_ssrc` back to the
+/// sender. Failures are logged; packets still missing later are not asked
+/// for again.
+async fn request_retransmission(
+    receiver: &mut RtpReceiver,
+    media_ssrc: u32,
+    lost: Vec<u16>,
+    metrics: &MetricsContext,
+) {
+    // ---
+    let count = lost.len() as u64;
+    let nack = Nack {
+        sender_ssrc: 0,
+        media_ssrc,
+        lost,
+    };
+    match receiver.send_rtcp(&nack.serialize()).await {
+        Ok(()) => metrics.nack_packets_requested_total.inc_by(count),
+        Err(e) => warn!("Failed to send NACK: {e:#}"),
+    }
+}
+
+/// Sends the sender an RTCP receiver report on `media_ssrc` and starts
+/// the next reporting interval, with an extended report for a receiving
+/// end with `delays` if given. Failures are logged; the next report
+/// covers the interval again in its cumulative count.
+async fn send_receiver_report(
+    receiver: &mut RtpReceiver,
+    media_ssrc: u32,
+    stats: &mut ReceiverStats,
+    delays: Option<PlayoutDelays>,
+    metrics: &MetricsContext,
+) {
+    // ---
+    let Some(reception) = stats.take_reception_report() else {
+        return;
+    };
+    let (last_sr, delay_since_last_sr) = match receiver.last_sender_report() {
+        Some((report, arrival)) if report.ssrc == media_ssrc => {
+            (report.compact_ntp(), dlsr_units(arrival.elapsed()))
+        }
+        _ => (0, 0),
+    };
+    let block = ReportBlock {
+        ssrc: media_ssrc,
+        fraction_lost: reception.fraction_lost,
+        cumulative_lost: reception
+            .cumulative_lost
+            .clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+        extended_highest_sequence: reception.extended_highest_sequence as u32,
+        jitter: stats.jitter_units() as u32,
+        last_sr,
+        delay_since_last_sr,
+    };
+    let report = ReceiverReport {
+        sender_ssrc: 0,
+        blocks: vec![block],
+    };
+    let mut compound = CompoundPacket::receiver_report(&report);
+    let extended = delays.and_then(|delays| stats.take_extended_report(media_ssrc, &delays));
+    if let Some(extended) = &extended {
+        compound = compound.with_extended_report(extended);
+    }
+    match receiver.send_rtcp(&compound.serialize()).await {
+        Ok(()) => {
+            metrics.receiver_reports_sent_total.inc();
+            if extended.is_some() {
+                metrics.extended_reports_sent_total.inc();
+            }
+            debug!(
+                "Receiver report: {} of {} lost, {} cumulative",
+                reception.lost_interval, reception.expected_interval, reception.cumulative_lost
+            );
+        }
+        Err(e) => debug!("Failed to send receiver report: {e:#}"),
+    }
+}
+
+/// Records a completed priming phase.
+fn publish_priming(report: &PrimingReport, metrics: &MetricsContext) {
+    // ---
+    metrics
+        .priming_duration_seconds
+        .observe(report.duration.as_secs_f64());
+    for delay in &report.held_delays {
+        metrics
+            .priming_buffered_delay_seconds
+            .observe(delay.as_secs_f64());
+    }
+}
+
+/// Counts payload bytes received, net and gross of RTP padding.
+fn record_bytes_received(packet: &RtpPacket, metrics: &MetricsContext) {
+    // ---
+    let net = packet.payload.len() as u64;
+    metrics.bytes_received_total.inc_by(net);
+    metrics
+        .bytes_received_gross_total
+        .inc_by(net + packet.padding as u64);
+}
+
+/// Exports the stream clock as gauges and to the `/stats` snapshot.
+fn publish_clock(clock: &StreamClock, stats: &mut ReceiverStats, metrics: &MetricsContext) {
+    // ---
+    stats.record_clock(Some(*clock));
+    metrics.clock_rate_hz.set(clock.clock_rate as i64);
+    metrics
+        .frame_duration_seconds
+        .set(clock.frame_duration.as_secs_f64());
+}
+
+/// Exports the jitter buffer counters since the last call: the counts are
+/// added to their totals, and the residence gauges describe the packets
+/// released meanwhile, if any.
+fn publish_jitter_stats(jitter_buffer: &mut JitterBuffer, metrics: &MetricsContext) {
+    // ---
+    let stats = jitter_buffer.take_stats();
+    metrics.jitter_buffer_lost_total.inc_by(stats.lost);
+    metrics
+        .jitter_buffer_gaps_skipped_total
+        .inc_by(stats.gaps_skipped);
+    metrics
+        .jitter_buffer_underruns_total
+        .inc_by(stats.underruns);
+    metrics
+        .jitter_buffer_catch_up_drops_total
+        .inc_by(stats.catch_up_drops);
+    metrics
+        .jitter_buffer_accelerated_total
+        .inc_by(stats.accelerated);
+
+    let residence = stats.residence;
+    if let Some(mean) = residence.mean() {
+        for (stat, delay) in [
+            ("min", residence.min),
+            ("mean", mean),
+            ("max", residence.max),
+        ] {
+            metrics
+                .jitter_buffer_residence_seconds
+                .with_label_values(&[stat])
+                .set(delay.as_secs_f64());
+        }
+    }
+}
+
+/// Routes a jitter buffer insert outcome to the matching stats and metrics.
+///
+/// `priming` is whether the buffer was still priming when the packet was
+/// inserted; overflow evictions during priming are also counted separately.
+///
+/// Returns `true` if the packet was buffered for playout.
+fn record_insert_outcome(
+    outcome: InsertOutcome,
+    sequence: u16,
+    was_reordered: bool,
+    priming: bool,
+    stats: &mut ReceiverStats,
+    metrics: &MetricsContext,
+) -> bool {
+    // ---
+    let was_reordered = match outcome {
+        InsertOutcome::Duplicate => {
+            stats.record_duplicate_packet();
+            metrics.packets_duplicate_total.inc();
+            return false;
+        }
+        InsertOutcome::Late { .. } => {
+            stats.record_late_packet(sequence);
+            metrics.packets_late_total.inc();
+            return false;
+        }
+        InsertOutcome::Overflowed { .. } => {
+            metrics.jitter_buffer_overflow_total.inc();
+            if priming {
+                metrics.jitter_buffer_priming_overflow_total.inc();
+            }
+            was_reordered
+        }
+        InsertOutcome::Probation => {
+            // Received, but neither loss nor a gap until confirmed
+            stats.record_packet_and_get_loss(sequence, false);
+            return false;
+        }
+        InsertOutcome::Resync => {
+            // New baseline: the jump is neither loss nor reordering
+            info!(
+                "Sequence resync: stream continues at seq={}",
+                sequence.wrapping_sub(1)
+            );
+            stats.resync();
+            metrics.jitter_buffer_resync_total.inc();
+            false
+        }
+        InsertOutcome::Inserted => was_reordered,
+    };
+
+    let lost_gap = stats.record_packet_and_get_loss(sequence, was_reordered);
+    if lost_gap > 0 {
+        metrics.packets_lost_total.inc_by(lost_gap);
+    }
+    if was_reordered {
+        metrics.packets_reordered_total.inc();
+    }
+    true
+}
+
+/// Runs the receive pipeline in validation mode.
+///
+/// Packets flow through the jitter buffer and decoder exactly as in
+/// [`receive_loop`], but decoded audio goes to a [`NullSink`] and every
+/// in-order packet is checked by a [`StreamValidator`]. The loop ends once
+/// the stream has started and no packet arrives for `idle_timeout`; any
+/// packets still buffered at that point are validated before returning.
+///
+/// # Errors
+///
+/// Returns error if network reception fails critically.
+pub async fn validate_loop(
+    receiver: &mut RtpReceiver,
+    decoders: &mut DecoderSet,
+    jitter_config: JitterBufferConfig,
+    idle_timeout: Duration,
+) -> Result<ValidationReport> {
+    // ---
+    let mut jitter_buffer = JitterBuffer::new(jitter_config);
+    let mut validator = StreamValidator::new();
+    let mut sink = NullSink::new();
+    let mut started = false;
+
+    loop {
+        let received = if started {
+            match tokio::time::timeout(idle_timeout, receiver.receive()).await {
+                Ok(result) => result?,
+                Err(_) => break,
+            }
+        } else {
+            receiver.receive().await?
+        };
+
+        if let Some(packet) = received {
+            let arrival = std::time::Instant::now();
+            started = true;
+            validator.record_arrival(arrival);
+            jitter_buffer.insert_with_arrival(packet, arrival);
+        }
+
+        while let Some(packet) = jitter_buffer.get_next() {
+            validate_packet(&packet, decoders, &mut validator, &mut sink);
+        }
+    }
+
+    // Stream went idle: drain whatever is still buffered
+    while let Some(packet) = jitter_buffer.get_next() {
+        validate_packet(&packet, decoders, &mut validator, &mut sink);
+    }
+    jitter_buffer.flush_anomalies();
+
+    info!(
+        "Validation finished: {} packets checked, {} frames decoded",
+        validator.report().packets,
+        sink.frames
+    );
+
+    Ok(validator.report())
+}
+
+fn validate_packet(
+    packet: &RtpPacket,
+    decoders: &mut DecoderSet,
+    validator: &mut StreamValidator,
+    sink: &mut NullSink,
+) {
+    // ---
+    validator.check(packet);
+    match decoders.decode(packet) {
+        Ok(Some(samples)) => sink.play(&samples),
+        Ok(None) | Err(_) => validator.record_decode_failure(packet.sequence),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use std::time::Instant;
+
+    fn make_packet(seq: u16) -> RtpPacket {
+        RtpPacket::new(seq, seq as u32 * 320, 0x12345678, vec![1, 2, 3])
+    }
+
+    #[test]
+    fn test_insert_outcomes_route_to_separate_metrics() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut stats = ReceiverStats::default();
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 0,
+            max_packets: 10,
+            ..Default::default()
+        });
+
+        let insert = |seq: u16, buffer: &mut JitterBuffer, stats: &mut ReceiverStats| {
+            let reordered = buffer.was_reordered(seq);
+            let outcome = buffer.insert(make_packet(seq));
+            record_insert_outcome(outcome, seq, reordered, false, stats, &metrics)
+        };
+
+        assert!(insert(0, &mut buffer, &mut stats));
+        assert!(!insert(0, &mut buffer, &mut stats)); // Duplicate
+        assert!(!insert(0, &mut buffer, &mut stats)); // Duplicate
+        buffer.get_next();
+        assert!(!insert(0, &mut buffer, &mut stats)); // Late
+        assert!(!insert(5000, &mut buffer, &mut stats)); // Probation
+        assert!(insert(5001, &mut buffer, &mut stats)); // Resync
+
+        assert_eq!(metrics.packets_duplicate_total.get(), 2);
+        assert_eq!(metrics.packets_late_total.get(), 1);
+        assert_eq!(metrics.jitter_buffer_resync_total.get(), 1);
+        assert_eq!(metrics.packets_lost_total.get(), 0);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.packets_duplicate, 2);
+        assert_eq!(snapshot.packets_late, 1);
+        assert_eq!(snapshot.packets_received, 3);
+        assert_eq!(snapshot.packets_reordered, 0);
+    }
+
+    #[test]
+    fn test_sequence_jump_resyncs_without_loss() {
+        // ---
+        // A restart far ahead, and one so far it looks behind
+        for jump in [30_000u16, 40_000] {
+            let metrics = MetricsContext::new("test").expect("metrics");
+            let mut stats = ReceiverStats::default();
+            let mut buffer = JitterBuffer::new(JitterBufferConfig {
+                depth_ms: 0,
+                prime_mode: PrimeMode::Immediate,
+                ..Default::default()
+            });
+
+            let sequences = (0..50u16).chain((50..100u16).map(|seq| seq.wrapping_add(jump)));
+            let mut played = Vec::new();
+            for seq in sequences {
+                let reordered = buffer.was_reordered(seq);
+                let outcome = buffer.insert(make_packet(seq));
+                record_insert_outcome(outcome, seq, reordered, false, &mut stats, &metrics);
+                while let Some(packet) = buffer.get_next() {
+                    played.push(packet.sequence);
                 }
             }
+
+            // One resync, every packet played in order, nothing lost
+            assert_eq!(metrics.jitter_buffer_resync_total.get(), 1);
+            assert_eq!(played.len(), 100);
+            assert_eq!(played[50], 50u16.wrapping_add(jump));
+            let snapshot = stats.snapshot();
+            assert_eq!(snapshot.packets_received, 100);
+            assert_eq!(snapshot.packets_lost, 0);
+            assert_eq!(snapshot.packets_late, 0);
+        }
+    }
+
+    #[test]
+    fn test_packet_arriving_after_skip_counts_late() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut stats = ReceiverStats::default();
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 20,
+            prime_mode: PrimeMode::Immediate,
+            max_wait_ms: Some(40),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let mut insert = |seq: u16, ms: u64, buffer: &mut JitterBuffer| {
+            let reordered = buffer.was_reordered(seq);
+            let outcome =
+                buffer.insert_with_arrival(make_packet(seq), start + Duration::from_millis(ms));
+            record_insert_outcome(outcome, seq, reordered, false, &mut stats, &metrics)
+        };
+
+        // Packets 5-7 are given up on, then 6 shows up after all
+        for seq in (0..5).chain([8]) {
+            assert!(insert(seq, seq as u64 * 20, &mut buffer));
+        }
+        let mut lost = 0;
+        while let Some(action) = buffer.next_action_at(start + Duration::from_millis(200)) {
+            if let PlayoutAction::Lost { .. } = action {
+                lost += 1;
+            }
+        }
+        assert_eq!(lost, 3);
+        assert!(!insert(6, 220, &mut buffer));
+
+        assert_eq!(metrics.packets_late_total.get(), 1);
+        assert_eq!(metrics.packets_lost_total.get(), 3);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.packets_late, 1);
+        assert_eq!(snapshot.packets_lost, 3);
+        assert_eq!(snapshot.packets_received, 6);
+    }
+
+    #[test]
+    fn test_jitter_stats_published_once() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 20,
+            prime_mode: PrimeMode::Immediate,
+            max_wait_ms: Some(40),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        for seq in [0, 1, 3] {
+            let arrival = start + Duration::from_millis(seq as u64 * 20);
+            buffer.insert_with_arrival(make_packet(seq), arrival);
+        }
+        while buffer
+            .next_action_at(start + Duration::from_millis(100))
+            .is_some()
+        {}
+
+        publish_jitter_stats(&mut buffer, &metrics);
+        publish_jitter_stats(&mut buffer, &metrics);
+        assert_eq!(metrics.jitter_buffer_lost_total.get(), 1);
+        assert_eq!(metrics.jitter_buffer_gaps_skipped_total.get(), 1);
+        assert_eq!(metrics.jitter_buffer_underruns_total.get(), 0);
+        let residence = |stat| {
+            metrics
+                .jitter_buffer_residence_seconds
+                .with_label_values(&[stat])
+                .get()
+        };
+        assert_eq!(residence("min"), 0.04);
+        assert_eq!(residence("max"), 0.1);
+    }
+
+    #[test]
+    fn test_padded_packets_count_net_and_gross_bytes() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+
+        let mut padded = RtpPacket::new(0, 0, 0x12345678, vec![0xAB; 40]);
+        padded.pad_to(100).expect("pad");
+        let wire = padded.serialize().expect("serialize");
+        let received = RtpPacket::deserialize(&wire).expect("deserialize");
+        record_bytes_received(&received, &metrics);
+        record_bytes_received(&make_packet(1), &metrics);
+
+        assert_eq!(metrics.bytes_received_total.get(), 40 + 3);
+        assert_eq!(metrics.bytes_received_gross_total.get(), 88 + 3);
+    }
+
+    #[test]
+    fn test_overflow_still_records_packet() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut stats = ReceiverStats::default();
+
+        let accepted = record_insert_outcome(
+            InsertOutcome::Overflowed { evicted_seq: 0 },
+            7,
+            false,
+            false,
+            &mut stats,
+            &metrics,
+        );
+
+        assert!(accepted);
+        assert_eq!(metrics.jitter_buffer_overflow_total.get(), 1);
+        assert_eq!(metrics.jitter_buffer_priming_overflow_total.get(), 0);
+        assert_eq!(stats.snapshot().packets_received, 1);
+    }
+
+    #[test]
+    fn test_priming_overflow_counted_separately() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut stats = ReceiverStats::default();
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 200,
+            max_packets: 2,
+            ..Default::default()
+        });
+        let t0 = std::time::Instant::now();
+
+        // Nothing is released while priming, so the third packet overflows
+        for seq in 0..3u16 {
+            let priming = !buffer.status().is_primed;
+            let outcome = buffer.insert_with_arrival(make_packet(seq), t0);
+            record_insert_outcome(outcome, seq, false, priming, &mut stats, &metrics);
+        }
+
+        assert_eq!(metrics.jitter_buffer_overflow_total.get(), 1);
+        assert_eq!(metrics.jitter_buffer_priming_overflow_total.get(), 1);
+    }
+
+    #[test]
+    fn test_priming_report_published() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: 200,
+            max_packets: 10,
+            ..Default::default()
+        });
+        let t0 = std::time::Instant::now();
+
+        buffer.insert_with_arrival(make_packet(0), t0);
+        buffer.insert_with_arrival(make_packet(1), t0 + Duration::from_millis(100));
+        buffer
+            .get_next_at(t0 + Duration::from_millis(200))
+            .expect("primed");
+        publish_priming(&buffer.take_priming_report().unwrap(), &metrics);
+
+        let duration = &metrics.priming_duration_seconds;
+        assert_eq!(duration.get_sample_count(), 1);
+        assert!((duration.get_sample_sum() - 0.2).abs() < 1e-9);
+
+        let held = &metrics.priming_buffered_delay_seconds;
+        assert_eq!(held.get_sample_count(), 2);
+        assert!((held.get_sample_sum() - 0.3).abs() < 1e-9);
+    }
+
+    #[cfg(feature = "formats")]
+    #[tokio::test]
+    async fn test_receive_loop_returns_when_idle_with_caller_stats() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+        let port = receiver.local_addr().expect("local_addr").port();
+
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        for seq in 0..10u16 {
+            let mut packet = RtpPacket::new(seq, seq as u32 * 160, 0x12345678, vec![0xFF; 160]);
+            packet.payload_type = g711::PAYLOAD_TYPE_PCMU;
+            let data = packet.serialize().expect("serialize");
+            socket
+                .send_to(&data, ("127.0.0.1", port))
+                .await
+                .expect("send");
         }
+
+        let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+        let mut sink = NullSink::new();
+        let mut stats = ReceiverStats::default();
+        let options = ReceiveOptions {
+            jitter: JitterBufferConfig {
+                prime_mode: PrimeMode::Immediate,
+                ..Default::default()
+            },
+            idle_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        receive_loop(
+            &mut receiver,
+            &mut decoders,
+            &mut sink,
+            &mut stats,
+            &options,
+            &metrics,
+        )
+        .await
+        .expect("receive loop");
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.packets_received, 10);
+        assert_eq!(snapshot.packets_lost, 0);
+        assert_eq!(sink.frames, 10);
+        assert_eq!(metrics.packets_received_total.get(), 10);
+    }
+
+    #[cfg(feature = "formats")]
+    #[tokio::test]
+    async fn test_bye_restarts_decoders_and_clock() {
+        // ---
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+        let port = receiver.local_addr().expect("local_addr").port();
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let send = |seq: u16, ticks: u32, ssrc: u32| {
+            let mut packet = RtpPacket::new(seq, seq as u32 * ticks, ssrc, vec![0xFF; 160]);
+            packet.payload_type = g711::PAYLOAD_TYPE_PCMU;
+            packet.serialize().expect("serialize")
+        };
+
+        // 8 kHz with 20ms frames, a BYE, then 48 kHz with 10ms frames
+        // starting a few sequence numbers on
+        let mut datagrams: Vec<Vec<u8>> = (0..60).map(|seq| send(seq, 160, 0x1111)).collect();
+        let report = ReceiverReport {
+            sender_ssrc: 0x1111,
+            blocks: Vec::new(),
+        };
+        let bye = rtp_opus_common::Bye {
+            ssrcs: vec![0x1111],
+            reason: None,
+        };
+        datagrams.push(
+            CompoundPacket::receiver_report(&report)
+                .with_bye(&bye)
+                .serialize(),
+        );
+        datagrams.extend((70..130).map(|seq| send(seq, 480, 0x2222)));
+        for data in &datagrams {
+            socket
+                .send_to(data, ("127.0.0.1", port))
+                .await
+                .expect("send");
+        }
+
+        let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+        let mut sink = NullSink::new();
+        let mut stats = ReceiverStats::default();
+        let options = ReceiveOptions {
+            jitter: JitterBufferConfig {
+                prime_mode: PrimeMode::Immediate,
+                ..Default::default()
+            },
+            idle_timeout: Some(Duration::from_millis(100)),
+            refresh: Some(RefreshConfig {
+                lost_packets: 2,
+                min_interval: Duration::ZERO,
+            }),
+            ..Default::default()
+        };
+        receive_loop(
+            &mut receiver,
+            &mut decoders,
+            &mut sink,
+            &mut stats,
+            &options,
+            &metrics,
+        )
+        .await
+        .expect("receive loop");
+
+        // A fresh decoder sees no loss run across the streams
+        assert_eq!(metrics.packets_received_total.get(), 120);
+        assert_eq!(metrics.decoder_refresh_requests_sent_total.get(), 0);
+        assert_eq!(metrics.decoder_refresh_requests_suppressed_total.get(), 0);
+
+        // The second stream's clock is detected anew
+        let clock = stats.snapshot().clock.expect("clock");
+        assert_eq!(
+            (clock.clock_rate, clock.frame_duration, clock.detected),
+            (48000, Duration::from_millis(10), true)
+        );
+        assert_eq!(metrics.clock_rate_hz.get(), 48000);
     }
 }
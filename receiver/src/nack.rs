@@ -0,0 +1,349 @@
+//! Retransmission requests for lost packets.
+//!
+//! A gap in the sequence numbers is given one frame to fill by itself,
+//! since the missing packet may only be reordered. After that the receiver
+//! sends the sender a Generic NACK (RFC 4585 §6.2.1) for it, which the
+//! sender answers by retransmitting the packet. A gap older than the
+//! jitter buffer depth is dropped: a retransmission would arrive too late
+//! to be played.
+//!
+//! NACKs are early feedback in the sense of AVPF (RFC 4585 §3.5): sent
+//! when needed rather than on the report schedule, but still held to the
+//! RTCP share of the session bandwidth. After one goes out, the next may
+//! only follow a report interval later; gaps that mature in between wait
+//! and go out together in the next NACK, so scattered losses cannot flood
+//! the sender with RTCP.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use rtp_opus_common::{Nack, RtcpInterval, DEFAULT_SESSION_BANDWIDTH};
+use tracing::debug;
+
+/// Default age before a gap is NACKed: one 20ms frame.
+pub const DEFAULT_NACK_MIN_AGE: Duration = Duration::from_millis(20);
+
+/// Default age past which a gap is no longer NACKed: the default jitter
+/// buffer depth.
+pub const DEFAULT_NACK_MAX_AGE: Duration = Duration::from_millis(60);
+
+/// Largest gap tracked; a longer jump is a new stream or an outage no
+/// retransmission could cover.
+pub const MAX_NACK_GAP: u16 = 64;
+
+/// Participants assumed when spacing NACKs: the sender and this receiver.
+const NACK_MEMBERS: usize = 2;
+
+/// When the receiver asks for lost packets again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NackConfig {
+    // ---
+    /// How long a gap may stay open before it is NACKed
+    pub min_age: Duration,
+
+    /// How long a gap is worth asking about, usually the jitter depth
+    pub max_age: Duration,
+
+    /// Session bandwidth in bits per second, whose RTCP share spaces the
+    /// NACKs out (see [`RtcpInterval`])
+    pub session_bandwidth: u64,
+}
+
+impl Default for NackConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            min_age: DEFAULT_NACK_MIN_AGE,
+            max_age: DEFAULT_NACK_MAX_AGE,
+            session_bandwidth: DEFAULT_SESSION_BANDWIDTH,
+        }
+    }
+}
+
+/// A sequence number seen missing.
+#[derive(Debug, Clone, Copy)]
+struct Missing {
+    // ---
+    sequence: u16,
+
+    /// When a later packet revealed the gap
+    detected: Instant,
+
+    /// Whether it has been NACKed
+    requested: bool,
+}
+
+/// Tracks gaps in the sequence numbers and decides which to NACK.
+///
+/// Fed every packet as it arrives; each missing packet is NACKed once, at
+/// least [`NackConfig::min_age`] and at most [`NackConfig::max_age`] after
+/// its gap opened, and no sooner than the RTCP interval after the last
+/// NACK. A gap that ages out while held back is not asked for.
+#[derive(Debug)]
+pub struct NackRequester {
+    // ---
+    config: NackConfig,
+
+    /// Highest sequence number seen
+    highest: Option<u16>,
+
+    /// Gaps still open, oldest first
+    missing: VecDeque<Missing>,
+
+    /// Spacing between NACKs, from the session bandwidth and their size
+    interval: RtcpInterval,
+
+    /// When the next NACK may go out, once one has
+    next_allowed: Option<Instant>,
+
+    /// Packets NACKed
+    requested: u64,
+
+    /// NACKed packets that arrived afterwards
+    recovered: u64,
+}
+
+impl NackRequester {
+    // ---
+    /// Creates a requester that has seen no packets yet.
+    pub fn new(config: NackConfig) -> Self {
+        // ---
+        let interval = RtcpInterval::new(config.session_bandwidth, Duration::ZERO);
+        Self {
+            config,
+            highest: None,
+            missing: VecDeque::new(),
+            interval,
+            next_allowed: None,
+            requested: 0,
+            recovered: 0,
+        }
+    }
+
+    /// Observes a packet with `sequence` arriving at `now`.
+    pub fn observe(&mut self, sequence: u16, now: Instant) {
+        // ---
+        let Some(highest) = self.highest else {
+            self.highest = Some(sequence);
+            return;
+        };
+
+        // More than half the sequence space ahead means behind
+        let ahead = sequence.wrapping_sub(highest);
+        if ahead == 0 {
+            return;
+        }
+        if ahead > 32768 {
+            if let Some(pos) = self.missing.iter().position(|m| m.sequence == sequence) {
+                if self.missing.remove(pos).is_some_and(|m| m.requested) {
+                    self.recovered += 1;
+                }
+            }
+            return;
+        }
+
+        if ahead > MAX_NACK_GAP {
+            debug!(
+                "Not tracking a gap of {} packets before seq={}",
+                ahead - 1,
+                sequence
+            );
+            self.missing.clear();
+        } else {
+            self.missing.extend((1..ahead).map(|offset| Missing {
+                sequence: highest.wrapping_add(offset),
+                detected: now,
+                requested: false,
+            }));
+        }
+        self.highest = Some(sequence);
+    }
+
+    /// Returns the sequence numbers to NACK as of `now`, oldest first, and
+    /// forgets gaps too old to be worth asking about.
+    ///
+    /// Returns nothing while the last NACK is less than an RTCP interval
+    /// old; the caller is expected to send whatever is returned as one
+    /// NACK.
+    pub fn due(&mut self, now: Instant) -> Vec<u16> {
+        // ---
+        let max_age = self.config.max_age;
+        self.missing
+            .retain(|m| now.saturating_duration_since(m.detected) <= max_age);
+        if self.next_allowed.is_some_and(|next| now < next) {
+            return Vec::new();
+        }
+
+        let mut due = Vec::new();
+        for missing in &mut self.missing {
+            let age = now.saturating_duration_since(missing.detected);
+            if !missing.requested && age >= self.config.min_age {
+                missing.requested = true;
+                due.push(missing.sequence);
+            }
+        }
+        if !due.is_empty() {
+            let nack = Nack {
+                sender_ssrc: 0,
+                media_ssrc: 0,
+                lost: due.clone(),
+            };
+            self.interval.record_packet(nack.serialize().len());
+            let interval = self.interval.interval(NACK_MEMBERS, 1, false);
+            self.next_allowed = Some(now + interval);
+        }
+        self.requested += due.len() as u64;
+        due
+    }
+
+    /// Returns when the next NACK may go out, if one has yet.
+    pub fn next_allowed(&self) -> Option<Instant> {
+        // ---
+        self.next_allowed
+    }
+
+    /// Returns the number of packets NACKed.
+    pub fn requested(&self) -> u64 {
+        // ---
+        self.requested
+    }
+
+    /// Returns the number of NACKed packets that arrived afterwards.
+    pub fn recovered(&self) -> u64 {
+        // ---
+        self.recovered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::jitter_buffer::{JitterBuffer, JitterBufferConfig};
+    use rtp_opus_common::RtpPacket;
+
+    fn make_packet(seq: u16) -> RtpPacket {
+        RtpPacket::new(seq, seq as u32 * 960, 0x12345678, vec![1, 2, 3])
+    }
+
+    #[test]
+    fn test_nacks_gaps_once_between_min_and_max_age() {
+        // ---
+        let mut requester = NackRequester::new(NackConfig::default());
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        requester.observe(65_534, at(0));
+        requester.observe(1, at(0)); // 65535 and 0 missing
+        assert!(requester.due(at(10)).is_empty(), "may only be reordered");
+
+        requester.observe(65_535, at(15)); // Reordered, filled by itself
+        assert_eq!(requester.due(at(20)), [0]);
+        assert!(requester.due(at(30)).is_empty(), "already asked");
+
+        requester.observe(0, at(40)); // The retransmission
+        requester.observe(4, at(40));
+        assert!(requester.due(at(200)).is_empty(), "too late to help");
+
+        assert_eq!(requester.requested(), 1);
+        assert_eq!(requester.recovered(), 1);
+    }
+
+    #[test]
+    fn test_nacks_held_to_rtcp_interval() {
+        // ---
+        let mut requester = NackRequester::new(NackConfig {
+            max_age: Duration::from_secs(2),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        requester.observe(0, at(0));
+        requester.observe(2, at(0)); // 1 missing
+        assert_eq!(requester.due(at(20)), [1]);
+        let next = requester.next_allowed().unwrap();
+        assert!(next > at(100), "5% of 64 kbit/s allows no more");
+
+        // Later gaps wait for the interval, then go out as one NACK
+        requester.observe(4, at(40)); // 3 missing
+        requester.observe(9, at(60)); // 5 to 8 missing
+        assert!(requester.due(at(80)).is_empty());
+        assert!(requester.due(next - Duration::from_millis(1)).is_empty());
+        assert_eq!(requester.due(next), [3, 5, 6, 7, 8]);
+        assert_eq!(requester.requested(), 6);
+    }
+
+    #[test]
+    fn test_held_gaps_age_out() {
+        // ---
+        let mut requester = NackRequester::new(NackConfig::default());
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        requester.observe(0, at(0));
+        requester.observe(2, at(0));
+        assert_eq!(requester.due(at(20)), [1]);
+
+        // Past the jitter depth by the time another NACK may go out
+        requester.observe(4, at(40));
+        let next = requester.next_allowed().unwrap();
+        assert!(requester.due(next).is_empty());
+        assert_eq!(requester.requested(), 1);
+    }
+
+    #[test]
+    fn test_nacks_while_jitter_buffer_waits_for_gap() {
+        // ---
+        let depth = Duration::from_millis(60);
+        let mut buffer = JitterBuffer::new(JitterBufferConfig {
+            depth_ms: depth.as_millis() as u32,
+            max_packets: 10,
+            ..Default::default()
+        });
+        let mut requester = NackRequester::new(NackConfig {
+            max_age: depth,
+            ..Default::default()
+        });
+        let start = Instant::now();
+        let frame = |n: u64| start + Duration::from_millis(20 * n);
+
+        // Packet 2 lost; playout stops at the gap once primed
+        for sequence in [0u16, 1, 3, 4, 5] {
+            let arrival = frame(sequence as u64);
+            requester.observe(sequence, arrival);
+            buffer.insert_with_arrival(make_packet(sequence), arrival);
+            let lost = requester.due(arrival);
+            match sequence {
+                4 => {
+                    assert_eq!(lost, [2]);
+                    assert_eq!(buffer.status().next_sequence, Some(0));
+                }
+                _ => assert!(lost.is_empty(), "seq={sequence}"),
+            }
+        }
+        let played: Vec<u16> = std::iter::from_fn(|| buffer.get_next_at(frame(5)))
+            .map(|(packet, _)| packet.sequence)
+            .collect();
+        assert_eq!(played, [0, 1]);
+        assert_eq!(buffer.status().next_sequence, Some(2));
+
+        // The retransmission fills the gap in time to be played
+        requester.observe(2, frame(6));
+        buffer.insert_with_arrival(make_packet(2), frame(6));
+        assert_eq!(buffer.get_next_at(frame(6)).unwrap().0.sequence, 2);
+        assert_eq!(requester.recovered(), 1);
+    }
+
+    #[test]
+    fn test_long_jumps_are_not_tracked() {
+        // ---
+        let mut requester = NackRequester::new(NackConfig::default());
+        let now = Instant::now();
+
+        requester.observe(0, now);
+        requester.observe(2, now);
+        requester.observe(2 + MAX_NACK_GAP + 1, now);
+        assert!(requester.due(now + DEFAULT_NACK_MIN_AGE).is_empty());
+    }
+}
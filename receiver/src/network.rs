@@ -1,30 +1,427 @@
 //! UDP network reception for RTP packets.
 //!
 //! Provides async UDP socket handling for receiving RTP packets
-//! from the sender.
+//! from the sender, read into a shared receive buffer ([`RecvBuffer`])
+//! that packet payloads are sliced out of, so the receive path neither
+//! copies payloads nor allocates per packet. Packets can also arrive
+//! over TCP (see [`crate::tcp`]).
 
 use anyhow::{Context, Result};
-use rtp_opus_common::RtpPacket;
-use tokio::net::UdpSocket;
+use bytes::{Bytes, BytesMut};
+use rtp_opus_common::{
+    is_rtcp, AnomalyClass, AnomalyLogger, Bye, Keepalive, RtcpInterval, RtcpSocket, RtpPacket,
+    RtpParseErrorKind, RtpParseMode, RtpdumpWriter, Sdes, SdesChunk, SenderReport, SrtpContext,
+    SrtpKey, Transport, DEFAULT_SESSION_BANDWIDTH, RTCP_PT_BYE, RTCP_PT_SDES, RTCP_PT_SR,
+};
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::time::Instant;
 use tracing::{debug, info, warn};
 
+use crate::source::{ObservedSource, SourceTracker, DEFAULT_REBIND_CONFIRM_PACKETS};
+use crate::tcp::TcpIncoming;
+
+/// Time between keepalives to a punched sender; well inside the 30 s
+/// many NATs keep an idle UDP binding open
+pub const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Network-side receiver configuration.
+#[derive(Debug, Clone)]
+pub struct ReceiverNetworkConfig {
+    // ---
+    /// Largest datagram accepted, in bytes
+    pub max_datagram_size: usize,
+
+    /// Datagrams' worth of space the receive buffer is allocated with at
+    /// a time (see [`RecvBuffer`])
+    pub recv_buffer_datagrams: usize,
+
+    /// Consecutive packets from a new source address before replies
+    /// follow it (symmetric RTP NAT rebinding)
+    pub rebind_confirm_packets: u32,
+
+    /// IPv4 multicast group to join. The port is then shared, so several
+    /// receivers on one host can listen to the same group.
+    pub multicast_group: Option<Ipv4Addr>,
+
+    /// Interface to join the group on, by address; unspecified lets the
+    /// system choose
+    pub multicast_interface: Ipv4Addr,
+
+    /// Socket receive buffer to request (`SO_RCVBUF`), in bytes, so a
+    /// burst that arrives while the receiver is descheduled is queued
+    /// rather than dropped; `None` keeps the system default. The kernel
+    /// may grant a different size.
+    pub recv_buffer_bytes: Option<usize>,
+
+    /// UDP datagrams, or RFC 4571 frames over a TCP connection from the
+    /// sender (no multicast)
+    pub transport: Transport,
+
+    /// Pre-shared SRTP master key: packets must authenticate under it and
+    /// are decrypted before parsing. `None` receives plain RTP.
+    pub srtp_key: Option<SrtpKey>,
+
+    /// Sender in listen mode to contact: a hello goes to it from the RTP
+    /// socket at startup, so it learns where to stream (through NAT too),
+    /// and keepalives follow while receiving. UDP unicast only.
+    pub punch: Option<SocketAddr>,
+
+    /// Time between keepalives to [`punch`](Self::punch)
+    pub keepalive_interval: Duration,
+
+    /// Carry RTCP on the RTP socket (RFC 5761) rather than on a socket of
+    /// its own on the next port (RFC 3550 §11); the sender must do the
+    /// same. UDP only, and not with SRTP, which takes no RTCP.
+    pub rtcp_mux: bool,
+
+    /// Session bandwidth in bits per second, headers included, that
+    /// receiver reports keep within 5% of (see [`RtcpInterval`])
+    pub session_bandwidth: u64,
+}
+
+impl Default for ReceiverNetworkConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            max_datagram_size: 2048, // Comfortably above a 1500-byte Ethernet MTU
+            recv_buffer_datagrams: DEFAULT_RECV_BUFFER_DATAGRAMS,
+            rebind_confirm_packets: DEFAULT_REBIND_CONFIRM_PACKETS,
+            multicast_group: None,
+            multicast_interface: Ipv4Addr::UNSPECIFIED,
+            recv_buffer_bytes: None,
+            transport: Transport::Udp,
+            srtp_key: None,
+            punch: None,
+            keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+            rtcp_mux: true,
+            session_bandwidth: DEFAULT_SESSION_BANDWIDTH,
+        }
+    }
+}
+
+/// Datagrams an [`RtpReceiver`] dropped for not parsing as RTP, by
+/// reason.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseErrorCounts {
+    // ---
+    counts: [u64; RtpParseErrorKind::ALL.len()],
+}
+
+impl ParseErrorCounts {
+    // ---
+    /// Returns how many datagrams were dropped for `kind`.
+    pub fn get(&self, kind: RtpParseErrorKind) -> u64 {
+        // ---
+        self.counts[kind as usize]
+    }
+
+    /// Returns how many datagrams were dropped for any reason.
+    pub fn total(&self) -> u64 {
+        // ---
+        self.counts.iter().sum()
+    }
+
+    /// Returns each reason with its count, zeros included.
+    pub fn iter(&self) -> impl Iterator<Item = (RtpParseErrorKind, u64)> + '_ {
+        // ---
+        RtpParseErrorKind::ALL
+            .into_iter()
+            .map(|kind| (kind, self.get(kind)))
+    }
+
+    fn record(&mut self, kind: RtpParseErrorKind) {
+        // ---
+        self.counts[kind as usize] += 1;
+    }
+}
+
+/// Counts of what an [`RtpReceiver`] took off the network.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RtpReceiverStats {
+    // ---
+    /// Valid packets handed out
+    pub packets_received: u64,
+
+    /// Bytes received, keepalives aside
+    pub bytes_received: u64,
+
+    /// Datagrams dropped for any reason: too large or not RTP, failing SRTP
+    /// authentication or the payload check, or an unexpected payload type
+    pub packets_dropped: u64,
+
+    /// Datagrams among the dropped that did not parse as RTP, by reason
+    pub parse_errors: ParseErrorCounts,
+}
+
+/// Datagrams' worth of space the receive buffer is allocated with at a
+/// time
+pub const DEFAULT_RECV_BUFFER_DATAGRAMS: usize = 64;
+
+/// Buffer datagrams are received into and handed out of without copying.
+///
+/// Each datagram is split off the front of one large allocation as
+/// [`Bytes`], which the packet parsed from it keeps alive. Once every
+/// datagram split from an allocation has been dropped, it is reused from
+/// the start; while some are still held (in the jitter buffer, say), a
+/// new one is made. Either way the receive path allocates about once per
+/// allocation's worth of datagrams, not once per packet.
+#[derive(Debug)]
+pub struct RecvBuffer {
+    // ---
+    buf: BytesMut,
+
+    /// Largest datagram accepted, in bytes
+    max_datagram_size: usize,
+
+    /// Size of each allocation
+    chunk: usize,
+}
+
+impl RecvBuffer {
+    // ---
+    /// Creates a buffer for datagrams of up to `max_datagram_size` bytes,
+    /// allocating room for `datagrams` of them (at least one) at a time.
+    pub fn new(max_datagram_size: usize, datagrams: usize) -> Self {
+        // ---
+        let chunk = (max_datagram_size + 1) * datagrams.max(1);
+        Self {
+            buf: BytesMut::with_capacity(chunk),
+            max_datagram_size,
+            chunk,
+        }
+    }
+
+    /// Returns the space the next datagram is received into, one byte
+    /// longer than `max_datagram_size` so that a datagram too large for it
+    /// shows as filling it. Only the part the last datagram took is zeroed
+    /// again; the rest holds whatever was there before.
+    pub fn space(&mut self) -> &mut [u8] {
+        // ---
+        let space = self.max_datagram_size + 1;
+        if self.buf.capacity() < space {
+            self.buf.clear();
+            self.buf.reserve(self.chunk);
+        }
+        self.buf.resize(space, 0);
+        &mut self.buf
+    }
+
+    /// Splits the first `len` bytes of the space off as a datagram, leaving
+    /// the rest of the allocation for the next one.
+    pub fn split(&mut self, len: usize) -> Bytes {
+        // ---
+        let len = len.min(self.buf.len());
+        self.buf.split_to(len).freeze()
+    }
+}
+
+/// Binds a socket to `port` that shares the port with other sockets
+/// (`SO_REUSEADDR`) and joins `group` on `interface`.
+fn bind_multicast(port: u16, group: Ipv4Addr, interface: Ipv4Addr) -> Result<UdpSocket> {
+    // ---
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    if !group.is_multicast() {
+        anyhow::bail!("{} is not a multicast address", group);
+    }
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .context("failed to create UDP socket")?;
+    socket
+        .set_reuse_address(true)
+        .context("failed to set SO_REUSEADDR")?;
+    socket.set_nonblocking(true)?;
+    let addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port);
+    socket
+        .bind(&addr.into())
+        .with_context(|| format!("failed to bind UDP socket to {}", addr))?;
+    socket
+        .join_multicast_v4(&group, &interface)
+        .with_context(|| format!("failed to join multicast group {} on {}", group, interface))?;
+    info!("Joined multicast group {} on {}", group, interface);
+
+    UdpSocket::from_std(socket.into()).context("failed to register UDP socket")
+}
+
+/// Requests a receive buffer of `bytes` on `socket` and logs what the
+/// kernel granted, which may be clamped (or, on Linux, doubled for
+/// bookkeeping). A refusal is logged and the default kept.
+fn set_recv_buffer(socket: socket2::SockRef<'_>, bytes: usize) {
+    // ---
+    if let Err(e) = socket.set_recv_buffer_size(bytes) {
+        warn!("Failed to set receive buffer to {} bytes: {}", bytes, e);
+        return;
+    }
+    match socket.recv_buffer_size() {
+        Ok(granted) => info!(
+            "Receive buffer: {} bytes requested, {} granted",
+            bytes, granted
+        ),
+        Err(e) => warn!("Failed to read back receive buffer size: {}", e),
+    }
+}
+
+/// Sends a [`Keepalive`] to `target` from the RTP socket, so the sender
+/// sees (and a NAT keeps open) the address packets should come back to.
+async fn send_keepalive(socket: &UdpSocket, target: SocketAddr) -> std::io::Result<()> {
+    // ---
+    let hello = Keepalive { sender_ssrc: 0 }.serialize();
+    socket.send_to(&hello, target).await.map(|_| ())
+}
+
 /// UDP receiver for RTP packet reception.
 ///
 /// Wraps a tokio UDP socket for async reception of RTP packets.
-/// Handles packet validation and provides statistics.
+/// Handles packet validation and provides statistics. Datagrams are read
+/// into a [`RecvBuffer`], and the payload of the returned [`RtpPacket`] is
+/// a view of its datagram there rather than a copy.
+///
+/// The receiver uses symmetric RTP: the address valid packets arrive from is
+/// tracked, and every reply goes back through [`send_reply`](Self::send_reply)
+/// to that address from the same socket, so a sender behind NAT needs no
+/// configuration.
+///
+/// With [`Transport::Tcp`], packets arrive over a TCP connection from the
+/// sender instead (see [`crate::tcp`]) and are returned the same way.
+/// Replies are not supported over TCP.
 pub struct RtpReceiver {
     // ---
-    socket: UdpSocket,
+    link: Link,
+    recv: RecvBuffer,
+    source: SourceTracker,
     packets_received: u64,
     bytes_received: u64,
     packets_dropped: u64,
+    anomalies: AnomalyLogger,
+
+    /// How strictly datagrams are parsed, and why those refused were
+    parse_mode: RtpParseMode,
+    parse_errors: ParseErrorCounts,
+
+    verify_payloads: bool,
+    payload_crc_mismatches: u64,
+
+    /// Only payload type accepted, if filtering
+    expected_payload_type: Option<u8>,
+    payload_type_mismatches: u64,
+
+    /// Session keys and rollover counters, with SRTP
+    srtp: Option<SrtpContext>,
+    srtp_auth_failures: u64,
+
+    /// Datagrams larger than the receive buffer takes
+    datagrams_oversized: u64,
+
+    /// Capture of every datagram as received, if one was set
+    rtpdump: Option<RtpdumpWriter<Box<dyn Write + Send>>>,
+
+    /// Sender being kept aware of this receiver, if punching
+    punch: Option<Punch>,
+
+    /// Empty datagrams the sender kept NAT bindings open with
+    keepalives_received: u64,
+
+    /// RTCP transport and report schedule, and what its handlers took in
+    rtcp: RtcpSocket<RtcpInbox>,
+    inbox: RtcpInbox,
+    session_bandwidth: u64,
+}
+
+/// What the receiver's RTCP handlers take in from the sender.
+#[derive(Default)]
+struct RtcpInbox {
+    // ---
+    /// RTCP sender reports received, and the last with when it arrived
+    sender_reports_received: u64,
+    last_sender_report: Option<(SenderReport, std::time::Instant)>,
+
+    /// SDES chunks received since last taken
+    source_descriptions: Vec<SdesChunk>,
+
+    /// BYE packets received since last taken
+    byes: Vec<Bye>,
+}
+
+impl RtcpInbox {
+    // ---
+    /// Records a sender report.
+    fn on_sender_report(&mut self, packet: &[u8], src: SocketAddr) {
+        // ---
+        match SenderReport::deserialize(packet) {
+            Ok(report) => {
+                debug!(
+                    "Sender report from {}: {} packets, {} octets, RTP timestamp {}",
+                    src, report.packet_count, report.octet_count, report.rtp_timestamp
+                );
+                self.sender_reports_received += 1;
+                self.last_sender_report = Some((report, std::time::Instant::now()));
+            }
+            Err(e) => debug!("Ignoring sender report from {}: {}", src, e),
+        }
+    }
+
+    /// Keeps the chunks of a source description.
+    fn on_sdes(&mut self, packet: &[u8], src: SocketAddr) {
+        // ---
+        match Sdes::deserialize(packet) {
+            Ok(sdes) => self.source_descriptions.extend(sdes.chunks),
+            Err(e) => debug!("Ignoring SDES from {}: {}", src, e),
+        }
+    }
+
+    /// Keeps a goodbye.
+    fn on_bye(&mut self, packet: &[u8], src: SocketAddr) {
+        // ---
+        match Bye::deserialize(packet) {
+            Ok(bye) => {
+                debug!("BYE from {} for {:08X?}: {:?}", src, bye.ssrcs, bye.reason);
+                self.byes.push(bye);
+            }
+            Err(e) => debug!("Ignoring BYE from {}: {}", src, e),
+        }
+    }
+}
+
+/// Keepalive schedule toward a sender in listen mode.
+struct Punch {
+    // ---
+    target: SocketAddr,
+    interval: Duration,
+
+    /// When the next keepalive is due
+    next: Instant,
+}
+
+/// Where packets arrive.
+enum Link {
+    // ---
+    /// One packet per datagram
+    Udp(UdpSocket),
+
+    /// Framed packets over a TCP connection
+    Tcp(TcpIncoming),
+}
+
+impl Link {
+    // ---
+    /// Returns the socket, for options and addresses.
+    fn socket(&self) -> socket2::SockRef<'_> {
+        // ---
+        match self {
+            Link::Udp(socket) => socket2::SockRef::from(socket),
+            Link::Tcp(incoming) => socket2::SockRef::from(incoming.listener()),
+        }
+    }
 }
 
 impl RtpReceiver {
     // ---
     /// Creates a new RTP receiver bound to the specified port.
     ///
-    /// Listens on all interfaces (0.0.0.0) for incoming packets.
+    /// Listens on all interfaces (0.0.0.0) for incoming packets, using the
+    /// default [`ReceiverNetworkConfig`].
     ///
     /// # Arguments
     ///
@@ -35,50 +432,446 @@ impl RtpReceiver {
     /// Returns error if socket binding fails.
     pub async fn new(port: u16) -> Result<Self> {
         // ---
+        Self::with_config(port, ReceiverNetworkConfig::default()).await
+    }
+
+    /// Creates a new RTP receiver with explicit network configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - UDP port to listen on
+    /// * `config` - Datagram size, receive buffer sizing, multicast group,
+    ///   socket receive buffer, and sender to punch through to
+    ///
+    /// # Errors
+    ///
+    /// Returns error if socket binding fails, if the multicast group is
+    /// not a multicast address, cannot be joined, or is given with TCP, or
+    /// if punching is asked for with TCP or multicast, or the hello cannot
+    /// be sent.
+    pub async fn with_config(port: u16, config: ReceiverNetworkConfig) -> Result<Self> {
+        // ---
+        if let Some(target) = config.punch {
+            if config.transport == Transport::Tcp || config.multicast_group.is_some() {
+                anyhow::bail!("cannot punch through to {} over TCP or multicast", target);
+            }
+            if config.keepalive_interval.is_zero() {
+                anyhow::bail!("keepalive interval must be greater than zero");
+            }
+        }
+
         let addr = format!("0.0.0.0:{}", port);
+        let link = match (config.transport, config.multicast_group) {
+            (Transport::Tcp, Some(group)) => {
+                anyhow::bail!("cannot join multicast group {} over TCP", group)
+            }
+            (Transport::Tcp, None) => {
+                let listener = TcpListener::bind(&addr)
+                    .await
+                    .with_context(|| format!("failed to listen for TCP on {}", addr))?;
+                info!("TCP listener bound to {}", listener.local_addr()?);
+                Link::Tcp(TcpIncoming::new(listener))
+            }
+            (Transport::Udp, Some(group)) => {
+                Link::Udp(bind_multicast(port, group, config.multicast_interface)?)
+            }
+            (Transport::Udp, None) => Link::Udp(
+                UdpSocket::bind(&addr)
+                    .await
+                    .with_context(|| format!("failed to bind UDP socket to {}", addr))?,
+            ),
+        };
 
-        let socket = UdpSocket::bind(&addr)
-            .await
-            .with_context(|| format!("failed to bind UDP socket to {}", addr))?;
+        if let Link::Udp(socket) = &link {
+            info!("UDP socket bound to {}", socket.local_addr()?);
+        }
+        if let Some(bytes) = config.recv_buffer_bytes {
+            // Connections accepted over TCP inherit the listener's buffer
+            set_recv_buffer(link.socket(), bytes);
+        }
+
+        let recv = RecvBuffer::new(config.max_datagram_size, config.recv_buffer_datagrams);
 
-        info!("UDP socket bound to {}", socket.local_addr()?);
+        // Without SRTCP, RTCP cannot be authenticated, so with SRTP none is
+        // taken and a socket for it would sit idle
+        let rtcp = match &link {
+            Link::Udp(socket) if !config.rtcp_mux && config.srtp_key.is_none() => {
+                let rtp_addr = socket.local_addr()?;
+                let rtcp = match config.multicast_group {
+                    Some(group) => RtcpSocket::from_socket(bind_multicast(
+                        rtp_addr.port() + 1,
+                        group,
+                        config.multicast_interface,
+                    )?),
+                    None => RtcpSocket::bind(rtp_addr).await?,
+                };
+                if let Some(addr) = rtcp.local_addr() {
+                    info!("RTCP socket bound to {}", addr);
+                }
+                rtcp
+            }
+            _ => RtcpSocket::muxed(),
+        };
+        let rtcp = rtcp
+            .with_handler(RTCP_PT_SR, RtcpInbox::on_sender_report)
+            .with_handler(RTCP_PT_SDES, RtcpInbox::on_sdes)
+            .with_handler(RTCP_PT_BYE, RtcpInbox::on_bye);
+
+        let punch = match (&link, config.punch) {
+            (Link::Udp(socket), Some(target)) => {
+                send_keepalive(socket, target)
+                    .await
+                    .with_context(|| format!("failed to say hello to {}", target))?;
+                info!("Said hello to {}", target);
+                Some(Punch {
+                    target,
+                    interval: config.keepalive_interval,
+                    next: Instant::now() + config.keepalive_interval,
+                })
+            }
+            _ => None,
+        };
 
         Ok(Self {
-            socket,
+            link,
+            recv,
+            source: SourceTracker::new(config.rebind_confirm_packets),
             packets_received: 0,
             bytes_received: 0,
             packets_dropped: 0,
+            anomalies: AnomalyLogger::default(),
+            parse_mode: RtpParseMode::default(),
+            parse_errors: ParseErrorCounts::default(),
+            verify_payloads: false,
+            payload_crc_mismatches: 0,
+            expected_payload_type: None,
+            payload_type_mismatches: 0,
+            srtp: config.srtp_key.as_ref().map(SrtpContext::new),
+            srtp_auth_failures: 0,
+            datagrams_oversized: 0,
+            rtpdump: None,
+            punch,
+            keepalives_received: 0,
+            rtcp,
+            inbox: RtcpInbox::default(),
+            session_bandwidth: config.session_bandwidth,
         })
     }
 
+    /// Replaces the logger for invalid packets, e.g. with one that exports
+    /// suppression metrics.
+    pub fn set_anomaly_logger(&mut self, logger: AnomalyLogger) {
+        // ---
+        self.anomalies = logger;
+    }
+
+    /// Parses datagrams as `mode` asks; lenient by default.
+    ///
+    /// [`receive`](Self::receive) drops datagrams that fail to parse,
+    /// counted by reason in [`stats`](Self::stats).
+    pub fn set_parse_mode(&mut self, mode: RtpParseMode) {
+        // ---
+        self.parse_mode = mode;
+    }
+
+    /// Expects every payload to end in a CRC32 trailer (see
+    /// [`RtpPacket::strip_payload_crc`]).
+    ///
+    /// With verification on, [`receive`](Self::receive) strips the trailer
+    /// from intact packets and drops the ones that fail the check, so a
+    /// corrupted payload never reaches the decoder. The sender must be
+    /// appending trailers, or every packet is dropped.
+    pub fn set_verify_payloads(&mut self, enabled: bool) {
+        // ---
+        self.verify_payloads = enabled;
+    }
+
+    /// Accepts only packets with `payload_type`, e.g. the one negotiated
+    /// in SDP; `None` accepts any.
+    ///
+    /// [`receive`](Self::receive) drops other packets before they reach
+    /// the source tracker or the jitter buffer. Packets of the same stream
+    /// with another payload type (e.g. comfort noise) then show up as lost.
+    pub fn set_expected_payload_type(&mut self, payload_type: Option<u8>) {
+        // ---
+        self.expected_payload_type = payload_type;
+    }
+
+    /// Returns how many packets were dropped for an unexpected payload
+    /// type.
+    pub fn payload_type_mismatches(&self) -> u64 {
+        // ---
+        self.payload_type_mismatches
+    }
+
+    /// Returns how many packets failed the payload CRC check.
+    pub fn payload_crc_mismatches(&self) -> u64 {
+        // ---
+        self.payload_crc_mismatches
+    }
+
+    /// Returns how many keepalives (empty datagrams) the sender sent
+    /// during silence. They are discarded, and not counted as received or
+    /// dropped packets.
+    pub fn keepalives_received(&self) -> u64 {
+        // ---
+        self.keepalives_received
+    }
+
+    /// Returns how many RTCP sender reports arrived. They are not counted
+    /// as received or dropped packets.
+    pub fn sender_reports_received(&self) -> u64 {
+        // ---
+        self.inbox.sender_reports_received
+    }
+
+    /// Returns the last RTCP sender report and when it arrived, as a
+    /// receiver report needs them (LSR and DLSR).
+    pub fn last_sender_report(&self) -> Option<(SenderReport, std::time::Instant)> {
+        // ---
+        self.inbox.last_sender_report
+    }
+
+    /// Takes the RTCP source description chunks received since the last
+    /// call, oldest first.
+    pub fn take_source_descriptions(&mut self) -> Vec<SdesChunk> {
+        // ---
+        std::mem::take(&mut self.inbox.source_descriptions)
+    }
+
+    /// Takes the RTCP BYE packets received since the last call, oldest
+    /// first.
+    pub fn take_byes(&mut self) -> Vec<Bye> {
+        // ---
+        std::mem::take(&mut self.inbox.byes)
+    }
+
+    /// Returns how many RTCP datagrams were refused as malformed
+    /// compound packets (see [`CompoundPacket::parse`](rtp_opus_common::CompoundPacket::parse)).
+    pub fn rtcp_malformed(&self) -> u64 {
+        // ---
+        self.rtcp.malformed()
+    }
+
+    /// Schedules receiver reports at least `min_interval` apart, and
+    /// further apart if the session bandwidth calls for it (see
+    /// [`RtcpInterval`]), or stops them with `None`. The schedule starts
+    /// over, with the first report one interval after the next call to
+    /// [`receiver_report_due`](Self::receiver_report_due).
+    pub fn schedule_receiver_reports(&mut self, min_interval: Option<Duration>) {
+        // ---
+        let interval =
+            min_interval.map(|interval| RtcpInterval::new(self.session_bandwidth, interval));
+        self.rtcp.schedule_reports(interval, false);
+    }
+
+    /// Returns whether a receiver report is due, scheduling the next one
+    /// if so (see [`schedule_receiver_reports`](Self::schedule_receiver_reports)).
+    pub fn receiver_report_due(&mut self) -> bool {
+        // ---
+        self.rtcp.report_due(Instant::now())
+    }
+
+    /// Returns how many packets failed SRTP authentication.
+    pub fn srtp_auth_failures(&self) -> u64 {
+        // ---
+        self.srtp_auth_failures
+    }
+
+    /// Returns how many datagrams were dropped for being larger than
+    /// [`ReceiverNetworkConfig::max_datagram_size`].
+    pub fn datagrams_oversized(&self) -> u64 {
+        // ---
+        self.datagrams_oversized
+    }
+
+    /// Records every datagram [`receive`](Self::receive) takes to `dump`,
+    /// keepalives and RTCP aside, as it came off the wire: before SRTP and
+    /// payload checks, and before the jitter buffer. If a write fails, a warning is logged and
+    /// capturing stops; the stream carries on.
+    pub fn set_rtpdump(&mut self, dump: RtpdumpWriter<Box<dyn Write + Send>>) {
+        // ---
+        self.rtpdump = Some(dump);
+    }
+
+    /// Stops capturing and returns the capture, to flush and close it.
+    /// Returns `None` if none was set or capturing stopped on an error.
+    pub fn take_rtpdump(&mut self) -> Option<RtpdumpWriter<Box<dyn Write + Send>>> {
+        // ---
+        self.rtpdump.take()
+    }
+
+    /// Logs invalid packets counted since the last aggregate line.
+    pub fn flush_anomalies(&mut self) {
+        // ---
+        self.anomalies.flush(std::time::Instant::now());
+    }
+
     /// Receives the next RTP packet.
     ///
     /// Blocks until a packet arrives, then deserializes and validates it.
-    /// Invalid packets are logged and counted as dropped.
+    /// When punching, keepalives due while waiting go out meanwhile.
+    /// With SRTP, the packet is authenticated and decrypted first.
+    /// Invalid packets, packets failing SRTP authentication, packets with
+    /// a payload type other than the expected one, and with payload
+    /// verification on packets failing the CRC check, are counted as
+    /// dropped and logged through the rate-limited anomaly logger.
+    /// Keepalives from the sender are counted apart (see
+    /// [`keepalives_received`](Self::keepalives_received)), and RTCP,
+    /// sharing the socket (RFC 5761) or on a socket of its own, is taken
+    /// aside: sender reports are recorded (see
+    /// [`last_sender_report`](Self::last_sender_report)) and source
+    /// descriptions and goodbyes kept (see
+    /// [`take_source_descriptions`](Self::take_source_descriptions) and
+    /// [`take_byes`](Self::take_byes)). Malformed compound packets are
+    /// refused whole (see [`rtcp_malformed`](Self::rtcp_malformed)), and
+    /// other RTCP is ignored.
     ///
     /// # Returns
     ///
-    /// The next valid RTP packet, or None if packet was invalid.
+    /// The next valid RTP packet, or None if packet was invalid, a
+    /// keepalive, or RTCP.
     ///
     /// # Errors
     ///
     /// Returns error if network reception fails.
     pub async fn receive(&mut self) -> Result<Option<RtpPacket>> {
         // ---
-        let mut buf = vec![0u8; 2048]; // Max UDP packet size for RTP
+        let max_datagram_size = self.recv.max_datagram_size;
+        let buf = self.recv.space();
 
-        let (len, src) = self
-            .socket
-            .recv_from(&mut buf)
-            .await
-            .context("failed to receive UDP packet")?;
+        let (len, src) = match &mut self.link {
+            Link::Udp(socket) => loop {
+                if self.punch.is_none() && self.rtcp.is_muxed() {
+                    break socket
+                        .recv_from(&mut *buf)
+                        .await
+                        .context("failed to receive UDP packet")?;
+                }
+                let punch_due = self.punch.as_ref().map(|punch| punch.next);
+                tokio::select! {
+                    received = socket.recv_from(&mut *buf) => {
+                        break received.context("failed to receive UDP packet")?;
+                    }
+                    // RTCP on a socket of its own: taken in by the handlers
+                    received = self.rtcp.recv(&mut self.inbox) => {
+                        received?;
+                        return Ok(None);
+                    }
+                    () = tokio::time::sleep_until(punch_due.unwrap_or_else(Instant::now)),
+                        if punch_due.is_some() =>
+                    {
+                        let Some(punch) = &mut self.punch else {
+                            continue;
+                        };
+                        // A lost keepalive is made up by the next one
+                        if let Err(e) = send_keepalive(socket, punch.target).await {
+                            debug!("Keepalive to {} failed: {}", punch.target, e);
+                        }
+                        punch.next = Instant::now() + punch.interval;
+                    }
+                }
+            },
+            Link::Tcp(incoming) => incoming
+                .recv(buf)
+                .await
+                .context("failed to receive over TCP")?,
+        };
+
+        // A sender's keepalive (RFC 6263 §4.1): not a packet, so neither
+        // dropped nor captured, and no sequence number to miss
+        if len == 0 {
+            self.keepalives_received += 1;
+            debug!("Keepalive from {}", src);
+            return Ok(None);
+        }
+
+        // Cut short by the buffer: whatever it was, what is left of it
+        // would parse as garbage or not at all
+        if len > max_datagram_size {
+            self.packets_dropped += 1;
+            self.datagrams_oversized += 1;
+            self.anomalies.record(
+                AnomalyClass::InvalidPacket,
+                None,
+                format_args!(
+                    "Dropped datagram from {}: larger than {} bytes",
+                    src, max_datagram_size
+                ),
+                std::time::Instant::now(),
+            );
+            return Ok(None);
+        }
+
+        // RTCP sharing the socket (RFC 5761): not a packet either. Without
+        // SRTCP, reports cannot be authenticated, so with SRTP none are
+        // taken.
+        if is_rtcp(&buf[..len]) {
+            if self.srtp.is_some() {
+                debug!("Ignoring unauthenticated {}-byte RTCP from {}", len, src);
+                return Ok(None);
+            }
+            self.rtcp.dispatch(&buf[..len], src, &mut self.inbox);
+            return Ok(None);
+        }
 
         self.bytes_received += len as u64;
 
-        // Parse RTP packet
-        match RtpPacket::deserialize(&buf[..len]) {
-            Ok(packet) => {
+        if let Some(dump) = &mut self.rtpdump {
+            if let Err(e) = dump.write_packet(&buf[..len], std::time::Instant::now()) {
+                warn!("Stopped the rtpdump capture: {}", e);
+                self.rtpdump = None;
+            }
+        }
+
+        // Forged or corrupted packets stop here, before the source tracker
+        // and the decoder see them
+        let plain_len = match &mut self.srtp {
+            Some(srtp) => match srtp.unprotect(&mut buf[..len]) {
+                Ok(len) => len,
+                Err(e) => {
+                    self.packets_dropped += 1;
+                    self.srtp_auth_failures += 1;
+                    self.anomalies.record(
+                        AnomalyClass::SrtpAuthFailed,
+                        None,
+                        format_args!("Dropped packet from {}: {}", src, e),
+                        std::time::Instant::now(),
+                    );
+                    return Ok(None);
+                }
+            },
+            None => len,
+        };
+
+        // Parse RTP packet, its payload left in place
+        let mut datagram = self.recv.split(len);
+        datagram.truncate(plain_len);
+        match RtpPacket::try_deserialize_bytes(datagram, self.parse_mode) {
+            Ok(packet)
+                if self
+                    .expected_payload_type
+                    .is_some_and(|expected| packet.payload_type != expected) =>
+            {
+                self.packets_dropped += 1;
+                self.payload_type_mismatches += 1;
+                self.anomalies.record(
+                    AnomalyClass::PayloadTypeMismatch,
+                    Some(packet.sequence),
+                    format_args!(
+                        "Dropped packet seq={} from {}: payload type {}, expected {}",
+                        packet.sequence,
+                        src,
+                        packet.payload_type,
+                        self.expected_payload_type.unwrap_or_default()
+                    ),
+                    std::time::Instant::now(),
+                );
+                Ok(None)
+            }
+            Ok(mut packet) => {
                 self.packets_received += 1;
+                self.source.observe(src, std::time::Instant::now());
 
                 if self.packets_received.is_multiple_of(100) {
                     debug!(
@@ -91,29 +884,149 @@ impl RtpReceiver {
                     );
                 }
 
+                if self.verify_payloads {
+                    if let Err(e) = packet.strip_payload_crc() {
+                        self.packets_dropped += 1;
+                        self.payload_crc_mismatches += 1;
+                        self.anomalies.record(
+                            AnomalyClass::PayloadCrcMismatch,
+                            Some(packet.sequence),
+                            format_args!(
+                                "Dropped packet seq={} from {}: {}",
+                                packet.sequence, src, e
+                            ),
+                            std::time::Instant::now(),
+                        );
+                        return Ok(None);
+                    }
+                }
+
                 Ok(Some(packet))
             }
             Err(e) => {
                 self.packets_dropped += 1;
-                warn!("Dropped invalid packet from {}: {}", src, e);
+                self.parse_errors.record(e.kind());
+                self.anomalies.record(
+                    AnomalyClass::InvalidPacket,
+                    None,
+                    format_args!("Dropped invalid packet from {}: {}", src, e),
+                    std::time::Instant::now(),
+                );
                 Ok(None)
             }
         }
     }
 
-    /// Returns statistics about packets received.
+    /// Returns the socket receive buffer size the kernel reports.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// Tuple of (packets_received, bytes_received, packets_dropped)
-    #[allow(dead_code)] // Will be used in Phase 3 for metrics
-    pub fn stats(&self) -> (u64, u64, u64) {
+    /// Returns error if the size cannot be queried.
+    pub fn recv_buffer_size(&self) -> Result<usize> {
         // ---
-        (
-            self.packets_received,
-            self.bytes_received,
-            self.packets_dropped,
-        )
+        self.link
+            .socket()
+            .recv_buffer_size()
+            .context("failed to query receive buffer size")
+    }
+
+    /// Returns the local address the socket is bound to.
+    ///
+    /// Useful when binding to port 0 to discover the assigned port.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the socket address cannot be queried.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        // ---
+        match &self.link {
+            Link::Udp(socket) => socket.local_addr(),
+            Link::Tcp(incoming) => incoming.listener().local_addr(),
+        }
+        .context("failed to query local address")
+    }
+
+    /// Returns the active source address and when it was last heard from.
+    ///
+    /// `None` until the first valid packet arrives.
+    pub fn observed_source(&self) -> Option<ObservedSource> {
+        // ---
+        self.source.current()
+    }
+
+    /// Returns the address replies are sent to (the observed source).
+    pub fn reply_addr(&self) -> Option<SocketAddr> {
+        // ---
+        self.source.current().map(|source| source.addr)
+    }
+
+    /// Returns how many times the source moved to a new address.
+    pub fn source_rebinds(&self) -> u64 {
+        // ---
+        self.source.rebinds()
+    }
+
+    /// Sends a reply datagram to the observed source.
+    ///
+    /// This is the reply path for anything sent back to the sender (receiver
+    /// reports, NACKs, application feedback). It goes out from the receiving
+    /// socket so the sender's NAT binding accepts it.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no packet has arrived yet, the send fails, or the
+    /// transport is TCP.
+    pub async fn send_reply(&self, data: &[u8]) -> Result<usize> {
+        // ---
+        let Link::Udp(socket) = &self.link else {
+            anyhow::bail!("replies are not supported over TCP");
+        };
+        let addr = self
+            .reply_addr()
+            .context("no source observed yet to reply to")?;
+        socket
+            .send_to(data, addr)
+            .await
+            .with_context(|| format!("failed to send reply to {}", addr))
+    }
+
+    /// Sends an RTCP datagram to the observed source: to its RTP address
+    /// from the RTP socket, or with RTCP on a socket of its own (see
+    /// [`ReceiverNetworkConfig::rtcp_mux`]) to the port after it.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no packet has arrived yet, the send fails, or the
+    /// transport is TCP.
+    pub async fn send_rtcp(&mut self, data: &[u8]) -> Result<()> {
+        // ---
+        let Link::Udp(socket) = &self.link else {
+            anyhow::bail!("RTCP is not supported over TCP");
+        };
+        let addr = self
+            .reply_addr()
+            .context("no source observed yet to send RTCP to")?;
+        self.rtcp
+            .send(socket, data, addr)
+            .await
+            .with_context(|| format!("failed to send RTCP to {}", self.rtcp.target(addr)))
+    }
+
+    /// Returns the sender this receiver punches through to, if any.
+    pub fn punch_target(&self) -> Option<SocketAddr> {
+        // ---
+        self.punch.as_ref().map(|punch| punch.target)
+    }
+
+    /// Returns statistics about packets received.
+    pub fn stats(&self) -> RtpReceiverStats {
+        // ---
+        RtpReceiverStats {
+            packets_received: self.packets_received,
+            bytes_received: self.bytes_received,
+            packets_dropped: self.packets_dropped,
+            parse_errors: self.parse_errors,
+        }
     }
 }
 
@@ -121,6 +1034,23 @@ impl RtpReceiver {
 mod tests {
     // ---
     use super::*;
+    use rtp_opus_common::RTP_HEADER_LEN;
+
+    #[tokio::test]
+    async fn test_receiver_recv_buffer_size() {
+        // ---
+        let default = RtpReceiver::new(0)
+            .await
+            .unwrap()
+            .recv_buffer_size()
+            .unwrap();
+        let config = ReceiverNetworkConfig {
+            recv_buffer_bytes: Some(default * 2),
+            ..Default::default()
+        };
+        let receiver = RtpReceiver::with_config(0, config).await.unwrap();
+        assert!(receiver.recv_buffer_size().unwrap() >= default);
+    }
 
     #[tokio::test]
     async fn test_receiver_creation() {
@@ -130,14 +1060,336 @@ mod tests {
         assert!(receiver.is_ok());
     }
 
+    #[test]
+    fn test_recv_buffer_reuses_an_allocation_once_released() {
+        // ---
+        let mut recv = RecvBuffer::new(100, 4);
+        let mut receive = |byte: u8| {
+            recv.space().fill(byte);
+            recv.split(100)
+        };
+
+        // Datagrams follow one another in the first allocation
+        let held: Vec<Bytes> = (0..4).map(&mut receive).collect();
+        let base = held[0].as_ptr();
+        for (index, datagram) in held.iter().enumerate() {
+            assert_eq!(datagram.as_ptr(), base.wrapping_add(100 * index));
+            assert!(datagram.iter().all(|&b| b == index as u8));
+        }
+
+        // With those still held, the next needs a new allocation
+        let next = receive(4);
+        let second = next.as_ptr();
+        assert!(!(base..base.wrapping_add(400)).contains(&second));
+        assert!(held[3].iter().all(|&b| b == 3), "not overwritten");
+        drop(held);
+        drop(next);
+
+        // Released, the allocation is used again from the start
+        for byte in 5..8 {
+            receive(byte);
+        }
+        assert_eq!(receive(8).as_ptr(), second);
+    }
+
+    #[tokio::test]
+    async fn test_received_payloads_share_the_receive_buffer() {
+        // ---
+        let mut receiver = RtpReceiver::new(0).await.expect("receiver creation failed");
+        let port = receiver.local_addr().expect("local_addr failed").port();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind failed");
+        let mut received = Vec::new();
+        for seq in 0..10u16 {
+            let packet = RtpPacket::new(seq, seq as u32 * 320, 0x12345678, vec![seq as u8; 40]);
+            let data = packet.serialize().expect("serialization failed");
+            socket
+                .send_to(&data, ("127.0.0.1", port))
+                .await
+                .expect("send failed");
+
+            let packet_received = receiver.receive().await.expect("receive failed");
+            assert_eq!(packet_received.as_ref(), Some(&packet));
+            received.extend(packet_received);
+        }
+
+        // Each payload sits right after the previous datagram: sliced out
+        // of one allocation, not copied
+        for pair in received.windows(2) {
+            let step = pair[1].payload.as_ptr() as usize - pair[0].payload.as_ptr() as usize;
+            assert_eq!(step, RTP_HEADER_LEN + 40);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replies_follow_source_port_change() {
+        // ---
+        let config = ReceiverNetworkConfig {
+            rebind_confirm_packets: 3,
+            ..Default::default()
+        };
+        let mut receiver = RtpReceiver::with_config(0, config)
+            .await
+            .expect("receiver creation failed");
+        let port = receiver.local_addr().expect("local_addr failed").port();
+        assert!(receiver.send_reply(b"rr").await.is_err());
+
+        // Same sender before and after a NAT rebinding to a new port
+        let before = UdpSocket::bind("127.0.0.1:0").await.expect("bind failed");
+        let after = UdpSocket::bind("127.0.0.1:0").await.expect("bind failed");
+
+        async fn send_from(socket: &UdpSocket, port: u16, seq: u16) {
+            let packet = RtpPacket::new(seq, seq as u32 * 320, 0x12345678, vec![0; 40]);
+            let data = packet.serialize().expect("serialization failed");
+            socket
+                .send_to(&data, ("127.0.0.1", port))
+                .await
+                .expect("send failed");
+        }
+
+        for seq in 0..5 {
+            send_from(&before, port, seq).await;
+            receiver.receive().await.expect("receive failed");
+        }
+        let before_addr = before.local_addr().unwrap();
+        assert_eq!(receiver.reply_addr(), Some(before_addr));
+
+        // Replies keep going to the old port until the new one is confirmed
+        for seq in 5..7 {
+            send_from(&after, port, seq).await;
+            receiver.receive().await.expect("receive failed");
+            assert_eq!(receiver.reply_addr(), Some(before_addr));
+        }
+        send_from(&after, port, 7).await;
+        receiver.receive().await.expect("receive failed");
+        assert_eq!(receiver.reply_addr(), Some(after.local_addr().unwrap()));
+        assert_eq!(receiver.source_rebinds(), 1);
+
+        receiver.send_reply(b"rr").await.expect("reply failed");
+        let mut buf = [0u8; 16];
+        let (len, from) = after.recv_from(&mut buf).await.expect("recv failed");
+        assert_eq!(&buf[..len], b"rr");
+        assert_eq!(from.port(), port);
+    }
+
+    #[tokio::test]
+    async fn test_receiver_local_addr() {
+        // ---
+        let receiver = RtpReceiver::new(0).await.expect("receiver creation failed");
+        let addr = receiver.local_addr().expect("local_addr failed");
+
+        assert_ne!(addr.port(), 0);
+    }
+
     #[tokio::test]
     async fn test_receiver_stats() {
         // ---
         let receiver = RtpReceiver::new(0).await.expect("receiver creation failed");
 
-        let (packets, bytes, dropped) = receiver.stats();
-        assert_eq!(packets, 0);
-        assert_eq!(bytes, 0);
-        assert_eq!(dropped, 0);
+        assert_eq!(receiver.stats(), RtpReceiverStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_verify_payloads_drops_corrupted_packets() {
+        // ---
+        let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+        receiver.set_verify_payloads(true);
+        let addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let mut intact = RtpPacket::new(1, 320, 0x1234, vec![0x78, 0xAA, 0xBB]);
+        intact.append_payload_crc();
+        let mut corrupted = intact.clone();
+        corrupted.sequence = 2;
+        let mut payload = corrupted.payload.to_vec();
+        payload[1] ^= 0x01;
+        corrupted.payload = payload.into();
+
+        for packet in [&intact, &corrupted] {
+            socket
+                .send_to(&packet.serialize().unwrap(), addr)
+                .await
+                .unwrap();
+        }
+
+        let received = receiver.receive().await.unwrap().expect("intact packet");
+        assert_eq!(received.payload, vec![0x78, 0xAA, 0xBB]);
+        assert!(receiver.receive().await.unwrap().is_none());
+        assert_eq!(receiver.payload_crc_mismatches(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_punch_says_hello_then_keeps_alive() {
+        // ---
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let config = ReceiverNetworkConfig {
+            punch: Some(sender.local_addr().unwrap()),
+            keepalive_interval: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let mut receiver = RtpReceiver::with_config(0, config).await.unwrap();
+        let port = receiver.local_addr().unwrap().port();
+
+        // The hello comes from the RTP socket before anything is received
+        let mut buf = [0u8; 64];
+        let (len, from) = sender.recv_from(&mut buf).await.unwrap();
+        assert_eq!(from.port(), port);
+        assert_eq!(
+            Keepalive::deserialize(&buf[..len]).unwrap(),
+            Keepalive { sender_ssrc: 0 }
+        );
+
+        // Keepalives go out while waiting, and the wait still ends with a
+        // packet
+        let packet = RtpPacket::new(1, 320, 7, vec![1, 2, 3]);
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let (received, keepalives) = tokio::join!(receiver.receive(), async {
+            let mut keepalives = 0;
+            while keepalives < 3 {
+                sender.recv_from(&mut buf).await.unwrap();
+                keepalives += 1;
+            }
+            sender
+                .send_to(&packet.serialize().unwrap(), addr)
+                .await
+                .unwrap();
+            keepalives
+        });
+        assert_eq!(received.unwrap().unwrap().sequence, 1);
+        assert_eq!(keepalives, 3);
+
+        let config = ReceiverNetworkConfig {
+            punch: Some(addr),
+            transport: Transport::Tcp,
+            ..Default::default()
+        };
+        assert!(RtpReceiver::with_config(0, config).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_keepalives_are_not_packets() {
+        // ---
+        let mut receiver = RtpReceiver::new(0).await.unwrap();
+        let addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let packet = RtpPacket::new(1, 320, 7, vec![1, 2, 3]);
+        sender.send_to(&[], addr).await.unwrap();
+        sender
+            .send_to(&packet.serialize().unwrap(), addr)
+            .await
+            .unwrap();
+
+        assert!(receiver.receive().await.unwrap().is_none());
+        assert_eq!(receiver.receive().await.unwrap().unwrap().sequence, 1);
+        assert_eq!(receiver.keepalives_received(), 1);
+        let stats = receiver.stats();
+        assert_eq!((stats.packets_received, stats.packets_dropped), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_payload_types_dropped_and_counted() {
+        // ---
+        let mut receiver = RtpReceiver::new(0).await.unwrap();
+        receiver.set_expected_payload_type(Some(111));
+        let addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        for (sequence, payload_type) in
+            [(1, 111), (2, rtp_opus_common::PAYLOAD_TYPE_OPUS), (3, 111)]
+        {
+            let packet = RtpPacket::new(sequence, 0, 7, vec![0x78])
+                .with_payload_type(payload_type)
+                .unwrap();
+            sender
+                .send_to(&packet.serialize().unwrap(), addr)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(receiver.receive().await.unwrap().unwrap().sequence, 1);
+        assert!(receiver.receive().await.unwrap().is_none());
+        assert_eq!(receiver.receive().await.unwrap().unwrap().sequence, 3);
+        assert_eq!(receiver.payload_type_mismatches(), 1);
+        let stats = receiver.stats();
+        assert_eq!(
+            (
+                stats.packets_received,
+                stats.bytes_received,
+                stats.packets_dropped
+            ),
+            (2, 39, 1)
+        );
+        assert_eq!(stats.parse_errors.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_parse_errors_counted_by_reason() {
+        // ---
+        let mut receiver = RtpReceiver::new(0).await.unwrap();
+        let addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let mut two_byte = RtpPacket::new(3, 0, 7, vec![0x78]);
+        two_byte.extension = Some(rtp_opus_common::RtpHeaderExtension {
+            profile: 0x1000,
+            data: vec![3, 1, 7, 0],
+        });
+        let two_byte = two_byte.serialize().unwrap();
+        let stun = [
+            0x00, 0x01, 0x00, 0x00, 0x21, 0x12, 0xA4, 0x42, 0, 0, 0, 0, 0, 0,
+        ];
+        for datagram in [&[0x80, 0x60][..], &stun, &two_byte] {
+            sender.send_to(datagram, addr).await.unwrap();
+        }
+        assert!(receiver.receive().await.unwrap().is_none());
+        assert!(receiver.receive().await.unwrap().is_none());
+        assert_eq!(receiver.receive().await.unwrap().unwrap().sequence, 3);
+
+        receiver.set_parse_mode(RtpParseMode::Strict);
+        sender.send_to(&two_byte, addr).await.unwrap();
+        assert!(receiver.receive().await.unwrap().is_none());
+
+        let stats = receiver.stats();
+        assert_eq!((stats.packets_received, stats.packets_dropped), (1, 3));
+        let errors = stats.parse_errors;
+        assert_eq!(errors.get(RtpParseErrorKind::TooShort), 1);
+        assert_eq!(errors.get(RtpParseErrorKind::BadVersion), 1);
+        assert_eq!(errors.get(RtpParseErrorKind::UnsupportedFeature), 1);
+        assert_eq!(errors.total(), 3);
+        assert_eq!(errors.iter().count(), RtpParseErrorKind::ALL.len());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_datagram_dropped_not_decoded() {
+        // ---
+        let config = ReceiverNetworkConfig {
+            max_datagram_size: 64,
+            ..Default::default()
+        };
+        let mut receiver = RtpReceiver::with_config(0, config).await.unwrap();
+        let addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+        let sender = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        // 12-byte header: 52 bytes of payload fill the buffer exactly
+        let fits = RtpPacket::new(1, 0, 7, vec![0x78; 52]);
+        let oversized = RtpPacket::new(2, 960, 7, vec![0x78; 53]);
+        let after = RtpPacket::new(3, 1920, 7, vec![0x78; 10]);
+        for packet in [&fits, &oversized, &after] {
+            sender
+                .send_to(&packet.serialize().unwrap(), addr)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(receiver.receive().await.unwrap().unwrap().sequence, 1);
+        assert!(receiver.receive().await.unwrap().is_none());
+        assert_eq!(receiver.receive().await.unwrap().unwrap().sequence, 3);
+
+        let stats = receiver.stats();
+        assert_eq!((stats.packets_received, stats.packets_dropped), (2, 1));
+        assert_eq!(stats.bytes_received, 64 + 22);
+        assert_eq!(receiver.datagrams_oversized(), 1);
+        assert_eq!(stats.parse_errors.total(), 0);
     }
 }
@@ -0,0 +1,181 @@
+//! Decoder refresh requests after long outages.
+//!
+//! After a long run of lost packets the decoder's prediction state no
+//! longer matches the encoder's, and audio recovers with an artifact tail.
+//! The receiver asks the sender to reset its encoder so both sides start
+//! from a clean state. Requests are rate limited so a flapping link cannot
+//! make the sender reset continuously.
+
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Default loss run that triggers a request (500ms at 20ms frames).
+pub const DEFAULT_REFRESH_LOST_PACKETS: u16 = 25;
+
+/// Default minimum time between two requests.
+pub const DEFAULT_REFRESH_MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// When the receiver asks the sender for a decoder refresh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshConfig {
+    // ---
+    /// Consecutive lost packets that make the decoder state stale
+    pub lost_packets: u16,
+
+    /// Minimum time between two requests
+    pub min_interval: Duration,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            lost_packets: DEFAULT_REFRESH_LOST_PACKETS,
+            min_interval: DEFAULT_REFRESH_MIN_INTERVAL,
+        }
+    }
+}
+
+/// Outcome of observing a loss run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshDecision {
+    // ---
+    /// The run was too short to matter
+    NotNeeded,
+
+    /// Send a request now
+    Request,
+
+    /// A request is warranted but one was sent too recently
+    RateLimited,
+}
+
+/// Decides when to send a decoder refresh request.
+///
+/// Fed the length of each loss run as playout resumes after it; a run of
+/// at least [`RefreshConfig::lost_packets`] triggers a request unless one
+/// was sent within [`RefreshConfig::min_interval`].
+#[derive(Debug)]
+pub struct RefreshRequester {
+    // ---
+    config: RefreshConfig,
+
+    /// When the last request was sent
+    last_request: Option<Instant>,
+
+    /// Requests sent
+    sent: u64,
+
+    /// Requests withheld by the rate limit
+    suppressed: u64,
+}
+
+impl RefreshRequester {
+    // ---
+    /// Creates a requester that has sent nothing yet.
+    pub fn new(config: RefreshConfig) -> Self {
+        // ---
+        Self {
+            config,
+            last_request: None,
+            sent: 0,
+            suppressed: 0,
+        }
+    }
+
+    /// Observes a loss run that ended at `now`.
+    pub fn observe_loss_run(&mut self, lost_packets: u16, now: Instant) -> RefreshDecision {
+        // ---
+        if lost_packets == 0 || lost_packets < self.config.lost_packets {
+            return RefreshDecision::NotNeeded;
+        }
+
+        let limited = self
+            .last_request
+            .is_some_and(|last| now.saturating_duration_since(last) < self.config.min_interval);
+        if limited {
+            debug!(
+                "Decoder refresh after {} lost packets withheld by rate limit",
+                lost_packets
+            );
+            self.suppressed += 1;
+            return RefreshDecision::RateLimited;
+        }
+
+        info!(
+            "Requesting decoder refresh after {} lost packets",
+            lost_packets
+        );
+        self.last_request = Some(now);
+        self.sent += 1;
+        RefreshDecision::Request
+    }
+
+    /// Returns the number of requests sent.
+    pub fn sent(&self) -> u64 {
+        // ---
+        self.sent
+    }
+
+    /// Returns the number of requests withheld by the rate limit.
+    pub fn suppressed(&self) -> u64 {
+        // ---
+        self.suppressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_requests_only_after_long_runs() {
+        // ---
+        let mut requester = RefreshRequester::new(RefreshConfig::default());
+        let now = Instant::now();
+
+        assert_eq!(
+            requester.observe_loss_run(0, now),
+            RefreshDecision::NotNeeded
+        );
+        assert_eq!(
+            requester.observe_loss_run(DEFAULT_REFRESH_LOST_PACKETS - 1, now),
+            RefreshDecision::NotNeeded
+        );
+        assert_eq!(
+            requester.observe_loss_run(DEFAULT_REFRESH_LOST_PACKETS, now),
+            RefreshDecision::Request
+        );
+        assert_eq!(requester.sent(), 1);
+    }
+
+    #[test]
+    fn test_rate_limits_requests() {
+        // ---
+        let config = RefreshConfig {
+            lost_packets: 10,
+            min_interval: Duration::from_secs(5),
+        };
+        let mut requester = RefreshRequester::new(config);
+        let start = Instant::now();
+
+        let decisions: Vec<_> = [0, 1, 4, 5]
+            .into_iter()
+            .map(|secs| requester.observe_loss_run(50, start + Duration::from_secs(secs)))
+            .collect();
+
+        assert_eq!(
+            decisions,
+            [
+                RefreshDecision::Request,
+                RefreshDecision::RateLimited,
+                RefreshDecision::RateLimited,
+                RefreshDecision::Request,
+            ]
+        );
+
+        assert_eq!(requester.sent(), 2);
+        assert_eq!(requester.suppressed(), 2);
+    }
+}
@@ -0,0 +1,187 @@
+//! Source address tracking for symmetric RTP.
+//!
+//! With symmetric RTP the receiver sends anything addressed back to the
+//! sender (receiver reports, NACKs, application feedback) to the address and
+//! port packets actually arrive from, rather than a configured address. This
+//! lets a sender behind NAT receive replies without any configuration. When
+//! the NAT rebinds mid-session the observed port changes; the tracker follows
+//! it once enough consecutive packets confirm the new address, so a single
+//! stray or spoofed datagram cannot hijack the reply path.
+
+use std::net::SocketAddr;
+use std::time::Instant;
+use tracing::info;
+
+/// Consecutive packets from a new address needed before replies follow it.
+pub const DEFAULT_REBIND_CONFIRM_PACKETS: u32 = 3;
+
+/// The source address replies are sent to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObservedSource {
+    // ---
+    /// Address and port the active stream arrives from
+    pub addr: SocketAddr,
+
+    /// When the last packet from `addr` arrived
+    pub last_seen: Instant,
+}
+
+/// Follows the active source address of an RTP stream.
+#[derive(Debug)]
+pub struct SourceTracker {
+    // ---
+    /// Consecutive packets needed to confirm a new address
+    confirm_packets: u32,
+
+    /// Confirmed source, once any packet has arrived
+    current: Option<ObservedSource>,
+
+    /// New address seen since the last packet from the current source,
+    /// with the number of consecutive packets from it
+    candidate: Option<(SocketAddr, u32)>,
+
+    /// Times the source moved to a new address
+    rebinds: u64,
+}
+
+impl SourceTracker {
+    // ---
+    /// Creates a tracker that switches after `confirm_packets` consecutive
+    /// packets from a new address (at least one).
+    pub fn new(confirm_packets: u32) -> Self {
+        // ---
+        Self {
+            confirm_packets: confirm_packets.max(1),
+            current: None,
+            candidate: None,
+            rebinds: 0,
+        }
+    }
+
+    /// Records a valid packet from `addr`.
+    ///
+    /// Returns the new source when this packet confirms a rebinding.
+    pub fn observe(&mut self, addr: SocketAddr, now: Instant) -> Option<ObservedSource> {
+        // ---
+        let current = match self.current.as_mut() {
+            Some(current) => current,
+            None => {
+                // First packet of the session sets the source directly
+                self.current = Some(ObservedSource {
+                    addr,
+                    last_seen: now,
+                });
+                return None;
+            }
+        };
+
+        if current.addr == addr {
+            current.last_seen = now;
+            self.candidate = None;
+            return None;
+        }
+
+        let count = match self.candidate {
+            Some((candidate, count)) if candidate == addr => count + 1,
+            _ => 1,
+        };
+        if count < self.confirm_packets {
+            self.candidate = Some((addr, count));
+            return None;
+        }
+
+        info!("RTP source rebound from {} to {}", current.addr, addr);
+        let source = ObservedSource {
+            addr,
+            last_seen: now,
+        };
+        self.current = Some(source);
+        self.candidate = None;
+        self.rebinds += 1;
+        Some(source)
+    }
+
+    /// Returns the confirmed source, if any packet has arrived.
+    pub fn current(&self) -> Option<ObservedSource> {
+        // ---
+        self.current
+    }
+
+    /// Returns how many times the source moved to a new address.
+    pub fn rebinds(&self) -> u64 {
+        // ---
+        self.rebinds
+    }
+}
+
+impl Default for SourceTracker {
+    fn default() -> Self {
+        // ---
+        Self::new(DEFAULT_REBIND_CONFIRM_PACKETS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use std::time::Duration;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([192, 0, 2, 1], port))
+    }
+
+    #[test]
+    fn test_first_packet_sets_source() {
+        // ---
+        let mut tracker = SourceTracker::default();
+        let t0 = Instant::now();
+
+        assert!(tracker.current().is_none());
+        assert!(tracker.observe(addr(4000), t0).is_none());
+        assert_eq!(tracker.current().unwrap().addr, addr(4000));
+
+        let t1 = t0 + Duration::from_millis(20);
+        tracker.observe(addr(4000), t1);
+        assert_eq!(tracker.current().unwrap().last_seen, t1);
+    }
+
+    #[test]
+    fn test_rebinds_after_confirmation() {
+        // ---
+        let mut tracker = SourceTracker::new(3);
+        let t0 = Instant::now();
+        tracker.observe(addr(4000), t0);
+
+        assert!(tracker.observe(addr(5000), t0).is_none());
+        assert!(tracker.observe(addr(5000), t0).is_none());
+        assert_eq!(tracker.current().unwrap().addr, addr(4000));
+
+        let rebound = tracker.observe(addr(5000), t0).expect("rebound");
+        assert_eq!(rebound.addr, addr(5000));
+        assert_eq!(tracker.current().unwrap().addr, addr(5000));
+        assert_eq!(tracker.rebinds(), 1);
+    }
+
+    #[test]
+    fn test_stray_packets_do_not_rebind() {
+        // ---
+        let mut tracker = SourceTracker::new(3);
+        let t0 = Instant::now();
+        tracker.observe(addr(4000), t0);
+
+        // Interleaved packets from the current source reset the count
+        for _ in 0..5 {
+            tracker.observe(addr(5000), t0);
+            tracker.observe(addr(5000), t0);
+            tracker.observe(addr(4000), t0);
+        }
+        // Alternating new addresses never confirm either
+        for port in [6000, 7000, 6000, 7000] {
+            tracker.observe(addr(port), t0);
+        }
+
+        assert_eq!(tracker.current().unwrap().addr, addr(4000));
+        assert_eq!(tracker.rebinds(), 0);
+    }
+}
@@ -0,0 +1,206 @@
+//! SSRC change detection.
+//!
+//! A sender that restarts picks a new random SSRC and sequence base. The
+//! receiver follows it once enough consecutive packets confirm the new
+//! SSRC, so the stream state (jitter buffer, decoder, reception stats) can
+//! start over instead of waiting for the old sequence numbers. Packets
+//! from an SSRC that has been replaced are recognized as stragglers of
+//! the old stream rather than as yet another change.
+
+use tracing::info;
+
+/// Consecutive packets from a new SSRC needed before it replaces the
+/// active one.
+pub const DEFAULT_SSRC_CONFIRM_PACKETS: u32 = 3;
+
+/// Replaced SSRCs remembered as stale
+const MAX_RETIRED: usize = 4;
+
+/// What a packet's SSRC means for the stream being played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SsrcUpdate {
+    // ---
+    /// From the active SSRC (or the first packet): play it
+    Active,
+
+    /// From a new SSRC not yet confirmed: drop it
+    Unconfirmed,
+
+    /// Confirms a new SSRC, which is now active: start the stream over
+    /// and play it
+    Switched {
+        /// SSRC the new one replaces
+        previous: u32,
+    },
+
+    /// From an SSRC that was replaced: drop it
+    Stale,
+}
+
+/// Follows the SSRC of the stream being played.
+#[derive(Debug)]
+pub struct SsrcTracker {
+    // ---
+    /// Consecutive packets needed to confirm a new SSRC
+    confirm_packets: u32,
+
+    /// SSRC being played, once any packet has arrived
+    active: Option<u32>,
+
+    /// New SSRC seen since the last packet from the active one, with the
+    /// number of consecutive packets from it
+    candidate: Option<(u32, u32)>,
+
+    /// SSRCs replaced, most recent last
+    retired: Vec<u32>,
+
+    /// Times a new SSRC replaced the active one
+    switches: u64,
+}
+
+impl SsrcTracker {
+    // ---
+    /// Creates a tracker that switches after `confirm_packets` consecutive
+    /// packets from a new SSRC (at least one).
+    pub fn new(confirm_packets: u32) -> Self {
+        // ---
+        Self {
+            confirm_packets: confirm_packets.max(1),
+            active: None,
+            candidate: None,
+            retired: Vec::new(),
+            switches: 0,
+        }
+    }
+
+    /// Records a packet from `ssrc`.
+    pub fn observe(&mut self, ssrc: u32) -> SsrcUpdate {
+        // ---
+        let Some(active) = self.active else {
+            // First packet of the session sets the SSRC directly
+            self.active = Some(ssrc);
+            return SsrcUpdate::Active;
+        };
+
+        if active == ssrc {
+            self.candidate = None;
+            return SsrcUpdate::Active;
+        }
+        if self.retired.contains(&ssrc) {
+            return SsrcUpdate::Stale;
+        }
+
+        let count = match self.candidate {
+            Some((candidate, count)) if candidate == ssrc => count + 1,
+            _ => 1,
+        };
+        if count < self.confirm_packets {
+            self.candidate = Some((ssrc, count));
+            return SsrcUpdate::Unconfirmed;
+        }
+
+        info!("New stream: SSRC 0x{:08X} replaces 0x{:08X}", ssrc, active);
+        if self.retired.len() == MAX_RETIRED {
+            self.retired.remove(0);
+        }
+        self.retired.push(active);
+        self.active = Some(ssrc);
+        self.candidate = None;
+        self.switches += 1;
+        SsrcUpdate::Switched { previous: active }
+    }
+
+    /// Forgets the active SSRC, for a stream that has ended: the next
+    /// packet sets it directly, whatever its SSRC.
+    pub fn reset(&mut self) {
+        // ---
+        self.active = None;
+        self.candidate = None;
+        self.retired.clear();
+    }
+
+    /// Returns the SSRC being played, if any packet has arrived.
+    pub fn active(&self) -> Option<u32> {
+        // ---
+        self.active
+    }
+
+    /// Returns how many times a new SSRC replaced the active one.
+    pub fn switches(&self) -> u64 {
+        // ---
+        self.switches
+    }
+}
+
+impl Default for SsrcTracker {
+    fn default() -> Self {
+        // ---
+        Self::new(DEFAULT_SSRC_CONFIRM_PACKETS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_first_packet_sets_ssrc() {
+        // ---
+        let mut tracker = SsrcTracker::default();
+
+        assert!(tracker.active().is_none());
+        assert_eq!(tracker.observe(0x1111), SsrcUpdate::Active);
+        assert_eq!(tracker.observe(0x1111), SsrcUpdate::Active);
+        assert_eq!(tracker.active(), Some(0x1111));
+        assert_eq!(tracker.switches(), 0);
+    }
+
+    #[test]
+    fn test_switches_after_confirmation() {
+        // ---
+        let mut tracker = SsrcTracker::new(3);
+        tracker.observe(0x1111);
+
+        assert_eq!(tracker.observe(0x2222), SsrcUpdate::Unconfirmed);
+        assert_eq!(tracker.observe(0x2222), SsrcUpdate::Unconfirmed);
+        assert_eq!(
+            tracker.observe(0x2222),
+            SsrcUpdate::Switched { previous: 0x1111 }
+        );
+        assert_eq!(tracker.active(), Some(0x2222));
+        assert_eq!(tracker.switches(), 1);
+
+        // Stragglers of the old stream do not switch back
+        for _ in 0..5 {
+            assert_eq!(tracker.observe(0x1111), SsrcUpdate::Stale);
+        }
+        assert_eq!(tracker.observe(0x2222), SsrcUpdate::Active);
+    }
+
+    #[test]
+    fn test_interleaved_ssrc_does_not_switch() {
+        // ---
+        let mut tracker = SsrcTracker::new(3);
+        tracker.observe(0x1111);
+
+        for _ in 0..10 {
+            assert_eq!(tracker.observe(0x2222), SsrcUpdate::Unconfirmed);
+            assert_eq!(tracker.observe(0x2222), SsrcUpdate::Unconfirmed);
+            assert_eq!(tracker.observe(0x1111), SsrcUpdate::Active);
+        }
+        assert_eq!(tracker.switches(), 0);
+    }
+
+    #[test]
+    fn test_reset_takes_next_ssrc_directly() {
+        // ---
+        let mut tracker = SsrcTracker::default();
+        tracker.observe(0x1111);
+        tracker.reset();
+
+        assert_eq!(tracker.observe(0x3333), SsrcUpdate::Active);
+        assert_eq!(tracker.active(), Some(0x3333));
+        assert_eq!(tracker.switches(), 0);
+    }
+}
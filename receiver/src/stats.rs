@@ -1,17 +1,116 @@
 //! Statistics tracking for RTP receiver.
 //!
 //! Tracks packet reception metrics including loss rate, jitter,
-//! and reordering events for observability and quality monitoring.
+//! and reordering events for observability and quality monitoring, and
+//! keeps a bounded history of playout events ([`PlayoutHistory`]) that can
+//! be dumped to disk for post-hoc glitch diagnosis.
 
-use std::time::{Duration, Instant};
-use tracing::info;
+use crate::clock::StreamClock;
+use crate::transport_cc::{TransportArrival, TransportHistory};
+use crate::xr::{self, PlayoutDelays, Reception, ReceptionHistory};
+use rtp_opus_common::{ExtendedReport, JitterEstimator, LossRle, SeqTracker, SeqUpdate, XrBlock};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Raw counters shared between the recorder and the stats ticker.
+///
+/// Only relaxed atomic increments happen on the hot path; all formatting
+/// and derived-percentage math happens on snapshots.
+#[derive(Debug, Default)]
+struct StatsCounters {
+    // ---
+    packets_received: AtomicU64,
+    packets_lost: AtomicU64,
+    packets_reordered: AtomicU64,
+    packets_late: AtomicU64,
+    packets_duplicate: AtomicU64,
+    talkspurts: AtomicU64,
+
+    /// Interarrival jitter, as `f64` bits: in timestamp units and in ms
+    jitter_units: AtomicU64,
+    jitter_ms: AtomicU64,
+
+    /// Canonical names of the sources by SSRC, from RTCP SDES; written a
+    /// few times a minute at most, so a lock off the packet path
+    cnames: Mutex<BTreeMap<u32, String>>,
+
+    /// RTP clock of the stream, once configured or detected
+    clock: Mutex<Option<StreamClock>>,
+}
 
 /// Network and reception statistics.
 ///
 /// Tracks key metrics for monitoring receiver health and network conditions.
-/// Statistics are designed to be logged periodically for observability.
-#[derive(Debug, Clone)]
+/// The record path is a handful of relaxed atomic increments; periodic
+/// logging is done off the hot path by a ticker task (see
+/// [`ReceiverStats::spawn_ticker`]) that formats published snapshots.
+#[derive(Debug)]
 pub struct ReceiverStats {
+    // ---
+    /// Counters published to snapshot readers
+    counters: Arc<StatsCounters>,
+
+    /// Extended sequence numbers of the packets recorded
+    sequence: SeqTracker,
+
+    /// Start time for rate calculations
+    start_time: Instant,
+
+    /// Interval between periodic logs
+    log_interval: Duration,
+
+    /// Arrivals of transport-wide sequence numbers
+    transport: TransportHistory,
+
+    /// Interarrival jitter, once the clock rate is known
+    jitter: Option<JitterEstimator>,
+
+    /// Extended sequence number reception (re)started from, and packets
+    /// recorded since, for receiver reports
+    base_sequence: Option<u64>,
+    received_since_base: u64,
+
+    /// Packets expected and received as of the last reception report
+    reported: (u64, u64),
+
+    /// What became of each sequence number, for extended reports
+    history: ReceptionHistory,
+}
+
+/// Reception statistics for an RTCP receiver report (RFC 3550 Appendix
+/// A.3), from [`ReceiverStats::take_reception_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceptionReport {
+    // ---
+    /// Highest extended sequence number received
+    pub extended_highest_sequence: u64,
+
+    /// Packets lost since reception (re)started: expected minus received
+    pub cumulative_lost: i64,
+
+    /// Packets expected since the previous report
+    pub expected_interval: u64,
+
+    /// Packets lost since the previous report; negative when packets
+    /// counted lost arrived after all
+    pub lost_interval: i64,
+
+    /// `lost_interval` as a fraction of `expected_interval`, in 256ths;
+    /// 0 when nothing was lost
+    pub fraction_lost: u8,
+}
+
+/// Point-in-time copy of the receiver counters.
+///
+/// All derived values (percentages, rates) are computed here rather than
+/// when packets are recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsSnapshot {
     // ---
     /// Total packets received successfully
     pub packets_received: u64,
@@ -25,17 +124,165 @@ pub struct ReceiverStats {
     /// Total packets that arrived too late (after playout deadline)
     pub packets_late: u64,
 
-    /// Last sequence number seen
-    last_sequence: Option<u16>,
+    /// Total duplicate packets discarded
+    pub packets_duplicate: u64,
 
-    /// Start time for rate calculations
+    /// Accepted packets with the RTP marker bit set, each the start of a
+    /// talkspurt
+    pub talkspurts: u64,
+
+    /// RFC 3550 interarrival jitter in RTP timestamp units
+    pub jitter_units: f64,
+
+    /// RFC 3550 interarrival jitter in milliseconds
+    pub jitter_ms: f64,
+
+    /// Canonical names the sources gave in RTCP SDES, by SSRC
+    pub cnames: BTreeMap<u32, String>,
+
+    /// RTP clock of the stream, once configured or detected
+    pub clock: Option<StreamClock>,
+
+    /// Time since the stats tracker was created
+    pub runtime: Duration,
+}
+
+impl StatsSnapshot {
+    // ---
+    /// Calculates packet loss percentage.
+    pub fn loss_percentage(&self) -> f64 {
+        // ---
+        let total = self.packets_received + self.packets_lost;
+        if total == 0 {
+            0.0
+        } else {
+            (self.packets_lost as f64 / total as f64) * 100.0
+        }
+    }
+
+    /// Calculates reorder percentage.
+    pub fn reorder_percentage(&self) -> f64 {
+        // ---
+        if self.packets_received == 0 {
+            0.0
+        } else {
+            (self.packets_reordered as f64 / self.packets_received as f64) * 100.0
+        }
+    }
+
+    /// Calculates packets per second reception rate.
+    pub fn packets_per_second(&self) -> f64 {
+        // ---
+        let elapsed = self.runtime.as_secs_f64();
+        if elapsed == 0.0 {
+            0.0
+        } else {
+            self.packets_received as f64 / elapsed
+        }
+    }
+
+    /// Formats the periodic stats log line, ending with the sources'
+    /// CNAMEs once any are known.
+    pub fn summary(&self) -> String {
+        // ---
+        let mut line = format!(
+            "RX Stats: {} pkts ({:.2} pkt/s), {:.2}% loss, {:.2}% reordered, {} late, {} dup, \
+             jitter {:.2}ms",
+            self.packets_received,
+            self.packets_per_second(),
+            self.loss_percentage(),
+            self.reorder_percentage(),
+            self.packets_late,
+            self.packets_duplicate,
+            self.jitter_ms
+        );
+        for (index, (ssrc, cname)) in self.cnames.iter().enumerate() {
+            let separator = if index == 0 { ", sources " } else { " " };
+            line.push_str(&format!("{separator}0x{ssrc:08X}={cname}"));
+        }
+        line
+    }
+
+    /// Formats the snapshot for the `/stats` endpoint: the summary line
+    /// without the sources, the stream clock, then one line per source
+    /// named in SDES.
+    pub fn details(&self) -> String {
+        // ---
+        let summary = StatsSnapshot {
+            cnames: BTreeMap::new(),
+            ..self.clone()
+        }
+        .summary();
+        let mut text = format!("{summary}\n");
+        match &self.clock {
+            Some(clock) => text.push_str(&format!(
+                "clock {}Hz, {:?} frames ({})\n",
+                clock.clock_rate,
+                clock.frame_duration,
+                if clock.detected {
+                    "detected"
+                } else {
+                    "configured"
+                }
+            )),
+            None => text.push_str("clock not yet detected\n"),
+        }
+        for (ssrc, cname) in &self.cnames {
+            text.push_str(&format!("ssrc 0x{ssrc:08X} cname {cname}\n"));
+        }
+        text
+    }
+}
+
+/// Cloneable, `Send` read handle onto a [`ReceiverStats`] recorder.
+#[derive(Debug, Clone)]
+pub struct StatsHandle {
+    // ---
+    counters: Arc<StatsCounters>,
     start_time: Instant,
+}
 
-    /// Last time stats were logged
-    last_log_time: Instant,
+impl StatsHandle {
+    // ---
+    /// Takes a snapshot of the current counters.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        // ---
+        let c = &self.counters;
+        StatsSnapshot {
+            packets_received: c.packets_received.load(Ordering::Relaxed),
+            packets_lost: c.packets_lost.load(Ordering::Relaxed),
+            packets_reordered: c.packets_reordered.load(Ordering::Relaxed),
+            packets_late: c.packets_late.load(Ordering::Relaxed),
+            packets_duplicate: c.packets_duplicate.load(Ordering::Relaxed),
+            talkspurts: c.talkspurts.load(Ordering::Relaxed),
+            jitter_units: f64::from_bits(c.jitter_units.load(Ordering::Relaxed)),
+            jitter_ms: f64::from_bits(c.jitter_ms.load(Ordering::Relaxed)),
+            cnames: c
+                .cnames
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+            clock: *c.clock.lock().unwrap_or_else(PoisonError::into_inner),
+            runtime: self.start_time.elapsed(),
+        }
+    }
+}
 
-    /// Interval between periodic logs
-    log_interval: Duration,
+/// Background task that logs stats snapshots on a fixed interval.
+///
+/// The task is aborted when this guard is dropped, so it never outlives the
+/// receive loop that owns it.
+#[derive(Debug)]
+pub struct StatsTicker {
+    // ---
+    task: JoinHandle<()>,
+}
+
+impl Drop for StatsTicker {
+    fn drop(&mut self) {
+        // ---
+        self.task.abort();
+    }
 }
 
 impl ReceiverStats {
@@ -44,25 +291,28 @@ impl ReceiverStats {
     ///
     /// # Arguments
     ///
-    /// * `log_interval` - How often to automatically log stats
+    /// * `log_interval` - How often the ticker task logs stats
     pub fn new(log_interval: Duration) -> Self {
         // ---
-        let now = Instant::now();
         Self {
-            packets_received: 0,
-            packets_lost: 0,
-            packets_reordered: 0,
-            packets_late: 0,
-            last_sequence: None,
-            start_time: now,
-            last_log_time: now,
+            counters: Arc::new(StatsCounters::default()),
+            sequence: SeqTracker::new(),
+            start_time: Instant::now(),
             log_interval,
+            transport: TransportHistory::default(),
+            jitter: None,
+            base_sequence: None,
+            received_since_base: 0,
+            reported: (0, 0),
+            history: ReceptionHistory::default(),
         }
     }
 
     /// Records a received packet.
     ///
     /// Detects loss based on sequence number gaps and tracks reordering.
+    /// This is the hot path: it performs no logging, formatting, or
+    /// floating-point work.
     ///
     /// # Arguments
     ///
@@ -70,76 +320,299 @@ impl ReceiverStats {
     /// * `was_reordered` - Whether packet arrived out of sequence
     pub fn record_packet(&mut self, sequence: u16, was_reordered: bool) {
         // ---
-        self.packets_received += 1;
+        self.record_packet_and_get_loss(sequence, was_reordered);
+    }
 
+    /// Records a received packet and returns how many packets were detected as lost
+    /// due to a sequence gap. A reordered packet (e.g. a retransmission)
+    /// takes itself back off the lost count.
+    pub fn record_packet_and_get_loss(&mut self, sequence: u16, was_reordered: bool) -> u64 {
+        // ---
+        let c = &self.counters;
+        c.packets_received.fetch_add(1, Ordering::Relaxed);
+
+        let update = self.sequence.update(sequence);
+        match update {
+            SeqUpdate::Restart { extended } => {
+                self.base_sequence = Some(extended);
+                self.received_since_base = 1;
+                self.reported = (0, 0);
+                self.history.restart(extended);
+            }
+            SeqUpdate::Jump => {}
+            SeqUpdate::Ahead { extended, .. } | SeqUpdate::Behind { extended } => {
+                self.received_since_base += 1;
+                self.history.record(extended, Reception::Received);
+            }
+        }
         if was_reordered {
-            self.packets_reordered += 1;
+            c.packets_reordered.fetch_add(1, Ordering::Relaxed);
+            // It fills a gap counted as lost when a later packet arrived
+            // (lost is expected minus received, RFC 3550 §6.4.1)
+            let _ = c
+                .packets_lost
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |lost| {
+                    lost.checked_sub(1)
+                });
+            return 0;
+        }
+
+        // Detect packet loss via sequence gaps. A jump too large to be
+        // loss is not counted; it starts a new baseline if the sender
+        // restarted
+        let SeqUpdate::Ahead { gap, .. } = update else {
+            return 0;
+        };
+        let lost = gap as u64 - 1;
+        if lost > 0 {
+            c.packets_lost.fetch_add(lost, Ordering::Relaxed);
         }
+        lost
+    }
 
-        // Detect packet loss via sequence gaps
-        if let Some(last_seq) = self.last_sequence {
-            let expected = last_seq.wrapping_add(1);
-            if sequence != expected && !was_reordered {
-                // Gap detected - calculate lost packets
-                let gap = sequence.wrapping_sub(expected);
-                self.packets_lost += gap as u64;
+    /// Records a packet with sequence number `sequence` that arrived too
+    /// late to be played. It stays counted lost, and is reported
+    /// discarded in extended reports.
+    pub fn record_late_packet(&mut self, sequence: u16) {
+        // ---
+        self.counters.packets_late.fetch_add(1, Ordering::Relaxed);
+        if let Some(extended) = self.sequence.extend(sequence) {
+            self.history.record(extended, Reception::Discarded);
+        }
+    }
+
+    /// Records a duplicate packet that was discarded.
+    pub fn record_duplicate_packet(&mut self) {
+        // ---
+        self.counters
+            .packets_duplicate
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records an accepted packet with the marker bit set, which starts a
+    /// talkspurt.
+    pub fn record_talkspurt(&mut self) {
+        // ---
+        self.counters.talkspurts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the arrival of a packet carrying transport-wide sequence
+    /// number `sequence`.
+    pub fn record_transport_sequence(&mut self, sequence: u16, arrival: Instant) {
+        // ---
+        self.transport.record(sequence, arrival);
+    }
+
+    /// Returns the recent transport-wide sequence numbers received and
+    /// when each arrived, in sequence order, for congestion control
+    /// feedback (see [`TransportHistory`]).
+    pub fn transport_feedback(&self) -> Vec<TransportArrival> {
+        // ---
+        self.transport.arrivals()
+    }
+
+    /// Updates the interarrival jitter with a packet stamped `timestamp`
+    /// arriving at `arrival`, in a stream clocked at `clock_rate` Hz.
+    ///
+    /// Kept apart from [`record_packet`](Self::record_packet), which does
+    /// no floating-point work. A change of clock rate restarts the
+    /// estimate.
+    ///
+    /// # Returns
+    ///
+    /// The updated estimate.
+    pub fn record_jitter(&mut self, timestamp: u32, arrival: Instant, clock_rate: u32) -> Duration {
+        // ---
+        let estimator = match &mut self.jitter {
+            Some(estimator) if estimator.clock_rate() == clock_rate => estimator,
+            jitter => jitter.insert(JitterEstimator::new(clock_rate)),
+        };
+        estimator.update(timestamp, arrival);
+
+        let c = &self.counters;
+        c.jitter_units
+            .store(estimator.jitter_units().to_bits(), Ordering::Relaxed);
+        c.jitter_ms
+            .store(estimator.jitter_ms().to_bits(), Ordering::Relaxed);
+        estimator.jitter()
+    }
+
+    /// Records the RTP clock the stream was configured with or detected to
+    /// have, for the `/stats` endpoint; `None` while a new stream's clock
+    /// is being detected.
+    pub fn record_clock(&mut self, clock: Option<StreamClock>) {
+        // ---
+        *self
+            .counters
+            .clock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = clock;
+    }
+
+    /// Records the canonical name a source gave in RTCP SDES. A new name
+    /// for an SSRC already named is logged as a warning: two senders
+    /// collided on the SSRC, or one restarted under another name.
+    ///
+    /// # Returns
+    ///
+    /// Whether the table changed.
+    pub fn record_cname(&mut self, ssrc: u32, cname: &str) -> bool {
+        // ---
+        let mut cnames = self
+            .counters
+            .cnames
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match cnames.insert(ssrc, cname.to_string()) {
+            None => {
+                info!("SSRC 0x{:08X} is CNAME {}", ssrc, cname);
+                true
+            }
+            Some(previous) if previous == cname => false,
+            Some(previous) => {
+                warn!(
+                    "SSRC 0x{:08X} changed CNAME from {} to {} (SSRC collision or sender restart?)",
+                    ssrc, previous, cname
+                );
+                true
             }
         }
+    }
+
+    /// Returns the statistics for a receiver report and starts the next
+    /// reporting interval. `None` before the first packet.
+    pub fn take_reception_report(&mut self) -> Option<ReceptionReport> {
+        // ---
+        let base = self.base_sequence?;
+        let highest = self.sequence.highest_extended()?;
+        let expected = (highest + 1).saturating_sub(base);
+        let received = self.received_since_base;
+
+        let (expected_prior, received_prior) =
+            std::mem::replace(&mut self.reported, (expected, received));
+        let expected_interval = expected.saturating_sub(expected_prior);
+        let lost_interval = expected_interval as i64 - (received - received_prior) as i64;
+        let fraction_lost = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval as i64).min(255) as u8
+        };
 
-        // Update last sequence only if not reordered (to maintain monotonic progression)
-        if !was_reordered {
-            self.last_sequence = Some(sequence);
+        Some(ReceptionReport {
+            extended_highest_sequence: highest,
+            cumulative_lost: expected as i64 - received as i64,
+            expected_interval,
+            lost_interval,
+            fraction_lost,
+        })
+    }
+
+    /// Returns an RTCP extended report on the stream from `ssrc` and
+    /// starts the next reporting interval: a Loss RLE block over the
+    /// sequence numbers since the previous report, late packets counted
+    /// received, and a VoIP Metrics block over the stream so far for a
+    /// receiving end with `delays`. `None` before the first packet.
+    ///
+    /// The report's own SSRC is left 0 for the caller to fill in.
+    pub fn take_extended_report(
+        &mut self,
+        ssrc: u32,
+        delays: &PlayoutDelays,
+    ) -> Option<ExtendedReport> {
+        // ---
+        let (begin, slots) = self.history.take()?;
+        let received: Vec<bool> = slots.iter().map(|&slot| slot != Reception::Lost).collect();
+        let loss = LossRle::encode(ssrc, begin as u16, &received);
+        let metrics = xr::voip_metrics(ssrc, self.history.tracker(), delays, None);
+
+        Some(ExtendedReport {
+            ssrc: 0,
+            blocks: vec![XrBlock::LossRle(loss), XrBlock::VoipMetrics(metrics)],
+        })
+    }
+
+    /// Returns the extended sequence numbers of the packets recorded.
+    pub fn sequence(&self) -> &SeqTracker {
+        // ---
+        &self.sequence
+    }
+
+    /// Forgets the last sequence seen after the stream jumped.
+    ///
+    /// The next recorded packet starts a new baseline, so a sequence
+    /// discontinuity (e.g. a sender restart) is not counted as loss.
+    pub fn resync(&mut self) {
+        // ---
+        self.sequence = SeqTracker::new();
+        self.base_sequence = None;
+        if let Some(jitter) = &mut self.jitter {
+            jitter.reset();
         }
+    }
 
-        // Periodic logging
-        self.maybe_log();
+    /// Returns a read handle for snapshotting from another task.
+    pub fn handle(&self) -> StatsHandle {
+        // ---
+        StatsHandle {
+            counters: Arc::clone(&self.counters),
+            start_time: self.start_time,
+        }
     }
 
-    /// Records a received packet and returns how many packets were detected as lost
-    /// due to a sequence gap.
-    pub fn record_packet_and_get_loss(&mut self, sequence: u16, was_reordered: bool) -> u64 {
+    /// Takes a snapshot of the current counters.
+    pub fn snapshot(&self) -> StatsSnapshot {
         // ---
-        let before = self.packets_lost;
-        self.record_packet(sequence, was_reordered);
-        self.packets_lost.saturating_sub(before)
+        self.handle().snapshot()
     }
 
-    /// Records a packet that arrived too late to be played.
-    pub fn record_late_packet(&mut self) {
+    /// Spawns the periodic logging task for this tracker.
+    ///
+    /// Must be called from within a tokio runtime. The returned guard stops
+    /// the task when dropped.
+    pub fn spawn_ticker(&self) -> StatsTicker {
         // ---
-        self.packets_late += 1;
+        let handle = self.handle();
+        let period = self.log_interval;
+        let task = tokio::spawn(async move {
+            // ---
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                ticker.tick().await;
+                info!("{}", handle.snapshot().summary());
+            }
+        });
+
+        StatsTicker { task }
     }
 
     /// Calculates current packet loss percentage.
     pub fn loss_percentage(&self) -> f64 {
         // ---
-        let total = self.packets_received + self.packets_lost;
-        if total == 0 {
-            0.0
-        } else {
-            (self.packets_lost as f64 / total as f64) * 100.0
-        }
+        self.snapshot().loss_percentage()
     }
 
     /// Calculates reorder percentage.
     pub fn reorder_percentage(&self) -> f64 {
         // ---
-        if self.packets_received == 0 {
-            0.0
-        } else {
-            (self.packets_reordered as f64 / self.packets_received as f64) * 100.0
-        }
+        self.snapshot().reorder_percentage()
     }
 
     /// Calculates packets per second reception rate.
     pub fn packets_per_second(&self) -> f64 {
         // ---
-        let elapsed = self.start_time.elapsed().as_secs_f64();
-        if elapsed == 0.0 {
-            0.0
-        } else {
-            self.packets_received as f64 / elapsed
-        }
+        self.snapshot().packets_per_second()
+    }
+
+    /// Returns the interarrival jitter in RTP timestamp units.
+    pub fn jitter_units(&self) -> f64 {
+        // ---
+        self.snapshot().jitter_units
+    }
+
+    /// Returns the interarrival jitter in milliseconds.
+    pub fn jitter_ms(&self) -> f64 {
+        // ---
+        self.snapshot().jitter_ms
     }
 
     /// Returns runtime duration.
@@ -148,36 +621,483 @@ impl ReceiverStats {
         self.start_time.elapsed()
     }
 
-    /// Logs statistics if interval has elapsed.
-    fn maybe_log(&mut self) {
+    /// Force log current statistics.
+    pub(crate) fn log(&self) {
+        // ---
+        info!("{}", self.snapshot().summary());
+    }
+}
+
+impl Default for ReceiverStats {
+    fn default() -> Self {
+        // ---
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+/// Default number of events the playout history keeps
+pub const DEFAULT_HISTORY_CAPACITY: usize = 16_384;
+
+/// Default span of history written by a dump
+pub const DEFAULT_HISTORY_WINDOW: Duration = Duration::from_secs(30);
+
+/// Default spacing of jitter buffer occupancy samples
+pub const DEFAULT_OCCUPANCY_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Kind of playout pipeline event kept in a [`PlayoutHistory`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayoutEventKind {
+    // ---
+    /// Packet arrived; value is the payload size in bytes
+    Arrival,
+
+    /// Packet left the jitter buffer for decode; value is the time it was
+    /// buffered, in microseconds
+    Release,
+
+    /// Frame was concealed; sequence is the packet that revealed the gap
+    Concealment,
+
+    /// Output device ran dry mid-playout; value is the samples of silence
+    /// inserted
+    Underrun,
+
+    /// Jitter buffer occupancy sample; value is the buffered packet count
+    Occupancy,
+}
+
+impl PlayoutEventKind {
+    // ---
+    /// Every kind, indexed by its packed code.
+    pub const ALL: [PlayoutEventKind; 5] = [
+        PlayoutEventKind::Arrival,
+        PlayoutEventKind::Release,
+        PlayoutEventKind::Concealment,
+        PlayoutEventKind::Underrun,
+        PlayoutEventKind::Occupancy,
+    ];
+
+    /// Returns the name used in history snapshots.
+    pub fn as_str(&self) -> &'static str {
         // ---
-        if self.last_log_time.elapsed() >= self.log_interval {
-            self.log();
-            self.last_log_time = Instant::now();
+        match self {
+            PlayoutEventKind::Arrival => "arrival",
+            PlayoutEventKind::Release => "release",
+            PlayoutEventKind::Concealment => "concealment",
+            PlayoutEventKind::Underrun => "underrun",
+            PlayoutEventKind::Occupancy => "occupancy",
         }
     }
+}
 
-    /// Force log current statistics.
-    pub fn log(&self) {
+/// One playout pipeline event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayoutEvent {
+    // ---
+    /// Wall-clock time, in microseconds since the Unix epoch
+    pub unix_us: u64,
+
+    /// What happened
+    pub kind: PlayoutEventKind,
+
+    /// RTP sequence number involved, if any
+    pub sequence: Option<u16>,
+
+    /// Kind-specific value (see [`PlayoutEventKind`])
+    pub value: u32,
+}
+
+impl PlayoutEvent {
+    // ---
+    /// Packs kind, sequence, and value into one word.
+    fn pack(&self) -> u64 {
         // ---
-        info!(
-            "RX Stats: {} pkts ({:.2} pkt/s), {:.2}% loss, {:.2}% reordered, {} late",
-            self.packets_received,
-            self.packets_per_second(),
-            self.loss_percentage(),
-            self.reorder_percentage(),
-            self.packets_late
+        let sequence = match self.sequence {
+            Some(sequence) => (1 << 16) | sequence as u64,
+            None => 0,
+        };
+        ((self.kind as u64) << 56) | (sequence << 32) | self.value as u64
+    }
+
+    /// Reverses [`pack`](Self::pack).
+    fn unpack(unix_us: u64, word: u64) -> Option<Self> {
+        // ---
+        let kind = *PlayoutEventKind::ALL.get((word >> 56) as usize)?;
+        let sequence = ((word >> 48) & 1 == 1).then_some((word >> 32) as u16);
+        Some(Self {
+            unix_us,
+            kind,
+            sequence,
+            value: word as u32,
+        })
+    }
+}
+
+/// One ring entry, guarded by a per-slot sequence lock.
+///
+/// `version` is odd while a write is in progress and `2 * index + 2` once
+/// the event with ring index `index` is complete, so readers can detect
+/// torn or overwritten slots without blocking writers.
+#[derive(Debug, Default)]
+struct HistorySlot {
+    // ---
+    version: AtomicU64,
+    unix_us: AtomicU64,
+    event: AtomicU64,
+}
+
+/// Bounded in-memory history of playout pipeline events.
+///
+/// Keeps the last `capacity` packet arrivals, jitter buffer releases,
+/// concealments, device underruns, and occupancy samples so a glitch
+/// reported after the fact ("around 14:32:05") can be diagnosed from a
+/// [`HistorySnapshot`]. Memory is fixed at construction; the oldest events
+/// are overwritten.
+///
+/// Recording takes `&self` and is lock-free: an atomic index claims a slot
+/// and a per-slot sequence lock publishes it, so the receive loop and the
+/// audio device callback can both record through a shared `Arc` while a
+/// snapshot is being taken.
+pub struct PlayoutHistory {
+    // ---
+    slots: Box<[HistorySlot]>,
+
+    /// Ring index of the next event
+    head: AtomicU64,
+
+    /// Time of the last occupancy sample, in microseconds since the epoch
+    last_occupancy_us: AtomicU64,
+
+    /// Minimum spacing of occupancy samples, in microseconds
+    occupancy_interval_us: u64,
+
+    /// Underruns recorded so far, for burst detection
+    underruns: AtomicU64,
+}
+
+impl std::fmt::Debug for PlayoutHistory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ---
+        f.debug_struct("PlayoutHistory")
+            .field("capacity", &self.capacity())
+            .field("recorded", &self.recorded())
+            .finish()
+    }
+}
+
+impl PlayoutHistory {
+    // ---
+    /// Creates a history holding up to `capacity` events, sampling jitter
+    /// buffer occupancy every [`DEFAULT_OCCUPANCY_SAMPLE_INTERVAL`].
+    pub fn new(capacity: usize) -> Self {
+        // ---
+        let slots = (0..capacity.max(1))
+            .map(|_| HistorySlot::default())
+            .collect();
+        Self {
+            slots,
+            head: AtomicU64::new(0),
+            last_occupancy_us: AtomicU64::new(0),
+            occupancy_interval_us: DEFAULT_OCCUPANCY_SAMPLE_INTERVAL.as_micros() as u64,
+            underruns: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of events the ring holds.
+    pub fn capacity(&self) -> usize {
+        // ---
+        self.slots.len()
+    }
+
+    /// Returns the number of events recorded since creation, including
+    /// ones since overwritten.
+    pub fn recorded(&self) -> u64 {
+        // ---
+        self.head.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of underruns recorded since creation.
+    pub fn underruns(&self) -> u64 {
+        // ---
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Records an event at the current wall-clock time.
+    pub fn record(&self, kind: PlayoutEventKind, sequence: Option<u16>, value: u32) {
+        // ---
+        self.record_at(unix_micros(SystemTime::now()), kind, sequence, value);
+    }
+
+    /// Records an event with an explicit timestamp.
+    pub fn record_at(
+        &self,
+        unix_us: u64,
+        kind: PlayoutEventKind,
+        sequence: Option<u16>,
+        value: u32,
+    ) {
+        // ---
+        if kind == PlayoutEventKind::Underrun {
+            self.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let event = PlayoutEvent {
+            unix_us,
+            kind,
+            sequence,
+            value,
+        };
+        let index = self.head.fetch_add(1, Ordering::AcqRel);
+        let slot = &self.slots[(index % self.slots.len() as u64) as usize];
+
+        slot.version.store(2 * index + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        slot.unix_us.store(unix_us, Ordering::Relaxed);
+        slot.event.store(event.pack(), Ordering::Relaxed);
+        slot.version.store(2 * index + 2, Ordering::Release);
+    }
+
+    /// Records a jitter buffer occupancy sample, unless one was recorded
+    /// less than the sample interval ago.
+    pub fn sample_occupancy(&self, buffered_packets: usize) {
+        // ---
+        let now_us = unix_micros(SystemTime::now());
+        let last = self.last_occupancy_us.load(Ordering::Relaxed);
+        if now_us.saturating_sub(last) < self.occupancy_interval_us {
+            return;
+        }
+        if self
+            .last_occupancy_us
+            .compare_exchange(last, now_us, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+        {
+            let packets = buffered_packets.min(u32::MAX as usize) as u32;
+            self.record_at(now_us, PlayoutEventKind::Occupancy, None, packets);
+        }
+    }
+
+    /// Copies out the events of the last `window`, oldest first.
+    ///
+    /// Slots being written or overwritten while the snapshot is taken are
+    /// skipped rather than waited for.
+    pub fn snapshot(&self, window: Duration, reason: DumpReason) -> HistorySnapshot {
+        // ---
+        let taken_at = SystemTime::now();
+        let since_us = unix_micros(taken_at).saturating_sub(window.as_micros() as u64);
+        let head = self.head.load(Ordering::Acquire);
+        let start = head.saturating_sub(self.slots.len() as u64);
+
+        let mut events = Vec::new();
+        for index in start..head {
+            let slot = &self.slots[(index % self.slots.len() as u64) as usize];
+            let version = slot.version.load(Ordering::Acquire);
+            let unix_us = slot.unix_us.load(Ordering::Relaxed);
+            let word = slot.event.load(Ordering::Relaxed);
+            fence(Ordering::Acquire);
+            if version != 2 * index + 2 || slot.version.load(Ordering::Relaxed) != version {
+                continue;
+            }
+            if unix_us < since_us {
+                continue;
+            }
+            if let Some(event) = PlayoutEvent::unpack(unix_us, word) {
+                events.push(event);
+            }
+        }
+
+        HistorySnapshot {
+            reason,
+            taken_at,
+            window,
+            overwritten: start,
+            events,
+        }
+    }
+}
+
+/// What triggered a history dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpReason {
+    // ---
+    /// Requested over the control socket
+    Command,
+
+    /// Underrun burst detected
+    UnderrunBurst,
+}
+
+impl DumpReason {
+    // ---
+    /// Returns the name used in snapshots and file names.
+    pub fn as_str(&self) -> &'static str {
+        // ---
+        match self {
+            DumpReason::Command => "command",
+            DumpReason::UnderrunBurst => "underrun_burst",
+        }
+    }
+}
+
+/// Events copied out of a [`PlayoutHistory`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistorySnapshot {
+    // ---
+    /// What triggered the snapshot
+    pub reason: DumpReason,
+
+    /// When the snapshot was taken
+    pub taken_at: SystemTime,
+
+    /// Span of history requested
+    pub window: Duration,
+
+    /// Events recorded before the oldest one still in the ring
+    pub overwritten: u64,
+
+    /// Events in the window, oldest first
+    pub events: Vec<PlayoutEvent>,
+}
+
+impl HistorySnapshot {
+    // ---
+    /// Returns the snapshot as compact JSON.
+    ///
+    /// Events are arrays in the order given by `"fields"`, with `null` for
+    /// a missing sequence number:
+    ///
+    /// ```text
+    /// {"reason":"command","taken_at_unix_us":...,"window_ms":30000,
+    ///  "overwritten":0,"fields":["unix_us","kind","seq","value"],
+    ///  "events":[[1760000000123456,"arrival",812,61],...]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        // ---
+        use std::fmt::Write;
+
+        let mut json = String::with_capacity(128 + self.events.len() * 40);
+        let _ = write!(
+            json,
+            "{{\"reason\":\"{}\",\"taken_at_unix_us\":{},\"window_ms\":{},\"overwritten\":{},\
+             \"fields\":[\"unix_us\",\"kind\",\"seq\",\"value\"],\"events\":[",
+            self.reason.as_str(),
+            unix_micros(self.taken_at),
+            self.window.as_millis(),
+            self.overwritten
         );
+        for (i, event) in self.events.iter().enumerate() {
+            let separator = if i == 0 { "" } else { "," };
+            let _ = match event.sequence {
+                Some(sequence) => write!(
+                    json,
+                    "{separator}[{},\"{}\",{},{}]",
+                    event.unix_us,
+                    event.kind.as_str(),
+                    sequence,
+                    event.value
+                ),
+                None => write!(
+                    json,
+                    "{separator}[{},\"{}\",null,{}]",
+                    event.unix_us,
+                    event.kind.as_str(),
+                    event.value
+                ),
+            };
+        }
+        json.push_str("]}\n");
+        json
+    }
+
+    /// Writes the snapshot to `dir` as
+    /// `playout-history-<unix ms>-<reason>.json`, creating the directory if
+    /// needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the directory or file cannot be written.
+    pub fn write_to(&self, dir: &Path) -> anyhow::Result<PathBuf> {
+        // ---
+        use anyhow::Context;
+
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create history directory {}", dir.display()))?;
+        let path = dir.join(format!(
+            "playout-history-{}-{}.json",
+            unix_micros(self.taken_at) / 1000,
+            self.reason.as_str()
+        ));
+        std::fs::write(&path, self.to_json())
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
     }
 }
 
-impl Default for ReceiverStats {
-    fn default() -> Self {
+/// Detects bursts of output underruns worth an automatic history dump.
+///
+/// Fires when at least `threshold` underruns fall within `window`, then
+/// stays quiet for `cooldown` so one bad stretch produces one dump.
+#[derive(Debug, Clone)]
+pub struct UnderrunBurstDetector {
+    // ---
+    threshold: usize,
+    window: Duration,
+    cooldown: Duration,
+    recent: VecDeque<Instant>,
+    last_fired: Option<Instant>,
+}
+
+impl UnderrunBurstDetector {
+    // ---
+    /// Creates a detector firing on `threshold` underruns within `window`.
+    pub fn new(threshold: usize, window: Duration, cooldown: Duration) -> Self {
         // ---
-        Self::new(Duration::from_secs(5))
+        Self {
+            threshold: threshold.max(1),
+            window,
+            cooldown,
+            recent: VecDeque::new(),
+            last_fired: None,
+        }
+    }
+
+    /// Records `count` new underruns at `now`.
+    ///
+    /// Returns `true` if they complete a burst and the cooldown has passed.
+    pub fn observe(&mut self, count: u64, now: Instant) -> bool {
+        // ---
+        for _ in 0..count.min(self.threshold as u64) {
+            self.recent.push_back(now);
+        }
+        while self.recent.len() > self.threshold {
+            self.recent.pop_front();
+        }
+        while self
+            .recent
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > self.window)
+        {
+            self.recent.pop_front();
+        }
+
+        let cooled_down = self
+            .last_fired
+            .is_none_or(|fired| now.duration_since(fired) >= self.cooldown);
+        if self.recent.len() >= self.threshold && cooled_down {
+            self.last_fired = Some(now);
+            self.recent.clear();
+            return true;
+        }
+        false
     }
 }
 
+/// Returns `time` in microseconds since the Unix epoch (0 before it).
+fn unix_micros(time: SystemTime) -> u64 {
+    // ---
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_micros() as u64)
+}
+
 #[cfg(test)]
 mod tests {
     // ---
@@ -192,8 +1112,8 @@ mod tests {
         stats.record_packet(1, false);
         stats.record_packet(2, false);
 
-        assert_eq!(stats.packets_received, 3);
-        assert_eq!(stats.packets_lost, 0);
+        assert_eq!(stats.snapshot().packets_received, 3);
+        assert_eq!(stats.snapshot().packets_lost, 0);
         assert_eq!(stats.loss_percentage(), 0.0);
     }
 
@@ -206,8 +1126,8 @@ mod tests {
         stats.record_packet(1, false);
         stats.record_packet(5, false); // Gap: lost 2, 3, 4
 
-        assert_eq!(stats.packets_received, 3);
-        assert_eq!(stats.packets_lost, 3); // Packets 2, 3, 4
+        assert_eq!(stats.snapshot().packets_received, 3);
+        assert_eq!(stats.snapshot().packets_lost, 3); // Packets 2, 3, 4
         assert_eq!(stats.loss_percentage(), 50.0); // 3 lost out of 6 total
     }
 
@@ -220,8 +1140,8 @@ mod tests {
         stats.record_packet(2, false);
         stats.record_packet(1, true); // Out of order
 
-        assert_eq!(stats.packets_received, 3);
-        assert_eq!(stats.packets_reordered, 1);
+        assert_eq!(stats.snapshot().packets_received, 3);
+        assert_eq!(stats.snapshot().packets_reordered, 1);
 
         // Use approximate equality for floating point
         let expected = 100.0 / 3.0;
@@ -243,8 +1163,91 @@ mod tests {
         stats.record_packet(65535, false);
         stats.record_packet(0, false); // Wraparound
 
-        assert_eq!(stats.packets_received, 3);
-        assert_eq!(stats.packets_lost, 0);
+        assert_eq!(stats.snapshot().packets_received, 3);
+        assert_eq!(stats.snapshot().packets_lost, 0);
+        assert_eq!(stats.sequence().highest_extended(), Some(65_536));
+    }
+
+    #[test]
+    fn test_sender_restart_is_not_counted_as_loss() {
+        // ---
+        let mut stats = ReceiverStats::default();
+
+        for sequence in 100..110 {
+            stats.record_packet(sequence, false);
+        }
+
+        // The sender restarts at a random sequence number
+        assert_eq!(stats.record_packet_and_get_loss(40_000, false), 0);
+        assert_eq!(stats.record_packet_and_get_loss(40_001, false), 0);
+        assert_eq!(stats.record_packet_and_get_loss(40_003, false), 1);
+
+        assert_eq!(stats.snapshot().packets_lost, 1);
+        assert_eq!(stats.sequence().highest_extended(), Some(40_003));
+    }
+
+    #[test]
+    fn test_reception_report_intervals() {
+        // ---
+        let mut stats = ReceiverStats::default();
+        assert_eq!(stats.take_reception_report(), None);
+
+        // 65530..=65539 across the wrap, with two lost
+        for sequence in (65_530u32..65_540).filter(|s| *s != 65_533 && *s != 65_537) {
+            stats.record_packet(sequence as u16, false);
+        }
+        let first = stats.take_reception_report().unwrap();
+        assert_eq!(first.extended_highest_sequence, 65_539);
+        assert_eq!(first.cumulative_lost, 2);
+        assert_eq!((first.expected_interval, first.lost_interval), (10, 2));
+        assert_eq!(first.fraction_lost, 51); // 2/10 in 256ths
+
+        // A late arrival of one of them makes the next interval negative
+        stats.record_packet(65_533u32 as u16, true);
+        for sequence in 65_540u32..65_544 {
+            stats.record_packet(sequence as u16, false);
+        }
+        let second = stats.take_reception_report().unwrap();
+        assert_eq!(second.cumulative_lost, 1);
+        assert_eq!((second.expected_interval, second.lost_interval), (4, -1));
+        assert_eq!(second.fraction_lost, 0);
+
+        // Nothing since: nothing expected, nothing lost
+        let third = stats.take_reception_report().unwrap();
+        assert_eq!((third.expected_interval, third.fraction_lost), (0, 0));
+        assert_eq!(third.cumulative_lost, 1);
+    }
+
+    #[test]
+    fn test_extended_report_marks_late_packets_discarded() {
+        // ---
+        let mut stats = ReceiverStats::default();
+        let delays = PlayoutDelays::new(
+            &crate::JitterBufferConfig::default(),
+            Duration::from_millis(20),
+        );
+        assert!(stats.take_extended_report(0x2076, &delays).is_none());
+
+        for seq in [10, 11, 13, 15, 16] {
+            stats.record_packet(seq, false);
+        }
+        stats.record_late_packet(12);
+        let report = stats.take_extended_report(0x2076, &delays).unwrap();
+        let [XrBlock::LossRle(loss), XrBlock::VoipMetrics(voip)] = report.blocks.as_slice() else {
+            panic!("unexpected blocks: {:?}", report.blocks);
+        };
+        assert_eq!((loss.begin_sequence, loss.end_sequence), (10, 17));
+        assert_eq!(loss.received(), [true, true, true, true, false, true, true]);
+        assert_eq!(voip.loss_rate, 36); // 1 of 7 in 256ths
+        assert_eq!(voip.discard_rate, 36);
+
+        // The next report starts where this one ended
+        stats.record_packet(17, false);
+        let report = stats.take_extended_report(0x2076, &delays).unwrap();
+        let XrBlock::LossRle(loss) = &report.blocks[0] else {
+            panic!("no Loss RLE block");
+        };
+        assert_eq!((loss.begin_sequence, loss.received()), (17, vec![true]));
     }
 
     #[test]
@@ -253,9 +1256,307 @@ mod tests {
         let mut stats = ReceiverStats::default();
 
         stats.record_packet(0, false);
-        stats.record_late_packet();
-        stats.record_late_packet();
+        stats.record_late_packet(0);
+        stats.record_late_packet(0);
+
+        assert_eq!(stats.snapshot().packets_late, 2);
+    }
+
+    #[test]
+    fn test_duplicates_counted_separately_from_late() {
+        // ---
+        let mut stats = ReceiverStats::default();
+
+        stats.record_packet(0, false);
+        stats.record_duplicate_packet();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.packets_duplicate, 1);
+        assert_eq!(snapshot.packets_late, 0);
+    }
+
+    #[test]
+    fn test_resync_does_not_count_jump_as_loss() {
+        // ---
+        let mut stats = ReceiverStats::default();
+
+        stats.record_packet(0, false);
+        stats.resync();
+        assert_eq!(stats.record_packet_and_get_loss(5000, false), 0);
+        assert_eq!(stats.record_packet_and_get_loss(5002, false), 1);
+    }
+
+    #[test]
+    fn test_reordered_gap_does_not_count_loss() {
+        // ---
+        let mut stats = ReceiverStats::default();
+
+        assert_eq!(stats.record_packet_and_get_loss(0, false), 0);
+        assert_eq!(stats.record_packet_and_get_loss(3, false), 2);
+        assert_eq!(stats.record_packet_and_get_loss(1, true), 0);
+        assert_eq!(stats.record_packet_and_get_loss(4, false), 0);
+
+        // The late arrival is no longer counted as lost
+        assert_eq!(stats.snapshot().packets_lost, 1);
+    }
+
+    #[test]
+    fn test_summary_line_format() {
+        // ---
+        let snapshot = StatsSnapshot {
+            packets_received: 90,
+            packets_lost: 10,
+            packets_reordered: 9,
+            packets_late: 2,
+            packets_duplicate: 1,
+            talkspurts: 3,
+            jitter_units: 120.0,
+            jitter_ms: 2.5,
+            cnames: BTreeMap::new(),
+            clock: None,
+            runtime: Duration::from_secs(2),
+        };
+
+        assert_eq!(
+            snapshot.summary(),
+            "RX Stats: 90 pkts (45.00 pkt/s), 10.00% loss, 10.00% reordered, 2 late, 1 dup, \
+             jitter 2.50ms"
+        );
+    }
+
+    #[test]
+    fn test_cname_table_updates() {
+        // ---
+        let mut stats = ReceiverStats::default();
+        let handle = stats.handle();
+
+        assert!(stats.record_cname(0x2222_2222, "bob@host-b"));
+        assert!(stats.record_cname(0x1111_1111, "alice@host-a"));
+        assert!(!stats.record_cname(0x1111_1111, "alice@host-a"));
+
+        // A new name for a known SSRC replaces the old one
+        assert!(stats.record_cname(0x2222_2222, "carol@host-c"));
+
+        let snapshot = handle.snapshot();
+        assert_eq!(
+            snapshot.cnames.into_iter().collect::<Vec<_>>(),
+            [
+                (0x1111_1111, "alice@host-a".to_string()),
+                (0x2222_2222, "carol@host-c".to_string())
+            ]
+        );
+        let snapshot = stats.snapshot();
+        assert!(snapshot
+            .summary()
+            .ends_with("jitter 0.00ms, sources 0x11111111=alice@host-a 0x22222222=carol@host-c"));
+        assert_eq!(
+            snapshot.details().lines().skip(2).collect::<Vec<_>>(),
+            [
+                "ssrc 0x11111111 cname alice@host-a",
+                "ssrc 0x22222222 cname carol@host-c"
+            ]
+        );
+        assert!(!snapshot
+            .details()
+            .lines()
+            .next()
+            .unwrap()
+            .contains("sources"));
+    }
+
+    #[test]
+    fn test_details_show_stream_clock() {
+        // ---
+        let mut stats = ReceiverStats::default();
+        let handle = stats.handle();
+        let clock_line =
+            |snapshot: StatsSnapshot| snapshot.details().lines().nth(1).unwrap().to_string();
+        assert_eq!(clock_line(handle.snapshot()), "clock not yet detected");
+
+        stats.record_clock(Some(StreamClock {
+            clock_rate: 16000,
+            frame_duration: Duration::from_millis(20),
+            detected: false,
+        }));
+        assert_eq!(
+            clock_line(handle.snapshot()),
+            "clock 16000Hz, 20ms frames (configured)"
+        );
+
+        let detected = StreamClock::infer(120).unwrap();
+        stats.record_clock(Some(detected));
+        assert_eq!(handle.snapshot().clock, Some(detected));
+        assert_eq!(
+            clock_line(handle.snapshot()),
+            "clock 48000Hz, 2.5ms frames (detected)"
+        );
+    }
+
+    #[test]
+    fn test_handle_sees_recorded_counts() {
+        // ---
+        let mut stats = ReceiverStats::default();
+        let handle = stats.handle();
+
+        stats.record_packet(0, false);
+        stats.record_packet(2, false);
+        stats.record_late_packet(1);
+
+        let snapshot = handle.snapshot();
+        assert_eq!(snapshot.packets_received, 2);
+        assert_eq!(snapshot.packets_lost, 1);
+        assert_eq!(snapshot.packets_late, 1);
+        assert_eq!(snapshot.loss_percentage(), stats.loss_percentage());
+    }
+
+    #[test]
+    fn test_jitter_published_in_units_and_ms() {
+        // ---
+        let mut stats = ReceiverStats::default();
+        let handle = stats.handle();
+        let t0 = Instant::now();
+
+        // Every other packet arrives 10ms late
+        for i in 0..200u32 {
+            let offset = 20 * i as u64 + 10 * (i % 2) as u64;
+            stats.record_jitter(i * 320, t0 + Duration::from_millis(offset), 16_000);
+        }
+
+        let snapshot = handle.snapshot();
+        assert!((snapshot.jitter_ms - 10.0).abs() < 0.01);
+        assert!((snapshot.jitter_units - 160.0).abs() < 0.1);
+        assert_eq!(stats.jitter_ms(), snapshot.jitter_ms);
+        assert!(snapshot.summary().ends_with(", jitter 10.00ms"));
+
+        // A new stream, or a new clock rate, starts over
+        stats.resync();
+        stats.record_jitter(0, t0, 16_000);
+        assert_eq!(stats.snapshot().jitter_units, 0.0);
+        stats.record_jitter(320, t0 + Duration::from_millis(30), 16_000);
+        stats.record_jitter(960, t0 + Duration::from_millis(60), 48_000);
+        assert_eq!(stats.jitter_units(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_ticker_stops_on_drop() {
+        // ---
+        let stats = ReceiverStats::new(Duration::from_millis(10));
+        let ticker = stats.spawn_ticker();
+        tokio::time::sleep(Duration::from_millis(35)).await;
+
+        assert!(!ticker.task.is_finished());
+        let task = ticker.task.abort_handle();
+        drop(ticker);
+        tokio::task::yield_now().await;
+        assert!(task.is_finished());
+    }
+
+    #[test]
+    fn test_history_keeps_last_capacity_events() {
+        // ---
+        let history = PlayoutHistory::new(8);
+        for seq in 0..20u16 {
+            history.record(PlayoutEventKind::Arrival, Some(seq), seq as u32 * 3);
+        }
+        history.record(PlayoutEventKind::Underrun, None, 160);
+
+        let snapshot = history.snapshot(Duration::from_secs(60), DumpReason::Command);
+        assert_eq!(snapshot.events.len(), 8);
+        assert_eq!(snapshot.overwritten, 13);
+        assert_eq!(history.recorded(), 21);
+        assert_eq!(history.underruns(), 1);
+
+        let sequences: Vec<Option<u16>> = snapshot.events.iter().map(|e| e.sequence).collect();
+        let expected: Vec<Option<u16>> = (13..20).map(Some).chain([None]).collect();
+        assert_eq!(sequences, expected);
+        assert_eq!(snapshot.events[0].value, 39);
+        assert_eq!(snapshot.events[7].kind, PlayoutEventKind::Underrun);
+    }
+
+    #[test]
+    fn test_history_snapshot_window_and_json() {
+        // ---
+        let history = PlayoutHistory::new(1000);
+        let now_us = unix_micros(SystemTime::now());
+        let old_us = now_us - 120_000_000;
+        history.record_at(old_us, PlayoutEventKind::Arrival, Some(1), 60);
+        history.record_at(now_us, PlayoutEventKind::Release, Some(2), 40_000);
+        history.record_at(now_us, PlayoutEventKind::Concealment, Some(4), 0);
+        history.record_at(now_us, PlayoutEventKind::Occupancy, None, 3);
+
+        let snapshot = history.snapshot(Duration::from_secs(30), DumpReason::UnderrunBurst);
+        assert_eq!(snapshot.events.len(), 3);
+        let json = snapshot.to_json();
+        assert!(json.starts_with("{\"reason\":\"underrun_burst\","));
+        assert!(json.contains("\"window_ms\":30000,\"overwritten\":0,"));
+        assert!(json.contains(&format!("[{now_us},\"release\",2,40000]")));
+        assert!(json.contains(&format!("[{now_us},\"occupancy\",null,3]")));
+        assert!(!json.contains("\"arrival\""));
+
+        // The dump is bounded by the ring, whatever was recorded
+        let history = PlayoutHistory::new(100);
+        for seq in 0..10_000u32 {
+            history.record(PlayoutEventKind::Release, Some(seq as u16), u32::MAX - seq);
+        }
+        let dir = std::env::temp_dir().join(format!("stats-history-test-{}", std::process::id()));
+        let path = history
+            .snapshot(Duration::from_secs(60), DumpReason::Command)
+            .write_to(&dir)
+            .unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.matches("\"release\"").count(), 100);
+        assert!(written.len() < 256 + 100 * 48, "{} bytes", written.len());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_history_concurrent_writers_never_tear() {
+        // ---
+        let history = Arc::new(PlayoutHistory::new(256));
+        let writers: Vec<_> = (0..4u32)
+            .map(|writer| {
+                let history = history.clone();
+                std::thread::spawn(move || {
+                    for i in 0..20_000u32 {
+                        let seq = (writer * 20_000 + i) as u16;
+                        history.record(PlayoutEventKind::Arrival, Some(seq), !(seq as u32));
+                    }
+                })
+            })
+            .collect();
+
+        while !writers.iter().all(|w| w.is_finished()) {
+            let snapshot = history.snapshot(Duration::from_secs(60), DumpReason::Command);
+            assert!(snapshot.events.len() <= 256);
+            for event in &snapshot.events {
+                assert_eq!(event.value, !(event.sequence.unwrap() as u32));
+            }
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(history.recorded(), 80_000);
+    }
+
+    #[test]
+    fn test_underrun_burst_detector() {
+        // ---
+        let start = Instant::now();
+        let ms = |ms: u64| start + Duration::from_millis(ms);
+        let mut detector =
+            UnderrunBurstDetector::new(3, Duration::from_secs(1), Duration::from_secs(10));
+
+        // Spread-out underruns are not a burst
+        assert!(!detector.observe(1, ms(0)));
+        assert!(!detector.observe(1, ms(1500)));
+        assert!(!detector.observe(1, ms(3000)));
+
+        // Three within a second are, once
+        assert!(!detector.observe(2, ms(4100)));
+        assert!(detector.observe(1, ms(4200)));
+        assert!(!detector.observe(5, ms(4500)));
 
-        assert_eq!(stats.packets_late, 2);
+        // After the cooldown a new burst fires again
+        assert!(detector.observe(3, ms(14_200)));
     }
 }
@@ -0,0 +1,189 @@
+//! RTP over TCP for [`RtpReceiver`](crate::RtpReceiver).
+//!
+//! The receiver listens for the sender's connection and splits the byte
+//! stream back into packets by their RFC 4571 length prefixes (see
+//! [`complete_frame`]). Reads return whatever has arrived, so bytes are
+//! kept until their frame is complete. When the connection closes, the
+//! receiver waits for the next one; a new connection also replaces the
+//! current one, since a sender only reconnects after losing the old one.
+
+use rtp_opus_common::{complete_frame, FRAME_HEADER_LEN};
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+/// Bytes read from the connection at a time
+const READ_CHUNK: usize = 4096;
+
+/// Listening socket and the connection being read.
+#[derive(Debug)]
+pub(crate) struct TcpIncoming {
+    // ---
+    listener: TcpListener,
+
+    /// Current connection and its peer
+    connection: Option<(TcpStream, SocketAddr)>,
+
+    /// Bytes read but not yet returned as a frame
+    pending: Vec<u8>,
+}
+
+impl TcpIncoming {
+    // ---
+    /// Waits for connections on `listener`.
+    pub(crate) fn new(listener: TcpListener) -> Self {
+        // ---
+        Self {
+            listener,
+            connection: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the listening socket.
+    pub(crate) fn listener(&self) -> &TcpListener {
+        // ---
+        &self.listener
+    }
+
+    /// Receives the next framed packet into `buf` and returns its length
+    /// and the peer it came from. As with a UDP datagram, a packet longer
+    /// than `buf` is truncated.
+    ///
+    /// Cancel safe: bytes read are kept for the next call.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if accepting a connection fails.
+    pub(crate) async fn recv(&mut self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        // ---
+        let mut chunk = [0u8; READ_CHUNK];
+        loop {
+            let Some((stream, peer)) = &mut self.connection else {
+                let (stream, peer) = self.listener.accept().await?;
+                self.accept(stream, peer);
+                continue;
+            };
+
+            if let Some(len) = complete_frame(&self.pending) {
+                let packet = &self.pending[FRAME_HEADER_LEN..FRAME_HEADER_LEN + len];
+                let copied = len.min(buf.len());
+                buf[..copied].copy_from_slice(&packet[..copied]);
+                self.pending.drain(..FRAME_HEADER_LEN + len);
+                return Ok((copied, *peer));
+            }
+
+            let peer = *peer;
+            tokio::select! {
+                accepted = self.listener.accept() => {
+                    let (stream, peer) = accepted?;
+                    self.accept(stream, peer);
+                }
+                read = stream.read(&mut chunk) => match read {
+                    Ok(0) => self.close(peer, None),
+                    Ok(n) => self.pending.extend_from_slice(&chunk[..n]),
+                    Err(e) => self.close(peer, Some(e)),
+                },
+            }
+        }
+    }
+
+    /// Reads from `stream` from now on, dropping any current connection.
+    fn accept(&mut self, stream: TcpStream, peer: SocketAddr) {
+        // ---
+        if let Some((_, current)) = &self.connection {
+            info!("TCP connection from {} replaces {}", peer, current);
+        } else {
+            info!("Accepted TCP connection from {}", peer);
+        }
+        if let Err(e) = stream.set_nodelay(true) {
+            warn!("Failed to set TCP_NODELAY for {}: {}", peer, e);
+        }
+        self.discard_partial_frame();
+        self.connection = Some((stream, peer));
+    }
+
+    /// Drops the connection to `peer` after it closed or failed.
+    fn close(&mut self, peer: SocketAddr, error: Option<io::Error>) {
+        // ---
+        match error {
+            Some(e) => warn!("TCP connection from {} failed: {}", peer, e),
+            None => info!("TCP connection from {} closed", peer),
+        }
+        self.discard_partial_frame();
+        self.connection = None;
+    }
+
+    /// Forgets the start of a frame the old connection did not finish.
+    fn discard_partial_frame(&mut self) {
+        // ---
+        if !self.pending.is_empty() {
+            warn!(
+                "Discarding {} bytes of an unfinished frame",
+                self.pending.len()
+            );
+            self.pending.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use rtp_opus_common::append_frame;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_frames_split_across_reads_and_connections() {
+        // ---
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = TcpIncoming::new(listener);
+
+        let mut frames = Vec::new();
+        for len in [3, 0, 300] {
+            append_frame(&vec![len as u8; len], &mut frames).unwrap();
+        }
+        let writer = tokio::spawn(async move {
+            // A byte at a time, so every frame arrives in pieces
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.set_nodelay(true).unwrap();
+            for byte in &frames {
+                stream.write_all(&[*byte]).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+
+            // Half a frame, then the connection drops
+            stream.write_all(&[0, 9, 1, 2]).await.unwrap();
+            drop(stream);
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let mut frame = Vec::new();
+            append_frame(&[7; 5], &mut frame).unwrap();
+            stream.write_all(&frame).await.unwrap();
+            stream
+        });
+
+        let mut buf = [0u8; 256];
+        let mut received = Vec::new();
+        for _ in 0..4 {
+            let (len, _) = tokio::time::timeout(Duration::from_secs(5), incoming.recv(&mut buf))
+                .await
+                .expect("frame in time")
+                .unwrap();
+            received.push(buf[..len].to_vec());
+        }
+        let _stream = writer.await.unwrap();
+
+        // The 300-byte packet is truncated to the buffer; the unfinished
+        // frame is dropped with its connection
+        assert_eq!(received[0], [3; 3]);
+        assert!(received[1].is_empty());
+        assert_eq!(received[2], [44; 256]);
+        assert_eq!(received[3], [7; 5]);
+    }
+}
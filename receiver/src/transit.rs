@@ -0,0 +1,137 @@
+//! One-way delay trend from abs-send-time stamps.
+//!
+//! Sender and receiver clocks are not synchronized, so the absolute transit
+//! time is unknown. What can be measured is how much longer a packet took
+//! than the fastest one so far: the difference between the time elapsed
+//! at the receiver and the time elapsed at the sender, by its stamps. A
+//! rising value means queues are building on the path.
+//!
+//! Unlike an estimate from RTP timestamps, this holds through DTX, loop
+//! gaps, and sender catch-up, where media time and send time part ways.
+
+use rtp_opus_common::ABS_SEND_TIME_UNITS_PER_SECOND;
+use std::time::{Duration, Instant};
+
+/// The 24-bit timestamp space of abs-send-time
+const WRAP: i64 = 1 << 24;
+
+/// Estimates the transit time of each packet above the fastest packet's.
+#[derive(Debug, Default)]
+pub struct TransitEstimator {
+    // ---
+    /// Last stamp seen, as received
+    last: Option<u32>,
+
+    /// Last stamp seen, unwrapped to a count since the first packet
+    extended: i64,
+
+    /// Unwrapped send time and arrival of the fastest packet so far
+    baseline: Option<(i64, Instant)>,
+}
+
+impl TransitEstimator {
+    // ---
+    /// Creates an estimator that has seen no packets yet.
+    pub fn new() -> Self {
+        // ---
+        Self::default()
+    }
+
+    /// Observes a packet stamped `send_time` (24-bit abs-send-time units)
+    /// arriving at `arrival`.
+    ///
+    /// # Returns
+    ///
+    /// How much longer the packet took than the fastest packet so far;
+    /// zero for the first packet and for a new fastest one, which becomes
+    /// the baseline.
+    pub fn observe(&mut self, send_time: u32, arrival: Instant) -> Duration {
+        // ---
+        // A step of more than half the space is backwards (reordering)
+        if let Some(last) = self.last {
+            let mut step = (send_time as i64 - last as i64).rem_euclid(WRAP);
+            if step >= WRAP / 2 {
+                step -= WRAP;
+            }
+            self.extended += step;
+        }
+        self.last = Some(send_time);
+
+        let Some((base_sent, base_arrival)) = self.baseline else {
+            self.baseline = Some((self.extended, arrival));
+            return Duration::ZERO;
+        };
+        let sent = (self.extended - base_sent) as f64 / ABS_SEND_TIME_UNITS_PER_SECOND as f64;
+        let received = if arrival >= base_arrival {
+            arrival.duration_since(base_arrival).as_secs_f64()
+        } else {
+            -base_arrival.duration_since(arrival).as_secs_f64()
+        };
+        let transit = received - sent;
+        if transit <= 0.0 {
+            self.baseline = Some((self.extended, arrival));
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(transit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    /// abs-send-time units for `ms` milliseconds
+    fn units(ms: u32) -> u32 {
+        // ---
+        (ms as u64 * ABS_SEND_TIME_UNITS_PER_SECOND as u64 / 1000) as u32
+    }
+
+    fn assert_near(actual: Duration, expected_ms: u64) {
+        // ---
+        let expected = Duration::from_millis(expected_ms);
+        assert!(
+            actual.abs_diff(expected) < Duration::from_micros(100),
+            "{actual:?}, expected {expected:?}"
+        );
+    }
+
+    #[test]
+    fn test_transit_follows_queueing_delay_across_wraparound() {
+        // ---
+        let mut estimator = TransitEstimator::new();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // Sent every 20ms from just before the 64 s wrap, arriving after a
+        // queue grows by 5ms per packet, then drains
+        let first = (1 << 24) - units(30);
+        let send = |index: u32| (first + units(20 * index)) % (1 << 24);
+        assert_eq!(estimator.observe(send(0), at(100)), Duration::ZERO);
+        assert_near(estimator.observe(send(1), at(125)), 5);
+        assert_near(estimator.observe(send(2), at(150)), 10);
+        assert_near(estimator.observe(send(3), at(170)), 10);
+
+        // A packet sent 20ms later arriving with the baseline's delay
+        assert_near(estimator.observe(send(4), at(180)), 0);
+
+        // A faster packet than any before becomes the baseline
+        assert_eq!(estimator.observe(send(5), at(195)), Duration::ZERO);
+        assert_near(estimator.observe(send(6), at(218)), 3);
+
+        // Reordered: sent earlier, arrives later
+        assert_near(estimator.observe(send(5), at(220)), 25);
+    }
+
+    #[test]
+    fn test_silence_between_packets_is_not_delay() {
+        // ---
+        let mut estimator = TransitEstimator::new();
+        let start = Instant::now();
+
+        // A 10 s gap in sending is a 10 s gap in arrival, not transit
+        estimator.observe(units(0), start);
+        let transit = estimator.observe(units(10_000), start + Duration::from_millis(10_002));
+        assert_near(transit, 2);
+    }
+}
@@ -0,0 +1,196 @@
+//! Arrival history of transport-wide sequence numbers.
+//!
+//! Groundwork for transport-wide congestion control: the sender numbers
+//! every packet with a 16-bit counter of its own, and the receiver keeps
+//! when each number arrived. Feedback built from this history (which
+//! packets arrived, and the spacing of their arrivals against the spacing
+//! of their sending) is what a bandwidth estimator runs on.
+
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// Arrivals kept unless configured: about 20 s of 20 ms frames
+pub const DEFAULT_TRANSPORT_HISTORY_PACKETS: usize = 1024;
+
+/// Sequence number space of the transport-wide counter
+const WRAP: i64 = 1 << 16;
+
+/// A packet's transport-wide sequence number and when it arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportArrival {
+    // ---
+    /// Sequence number as carried in the packet
+    pub sequence: u16,
+
+    /// Sequence number unwrapped to count on past the 16-bit range, so
+    /// arrivals order correctly across a wrap; the first packet seen keeps
+    /// its own value
+    pub extended: i64,
+
+    /// When the packet arrived (the first copy, for a duplicate)
+    pub arrival: Instant,
+}
+
+/// Bounded history of transport-wide sequence number arrivals.
+///
+/// Keeps the `capacity` highest sequence numbers seen, in sequence order
+/// whatever order they arrived in; older ones, and late packets older than
+/// all of them, are dropped.
+#[derive(Debug, Clone)]
+pub struct TransportHistory {
+    // ---
+    capacity: usize,
+
+    /// Highest sequence number seen, as carried and unwrapped
+    highest: Option<(u16, i64)>,
+
+    /// Arrival of each kept sequence number, by unwrapped sequence number
+    arrivals: BTreeMap<i64, Instant>,
+}
+
+impl Default for TransportHistory {
+    fn default() -> Self {
+        // ---
+        Self::new(DEFAULT_TRANSPORT_HISTORY_PACKETS)
+    }
+}
+
+impl TransportHistory {
+    // ---
+    /// Creates an empty history of at most `capacity` arrivals (at least
+    /// one).
+    pub fn new(capacity: usize) -> Self {
+        // ---
+        Self {
+            capacity: capacity.max(1),
+            highest: None,
+            arrivals: BTreeMap::new(),
+        }
+    }
+
+    /// Records the packet numbered `sequence` arriving at `arrival`.
+    pub fn record(&mut self, sequence: u16, arrival: Instant) {
+        // ---
+        // A step of more than half the space is backwards (reordering)
+        let extended = match self.highest {
+            None => sequence as i64,
+            Some((last, extended)) => {
+                let mut step = (sequence as i64 - last as i64).rem_euclid(WRAP);
+                if step >= WRAP / 2 {
+                    step -= WRAP;
+                }
+                extended + step
+            }
+        };
+        if self.highest.is_none_or(|(_, highest)| extended > highest) {
+            self.highest = Some((sequence, extended));
+        }
+
+        if self.arrivals.len() >= self.capacity
+            && self
+                .arrivals
+                .first_key_value()
+                .is_some_and(|(&oldest, _)| extended < oldest)
+        {
+            return;
+        }
+        self.arrivals.entry(extended).or_insert(arrival);
+        while self.arrivals.len() > self.capacity {
+            self.arrivals.pop_first();
+        }
+    }
+
+    /// Returns the kept arrivals in sequence order.
+    pub fn arrivals(&self) -> Vec<TransportArrival> {
+        // ---
+        self.arrivals
+            .iter()
+            .map(|(&extended, &arrival)| TransportArrival {
+                sequence: extended.rem_euclid(WRAP) as u16,
+                extended,
+                arrival,
+            })
+            .collect()
+    }
+
+    /// Returns the number of arrivals kept.
+    pub fn len(&self) -> usize {
+        // ---
+        self.arrivals.len()
+    }
+
+    /// Returns whether no arrivals are kept.
+    pub fn is_empty(&self) -> bool {
+        // ---
+        self.arrivals.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_keeps_order_through_70k_packets_across_the_wrap() {
+        // ---
+        let mut history = TransportHistory::new(256);
+        let start = Instant::now();
+        let first: u16 = 60_000;
+
+        // Every eighth pair arrives swapped
+        let count: u32 = 70_000;
+        for index in 0..count {
+            let sent = match index % 8 {
+                3 => index + 1,
+                4 => index - 1,
+                _ => index,
+            };
+            let sequence = first.wrapping_add(sent as u16);
+            history.record(sequence, start + Duration::from_micros(index as u64));
+        }
+
+        let arrivals = history.arrivals();
+        assert_eq!(arrivals.len(), 256);
+        let last = first as i64 + count as i64 - 1;
+        assert_eq!(arrivals.last().unwrap().extended, last);
+        for (pair, expected) in arrivals.windows(2).zip(last - 255..) {
+            assert_eq!(pair[0].extended, expected);
+            assert_eq!(pair[1].extended, pair[0].extended + 1);
+            assert_eq!(pair[1].sequence, pair[0].sequence.wrapping_add(1));
+        }
+        assert_eq!(
+            arrivals[0].sequence,
+            first.wrapping_add((count - 256) as u16)
+        );
+    }
+
+    #[test]
+    fn test_late_and_duplicate_packets() {
+        // ---
+        let mut history = TransportHistory::new(4);
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // Reordered across the wrap: 65535 arrives after 0
+        history.record(65_534, at(0));
+        history.record(0, at(1));
+        history.record(65_535, at(2));
+        history.record(1, at(3));
+        let sequences: Vec<u16> = history.arrivals().iter().map(|a| a.sequence).collect();
+        assert_eq!(sequences, [65_534, 65_535, 0, 1]);
+        assert_eq!(history.arrivals()[1].arrival, at(2));
+
+        // A retransmission keeps the first arrival
+        history.record(0, at(10));
+        assert_eq!(history.arrivals()[2].arrival, at(1));
+
+        // Once full, older numbers are dropped, late or not
+        history.record(2, at(11));
+        history.record(65_534, at(12));
+        let sequences: Vec<u16> = history.arrivals().iter().map(|a| a.sequence).collect();
+        assert_eq!(sequences, [65_535, 0, 1, 2]);
+        assert_eq!(history.len(), 4);
+    }
+}
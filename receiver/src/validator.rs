@@ -0,0 +1,537 @@
+//! RTP stream consistency validation.
+//!
+//! Checks a stream for header-level correctness instead of playing it.
+//! Intended for debugging third-party senders: the validator consumes the
+//! in-order output of the jitter buffer and reports each specific violation
+//! (e.g. "sequence 42: timestamp went backwards by 320") exactly once.
+
+use rtp_opus_common::RtpPacket;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::codec::SAMPLE_RATE;
+
+/// A single stream-correctness violation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// Timestamp is lower than that of the previous packet in sequence order.
+    TimestampBackwards { sequence: u16, by: u32 },
+
+    /// Timestamp does not match the step inferred from earlier packets.
+    TimestampStep {
+        sequence: u16,
+        expected: u32,
+        actual: u32,
+    },
+
+    /// SSRC differs from the one established at stream start.
+    SsrcChanged { sequence: u16, from: u32, to: u32 },
+
+    /// Payload type differs from that of the previous packet.
+    PayloadTypeChanged { sequence: u16, from: u8, to: u8 },
+
+    /// Marker bit set on a packet that neither starts the stream nor
+    /// follows a timestamp gap (RFC 3551 §4.1).
+    MarkerMisplaced { sequence: u16 },
+
+    /// Payload could not be decoded.
+    DecodeFailed { sequence: u16 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        match self {
+            Violation::TimestampBackwards { sequence, by } => {
+                write!(
+                    f,
+                    "sequence {}: timestamp went backwards by {}",
+                    sequence, by
+                )
+            }
+            Violation::TimestampStep {
+                sequence,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "sequence {}: timestamp {} does not match expected {}",
+                sequence, actual, expected
+            ),
+            Violation::SsrcChanged { sequence, from, to } => write!(
+                f,
+                "sequence {}: SSRC changed from 0x{:08X} to 0x{:08X}",
+                sequence, from, to
+            ),
+            Violation::PayloadTypeChanged { sequence, from, to } => write!(
+                f,
+                "sequence {}: payload type changed from {} to {}",
+                sequence, from, to
+            ),
+            Violation::MarkerMisplaced { sequence } => write!(
+                f,
+                "sequence {}: marker bit set without a timestamp gap before it",
+                sequence
+            ),
+            Violation::DecodeFailed { sequence } => {
+                write!(f, "sequence {}: payload failed to decode", sequence)
+            }
+        }
+    }
+}
+
+/// Inter-packet arrival statistics observed on the network side.
+#[derive(Debug, Clone, Default)]
+pub struct ArrivalStats {
+    // ---
+    /// Number of inter-arrival intervals measured
+    pub intervals: u64,
+
+    /// Shortest gap between consecutive arrivals
+    pub min: Option<Duration>,
+
+    /// Longest gap between consecutive arrivals
+    pub max: Option<Duration>,
+
+    /// Mean gap between consecutive arrivals
+    pub mean: Option<Duration>,
+}
+
+/// Pass/fail result of a validation run.
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    // ---
+    /// Packets checked (in sequence order)
+    pub packets: u64,
+
+    /// Timestamp increment per sequence step inferred from the stream
+    pub timestamp_step: Option<u32>,
+
+    /// Frame duration implied by `timestamp_step` at the codec clock rate
+    pub frame_duration_ms: Option<f64>,
+
+    /// Arrival timing observed before buffering
+    pub arrivals: ArrivalStats,
+
+    /// Every violation found, in stream order
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    // ---
+    /// Returns `true` if no violations were found.
+    pub fn passed(&self) -> bool {
+        // ---
+        self.violations.is_empty()
+    }
+}
+
+/// Reference point used to predict timestamps of later packets.
+#[derive(Debug, Clone, Copy)]
+struct Anchor {
+    sequence: u16,
+    timestamp: u32,
+}
+
+impl Anchor {
+    // ---
+    fn expected(&self, sequence: u16, step: u32) -> u32 {
+        // ---
+        let steps = sequence.wrapping_sub(self.sequence) as u32;
+        self.timestamp.wrapping_add(step.wrapping_mul(steps))
+    }
+}
+
+/// Validates RTP header consistency for a single stream.
+///
+/// Feed packets in sequence order with [`StreamValidator::check`] (typically
+/// straight from the jitter buffer) and raw arrival instants with
+/// [`StreamValidator::record_arrival`].
+///
+/// Timestamp checks are anchored: a single packet with a bad timestamp is
+/// reported once and the following packets are still checked against the
+/// previous anchor. If the following packet agrees with the deviating one
+/// instead, the deviation is treated as a deliberate discontinuity and the
+/// stream is re-anchored without further reports.
+#[derive(Debug, Default)]
+pub struct StreamValidator {
+    // ---
+    packets: u64,
+    ssrc: Option<u32>,
+    payload_type: Option<u8>,
+    step: Option<u32>,
+    anchor: Option<Anchor>,
+    candidate: Option<Anchor>,
+    last: Option<Anchor>,
+    violations: Vec<Violation>,
+
+    last_arrival: Option<Instant>,
+    arrivals: ArrivalStats,
+    arrival_total: Duration,
+}
+
+impl StreamValidator {
+    // ---
+    pub fn new() -> Self {
+        // ---
+        Self::default()
+    }
+
+    /// Records the arrival instant of a packet as seen by the socket.
+    pub fn record_arrival(&mut self, arrival: Instant) {
+        // ---
+        if let Some(prev) = self.last_arrival {
+            let gap = arrival.saturating_duration_since(prev);
+            let stats = &mut self.arrivals;
+            stats.intervals += 1;
+            stats.min = Some(stats.min.map_or(gap, |m| m.min(gap)));
+            stats.max = Some(stats.max.map_or(gap, |m| m.max(gap)));
+            self.arrival_total += gap;
+            stats.mean = Some(self.arrival_total / stats.intervals as u32);
+        }
+        self.last_arrival = Some(arrival);
+    }
+
+    /// Checks the next packet in sequence order.
+    pub fn check(&mut self, packet: &RtpPacket) {
+        // ---
+        self.packets += 1;
+        let current = Anchor {
+            sequence: packet.sequence,
+            timestamp: packet.timestamp,
+        };
+
+        // SSRC constancy
+        match self.ssrc {
+            None => self.ssrc = Some(packet.ssrc),
+            Some(ssrc) if ssrc != packet.ssrc => {
+                self.violations.push(Violation::SsrcChanged {
+                    sequence: packet.sequence,
+                    from: ssrc,
+                    to: packet.ssrc,
+                });
+                self.ssrc = Some(packet.ssrc);
+            }
+            Some(_) => {}
+        }
+
+        // Payload type constancy
+        match self.payload_type {
+            Some(payload_type) if payload_type != packet.payload_type => {
+                self.violations.push(Violation::PayloadTypeChanged {
+                    sequence: packet.sequence,
+                    from: payload_type,
+                    to: packet.payload_type,
+                });
+            }
+            _ => {}
+        }
+        self.payload_type = Some(packet.payload_type);
+
+        // Marker only on the first packet and at the end of a silence
+        if packet.marker && self.packets > 1 && !self.follows_gap(current) {
+            self.violations.push(Violation::MarkerMisplaced {
+                sequence: packet.sequence,
+            });
+        }
+
+        self.check_timestamp(current);
+        self.last = Some(current);
+    }
+
+    /// Returns `true` if `current` is further ahead of the previous packet
+    /// than its sequence distance accounts for, as after DTX or a paused
+    /// source. Without an inferred step, any packet counts.
+    fn follows_gap(&self, current: Anchor) -> bool {
+        // ---
+        let (Some(last), Some(step)) = (self.last, self.step) else {
+            return true;
+        };
+        let steps = current.sequence.wrapping_sub(last.sequence) as u32;
+        let advance = current.timestamp.wrapping_sub(last.timestamp);
+        (advance as i32) > 0 && advance > step.wrapping_mul(steps)
+    }
+
+    /// Records a payload that failed to decode.
+    pub fn record_decode_failure(&mut self, sequence: u16) {
+        // ---
+        self.violations.push(Violation::DecodeFailed { sequence });
+    }
+
+    fn check_timestamp(&mut self, current: Anchor) {
+        // ---
+        let (Some(anchor), Some(last)) = (self.anchor, self.last) else {
+            self.anchor = Some(current);
+            return;
+        };
+
+        // Infer the step from the first pair of packets
+        let step = match self.step {
+            Some(step) => step,
+            None => {
+                let seq_delta = current.sequence.wrapping_sub(last.sequence).max(1) as u32;
+                let ts_delta = current.timestamp.wrapping_sub(last.timestamp);
+                if (ts_delta as i32) > 0 {
+                    self.step = Some(ts_delta / seq_delta);
+                    self.anchor = Some(current);
+                } else if ts_delta != 0 {
+                    self.violations.push(Violation::TimestampBackwards {
+                        sequence: current.sequence,
+                        by: last.timestamp.wrapping_sub(current.timestamp),
+                    });
+                    self.anchor = Some(current);
+                }
+                return;
+            }
+        };
+
+        let expected = anchor.expected(current.sequence, step);
+        if current.timestamp == expected {
+            self.candidate = None;
+            return;
+        }
+
+        // Agrees with the previous outlier: a discontinuity, not an error
+        if let Some(candidate) = self.candidate.take() {
+            if current.timestamp == candidate.expected(current.sequence, step) {
+                self.anchor = Some(candidate);
+                return;
+            }
+        }
+
+        let backwards = last.timestamp.wrapping_sub(current.timestamp);
+        if (backwards as i32) > 0 {
+            self.violations.push(Violation::TimestampBackwards {
+                sequence: current.sequence,
+                by: backwards,
+            });
+        } else {
+            self.violations.push(Violation::TimestampStep {
+                sequence: current.sequence,
+                expected,
+                actual: current.timestamp,
+            });
+        }
+        self.candidate = Some(current);
+    }
+
+    /// Builds the report for everything checked so far.
+    pub fn report(&self) -> ValidationReport {
+        // ---
+        ValidationReport {
+            packets: self.packets,
+            timestamp_step: self.step,
+            frame_duration_ms: self
+                .step
+                .map(|step| step as f64 * 1000.0 / SAMPLE_RATE as f64),
+            arrivals: self.arrivals.clone(),
+            violations: self.violations.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    fn packet(seq: u16, ts: u32) -> RtpPacket {
+        RtpPacket::new(seq, ts, 0x12345678, vec![1, 2, 3])
+    }
+
+    fn run(packets: &[RtpPacket]) -> ValidationReport {
+        // ---
+        let mut validator = StreamValidator::new();
+        for p in packets {
+            validator.check(p);
+        }
+        validator.report()
+    }
+
+    #[test]
+    fn test_clean_stream_passes() {
+        // ---
+        let packets: Vec<_> = (0..50).map(|i| packet(i, i as u32 * 320)).collect();
+        let report = run(&packets);
+
+        assert!(report.passed(), "{:?}", report.violations);
+        assert_eq!(report.packets, 50);
+        assert_eq!(report.timestamp_step, Some(320));
+        assert_eq!(report.frame_duration_ms, Some(20.0));
+    }
+
+    #[test]
+    fn test_timestamp_backwards_reported_once() {
+        // ---
+        let mut packets: Vec<_> = (0..10).map(|i| packet(i, i as u32 * 320)).collect();
+        packets[5].timestamp = 3 * 320; // 2 frames behind packet 4
+
+        let report = run(&packets);
+
+        assert_eq!(
+            report.violations,
+            vec![Violation::TimestampBackwards {
+                sequence: 5,
+                by: 320
+            }]
+        );
+        assert_eq!(
+            report.violations[0].to_string(),
+            "sequence 5: timestamp went backwards by 320"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_step_outlier_reported_once() {
+        // ---
+        let mut packets: Vec<_> = (0..10).map(|i| packet(i, i as u32 * 320)).collect();
+        packets[4].timestamp += 100;
+
+        let report = run(&packets);
+
+        assert_eq!(
+            report.violations,
+            vec![Violation::TimestampStep {
+                sequence: 4,
+                expected: 1280,
+                actual: 1380
+            }]
+        );
+    }
+
+    #[test]
+    fn test_timestamp_discontinuity_reported_once() {
+        // ---
+        // Sender jumps its timestamp base permanently at sequence 5
+        let packets: Vec<_> = (0..10)
+            .map(|i| {
+                let base = if i >= 5 { 100_000 } else { 0 };
+                packet(i, base + i as u32 * 320)
+            })
+            .collect();
+
+        let report = run(&packets);
+
+        assert_eq!(report.violations.len(), 1);
+        assert!(matches!(
+            report.violations[0],
+            Violation::TimestampStep { sequence: 5, .. }
+        ));
+    }
+
+    #[test]
+    fn test_ssrc_change_reported_once() {
+        // ---
+        let mut packets: Vec<_> = (0..10).map(|i| packet(i, i as u32 * 320)).collect();
+        for p in packets.iter_mut().skip(6) {
+            p.ssrc = 0xCAFEBABE;
+        }
+
+        let report = run(&packets);
+
+        assert_eq!(
+            report.violations,
+            vec![Violation::SsrcChanged {
+                sequence: 6,
+                from: 0x12345678,
+                to: 0xCAFEBABE
+            }]
+        );
+    }
+
+    #[test]
+    fn test_payload_type_change_reported_once() {
+        // ---
+        let mut packets: Vec<_> = (0..10).map(|i| packet(i, i as u32 * 320)).collect();
+        for p in packets.iter_mut().skip(4) {
+            p.payload_type = 111;
+        }
+
+        let report = run(&packets);
+
+        assert_eq!(
+            report.violations,
+            vec![Violation::PayloadTypeChanged {
+                sequence: 4,
+                from: 96,
+                to: 111
+            }]
+        );
+        assert_eq!(
+            report.violations[0].to_string(),
+            "sequence 4: payload type changed from 96 to 111"
+        );
+    }
+
+    #[test]
+    fn test_marker_misplaced_reported_once() {
+        // ---
+        // Marked at the start (fine), mid-stream (misplaced), and after a
+        // five-frame DTX gap at sequence 8 (fine)
+        let mut packets: Vec<_> = (0..12)
+            .map(|i| {
+                let gap = if i >= 8 { 5 * 320 } else { 0 };
+                packet(i, gap + i as u32 * 320)
+            })
+            .collect();
+        for seq in [0, 5, 8] {
+            packets[seq].marker = true;
+        }
+
+        let report = run(&packets);
+
+        let markers: Vec<_> = report
+            .violations
+            .iter()
+            .filter(|v| matches!(v, Violation::MarkerMisplaced { .. }))
+            .collect();
+        assert_eq!(markers, [&Violation::MarkerMisplaced { sequence: 5 }]);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn test_marker_after_lost_packets_is_misplaced() {
+        // ---
+        // Sequence 4-5 lost in transit: the timestamp keeps pace with the
+        // sequence number, so there was no gap to mark
+        let mut packets: Vec<_> = (0..10)
+            .filter(|&i| i != 4 && i != 5)
+            .map(|i| packet(i, i as u32 * 320))
+            .collect();
+        packets[4].marker = true;
+
+        let report = run(&packets);
+
+        assert_eq!(
+            report.violations,
+            vec![Violation::MarkerMisplaced { sequence: 6 }]
+        );
+    }
+
+    #[test]
+    fn test_timestamp_wraparound_is_not_a_violation() {
+        // ---
+        let base = u32::MAX - 640;
+        let packets: Vec<_> = (0..6)
+            .map(|i| packet(i, base.wrapping_add(i as u32 * 320)))
+            .collect();
+
+        assert!(run(&packets).passed());
+    }
+
+    #[test]
+    fn test_arrival_stats() {
+        // ---
+        let mut validator = StreamValidator::new();
+        let t0 = Instant::now();
+        validator.record_arrival(t0);
+        validator.record_arrival(t0 + Duration::from_millis(10));
+        validator.record_arrival(t0 + Duration::from_millis(40));
+
+        let arrivals = validator.report().arrivals;
+        assert_eq!(arrivals.intervals, 2);
+        assert_eq!(arrivals.min, Some(Duration::from_millis(10)));
+        assert_eq!(arrivals.max, Some(Duration::from_millis(30)));
+        assert_eq!(arrivals.mean, Some(Duration::from_millis(20)));
+    }
+}
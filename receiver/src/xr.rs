@@ -0,0 +1,559 @@
+//! Measurements behind RTCP extended reports (RFC 3611).
+//!
+//! [`ReceptionHistory`] keeps what became of each sequence number of the
+//! stream since the last report — received, lost, or discarded as late —
+//! for the Loss RLE block, and folds the reported ones into a
+//! [`BurstGapTracker`] for the burst and gap figures of the VoIP Metrics
+//! block. [`voip_metrics`] fills that block in, with a MOS estimate from
+//! the ITU-T G.107 E-model.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rtp_opus_common::{VoipMetrics, XR_DEFAULT_GMIN, XR_UNAVAILABLE};
+
+use crate::jitter_buffer::JitterBufferConfig;
+
+/// Most sequence numbers held between reports; older ones are folded
+/// into the burst and gap figures without being reported one by one
+pub const MAX_RECEPTION_HISTORY: usize = 4096;
+
+/// Equipment impairment assumed for Opus at speech bitrates. ITU-T G.113
+/// has no figures for Opus, so these two are assumptions.
+const OPUS_IE: f64 = 0.0;
+
+/// Robustness to random loss assumed for Opus with its concealment
+const OPUS_BPL: f64 = 20.0;
+
+/// Packet loss concealment (enhanced: Opus conceals lost frames) and a
+/// jitter buffer that does not adapt, as the VoIP Metrics RX config
+const RX_CONFIG: u8 = 0b1010_0000;
+
+/// The RX config with an adaptive jitter buffer instead
+const RX_CONFIG_ADAPTIVE: u8 = 0b1011_0000;
+
+/// What became of one sequence number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reception {
+    // ---
+    /// Arrived in time to be played
+    Received,
+
+    /// Never arrived
+    Lost,
+
+    /// Arrived too late to be played
+    Discarded,
+}
+
+impl Reception {
+    // ---
+    /// Returns whether the packet was lost or discarded: a loss event to
+    /// the listener.
+    pub fn is_loss_event(self) -> bool {
+        // ---
+        self != Reception::Received
+    }
+}
+
+/// Burst and gap figures of a stream (RFC 3611 §4.7.2).
+///
+/// A burst is a stretch that starts and ends with a loss event and holds
+/// no run of [`XR_DEFAULT_GMIN`] packets received in a row; a gap is the
+/// rest. A loss event alone between such runs counts toward the gap, as
+/// an isolated loss does in the RFC's reference algorithm.
+#[derive(Debug, Clone)]
+pub struct BurstGapTracker {
+    // ---
+    /// Packets received in a row that end a burst
+    gmin: u8,
+
+    /// Sequence numbers observed, and of them lost and discarded
+    packets: u64,
+    lost: u64,
+    discarded: u64,
+
+    /// Bursts ended, and the packets and loss events within them
+    bursts: u64,
+    burst_packets: u64,
+    burst_losses: u64,
+
+    /// Packets and loss events since the first loss event of a burst yet
+    /// to end, up to its last loss event
+    pending: Option<(u64, u64)>,
+
+    /// Packets received in a row since the last loss event
+    run: u64,
+}
+
+/// Summary of a [`BurstGapTracker`], in fractions and packets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurstGapSummary {
+    // ---
+    /// Loss events of all packets within bursts
+    pub burst_density: f64,
+
+    /// Loss events of all packets within gaps
+    pub gap_density: f64,
+
+    /// Mean length of a burst in packets (0 without bursts)
+    pub burst_length: f64,
+
+    /// Mean length of a gap in packets, taking a gap before the first
+    /// burst and one after each
+    pub gap_length: f64,
+}
+
+impl BurstGapTracker {
+    // ---
+    /// Creates a tracker that has observed nothing yet.
+    pub fn new(gmin: u8) -> Self {
+        // ---
+        Self {
+            gmin: gmin.max(1),
+            packets: 0,
+            lost: 0,
+            discarded: 0,
+            bursts: 0,
+            burst_packets: 0,
+            burst_losses: 0,
+            pending: None,
+            run: 0,
+        }
+    }
+
+    /// Observes the next sequence number in order.
+    pub fn observe(&mut self, reception: Reception) {
+        // ---
+        self.packets += 1;
+        match reception {
+            Reception::Received => {
+                self.run += 1;
+                if self.run >= self.gmin as u64 {
+                    if let Some((packets, losses)) = self.pending.take() {
+                        self.end_burst(packets, losses);
+                    }
+                }
+                return;
+            }
+            Reception::Lost => self.lost += 1,
+            Reception::Discarded => self.discarded += 1,
+        }
+        self.pending = Some(match self.pending {
+            Some((packets, losses)) => (packets + self.run + 1, losses + 1),
+            None => (1, 1),
+        });
+        self.run = 0;
+    }
+
+    /// Counts a stretch that ended as a burst if it holds more than one
+    /// loss event.
+    fn end_burst(&mut self, packets: u64, losses: u64) {
+        // ---
+        if losses > 1 {
+            self.bursts += 1;
+            self.burst_packets += packets;
+            self.burst_losses += losses;
+        }
+    }
+
+    /// Returns the packets received in a row that end a burst.
+    pub fn gmin(&self) -> u8 {
+        // ---
+        self.gmin
+    }
+
+    /// Returns the sequence numbers observed.
+    pub fn packets(&self) -> u64 {
+        // ---
+        self.packets
+    }
+
+    /// Returns the packets observed lost.
+    pub fn lost(&self) -> u64 {
+        // ---
+        self.lost
+    }
+
+    /// Returns the packets observed discarded.
+    pub fn discarded(&self) -> u64 {
+        // ---
+        self.discarded
+    }
+
+    /// Returns the burst and gap figures so far, a burst still open
+    /// counted as ended.
+    pub fn summary(&self) -> BurstGapSummary {
+        // ---
+        let mut ended = self.clone();
+        if let Some((packets, losses)) = ended.pending.take() {
+            ended.end_burst(packets, losses);
+        }
+        let losses = ended.lost + ended.discarded;
+        let gap_packets = ended.packets - ended.burst_packets;
+        let gap_losses = losses - ended.burst_losses;
+        let ratio = |part: u64, whole: u64| match whole {
+            0 => 0.0,
+            whole => part as f64 / whole as f64,
+        };
+
+        BurstGapSummary {
+            burst_density: ratio(ended.burst_losses, ended.burst_packets),
+            gap_density: ratio(gap_losses, gap_packets),
+            burst_length: ratio(ended.burst_packets, ended.bursts),
+            gap_length: match gap_packets {
+                0 => 0.0,
+                _ => ratio(gap_packets, ended.bursts + 1),
+            },
+        }
+    }
+}
+
+impl Default for BurstGapTracker {
+    fn default() -> Self {
+        // ---
+        Self::new(XR_DEFAULT_GMIN)
+    }
+}
+
+/// What became of each sequence number since the last report.
+///
+/// Indexed by extended sequence number. Recording a number past the end
+/// marks the ones skipped lost; a lost one can still turn received or
+/// discarded until it is reported. Numbers taken for a report, or pushed
+/// out past [`MAX_RECEPTION_HISTORY`], are folded into the burst and gap
+/// figures.
+#[derive(Debug, Clone, Default)]
+pub struct ReceptionHistory {
+    // ---
+    /// Extended sequence number of the first held
+    begin: Option<u64>,
+
+    /// What became of each, in sequence order
+    slots: VecDeque<Reception>,
+
+    /// Figures over every sequence number folded in
+    tracker: BurstGapTracker,
+}
+
+impl ReceptionHistory {
+    // ---
+    /// Starts over at `extended`, the first packet of a new baseline;
+    /// numbers still held are folded in first.
+    pub fn restart(&mut self, extended: u64) {
+        // ---
+        self.fold();
+        self.begin = Some(extended);
+        self.record(extended, Reception::Received);
+    }
+
+    /// Records what became of `extended`.
+    ///
+    /// Numbers already reported are left alone, and a number held only
+    /// changes from lost.
+    pub fn record(&mut self, extended: u64, reception: Reception) {
+        // ---
+        let begin = *self.begin.get_or_insert(extended);
+        let Some(index) = extended.checked_sub(begin) else {
+            return;
+        };
+        let index = index as usize;
+        if index >= self.slots.len() {
+            self.slots.resize(index, Reception::Lost);
+            self.slots.push_back(reception);
+        } else if self.slots[index] == Reception::Lost {
+            self.slots[index] = reception;
+        }
+
+        while self.slots.len() > MAX_RECEPTION_HISTORY {
+            if let Some(oldest) = self.slots.pop_front() {
+                self.tracker.observe(oldest);
+            }
+            self.begin = Some(self.begin.unwrap_or_default() + 1);
+        }
+    }
+
+    /// Takes the numbers held for a report, folding them in: returns the
+    /// first one's extended sequence number and what became of each.
+    /// `None` when nothing is held.
+    pub fn take(&mut self) -> Option<(u64, Vec<Reception>)> {
+        // ---
+        let begin = self.begin?;
+        if self.slots.is_empty() {
+            return None;
+        }
+        let slots: Vec<Reception> = self.slots.drain(..).collect();
+        for &slot in &slots {
+            self.tracker.observe(slot);
+        }
+        self.begin = Some(begin + slots.len() as u64);
+        Some((begin, slots))
+    }
+
+    /// Folds in whatever is held, reporting none of it.
+    fn fold(&mut self) {
+        // ---
+        for slot in self.slots.drain(..) {
+            self.tracker.observe(slot);
+        }
+    }
+
+    /// Returns the figures over every sequence number folded in.
+    pub fn tracker(&self) -> &BurstGapTracker {
+        // ---
+        &self.tracker
+    }
+}
+
+/// Delays of the receiving end for the VoIP Metrics block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayoutDelays {
+    // ---
+    /// Duration of one frame
+    pub frame: Duration,
+
+    /// Delay the jitter buffer holds packets for
+    pub jitter_nominal: Duration,
+
+    /// Longest a packet can wait in the jitter buffer
+    pub jitter_maximum: Duration,
+
+    /// Whether the jitter buffer adapts its depth
+    pub adaptive: bool,
+}
+
+impl PlayoutDelays {
+    // ---
+    /// Returns the delays of a jitter buffer configured by `config`
+    /// playing frames of `frame`: it holds packets its depth, and at most
+    /// as long as its capacity in frames lasts, or an adaptive depth's
+    /// bound. The absolute maximum reported is the same.
+    ///
+    /// An adaptive buffer's nominal delay is its initial depth here; the
+    /// caller sets the current one.
+    pub fn new(config: &JitterBufferConfig, frame: Duration) -> Self {
+        // ---
+        let nominal = Duration::from_millis(config.depth_ms as u64);
+        let maximum = match &config.adaptive {
+            Some(adaptive) => Duration::from_millis(adaptive.max_ms as u64),
+            None => frame * config.max_packets as u32,
+        };
+        Self {
+            frame,
+            jitter_nominal: nominal,
+            jitter_maximum: maximum.max(nominal),
+            adaptive: config.adaptive.is_some(),
+        }
+    }
+}
+
+/// Returns the VoIP Metrics block on `ssrc` from the figures of
+/// `tracker` and the receiving end's `delays`.
+///
+/// Loss and discard rates are over every sequence number observed since
+/// reception started. The end system delay is the jitter buffer's plus
+/// a frame to decode. Without a `round_trip` measured, the round trip
+/// delay is 0 and the conversational quality counts the end system delay
+/// alone. Levels and echo are not measured.
+pub fn voip_metrics(
+    ssrc: u32,
+    tracker: &BurstGapTracker,
+    delays: &PlayoutDelays,
+    round_trip: Option<Duration>,
+) -> VoipMetrics {
+    // ---
+    let summary = tracker.summary();
+    let packets = tracker.packets();
+    let share = |count: u64| match packets {
+        0 => 0,
+        packets => ((count << 8) / packets).min(255) as u8,
+    };
+    let density = |ratio: f64| (ratio * 256.0).min(255.0) as u8;
+    let millis = |duration: Duration| duration.as_millis().min(u16::MAX as u128) as u16;
+    let frame_ms = delays.frame.as_secs_f64() * 1000.0;
+    let length_ms = |frames: f64| (frames * frame_ms).round().min(u16::MAX as f64) as u16;
+
+    let end_system = delays.jitter_nominal + delays.frame;
+    let one_way = end_system + round_trip.unwrap_or_default() / 2;
+    let loss_events = match packets {
+        0 => 0.0,
+        packets => (tracker.lost() + tracker.discarded()) as f64 / packets as f64,
+    };
+    let listening = r_factor(loss_events, Duration::ZERO);
+    let conversational = r_factor(loss_events, one_way);
+
+    VoipMetrics {
+        ssrc,
+        loss_rate: share(tracker.lost()),
+        discard_rate: share(tracker.discarded()),
+        burst_density: density(summary.burst_density),
+        gap_density: density(summary.gap_density),
+        burst_duration: length_ms(summary.burst_length),
+        gap_duration: length_ms(summary.gap_length),
+        round_trip_delay: round_trip.map_or(0, millis),
+        end_system_delay: millis(end_system),
+        signal_level: XR_UNAVAILABLE as i8,
+        noise_level: XR_UNAVAILABLE as i8,
+        rerl: XR_UNAVAILABLE,
+        gmin: tracker.gmin(),
+        r_factor: conversational.round().clamp(0.0, 100.0) as u8,
+        ext_r_factor: XR_UNAVAILABLE,
+        mos_lq: (mos(listening) * 10.0).round() as u8,
+        mos_cq: (mos(conversational) * 10.0).round() as u8,
+        rx_config: if delays.adaptive {
+            RX_CONFIG_ADAPTIVE
+        } else {
+            RX_CONFIG
+        },
+        jb_nominal: millis(delays.jitter_nominal),
+        jb_maximum: millis(delays.jitter_maximum),
+        jb_abs_max: millis(delays.jitter_maximum),
+    }
+}
+
+/// Returns the E-model R factor (ITU-T G.107) for a share of
+/// `loss_events` among packets and a mouth-to-ear `delay`, with the
+/// default values for everything else and losses taken as random.
+pub fn r_factor(loss_events: f64, delay: Duration) -> f64 {
+    // ---
+    let ppl = loss_events * 100.0;
+    let ie_eff = OPUS_IE + (95.0 - OPUS_IE) * ppl / (ppl + OPUS_BPL);
+    let d = delay.as_secs_f64() * 1000.0;
+    let id = 0.024 * d + 0.11 * (d - 177.3).max(0.0);
+    93.2 - ie_eff - id
+}
+
+/// Returns the MOS an R factor maps to (ITU-T G.107 Annex B).
+pub fn mos(r: f64) -> f64 {
+    // ---
+    if r <= 0.0 {
+        1.0
+    } else if r >= 100.0 {
+        4.5
+    } else {
+        1.0 + 0.035 * r + r * (r - 60.0) * (100.0 - r) * 7e-6
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use Reception::{Discarded, Lost, Received};
+
+    #[test]
+    fn test_bursts_and_gaps() {
+        // ---
+        let mut tracker = BurstGapTracker::new(4);
+
+        // Gap: 10 received and an isolated loss, then 5 received
+        for reception in [[Received; 10].as_slice(), &[Lost], &[Received; 5]].concat() {
+            tracker.observe(reception);
+        }
+        // Burst: lost, received, discarded, lost, then 4 received end it
+        for reception in [
+            Lost, Received, Discarded, Lost, Received, Received, Received, Received,
+        ] {
+            tracker.observe(reception);
+        }
+        // Gap again
+        for _ in 0..4 {
+            tracker.observe(Received);
+        }
+
+        let summary = tracker.summary();
+        assert_eq!(tracker.packets(), 28);
+        assert_eq!((tracker.lost(), tracker.discarded()), (3, 1));
+        assert_eq!(summary.burst_length, 4.0);
+        assert_eq!(summary.burst_density, 0.75);
+        assert_eq!(summary.gap_length, 12.0);
+        assert_eq!(summary.gap_density, 1.0 / 24.0);
+    }
+
+    #[test]
+    fn test_open_burst_counts() {
+        // ---
+        let mut tracker = BurstGapTracker::default();
+        for reception in [Received, Lost, Lost, Received, Lost] {
+            tracker.observe(reception);
+        }
+        let summary = tracker.summary();
+        assert_eq!(summary.burst_length, 4.0);
+        assert_eq!(summary.burst_density, 0.75);
+        assert_eq!(summary.gap_length, 0.5);
+        assert_eq!(summary.gap_density, 0.0);
+    }
+
+    #[test]
+    fn test_reception_history() {
+        // ---
+        let mut history = ReceptionHistory::default();
+        history.restart(100);
+        history.record(103, Received); // 101 and 102 lost
+        history.record(101, Received); // Reordered after all
+        history.record(102, Discarded);
+        history.record(99, Received); // Before the baseline
+        assert_eq!(
+            history.take(),
+            Some((100, vec![Received, Received, Discarded, Received]))
+        );
+        assert_eq!(history.tracker().packets(), 4);
+
+        // Reported numbers stay reported
+        assert_eq!(history.take(), None);
+        history.record(102, Received);
+        history.record(105, Received);
+        assert_eq!(history.take(), Some((104, vec![Lost, Received])));
+        assert_eq!(history.tracker().discarded(), 1);
+        assert_eq!(history.tracker().lost(), 1);
+    }
+
+    #[test]
+    fn test_reception_history_is_bounded() {
+        // ---
+        let mut history = ReceptionHistory::default();
+        history.restart(0);
+        history.record(MAX_RECEPTION_HISTORY as u64 + 9, Received);
+        assert_eq!(history.tracker().packets(), 10);
+        let (begin, slots) = history.take().unwrap();
+        assert_eq!(begin, 10);
+        assert_eq!(slots.len(), MAX_RECEPTION_HISTORY);
+    }
+
+    #[test]
+    fn test_voip_metrics_without_loss() {
+        // ---
+        let mut tracker = BurstGapTracker::default();
+        for _ in 0..500 {
+            tracker.observe(Received);
+        }
+        let delays = PlayoutDelays::new(&JitterBufferConfig::default(), Duration::from_millis(20));
+        let metrics = voip_metrics(7, &tracker, &delays, None);
+
+        assert_eq!((metrics.loss_rate, metrics.discard_rate), (0, 0));
+        assert_eq!((metrics.burst_density, metrics.gap_density), (0, 0));
+        assert_eq!((metrics.burst_duration, metrics.gap_duration), (0, 10_000));
+        assert_eq!(metrics.end_system_delay, 80);
+        assert_eq!((metrics.jb_nominal, metrics.jb_maximum), (60, 2000));
+        assert_eq!(metrics.round_trip_delay, 0);
+        assert_eq!(metrics.r_factor, 91);
+        assert_eq!(metrics.mos_lq, 44);
+        assert_eq!(metrics.mos_cq, 44);
+        assert_eq!(metrics.rx_config, 0xA0);
+    }
+
+    #[test]
+    fn test_mos_falls_with_loss_and_delay() {
+        // ---
+        let clean = r_factor(0.0, Duration::ZERO);
+        assert!((clean - 93.2).abs() < 1e-9);
+        assert!((mos(clean) - 4.41).abs() < 0.01);
+
+        let lossy = r_factor(0.05, Duration::ZERO);
+        assert!(mos(lossy) < mos(clean));
+        let late = r_factor(0.05, Duration::from_millis(300));
+        assert!(mos(late) < mos(lossy));
+        assert_eq!(mos(-5.0), 1.0);
+        assert_eq!(mos(120.0), 4.5);
+    }
+}
@@ -0,0 +1,67 @@
+//! Feature matrix smoke test.
+//!
+//! Runs in every feature combination CI builds (see the `features` job in
+//! `.github/workflows/rust.yml`) and exercises whichever feature-gated
+//! constructors this build has, checking the reported build info agrees.
+
+use receiver::{DecoderSet, MetricsContext, RtpPacket, PAYLOAD_TYPE_OPUS};
+
+/// G.711 PCMU, decoded only with the `formats` feature
+const PCMU: u8 = 0;
+
+#[test]
+fn test_build_info_matches_enabled_features() {
+    // ---
+    let info = receiver::build_info();
+    assert_eq!(info.binary(), "receiver");
+    assert_eq!(info.version(), env!("CARGO_PKG_VERSION"));
+    assert_eq!(
+        info.has_feature("receiver/playback"),
+        cfg!(feature = "playback")
+    );
+    assert_eq!(
+        info.has_feature("receiver/formats"),
+        cfg!(feature = "formats")
+    );
+    assert_eq!(
+        info.has_feature("receiver/metrics-server"),
+        cfg!(feature = "metrics-server")
+    );
+    assert_eq!(
+        info.has_feature("rtp-opus-common/metrics-server"),
+        cfg!(feature = "metrics-server")
+    );
+
+    let metrics = MetricsContext::new("test_features").expect("metrics");
+    metrics.set_build_info(&info);
+    let features = info.features().join(",");
+    let labels = [info.binary(), info.version(), info.git_hash(), &features];
+    assert_eq!(metrics.build_info.with_label_values(&labels).get(), 1);
+}
+
+#[test]
+fn test_feature_gated_constructors() {
+    // ---
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut packet = RtpPacket::new(0, 0, 0x1234_5678, vec![0xFF; 160]);
+    packet.payload_type = PCMU;
+    let decoded = decoders.decode(&packet).expect("decode");
+
+    #[cfg(feature = "formats")]
+    {
+        assert!(decoded.is_some_and(|samples| !samples.is_empty()));
+        let _ = receiver::G711Decoder::new(receiver::G711Law::ALaw);
+    }
+    #[cfg(not(feature = "formats"))]
+    {
+        assert!(decoded.is_none());
+        assert_eq!(decoders.stats()[&PCMU].unsupported, 1);
+    }
+
+    // Opening a device needs audio hardware, so only the constructor is
+    // exercised; failing for lack of a device is fine
+    #[cfg(feature = "playback")]
+    if let Err(e) = receiver::AudioPlayer::new() {
+        println!("No audio device ({e:#})");
+    }
+}
@@ -0,0 +1,89 @@
+//! Integration tests for receiver startup
+//!
+//! Packets that arrive while the audio sink is still opening must be
+//! buffered and played once it is ready, not counted as late or lost.
+
+use receiver::{
+    g711::PAYLOAD_TYPE_PCMU, receive_loop_with_setup, DecoderSet, DumpReason, MetricsContext,
+    NullSink, PlayoutEventKind, PlayoutHistory, ReceiveOptions, ReceiverStats, RtpPacket,
+    RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+/// Artificial audio sink setup delay
+const SINK_SETUP_DELAY: Duration = Duration::from_millis(300);
+
+/// Packets sent, one every 20ms
+const PACKET_COUNT: u16 = 40;
+
+#[tokio::test]
+async fn test_packets_during_slow_sink_setup_are_not_late_or_lost() {
+    // ---
+    let metrics = MetricsContext::new("test").expect("metrics");
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().expect("local_addr").port();
+
+    // The sender starts before the sink is ready
+    let sender = tokio::spawn(async move {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        for seq in 0..PACKET_COUNT {
+            let mut packet = RtpPacket::new(seq, seq as u32 * 160, 0x12345678, vec![0xFF; 160]);
+            packet.payload_type = PAYLOAD_TYPE_PCMU;
+            let data = packet.serialize().expect("serialize");
+            socket
+                .send_to(&data, ("127.0.0.1", port))
+                .await
+                .expect("send");
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+
+    let sink_setup = async {
+        tokio::time::sleep(SINK_SETUP_DELAY).await;
+        Ok(NullSink::new())
+    };
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut stats = ReceiverStats::default();
+    let history = Arc::new(PlayoutHistory::new(1024));
+    let options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_millis(200)),
+        history: Some(history.clone()),
+        ..Default::default()
+    };
+    let sink = receive_loop_with_setup(
+        &mut receiver,
+        &mut decoders,
+        sink_setup,
+        &mut stats,
+        &options,
+        &metrics,
+    )
+    .await
+    .expect("receive loop");
+    sender.await.expect("sender task");
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, PACKET_COUNT as u64);
+    assert_eq!(snapshot.packets_late, 0);
+    assert_eq!(snapshot.packets_lost, 0);
+    assert_eq!(metrics.packets_late_total.get(), 0);
+    assert_eq!(metrics.packets_lost_total.get(), 0);
+    assert_eq!(sink.frames, PACKET_COUNT as u64);
+
+    // Roughly 15 packets arrive during the 300ms setup
+    assert!(metrics.sink_setup_seconds.get() >= SINK_SETUP_DELAY.as_secs_f64());
+    assert!(metrics.sink_setup_buffered_packets.get() >= 5);
+
+    // Every packet's arrival and release is in the playout history,
+    // along with a few occupancy samples
+    let snapshot = history.snapshot(Duration::from_secs(60), DumpReason::Command);
+    let count = |kind| snapshot.events.iter().filter(|e| e.kind == kind).count();
+    assert_eq!(count(PlayoutEventKind::Arrival), PACKET_COUNT as usize);
+    assert_eq!(count(PlayoutEventKind::Release), PACKET_COUNT as usize);
+    assert_eq!(count(PlayoutEventKind::Concealment), 0);
+    assert!(count(PlayoutEventKind::Occupancy) >= 3);
+}
@@ -0,0 +1,295 @@
+//! RTP Opus tools - CLI binary.
+//!
+//! Hosts subcommands that drive the sender and receiver libraries together:
+//! a zero-configuration local demo and an input file probe.
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use tracing::{info, warn};
+
+#[cfg(feature = "playback")]
+use receiver::AudioPlayer;
+use receiver::NullSink;
+use rtp_opus::{build_info, run_demo, DemoConfig, DemoSummary};
+use rtp_opus_common::{handle_verbose_version, init_tracing, ColorWhen, NetworkSimulatorConfig};
+use sender::SilenceConfig;
+
+/// Packet loss rate injected by `--chaos`
+const CHAOS_LOSS_RATE: f64 = 0.05;
+
+/// Jitter (±ms) injected by `--chaos`
+const CHAOS_JITTER_MS: u32 = 20;
+
+/// Reordering rate injected by `--chaos`
+const CHAOS_REORDER_RATE: f64 = 0.05;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum ColorArg {
+    Auto,
+    Always,
+    Never,
+}
+
+impl From<ColorArg> for ColorWhen {
+    fn from(v: ColorArg) -> Self {
+        match v {
+            ColorArg::Auto => ColorWhen::Auto,
+            ColorArg::Always => ColorWhen::Always,
+            ColorArg::Never => ColorWhen::Never,
+        }
+    }
+}
+
+/// RTP Opus tools - Demos and utilities built on the sender and receiver
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    // ---
+    #[command(subcommand)]
+    command: Command,
+
+    /// Coloring
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = ColorArg::Auto,
+        help = "Coloring",
+        long_help = "Controls colored output.\n\n\
+                     auto: Enable colors when stdout is a TTY and EMACS is not set.\n\
+                     always: Always enable colors.\n\
+                     never: Disable colors."
+    )]
+    color: ColorArg,
+
+    /// With --version, print the full build report
+    #[arg(
+        long,
+        help = "With --version, also print crate versions, git commit, and features",
+        long_help = "Use as --version --verbose to print the versions of all linked crates,\n\
+                     the git commit, and the Cargo features this binary was built with."
+    )]
+    verbose: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Stream an audio file from a local sender to a local receiver
+    Demo(DemoArgs),
+
+    /// Analyze an audio file before streaming it
+    Probe(ProbeArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ProbeArgs {
+    // ---
+    /// Input audio file (WAV, FLAC, or MP3)
+    #[arg(
+        short,
+        long,
+        help = "Input audio file (WAV, FLAC, or MP3)",
+        long_help = "Path to the WAV, FLAC, or MP3 file to analyze."
+    )]
+    input: String,
+
+    /// Level below which a frame counts as silent
+    #[arg(
+        long,
+        default_value_t = -50.0,
+        allow_hyphen_values = true,
+        help = "Level (dBFS) below which a frame counts as silent",
+        long_help = "RMS level in dBFS below which a 20ms frame counts as silent."
+    )]
+    silence_threshold_dbfs: f64,
+
+    /// Warn above this percentage of silent frames
+    #[arg(
+        long,
+        default_value_t = 50.0,
+        help = "Warn above this percentage of silent frames",
+        long_help = "Warn when more than this percentage of frames is silent."
+    )]
+    max_silent_percent: f64,
+
+    /// Warn on silent runs at least this long
+    #[arg(
+        long,
+        default_value_t = 2.0,
+        help = "Warn on silent runs at least this many seconds long",
+        long_help = "Warn when a single run of silent frames lasts at least this many seconds."
+    )]
+    max_silent_run_secs: f64,
+}
+
+#[derive(clap::Args, Debug)]
+struct DemoArgs {
+    // ---
+    /// Input audio file (WAV, FLAC, or MP3)
+    #[arg(
+        short,
+        long,
+        help = "Input audio file (WAV, FLAC, or MP3)",
+        long_help = "Path to an input WAV, FLAC, or MP3 file to stream through the local demo.\n\n\
+                     The file is streamed once; the demo exits when it completes."
+    )]
+    input: String,
+
+    /// Inject simulated loss, jitter, and reordering
+    #[arg(
+        long,
+        help = "Inject simulated loss, jitter, and reordering",
+        long_help = "Route packets through an in-process network simulator.\n\n\
+                     Applies 5% loss, ±20ms jitter, and 5% reordering between the\n\
+                     sender and the receiver."
+    )]
+    chaos: bool,
+
+    /// Random seed for `--chaos`
+    #[arg(
+        long,
+        requires = "chaos",
+        help = "Random seed for --chaos",
+        long_help = "Seed for the network simulator so --chaos runs are reproducible."
+    )]
+    seed: Option<u64>,
+
+    /// Discard audio instead of playing it
+    #[arg(
+        long,
+        help = "Discard audio instead of playing it",
+        long_help = "Decode audio but do not open an output device.\n\n\
+                     Also used automatically when no output device is available."
+    )]
+    no_playback: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // ---
+    let build = build_info();
+    handle_verbose_version(&build);
+    let args = Args::parse();
+    init_tracing(args.color.into())?;
+    info!("Starting {build}");
+
+    match args.command {
+        Command::Demo(demo) => run_demo_command(demo).await,
+        Command::Probe(probe) => run_probe_command(probe).await,
+    }
+}
+
+async fn run_probe_command(args: ProbeArgs) -> Result<()> {
+    // ---
+    let config = SilenceConfig {
+        threshold_dbfs: args.silence_threshold_dbfs,
+        max_silent_percent: args.max_silent_percent,
+        max_silent_run_secs: args.max_silent_run_secs,
+    };
+
+    let input_path = args.input.clone();
+    let probe_config = config.clone();
+    let (audio, report) = match tokio::task::spawn_blocking(move || {
+        sender::read_audio_with_silence(input_path, &probe_config)
+    })
+    .await
+    .context("audio reading task failed")?
+    {
+        Ok(result) => result,
+        Err(err) => {
+            tracing::error!("Failed to read audio file: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    info!(
+        "{}: {:.2}s, {}Hz, {} channel(s)",
+        args.input,
+        audio.duration_secs(),
+        audio.original_sample_rate,
+        audio.original_channels
+    );
+    info!("Silence: {report}");
+
+    let warnings = report.warnings(&config);
+    for warning in &warnings {
+        warn!("{warning}");
+    }
+    if warnings.is_empty() {
+        info!("No problems found");
+    }
+    Ok(())
+}
+
+async fn run_demo_command(args: DemoArgs) -> Result<()> {
+    // ---
+    info!("Input file: {}", args.input);
+
+    let input_path = args.input.clone();
+    let audio = match tokio::task::spawn_blocking(move || sender::read_audio(input_path))
+        .await
+        .context("audio reading task failed")?
+    {
+        Ok(audio) => audio,
+        Err(err) => {
+            tracing::error!("Failed to read audio file: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut config = DemoConfig::default();
+    if args.chaos {
+        config.chaos = Some(NetworkSimulatorConfig {
+            loss_rate: CHAOS_LOSS_RATE,
+            jitter_ms: CHAOS_JITTER_MS,
+            reorder_rate: CHAOS_REORDER_RATE,
+            corrupt_rate: 0.0,
+            seed: args.seed,
+        });
+        info!("Chaos: {:?}", config.chaos);
+    }
+
+    #[cfg(feature = "playback")]
+    let player = if args.no_playback {
+        None
+    } else {
+        match AudioPlayer::new() {
+            Ok(player) => Some(player),
+            Err(e) => {
+                warn!("No audio playback ({e:#}), discarding audio");
+                None
+            }
+        }
+    };
+    #[cfg(not(feature = "playback"))]
+    let player: Option<NullSink> = {
+        if !args.no_playback {
+            warn!("Built without the playback feature, discarding audio");
+        }
+        None
+    };
+
+    let summary = match player {
+        Some(mut player) => run_demo(&audio, &config, &mut player).await?,
+        None => run_demo(&audio, &config, &mut NullSink::new()).await?,
+    };
+
+    report(&summary);
+    Ok(())
+}
+
+fn report(summary: &DemoSummary) {
+    // ---
+    info!("Demo complete: {summary}");
+    if let Some(chaos) = &summary.chaos {
+        info!(
+            "Simulator: {} lost, {} delayed, {} reordered of {} sent",
+            chaos.packets_lost, chaos.packets_delayed, chaos.packets_reordered, chaos.packets_sent
+        );
+    }
+    if summary.is_clean() {
+        info!("End-to-end quality: clean");
+    } else {
+        warn!("End-to-end quality: degraded");
+    }
+}
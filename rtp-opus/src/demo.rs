@@ -0,0 +1,356 @@
+//! Local sender → receiver demo over loopback UDP.
+//!
+//! Runs the sender pipeline and the receiver pipeline inside one process,
+//! optionally passing packets through a [`NetworkSimulator`] relay, and
+//! reports the end-to-end quality once the input has been fully streamed.
+
+use anyhow::{Context, Result};
+use receiver::{
+    codec, receive_loop, AudioSink, DecoderSet, JitterBufferConfig, ReceiveOptions, ReceiverStats,
+    RtpReceiver,
+};
+use rtp_opus_common::{
+    is_rtcp, MetricsContext, NetworkSimulator, NetworkSimulatorConfig, NetworkSimulatorStats,
+    RtpPacket, PAYLOAD_TYPE_OPUS,
+};
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// How often the chaos relay polls the simulator for deliverable packets
+const RELAY_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Minimum quiet period after which the stream is considered finished
+const MIN_IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Demo configuration.
+#[derive(Debug, Clone)]
+pub struct DemoConfig {
+    // ---
+    /// Sender pacing between packets in milliseconds
+    pub interval_ms: u64,
+
+    /// Receiver jitter buffer configuration
+    pub jitter: JitterBufferConfig,
+
+    /// Network impairments to inject between sender and receiver
+    pub chaos: Option<NetworkSimulatorConfig>,
+
+    /// How often the live status line is logged
+    pub status_interval: Duration,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            interval_ms: codec::FRAME_DURATION_MS as u64,
+            jitter: JitterBufferConfig::default(),
+            chaos: None,
+            status_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl DemoConfig {
+    // ---
+    fn idle_timeout(&self) -> Duration {
+        // ---
+        let jitter = self
+            .chaos
+            .as_ref()
+            .map_or(0, |c| 2 * c.jitter_ms as u64 + self.interval_ms);
+        MIN_IDLE_TIMEOUT.max(Duration::from_millis(10 * self.interval_ms + jitter))
+    }
+}
+
+/// End-to-end quality summary of a demo run.
+#[derive(Debug, Clone)]
+pub struct DemoSummary {
+    // ---
+    /// Packets transmitted by the sender
+    pub packets_sent: u64,
+
+    /// Packets accepted by the receiver
+    pub packets_received: u64,
+
+    /// Packets detected as lost via sequence gaps
+    pub packets_lost: u64,
+
+    /// Packets that arrived too late to be played
+    pub packets_late: u64,
+
+    /// Packets that arrived out of order
+    pub packets_reordered: u64,
+
+    /// Frames decoded and handed to the sink
+    pub frames_played: u64,
+
+    /// Frames synthesized by loss concealment
+    pub frames_concealed: u64,
+
+    /// Lost frames rebuilt from the in-band FEC of the packet after them
+    pub frames_fec_recovered: u64,
+
+    /// Payloads that failed to decode
+    pub decode_failures: u64,
+
+    /// Final RFC 3550 interarrival jitter estimate
+    pub jitter: Duration,
+
+    /// Impairments applied by the chaos relay, if enabled
+    pub chaos: Option<NetworkSimulatorStats>,
+}
+
+impl DemoSummary {
+    // ---
+    /// Returns `true` if every sent frame was received, decoded, and played.
+    pub fn is_clean(&self) -> bool {
+        // ---
+        self.packets_lost == 0
+            && self.packets_late == 0
+            && self.decode_failures == 0
+            && self.frames_concealed == 0
+            && self.frames_fec_recovered == 0
+            && self.frames_played == self.packets_sent
+    }
+
+    /// Percentage of sent packets that never reached the receiver.
+    pub fn loss_percentage(&self) -> f64 {
+        // ---
+        if self.packets_sent == 0 {
+            0.0
+        } else {
+            let missing = self.packets_sent.saturating_sub(self.packets_received);
+            missing as f64 / self.packets_sent as f64 * 100.0
+        }
+    }
+}
+
+impl fmt::Display for DemoSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(
+            f,
+            "sent {}, received {} ({:.2}% loss), {} reordered, {} late, \
+             played {} frames ({} concealed, {} from FEC, {} decode failures), jitter {:.2}ms",
+            self.packets_sent,
+            self.packets_received,
+            self.loss_percentage(),
+            self.packets_reordered,
+            self.packets_late,
+            self.frames_played,
+            self.frames_concealed,
+            self.frames_fec_recovered,
+            self.decode_failures,
+            self.jitter.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// Receiver-side results gathered by [`receive_until_idle`].
+struct ReceiveOutcome {
+    stats: ReceiverStats,
+    frames_played: u64,
+    frames_concealed: u64,
+    frames_fec_recovered: u64,
+    decode_failures: u64,
+}
+
+/// Streams `audio` through a local sender and receiver and reports quality.
+///
+/// The receiver binds an ephemeral loopback port; the sender streams the
+/// audio once (no looping). When `config.chaos` is set, a relay socket
+/// between the two applies the simulated impairments. Returns once the
+/// sender has finished and the receiver has gone idle.
+///
+/// # Errors
+///
+/// Returns error if the audio is empty, sockets cannot be bound, or the
+/// sender fails.
+pub async fn run_demo(
+    audio: &AudioData,
+    config: &DemoConfig,
+    sink: &mut impl AudioSink,
+) -> Result<DemoSummary> {
+    // ---
+    if audio.frame_count() == 0 {
+        anyhow::bail!("input has no complete frames to stream");
+    }
+
+    let mut receiver = RtpReceiver::new(0)
+        .await
+        .context("failed to create receiver")?;
+    let receiver_addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr()?.port()));
+
+    let relay = match &config.chaos {
+        Some(sim_config) => {
+            let socket = UdpSocket::bind("127.0.0.1:0")
+                .await
+                .context("failed to bind chaos relay")?;
+            Some((socket, NetworkSimulator::new(sim_config.clone())))
+        }
+        None => None,
+    };
+    let send_addr = match &relay {
+        Some((socket, _)) => socket.local_addr()?,
+        None => receiver_addr,
+    };
+
+    let mut encoder = OpusEncoderWrapper::new().context("failed to create encoder")?;
+    let mut sender = RtpSender::new(send_addr.to_string())
+        .await
+        .context("failed to create sender")?;
+    let metrics = MetricsContext::new("demo")?;
+    let ssrc = rand::random::<u32>();
+    info!(
+        "Demo: streaming {:.2}s ({} frames) to {} via {}",
+        audio.duration_secs(),
+        audio.frame_count(),
+        receiver_addr,
+        if relay.is_some() {
+            "chaos relay"
+        } else {
+            "loopback"
+        }
+    );
+
+    let (done_tx, done_rx) = watch::channel(false);
+
+    let send = async {
+        let result = stream_audio(
+            audio,
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            ssrc,
+            &StreamOptions {
+                interval_ms: config.interval_ms,
+                ..Default::default()
+            },
+        )
+        .await;
+        let _ = done_tx.send(true);
+        result
+    };
+
+    let chaos = async {
+        match relay {
+            Some((socket, sim)) => run_relay(socket, sim, receiver_addr, done_rx)
+                .await
+                .map(Some),
+            None => Ok(None),
+        }
+    };
+
+    let receive = receive_until_idle(&mut receiver, config, sink);
+
+    let (sent, chaos, outcome) = tokio::try_join!(send, chaos, receive)?;
+
+    let snapshot = outcome.stats.snapshot();
+    Ok(DemoSummary {
+        packets_sent: sent.packets_sent,
+        packets_received: snapshot.packets_received,
+        packets_lost: snapshot.packets_lost,
+        packets_late: snapshot.packets_late,
+        packets_reordered: snapshot.packets_reordered,
+        frames_played: outcome.frames_played,
+        frames_concealed: outcome.frames_concealed,
+        frames_fec_recovered: outcome.frames_fec_recovered,
+        decode_failures: outcome.decode_failures,
+        jitter: Duration::from_secs_f64(snapshot.jitter_ms / 1000.0),
+        chaos,
+    })
+}
+
+/// Forwards datagrams from the sender to the receiver through the simulator.
+///
+/// Runs until the sender has finished and every in-flight packet has been
+/// delivered or dropped. RTCP, such as sender reports, is passed on as it
+/// comes: the simulator only models RTP.
+async fn run_relay(
+    socket: UdpSocket,
+    mut sim: NetworkSimulator,
+    target: SocketAddr,
+    done: watch::Receiver<bool>,
+) -> Result<NetworkSimulatorStats> {
+    // ---
+    let mut buf = vec![0u8; 2048];
+    let mut poll = tokio::time::interval(RELAY_POLL_INTERVAL);
+
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let (len, _) = received.context("chaos relay receive failed")?;
+                if is_rtcp(&buf[..len]) {
+                    socket
+                        .send_to(&buf[..len], target)
+                        .await
+                        .context("chaos relay send failed")?;
+                } else {
+                    match RtpPacket::deserialize(&buf[..len]) {
+                        Ok(packet) => sim.send(packet),
+                        Err(e) => warn!("Chaos relay dropped invalid packet: {}", e),
+                    }
+                }
+            }
+            _ = poll.tick() => {}
+        }
+
+        while let Some(packet) = sim.receive() {
+            let data = packet.serialize()?;
+            socket
+                .send_to(&data, target)
+                .await
+                .context("chaos relay send failed")?;
+        }
+
+        if *done.borrow() && sim.in_flight() == 0 {
+            return Ok(sim.stats());
+        }
+    }
+}
+
+/// Runs the receive pipeline until the stream goes idle.
+async fn receive_until_idle(
+    receiver: &mut RtpReceiver,
+    config: &DemoConfig,
+    sink: &mut impl AudioSink,
+) -> Result<ReceiveOutcome> {
+    // ---
+    let metrics = MetricsContext::new("demo_receiver")?;
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut stats = ReceiverStats::new(config.status_interval);
+    let options = ReceiveOptions {
+        jitter: config.jitter.clone(),
+        idle_timeout: Some(config.idle_timeout()),
+        ..Default::default()
+    };
+
+    let _ticker = stats.spawn_ticker();
+    receive_loop(
+        receiver,
+        &mut decoders,
+        sink,
+        &mut stats,
+        &options,
+        &metrics,
+    )
+    .await?;
+
+    let decodes = decoders.stats().values();
+    let (frames_played, decode_failures) = decodes.fold((0, 0), |(played, failed), pt| {
+        (played + pt.decoded, failed + pt.failed + pt.unsupported)
+    });
+    Ok(ReceiveOutcome {
+        stats,
+        frames_played,
+        frames_concealed: metrics.frames_concealed_total.get(),
+        frames_fec_recovered: metrics.frames_fec_recovered_total.get(),
+        decode_failures,
+    })
+}
@@ -0,0 +1,23 @@
+//! RTP Opus Tools Library
+//!
+//! Utilities that combine the sender and receiver libraries in a single
+//! process. Everything here is built only on the public `sender`,
+//! `receiver`, and `rtp_opus_common` APIs, so it also serves as an
+//! example of integrating them.
+
+pub mod demo;
+
+pub use demo::{run_demo, DemoConfig, DemoSummary};
+
+use rtp_opus_common::BuildInfo;
+
+/// Returns the version, commit, and enabled features of this crate and
+/// the crates it links.
+pub fn build_info() -> BuildInfo {
+    // ---
+    BuildInfo::new("rtp-opus", env!("CARGO_PKG_VERSION"))
+        .with_feature("rtp-opus/playback", cfg!(feature = "playback"))
+        .with_feature("rtp-opus/formats", cfg!(feature = "formats"))
+        .including(sender::build_info())
+        .including(receiver::build_info())
+}
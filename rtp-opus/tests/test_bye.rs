@@ -0,0 +1,128 @@
+//! Integration test for RTCP BYE.
+//!
+//! Streams to a receiver and ends the stream with a BYE, and checks that
+//! the receive loop plays out the stream and returns with its stats
+//! complete, or, without `exit_on_bye`, starts over for the next stream.
+
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverStats, RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::MetricsContext;
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+
+/// Packets sent per stream
+const PACKETS: usize = 50;
+
+/// Streams `PACKETS` frames of a tone on `ssrc`.
+async fn stream(sender: &mut RtpSender, ssrc: u32, metrics: &MetricsContext) {
+    // ---
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    stream_audio(
+        &audio,
+        &mut encoder,
+        sender,
+        metrics,
+        ssrc,
+        &StreamOptions::default(),
+    )
+    .await
+    .expect("stream");
+}
+
+#[tokio::test]
+async fn test_bye_ends_receive_loop() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().unwrap().port();
+    let mut sender = RtpSender::new(format!("127.0.0.1:{port}"))
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let options = ReceiveOptions {
+        exit_on_bye: true,
+        ..Default::default()
+    };
+
+    // No idle timeout: only the BYE ends the loop
+    let send = async {
+        stream(&mut sender, 0x2072_0001, &sender_metrics).await;
+        assert!(sender.send_bye("end of file").await);
+    };
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &receiver_metrics,
+    );
+    let (_, received) = tokio::time::timeout(Duration::from_secs(10), async {
+        tokio::join!(send, receive)
+    })
+    .await
+    .expect("receive loop ended by BYE");
+    received.expect("receive");
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, PACKETS as u64);
+    assert_eq!(snapshot.packets_lost, 0);
+    assert_eq!(
+        receiver_metrics.packets_received_total.get(),
+        PACKETS as u64
+    );
+}
+
+#[tokio::test]
+async fn test_bye_awaits_next_stream() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().unwrap().port();
+    let mut sender = RtpSender::new(format!("127.0.0.1:{port}"))
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_millis(500)),
+        ..Default::default()
+    };
+
+    // Two streams one after the other, the first ended by a BYE and the
+    // second going idle
+    let send = async {
+        stream(&mut sender, 0x2072_0001, &sender_metrics).await;
+        assert!(sender.send_bye("end of file").await);
+        stream(&mut sender, 0x2072_0002, &sender_metrics).await;
+    };
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &receiver_metrics,
+    );
+    let (_, received) = tokio::join!(send, receive);
+    received.expect("receive");
+
+    // The new stream's sequence numbers are not counted against the old
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, 2 * PACKETS as u64);
+    assert_eq!(snapshot.packets_lost, 0);
+}
@@ -0,0 +1,94 @@
+//! Integration tests for the local sender → receiver demo.
+//!
+//! Runs the full demo pipeline on the bundled sample file with a null sink.
+
+use receiver::NullSink;
+use rtp_opus::{run_demo, DemoConfig};
+use rtp_opus_common::NetworkSimulatorConfig;
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../samples/sine_1k.wav");
+
+/// Streams the fixture over clean loopback and expects a clean summary.
+#[tokio::test]
+async fn test_demo_fixture_clean_summary() {
+    // ---
+    let audio = sender::read_wav(FIXTURE).expect("fixture should load");
+    let config = DemoConfig {
+        interval_ms: 1, // Faster than real time; loopback keeps up
+        ..Default::default()
+    };
+    let mut sink = NullSink::new();
+
+    let summary = run_demo(&audio, &config, &mut sink)
+        .await
+        .expect("demo failed");
+
+    println!("{summary}");
+    assert!(summary.is_clean(), "{summary}");
+    assert_eq!(summary.packets_sent, audio.frame_count() as u64);
+    assert_eq!(sink.frames, summary.frames_played);
+    assert!(summary.chaos.is_none());
+}
+
+/// Chaos mode routes every packet through the simulator relay.
+#[tokio::test]
+async fn test_demo_chaos_reports_simulator_stats() {
+    // ---
+    let mut audio = sender::read_wav(FIXTURE).expect("fixture should load");
+    audio.samples.truncate(audio.samples.len() / 10);
+    let config = DemoConfig {
+        interval_ms: 1,
+        chaos: Some(NetworkSimulatorConfig {
+            loss_rate: 0.0,
+            jitter_ms: 5,
+            reorder_rate: 0.0,
+            corrupt_rate: 0.0,
+            seed: Some(42),
+        }),
+        ..Default::default()
+    };
+
+    let summary = run_demo(&audio, &config, &mut NullSink::new())
+        .await
+        .expect("demo failed");
+
+    println!("{summary}");
+    let chaos = summary.chaos.expect("simulator stats");
+    assert_eq!(chaos.packets_sent, summary.packets_sent);
+    assert_eq!(summary.packets_received, summary.packets_sent);
+}
+
+/// Packets lost on the way are concealed, and every packet received is
+/// played, the last ones included.
+#[tokio::test]
+async fn test_demo_conceals_lost_packets() {
+    // ---
+    let mut audio = sender::read_wav(FIXTURE).expect("fixture should load");
+    audio.samples.truncate(audio.samples.len() / 10);
+    let config = DemoConfig {
+        interval_ms: 1,
+        chaos: Some(NetworkSimulatorConfig {
+            loss_rate: 0.1,
+            jitter_ms: 0,
+            reorder_rate: 0.0,
+            corrupt_rate: 0.0,
+            seed: Some(7),
+        }),
+        ..Default::default()
+    };
+    let mut sink = NullSink::new();
+
+    let summary = run_demo(&audio, &config, &mut sink)
+        .await
+        .expect("demo failed");
+
+    println!("{summary}");
+    assert!(!summary.is_clean());
+    assert!(summary.packets_received < summary.packets_sent);
+    assert!(summary.frames_concealed > 0, "{summary}");
+    assert_eq!(summary.frames_played, summary.packets_received);
+    assert_eq!(
+        sink.frames,
+        summary.frames_played + summary.frames_concealed + summary.frames_fec_recovered
+    );
+}
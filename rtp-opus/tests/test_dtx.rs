@@ -0,0 +1,99 @@
+//! Integration test for sender DTX.
+//!
+//! Streams tone bursts separated by silence with DTX frames
+//! suppressed, and checks that the receiver treats the resulting timestamp
+//! gaps as silence rather than packet loss, with the marker bit flagging
+//! each talkspurt that follows one.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverStats, RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::MetricsContext;
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+
+/// One second of tone followed by two seconds of silence, in frames.
+const TALK_FRAMES: usize = 50;
+const SILENT_FRAMES: usize = 100;
+
+#[tokio::test]
+async fn test_dtx_gaps_are_not_loss() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let receiver_addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+
+    let mut samples = Vec::new();
+    for _ in 0..3 {
+        samples.extend(
+            (0..TALK_FRAMES * SAMPLES_PER_FRAME).map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16),
+        );
+        samples.extend(std::iter::repeat_n(0i16, SILENT_FRAMES * SAMPLES_PER_FRAME));
+    }
+    let audio = AudioData::from_samples(samples);
+
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    encoder.set_dtx(true);
+    let mut sender = RtpSender::new(receiver_addr.to_string())
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions {
+        interval_ms: 2, // Faster than real time, but paced so nothing overflows
+        suppress_dtx: true,
+        ..Default::default()
+    };
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        0xD7D7_0001,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+
+    // Most of each silence was not transmitted
+    let suppressed = sender_metrics.frames_dtx_suppressed_total.get();
+    assert!(
+        suppressed >= 3 * SILENT_FRAMES as u64 * 3 / 4,
+        "{suppressed}"
+    );
+
+    // ...and none of it counts as loss or gets concealed
+    let snapshot = stats.snapshot();
+    assert_eq!(
+        snapshot.packets_received,
+        sender_metrics.packets_sent_total.get()
+    );
+    assert_eq!(snapshot.packets_lost, 0);
+    assert_eq!(receiver_metrics.packets_lost_total.get(), 0);
+    assert_eq!(receiver_metrics.frames_concealed_total.get(), 0);
+
+    // Every burst starts a talkspurt; DTX keepalive frames in the
+    // silences may start more
+    assert!(snapshot.talkspurts >= 3, "{}", snapshot.talkspurts);
+    assert!(snapshot.talkspurts < snapshot.packets_received / 10);
+}
@@ -0,0 +1,236 @@
+//! Integration tests for Opus in-band FEC.
+//!
+//! Encodes a speech-like signal, drops packets through the
+//! `NetworkSimulator`, and checks that the receiver rebuilds isolated
+//! losses from the FEC in the following packet instead of concealing them,
+//! both in the decode path alone and through the receive loop.
+
+use std::time::Duration;
+
+use receiver::{DecoderSet, NullSink, ReceiveOptions, ReceiverStats, Recovery, RtpReceiver};
+use rtp_opus_common::{
+    MetricsContext, NetworkSimulator, NetworkSimulatorConfig, RtpPacket, PAYLOAD_TYPE_OPUS,
+};
+use sender::codec::{SAMPLES_PER_FRAME, SAMPLE_RATE};
+use sender::{OpusEncoderConfig, OpusEncoderWrapper, RtpSender};
+
+const FRAMES: usize = 250;
+
+/// Five seconds of voiced syllables with a gliding pitch and short pauses.
+///
+/// The pitch glide keeps concealment from simply extrapolating the previous
+/// frame; the pauses let the encoder's VAD tell speech from background, as
+/// SILK only spends LBRR on active speech.
+fn speech_like() -> Vec<i16> {
+    // ---
+    let mut phase = 0.0f32;
+    let mut seed = 0x2003u32;
+    (0..FRAMES * SAMPLES_PER_FRAME)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let pitch = 190.0 + 50.0 * (t * 1.3 * std::f32::consts::TAU).sin();
+            phase += pitch / SAMPLE_RATE as f32 * std::f32::consts::TAU;
+            let envelope = (((t * 3.0).fract() * std::f32::consts::PI).sin() * 1.5).min(1.0);
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let breath = (seed >> 16) as i16 as f32 / 32768.0;
+            let voice = phase.sin() + 0.6 * (2.0 * phase).sin() + 0.3 * (3.0 * phase).sin();
+            ((voice + 0.2 * breath) * envelope * 7000.0) as i16
+        })
+        .collect()
+}
+
+/// Encodes `samples` into one RTP packet per frame.
+fn encode(samples: &[i16], fec: bool) -> Vec<RtpPacket> {
+    // ---
+    let mut encoder = OpusEncoderWrapper::with_config(OpusEncoderConfig {
+        fec,
+        expected_loss_pct: 10,
+        ..Default::default()
+    })
+    .expect("encoder");
+
+    samples
+        .chunks_exact(SAMPLES_PER_FRAME)
+        .enumerate()
+        .map(|(i, frame)| {
+            let payload = encoder.encode(frame).expect("encode");
+            let timestamp = (i * SAMPLES_PER_FRAME) as u32;
+            let mut packet = RtpPacket::new(i as u16, timestamp, 0x5EC0_F00D, payload);
+            packet.payload_type = PAYLOAD_TYPE_OPUS;
+            packet
+        })
+        .collect()
+}
+
+/// Result of playing a stream through the receiver's decode path.
+struct Playout {
+    // ---
+    /// One decoded or recovered frame per sequence number played
+    frames: Vec<Option<Vec<i16>>>,
+    fec_recovered: usize,
+    concealed: usize,
+}
+
+/// Decodes the packets that arrived, filling gaps the way `receive_loop` does.
+fn play(packets: &[RtpPacket]) -> Playout {
+    // ---
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut playout = Playout {
+        frames: vec![None; FRAMES],
+        fec_recovered: 0,
+        concealed: 0,
+    };
+
+    for packet in packets {
+        let recovered = decoders.recover_gap(packet);
+        let first = packet.sequence as usize - recovered.len();
+        for (offset, frame) in recovered.into_iter().enumerate() {
+            match frame.recovery {
+                Recovery::Fec => playout.fec_recovered += 1,
+                Recovery::Concealed => playout.concealed += 1,
+            }
+            playout.frames[first + offset] = Some(frame.samples);
+        }
+        let samples = decoders.decode(packet).expect("decode").expect("opus");
+        playout.frames[packet.sequence as usize] = Some(samples);
+    }
+
+    playout
+}
+
+/// Sends every packet through a lossy simulator and returns what arrived.
+fn transmit(packets: &[RtpPacket]) -> Vec<RtpPacket> {
+    // ---
+    let mut sim = NetworkSimulator::new(NetworkSimulatorConfig {
+        loss_rate: 0.10,
+        jitter_ms: 0,
+        reorder_rate: 0.0,
+        corrupt_rate: 0.0,
+        seed: Some(2003),
+    });
+
+    let mut arrived = Vec::new();
+    for packet in packets {
+        sim.send(packet.clone());
+        while let Some(packet) = sim.receive() {
+            arrived.push(packet);
+        }
+    }
+    arrived
+}
+
+/// Squared error of the frames at `lost` against the loss-free decode.
+fn error_energy(stream: &[RtpPacket], received: &Playout, lost: &[usize]) -> f64 {
+    // ---
+    let clean = play(stream);
+    lost.iter()
+        .map(|&seq| {
+            let reference = clean.frames[seq].as_ref().expect("clean frame");
+            let played = received.frames[seq].as_ref().expect("recovered frame");
+            reference
+                .iter()
+                .zip(played)
+                .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+                .sum::<f64>()
+        })
+        .sum()
+}
+
+#[test]
+fn test_fec_recovers_isolated_losses() {
+    // ---
+    let signal = speech_like();
+    let protected = encode(&signal, true);
+    let plain = encode(&signal, false);
+
+    // Same seed, so both streams lose the same packets
+    let protected_arrived = transmit(&protected);
+    let plain_arrived = transmit(&plain);
+    let arrived: Vec<u16> = protected_arrived.iter().map(|p| p.sequence).collect();
+    assert_eq!(
+        arrived,
+        plain_arrived.iter().map(|p| p.sequence).collect::<Vec<_>>()
+    );
+
+    // FEC can only help when the packet after a loss arrives
+    let isolated: Vec<usize> = (1..FRAMES - 1)
+        .filter(|&seq| {
+            let has = |s: usize| arrived.contains(&(s as u16));
+            !has(seq) && has(seq - 1) && has(seq + 1)
+        })
+        .collect();
+    assert!(
+        isolated.len() >= 10,
+        "only {} isolated losses",
+        isolated.len()
+    );
+
+    let with_fec = play(&protected_arrived);
+    let without_fec = play(&plain_arrived);
+    // Losses in the pauses between syllables carry no LBRR and are concealed
+    assert!(
+        with_fec.fec_recovered * 4 >= isolated.len() * 3,
+        "{} recovered via FEC, {} isolated losses",
+        with_fec.fec_recovered,
+        isolated.len()
+    );
+    assert_eq!(without_fec.fec_recovered, 0);
+    assert!(without_fec.concealed >= isolated.len());
+
+    // FEC frames track the original far more closely than PLC does
+    let fec_error = error_energy(&protected, &with_fec, &isolated);
+    let plc_error = error_energy(&plain, &without_fec, &isolated);
+    assert!(
+        fec_error * 2.0 < plc_error,
+        "FEC error {fec_error:.3e} vs PLC error {plc_error:.3e}"
+    );
+}
+
+#[tokio::test]
+async fn test_receive_loop_recovers_isolated_losses_from_fec() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().unwrap().port();
+    let mut sender = RtpSender::new(format!("127.0.0.1:{port}"))
+        .await
+        .expect("sender");
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_millis(500)),
+        ..Default::default()
+    };
+
+    // Drop one packet every half second, each mid-syllable, so the packet
+    // after it carries LBRR
+    let packets = encode(&speech_like(), true);
+    let dropped: Vec<usize> = (0..FRAMES / 25).map(|i| 25 * i + 12).collect();
+    let send = async {
+        for packet in &packets {
+            if !dropped.contains(&(packet.sequence as usize)) {
+                sender.send(packet).await.expect("send");
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+    };
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &metrics,
+    );
+    let (_, received) = tokio::join!(send, receive);
+    received.expect("receive");
+
+    assert_eq!(
+        metrics.frames_fec_recovered_total.get(),
+        dropped.len() as u64
+    );
+    assert_eq!(metrics.frames_concealed_total.get(), 0);
+    assert_eq!(sink.frames, FRAMES as u64);
+}
@@ -0,0 +1,96 @@
+//! Integration test for NAT keepalives during silence.
+//!
+//! Streams two passes of a tone with a minute of skipped silence between
+//! them, on a paused clock, and checks that the sender kept the path open
+//! with keepalives every 15 seconds of the gap, and that the receiver
+//! discarded them without counting a packet, a loss, or a decoded frame.
+
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverStats, RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::MetricsContext;
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::network::DEFAULT_KEEPALIVE_INTERVAL;
+use sender::{stream_audio, AudioData, GapPolicy, OpusEncoderWrapper, RtpSender, StreamOptions};
+
+/// Frames in each pass
+const FRAMES: usize = 25;
+
+/// Silence between the passes
+const GAP: Duration = Duration::from_secs(60);
+
+#[tokio::test(start_paused = true)]
+async fn test_keepalives_hold_a_silent_gap_open() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().unwrap().port();
+
+    let tone: Vec<i16> = (0..FRAMES * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let mut sender = RtpSender::new(format!("127.0.0.1:{port}"))
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions {
+        loop_audio: true,
+        loop_count: 2,
+        loop_gap: GAP,
+        gap: GapPolicy::Skip,
+        ..Default::default()
+    };
+
+    // Waits out the gap only because keepalives keep arriving
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(DEFAULT_KEEPALIVE_INTERVAL + Duration::from_secs(5)),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        0x2058_2058,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+
+    // One keepalive per 15 seconds of the 60 second gap
+    let keepalives = GAP.as_secs() / DEFAULT_KEEPALIVE_INTERVAL.as_secs();
+    let sender_stats = sender.stats();
+    assert_eq!(sender_stats.keepalives_sent, keepalives);
+    assert_eq!(sender_stats.packets_sent, 2 * FRAMES as u64);
+    assert_eq!(sender_metrics.keepalives_sent_total.get(), keepalives);
+
+    // All of them arrived and were set aside
+    assert_eq!(receiver.keepalives_received(), keepalives);
+    assert_eq!(receiver_metrics.keepalives_received_total.get(), keepalives);
+    assert_eq!(receiver.stats().packets_dropped, 0);
+
+    // Neither pass lost a packet or decoded a frame it should not have
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, 2 * FRAMES as u64);
+    assert_eq!(snapshot.packets_lost, 0);
+    let opus = decoders.stats()[&PAYLOAD_TYPE_OPUS];
+    assert_eq!(opus.failed, 0);
+    assert_eq!(opus.decoded, 2 * FRAMES as u64);
+}
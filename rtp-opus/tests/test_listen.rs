@@ -0,0 +1,111 @@
+//! Integration test for symmetric RTP (sender listen mode).
+//!
+//! A receiver on an ephemeral port punches through to a listening sender,
+//! which must learn that port from the hello alone and stream the audio
+//! back to it, ignoring a stranger that tries to take the stream over.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverNetworkConfig, ReceiverStats, RtpReceiver,
+    PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::MetricsContext;
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{
+    stream_audio, AudioData, OpusEncoderWrapper, RtpSender, SenderNetworkConfig, StreamOptions,
+};
+use tokio::net::UdpSocket;
+
+/// Packets sent in total
+const PACKETS: usize = 100;
+
+#[tokio::test]
+async fn test_listening_sender_streams_to_punching_receiver() {
+    // ---
+    let sender_config = SenderNetworkConfig {
+        local_addr: Some(SocketAddr::from(([127, 0, 0, 1], 0))),
+        ..Default::default()
+    };
+    let mut sender = RtpSender::listen(0, sender_config).await.expect("sender");
+    let sender_addr = sender.local_addr().unwrap();
+
+    // Keepalives every 20ms, so several arrive during the stream
+    let receiver_config = ReceiverNetworkConfig {
+        punch: Some(sender_addr),
+        keepalive_interval: Duration::from_millis(20),
+        ..Default::default()
+    };
+    let mut receiver = RtpReceiver::with_config(0, receiver_config)
+        .await
+        .expect("receiver");
+    let receiver_port = receiver.local_addr().unwrap().port();
+
+    // The sender was told nothing but the hello
+    let peer = tokio::time::timeout(Duration::from_secs(1), sender.wait_for_peer())
+        .await
+        .expect("no hello")
+        .expect("wait for peer");
+    assert_eq!(peer, SocketAddr::from(([127, 0, 0, 1], receiver_port)));
+
+    // A hello from anyone else does not redirect the stream
+    let stranger = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    stranger.send_to(b"hello", sender_addr).await.unwrap();
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions {
+        interval_ms: 5,
+        ..Default::default()
+    };
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        0x2055_2055,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+
+    // Still streaming to the receiver, and nothing went astray
+    assert_eq!(sender.peer_addr(), Some(peer));
+    let sender_stats = sender.stats();
+    assert_eq!(sender_stats.packets_sent, PACKETS as u64);
+    assert_eq!(sender_stats.packets_failed, 0);
+    let mut buf = [0u8; 64];
+    assert!(stranger.try_recv_from(&mut buf).is_err());
+
+    // Every packet came back to the punched port and decoded
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, PACKETS as u64);
+    assert_eq!(snapshot.packets_lost, 0);
+    let opus = decoders.stats()[&PAYLOAD_TYPE_OPUS];
+    assert_eq!(opus.failed, 0);
+    assert_eq!(opus.decoded, PACKETS as u64);
+}
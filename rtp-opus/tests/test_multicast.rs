@@ -0,0 +1,66 @@
+//! Integration test for multicast transmission.
+//!
+//! Two receivers on one host join a link-local group on the same port over
+//! loopback, and each gets every packet the sender addresses to the group.
+//! TTL 0 keeps the packets on this host.
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use receiver::{ReceiverNetworkConfig, RtpReceiver};
+use rtp_opus_common::RtpPacket;
+use sender::{RtpSender, SenderNetworkConfig};
+
+/// Link-local group, never forwarded by routers
+const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 123);
+
+#[tokio::test]
+async fn test_multicast_reaches_every_receiver() {
+    // ---
+    let config = ReceiverNetworkConfig {
+        multicast_group: Some(GROUP),
+        multicast_interface: Ipv4Addr::LOCALHOST,
+        ..Default::default()
+    };
+    let mut first = RtpReceiver::with_config(0, config.clone())
+        .await
+        .expect("first receiver");
+    let port = first.local_addr().unwrap().port();
+    let mut second = RtpReceiver::with_config(port, config)
+        .await
+        .expect("second receiver shares the port");
+
+    let mut sender = RtpSender::with_config(
+        format!("{GROUP}:{port}"),
+        SenderNetworkConfig {
+            multicast_ttl: 0,
+            multicast_interface: Some(Ipv4Addr::LOCALHOST),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("sender");
+    for sequence in 0..3u16 {
+        let packet = RtpPacket::new(sequence, sequence as u32 * 320, 7, vec![1, 2, 3]);
+        assert!(sender.send(&packet).await.expect("send"));
+    }
+
+    for receiver in [&mut first, &mut second] {
+        for sequence in 0..3u16 {
+            let packet = tokio::time::timeout(Duration::from_secs(2), receiver.receive())
+                .await
+                .expect("packet in time")
+                .expect("receive")
+                .expect("valid packet");
+            assert_eq!(packet.sequence, sequence);
+            assert_eq!(packet.payload, [1, 2, 3][..]);
+        }
+    }
+
+    // Unicast addresses are not groups
+    let config = ReceiverNetworkConfig {
+        multicast_group: Some(Ipv4Addr::LOCALHOST),
+        ..Default::default()
+    };
+    assert!(RtpReceiver::with_config(0, config).await.is_err());
+}
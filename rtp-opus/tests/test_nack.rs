@@ -0,0 +1,153 @@
+//! Integration test for retransmission on NACK.
+//!
+//! Streams through a relay that loses 5% of the media packets (but none of
+//! the feedback) to a receiver that NACKs its gaps, and checks that the
+//! sender's retransmissions fill nearly all of them.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NackConfig, NullSink, ReceiveOptions, ReceiverStats, RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::{
+    is_rtcp, MetricsContext, NetworkSimulator, NetworkSimulatorConfig, NetworkSimulatorStats,
+    RtpPacket,
+};
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+
+/// Packets sent in total, before retransmissions
+const PACKETS: usize = 200;
+
+/// Passes media from the sender to `receiver` through `sim`, its RTCP
+/// and everything the receiver sends back untouched, until `stop` fires.
+async fn relay(
+    socket: UdpSocket,
+    receiver: SocketAddr,
+    mut sim: NetworkSimulator,
+    mut stop: oneshot::Receiver<()>,
+) -> NetworkSimulatorStats {
+    // ---
+    let mut sender = None;
+    let mut buf = [0u8; 1500];
+    loop {
+        let (len, from) = tokio::select! {
+            _ = &mut stop => break,
+            received = socket.recv_from(&mut buf) => received.unwrap(),
+        };
+        if from == receiver {
+            if let Some(sender) = sender {
+                socket.send_to(&buf[..len], sender).await.unwrap();
+            }
+            continue;
+        }
+        sender = Some(from);
+        if is_rtcp(&buf[..len]) {
+            socket.send_to(&buf[..len], receiver).await.unwrap();
+            continue;
+        }
+        sim.send(RtpPacket::deserialize(&buf[..len]).unwrap());
+        while let Some(packet) = sim.receive() {
+            let data = packet.serialize().unwrap();
+            socket.send_to(&data, receiver).await.unwrap();
+        }
+    }
+    sim.stats()
+}
+
+#[tokio::test]
+async fn test_nack_recovers_lost_packets() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let receiver_addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+
+    let relay_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let relay_addr = relay_socket.local_addr().unwrap();
+    let sim = NetworkSimulator::new(NetworkSimulatorConfig {
+        loss_rate: 0.05,
+        jitter_ms: 0,
+        reorder_rate: 0.0,
+        corrupt_rate: 0.0,
+        seed: Some(2057),
+    });
+    let (stop, stopped) = oneshot::channel();
+    let relay = tokio::spawn(relay(relay_socket, receiver_addr, sim, stopped));
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let mut sender = RtpSender::new(relay_addr.to_string())
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions {
+        interval_ms: 5,
+        ..Default::default()
+    };
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        // Four times the real-time rate, and bandwidth for NACKs to match
+        nack: Some(NackConfig {
+            session_bandwidth: 1_000_000,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        0x2057_2057,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+    stop.send(()).unwrap();
+    let network = relay.await.unwrap();
+
+    // The relay lost packets, and the receiver asked for them
+    assert!(network.packets_lost >= 5, "{network:?}");
+    let requested = receiver_metrics.nack_packets_requested_total.get();
+    assert!(requested > 0);
+
+    // The sender answered from its cache
+    let retransmitted = sender.stats().packets_retransmitted;
+    assert!(retransmitted > 0);
+    assert!(retransmitted <= requested);
+    assert_eq!(
+        sender_metrics.packets_retransmitted_total.get(),
+        retransmitted
+    );
+    assert_eq!(sender_metrics.nack_requests_received_total.get(), requested);
+
+    // Nearly every loss was repaired; a retransmission can be lost too
+    let snapshot = stats.snapshot();
+    assert!(
+        snapshot.packets_lost <= 2,
+        "{} of {} lost packets not recovered",
+        snapshot.packets_lost,
+        network.packets_lost
+    );
+    assert!(snapshot.packets_received >= PACKETS as u64 - 2);
+}
@@ -0,0 +1,157 @@
+//! Integration test for the decoder refresh feedback cycle.
+//!
+//! Streams through a two-way relay whose forward path runs through the
+//! `NetworkSimulator` with a two-second outage, and checks that the
+//! receiver asks for a refresh and the sender resets its encoder once. The
+//! sender trickle-starts, so the refresh request also stops its ramp short
+//! of the target bitrate.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverStats, RefreshConfig, RtpReceiver,
+    PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::{
+    is_rtcp, MetricsContext, NetworkSimulator, NetworkSimulatorConfig, RtpPacket,
+};
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RampConfig, RtpSender, StreamOptions};
+use tokio::net::UdpSocket;
+
+/// Packets sent in total; packets 100..200 are lost (2s of 20ms frames).
+const PACKETS: usize = 300;
+
+/// Forwards sender packets through `sim` to `target`, its RTCP straight
+/// there, and anything `target` sends back straight to the sender.
+async fn run_relay(socket: UdpSocket, mut sim: NetworkSimulator, target: SocketAddr) {
+    // ---
+    let mut buf = [0u8; 2048];
+    let mut sender_addr = None;
+
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await.expect("relay recv");
+        if from == target {
+            if let Some(sender_addr) = sender_addr {
+                socket.send_to(&buf[..len], sender_addr).await.ok();
+            }
+            continue;
+        }
+
+        sender_addr = Some(from);
+        if is_rtcp(&buf[..len]) {
+            socket
+                .send_to(&buf[..len], target)
+                .await
+                .expect("relay send");
+            continue;
+        }
+        sim.send(RtpPacket::deserialize(&buf[..len]).expect("rtp"));
+        while let Some(packet) = sim.receive() {
+            let data = packet.serialize().expect("serialize");
+            socket.send_to(&data, target).await.expect("relay send");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_outage_triggers_one_refresh_cycle() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let receiver_addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+
+    let mut sim = NetworkSimulator::new(NetworkSimulatorConfig::default());
+    sim.add_outage(100..200);
+    let relay_socket = UdpSocket::bind("127.0.0.1:0").await.expect("relay bind");
+    let relay_addr = relay_socket.local_addr().unwrap();
+    let relay = tokio::spawn(run_relay(relay_socket, sim, receiver_addr));
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let mut sender = RtpSender::new(relay_addr.to_string())
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions {
+        interval_ms: 5, // Faster than real time; the outage spans 100 packets
+        // The receiver notices the outage when packet 200 arrives (1s in),
+        // while the ramp is still short of the target
+        ramp: Some(RampConfig {
+            duration: Duration::from_millis(1250),
+            step: Duration::from_millis(50),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        refresh: Some(RefreshConfig::default()),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        0x0BAD_CAFE,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+    relay.abort();
+
+    assert_eq!(
+        receiver_metrics.decoder_refresh_requests_sent_total.get(),
+        1
+    );
+    assert_eq!(
+        receiver_metrics
+            .decoder_refresh_requests_suppressed_total
+            .get(),
+        0
+    );
+    assert_eq!(
+        sender_metrics.decoder_refresh_requests_received_total.get(),
+        1
+    );
+    assert_eq!(sender_metrics.encoder_resets_total.get(), 1);
+
+    // The ramp climbed off the floor, then held when the loss was reported
+    let held = sender_metrics.encoder_target_bitrate_bps.get();
+    assert!(
+        held > 12000 && held < sender::codec::BITRATE as i64,
+        "{held}"
+    );
+    assert_eq!(encoder.bitrate() as i64, held);
+    assert_eq!(stats.snapshot().packets_received, (PACKETS - 100) as u64);
+
+    // The receiver saw the outage as a pause, then a degraded stretch
+    let seconds_in = |state: &str| {
+        receiver_metrics
+            .session_state_seconds_total
+            .with_label_values(&[state])
+            .get()
+    };
+    assert!(seconds_in("paused") > 0.0);
+    assert!(seconds_in("degraded") > 0.0);
+    assert!(seconds_in("streaming") > 0.0);
+}
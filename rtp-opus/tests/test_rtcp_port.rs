@@ -0,0 +1,110 @@
+//! Integration test for RTCP on a port of its own.
+//!
+//! Streams with rtcp-mux off at both ends, and checks that the sender's
+//! reports reach the receiver and the receiver's reports reach the sender
+//! through the sockets on the ports after the RTP ones.
+
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverNetworkConfig, ReceiverStats, RtpReceiver,
+    PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::MetricsContext;
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{
+    stream_audio, AudioData, OpusEncoderWrapper, RtpSender, SenderNetworkConfig, StreamOptions,
+};
+
+/// Packets sent, 20ms apart
+const PACKETS: usize = 100;
+
+/// SSRC of the stream
+const SSRC: u32 = 0x2073_2073;
+
+/// Binds a receiver on some port whose next port is free for RTCP.
+async fn bind_receiver() -> RtpReceiver {
+    // ---
+    for _ in 0..10 {
+        let config = ReceiverNetworkConfig {
+            rtcp_mux: false,
+            ..Default::default()
+        };
+        if let Ok(receiver) = RtpReceiver::with_config(0, config).await {
+            return receiver;
+        }
+    }
+    panic!("no port pair free for the receiver");
+}
+
+/// Binds a sender to `port` on some port whose next port is free for RTCP.
+async fn bind_sender(port: u16) -> RtpSender {
+    // ---
+    for _ in 0..10 {
+        let config = SenderNetworkConfig {
+            sender_report_interval: Some(Duration::from_millis(200)),
+            rtcp_mux: false,
+            ..Default::default()
+        };
+        if let Ok(sender) = RtpSender::with_config(format!("127.0.0.1:{port}"), config).await {
+            return sender;
+        }
+    }
+    panic!("no port pair free for the sender");
+}
+
+#[tokio::test]
+async fn test_rtcp_on_next_port() {
+    // ---
+    let mut receiver = bind_receiver().await;
+    let port = receiver.local_addr().unwrap().port();
+    let mut sender = bind_sender(port).await;
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let stream_options = StreamOptions::default();
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_millis(500)),
+        receiver_report_interval: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        SSRC,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+
+    assert_eq!(stats.snapshot().packets_received, PACKETS as u64);
+    assert!(receiver.sender_reports_received() > 0);
+    assert!(receiver_metrics.receiver_reports_sent_total.get() > 0);
+    assert_eq!(receiver.rtcp_malformed(), 0);
+    let report = sender
+        .remote_report()
+        .expect("receiver report at the sender");
+    assert_eq!(report.ssrc, SSRC);
+    assert_eq!(sender.rtcp_malformed(), 0);
+}
@@ -0,0 +1,181 @@
+//! Integration test for RTCP receiver reports.
+//!
+//! Streams through a relay that loses 10% of the media packets to a
+//! receiver sending a report every half second, and checks that the
+//! fraction lost the reports carry matches the relay's loss rate and that
+//! the sender takes the reports in.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverReport, ReceiverStats, RtpReceiver,
+    PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::{
+    is_rtcp, MetricsContext, NetworkSimulator, NetworkSimulatorConfig, NetworkSimulatorStats,
+    RtpPacket,
+};
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+
+/// Packets sent, 20ms apart
+const PACKETS: usize = 200;
+
+/// Share of media packets the relay loses
+const LOSS_RATE: f64 = 0.10;
+
+/// SSRC of the stream
+const SSRC: u32 = 0x2070_2070;
+
+/// Passes media from the sender to `receiver` through `sim`, and its RTCP
+/// and everything the receiver sends back untouched, until `stop` fires.
+/// Returns the receiver reports passed back along with the relay's
+/// counts.
+async fn relay(
+    socket: UdpSocket,
+    receiver: SocketAddr,
+    mut sim: NetworkSimulator,
+    mut stop: oneshot::Receiver<()>,
+) -> (Vec<ReceiverReport>, NetworkSimulatorStats) {
+    // ---
+    let mut sender = None;
+    let mut reports = Vec::new();
+    let mut buf = [0u8; 1500];
+    loop {
+        let (len, from) = tokio::select! {
+            _ = &mut stop => break,
+            received = socket.recv_from(&mut buf) => received.unwrap(),
+        };
+        if from == receiver {
+            reports.push(ReceiverReport::deserialize(&buf[..len]).expect("receiver report"));
+            if let Some(sender) = sender {
+                socket.send_to(&buf[..len], sender).await.unwrap();
+            }
+            continue;
+        }
+        sender = Some(from);
+        if is_rtcp(&buf[..len]) {
+            socket.send_to(&buf[..len], receiver).await.unwrap();
+            continue;
+        }
+        sim.send(RtpPacket::deserialize(&buf[..len]).unwrap());
+        while let Some(packet) = sim.receive() {
+            let data = packet.serialize().unwrap();
+            socket.send_to(&data, receiver).await.unwrap();
+        }
+    }
+    (reports, sim.stats())
+}
+
+#[tokio::test]
+async fn test_receiver_reports_fraction_lost() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let receiver_addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+
+    let relay_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let relay_addr = relay_socket.local_addr().unwrap();
+    let sim = NetworkSimulator::new(NetworkSimulatorConfig {
+        loss_rate: LOSS_RATE,
+        jitter_ms: 0,
+        reorder_rate: 0.0,
+        corrupt_rate: 0.0,
+        seed: Some(2070),
+    });
+    let (stop, stopped) = oneshot::channel();
+    let relay = tokio::spawn(relay(relay_socket, receiver_addr, sim, stopped));
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let mut sender = RtpSender::new(relay_addr.to_string())
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions::default();
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        receiver_report_interval: Some(Duration::from_millis(500)),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        SSRC,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+    stop.send(()).unwrap();
+    let (reports, network) = relay.await.unwrap();
+
+    // A report about every half second of the four-second stream
+    assert!(reports.len() >= 6, "{} reports", reports.len());
+    assert_eq!(
+        receiver_metrics.receiver_reports_sent_total.get(),
+        reports.len() as u64
+    );
+    let blocks: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            assert_eq!(report.blocks.len(), 1);
+            report.blocks[0]
+        })
+        .collect();
+
+    // Weighted by the packets each interval expected, the fractions lost
+    // match the relay's loss rate. A jitter buffer resync restarts the
+    // cumulative count, but not the fraction of its interval.
+    let mut expected = 0.0;
+    let mut lost = 0.0;
+    for window in blocks.windows(2) {
+        let (previous, block) = (window[0], window[1]);
+        assert_eq!(block.ssrc, SSRC);
+        let interval_expected = block
+            .extended_highest_sequence
+            .wrapping_sub(previous.extended_highest_sequence)
+            as f64;
+        expected += interval_expected;
+        lost += block.fraction_lost_ratio() * interval_expected;
+    }
+    let reported_rate = lost / expected;
+    assert!(
+        (reported_rate - LOSS_RATE).abs() < 0.05,
+        "reported {reported_rate:.3}, relay {network:?}"
+    );
+    let last = blocks.last().unwrap();
+    assert!(last.cumulative_lost as u64 <= network.packets_lost);
+
+    // The sender took in what reached it before it stopped polling
+    let taken = sender.stats().receiver_reports_received;
+    assert!(taken + 1 >= reports.len() as u64, "{taken} taken");
+    assert_eq!(sender_metrics.receiver_reports_received_total.get(), taken);
+    let remote = sender.remote_report().expect("remote report");
+    assert!(blocks.contains(&remote));
+    assert_eq!(
+        sender_metrics.remote_fraction_lost.get(),
+        remote.fraction_lost_ratio()
+    );
+}
@@ -0,0 +1,166 @@
+//! Integration test for RTCP sender reports.
+//!
+//! Streams five seconds of a tone on a paused clock with a report every
+//! second, and checks that the reports arrive on the RTP socket at that
+//! interval with growing packet and octet counts and the stream's CNAME,
+//! and that the receiver takes them aside without counting a packet or a
+//! drop.
+
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverStats, RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::{
+    compound_packets, is_rtcp, MetricsContext, RtpPacket, Sdes, SdesChunk, SenderReport,
+};
+use sender::codec::{SAMPLES_PER_FRAME, SAMPLE_RATE};
+use sender::network::SenderNetworkConfig;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+use tokio::net::UdpSocket;
+
+/// Frames streamed: five seconds of 20ms frames
+const FRAMES: usize = 250;
+
+/// Time between reports
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// SSRC of the stream
+const SSRC: u32 = 0x2069_2069;
+
+/// CNAME sent with each report
+const CNAME: &str = "tone@test";
+
+/// Streams the tone to `port` with a report every [`INTERVAL`].
+async fn stream_to(port: u16) -> RtpSender {
+    // ---
+    let tone: Vec<i16> = (0..FRAMES * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let config = SenderNetworkConfig {
+        sender_report_interval: Some(INTERVAL),
+        cname: CNAME.to_string(),
+        ..Default::default()
+    };
+    let mut sender = RtpSender::with_config(format!("127.0.0.1:{port}"), config)
+        .await
+        .expect("sender");
+    let metrics = MetricsContext::new("test_sender").expect("metrics");
+    stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &metrics,
+        SSRC,
+        &StreamOptions::default(),
+    )
+    .await
+    .expect("stream");
+    assert_eq!(
+        metrics.sender_reports_sent_total.get(),
+        sender.stats().sender_reports_sent
+    );
+    sender
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_sender_reports_arrive_periodically() {
+    // ---
+    let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+    let port = socket.local_addr().unwrap().port();
+
+    let collect = async {
+        let mut buf = [0u8; 1500];
+        let mut packets = 0u64;
+        let mut reports = Vec::new();
+        while packets < FRAMES as u64 {
+            let len = socket.recv(&mut buf).await.expect("recv");
+            if is_rtcp(&buf[..len]) {
+                let compound: Vec<&[u8]> = compound_packets(&buf[..len]).collect();
+                assert_eq!(compound.len(), 2);
+                let report = SenderReport::deserialize(compound[0]).expect("sender report");
+                let sdes = Sdes::deserialize(compound[1]).expect("SDES");
+                assert_eq!(sdes.chunks, [SdesChunk::with_cname(SSRC, CNAME)]);
+                reports.push((packets, report));
+            } else {
+                RtpPacket::deserialize(&buf[..len]).expect("RTP");
+                packets += 1;
+            }
+        }
+        reports
+    };
+    let (sender, reports) = tokio::join!(stream_to(port), collect);
+
+    // The first report follows the first packet, then one per second
+    assert_eq!(reports.len() as u64, sender.stats().sender_reports_sent);
+    assert!(
+        (4..=5).contains(&reports.len()),
+        "{} reports",
+        reports.len()
+    );
+    let (received_before, first) = reports[0];
+    assert_eq!((received_before, first.packet_count), (1, 1));
+    for window in reports.windows(2) {
+        let ((_, earlier), (received_before, later)) = (window[0], window[1]);
+        assert_eq!(later.ssrc, SSRC);
+        assert_eq!(later.packet_count as u64, received_before);
+        assert!((49..=51).contains(&(later.packet_count - earlier.packet_count)));
+        assert!(later.octet_count > earlier.octet_count);
+
+        // A second of media between reports, on a paused wallclock: the
+        // RTP timestamp follows the packets sent
+        let step = later.rtp_timestamp.wrapping_sub(earlier.rtp_timestamp);
+        let expected = (later.packet_count - earlier.packet_count) * SAMPLES_PER_FRAME as u32;
+        assert!(
+            step.abs_diff(expected) <= SAMPLE_RATE / 100,
+            "{step} vs {expected}"
+        );
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_receiver_records_sender_reports() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().unwrap().port();
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(2)),
+        ..Default::default()
+    };
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &metrics,
+    );
+    let (sender, received) = tokio::join!(stream_to(port), receive);
+    received.expect("receive");
+
+    let reports = sender.stats().sender_reports_sent;
+    assert!(reports >= 4);
+    assert_eq!(receiver.sender_reports_received(), reports);
+    assert_eq!(metrics.sender_reports_received_total.get(), reports);
+    let (last, _) = receiver.last_sender_report().expect("last report");
+    assert_eq!(last.ssrc, SSRC);
+    assert!(last.packet_count as usize > FRAMES - 60);
+
+    // Each came with the stream's CNAME
+    let snapshot = stats.snapshot();
+    assert_eq!(
+        snapshot.cnames.into_iter().collect::<Vec<_>>(),
+        [(SSRC, CNAME.to_string())]
+    );
+
+    // Reports are neither packets nor drops
+    assert_eq!(receiver.stats().packets_dropped, 0);
+    assert_eq!(snapshot.packets_received, FRAMES as u64);
+}
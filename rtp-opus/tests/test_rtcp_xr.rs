@@ -0,0 +1,156 @@
+//! Integration tests for RTCP extended reports.
+//!
+//! Runs a lossy stream through the receiver stats and checks that the
+//! Loss RLE and VoIP Metrics blocks agree with the counts, then streams
+//! to a receiver sending extended reports and checks that the sender
+//! takes the VoIP Metrics in.
+
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, JitterBufferConfig, NullSink, PlayoutDelays, ReceiveOptions, ReceiverStats,
+    RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::{
+    MetricsContext, NetworkSimulator, NetworkSimulatorConfig, RtpPacket, XrBlock,
+};
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+
+/// Packets sent, 20ms apart
+const PACKETS: usize = 100;
+
+/// SSRC of the stream
+const SSRC: u32 = 0x2076_2076;
+
+#[test]
+fn test_extended_report_matches_stats() {
+    // ---
+    let mut sim = NetworkSimulator::new(NetworkSimulatorConfig {
+        loss_rate: 0.08,
+        seed: Some(2076),
+        ..Default::default()
+    });
+    let mut stats = ReceiverStats::default();
+    let delays = PlayoutDelays::new(&JitterBufferConfig::default(), Duration::from_millis(20));
+    let mut reports = Vec::new();
+
+    for seq in 0..1000u16 {
+        sim.send(RtpPacket::new(seq, seq as u32 * 960, SSRC, vec![0; 40]));
+        while let Some(packet) = sim.receive() {
+            // Every 40th packet through arrives too late to play
+            if packet.sequence % 40 == 20 {
+                stats.record_late_packet(packet.sequence);
+            } else {
+                stats.record_packet(packet.sequence, false);
+            }
+        }
+        if seq % 250 == 249 {
+            reports.extend(stats.take_extended_report(SSRC, &delays));
+        }
+    }
+    let mut received = Vec::new();
+    for report in &reports {
+        let XrBlock::LossRle(loss) = &report.blocks[0] else {
+            panic!("no Loss RLE block");
+        };
+        assert_eq!(loss.begin_sequence as usize, received.len());
+        received.extend(loss.received());
+    }
+
+    // The Loss RLE blocks mark every packet through, late ones too
+    let snapshot = stats.snapshot();
+    let expected = received.len() as u64;
+    let lost = received.iter().filter(|&&received| !received).count() as u64;
+    assert_eq!(expected, stats.sequence().highest_extended().unwrap() + 1);
+    assert_eq!(lost, snapshot.packets_lost - snapshot.packets_late);
+    assert!(snapshot.packets_late > 0);
+    assert_eq!(
+        snapshot.packets_received + snapshot.packets_late,
+        expected - lost
+    );
+
+    // The last VoIP Metrics block covers the whole run
+    let Some(XrBlock::VoipMetrics(voip)) = reports.last().unwrap().blocks.last() else {
+        panic!("no VoIP Metrics block");
+    };
+    assert_eq!(voip.ssrc, SSRC);
+    assert_eq!(voip.loss_rate as u64, (lost << 8) / expected);
+    assert_eq!(
+        voip.discard_rate as u64,
+        (snapshot.packets_late << 8) / expected
+    );
+    assert!(voip.burst_density >= voip.gap_density);
+    assert!(voip.gap_duration > 0);
+    assert!(
+        voip.mos_lq < 44 && voip.mos_lq > 10,
+        "MOS-LQ {}",
+        voip.mos_lq
+    );
+    assert!(voip.mos_cq <= voip.mos_lq);
+    assert_eq!(voip.jb_nominal, 60);
+}
+
+#[tokio::test]
+async fn test_sender_takes_in_voip_metrics() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().unwrap().port();
+    let mut sender = RtpSender::new(format!("127.0.0.1:{port}"))
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let stream_options = StreamOptions::default();
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_millis(500)),
+        receiver_report_interval: Some(Duration::from_millis(200)),
+        extended_reports: true,
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        SSRC,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+
+    let sent = receiver_metrics.extended_reports_sent_total.get();
+    assert!(sent > 0);
+    assert_eq!(sent, receiver_metrics.receiver_reports_sent_total.get());
+    assert!(sender_metrics.extended_reports_received_total.get() > 0);
+    assert_eq!(sender.rtcp_malformed(), 0);
+
+    let voip = sender
+        .remote_voip_metrics()
+        .expect("VoIP metrics at the sender");
+    assert_eq!(voip.ssrc, SSRC);
+    assert_eq!((voip.loss_rate, voip.discard_rate), (0, 0));
+    assert_eq!(voip.jb_nominal, 60);
+    assert_eq!(voip.mos_lq, 44);
+    assert_eq!(sender_metrics.remote_mos_lq.get(), 4.4);
+}
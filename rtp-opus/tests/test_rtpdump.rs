@@ -0,0 +1,136 @@
+//! Integration test for rtpdump capture and replay.
+//!
+//! Sends a lossy stream with uneven timing to a receiver that captures it,
+//! replays the capture to a second receiver, and checks that the second
+//! sees the same packets, gaps, and timing as the first.
+
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use receiver::RtpReceiver;
+use rtp_opus_common::{
+    read_rtpdump, MetricsContext, NetworkSimulator, NetworkSimulatorConfig, RtpPacket,
+    RtpdumpWriter,
+};
+use sender::{stream_rtpdump, RtpSender, StreamOptions};
+use tokio::net::UdpSocket;
+
+/// Packets in the original stream, before loss
+const PACKETS: u16 = 100;
+
+/// How far replayed arrival times may stray from the captured ones
+const TOLERANCE: Duration = Duration::from_millis(15);
+
+/// Receives until nothing has arrived for half a second, returning each
+/// packet's sequence number and arrival time.
+async fn collect(receiver: &mut RtpReceiver) -> Vec<(u16, Instant)> {
+    // ---
+    let mut arrivals = Vec::new();
+    while let Ok(received) =
+        tokio::time::timeout(Duration::from_millis(500), receiver.receive()).await
+    {
+        if let Some(packet) = received.expect("receive") {
+            arrivals.push((packet.sequence, Instant::now()));
+        }
+    }
+    arrivals
+}
+
+/// Returns the sequence numbers of `arrivals`, and their arrival times
+/// after the first.
+fn timeline(arrivals: &[(u16, Instant)]) -> (Vec<u16>, Vec<Duration>) {
+    // ---
+    let first = arrivals[0].1;
+    arrivals
+        .iter()
+        .map(|&(sequence, at)| (sequence, at - first))
+        .unzip()
+}
+
+async fn bind_receiver() -> (RtpReceiver, SocketAddr) {
+    // ---
+    let receiver = RtpReceiver::new(0).await.expect("receiver");
+    let addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+    (receiver, addr)
+}
+
+#[tokio::test]
+async fn test_replayed_capture_matches_original() {
+    // ---
+    let path = std::env::temp_dir().join(format!("rtpdump-test-{}.rtp", std::process::id()));
+
+    // The original session: 10% loss, 5-25ms between packets
+    let (mut capturing, capturing_addr) = bind_receiver().await;
+    let source = match capturing.local_addr().unwrap() {
+        SocketAddr::V4(addr) => addr,
+        SocketAddr::V6(_) => unreachable!("bound to IPv4"),
+    };
+    capturing.set_rtpdump(RtpdumpWriter::create(&path, source).unwrap().boxed());
+
+    let mut sim = NetworkSimulator::new(NetworkSimulatorConfig {
+        loss_rate: 0.1,
+        jitter_ms: 0,
+        reorder_rate: 0.0,
+        corrupt_rate: 0.0,
+        seed: Some(2054),
+    });
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let send = async {
+        for sequence in 0..PACKETS {
+            sim.send(RtpPacket::new(
+                sequence,
+                sequence as u32 * 320,
+                0x2054_2054,
+                vec![sequence as u8; 60],
+            ));
+            while let Some(packet) = sim.receive() {
+                let data = packet.serialize().unwrap();
+                socket.send_to(&data, capturing_addr).await.unwrap();
+            }
+            let gap = 5 + (sequence as u64 * 7) % 21;
+            tokio::time::sleep(Duration::from_millis(gap)).await;
+        }
+    };
+    let ((), captured) = tokio::join!(send, collect(&mut capturing));
+    let dump = capturing.take_rtpdump().expect("capture still open");
+    assert_eq!(dump.packets_written(), captured.len() as u64);
+    dump.into_inner().unwrap();
+
+    let (sequences, captured_times) = timeline(&captured);
+    assert!(sim.stats().packets_lost > 0, "no loss simulated");
+    assert_eq!(
+        sequences.len() as u64,
+        PACKETS as u64 - sim.stats().packets_lost
+    );
+
+    // The replay, from the file
+    let file = read_rtpdump(&path).expect("read capture");
+    std::fs::remove_file(&path).ok();
+    assert_eq!(file.source, source);
+    assert_eq!(file.packets.len(), captured.len());
+
+    let (mut replayed_to, replay_addr) = bind_receiver().await;
+    let mut sender = RtpSender::new(replay_addr.to_string())
+        .await
+        .expect("sender");
+    let metrics = MetricsContext::new("test_sender").expect("metrics");
+    let options = StreamOptions::default();
+    let (summary, replayed) = tokio::join!(
+        stream_rtpdump(&file, &mut sender, &metrics, &options),
+        collect(&mut replayed_to)
+    );
+    assert_eq!(summary.expect("replay").packets_sent, captured.len() as u64);
+
+    // Same packets, so the same gaps, arriving on the same schedule
+    let (replayed_sequences, replayed_times) = timeline(&replayed);
+    assert_eq!(replayed_sequences, sequences);
+    for (index, (&original, &replay)) in captured_times.iter().zip(&replayed_times).enumerate() {
+        assert!(
+            replay.abs_diff(original) <= TOLERANCE,
+            "packet {} arrived at {:?}, captured at {:?}",
+            sequences[index],
+            replay,
+            original
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Integration test for SRTP.
+//!
+//! Streams over loopback UDP with both ends sharing a key, after forged
+//! packets have been queued at the receiver, and checks that every real
+//! packet decodes while the forged ones are dropped and counted.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverNetworkConfig, ReceiverStats, RtpReceiver,
+    PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::{MetricsContext, RtpPacket, SrtpContext, SrtpKey};
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{
+    stream_audio, AudioData, OpusEncoderWrapper, RtpSender, SenderNetworkConfig, StreamOptions,
+};
+use tokio::net::UdpSocket;
+
+/// Packets sent in total
+const PACKETS: usize = 100;
+
+/// SSRC of the stream, which the forged packets claim too
+const SSRC: u32 = 0x3711_0080;
+
+#[tokio::test]
+async fn test_srtp_stream_drops_forged_packets() {
+    // ---
+    let key = SrtpKey::from_base64("4fl6DT4Bi+DWT6MsBt5BOQ7Gda1Jiv7rtpYLOqvm").unwrap();
+    let receiver_config = ReceiverNetworkConfig {
+        srtp_key: Some(key.clone()),
+        ..Default::default()
+    };
+    let mut receiver = RtpReceiver::with_config(0, receiver_config)
+        .await
+        .expect("receiver");
+    let receiver_addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+
+    // Plain RTP, and SRTP under the wrong key, both waiting in the socket
+    // before the stream starts
+    let forger = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut forged = Vec::new();
+    for sequence in 0..2 {
+        forged.push(
+            RtpPacket::new(sequence, 0, SSRC, vec![0xFF; 40])
+                .serialize()
+                .unwrap(),
+        );
+    }
+    let wrong_key = SrtpKey::from_base64(&"A".repeat(40)).unwrap();
+    let mut packet = RtpPacket::new(2, 0, SSRC, vec![0xFF; 40])
+        .serialize()
+        .unwrap();
+    SrtpContext::new(&wrong_key).protect(&mut packet).unwrap();
+    forged.push(packet);
+    for packet in &forged {
+        forger.send_to(packet, receiver_addr).await.unwrap();
+    }
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let sender_config = SenderNetworkConfig {
+        srtp_key: Some(key),
+        ..Default::default()
+    };
+    let mut sender = RtpSender::with_config(receiver_addr.to_string(), sender_config)
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions {
+        interval_ms: 5,
+        ..Default::default()
+    };
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        SSRC,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+
+    // Every protected packet went out
+    let sender_stats = sender.stats();
+    assert_eq!(sender_stats.packets_sent, PACKETS as u64);
+    assert_eq!(sender_stats.packets_failed, 0);
+
+    // The forged packets were dropped before parsing, and counted
+    assert_eq!(receiver.srtp_auth_failures(), forged.len() as u64);
+    assert_eq!(
+        receiver_metrics.srtp_auth_failures_total.get(),
+        forged.len() as u64
+    );
+    assert_eq!(receiver.stats().packets_dropped, forged.len() as u64);
+
+    // The real packets authenticated, decrypted, and decoded
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, PACKETS as u64);
+    assert_eq!(snapshot.packets_lost, 0);
+    let opus = decoders.stats()[&PAYLOAD_TYPE_OPUS];
+    assert_eq!(opus.failed, 0);
+    assert_eq!(opus.decoded, PACKETS as u64);
+}
@@ -0,0 +1,107 @@
+//! Integration test for SSRC changes.
+//!
+//! Streams to a receiver, then restarts the sender with a new SSRC and a
+//! sequence numbering that starts over, with no BYE in between, and checks
+//! that the receive loop follows the new stream: playback resumes, the
+//! restart is not counted as loss, and packets that still arrive from the
+//! old SSRC are dropped.
+
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverStats, RtpPacket, RtpReceiver, PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::MetricsContext;
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{stream_audio, AudioData, OpusEncoderWrapper, RtpSender, StreamOptions};
+
+/// Packets sent per stream
+const PACKETS: usize = 50;
+
+/// Packets from a new SSRC dropped before it is confirmed
+const UNCONFIRMED: usize = receiver::ssrc::DEFAULT_SSRC_CONFIRM_PACKETS as usize - 1;
+
+const FIRST_SSRC: u32 = 0x2082_0001;
+const SECOND_SSRC: u32 = 0x2082_0002;
+
+/// Streams `PACKETS` frames of a tone on `ssrc`, numbered from zero.
+async fn stream(sender: &mut RtpSender, ssrc: u32, metrics: &MetricsContext) {
+    // ---
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    stream_audio(
+        &audio,
+        &mut encoder,
+        sender,
+        metrics,
+        ssrc,
+        &StreamOptions::default(),
+    )
+    .await
+    .expect("stream");
+}
+
+#[tokio::test]
+async fn test_new_ssrc_starts_stream_over() {
+    // ---
+    let mut receiver = RtpReceiver::new(0).await.expect("receiver");
+    let port = receiver.local_addr().unwrap().port();
+    let mut sender = RtpSender::new(format!("127.0.0.1:{port}"))
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_millis(500)),
+        ..Default::default()
+    };
+
+    // The sender restarts without a BYE, then a straggler of the first
+    // stream arrives after the second
+    let send = async {
+        stream(&mut sender, FIRST_SSRC, &sender_metrics).await;
+        stream(&mut sender, SECOND_SSRC, &sender_metrics).await;
+        let straggler = RtpPacket::new(PACKETS as u16, 0, FIRST_SSRC, vec![0xFC; 20]);
+        assert!(sender.send(&straggler).await.expect("send"));
+    };
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &options,
+        &receiver_metrics,
+    );
+    let (_, received) = tokio::join!(send, receive);
+    received.expect("receive");
+
+    assert_eq!(receiver_metrics.ssrc_changes_total.get(), 1);
+    assert_eq!(
+        receiver_metrics.ssrc_unconfirmed_packets_total.get(),
+        UNCONFIRMED as u64
+    );
+    assert_eq!(receiver_metrics.ssrc_stale_packets_total.get(), 1);
+
+    // Playback resumed on the new stream after the confirming packets
+    let played = 2 * PACKETS - UNCONFIRMED;
+    assert!(
+        sink.frames >= played as u64,
+        "played {} frames, expected at least {}",
+        sink.frames,
+        played
+    );
+
+    // The restarted sequence numbers are not counted against the old
+    // stream
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, played as u64);
+    assert_eq!(snapshot.packets_lost, 0);
+    assert_eq!(receiver_metrics.jitter_buffer_lost_total.get(), 0);
+}
@@ -0,0 +1,142 @@
+//! Integration test for RTP over TCP.
+//!
+//! Streams through a TCP proxy that cuts the first connection mid-stream,
+//! and checks that the sender reconnects, the receiver takes the new
+//! connection, and decoding carries on after it.
+
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use receiver::{
+    DecoderSet, NullSink, ReceiveOptions, ReceiverNetworkConfig, ReceiverStats, RtpReceiver,
+    PAYLOAD_TYPE_OPUS,
+};
+use rtp_opus_common::{complete_frame, MetricsContext, Transport, FRAME_HEADER_LEN};
+use sender::codec::SAMPLES_PER_FRAME;
+use sender::{
+    stream_audio, AudioData, OpusEncoderWrapper, RtpSender, SenderNetworkConfig, StreamOptions,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Packets sent in total
+const PACKETS: usize = 200;
+
+/// Packets forwarded before the proxy cuts the first connection
+const CUT_AFTER: usize = 100;
+
+/// Forwards each connection to `target`, counting them in `connections`.
+/// The first is cut after `CUT_AFTER` whole frames; later ones are
+/// forwarded until they close.
+async fn run_proxy(listener: TcpListener, target: SocketAddr, connections: Arc<AtomicUsize>) {
+    // ---
+    loop {
+        let (mut inbound, _) = listener.accept().await.expect("proxy accept");
+        let mut outbound = TcpStream::connect(target).await.expect("proxy connect");
+        if connections.fetch_add(1, Ordering::SeqCst) > 0 {
+            tokio::io::copy(&mut inbound, &mut outbound).await.ok();
+            continue;
+        }
+
+        let mut pending = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let mut frames = 0;
+        while frames < CUT_AFTER {
+            let n = inbound.read(&mut chunk).await.expect("proxy read");
+            pending.extend_from_slice(&chunk[..n]);
+            while let Some(len) = complete_frame(&pending).filter(|_| frames < CUT_AFTER) {
+                let frame: Vec<u8> = pending.drain(..FRAME_HEADER_LEN + len).collect();
+                outbound.write_all(&frame).await.expect("proxy write");
+                frames += 1;
+            }
+        }
+        // Both connections drop here
+    }
+}
+
+#[tokio::test]
+async fn test_tcp_stream_survives_reconnection() {
+    // ---
+    let receiver_config = ReceiverNetworkConfig {
+        transport: Transport::Tcp,
+        ..Default::default()
+    };
+    let mut receiver = RtpReceiver::with_config(0, receiver_config)
+        .await
+        .expect("receiver");
+    let receiver_addr = SocketAddr::from(([127, 0, 0, 1], receiver.local_addr().unwrap().port()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("proxy bind");
+    let proxy_addr = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let proxy = tokio::spawn(run_proxy(listener, receiver_addr, connections.clone()));
+
+    let tone: Vec<i16> = (0..PACKETS * SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+        .collect();
+    let audio = AudioData::from_samples(tone);
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let sender_config = SenderNetworkConfig {
+        transport: Transport::Tcp,
+        ..Default::default()
+    };
+    let mut sender = RtpSender::with_config(proxy_addr.to_string(), sender_config)
+        .await
+        .expect("sender");
+    let sender_metrics = MetricsContext::new("test_sender").expect("metrics");
+    let stream_options = StreamOptions {
+        // The cut comes 1s in, late enough to reconnect right away
+        interval_ms: 10,
+        ..Default::default()
+    };
+
+    let mut decoders = DecoderSet::new(PAYLOAD_TYPE_OPUS);
+    let mut sink = NullSink::new();
+    let mut stats = ReceiverStats::new(Duration::from_secs(60));
+    let receiver_metrics = MetricsContext::new("test_receiver").expect("metrics");
+    let receive_options = ReceiveOptions {
+        idle_timeout: Some(Duration::from_secs(1)),
+        ..Default::default()
+    };
+
+    let send = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &sender_metrics,
+        0x0660_4571,
+        &stream_options,
+    );
+    let receive = receiver::receive_loop(
+        &mut receiver,
+        &mut decoders,
+        &mut sink,
+        &mut stats,
+        &receive_options,
+        &receiver_metrics,
+    );
+    let (sent, received) = tokio::join!(send, receive);
+    sent.expect("stream");
+    received.expect("receive");
+    proxy.abort();
+
+    // The sender saw the cut before its next write and connected again,
+    // so no packet went into the dead connection
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+    let sender_stats = sender.stats();
+    assert_eq!(sender_stats.packets_sent, PACKETS as u64);
+    assert_eq!(sender_stats.packets_failed, 0);
+
+    // The receiver followed the new connection and got every packet
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, PACKETS as u64);
+    assert_eq!(snapshot.packets_lost, 0);
+    assert_eq!(receiver.source_rebinds(), 1);
+
+    // Every packet decoded, after the reconnection too
+    let opus = decoders.stats()[&PAYLOAD_TYPE_OPUS];
+    assert_eq!(opus.failed, 0);
+    assert_eq!(opus.decoded, PACKETS as u64);
+}
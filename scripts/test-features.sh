@@ -0,0 +1,29 @@
+#!/bin/bash
+# Build, lint, and test each crate in the feature combinations packagers
+# use. Keep in sync with the `features` job in .github/workflows/rust.yml.
+set -e
+
+COMBINATIONS=(
+    "-p rtp-opus-common --no-default-features"
+    "-p rtp-opus-common --all-features"
+    "-p sender --no-default-features"
+    "-p receiver --no-default-features"
+    "-p receiver --no-default-features --features playback"
+    "-p receiver --no-default-features --features formats"
+    "-p receiver --no-default-features --features metrics-server"
+    "-p rtp-opus --no-default-features"
+)
+
+echo "=== Running feature matrix ==="
+
+for combination in "${COMBINATIONS[@]}"; do
+    echo ""
+    echo "--- cargo test $combination"
+    # shellcheck disable=SC2086
+    cargo clippy --no-deps --color never --all-targets $combination -- -D warnings
+    # shellcheck disable=SC2086
+    cargo test --color never $combination
+done
+
+echo ""
+echo "✅ All feature combinations passed!"
@@ -0,0 +1,85 @@
+//! Micro-benchmark for the encode path.
+//!
+//! Run with `cargo bench -p sender --bench encode`. Counts heap
+//! allocations with a wrapping global allocator and compares
+//! `OpusEncoderWrapper::encode`, which allocates its output per frame, with
+//! `encode_into` reusing one buffer, which must not allocate once warm.
+
+use sender::codec::{OpusEncoderWrapper, MAX_PACKET_BYTES, SAMPLES_PER_FRAME};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Frames encoded per path (20 seconds of 20ms frames)
+const FRAMES: u64 = 1000;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // ---
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // ---
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Runs `encode` over `FRAMES` frames of tone and returns the time taken
+/// and the allocations made.
+fn measure(mut encode: impl FnMut(&[i16])) -> (Duration, u64) {
+    // ---
+    let tone: Vec<i16> = (0..SAMPLES_PER_FRAME)
+        .map(|i| ((i as f32 * 0.07).sin() * 12000.0) as i16)
+        .collect();
+
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        encode(black_box(&tone));
+    }
+    let elapsed = start.elapsed();
+    (
+        elapsed,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations_before,
+    )
+}
+
+fn report(name: &str, (elapsed, allocations): (Duration, u64)) {
+    // ---
+    println!(
+        "{}: {:.1} us/frame over {} frames, {} allocations",
+        name,
+        elapsed.as_nanos() as f64 / 1000.0 / FRAMES as f64,
+        FRAMES,
+        allocations
+    );
+}
+
+fn main() {
+    // ---
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let allocating = measure(|frame| {
+        black_box(encoder.encode(frame).expect("encode"));
+    });
+    report("encode", allocating);
+
+    let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+    let mut out = Vec::with_capacity(MAX_PACKET_BYTES);
+    let reusing = measure(|frame| {
+        black_box(encoder.encode_into(frame, &mut out).expect("encode_into"));
+    });
+    report("encode_into", reusing);
+
+    assert!(allocating.1 >= FRAMES, "encode should allocate per frame");
+    assert_eq!(reusing.1, 0, "encode_into allocated");
+}
@@ -0,0 +1,100 @@
+//! Micro-benchmark for the send path.
+//!
+//! Run with `cargo bench -p sender --bench send`. Sends RTP-sized
+//! datagrams over loopback three ways: `send_to` with the address as a
+//! string, parsed on every call (how `RtpSender` used to send);
+//! `send_to` with a resolved `SocketAddr` (fan-out); and `send` on a
+//! connected socket (a single destination). Then compares
+//! `RtpSender::send` for each packet with `RtpSender::send_batch`, which
+//! must count every packet exactly.
+
+use sender::network::{RtpSender, DEFAULT_BATCH};
+use sender::RtpPacket;
+use std::hint::black_box;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+/// Datagrams sent per path
+const PACKETS: u32 = 50_000;
+
+/// A 32 kbps 20ms Opus frame plus the RTP header
+const PACKET_BYTES: usize = 92;
+
+/// Runs `send` `PACKETS` times and returns the time taken.
+async fn measure<F, Fut>(mut send: F) -> Duration
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = std::io::Result<usize>>,
+{
+    // ---
+    let start = Instant::now();
+    for _ in 0..PACKETS {
+        black_box(send().await.expect("send"));
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    // ---
+    println!(
+        "{}: {:.2} us/packet over {} packets",
+        name,
+        elapsed.as_nanos() as f64 / 1000.0 / PACKETS as f64,
+        PACKETS
+    );
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // ---
+    // Drained by nobody: the kernel drops what overflows its buffer
+    let receiver = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+    let target: SocketAddr = receiver.local_addr().unwrap();
+    let addr = target.to_string();
+    let data = [0x80u8; PACKET_BYTES];
+
+    let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+    report(
+        "send_to(&str)",
+        measure(|| socket.send_to(&data, addr.as_str())).await,
+    );
+    report(
+        "send_to(SocketAddr)",
+        measure(|| socket.send_to(&data, target)).await,
+    );
+
+    socket.connect(target).await.expect("connect");
+    report("send (connected)", measure(|| socket.send(&data)).await);
+
+    let packets: Vec<RtpPacket> = (0..PACKETS)
+        .map(|i| {
+            let payload = vec![0u8; PACKET_BYTES - 12];
+            RtpPacket::new(i as u16, i * 320, 7, payload)
+        })
+        .collect();
+
+    let mut sender = RtpSender::new(addr.as_str()).await.expect("sender");
+    let start = Instant::now();
+    for packet in &packets {
+        assert!(sender.send(packet).await.expect("send"));
+    }
+    report("RtpSender::send", start.elapsed());
+    let unbatched = sender.stats();
+
+    let mut sender = RtpSender::new(addr.as_str()).await.expect("sender");
+    let mut sent = Vec::with_capacity(DEFAULT_BATCH);
+    let start = Instant::now();
+    for batch in packets.chunks(DEFAULT_BATCH) {
+        sender.send_batch(batch, &mut sent).await.expect("send");
+    }
+    report(
+        &format!("RtpSender::send_batch ({DEFAULT_BATCH})"),
+        start.elapsed(),
+    );
+    let batched = sender.stats();
+
+    assert_eq!(unbatched.packets_sent, PACKETS as u64);
+    assert_eq!(batched.packets_sent, unbatched.packets_sent);
+    assert_eq!(batched.bytes_sent, unbatched.bytes_sent);
+}
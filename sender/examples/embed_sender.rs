@@ -0,0 +1,69 @@
+//! Embedding the sender library in another tokio application.
+//!
+//! Streams three seconds of a generated 440 Hz tone to a UDP address with
+//! the application's own metrics registry, then prints a short summary.
+//!
+//! ```bash
+//! cargo run -p sender --example embed_sender -- 127.0.0.1:5004
+//! ```
+
+use anyhow::Result;
+use sender::codec::SAMPLE_RATE;
+use sender::{
+    stream_audio, AudioData, MetricsContext, OpusEncoderWrapper, RtpSender, StreamOptions,
+};
+
+/// Length of the generated tone
+const TONE_SECS: u32 = 3;
+
+/// Frequency of the generated tone
+const TONE_HZ: f32 = 440.0;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // ---
+    let remote = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:5004".to_string());
+
+    // Any 16kHz mono PCM works; here a tone at half scale
+    let samples = (0..SAMPLE_RATE * TONE_SECS)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            ((t * TONE_HZ * std::f32::consts::TAU).sin() * 16000.0) as i16
+        })
+        .collect();
+    let audio = AudioData::from_samples(samples);
+
+    // Metrics land in the application's registry; serve them with
+    // `spawn_metrics_server` or read the handles directly as below
+    let metrics = MetricsContext::new("embed-sender")?;
+    let mut encoder = OpusEncoderWrapper::new()?;
+    let mut sender = RtpSender::new(remote.as_str()).await?;
+    let options = StreamOptions {
+        pad_packets_to: Some(120),
+        ..Default::default()
+    };
+
+    println!("Streaming {:.1}s to {remote}", audio.duration_secs());
+    let summary = stream_audio(
+        &audio,
+        &mut encoder,
+        &mut sender,
+        &metrics,
+        rand::random(),
+        &options,
+    )
+    .await?;
+
+    let stats = sender.stats();
+    println!(
+        "Sent {} packets ({} bytes on the wire), {} payload bytes, {} padding bytes",
+        stats.packets_sent,
+        stats.bytes_sent,
+        summary.bytes_sent,
+        metrics.padding_bytes_sent_total.get()
+    );
+
+    Ok(())
+}
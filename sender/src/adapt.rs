@@ -0,0 +1,541 @@
+//! Encoder adaptation to receiver feedback.
+//!
+//! [`AdaptationController`] closes the loop from RTCP receiver reports to
+//! the encoder. Loss above a threshold in consecutive reports steps the
+//! bitrate down a ladder of common Opus rates and raises the loss the
+//! encoder plans its in-band FEC for; loss that stays near zero for a
+//! while steps it back up. Its decisions reach
+//! [`stream_audio`](crate::stream_audio) through
+//! [`StreamOptions::encoder_control`](crate::StreamOptions::encoder_control).
+//!
+//! The controller takes the current time as an argument, so scripted
+//! feedback can be played against it over simulated time.
+
+use anyhow::Result;
+use rtp_opus_common::{MetricsContext, ReportBlock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use crate::codec::{EncoderSettings, MAX_BITRATE, MIN_BITRATE};
+
+/// Default lowest bitrate adaptation steps down to, in bits per second
+pub const DEFAULT_ADAPT_MIN_BITRATE: i32 = 12000;
+
+/// Default fraction lost above which a report counts as lossy
+pub const DEFAULT_ADAPT_LOSS_THRESHOLD: f64 = 0.05;
+
+/// Default fraction lost at or below which a report counts as clean
+pub const DEFAULT_ADAPT_CLEAN_THRESHOLD: f64 = 0.01;
+
+/// Default lossy reports in a row that step the bitrate down
+pub const DEFAULT_ADAPT_LOSS_REPORTS: u32 = 2;
+
+/// Default time reports must stay clean before the bitrate steps up
+pub const DEFAULT_ADAPT_RECOVER_AFTER: Duration = Duration::from_secs(10);
+
+/// Default highest loss the encoder is told to plan for, in percent
+pub const DEFAULT_ADAPT_MAX_EXPECTED_LOSS: u8 = 25;
+
+/// Rates the bitrate steps between, in bits per second; the configured
+/// bounds are steps too
+const BITRATE_LADDER: [i32; 10] = [
+    6000, 8000, 12000, 16000, 24000, 32000, 48000, 64000, 96000, 128000,
+];
+
+/// Bounds and hysteresis of the adaptation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptationConfig {
+    // ---
+    /// Lowest bitrate to step down to, in bits per second
+    pub min_bitrate: i32,
+
+    /// Highest bitrate to step up to, in bits per second
+    pub max_bitrate: i32,
+
+    /// Fraction lost above which a report counts as lossy
+    pub loss_threshold: f64,
+
+    /// Fraction lost at or below which a report counts as clean; reports
+    /// in between neither step down nor count towards recovery
+    pub clean_threshold: f64,
+
+    /// Lossy reports in a row that step the bitrate down
+    pub loss_reports: u32,
+
+    /// Time reports must stay clean before the bitrate steps up, and
+    /// between steps up
+    pub recover_after: Duration,
+
+    /// Highest loss the encoder is told to plan for, in percent
+    pub max_expected_loss: u8,
+}
+
+impl Default for AdaptationConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            min_bitrate: DEFAULT_ADAPT_MIN_BITRATE,
+            max_bitrate: crate::codec::BITRATE,
+            loss_threshold: DEFAULT_ADAPT_LOSS_THRESHOLD,
+            clean_threshold: DEFAULT_ADAPT_CLEAN_THRESHOLD,
+            loss_reports: DEFAULT_ADAPT_LOSS_REPORTS,
+            recover_after: DEFAULT_ADAPT_RECOVER_AFTER,
+            max_expected_loss: DEFAULT_ADAPT_MAX_EXPECTED_LOSS,
+        }
+    }
+}
+
+impl AdaptationConfig {
+    // ---
+    /// Checks the bounds and thresholds.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a bound is outside what Opus supports or the
+    /// minimum is above the maximum, the thresholds are not fractions
+    /// with the clean one below the lossy one, no lossy reports are
+    /// needed, or the expected loss cap is above 100%.
+    pub fn validate(&self) -> Result<()> {
+        // ---
+        for bitrate in [self.min_bitrate, self.max_bitrate] {
+            if !(MIN_BITRATE..=MAX_BITRATE).contains(&bitrate) {
+                anyhow::bail!(
+                    "adaptation bound {} bps is out of range: Opus supports {} to {} bps",
+                    bitrate,
+                    MIN_BITRATE,
+                    MAX_BITRATE
+                );
+            }
+        }
+        if self.min_bitrate > self.max_bitrate {
+            anyhow::bail!(
+                "adaptation minimum {} bps is above the maximum {} bps",
+                self.min_bitrate,
+                self.max_bitrate
+            );
+        }
+        if !(0.0..=1.0).contains(&self.loss_threshold)
+            || !(0.0..self.loss_threshold).contains(&self.clean_threshold)
+        {
+            anyhow::bail!(
+                "adaptation thresholds {} and {} must be fractions with the clean one below the lossy one",
+                self.clean_threshold,
+                self.loss_threshold
+            );
+        }
+        if self.loss_reports == 0 {
+            anyhow::bail!("adaptation needs at least one lossy report to step down");
+        }
+        if self.max_expected_loss > 100 {
+            anyhow::bail!(
+                "expected loss cap {}% is out of range: use 0 to 100",
+                self.max_expected_loss
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Why the controller changed the encoder settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdaptationReason {
+    /// Reports stayed lossy
+    Loss,
+
+    /// Reports stayed clean
+    Recovered,
+}
+
+impl AdaptationReason {
+    // ---
+    /// Returns the reason name used in logs.
+    pub fn as_str(self) -> &'static str {
+        // ---
+        match self {
+            AdaptationReason::Loss => "loss",
+            AdaptationReason::Recovered => "recovered",
+        }
+    }
+}
+
+impl std::fmt::Display for AdaptationReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// Decides encoder settings from the loss receivers report.
+#[derive(Debug)]
+pub struct AdaptationController {
+    // ---
+    /// Bounds and hysteresis
+    config: AdaptationConfig,
+
+    /// Bitrates the controller steps between, ascending
+    ladder: Vec<i32>,
+
+    /// Expected loss to return to once reports are clean at the top
+    base_expected_loss: u8,
+
+    /// Settings the controller currently wants
+    settings: EncoderSettings,
+
+    /// Lossy reports in a row since the last step
+    lossy_reports: u32,
+
+    /// When the current run of clean reports began, or the last step up
+    /// within it
+    clean_since: Option<Instant>,
+}
+
+impl AdaptationController {
+    // ---
+    /// Creates a controller starting from `initial`, its bitrate brought
+    /// within the bounds.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `config` is invalid.
+    pub fn new(config: AdaptationConfig, initial: EncoderSettings) -> Result<Self> {
+        // ---
+        config.validate()?;
+        let mut ladder: Vec<i32> = BITRATE_LADDER
+            .into_iter()
+            .filter(|&bitrate| bitrate > config.min_bitrate && bitrate < config.max_bitrate)
+            .collect();
+        ladder.insert(0, config.min_bitrate);
+        if config.max_bitrate > config.min_bitrate {
+            ladder.push(config.max_bitrate);
+        }
+        let settings = EncoderSettings {
+            bitrate: initial
+                .bitrate
+                .clamp(config.min_bitrate, config.max_bitrate),
+            ..initial
+        };
+
+        Ok(Self {
+            config,
+            ladder,
+            base_expected_loss: initial.expected_loss_pct,
+            settings,
+            lossy_reports: 0,
+            clean_since: None,
+        })
+    }
+
+    /// Returns the settings the controller currently wants.
+    pub fn settings(&self) -> EncoderSettings {
+        // ---
+        self.settings
+    }
+
+    /// Takes in a receiver report of `fraction_lost` at `now`.
+    ///
+    /// # Returns
+    ///
+    /// The settings to apply when they changed, with the reason, `None`
+    /// otherwise.
+    pub fn on_report(
+        &mut self,
+        fraction_lost: f64,
+        now: Instant,
+    ) -> Option<(EncoderSettings, AdaptationReason)> {
+        // ---
+        if fraction_lost > self.config.loss_threshold {
+            self.clean_since = None;
+            self.lossy_reports += 1;
+            if self.lossy_reports < self.config.loss_reports {
+                return None;
+            }
+            self.lossy_reports = 0;
+
+            // Plan FEC for at least the loss just reported
+            let reported = (fraction_lost * 100.0).ceil() as u8;
+            let settings = EncoderSettings {
+                bitrate: self.step_down(),
+                expected_loss_pct: self
+                    .settings
+                    .expected_loss_pct
+                    .max(reported.min(self.config.max_expected_loss)),
+            };
+            return self.change(settings, AdaptationReason::Loss);
+        }
+        self.lossy_reports = 0;
+        if fraction_lost > self.config.clean_threshold {
+            self.clean_since = None;
+            return None;
+        }
+
+        let since = *self.clean_since.get_or_insert(now);
+        if now.saturating_duration_since(since) < self.config.recover_after {
+            return None;
+        }
+        self.clean_since = Some(now);
+
+        // Halve the FEC planned on the way up, back to the start at the top
+        let bitrate = self.step_up();
+        let expected_loss_pct = if bitrate == self.config.max_bitrate {
+            self.base_expected_loss
+        } else {
+            (self.settings.expected_loss_pct / 2).max(self.base_expected_loss)
+        };
+        let settings = EncoderSettings {
+            bitrate,
+            expected_loss_pct,
+        };
+        self.change(settings, AdaptationReason::Recovered)
+    }
+
+    /// Returns the step below the current bitrate, or the lowest.
+    fn step_down(&self) -> i32 {
+        // ---
+        let current = self.settings.bitrate;
+        self.ladder
+            .iter()
+            .rev()
+            .copied()
+            .find(|&bitrate| bitrate < current)
+            .unwrap_or(self.config.min_bitrate)
+    }
+
+    /// Returns the step above the current bitrate, or the highest.
+    fn step_up(&self) -> i32 {
+        // ---
+        let current = self.settings.bitrate;
+        self.ladder
+            .iter()
+            .copied()
+            .find(|&bitrate| bitrate > current)
+            .unwrap_or(self.config.max_bitrate)
+    }
+
+    /// Moves to `settings` if they differ, logging the decision.
+    fn change(
+        &mut self,
+        settings: EncoderSettings,
+        reason: AdaptationReason,
+    ) -> Option<(EncoderSettings, AdaptationReason)> {
+        // ---
+        if settings == self.settings {
+            return None;
+        }
+        tracing::info!(
+            reason = %reason,
+            bitrate_bps = settings.bitrate,
+            previous_bitrate_bps = self.settings.bitrate,
+            expected_loss_pct = settings.expected_loss_pct,
+            "Adapting encoder to receiver feedback"
+        );
+        self.settings = settings;
+        Some((settings, reason))
+    }
+
+    /// Runs the controller in a background task on the report blocks
+    /// from `reports` (see [`RtpSender::subscribe_reports`]), sending each
+    /// change on `control` and recording the bitrate in the
+    /// `current_bitrate_bps` gauge of `metrics`.
+    ///
+    /// The task ends once either channel closes.
+    ///
+    /// [`RtpSender::subscribe_reports`]: crate::RtpSender::subscribe_reports
+    pub fn spawn(
+        mut self,
+        mut reports: mpsc::UnboundedReceiver<ReportBlock>,
+        control: watch::Sender<EncoderSettings>,
+        metrics: MetricsContext,
+    ) -> JoinHandle<()> {
+        // ---
+        metrics
+            .current_bitrate_bps
+            .set(self.settings.bitrate as i64);
+        tokio::spawn(async move {
+            loop {
+                let block = tokio::select! {
+                    block = reports.recv() => block,
+                    () = control.closed() => None,
+                };
+                let Some(block) = block else {
+                    break;
+                };
+                let fraction_lost = block.fraction_lost_ratio();
+                tracing::debug!(fraction_lost, "Adaptation took in a receiver report");
+                if let Some((settings, _)) = self.on_report(fraction_lost, Instant::now()) {
+                    metrics.current_bitrate_bps.set(settings.bitrate as i64);
+                    if control.send(settings).is_err() {
+                        break;
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    /// Interval between scripted receiver reports
+    const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+    fn new_controller() -> AdaptationController {
+        // ---
+        let initial = EncoderSettings {
+            bitrate: 24000,
+            expected_loss_pct: 0,
+        };
+        AdaptationController::new(AdaptationConfig::default(), initial).unwrap()
+    }
+
+    /// Plays `losses` as reports `REPORT_INTERVAL` apart and returns the
+    /// bitrate after each.
+    fn play(controller: &mut AdaptationController, losses: &[f64]) -> Vec<i32> {
+        // ---
+        let start = Instant::now();
+        losses
+            .iter()
+            .enumerate()
+            .map(|(i, &loss)| {
+                controller.on_report(loss, start + REPORT_INTERVAL * i as u32);
+                controller.settings().bitrate
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_loss_spike_steps_down_to_floor() {
+        // ---
+        let mut controller = new_controller();
+        let bitrates = play(&mut controller, &[0.0, 0.10, 0.12, 0.15, 0.20, 0.30, 0.30]);
+        assert_eq!(bitrates, [24000, 24000, 16000, 16000, 12000, 12000, 12000]);
+        assert_eq!(
+            controller.settings().expected_loss_pct,
+            DEFAULT_ADAPT_MAX_EXPECTED_LOSS
+        );
+    }
+
+    #[test]
+    fn test_single_spike_does_not_step_down() {
+        // ---
+        let mut controller = new_controller();
+        let bitrates = play(&mut controller, &[0.20, 0.0, 0.20, 0.0]);
+        assert_eq!(bitrates, [24000; 4]);
+    }
+
+    #[test]
+    fn test_recovery_steps_back_up() {
+        // ---
+        let mut controller = new_controller();
+        // Down twice, then clean: a step up every 10s of clean reports
+        let bitrates = play(
+            &mut controller,
+            &[0.10, 0.10, 0.10, 0.10, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        );
+        assert_eq!(
+            bitrates,
+            [24000, 16000, 16000, 12000, 12000, 12000, 16000, 16000, 24000, 24000, 24000]
+        );
+        assert_eq!(controller.settings().expected_loss_pct, 0);
+    }
+
+    #[test]
+    fn test_fec_raised_with_loss_and_lowered_on_recovery() {
+        // ---
+        let mut controller = new_controller();
+        let mut now = Instant::now();
+        let mut next = |loss| {
+            now += REPORT_INTERVAL;
+            controller.on_report(loss, now)
+        };
+        assert_eq!(next(0.08), None);
+        let (settings, reason) = next(0.08).unwrap();
+        assert_eq!(reason, AdaptationReason::Loss);
+        assert_eq!((settings.bitrate, settings.expected_loss_pct), (16000, 8));
+
+        assert_eq!(next(0.15), None);
+        let (settings, _) = next(0.15).unwrap();
+        assert_eq!((settings.bitrate, settings.expected_loss_pct), (12000, 15));
+
+        assert_eq!(next(0.0), None);
+        assert_eq!(next(0.0), None);
+        let (settings, reason) = next(0.0).unwrap();
+        assert_eq!(reason, AdaptationReason::Recovered);
+        assert_eq!((settings.bitrate, settings.expected_loss_pct), (16000, 7));
+    }
+
+    #[test]
+    fn test_oscillating_loss_holds() {
+        // ---
+        let mut controller = new_controller();
+        // Lossy and clean reports take turns: never two lossy in a row,
+        // never 10s clean
+        let losses: Vec<f64> = (0..20)
+            .map(|i| if i % 2 == 0 { 0.10 } else { 0.0 })
+            .collect();
+        assert_eq!(play(&mut controller, &losses), [24000; 20]);
+
+        // Loss between the thresholds breaks a clean run without counting
+        // as lossy
+        let mut controller = new_controller();
+        let bitrates = play(
+            &mut controller,
+            &[0.10, 0.10, 0.0, 0.0, 0.03, 0.0, 0.0, 0.03, 0.10, 0.03, 0.10],
+        );
+        assert_eq!(
+            bitrates,
+            [24000, 16000, 16000, 16000, 16000, 16000, 16000, 16000, 16000, 16000, 16000]
+        );
+    }
+
+    #[test]
+    fn test_bounds() {
+        // ---
+        let config = AdaptationConfig {
+            min_bitrate: 10000,
+            max_bitrate: 40000,
+            loss_reports: 1,
+            recover_after: Duration::ZERO,
+            ..Default::default()
+        };
+        let initial = EncoderSettings {
+            bitrate: 64000,
+            expected_loss_pct: 5,
+        };
+        let mut controller = AdaptationController::new(config.clone(), initial).unwrap();
+        assert_eq!(controller.settings().bitrate, 40000);
+
+        let bitrates = play(
+            &mut controller,
+            &[0.5, 0.5, 0.5, 0.5, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0],
+        );
+        assert_eq!(
+            bitrates,
+            [32000, 24000, 16000, 12000, 10000, 12000, 16000, 24000, 32000, 40000]
+        );
+        assert_eq!(controller.settings().expected_loss_pct, 5);
+
+        for invalid in [
+            AdaptationConfig {
+                min_bitrate: 50000,
+                ..config.clone()
+            },
+            AdaptationConfig {
+                max_bitrate: 1000,
+                ..config.clone()
+            },
+            AdaptationConfig {
+                clean_threshold: 0.10,
+                ..config.clone()
+            },
+            AdaptationConfig {
+                loss_reports: 0,
+                ..config.clone()
+            },
+        ] {
+            assert!(AdaptationController::new(invalid, initial).is_err());
+        }
+    }
+}
@@ -1,14 +1,23 @@
 //! Audio file reading and preprocessing.
 //!
-//! Handles WAV file parsing and conversion to the format required
-//! for Opus encoding (16kHz mono PCM).
+//! Handles WAV file parsing, FLAC and MP3 decoding (through symphonia),
+//! conversion to the format required for Opus encoding (16kHz mono PCM), and
+//! generation of test signals in that format.
 
 use anyhow::{Context, Result};
 use hound::{WavReader, WavSpec};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::ops::Range;
 use std::path::Path;
-use tracing::info;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn};
 
 use crate::codec::{SAMPLES_PER_FRAME, SAMPLE_RATE};
+use crate::probe::{analyze_silence, SilenceAnalyzer, SilenceConfig, SilenceReport};
+use crate::source::{apply_loop_fade, duration_samples, FrameSource, TailPolicy};
 
 /// Audio data container with PCM samples and metadata.
 ///
@@ -27,10 +36,28 @@ pub struct AudioData {
     /// Number of channels in the original file
     #[allow(dead_code)] // Metadata for debugging/logging
     pub original_channels: u16,
+
+    /// Samples clipped at full scale while loading: float input beyond
+    /// +/-1.0 (counted per input sample) and samples pushed past full
+    /// scale by gain or normalization
+    pub clipped_samples: u64,
 }
 
 impl AudioData {
     // ---
+    /// Wraps PCM samples that are already 16kHz mono.
+    ///
+    /// Use this to stream generated or captured audio without a WAV file.
+    pub fn from_samples(samples: Vec<i16>) -> Self {
+        // ---
+        Self {
+            samples,
+            original_sample_rate: SAMPLE_RATE,
+            original_channels: 1,
+            clipped_samples: 0,
+        }
+    }
+
     /// Returns an iterator over 20ms audio frames.
     ///
     /// Each frame contains exactly SAMPLES_PER_FRAME (320) samples,
@@ -57,7 +84,10 @@ impl AudioData {
 /// Reads and preprocesses a WAV file for streaming.
 ///
 /// Automatically converts the audio to 16kHz mono format required for
-/// Opus encoding. Supports various input sample rates and channel configurations.
+/// Opus encoding. Supports various input sample rates and channel configurations,
+/// and 8, 16, or 24-bit integer or 32-bit float samples; 8 and 24-bit samples
+/// are scaled to 16 bits, and float samples are rounded with TPDF dither
+/// (see [`read_wav_with_options`]).
 ///
 /// # Arguments
 ///
@@ -84,10 +114,434 @@ impl AudioData {
 /// ```
 pub fn read_wav<P: AsRef<Path>>(path: P) -> Result<AudioData> {
     // ---
-    let path = path.as_ref();
+    read_wav_with_options(path, &AudioReadOptions::default())
+}
+
+/// Reads a WAV file like [`read_wav`], reading its samples as set in
+/// `options`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`read_wav`].
+pub fn read_wav_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &AudioReadOptions,
+) -> Result<AudioData> {
+    // ---
+    let raw = read_wav_samples(path.as_ref(), options)?;
+    to_audio_data(&raw)
+}
+
+/// Reads a WAV file like [`read_wav`] and also analyzes it for silence.
+///
+/// Silence analysis runs on the original channels before downmix, so a
+/// muted channel in a stereo file is reported even though the mono mix is
+/// not silent.
+///
+/// # Errors
+///
+/// Returns the same errors as [`read_wav`].
+pub fn read_wav_with_silence<P: AsRef<Path>>(
+    path: P,
+    config: &SilenceConfig,
+) -> Result<(AudioData, SilenceReport)> {
+    // ---
+    let raw = read_wav_samples(path.as_ref(), &AudioReadOptions::default())?;
+    let report = analyze_silence(
+        &raw.samples,
+        raw.spec.channels as usize,
+        raw.spec.sample_rate,
+        config,
+    );
+    Ok((to_audio_data(&raw)?, report))
+}
+
+/// Container format of an audio file, as detected by [`AudioFormat::detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    // ---
+    /// RIFF WAVE
+    Wav,
+
+    /// Free Lossless Audio Codec
+    Flac,
+
+    /// MPEG-1/2 Audio Layer III
+    Mp3,
+
+    /// Ogg (Opus or Vorbis)
+    Ogg,
+
+    /// ISO base media (MP4, M4A)
+    Mp4,
+
+    /// Audio Interchange File Format
+    Aiff,
+
+    /// Matroska or WebM
+    Matroska,
+
+    /// Nothing recognized
+    Unknown,
+}
+
+impl AudioFormat {
+    // ---
+    /// Formats [`read_audio`] decodes.
+    pub const SUPPORTED: [AudioFormat; 3] = [AudioFormat::Wav, AudioFormat::Flac, AudioFormat::Mp3];
+
+    /// Returns the short name used in logs and errors.
+    pub fn as_str(self) -> &'static str {
+        // ---
+        match self {
+            AudioFormat::Wav => "WAV",
+            AudioFormat::Flac => "FLAC",
+            AudioFormat::Mp3 => "MP3",
+            AudioFormat::Ogg => "Ogg",
+            AudioFormat::Mp4 => "MP4",
+            AudioFormat::Aiff => "AIFF",
+            AudioFormat::Matroska => "Matroska",
+            AudioFormat::Unknown => "unknown",
+        }
+    }
+
+    /// Returns true if [`read_audio`] can decode this format.
+    pub fn is_supported(self) -> bool {
+        // ---
+        Self::SUPPORTED.contains(&self)
+    }
+
+    /// Identifies a format from the first bytes of a file, falling back to
+    /// the file extension when the header is not recognized.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - Start of the file (12 bytes suffice)
+    /// * `extension` - File extension without the dot, if any
+    pub fn detect(header: &[u8], extension: Option<&str>) -> Self {
+        // ---
+        let by_magic = if header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WAVE") {
+            Some(AudioFormat::Wav)
+        } else if header.starts_with(b"fLaC") {
+            Some(AudioFormat::Flac)
+        } else if header.starts_with(b"ID3") || is_mpeg_audio_sync(header) {
+            Some(AudioFormat::Mp3)
+        } else if header.starts_with(b"OggS") {
+            Some(AudioFormat::Ogg)
+        } else if header.get(4..8) == Some(b"ftyp") {
+            Some(AudioFormat::Mp4)
+        } else if header.starts_with(b"FORM") && header.get(8..11) == Some(b"AIF") {
+            Some(AudioFormat::Aiff)
+        } else if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            Some(AudioFormat::Matroska)
+        } else {
+            None
+        };
+        if let Some(format) = by_magic {
+            return format;
+        }
+
+        match extension.map(|ext| ext.to_ascii_lowercase()).as_deref() {
+            Some("wav" | "wave") => AudioFormat::Wav,
+            Some("flac") => AudioFormat::Flac,
+            Some("mp3") => AudioFormat::Mp3,
+            Some("ogg" | "oga" | "opus") => AudioFormat::Ogg,
+            Some("mp4" | "m4a") => AudioFormat::Mp4,
+            Some("aif" | "aiff") => AudioFormat::Aiff,
+            Some("mka" | "webm") => AudioFormat::Matroska,
+            _ => AudioFormat::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns true if `header` starts with an MPEG audio frame sync for
+/// Layer III.
+fn is_mpeg_audio_sync(header: &[u8]) -> bool {
+    // ---
+    matches!(header, [0xFF, second, ..] if second & 0xE0 == 0xE0 && (second >> 1) & 0x03 == 0x01)
+}
+
+/// Reads and preprocesses a WAV, FLAC, or MP3 file for streaming.
+///
+/// The format is detected from the file header (or, failing that, its
+/// extension). WAV goes through [`read_wav`]; FLAC and MP3 are decoded to
+/// PCM first. Either way the result is converted to 16kHz mono.
+///
+/// # Arguments
+///
+/// * `path` - Path to the audio file
+///
+/// # Errors
+///
+/// Returns error if the file cannot be read, its format is not one of
+/// [`AudioFormat::SUPPORTED`] (the error names the detected format), or
+/// decoding fails.
+///
+/// # Example
+///
+/// ```no_run
+/// use sender::audio::read_audio;
+///
+/// let audio = read_audio("voice.flac").unwrap();
+/// println!("Duration: {:.2}s", audio.duration_secs());
+/// ```
+pub fn read_audio<P: AsRef<Path>>(path: P) -> Result<AudioData> {
+    // ---
+    let raw = read_audio_samples(path.as_ref(), &AudioReadOptions::default())?;
+    to_audio_data(&raw)
+}
+
+/// Reads an audio file like [`read_audio`] and also analyzes it for
+/// silence, as [`read_wav_with_silence`] does.
+///
+/// # Errors
+///
+/// Returns the same errors as [`read_audio`].
+pub fn read_audio_with_silence<P: AsRef<Path>>(
+    path: P,
+    config: &SilenceConfig,
+) -> Result<(AudioData, SilenceReport)> {
+    // ---
+    read_audio_with_options(
+        path,
+        config,
+        &AudioReadOptions::default(),
+        &ConvertOptions::default(),
+    )
+}
+
+/// Reads an audio file like [`read_audio_with_silence`], reading the
+/// samples as set in `read` and converting them to 16kHz mono as set in
+/// `convert`.
+///
+/// Silence is analyzed on every original channel, whichever are kept, over
+/// the window `read` selects.
+///
+/// # Errors
+///
+/// Returns the same errors as [`read_audio`], an error if `read.start` is
+/// past the end of the file, and an error if `convert.channel_map` names a
+/// channel the file does not have.
+pub fn read_audio_with_options<P: AsRef<Path>>(
+    path: P,
+    config: &SilenceConfig,
+    read: &AudioReadOptions,
+    convert: &ConvertOptions,
+) -> Result<(AudioData, SilenceReport)> {
+    // ---
+    let raw = read_audio_samples(path.as_ref(), read)?;
+    let report = analyze_silence(
+        &raw.samples,
+        raw.spec.channels as usize,
+        raw.spec.sample_rate,
+        config,
+    );
+    let (samples, gain_clipped) = convert_to_target_format(&raw.samples, &raw.spec, convert)?;
+    Ok((
+        AudioData {
+            samples,
+            original_sample_rate: raw.spec.sample_rate,
+            original_channels: raw.spec.channels,
+            clipped_samples: raw.clipped + gain_clipped,
+        },
+        report,
+    ))
+}
+
+/// Detects the format of the file at `path`.
+///
+/// # Errors
+///
+/// Returns error if the file cannot be opened.
+pub fn detect_format(path: &Path) -> Result<AudioFormat> {
+    // ---
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("failed to open audio file: {}", path.display()))?;
+    let mut header = Vec::with_capacity(12);
+    file.by_ref()
+        .take(12)
+        .read_to_end(&mut header)
+        .with_context(|| format!("failed to read audio file: {}", path.display()))?;
+
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    Ok(AudioFormat::detect(&header, extension))
+}
+
+/// Interleaved 16-bit samples read from a file, before conversion.
+struct RawAudio {
+    // ---
+    /// Interleaved samples
+    samples: Vec<i16>,
+
+    /// Layout of `samples`
+    spec: WavSpec,
+
+    /// Samples clamped to full scale reading the file
+    clipped: u64,
+}
+
+impl RawAudio {
+    // ---
+    /// Keeps only the window `options` selects.
+    fn window(mut self, options: &AudioReadOptions) -> Result<Self> {
+        // ---
+        let channels = self.spec.channels as usize;
+        let total = (self.samples.len() / channels) as u64;
+        let (start, len) = options.window(self.spec.sample_rate, total)?;
+        let start = start as usize * channels;
+        self.samples.truncate(start + len as usize * channels);
+        self.samples.drain(..start);
+        Ok(self)
+    }
+}
+
+/// Reads interleaved 16-bit samples and a spec describing them from any
+/// supported format, as set in `options`.
+fn read_audio_samples(path: &Path, options: &AudioReadOptions) -> Result<RawAudio> {
+    // ---
+    let format = detect_format(path)?;
+    match format {
+        AudioFormat::Wav => read_wav_samples(path, options),
+        AudioFormat::Flac | AudioFormat::Mp3 => decode_samples(path, format)?.window(options),
+        unsupported => anyhow::bail!(
+            "unsupported audio format {} in {} (supported: WAV, FLAC, MP3)",
+            unsupported,
+            path.display()
+        ),
+    }
+}
+
+/// Decodes a FLAC or MP3 file to interleaved 16-bit samples.
+///
+/// Packets the decoder rejects are skipped with a warning, as players do
+/// with a damaged frame in the middle of a file.
+fn decode_samples(path: &Path, format: AudioFormat) -> Result<RawAudio> {
+    // ---
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::DecoderOptions;
+    use symphonia::core::errors::Error as DecodeError;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    info!("Reading {} file: {}", format, path.display());
+
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("failed to open {} file: {}", format, path.display()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension(match format {
+        AudioFormat::Flac => "flac",
+        _ => "mp3",
+    });
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .with_context(|| format!("failed to parse {} file: {}", format, path.display()))?;
+    let mut reader = probed.format;
+
+    let track = reader
+        .default_track()
+        .with_context(|| format!("{} file has no audio track", format))?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .with_context(|| format!("no {} decoder for this stream", format))?;
+
+    let mut samples = Vec::new();
+    let mut buffer: Option<SampleBuffer<i16>> = None;
+    let mut spec = None;
+    let mut skipped = 0;
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(DecodeError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e).context(format!("failed to read {} packet", format)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(DecodeError::DecodeError(reason)) => {
+                skipped += 1;
+                tracing::debug!("Skipping undecodable {} packet: {}", format, reason);
+                continue;
+            }
+            Err(e) => return Err(e).context(format!("failed to decode {} packet", format)),
+        };
+
+        // Buffers are sized for the largest packet the decoder can emit
+        let decoded_spec = *decoded.spec();
+        let buffer = buffer
+            .get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, decoded_spec));
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buffer.samples());
+        spec.get_or_insert(WavSpec {
+            channels: decoded_spec.channels.count() as u16,
+            sample_rate: decoded_spec.rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        });
+    }
+
+    if skipped > 0 {
+        warn!("Skipped {} undecodable {} packets", skipped, format);
+    }
+    let spec = spec.with_context(|| format!("{} file contains no audio", format))?;
+    info!(
+        "{} format: {}Hz, {} channels, {} samples",
+        format,
+        spec.sample_rate,
+        spec.channels,
+        samples.len()
+    );
+    Ok(RawAudio {
+        samples,
+        spec,
+        clipped: 0,
+    })
+}
+
+/// Reads interleaved 16-bit samples and the format spec from a WAV file.
+fn read_wav_samples(path: &Path, options: &AudioReadOptions) -> Result<RawAudio> {
+    // ---
     info!("Reading WAV file: {}", path.display());
 
-    let mut reader = WavReader::open(path)
+    let mut reader = open_wav(path)?;
+    let spec = reader.spec();
+
+    let window = seek_window(&mut reader, options)? as usize;
+    let mut samples = Vec::with_capacity(window);
+    let mut float = FloatConverter::new(options);
+    read_wav_chunk(&mut reader, window, &mut samples, &mut float)?;
+
+    info!("Read {} samples from file", samples.len());
+    float.warn(samples.len() as u64);
+    Ok(RawAudio {
+        samples,
+        spec,
+        clipped: float.clipped(),
+    })
+}
+
+/// Opens a WAV file and checks its sample format is one we can read.
+fn open_wav(path: &Path) -> Result<WavReader<BufReader<File>>> {
+    // ---
+    let reader = WavReader::open(path)
         .with_context(|| format!("failed to open WAV file: {}", path.display()))?;
 
     let spec = reader.spec();
@@ -98,29 +552,13 @@ pub fn read_wav<P: AsRef<Path>>(path: P) -> Result<AudioData> {
 
     use hound::SampleFormat;
 
-    let raw_samples: Vec<i16> = match (spec.sample_format, spec.bits_per_sample) {
-        // --- Native path
-        (SampleFormat::Int, 16) => reader
-            .samples::<i16>()
-            .collect::<Result<Vec<_>, _>>()
-            .context("failed to read 16-bit PCM WAV samples")?,
-
-        // --- Float path
-        (SampleFormat::Float, 32) => reader
-            .samples::<f32>()
-            .collect::<Result<Vec<_>, _>>()
-            .context("failed to read 32-bit float WAV samples")?
-            .into_iter()
-            .map(|s| {
-                let clamped = s.clamp(-1.0, 1.0);
-                (clamped * i16::MAX as f32) as i16
-            })
-            .collect(),
+    match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Int, 8 | 16 | 24) | (SampleFormat::Float, 32) => Ok(reader),
 
         // --- Explicit rejection (GOOD error)
         (SampleFormat::Int, bits) => {
             anyhow::bail!(
-                "unsupported integer PCM WAV format: {}-bit (only 16-bit PCM is supported)",
+                "unsupported integer PCM WAV format: {}-bit (only 8, 16, and 24-bit PCM are supported)",
                 bits
             );
         }
@@ -131,154 +569,3074 @@ pub fn read_wav<P: AsRef<Path>>(path: P) -> Result<AudioData> {
                 bits
             );
         }
-    };
-
-    info!("Read {} samples from file", raw_samples.len());
-
-    // Convert to target format (16kHz mono)
-    let samples = convert_to_target_format(&raw_samples, &spec)?;
-
-    Ok(AudioData {
-        samples,
-        original_sample_rate: spec.sample_rate,
-        original_channels: spec.channels,
-    })
+    }
 }
 
-/// Converts audio samples to target format (16kHz mono).
-///
-/// Handles resampling and channel conversion. Uses simple linear
-/// interpolation for resampling - sufficient for voice quality
-/// but not suitable for high-fidelity music.
-fn convert_to_target_format(samples: &[i16], spec: &WavSpec) -> Result<Vec<i16>> {
+/// Seed for the dither added converting float samples, so a file always
+/// converts to the same 16-bit samples
+const DITHER_SEED: u64 = 0x5EED_2026;
+
+/// How a file's samples are read, before conversion to 16kHz mono.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioReadOptions {
     // ---
-    let mut mono_samples = if spec.channels > 1 {
-        info!("Converting {} channels to mono", spec.channels);
-        convert_to_mono(samples, spec.channels as usize)
-    } else {
-        samples.to_vec()
-    };
+    /// Add TPDF dither converting float samples to 16-bit, so quiet
+    /// passages get a low, even noise floor instead of quantization
+    /// distortion. Integer samples are never dithered.
+    pub dither: bool,
 
-    // Resample if needed
-    if spec.sample_rate != SAMPLE_RATE {
-        info!(
-            "Resampling from {}Hz to {}Hz",
-            spec.sample_rate, SAMPLE_RATE
-        );
-        mono_samples = resample_linear(&mono_samples, spec.sample_rate, SAMPLE_RATE);
-    }
+    /// Skip this much of the file
+    pub start: Duration,
 
-    info!(
-        "Converted to target format: {} samples ({} frames)",
-        mono_samples.len(),
-        mono_samples.len().div_ceil(SAMPLES_PER_FRAME)
-    );
+    /// Read at most this much after `start`; a window running past the
+    /// end of the file is cut short
+    pub duration: Option<Duration>,
+}
 
-    Ok(mono_samples)
+impl Default for AudioReadOptions {
+    fn default() -> Self {
+        // ---
+        Self {
+            dither: true,
+            start: Duration::ZERO,
+            duration: None,
+        }
+    }
 }
 
-/// Converts multi-channel audio to mono by averaging channels.
-fn convert_to_mono(samples: &[i16], channels: usize) -> Vec<i16> {
+impl AudioReadOptions {
     // ---
-    let frame_count = samples.len() / channels;
-    let mut mono = Vec::with_capacity(frame_count);
-
-    for frame in samples.chunks(channels) {
-        let sum: i32 = frame.iter().map(|&s| s as i32).sum();
-        let avg = (sum / channels as i32) as i16;
-        mono.push(avg);
+    /// Returns the first sample frame of the window and how many sample
+    /// frames it holds, for input of `total` frames at `rate`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `start` is at or past the end of non-empty input.
+    fn window(&self, rate: u32, total: u64) -> Result<(u64, u64)> {
+        // ---
+        let start = (self.start.as_secs_f64() * rate as f64).round() as u64;
+        if start > 0 && start >= total {
+            anyhow::bail!(
+                "start offset {:.2}s is past the end of the input ({:.2}s)",
+                self.start.as_secs_f64(),
+                total as f64 / rate as f64
+            );
+        }
+        let available = total.saturating_sub(start);
+        let len = match self.duration {
+            Some(duration) => {
+                ((duration.as_secs_f64() * rate as f64).round() as u64).min(available)
+            }
+            None => available,
+        };
+        Ok((start, len))
     }
-
-    mono
 }
 
-/// Resamples audio using linear interpolation.
+/// Seeks `reader` to the start of the window `options` selects, returning
+/// how many interleaved samples the window holds.
 ///
-/// This is a simple resampling algorithm suitable for voice.
-/// For high-quality music, consider using a proper resampling library.
-fn resample_linear(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+/// # Errors
+///
+/// Returns error if the window starts past the end of the file or the
+/// seek fails.
+fn seek_window<R: Read + Seek>(
+    reader: &mut WavReader<R>,
+    options: &AudioReadOptions,
+) -> Result<u64> {
     // ---
-    if from_rate == to_rate {
-        return samples.to_vec();
-    }
+    let spec = reader.spec();
+    let (start, len) = options.window(spec.sample_rate, reader.duration() as u64)?;
+    reader
+        .seek(start as u32)
+        .context("failed to seek in WAV file")?;
+    Ok(len * spec.channels as u64)
+}
 
-    let ratio = from_rate as f64 / to_rate as f64;
-    let new_len = (samples.len() as f64 / ratio) as usize;
-    let mut resampled = Vec::with_capacity(new_len);
+/// Converts float samples to 16-bit: scaled, dithered if enabled, rounded
+/// to nearest, and clamped at full scale, counting the samples clamped.
+#[derive(Debug, Clone, Copy)]
+struct FloatConverter {
+    // ---
+    /// Add TPDF dither before rounding
+    dither: bool,
 
-    for i in 0..new_len {
-        let src_pos = i as f64 * ratio;
-        let src_idx = src_pos as usize;
+    /// splitmix64 state of the dither
+    state: u64,
 
-        if src_idx >= samples.len() - 1 {
-            // Near end, just copy last sample
-            resampled.push(samples[samples.len() - 1]);
-        } else {
-            // Linear interpolation between adjacent samples
-            let frac = src_pos - src_idx as f64;
-            let s0 = samples[src_idx] as f64;
-            let s1 = samples[src_idx + 1] as f64;
-            let interpolated = s0 + (s1 - s0) * frac;
-            resampled.push(interpolated as i16);
-        }
-    }
+    /// Samples beyond +/-1.0
+    clipped: u64,
 
-    resampled
+    /// Largest magnitude among them
+    peak: f32,
 }
 
-#[cfg(test)]
-mod tests {
+impl FloatConverter {
     // ---
-    use super::*;
-
-    #[test]
-    fn test_convert_to_mono_stereo() {
+    /// Starts a conversion as set in `options`.
+    fn new(options: &AudioReadOptions) -> Self {
         // ---
-        // Stereo: [L1, R1, L2, R2, L3, R3]
-        let stereo = vec![100, 200, 300, 400, 500, 600];
-        let mono = convert_to_mono(&stereo, 2);
+        Self {
+            dither: options.dither,
+            state: DITHER_SEED,
+            clipped: 0,
+            peak: 0.0,
+        }
+    }
 
-        // Should average each pair
-        assert_eq!(mono, vec![150, 350, 550]);
+    /// Restarts the dither sequence, so converting the same samples again
+    /// gives the same result.
+    fn restart(&mut self) {
+        // ---
+        self.state = DITHER_SEED;
     }
 
-    #[test]
-    fn test_convert_to_mono_quad() {
+    /// Converts `sample` to 16-bit, counting it if it had to be clamped.
+    fn convert(&mut self, sample: f32) -> i16 {
         // ---
-        let quad = vec![100, 200, 300, 400]; // One quad frame
-        let mono = convert_to_mono(&quad, 4);
+        let magnitude = sample.abs();
+        if magnitude > 1.0 {
+            self.clipped += 1;
+            self.peak = self.peak.max(magnitude);
+            return if sample > 0.0 { i16::MAX } else { -i16::MAX };
+        }
 
-        assert_eq!(mono.len(), 1);
-        assert_eq!(mono[0], 250); // Average of 4 channels
+        let mut scaled = sample as f64 * i16::MAX as f64;
+        if self.dither {
+            // Triangular over +/-1 LSB: the sum of two uniform values
+            scaled += self.uniform() + self.uniform();
+        }
+        scaled.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16
     }
 
-    #[test]
-    fn test_resample_linear_upsample() {
+    /// Returns the next dither value, uniform in -0.5..0.5.
+    fn uniform(&mut self) -> f64 {
         // ---
-        let samples = vec![0, 1000, 2000];
-        let resampled = resample_linear(&samples, 8000, 16000);
+        (splitmix64(&mut self.state) >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+    }
 
-        // Should approximately double the sample count
-        assert!(resampled.len() >= 5 && resampled.len() <= 7);
+    /// Returns the number of samples clamped so far.
+    fn clipped(&self) -> u64 {
+        // ---
+        self.clipped
     }
 
-    #[test]
-    fn test_resample_linear_downsample() {
+    /// Returns how far the largest clamped sample was past full scale, in
+    /// dB (0 when none was).
+    fn overshoot_db(&self) -> f64 {
         // ---
-        let samples = vec![0, 500, 1000, 1500, 2000];
+        20.0 * (self.peak.max(1.0) as f64).log10()
+    }
+
+    /// Warns if any of `total` samples read were clamped.
+    fn warn(&self, total: u64) {
+        // ---
+        if self.clipped > 0 {
+            warn!(
+                "Float input clipped {} of {} samples at full scale ({:.2}%, peaks {:.1} dB over)",
+                self.clipped,
+                total,
+                self.clipped as f64 / total.max(1) as f64 * 100.0,
+                self.overshoot_db()
+            );
+        }
+    }
+}
+
+/// Appends up to `max` interleaved samples from a reader opened with
+/// [`open_wav`] to `out`, returning how many were read (0 at the end).
+/// Float samples are converted by `float`.
+fn read_wav_chunk<R: Read>(
+    reader: &mut WavReader<R>,
+    max: usize,
+    out: &mut Vec<i16>,
+    float: &mut FloatConverter,
+) -> Result<usize> {
+    // ---
+    let start = out.len();
+    let spec = reader.spec();
+    match spec.sample_format {
+        // --- Native path
+        hound::SampleFormat::Int if spec.bits_per_sample == 16 => {
+            for sample in reader.samples::<i16>().take(max) {
+                out.push(sample.context("failed to read 16-bit PCM WAV samples")?);
+            }
+        }
+
+        // --- 8-bit path: unsigned on disk, offset to signed by hound
+        hound::SampleFormat::Int if spec.bits_per_sample == 8 => {
+            for sample in reader.samples::<i8>().take(max) {
+                let sample = sample.context("failed to read 8-bit PCM WAV samples")?;
+                out.push((sample as i16) << 8);
+            }
+        }
+
+        // --- 24-bit path: drop the low byte, rounding to nearest
+        hound::SampleFormat::Int => {
+            for sample in reader.samples::<i32>().take(max) {
+                let sample = sample.context("failed to read 24-bit PCM WAV samples")?;
+                out.push(((sample + 0x80) >> 8).min(i16::MAX as i32) as i16);
+            }
+        }
+
+        // --- Float path
+        hound::SampleFormat::Float => {
+            for sample in reader.samples::<f32>().take(max) {
+                let sample = sample.context("failed to read 32-bit float WAV samples")?;
+                out.push(float.convert(sample));
+            }
+        }
+    }
+    Ok(out.len() - start)
+}
+
+/// Analyzes the window `read` selects of a WAV file for silence like
+/// [`read_wav_with_silence`], reading it in chunks instead of loading it.
+///
+/// # Errors
+///
+/// Returns the same errors as [`read_wav_with_options`].
+pub fn analyze_wav_silence<P: AsRef<Path>>(
+    path: P,
+    config: &SilenceConfig,
+    read: &AudioReadOptions,
+) -> Result<SilenceReport> {
+    // ---
+    let mut reader = open_wav(path.as_ref())?;
+    let spec = reader.spec();
+    let mut analyzer = SilenceAnalyzer::new(spec.channels as usize, spec.sample_rate, config);
+
+    let mut left = seek_window(&mut reader, read)? as usize;
+    let chunk_len = WAV_CHUNK_FRAMES * spec.channels as usize;
+    let mut chunk = Vec::with_capacity(chunk_len);
+    let mut float = FloatConverter::new(read);
+    while left > 0 && read_wav_chunk(&mut reader, chunk_len.min(left), &mut chunk, &mut float)? > 0
+    {
+        left -= chunk.len();
+        analyzer.push(&chunk);
+        chunk.clear();
+    }
+    Ok(analyzer.finish())
+}
+
+/// Sample frames (one sample per channel) read from a WAV file at a time
+/// when streaming it
+const WAV_CHUNK_FRAMES: usize = 4096;
+
+/// Frames read from a WAV file as they are streamed, instead of loading the
+/// whole file with [`read_wav`].
+///
+/// Each chunk is downmixed and resampled to 16kHz mono as it is read, so
+/// memory use stays bounded whatever the file length. The frames are the
+/// ones [`read_wav`] followed by [`PcmFrames`](crate::source::PcmFrames)
+/// produce: complete frames only unless [`with_tail`](Self::with_tail)
+/// pads the last one, and when looping the file is rewound after it. Reads are small and buffered, so they run
+/// on the streaming task.
+pub struct WavFrameReader {
+    // ---
+    /// Open file, positioned after the samples read so far
+    reader: WavReader<BufReader<File>>,
+
+    /// Rewind after the last complete frame
+    looping: bool,
+
+    /// Sample rate conversion used for the file
+    quality: ResampleQuality,
+
+    /// Channels that make up the mono signal
+    channel_map: ChannelMap,
+
+    /// Converts the downmixed input to 16kHz
+    resampler: Resampler,
+
+    /// Applied to converted samples
+    gain: Gain,
+
+    /// Converts float samples, counting those clamped
+    float: FloatConverter,
+
+    /// Interleaved samples of the chunk being converted
+    chunk: Vec<i16>,
+
+    /// Converted samples not yet handed out
+    pending: Vec<i16>,
+
+    /// Media position of the next frame in `pending`
+    next_index: u64,
+
+    /// Frames handed out since the last rewind
+    pass_frames: u64,
+
+    /// First sample frame of the window that is streamed
+    start_frame: u64,
+
+    /// Interleaved samples in the window
+    window: u64,
+
+    /// Interleaved samples of the window not yet read in this pass
+    left: u64,
+
+    /// Passes read before the end when looping (0 = no end)
+    loop_count: u64,
+
+    /// Samples faded in and out at each end of a pass when looping
+    fade: usize,
+
+    /// Whether a trailing partial frame is sent
+    tail: TailPolicy,
+
+    /// Samples of silence served between passes when looping
+    gap: usize,
+
+    /// Frames of silence still to serve before the next pass
+    gap_frames: u64,
+
+    /// The frame last served was part of the gap
+    in_gap: bool,
+
+    /// Passes read to the end so far
+    passes: u64,
+
+    /// Trailing samples left out of the passes so far
+    discarded: u64,
+
+    /// The file has been read to the end
+    eof: bool,
+}
+
+impl WavFrameReader {
+    // ---
+    /// Opens `path` for streaming, once or `looping` forever.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be opened or its sample format is
+    /// unsupported, as for [`read_wav`].
+    pub fn open<P: AsRef<Path>>(path: P, looping: bool) -> Result<Self> {
+        // ---
+        info!("Streaming WAV file: {}", path.as_ref().display());
+        let reader = open_wav(path.as_ref())?;
+        let spec = reader.spec();
+        let len = reader.len() as u64;
+
+        Ok(Self {
+            reader,
+            looping,
+            quality: ResampleQuality::default(),
+            channel_map: ChannelMap::default(),
+            resampler: Resampler::new(spec.sample_rate, SAMPLE_RATE, ResampleQuality::default()),
+            gain: Gain::new(0.0),
+            float: FloatConverter::new(&AudioReadOptions::default()),
+            chunk: Vec::with_capacity(WAV_CHUNK_FRAMES * spec.channels as usize),
+            pending: Vec::new(),
+            next_index: 0,
+            pass_frames: 0,
+            start_frame: 0,
+            window: len,
+            left: len,
+            loop_count: 0,
+            fade: 0,
+            tail: TailPolicy::Discard,
+            gap: 0,
+            gap_frames: 0,
+            in_gap: false,
+            passes: 0,
+            discarded: 0,
+            eof: false,
+        })
+    }
+
+    /// Opens `path` for streaming, read as set in `read` and converted as
+    /// set in `convert`.
+    ///
+    /// With `convert.normalize` set the window is read through once first
+    /// to measure its converted level.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`open`](Self::open),
+    /// [`with_read_options`](Self::with_read_options), and
+    /// [`with_channel_map`](Self::with_channel_map), and an error if
+    /// `convert.trim` is set: trimming needs the whole file, so read it
+    /// with [`read_audio_with_options`] instead.
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        looping: bool,
+        read: &AudioReadOptions,
+        convert: &ConvertOptions,
+    ) -> Result<Self> {
+        // ---
+        let path = path.as_ref();
+        if convert.trim.is_some() {
+            anyhow::bail!("silence trimming needs the whole file, it cannot be streamed from disk");
+        }
+        let mut gain_db = convert.gain_db;
+        if let Some(normalize) = &convert.normalize {
+            let level = Self::open(path, false)?
+                .with_read_options(read)?
+                .with_quality(convert.quality)
+                .with_channel_map(convert.channel_map)?
+                .measure()?;
+            gain_db += log_normalize(normalize, &level);
+        }
+
+        Ok(Self::open(path, looping)?
+            .with_read_options(read)?
+            .with_quality(convert.quality)
+            .with_channel_map(convert.channel_map)?
+            .with_gain_db(gain_db))
+    }
+
+    /// Reads the whole window and returns the level of its converted
+    /// samples.
+    fn measure(mut self) -> Result<LevelMeter> {
+        // ---
+        let mut level = LevelMeter::default();
+        while self.fill(1)? {
+            level.push(&self.pending);
+            self.pending.clear();
+        }
+        Ok(level)
+    }
+
+    /// Converts the file's sample rate with `quality` instead of the
+    /// default [`ResampleQuality::High`].
+    pub fn with_quality(mut self, quality: ResampleQuality) -> Self {
+        // ---
+        self.quality = quality;
+        self.resampler = Resampler::new(self.reader.spec().sample_rate, SAMPLE_RATE, quality);
+        self
+    }
+
+    /// Keeps the channels selected by `channel_map` instead of mixing all
+    /// of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file does not have the selected channel.
+    pub fn with_channel_map(mut self, channel_map: ChannelMap) -> Result<Self> {
+        // ---
+        channel_map.check(self.reader.spec().channels)?;
+        self.channel_map = channel_map;
+        Ok(self)
+    }
+
+    /// Applies `gain_db` to the converted samples, clipping at full scale.
+    pub fn with_gain_db(mut self, gain_db: f64) -> Self {
+        // ---
+        self.gain = Gain::new(gain_db);
+        self
+    }
+
+    /// Returns the number of samples the gain has clipped so far.
+    pub fn clipped_samples(&self) -> u64 {
+        // ---
+        self.gain.clipped()
+    }
+
+    /// Reads the file's samples as set in `options` instead of the
+    /// defaults, streaming only the window it selects; looping rewinds to
+    /// the start of the window.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the window starts past the end of the file or the
+    /// seek fails.
+    pub fn with_read_options(mut self, options: &AudioReadOptions) -> Result<Self> {
+        // ---
+        let spec = self.reader.spec();
+        let (start, len) = options.window(spec.sample_rate, self.reader.duration() as u64)?;
+        self.reader
+            .seek(start as u32)
+            .context("failed to seek in WAV file")?;
+        self.start_frame = start;
+        self.window = len * spec.channels as u64;
+        self.left = self.window;
+        self.float = FloatConverter {
+            dither: options.dither,
+            ..self.float
+        };
+        Ok(self)
+    }
+
+    /// Returns the number of float samples beyond full scale clamped so
+    /// far, counting every pass when looping. The first pass logs a
+    /// warning if there were any.
+    pub fn input_clipped_samples(&self) -> u64 {
+        // ---
+        self.float.clipped()
+    }
+
+    /// When looping, ends after `count` passes, as
+    /// [`PcmFrames::with_loop_count`](crate::source::PcmFrames::with_loop_count)
+    /// does.
+    pub fn with_loop_count(mut self, count: u64) -> Self {
+        // ---
+        self.loop_count = count;
+        self
+    }
+
+    /// When looping, fades the first and last `fade` of every pass so the
+    /// wrap-around does not click, as
+    /// [`PcmFrames::with_loop_fade`](crate::source::PcmFrames::with_loop_fade)
+    /// does.
+    pub fn with_loop_fade(mut self, fade: Duration) -> Self {
+        // ---
+        self.fade = duration_samples(fade);
+        self
+    }
+
+    /// Handles a trailing partial frame as `tail` says, as
+    /// [`PcmFrames::with_tail`](crate::source::PcmFrames::with_tail) does.
+    pub fn with_tail(mut self, tail: TailPolicy) -> Self {
+        // ---
+        self.tail = tail;
+        self
+    }
+
+    /// When looping, serves `gap` of silence between passes, as
+    /// [`PcmFrames::with_loop_gap`](crate::source::PcmFrames::with_loop_gap)
+    /// does.
+    pub fn with_loop_gap(mut self, gap: Duration) -> Self {
+        // ---
+        self.gap = duration_samples(gap);
+        self
+    }
+
+    /// Returns the format of the file.
+    pub fn spec(&self) -> WavSpec {
+        // ---
+        self.reader.spec()
+    }
+
+    /// Returns the duration of the streamed window (by default the whole
+    /// file) in seconds.
+    pub fn duration_secs(&self) -> f64 {
+        // ---
+        let spec = self.reader.spec();
+        self.window as f64 / spec.channels as f64 / spec.sample_rate as f64
+    }
+
+    /// Reads until `pending` holds at least `len` samples; returns false if
+    /// the file ended first.
+    fn fill(&mut self, len: usize) -> Result<bool> {
+        // ---
+        let channels = self.reader.spec().channels as usize;
+        while self.pending.len() < len {
+            if self.eof {
+                return Ok(false);
+            }
+            self.chunk.clear();
+            let read = read_wav_chunk(
+                &mut self.reader,
+                (WAV_CHUNK_FRAMES * channels).min(self.left as usize),
+                &mut self.chunk,
+                &mut self.float,
+            )?;
+            self.left -= read as u64;
+            let converted = self.pending.len();
+            if read == 0 {
+                self.resampler.finish(&mut self.pending);
+                self.eof = true;
+            } else if channels > 1 {
+                let mono = self.channel_map.apply(&self.chunk, channels);
+                self.resampler.process(&mono, &mut self.pending);
+            } else {
+                self.resampler.process(&self.chunk, &mut self.pending);
+            }
+            self.gain.apply(&mut self.pending[converted..]);
+        }
+        Ok(true)
+    }
+
+    /// Starts the window over from its first sample.
+    fn rewind(&mut self) -> Result<()> {
+        // ---
+        self.reader
+            .seek(self.start_frame as u32)
+            .context("failed to rewind WAV file")?;
+        self.left = self.window;
+        self.resampler = Resampler::new(self.reader.spec().sample_rate, SAMPLE_RATE, self.quality);
+        self.float.restart();
+        self.pending.clear();
+        self.pass_frames = 0;
+        self.eof = false;
+        Ok(())
+    }
+
+    /// Pads a partial frame left at EOF with silence if the tail policy
+    /// says so; returns false if there is nothing to pad.
+    fn pad_tail(&mut self, len: usize) -> bool {
+        // ---
+        if self.tail != TailPolicy::PadWithSilence || self.pending.is_empty() {
+            return false;
+        }
+        if self.next_index == self.pass_frames {
+            info!(
+                "Padding {} trailing samples with silence to a full frame",
+                self.pending.len()
+            );
+        }
+        self.pending.resize(len, 0);
+        true
+    }
+
+    /// Moves the next frame into `frame`, rewinding first if the file is
+    /// done and looping; returns false once there is none.
+    fn next_frame(&mut self, frame: &mut [i16]) -> Result<bool> {
+        // ---
+        self.in_gap = self.gap_frames > 0;
+        if self.in_gap {
+            frame.fill(0);
+            self.gap_frames -= 1;
+            self.next_index += 1;
+            return Ok(true);
+        }
+
+        if !self.fill(frame.len())? && !self.pad_tail(frame.len()) {
+            // No rewind yet while every frame handed out is in this pass,
+            // so these are logged once rather than on every pass
+            if self.next_index == self.pass_frames {
+                self.float.warn(self.window);
+                if !self.pending.is_empty() {
+                    warn!(
+                        "Discarding {} trailing samples at EOF (not enough for a full frame)",
+                        self.pending.len()
+                    );
+                }
+            }
+            // A file without one frame to send would rewind forever
+            if self.pass_frames == 0 {
+                return Ok(false);
+            }
+            self.passes += 1;
+            self.discarded += self.pending.len() as u64;
+            if !self.looping || self.passes == self.loop_count {
+                // Counted once however often the end is asked for
+                self.pass_frames = 0;
+                self.pending.clear();
+                return Ok(false);
+            }
+            self.rewind()?;
+            if self.gap > 0 {
+                // The next pass starts once the gap is out
+                self.gap_frames = self.gap.div_ceil(frame.len()) as u64;
+                return self.next_frame(frame);
+            }
+            if !self.fill(frame.len())? && !self.pad_tail(frame.len()) {
+                return Ok(false);
+            }
+        }
+
+        let fading = self.looping && self.fade > 0;
+        if fading {
+            // Read far enough ahead to know whether the pass ends within
+            // the fade, allowing for a partial frame at the end; at EOF
+            // everything left is pending
+            self.fill(2 * frame.len() + self.fade)?;
+        }
+
+        frame.copy_from_slice(&self.pending[..frame.len()]);
+        if fading {
+            let start = self.pass_frames as usize * frame.len();
+            let remaining = self.eof.then(|| match self.tail {
+                TailPolicy::Discard => self.pending.len() - self.pending.len() % frame.len(),
+                TailPolicy::PadWithSilence => {
+                    self.pending.len().div_ceil(frame.len()) * frame.len()
+                }
+            });
+            apply_loop_fade(frame, start, remaining, self.fade);
+        }
+        self.pending.drain(..frame.len());
+        self.pass_frames += 1;
+        self.next_index += 1;
+        Ok(true)
+    }
+}
+
+impl fmt::Debug for WavFrameReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.debug_struct("WavFrameReader")
+            .field("spec", &self.reader.spec())
+            .field("looping", &self.looping)
+            .field("quality", &self.quality)
+            .field("channel_map", &self.channel_map)
+            .field("next_index", &self.next_index)
+            .field("eof", &self.eof)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FrameSource for WavFrameReader {
+    async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+        // ---
+        // Frames the pacer skipped are read and discarded
+        while self.next_index < index {
+            if !self.next_frame(frame)? {
+                return Ok(false);
+            }
+        }
+        self.next_frame(frame)
+    }
+
+    fn in_gap(&self) -> bool {
+        // ---
+        self.in_gap
+    }
+
+    fn passes_completed(&self) -> u64 {
+        // ---
+        self.passes
+    }
+
+    fn discarded_samples(&self) -> u64 {
+        // ---
+        self.discarded
+    }
+}
+
+/// Converts raw interleaved samples into streamable [`AudioData`].
+fn to_audio_data(raw: &RawAudio) -> Result<AudioData> {
+    // ---
+    // Convert to target format (16kHz mono)
+    let (samples, gain_clipped) =
+        convert_to_target_format(&raw.samples, &raw.spec, &ConvertOptions::default())?;
+
+    Ok(AudioData {
+        samples,
+        original_sample_rate: raw.spec.sample_rate,
+        original_channels: raw.spec.channels,
+        clipped_samples: raw.clipped + gain_clipped,
+    })
+}
+
+/// Converts audio samples to target format (16kHz mono).
+///
+/// Handles resampling and channel conversion. Resampling runs here, when
+/// the file is loaded, so its cost is paid once before streaming starts.
+/// Returns the converted samples and how many of them the gain clipped.
+///
+/// # Errors
+///
+/// Returns error if `convert.channel_map` names a channel beyond
+/// `spec.channels`.
+fn convert_to_target_format(
+    samples: &[i16],
+    spec: &WavSpec,
+    convert: &ConvertOptions,
+) -> Result<(Vec<i16>, u64)> {
+    // ---
+    let quality = convert.quality;
+    convert.channel_map.check(spec.channels)?;
+    let mut mono_samples = if spec.channels > 1 {
+        info!(
+            "Converting {} channels to mono ({})",
+            spec.channels, convert.channel_map
+        );
+        convert.channel_map.apply(samples, spec.channels as usize)
+    } else {
+        samples.to_vec()
+    };
+
+    // Resample if needed
+    if spec.sample_rate != SAMPLE_RATE {
+        info!(
+            "Resampling from {}Hz to {}Hz ({} quality)",
+            spec.sample_rate, SAMPLE_RATE, quality
+        );
+        mono_samples = resample(&mono_samples, spec.sample_rate, SAMPLE_RATE, quality);
+    }
+
+    if let Some(trim) = &convert.trim {
+        let keep = trim.keep_range(&mono_samples);
+        let trailing = mono_samples.len() - keep.end;
+        if keep.start > 0 || trailing > 0 {
+            info!(
+                "Trimmed {:.2}s of leading and {:.2}s of trailing silence",
+                keep.start as f64 / SAMPLE_RATE as f64,
+                trailing as f64 / SAMPLE_RATE as f64
+            );
+        }
+        mono_samples.truncate(keep.end);
+        mono_samples.drain(..keep.start);
+    }
+
+    let mut gain_db = convert.gain_db;
+    if let Some(normalize) = &convert.normalize {
+        let mut level = LevelMeter::default();
+        level.push(&mono_samples);
+        gain_db += log_normalize(normalize, &level);
+    }
+
+    let mut gain = Gain::new(gain_db);
+    if gain_db != 0.0 {
+        info!("Applying {:+.1} dB gain", gain_db);
+        gain.apply(&mut mono_samples);
+        if gain.clipped() > 0 {
+            warn!(
+                "Gain of {:+.1} dB clipped {} of {} samples ({:.2}%)",
+                gain_db,
+                gain.clipped(),
+                mono_samples.len(),
+                gain.clipped() as f64 / mono_samples.len() as f64 * 100.0
+            );
+        }
+    }
+
+    info!(
+        "Converted to target format: {} samples ({} frames)",
+        mono_samples.len(),
+        mono_samples.len().div_ceil(SAMPLES_PER_FRAME)
+    );
+
+    Ok((mono_samples, gain.clipped()))
+}
+
+/// Returns the gain `normalize` calls for at `level`, logging it.
+fn log_normalize(normalize: &NormalizeConfig, level: &LevelMeter) -> f64 {
+    // ---
+    let gain_db = normalize.gain_db(level);
+    info!(
+        "Normalizing {} level {:.1} dBFS to {:.1} dBFS ({:+.1} dB)",
+        normalize.mode,
+        level.level_dbfs(normalize.mode),
+        normalize.target_dbfs,
+        gain_db
+    );
+    gain_db
+}
+
+/// Which input channels make up the mono signal that is encoded.
+///
+/// Parsed from `mix`, `left`, `right`, or `channel:N` (zero-based, so
+/// `channel:0` is `left`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChannelMap {
+    // ---
+    /// Average of all channels
+    #[default]
+    Mix,
+
+    /// First channel only
+    Left,
+
+    /// Second channel only
+    Right,
+
+    /// This channel only (zero-based)
+    Channel(u16),
+}
+
+impl ChannelMap {
+    // ---
+    /// Returns the index of the single channel kept, or None for
+    /// [`ChannelMap::Mix`].
+    pub fn index(self) -> Option<u16> {
+        // ---
+        match self {
+            ChannelMap::Mix => None,
+            ChannelMap::Left => Some(0),
+            ChannelMap::Right => Some(1),
+            ChannelMap::Channel(index) => Some(index),
+        }
+    }
+
+    /// Checks the selected channel exists in input with `channels`
+    /// interleaved channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns error naming the channel and the input's channel count if it
+    /// does not.
+    pub fn check(self, channels: u16) -> Result<()> {
+        // ---
+        match self.index() {
+            Some(index) if index >= channels => anyhow::bail!(
+                "channel {} ({}) requested, but the input has {} channel{} (0 to {})",
+                index,
+                self,
+                channels,
+                if channels == 1 { "" } else { "s" },
+                channels.saturating_sub(1)
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reduces interleaved samples with `channels` channels to mono.
+    ///
+    /// The selected channel must exist (see [`check`](Self::check)).
+    pub(crate) fn apply(self, samples: &[i16], channels: usize) -> Vec<i16> {
+        // ---
+        match self.index() {
+            None => convert_to_mono(samples, channels),
+            Some(index) => samples
+                .iter()
+                .skip(index as usize)
+                .step_by(channels)
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for ChannelMap {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        // ---
+        match spec {
+            "mix" => Ok(ChannelMap::Mix),
+            "left" => Ok(ChannelMap::Left),
+            "right" => Ok(ChannelMap::Right),
+            _ => {
+                let index = spec
+                    .strip_prefix("channel:")
+                    .and_then(|index| index.parse().ok())
+                    .with_context(|| {
+                        format!(
+                            "invalid channel selection '{spec}' (expected mix, left, right, or channel:N)"
+                        )
+                    })?;
+                Ok(ChannelMap::Channel(index))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ChannelMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        match self {
+            ChannelMap::Mix => f.write_str("mix"),
+            ChannelMap::Left => f.write_str("left"),
+            ChannelMap::Right => f.write_str("right"),
+            ChannelMap::Channel(index) => write!(f, "channel:{index}"),
+        }
+    }
+}
+
+/// How input audio is converted to the 16kHz mono that is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ConvertOptions {
+    // ---
+    /// Channels that make up the mono signal
+    pub channel_map: ChannelMap,
+
+    /// Sample rate conversion used when the input is not 16kHz
+    pub quality: ResampleQuality,
+
+    /// Gain applied after conversion, in dB; samples pushed past full
+    /// scale clip
+    pub gain_db: f64,
+
+    /// Measure the converted input and add the gain that brings it to a
+    /// target level
+    pub normalize: Option<NormalizeConfig>,
+
+    /// Trim silence from both ends of the converted input, before gain
+    /// and normalization; needs the whole input, so file input only
+    pub trim: Option<TrimConfig>,
+}
+
+/// Gain in dB applied to converted samples, counting the samples it
+/// clips.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Gain {
+    // ---
+    /// Linear factor
+    factor: f64,
+
+    /// Samples clamped to the i16 range so far
+    clipped: u64,
+}
+
+impl Gain {
+    // ---
+    /// Creates a gain of `db` decibels.
+    pub(crate) fn new(db: f64) -> Self {
+        // ---
+        Self {
+            factor: 10f64.powf(db / 20.0),
+            clipped: 0,
+        }
+    }
+
+    /// Scales `samples` in place, saturating at the i16 limits.
+    pub(crate) fn apply(&mut self, samples: &mut [i16]) {
+        // ---
+        if self.factor == 1.0 {
+            return;
+        }
+        for sample in samples {
+            let scaled = (*sample as f64 * self.factor).round();
+            if scaled > i16::MAX as f64 || scaled < i16::MIN as f64 {
+                self.clipped += 1;
+            }
+            *sample = scaled.clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        }
+    }
+
+    /// Returns the number of samples clipped so far.
+    pub(crate) fn clipped(&self) -> u64 {
+        // ---
+        self.clipped
+    }
+}
+
+/// Most gain normalization or AGC applies, so near-silent input is not
+/// raised to full scale
+pub const MAX_NORMALIZE_GAIN_DB: f64 = 30.0;
+
+/// Level measured to normalize audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalizeMode {
+    // ---
+    /// Highest absolute sample
+    #[default]
+    Peak,
+
+    /// Root mean square over the input (or, for AGC, over each frame)
+    Rms,
+}
+
+impl NormalizeMode {
+    // ---
+    /// Every mode
+    pub const ALL: [NormalizeMode; 2] = [NormalizeMode::Peak, NormalizeMode::Rms];
+
+    /// Returns the label used in logs.
+    pub fn as_str(self) -> &'static str {
+        // ---
+        match self {
+            NormalizeMode::Peak => "peak",
+            NormalizeMode::Rms => "RMS",
+        }
+    }
+}
+
+impl fmt::Display for NormalizeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// Peak and RMS level of the samples pushed so far, in dBFS.
+#[derive(Debug, Clone, Default)]
+pub struct LevelMeter {
+    // ---
+    /// Highest absolute sample
+    peak: u32,
+
+    /// Summed squared samples
+    energy: f64,
+
+    /// Samples pushed
+    count: u64,
+}
+
+impl LevelMeter {
+    // ---
+    /// Adds `samples` to the measurement.
+    pub fn push(&mut self, samples: &[i16]) {
+        // ---
+        for &sample in samples {
+            self.peak = self.peak.max(sample.unsigned_abs() as u32);
+            self.energy += (sample as f64) * (sample as f64);
+        }
+        self.count += samples.len() as u64;
+    }
+
+    /// Returns the peak level in dBFS (negative infinity for silence).
+    pub fn peak_dbfs(&self) -> f64 {
+        // ---
+        20.0 * (self.peak as f64 / i16::MAX as f64).log10()
+    }
+
+    /// Returns the RMS level in dBFS (negative infinity for silence).
+    pub fn rms_dbfs(&self) -> f64 {
+        // ---
+        let rms = (self.energy / self.count.max(1) as f64).sqrt();
+        20.0 * (rms / i16::MAX as f64).log10()
+    }
+
+    /// Returns the level `mode` measures, in dBFS.
+    pub fn level_dbfs(&self, mode: NormalizeMode) -> f64 {
+        // ---
+        match mode {
+            NormalizeMode::Peak => self.peak_dbfs(),
+            NormalizeMode::Rms => self.rms_dbfs(),
+        }
+    }
+}
+
+/// Brings file input to a fixed level before streaming.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizeConfig {
+    // ---
+    /// Level that is measured and set
+    pub mode: NormalizeMode,
+
+    /// Level the input is brought to, in dBFS
+    pub target_dbfs: f64,
+}
+
+impl Default for NormalizeConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            mode: NormalizeMode::Peak,
+            target_dbfs: -3.0,
+        }
+    }
+}
+
+impl NormalizeConfig {
+    // ---
+    /// Returns the gain in dB that brings input measured by `level` to the
+    /// target, at most [`MAX_NORMALIZE_GAIN_DB`], and 0 for silent input.
+    pub fn gain_db(&self, level: &LevelMeter) -> f64 {
+        // ---
+        let measured = level.level_dbfs(self.mode);
+        if !measured.is_finite() {
+            return 0.0;
+        }
+        (self.target_dbfs - measured).min(MAX_NORMALIZE_GAIN_DB)
+    }
+}
+
+/// Level below which samples count as silence for [`TrimConfig`]
+pub const DEFAULT_TRIM_THRESHOLD_DBFS: f64 = -50.0;
+
+/// Shortest silence at either end that [`TrimConfig`] trims
+pub const DEFAULT_TRIM_MIN_SILENCE: Duration = Duration::from_millis(200);
+
+/// Trims leading and trailing silence from file input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimConfig {
+    // ---
+    /// Level in dBFS a sample must exceed to count as sound
+    pub threshold_dbfs: f64,
+
+    /// Silence at either end shorter than this is kept, so a brief pause
+    /// before or after speech stays as it is
+    pub min_silence: Duration,
+}
+
+impl Default for TrimConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            threshold_dbfs: DEFAULT_TRIM_THRESHOLD_DBFS,
+            min_silence: DEFAULT_TRIM_MIN_SILENCE,
+        }
+    }
+}
+
+impl TrimConfig {
+    // ---
+    /// Returns the range of `samples` (16kHz) that is kept: from the first
+    /// sample above the threshold to the last, each end trimmed only if
+    /// its silence lasts at least `min_silence`. Pauses in between are
+    /// never touched, and input with no sound is kept whole.
+    pub fn keep_range(&self, samples: &[i16]) -> Range<usize> {
+        // ---
+        let threshold = i16::MAX as f64 * 10f64.powf(self.threshold_dbfs / 20.0);
+        let loud = |sample: &i16| sample.unsigned_abs() as f64 > threshold;
+        let (Some(first), Some(last)) = (
+            samples.iter().position(loud),
+            samples.iter().rposition(loud),
+        ) else {
+            return 0..samples.len();
+        };
+
+        let min_silence = duration_samples(self.min_silence);
+        let start = if first >= min_silence { first } else { 0 };
+        let trailing = samples.len() - last - 1;
+        let end = if trailing >= min_silence {
+            last + 1
+        } else {
+            samples.len()
+        };
+        start..end
+    }
+}
+
+/// Settings for [`Agc`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AgcConfig {
+    // ---
+    /// Level measured on each frame and steered to the target
+    pub mode: NormalizeMode,
+
+    /// Level the output is steered to, in dBFS
+    pub target_dbfs: f64,
+
+    /// Time constant for lowering the gain when the input gets louder
+    pub attack: Duration,
+
+    /// Time constant for raising the gain when the input gets quieter
+    pub release: Duration,
+
+    /// Most gain applied, in dB
+    pub max_gain_db: f64,
+
+    /// Frames with an RMS level below this (dBFS) hold the gain, so
+    /// silence and background noise are not raised
+    pub gate_dbfs: f64,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            mode: NormalizeMode::Peak,
+            target_dbfs: -3.0,
+            attack: Duration::from_millis(5),
+            release: Duration::from_millis(500),
+            max_gain_db: MAX_NORMALIZE_GAIN_DB,
+            gate_dbfs: -50.0,
+        }
+    }
+}
+
+/// Automatic gain control for live input that cannot be scanned ahead.
+///
+/// Once per frame the gain moves toward the one that would bring that
+/// frame to the target, quickly when it must drop (attack) and slowly when
+/// it may rise (release). The change is ramped across the frame, and
+/// output saturates at full scale.
+#[derive(Debug, Clone)]
+pub struct Agc {
+    // ---
+    /// Settings
+    config: AgcConfig,
+
+    /// Linear gain reached at the end of the last frame
+    gain: f64,
+
+    /// Samples clamped to the i16 range so far
+    clipped: u64,
+}
+
+impl Agc {
+    // ---
+    /// Starts at unity gain.
+    pub fn new(config: AgcConfig) -> Self {
+        // ---
+        Self {
+            config,
+            gain: 1.0,
+            clipped: 0,
+        }
+    }
+
+    /// Applies the gain to one 16kHz frame in place, then adapts it.
+    pub fn process(&mut self, frame: &mut [i16]) {
+        // ---
+        if frame.is_empty() {
+            return;
+        }
+        let mut level = LevelMeter::default();
+        level.push(frame);
+
+        let start = self.gain;
+        if level.rms_dbfs() >= self.config.gate_dbfs {
+            let wanted_db = (self.config.target_dbfs - level.level_dbfs(self.config.mode))
+                .min(self.config.max_gain_db);
+            let wanted = 10f64.powf(wanted_db / 20.0);
+            let time_constant = if wanted < self.gain {
+                self.config.attack
+            } else {
+                self.config.release
+            };
+            let frame_secs = frame.len() as f64 / SAMPLE_RATE as f64;
+            let step = 1.0 - (-frame_secs / time_constant.as_secs_f64().max(1e-6)).exp();
+            self.gain += (wanted - self.gain) * step;
+        }
+
+        let ramp = (self.gain - start) / frame.len() as f64;
+        for (i, sample) in frame.iter_mut().enumerate() {
+            let scaled = (*sample as f64 * (start + ramp * (i + 1) as f64)).round();
+            if scaled > i16::MAX as f64 || scaled < i16::MIN as f64 {
+                self.clipped += 1;
+            }
+            *sample = scaled.clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        }
+    }
+
+    /// Returns the current gain in dB.
+    pub fn gain_db(&self) -> f64 {
+        // ---
+        20.0 * self.gain.log10()
+    }
+
+    /// Returns the number of samples clipped so far.
+    pub fn clipped(&self) -> u64 {
+        // ---
+        self.clipped
+    }
+}
+
+/// Converts multi-channel audio to mono by averaging channels.
+pub(crate) fn convert_to_mono(samples: &[i16], channels: usize) -> Vec<i16> {
+    // ---
+    let frame_count = samples.len() / channels;
+    let mut mono = Vec::with_capacity(frame_count);
+
+    for frame in samples.chunks(channels) {
+        let sum: i32 = frame.iter().map(|&s| s as i32).sum();
+        let avg = (sum / channels as i32) as i16;
+        mono.push(avg);
+    }
+
+    mono
+}
+
+/// Resamples audio using linear interpolation ([`ResampleQuality::Fast`]).
+///
+/// This is a simple resampling algorithm suitable for voice. Downsampling
+/// music aliases audibly; [`ResampleQuality::High`] filters first.
+fn resample_linear(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    // ---
+    if from_rate == to_rate {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let new_len = (samples.len() as f64 / ratio) as usize;
+    let mut resampled = Vec::with_capacity(new_len);
+
+    for i in 0..new_len {
+        let src_pos = i as f64 * ratio;
+        let src_idx = src_pos as usize;
+
+        if src_idx >= samples.len() - 1 {
+            // Near end, just copy last sample
+            resampled.push(samples[samples.len() - 1]);
+        } else {
+            // Linear interpolation between adjacent samples
+            let frac = src_pos - src_idx as f64;
+            let s0 = samples[src_idx] as f64;
+            let s1 = samples[src_idx + 1] as f64;
+            let interpolated = s0 + (s1 - s0) * frac;
+            resampled.push(interpolated as i16);
+        }
+    }
+
+    resampled
+}
+
+/// Incremental form of [`resample_linear`] for input that arrives in
+/// chunks of any size.
+///
+/// Produces the same samples as resampling the whole input at once; only
+/// the length may differ by a sample at the very end.
+#[derive(Debug)]
+pub(crate) struct LinearResampler {
+    // ---
+    /// Input samples per output sample
+    ratio: f64,
+
+    /// Index of the next output sample
+    next_output: u64,
+
+    /// Input samples dropped from the front of `history`
+    consumed: u64,
+
+    /// Input not yet needed by every output sample
+    history: Vec<i16>,
+}
+
+impl LinearResampler {
+    // ---
+    /// Creates a resampler from `from_rate` to `to_rate`.
+    pub(crate) fn new(from_rate: u32, to_rate: u32) -> Self {
+        // ---
+        Self {
+            ratio: from_rate as f64 / to_rate as f64,
+            next_output: 0,
+            consumed: 0,
+            history: Vec::new(),
+        }
+    }
+
+    /// Appends `input` and pushes every output sample it completes onto
+    /// `output`.
+    pub(crate) fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        // ---
+        if self.ratio == 1.0 {
+            output.extend_from_slice(input);
+            return;
+        }
+
+        self.history.extend_from_slice(input);
+        let available = self.consumed + self.history.len() as u64;
+        loop {
+            let src_pos = self.next_output as f64 * self.ratio;
+            let src_idx = src_pos as u64;
+
+            // Interpolation needs the sample after src_idx
+            if src_idx + 1 >= available {
+                break;
+            }
+            let i = (src_idx - self.consumed) as usize;
+            let frac = src_pos - src_idx as f64;
+            let s0 = self.history[i] as f64;
+            let s1 = self.history[i + 1] as f64;
+            output.push((s0 + (s1 - s0) * frac) as i16);
+            self.next_output += 1;
+        }
+
+        // Keep only input from the next output's position on
+        let needed_from = (self.next_output as f64 * self.ratio) as u64;
+        let drop = (needed_from.saturating_sub(self.consumed) as usize).min(self.history.len());
+        self.history.drain(..drop);
+        self.consumed += drop as u64;
+    }
+}
+
+/// Zero crossings of the sinc kernel on each side of its centre
+const SINC_ZERO_CROSSINGS: f64 = 16.0;
+
+/// Cutoff as a fraction of the lower of the two Nyquist frequencies
+const SINC_ROLLOFF: f64 = 0.9;
+
+/// Most kernel phases precomputed; rate pairs needing more use the nearest
+const SINC_MAX_PHASES: u64 = 1024;
+
+/// How sample rate conversion trades speed for fidelity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleQuality {
+    // ---
+    /// Linear interpolation: cheap, but aliases when downsampling music
+    Fast,
+
+    /// Band-limited polyphase windowed sinc (Blackman window, 16 zero
+    /// crossings per side); content above the output Nyquist frequency is
+    /// filtered out instead of folding back
+    #[default]
+    High,
+}
+
+impl ResampleQuality {
+    // ---
+    /// Every quality, in order of increasing cost
+    pub const ALL: [ResampleQuality; 2] = [ResampleQuality::Fast, ResampleQuality::High];
+
+    /// Returns the lowercase label used in logs.
+    pub fn as_str(self) -> &'static str {
+        // ---
+        match self {
+            ResampleQuality::Fast => "fast",
+            ResampleQuality::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for ResampleQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// Resamples mono samples from `from_rate` to `to_rate`.
+///
+/// Output has `samples.len() * to_rate / from_rate` samples (rounded
+/// down), aligned with the input: neither quality adds delay.
+pub fn resample(
+    samples: &[i16],
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Vec<i16> {
+    // ---
+    if from_rate == to_rate {
+        return samples.to_vec();
+    }
+    match quality {
+        ResampleQuality::Fast => resample_linear(samples, from_rate, to_rate),
+        ResampleQuality::High => {
+            let mut resampler = SincResampler::new(from_rate, to_rate);
+            let mut output = Vec::with_capacity(
+                (samples.len() as u64 * to_rate as u64 / from_rate as u64) as usize,
+            );
+            resampler.process(samples, &mut output);
+            resampler.finish(&mut output);
+            output
+        }
+    }
+}
+
+/// Incremental resampler of either [`ResampleQuality`].
+#[derive(Debug)]
+pub(crate) enum Resampler {
+    // ---
+    /// [`ResampleQuality::Fast`], or rates that already match
+    Linear(LinearResampler),
+
+    /// [`ResampleQuality::High`]
+    Sinc(SincResampler),
+}
+
+impl Resampler {
+    // ---
+    /// Creates a resampler from `from_rate` to `to_rate`.
+    pub(crate) fn new(from_rate: u32, to_rate: u32, quality: ResampleQuality) -> Self {
+        // ---
+        if quality == ResampleQuality::Fast || from_rate == to_rate {
+            Resampler::Linear(LinearResampler::new(from_rate, to_rate))
+        } else {
+            Resampler::Sinc(SincResampler::new(from_rate, to_rate))
+        }
+    }
+
+    /// Appends `input` and pushes every output sample it completes onto
+    /// `output`.
+    pub(crate) fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        // ---
+        match self {
+            Resampler::Linear(linear) => linear.process(input, output),
+            Resampler::Sinc(sinc) => sinc.process(input, output),
+        }
+    }
+
+    /// Pushes the output samples still waiting on input that will never
+    /// come, once the input has ended.
+    pub(crate) fn finish(&mut self, output: &mut Vec<i16>) {
+        // ---
+        if let Resampler::Sinc(sinc) = self {
+            sinc.finish(output);
+        }
+    }
+}
+
+/// Polyphase windowed-sinc resampler for input that arrives in chunks of
+/// any size.
+///
+/// Each output sample is a weighted sum of the input around its position,
+/// with weights from a Blackman-windowed sinc low-pass at
+/// [`SINC_ROLLOFF`] of the lower Nyquist frequency. The weights for every
+/// fractional position (phase) are computed up front, so the per-sample
+/// work is a dot product. Output is identical however the input is
+/// chunked.
+#[derive(Debug)]
+pub(crate) struct SincResampler {
+    // ---
+    /// Output samples per `step_in` input samples, reduced
+    step_out: u64,
+
+    /// Input samples per `step_out` output samples, reduced
+    step_in: u64,
+
+    /// Kernel phases in `coeffs`
+    phases: u64,
+
+    /// Kernel taps per phase; output n uses input from its integer position
+    /// minus `taps / 2 - 1` on
+    taps: usize,
+
+    /// `phases` rows of `taps` weights, each row summing to one
+    coeffs: Vec<f32>,
+
+    /// Index of the next output sample
+    next_output: u64,
+
+    /// Input samples dropped from the front of `history`
+    consumed: u64,
+
+    /// Input still needed by upcoming output samples
+    history: Vec<f32>,
+}
+
+impl SincResampler {
+    // ---
+    /// Creates a resampler from `from_rate` to `to_rate`.
+    pub(crate) fn new(from_rate: u32, to_rate: u32) -> Self {
+        // ---
+        let gcd = gcd(from_rate as u64, to_rate as u64);
+        let (step_in, step_out) = (from_rate as u64 / gcd, to_rate as u64 / gcd);
+        let phases = step_out.min(SINC_MAX_PHASES);
+
+        // Cutoff relative to the input Nyquist frequency, and the kernel
+        // half-width in input samples that it implies
+        let cutoff = SINC_ROLLOFF * (to_rate as f64 / from_rate as f64).min(1.0);
+        let half_width = SINC_ZERO_CROSSINGS / cutoff;
+        let half_taps = half_width.ceil() as usize;
+        let taps = 2 * half_taps;
+
+        let mut coeffs = Vec::with_capacity(phases as usize * taps);
+        for phase in 0..phases {
+            // Tap k reads input (integer position + k + 1 - half_taps)
+            let frac = phase as f64 / phases as f64;
+            let row: Vec<f64> = (0..taps)
+                .map(|k| {
+                    let distance = frac - (k as f64 + 1.0 - half_taps as f64);
+                    let t = distance.abs() / half_width;
+                    if t >= 1.0 {
+                        return 0.0;
+                    }
+                    let window = 0.42
+                        + 0.5 * (std::f64::consts::PI * t).cos()
+                        + 0.08 * (std::f64::consts::TAU * t).cos();
+                    sinc(cutoff * distance) * window
+                })
+                .collect();
+            let sum: f64 = row.iter().sum();
+            coeffs.extend(row.iter().map(|&c| (c / sum) as f32));
+        }
+
+        Self {
+            step_out,
+            step_in,
+            phases,
+            taps,
+            coeffs,
+            next_output: 0,
+            consumed: 0,
+            history: Vec::new(),
+        }
+    }
+
+    /// Returns the integer input position of output `n` and its kernel
+    /// phase.
+    fn position(&self, n: u64) -> (u64, u64) {
+        // ---
+        let pos = n * self.step_in;
+        let (whole, rem) = (pos / self.step_out, pos % self.step_out);
+        let phase = (rem * self.phases + self.step_out / 2) / self.step_out;
+        if phase == self.phases {
+            (whole + 1, 0)
+        } else {
+            (whole, phase)
+        }
+    }
+
+    /// Computes output sample `n`, treating input at and beyond
+    /// `available` as silence.
+    fn output(&self, n: u64, available: u64) -> i16 {
+        // ---
+        let (whole, phase) = self.position(n);
+        let row = &self.coeffs[phase as usize * self.taps..][..self.taps];
+
+        // Input index of tap 0, and the taps that land on input we have
+        let first = whole as i64 + 1 - (self.taps / 2) as i64;
+        let lo = (-first).max(0) as usize;
+        let hi = ((available as i64 - first).max(0) as usize).min(self.taps);
+        if lo >= hi {
+            return 0;
+        }
+        let start = (first + lo as i64) as usize - self.consumed as usize;
+        let sum: f32 = row[lo..hi]
+            .iter()
+            .zip(&self.history[start..])
+            .map(|(c, s)| c * s)
+            .sum();
+        sum.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
+    /// Appends `input` and pushes every output sample it completes onto
+    /// `output`.
+    pub(crate) fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        // ---
+        self.history.extend(input.iter().map(|&s| s as f32));
+        let available = self.consumed + self.history.len() as u64;
+        loop {
+            // The last tap must have arrived
+            let (whole, _) = self.position(self.next_output);
+            if whole + (self.taps / 2) as u64 >= available {
+                break;
+            }
+            output.push(self.output(self.next_output, available));
+            self.next_output += 1;
+        }
+
+        // Keep only input from the next output's first tap on
+        let (whole, _) = self.position(self.next_output);
+        let needed_from = (whole + 1).saturating_sub((self.taps / 2) as u64);
+        let drop = (needed_from.saturating_sub(self.consumed) as usize).min(self.history.len());
+        self.history.drain(..drop);
+        self.consumed += drop as u64;
+    }
+
+    /// Pushes the remaining output samples once the input has ended, up to
+    /// `input length * to_rate / from_rate`.
+    pub(crate) fn finish(&mut self, output: &mut Vec<i16>) {
+        // ---
+        let available = self.consumed + self.history.len() as u64;
+        while (self.next_output + 1) * self.step_in <= available * self.step_out {
+            output.push(self.output(self.next_output, available));
+            self.next_output += 1;
+        }
+    }
+}
+
+/// Normalized sinc, sin(pi x) / (pi x).
+fn sinc(x: f64) -> f64 {
+    // ---
+    if x == 0.0 {
+        1.0
+    } else {
+        let px = std::f64::consts::PI * x;
+        px.sin() / px
+    }
+}
+
+/// Greatest common divisor, for reducing a rate ratio.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    // ---
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Advances splitmix64 `state` and returns the next pseudo-random value.
+fn splitmix64(state: &mut u64) -> u64 {
+    // ---
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Tone frequency used by a bare `tone` spec
+pub const DEFAULT_TONE_HZ: f64 = 440.0;
+
+/// Sweep range used by a bare `sweep` spec
+pub const DEFAULT_SWEEP_HZ: (f64, f64) = (50.0, 7000.0);
+
+/// Length of one pass of a [`SignalGenerator`] unless set otherwise
+pub const DEFAULT_SIGNAL_DURATION: Duration = Duration::from_secs(10);
+
+/// Peak level of a [`SignalGenerator`] unless set otherwise (-6 dBFS)
+pub const DEFAULT_SIGNAL_AMPLITUDE: f64 = 0.5;
+
+/// Seed for the white noise sequence, so every run sends the same noise
+const NOISE_SEED: u64 = 0x5EED_2015;
+
+/// Waveform produced by a [`SignalGenerator`].
+///
+/// Parsed from the `--input` specs `tone`, `tone:<HZ>`, `sweep`,
+/// `sweep:<START>-<END>`, and `noise`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    // ---
+    /// Sine tone at this frequency in Hz
+    Tone(f64),
+
+    /// Logarithmic sweep from `start_hz` to `end_hz` over one pass
+    Sweep { start_hz: f64, end_hz: f64 },
+
+    /// Uniform white noise
+    Noise,
+}
+
+impl Signal {
+    // ---
+    /// Spec names accepted by [`Signal::from_str`](std::str::FromStr)
+    pub const NAMES: [&'static str; 3] = ["tone", "sweep", "noise"];
+
+    /// Returns true if `input` names a signal rather than a file, i.e. it is
+    /// one of [`Signal::NAMES`], optionally followed by `:` and parameters.
+    pub fn is_spec(input: &str) -> bool {
+        // ---
+        let name = input.split_once(':').map_or(input, |(name, _)| name);
+        Self::NAMES.contains(&name)
+    }
+}
+
+/// Parses a frequency for a signal spec, which must lie below Nyquist.
+fn parse_frequency(value: &str, spec: &str) -> Result<f64> {
+    // ---
+    let hz: f64 = value
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid frequency '{value}' in signal '{spec}'"))?;
+    let nyquist = SAMPLE_RATE as f64 / 2.0;
+    if !(hz > 0.0 && hz < nyquist) {
+        anyhow::bail!("frequency {hz} Hz in signal '{spec}' out of range (0 to {nyquist} Hz)");
+    }
+    Ok(hz)
+}
+
+impl FromStr for Signal {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        // ---
+        let (name, params) = match spec.split_once(':') {
+            Some((name, params)) => (name, Some(params)),
+            None => (spec, None),
+        };
+        match (name, params) {
+            ("tone", None) => Ok(Signal::Tone(DEFAULT_TONE_HZ)),
+            ("tone", Some(hz)) => Ok(Signal::Tone(parse_frequency(hz, spec)?)),
+            ("sweep", None) => Ok(Signal::Sweep {
+                start_hz: DEFAULT_SWEEP_HZ.0,
+                end_hz: DEFAULT_SWEEP_HZ.1,
+            }),
+            ("sweep", Some(range)) => {
+                let (start, end) = range.split_once('-').with_context(|| {
+                    format!("invalid signal '{spec}' (expected sweep:<START>-<END>)")
+                })?;
+                let start_hz = parse_frequency(start, spec)?;
+                let end_hz = parse_frequency(end, spec)?;
+                if start_hz == end_hz {
+                    anyhow::bail!("sweep '{spec}' starts and ends at the same frequency");
+                }
+                Ok(Signal::Sweep { start_hz, end_hz })
+            }
+            ("noise", None) => Ok(Signal::Noise),
+            _ => anyhow::bail!(
+                "invalid signal '{spec}' (expected tone[:HZ], sweep[:START-END], or noise)"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        match self {
+            Signal::Tone(hz) => write!(f, "{hz} Hz tone"),
+            Signal::Sweep { start_hz, end_hz } => {
+                write!(f, "{start_hz}-{end_hz} Hz logarithmic sweep")
+            }
+            Signal::Noise => write!(f, "white noise"),
+        }
+    }
+}
+
+/// Test signal generated at the codec sample rate instead of read from a
+/// file.
+///
+/// Serves frames through [`FrameSource`], so it streams like any other
+/// input. Samples are computed from their media position, which keeps the
+/// waveform continuous across frames the pacer skipped. One pass lasts
+/// [`DEFAULT_SIGNAL_DURATION`] unless changed with
+/// [`with_duration`](Self::with_duration); a sweep spans exactly one pass,
+/// and a looping generator never ends.
+///
+/// # Example
+///
+/// ```
+/// use sender::audio::{Signal, SignalGenerator};
+///
+/// let tone = SignalGenerator::new("tone:1000".parse::<Signal>().unwrap());
+/// assert_eq!(tone.duration_secs(), 10.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SignalGenerator {
+    // ---
+    /// Waveform to produce
+    signal: Signal,
+
+    /// Peak level as a fraction of full scale
+    amplitude: f64,
+
+    /// Samples in one pass
+    pass_samples: u64,
+
+    /// Start another pass after the last one
+    looping: bool,
+}
+
+impl SignalGenerator {
+    // ---
+    /// Generates one pass of `signal` at [`DEFAULT_SIGNAL_AMPLITUDE`].
+    pub fn new(signal: Signal) -> Self {
+        // ---
+        Self {
+            signal,
+            amplitude: DEFAULT_SIGNAL_AMPLITUDE,
+            pass_samples: DEFAULT_SIGNAL_DURATION.as_secs() * SAMPLE_RATE as u64,
+            looping: false,
+        }
+    }
+
+    /// Sets the length of one pass (and so of a sweep).
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        // ---
+        self.pass_samples = (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as u64;
+        self
+    }
+
+    /// Sets the peak level as a fraction of full scale, clamped to 0..=1.
+    pub fn with_amplitude(mut self, amplitude: f64) -> Self {
+        // ---
+        self.amplitude = amplitude.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Repeats the pass forever when `looping` is set.
+    pub fn looping(mut self, looping: bool) -> Self {
+        // ---
+        self.looping = looping;
+        self
+    }
+
+    /// Returns the signal being generated.
+    pub fn signal(&self) -> Signal {
+        // ---
+        self.signal
+    }
+
+    /// Returns the length of one pass in seconds.
+    pub fn duration_secs(&self) -> f64 {
+        // ---
+        self.pass_samples as f64 / SAMPLE_RATE as f64
+    }
+
+    /// Returns the sample at media position `n`, counted from the start of
+    /// the stream.
+    fn sample(&self, n: u64) -> i16 {
+        // ---
+        let rate = SAMPLE_RATE as f64;
+        let value = match self.signal {
+            Signal::Tone(hz) => {
+                // Reduce to one cycle first so precision holds on long runs
+                let cycles = (n as f64 * hz / rate).fract();
+                (std::f64::consts::TAU * cycles).sin()
+            }
+            Signal::Sweep { start_hz, end_hz } => {
+                // Exponential chirp: the instantaneous frequency rises (or
+                // falls) by the same ratio per second throughout the pass
+                let t = (n % self.pass_samples.max(1)) as f64 / rate;
+                let pass = self.duration_secs();
+                let k = (end_hz / start_hz).ln();
+                let phase = start_hz * pass / k * ((k * t / pass).exp() - 1.0);
+                (std::f64::consts::TAU * phase.fract()).sin()
+            }
+            Signal::Noise => {
+                // splitmix64 of the position, mapped to -1..1
+                let z = splitmix64(&mut (NOISE_SEED ^ n));
+                (z >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+            }
+        };
+        (value * self.amplitude * i16::MAX as f64).round() as i16
+    }
+}
+
+impl FrameSource for SignalGenerator {
+    async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+        // ---
+        let frames = self.pass_samples / frame.len() as u64;
+        if frames == 0 || (!self.looping && index >= frames) {
+            return Ok(false);
+        }
+
+        let start = index * frame.len() as u64;
+        for (offset, sample) in frame.iter_mut().enumerate() {
+            *sample = self.sample(start + offset as u64);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_convert_to_mono_stereo() {
+        // ---
+        // Stereo: [L1, R1, L2, R2, L3, R3]
+        let stereo = vec![100, 200, 300, 400, 500, 600];
+        let mono = convert_to_mono(&stereo, 2);
+
+        // Should average each pair
+        assert_eq!(mono, vec![150, 350, 550]);
+    }
+
+    #[test]
+    fn test_convert_to_mono_quad() {
+        // ---
+        let quad = vec![100, 200, 300, 400]; // One quad frame
+        let mono = convert_to_mono(&quad, 4);
+
+        assert_eq!(mono.len(), 1);
+        assert_eq!(mono[0], 250); // Average of 4 channels
+    }
+
+    #[test]
+    fn test_channel_map_stereo() {
+        // ---
+        let stereo = vec![100, 200, 300, 400, 500, 600];
+        let cases = [
+            ("mix", vec![150, 350, 550]),
+            ("left", vec![100, 300, 500]),
+            ("right", vec![200, 400, 600]),
+            ("channel:1", vec![200, 400, 600]),
+        ];
+        for (spec, expected) in cases {
+            let map: ChannelMap = spec.parse().unwrap();
+            assert_eq!(map.to_string(), spec);
+            map.check(2).unwrap();
+            assert_eq!(map.apply(&stereo, 2), expected, "{spec}");
+        }
+
+        let err = "channel:2"
+            .parse::<ChannelMap>()
+            .unwrap()
+            .check(2)
+            .unwrap_err();
+        assert!(err.to_string().contains("has 2 channels (0 to 1)"), "{err}");
+        assert!(ChannelMap::Right.check(1).is_err());
+        assert!(ChannelMap::Left.check(1).is_ok());
+        for bad in ["center", "channel:", "channel:-1", "channel:x"] {
+            assert!(bad.parse::<ChannelMap>().is_err(), "{bad} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_channel_map_quad() {
+        // ---
+        // Two quad frames
+        let quad = vec![100, 200, 300, 400, 1100, 1200, 1300, 1400];
+        let cases = [
+            (ChannelMap::Mix, vec![250, 1250]),
+            (ChannelMap::Left, vec![100, 1100]),
+            (ChannelMap::Right, vec![200, 1200]),
+            (ChannelMap::Channel(2), vec![300, 1300]),
+            (ChannelMap::Channel(3), vec![400, 1400]),
+        ];
+        for (map, expected) in cases {
+            map.check(4).unwrap();
+            assert_eq!(map.apply(&quad, 4), expected, "{map}");
+        }
+        assert!(ChannelMap::Channel(4).check(4).is_err());
+    }
+
+    #[test]
+    fn test_resample_linear_upsample() {
+        // ---
+        let samples = vec![0, 1000, 2000];
+        let resampled = resample_linear(&samples, 8000, 16000);
+
+        // Should approximately double the sample count
+        assert!(resampled.len() >= 5 && resampled.len() <= 7);
+    }
+
+    #[test]
+    fn test_resample_linear_downsample() {
+        // ---
+        let samples = vec![0, 500, 1000, 1500, 2000];
         let resampled = resample_linear(&samples, 16000, 8000);
 
-        // Should approximately halve the sample count
-        assert!(resampled.len() >= 2 && resampled.len() <= 3);
+        // Should approximately halve the sample count
+        assert!(resampled.len() >= 2 && resampled.len() <= 3);
+    }
+
+    #[test]
+    fn test_resample_linear_same_rate() {
+        // ---
+        let samples = vec![100, 200, 300];
+        let resampled = resample_linear(&samples, 16000, 16000);
+
+        assert_eq!(resampled, samples);
+    }
+
+    #[test]
+    fn test_linear_resampler_matches_batch() {
+        // ---
+        let input: Vec<i16> = (0..4801).map(|i| ((i * 37) % 2000 - 1000) as i16).collect();
+        for (from, to) in [
+            (48000, 16000),
+            (44100, 16000),
+            (8000, 16000),
+            (16000, 16000),
+        ] {
+            let batch = resample_linear(&input, from, to);
+
+            // Uneven chunks, including empty ones
+            let mut resampler = LinearResampler::new(from, to);
+            let mut streamed = Vec::new();
+            for chunk in input.chunks(333).flat_map(|c| [c, &c[..0]]) {
+                resampler.process(chunk, &mut streamed);
+            }
+
+            // Lengths differ by rounding at the end only
+            let common = batch.len().min(streamed.len());
+            assert!(batch.len().abs_diff(streamed.len()) <= 2, "{from}->{to}");
+            assert_eq!(streamed[..common], batch[..common], "{from}->{to}");
+            assert!(resampler.history.len() <= 4);
+        }
+    }
+
+    #[test]
+    fn test_sinc_resampler_matches_batch() {
+        // ---
+        let input: Vec<i16> = (0..4801).map(|i| ((i * 37) % 2000 - 1000) as i16).collect();
+        for (from, to) in [
+            (48000u32, 16000u32),
+            (44100, 16000),
+            (8000, 16000),
+            (44101, 16000),
+        ] {
+            let batch = resample(&input, from, to, ResampleQuality::High);
+            assert_eq!(batch.len(), input.len() * to as usize / from as usize);
+
+            // Uneven chunks, including empty ones
+            let mut resampler = SincResampler::new(from, to);
+            let mut streamed = Vec::new();
+            for chunk in input.chunks(333).flat_map(|c| [c, &c[..0]]) {
+                resampler.process(chunk, &mut streamed);
+            }
+            assert!(resampler.history.len() <= resampler.taps + 333);
+            resampler.finish(&mut streamed);
+            assert_eq!(streamed, batch, "{from}->{to}");
+        }
+    }
+
+    /// Tone of `hz` at `rate` and 0.5 full scale.
+    fn tone_at(hz: f64, rate: u32, secs: f64) -> Vec<i16> {
+        // ---
+        (0..(rate as f64 * secs) as usize)
+            .map(|i| {
+                let phase = std::f64::consts::TAU * hz * i as f64 / rate as f64;
+                (phase.sin() * 16384.0) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sinc_resampler_attenuates_aliases() {
+        // ---
+        // 10kHz is above the 8kHz output Nyquist: all that reaches the
+        // output is its alias at 6kHz. Edges are skipped.
+        let input = tone_at(10_000.0, 44100, 1.0);
+        let level = |quality| {
+            let output = resample(&input, 44100, SAMPLE_RATE, quality);
+            rms(&output[1000..output.len() - 1000])
+        };
+        let (fast, high) = (level(ResampleQuality::Fast), level(ResampleQuality::High));
+        let attenuation_db = 20.0 * (fast / high.max(1e-9)).log10();
+        assert!(
+            attenuation_db > 60.0,
+            "alias only {attenuation_db:.1} dB below the linear path ({fast:.0} vs {high:.0})"
+        );
+
+        // A 1kHz tone passes at its level (0.5 full scale peak)
+        let output = resample(
+            &tone_at(1000.0, 44100, 1.0),
+            44100,
+            SAMPLE_RATE,
+            ResampleQuality::High,
+        );
+        let level = rms(&output[1000..output.len() - 1000]);
+        assert!(
+            (level - 16384.0 / 2f64.sqrt()).abs() < 16384.0 * 0.01,
+            "{level}"
+        );
+        assert!(goertzel(&output, 1000.0) > 1000.0 * goertzel(&output, 6000.0));
     }
 
     #[test]
-    fn test_resample_linear_same_rate() {
+    fn test_read_wav_with_silence_stereo_fixture() {
         // ---
-        let samples = vec![100, 200, 300];
-        let resampled = resample_linear(&samples, 16000, 16000);
+        // Stereo 48kHz fixture: tone on the left, muted right channel
+        let path = std::env::temp_dir().join(format!("silence-{}.wav", std::process::id()));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create fixture");
+        for i in 0..48000 {
+            let left = ((i as f32 * 0.05).sin() * 8000.0) as i16;
+            writer.write_sample(left).expect("write left");
+            writer.write_sample(0i16).expect("write right");
+        }
+        writer.finalize().expect("finalize fixture");
 
-        assert_eq!(resampled, samples);
+        let config = SilenceConfig::default();
+        let result = read_wav_with_silence(&path, &config);
+        std::fs::remove_file(&path).ok();
+        let (audio, report) = result.expect("read fixture");
+
+        assert_eq!(audio.original_channels, 2);
+        assert_eq!(report.frames, 50);
+        assert_eq!(report.channels[1].silent_percent, 100.0);
+        assert_eq!(report.warnings(&config).len(), 1);
+    }
+
+    /// Encodes interleaved 16-bit samples as a FLAC stream of verbatim
+    /// (uncompressed) subframes.
+    fn flac_fixture(samples: &[i16], sample_rate: u32, channels: usize) -> Vec<u8> {
+        // ---
+        const BLOCK: usize = 4096;
+
+        fn crc8(data: &[u8]) -> u8 {
+            // ---
+            data.iter().fold(0u8, |crc, &byte| {
+                (0..8).fold(crc ^ byte, |c, _| {
+                    if c & 0x80 != 0 {
+                        (c << 1) ^ 0x07
+                    } else {
+                        c << 1
+                    }
+                })
+            })
+        }
+
+        fn crc16(data: &[u8]) -> u16 {
+            // ---
+            data.iter().fold(0u16, |crc, &byte| {
+                (0..8).fold(crc ^ ((byte as u16) << 8), |c, _| {
+                    if c & 0x8000 != 0 {
+                        (c << 1) ^ 0x8005
+                    } else {
+                        c << 1
+                    }
+                })
+            })
+        }
+
+        let frames = samples.len() / channels;
+        let mut out = b"fLaC".to_vec();
+
+        // Last metadata block: STREAMINFO, 34 bytes
+        out.extend_from_slice(&[0x80, 0, 0, 34]);
+        out.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+        out.extend_from_slice(&(BLOCK as u16).to_be_bytes());
+        out.extend_from_slice(&[0; 6]);
+        let packed = ((sample_rate as u64) << 44)
+            | (((channels - 1) as u64) << 41)
+            | (15u64 << 36)
+            | frames as u64;
+        out.extend_from_slice(&packed.to_be_bytes());
+        out.extend_from_slice(&[0; 16]);
+
+        for (index, block) in samples.chunks(BLOCK * channels).enumerate() {
+            let mut frame = vec![0xFF, 0xF8, 0x70, (((channels - 1) as u8) << 4) | 0x08];
+            assert!(index < 128);
+            frame.push(index as u8);
+            frame.extend_from_slice(&((block.len() / channels - 1) as u16).to_be_bytes());
+            frame.push(crc8(&frame));
+            for channel in 0..channels {
+                frame.push(0x02);
+                for sample in block.iter().skip(channel).step_by(channels) {
+                    frame.extend_from_slice(&sample.to_be_bytes());
+                }
+            }
+            let crc = crc16(&frame);
+            frame.extend_from_slice(&crc.to_be_bytes());
+            out.extend(frame);
+        }
+        out
+    }
+
+    /// Builds `frames` MPEG-1 Layer III frames of silence (32 kbps, 48kHz,
+    /// mono), each decoding to 1152 samples.
+    fn mp3_fixture(frames: usize) -> Vec<u8> {
+        // ---
+        const FRAME_BYTES: usize = 144 * 32000 / 48000;
+
+        let mut out = Vec::with_capacity(frames * FRAME_BYTES);
+        for _ in 0..frames {
+            // Zeroed side info and main data: no coded spectrum
+            let mut frame = vec![0xFF, 0xFB, 0x14, 0xC0];
+            frame.resize(FRAME_BYTES, 0);
+            out.extend(frame);
+        }
+        out
+    }
+
+    /// Writes a 44.1kHz stereo WAV fixture of `secs` seconds (different
+    /// tones per channel) and returns its path.
+    fn write_stereo_fixture(name: &str, secs: f32) -> std::path::PathBuf {
+        // ---
+        let path = std::env::temp_dir().join(format!("{name}-{}.wav", std::process::id()));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create fixture");
+        for i in 0..(44100.0 * secs) as usize {
+            let left = ((i as f32 * 0.031).sin() * 9000.0) as i16;
+            let right = ((i as f32 * 0.007).sin() * 5000.0) as i16;
+            writer.write_sample(left).expect("write left");
+            writer.write_sample(right).expect("write right");
+        }
+        writer.finalize().expect("finalize fixture");
+        path
+    }
+
+    #[tokio::test]
+    async fn test_wav_frame_reader_matches_whole_file() {
+        // ---
+        // Long enough for many read chunks, with a tail short of a frame
+        let path = write_stereo_fixture("stream", 7.013);
+        let whole = read_wav(&path);
+        let streamed = WavFrameReader::open(&path, false);
+        let looped = WavFrameReader::open(&path, true);
+        std::fs::remove_file(&path).ok();
+        let whole = whole.expect("read fixture");
+        let (mut streamed, mut looped) = (streamed.unwrap(), looped.unwrap());
+        assert!((streamed.duration_secs() - 7.013).abs() < 0.001);
+
+        let mut expected = crate::source::PcmFrames::new(&whole.samples, false);
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        let mut want = vec![0i16; SAMPLES_PER_FRAME];
+        let mut frames = 0;
+        while expected.read_frame(frames, &mut want).await.unwrap() {
+            assert!(streamed.read_frame(frames, &mut frame).await.unwrap());
+            assert_eq!(frame, want, "frame {frames}");
+            frames += 1;
+        }
+        assert_eq!(
+            frames,
+            whole.samples.len() as u64 / SAMPLES_PER_FRAME as u64
+        );
+        assert!(!streamed.read_frame(frames, &mut frame).await.unwrap());
+        assert_eq!(streamed.passes_completed(), 1);
+        assert_eq!(
+            streamed.discarded_samples(),
+            whole.samples.len() as u64 % SAMPLES_PER_FRAME as u64
+        );
+
+        // Looping rewinds after the last complete frame, skipping ahead too
+        let mut first = vec![0i16; SAMPLES_PER_FRAME];
+        assert!(looped.read_frame(0, &mut first).await.unwrap());
+        assert!(looped.read_frame(frames, &mut frame).await.unwrap());
+        assert_eq!(frame, first);
+        assert!(looped.read_frame(2 * frames + 3, &mut frame).await.unwrap());
+        expected.read_frame(3, &mut want).await.unwrap();
+        assert_eq!(frame, want);
+        assert!(looped.pending.len() < SAMPLES_PER_FRAME + WAV_CHUNK_FRAMES);
+    }
+
+    #[tokio::test]
+    async fn test_wav_frame_reader_loop_count_matches_pcm_frames() {
+        // ---
+        // Two passes with a gap between them but none after the last
+        let path = write_stereo_fixture("loop-count", 0.5);
+        let whole = read_wav(&path);
+        let looped = WavFrameReader::open(&path, true);
+        std::fs::remove_file(&path).ok();
+        let whole = whole.expect("read fixture");
+        let gap = Duration::from_millis(40);
+        let mut looped = looped.unwrap().with_loop_gap(gap).with_loop_count(2);
+        let mut expected = crate::source::PcmFrames::new(&whole.samples, true)
+            .with_loop_gap(gap)
+            .with_loop_count(2);
+
+        let pass = (whole.samples.len() / SAMPLES_PER_FRAME) as u64;
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        let mut want = vec![0i16; SAMPLES_PER_FRAME];
+        for index in 0..2 * pass + 2 {
+            assert!(expected.read_frame(index, &mut want).await.unwrap());
+            assert!(looped.read_frame(index, &mut frame).await.unwrap());
+            assert_eq!(frame, want, "frame {index}");
+        }
+        assert!(!expected.read_frame(2 * pass + 2, &mut want).await.unwrap());
+        assert!(!looped.read_frame(2 * pass + 2, &mut frame).await.unwrap());
+        assert_eq!(expected.passes_completed(), 2);
+        assert_eq!(looped.passes_completed(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_wav_frame_reader_loop_fade_matches_pcm_frames() {
+        // ---
+        // A fade longer than a frame and a gap of part of a frame over two
+        // passes, with the partial tail dropped or padded
+        let path = write_stereo_fixture("loop-fade", 1.013);
+        let whole = read_wav(&path);
+        let readers = [TailPolicy::Discard, TailPolicy::PadWithSilence]
+            .map(|tail| (tail, WavFrameReader::open(&path, true)));
+        std::fs::remove_file(&path).ok();
+        let whole = whole.expect("read fixture");
+        let fade = Duration::from_millis(30);
+        let gap = Duration::from_millis(50);
+
+        for (tail, looped) in readers {
+            let mut looped = looped
+                .unwrap()
+                .with_loop_fade(fade)
+                .with_loop_gap(gap)
+                .with_tail(tail);
+            let mut expected = crate::source::PcmFrames::new(&whole.samples, true)
+                .with_loop_fade(fade)
+                .with_loop_gap(gap)
+                .with_tail(tail);
+
+            let pass = match tail {
+                TailPolicy::Discard => whole.samples.len() / SAMPLES_PER_FRAME,
+                TailPolicy::PadWithSilence => whole.samples.len().div_ceil(SAMPLES_PER_FRAME),
+            } as u64;
+            // 50ms rounds up to three frames
+            let cycle = pass + 3;
+            let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+            let mut want = vec![0i16; SAMPLES_PER_FRAME];
+            for index in 0..=2 * cycle {
+                assert!(expected.read_frame(index, &mut want).await.unwrap());
+                assert!(looped.read_frame(index, &mut frame).await.unwrap());
+                assert_eq!(frame, want, "{tail:?} frame {index}");
+                assert_eq!(looped.in_gap(), index % cycle >= pass, "frame {index}");
+                assert_eq!(expected.in_gap(), looped.in_gap(), "frame {index}");
+            }
+            // The third pass starts from silence
+            assert_eq!(want[0], 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_window_limits_frames() {
+        // ---
+        // Two seconds of a ramp, so each sample tells its position
+        let ramp: Vec<i32> = (0..2 * SAMPLE_RATE as i32).map(|i| i % 30000).collect();
+        let path = write_int_fixture("window", 16, &ramp);
+
+        // (start, duration, frames): the last window runs past the end
+        let windows = [
+            (0.0, None, 100),
+            (0.0, Some(0.3), 15),
+            (0.5, Some(0.4), 20),
+            (1.0, None, 50),
+            (1.8, Some(1.0), 10),
+        ];
+        for (start, duration, frames) in windows {
+            let read = AudioReadOptions {
+                start: Duration::from_secs_f64(start),
+                duration: duration.map(Duration::from_secs_f64),
+                ..Default::default()
+            };
+            let loaded = read_wav_with_options(&path, &read).expect("read window");
+            assert_eq!(loaded.frame_count(), frames, "{start} {duration:?}");
+            assert_eq!(loaded.samples[0] as f64, start * SAMPLE_RATE as f64);
+
+            let mut reader = WavFrameReader::open(&path, false)
+                .unwrap()
+                .with_read_options(&read)
+                .expect("open window");
+            assert!((reader.duration_secs() - loaded.duration_secs()).abs() < 1e-9);
+            assert_eq!(read_all(&mut reader).await, loaded.samples);
+        }
+
+        // Looping repeats the window, not the file
+        let read = AudioReadOptions {
+            start: Duration::from_millis(500),
+            duration: Some(Duration::from_millis(400)),
+            ..Default::default()
+        };
+        let mut looped = WavFrameReader::open(&path, true)
+            .unwrap()
+            .with_read_options(&read)
+            .unwrap();
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        assert!(looped.read_frame(20, &mut frame).await.unwrap());
+        assert_eq!(frame[0], 8000);
+
+        // Starting at or past the end is an error
+        for start in [2.0, 2.5] {
+            let read = AudioReadOptions {
+                start: Duration::from_secs_f64(start),
+                ..Default::default()
+            };
+            let err = read_wav_with_options(&path, &read).unwrap_err();
+            assert!(err.to_string().contains("past the end"), "{err}");
+            let reader = WavFrameReader::open(&path, false).unwrap();
+            assert!(reader.with_read_options(&read).is_err());
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_channel_map_on_file_input() {
+        // ---
+        let path = write_stereo_fixture("channels", 0.5);
+        let right = ConvertOptions {
+            channel_map: ChannelMap::Right,
+            ..Default::default()
+        };
+        let loaded = read_audio_with_options(
+            &path,
+            &SilenceConfig::default(),
+            &AudioReadOptions::default(),
+            &right,
+        );
+        let missing = ConvertOptions {
+            channel_map: ChannelMap::Channel(2),
+            ..Default::default()
+        };
+        let rejected = read_audio_with_options(
+            &path,
+            &SilenceConfig::default(),
+            &AudioReadOptions::default(),
+            &missing,
+        );
+        let streamed = WavFrameReader::open(&path, false).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        // The right channel alone is a quieter tone than the mix
+        let (audio, _) = loaded.expect("read fixture");
+        let mut streamed = streamed.with_channel_map(ChannelMap::Right).unwrap();
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        assert!(streamed.read_frame(0, &mut frame).await.unwrap());
+        assert_eq!(frame, audio.samples[..SAMPLES_PER_FRAME]);
+        assert!(rms(&audio.samples) < 5000.0 / 2f64.sqrt() * 1.02);
+
+        let err = rejected.unwrap_err();
+        assert!(err.to_string().contains("channel 2"), "{err}");
+    }
+
+    #[test]
+    fn test_analyze_wav_silence_matches_whole_file() {
+        // ---
+        let path = write_stereo_fixture("probe", 2.5);
+        let config = SilenceConfig::default();
+        let whole = read_wav_with_silence(&path, &config);
+        let streamed = analyze_wav_silence(&path, &config, &AudioReadOptions::default());
+        std::fs::remove_file(&path).ok();
+        let (_, whole) = whole.expect("read fixture");
+        let streamed = streamed.expect("analyze fixture");
+
+        assert_eq!(streamed.frames, whole.frames);
+        assert_eq!(streamed.silent_frames, whole.silent_frames);
+        assert_eq!(streamed.channels, whole.channels);
+    }
+
+    /// Writes a 16kHz mono WAV fixture of `bits`-bit integer samples.
+    fn write_int_fixture(name: &str, bits: u16, samples: &[i32]) -> std::path::PathBuf {
+        // ---
+        let path = std::env::temp_dir().join(format!("{name}-{}.wav", std::process::id()));
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: bits,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create fixture");
+        for &sample in samples {
+            if bits == 8 {
+                writer.write_sample(sample as i8).expect("write sample");
+            } else {
+                writer.write_sample(sample).expect("write sample");
+            }
+        }
+        writer.finalize().expect("finalize fixture");
+        path
+    }
+
+    #[test]
+    fn test_gain_restores_attenuated_file() {
+        // ---
+        let original: Vec<i32> = tone_at(440.0, SAMPLE_RATE, 0.5)
+            .iter()
+            .map(|&s| s as i32 * 2)
+            .collect();
+        let attenuation = 10f64.powf(-6.0 / 20.0);
+        let quiet: Vec<i32> = original
+            .iter()
+            .map(|&s| (s as f64 * attenuation).round() as i32)
+            .collect();
+        let path = write_int_fixture("gain", 16, &quiet);
+        let boost = ConvertOptions {
+            gain_db: 6.0,
+            ..Default::default()
+        };
+        let result = read_audio_with_options(
+            &path,
+            &SilenceConfig::default(),
+            &AudioReadOptions::default(),
+            &boost,
+        );
+        std::fs::remove_file(&path).ok();
+        let (audio, _) = result.expect("read fixture");
+
+        for (&boosted, &expected) in audio.samples.iter().zip(&original) {
+            assert!(
+                (boosted as i32 - expected).abs() <= 1,
+                "{boosted} vs {expected}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gain_clips_and_counts_full_scale() {
+        // ---
+        let mut gain = Gain::new(20.0);
+        let mut samples = vec![i16::MAX, i16::MIN, 1000, -1000, 100];
+        gain.apply(&mut samples);
+        assert_eq!(samples, [i16::MAX, i16::MIN, 10000, -10000, 1000]);
+        assert_eq!(gain.clipped(), 2);
+
+        // Streamed: a full-scale square wave clips on every sample
+        let square: Vec<i32> = (0..SAMPLES_PER_FRAME * 4)
+            .map(|i| if (i / 20) % 2 == 0 { 32767 } else { -32768 })
+            .collect();
+        let path = write_int_fixture("clip", 16, &square);
+        let reader = WavFrameReader::open(&path, false);
+        std::fs::remove_file(&path).ok();
+        let mut reader = reader.unwrap().with_gain_db(20.0);
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        assert!(reader.read_frame(0, &mut frame).await.unwrap());
+        assert!(frame.iter().all(|&s| s == i16::MAX || s == i16::MIN));
+        assert!(reader.clipped_samples() >= SAMPLES_PER_FRAME as u64);
+    }
+
+    /// Reads every frame of `reader` until it ends.
+    async fn read_all(reader: &mut WavFrameReader) -> Vec<i16> {
+        // ---
+        let mut samples = Vec::new();
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        let mut index = 0;
+        while reader.read_frame(index, &mut frame).await.unwrap() {
+            samples.extend_from_slice(&frame);
+            index += 1;
+        }
+        samples
+    }
+
+    /// Level of `samples` measured by `mode`, in dBFS.
+    fn level_of(samples: &[i16], mode: NormalizeMode) -> f64 {
+        // ---
+        let mut level = LevelMeter::default();
+        level.push(samples);
+        level.level_dbfs(mode)
+    }
+
+    #[tokio::test]
+    async fn test_normalize_file_to_target() {
+        // ---
+        // A tone peaking near -18 dBFS, loaded and streamed
+        let quiet: Vec<i32> = tone_at(440.0, SAMPLE_RATE, 1.0)
+            .iter()
+            .map(|&s| s as i32 / 4)
+            .collect();
+        let path = write_int_fixture("normalize", 16, &quiet);
+
+        for mode in NormalizeMode::ALL {
+            let convert = ConvertOptions {
+                normalize: Some(NormalizeConfig {
+                    mode,
+                    target_dbfs: -3.0,
+                }),
+                ..Default::default()
+            };
+            let loaded = read_audio_with_options(
+                &path,
+                &SilenceConfig::default(),
+                &AudioReadOptions::default(),
+                &convert,
+            );
+            let streamed =
+                WavFrameReader::open_with(&path, false, &AudioReadOptions::default(), &convert);
+            let (loaded, _) = loaded.expect("load");
+            let streamed = read_all(&mut streamed.expect("open")).await;
+
+            let loaded_dbfs = level_of(&loaded.samples, mode);
+            let streamed_dbfs = level_of(&streamed, mode);
+            assert!((loaded_dbfs + 3.0).abs() < 0.05, "{mode}: {loaded_dbfs}");
+            assert!(
+                (streamed_dbfs + 3.0).abs() < 0.05,
+                "{mode}: {streamed_dbfs}"
+            );
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn test_normalize_leaves_silent_file_silent() {
+        // ---
+        let path = write_int_fixture("normalize-silent", 16, &[0; SAMPLES_PER_FRAME * 5]);
+        let convert = ConvertOptions {
+            normalize: Some(NormalizeConfig::default()),
+            ..Default::default()
+        };
+        let loaded = read_audio_with_options(
+            &path,
+            &SilenceConfig::default(),
+            &AudioReadOptions::default(),
+            &convert,
+        );
+        let streamed =
+            WavFrameReader::open_with(&path, false, &AudioReadOptions::default(), &convert);
+        std::fs::remove_file(&path).ok();
+
+        assert!(loaded.expect("load").0.samples.iter().all(|&s| s == 0));
+        assert!(read_all(&mut streamed.expect("open"))
+            .await
+            .iter()
+            .all(|&s| s == 0));
+    }
+
+    /// `lead` samples of silence, `sound` of a -20 dBFS square wave with
+    /// `pause` samples of silence in the middle, then `trail` of silence.
+    fn padded_sound(lead: usize, sound: usize, pause: usize, trail: usize) -> Vec<i16> {
+        // ---
+        let square = |i: usize| {
+            if (i / 20).is_multiple_of(2) {
+                3277
+            } else {
+                -3277
+            }
+        };
+        let half = (sound - pause) / 2;
+        let mut samples = vec![0; lead];
+        samples.extend((0..half).map(square));
+        samples.extend(vec![0; pause]);
+        samples.extend((0..sound - pause - half).map(square));
+        samples.extend(vec![0; trail]);
+        samples
+    }
+
+    #[test]
+    fn test_trim_keeps_first_to_last_sound() {
+        // ---
+        let trim = TrimConfig::default();
+
+        // Half a second before, a second after; the 300ms pause inside
+        // the sound is kept
+        let samples = padded_sound(8000, 16000, 4800, 16000);
+        assert_eq!(trim.keep_range(&samples), 8000..24000);
+
+        // Quieter than the threshold counts as silence
+        let mut hum = samples.clone();
+        hum[..8000].fill(40);
+        assert_eq!(trim.keep_range(&hum), 8000..24000);
+
+        // No sound at all: nothing to go by, so all is kept
+        assert_eq!(trim.keep_range(&[40; 8000]), 0..8000);
+    }
+
+    #[test]
+    fn test_trim_keeps_silence_shorter_than_minimum() {
+        // ---
+        let trim = TrimConfig {
+            min_silence: Duration::from_millis(200),
+            ..Default::default()
+        };
+
+        // 100ms before is kept, 200ms after is trimmed
+        let samples = padded_sound(1600, 8000, 0, 3200);
+        assert_eq!(trim.keep_range(&samples), 0..9600);
+        let samples = padded_sound(3200, 8000, 0, 1599);
+        assert_eq!(trim.keep_range(&samples), 3200..12799);
+    }
+
+    #[test]
+    fn test_trim_applies_after_conversion() {
+        // ---
+        let samples: Vec<i32> = padded_sound(8000, 16000, 3200, 8000)
+            .into_iter()
+            .map(i32::from)
+            .collect();
+        let path = write_int_fixture("trim", 16, &samples);
+        let convert = ConvertOptions {
+            trim: Some(TrimConfig::default()),
+            gain_db: 6.0,
+            ..Default::default()
+        };
+        let loaded = read_audio_with_options(
+            &path,
+            &SilenceConfig::default(),
+            &AudioReadOptions::default(),
+            &convert,
+        );
+        let streamed =
+            WavFrameReader::open_with(&path, false, &AudioReadOptions::default(), &convert);
+        std::fs::remove_file(&path).ok();
+
+        // Trimmed before the gain, so the threshold applies to the input
+        let (loaded, _) = loaded.expect("load");
+        assert_eq!(loaded.samples.len(), 16000);
+        assert_eq!(loaded.duration_secs(), 1.0);
+        assert_eq!(loaded.samples[0], 6538);
+        assert!(streamed.is_err());
+    }
+
+    #[test]
+    fn test_agc_converges_to_target() {
+        // ---
+        // A tone peaking near -30 dBFS for 4 seconds
+        let input: Vec<i16> = tone_at(440.0, SAMPLE_RATE, 4.0)
+            .iter()
+            .map(|&s| s / 16)
+            .collect();
+        let mut agc = Agc::new(AgcConfig::default());
+        let mut output = input.clone();
+        for frame in output.chunks_mut(SAMPLES_PER_FRAME) {
+            agc.process(frame);
+        }
+
+        let last_second = &output[output.len() - SAMPLE_RATE as usize..];
+        let peak = level_of(last_second, NormalizeMode::Peak);
+        assert!((peak + 3.0).abs() < 0.5, "peak {peak}");
+        assert_eq!(agc.clipped(), 0);
+
+        // A sudden jump to full scale is pulled down within a few frames
+        let mut loud: Vec<i16> = tone_at(440.0, SAMPLE_RATE, 0.2)
+            .iter()
+            .map(|&s| s.saturating_mul(2))
+            .collect();
+        for frame in loud.chunks_mut(SAMPLES_PER_FRAME) {
+            agc.process(frame);
+        }
+        let tail = &loud[loud.len() - SAMPLES_PER_FRAME..];
+        assert!(level_of(tail, NormalizeMode::Peak) < -2.0);
+    }
+
+    #[test]
+    fn test_agc_does_not_raise_silence_or_noise() {
+        // ---
+        let mut agc = Agc::new(AgcConfig::default());
+        let mut silence = vec![0i16; SAMPLES_PER_FRAME * 100];
+        for frame in silence.chunks_mut(SAMPLES_PER_FRAME) {
+            agc.process(frame);
+        }
+        assert!(silence.iter().all(|&s| s == 0));
+        assert_eq!(agc.gain_db(), 0.0);
+
+        // Noise at about -70 dBFS stays below the gate and is not raised
+        let mut state = NOISE_SEED;
+        let mut noise: Vec<i16> = (0..SAMPLES_PER_FRAME * 100)
+            .map(|_| {
+                state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                ((state >> 60) as i16 - 8) * 2
+            })
+            .collect();
+        for frame in noise.chunks_mut(SAMPLES_PER_FRAME) {
+            agc.process(frame);
+        }
+        assert!(level_of(&noise, NormalizeMode::Peak) < -60.0);
+        assert_eq!(agc.gain_db(), 0.0);
+    }
+
+    #[test]
+    fn test_read_wav_24_bit_scaling() {
+        // ---
+        // Full scale both ways, half scale, and the rounding boundary
+        let input = [8_388_607, -8_388_608, 4_194_304, 0x7F, 0x80, -0x81, 0];
+        let path = write_int_fixture("pcm24", 24, &input);
+        let audio = read_wav(&path);
+        std::fs::remove_file(&path).ok();
+        let audio = audio.expect("read 24-bit fixture");
+
+        assert_eq!(audio.samples, [i16::MAX, i16::MIN, 16384, 0, 1, -1, 0]);
+    }
+
+    #[test]
+    fn test_read_wav_8_bit_scaling() {
+        // ---
+        let input = [127, -128, 64, 0, -1];
+        let path = write_int_fixture("pcm8", 8, &input);
+        let audio = read_wav(&path);
+        std::fs::remove_file(&path).ok();
+        let audio = audio.expect("read 8-bit fixture");
+
+        // Full scale in comes out within 1% of full scale
+        assert_eq!(audio.samples, [32512, i16::MIN, 16384, 0, -256]);
+        assert!(audio.samples[0] as f64 >= 0.99 * i16::MAX as f64);
+
+        // 32-bit integer stays rejected
+        let path = write_int_fixture("pcm32", 32, &[0]);
+        let err = read_wav(&path).unwrap_err();
+        std::fs::remove_file(&path).ok();
+        assert!(err.to_string().contains("32-bit"), "{err}");
+    }
+
+    /// Writes a 16kHz mono 32-bit float WAV fixture.
+    fn write_float_fixture(name: &str, samples: &[f32]) -> std::path::PathBuf {
+        // ---
+        let path = std::env::temp_dir().join(format!("{name}-{}.wav", std::process::id()));
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).expect("create fixture");
+        for &sample in samples {
+            writer.write_sample(sample).expect("write sample");
+        }
+        writer.finalize().expect("finalize fixture");
+        path
+    }
+
+    #[tokio::test]
+    async fn test_float_wav_reports_clipped_samples() {
+        // ---
+        // Two frames of a tone with every tenth sample pushed to +/-1.5
+        let hot: Vec<f32> = (0..2 * SAMPLES_PER_FRAME)
+            .map(|i| match i % 20 {
+                0 => 1.5,
+                10 => -1.5,
+                _ => (i as f32 * 0.05).sin() * 0.5,
+            })
+            .collect();
+        let path = write_float_fixture("float-hot", &hot);
+        let loaded = read_wav(&path);
+        let streamed = WavFrameReader::open(&path, false);
+        std::fs::remove_file(&path).ok();
+
+        let loaded = loaded.expect("read fixture");
+        assert_eq!(loaded.clipped_samples, 64);
+        assert_eq!(loaded.samples[0], i16::MAX);
+        assert_eq!(loaded.samples[10], -i16::MAX);
+
+        let mut streamed = streamed.expect("open fixture");
+        let frames = read_all(&mut streamed).await;
+        assert_eq!(frames.len(), 2 * SAMPLES_PER_FRAME);
+        assert_eq!(streamed.input_clipped_samples(), 64);
+        assert_eq!(streamed.clipped_samples(), 0);
+
+        let mut float = FloatConverter::new(&AudioReadOptions::default());
+        float.convert(1.5);
+        assert!((float.overshoot_db() - 3.52).abs() < 0.01);
+
+        // Within full scale: nothing clipped
+        let clean: Vec<f32> = hot.iter().map(|s| s.clamp(-1.0, 1.0)).collect();
+        let path = write_float_fixture("float-clean", &clean);
+        let loaded = read_wav(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.expect("read fixture").clipped_samples, 0);
+    }
+
+    #[test]
+    fn test_float_wav_rounds_to_nearest() {
+        // ---
+        let lsb = 1.0 / i16::MAX as f32;
+        let input = [1.6 * lsb, -1.6 * lsb, 0.4 * lsb, -0.4 * lsb, 0.5, 1.0, -1.0];
+        let path = write_float_fixture("float-round", &input);
+        let plain = read_wav_with_options(
+            &path,
+            &AudioReadOptions {
+                dither: false,
+                ..Default::default()
+            },
+        );
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(
+            plain.expect("read fixture").samples,
+            [2, -2, 0, 0, 16384, i16::MAX, -i16::MAX]
+        );
+    }
+
+    #[test]
+    fn test_float_wav_dither_varies_constant_signal() {
+        // ---
+        // A quarter of an LSB: 0 every time without dither
+        let level = 0.25 / i16::MAX as f32;
+        let path = write_float_fixture("float-dither", &vec![level; 16000]);
+        let plain = read_wav_with_options(
+            &path,
+            &AudioReadOptions {
+                dither: false,
+                ..Default::default()
+            },
+        );
+        let dithered = read_wav(&path);
+        std::fs::remove_file(&path).ok();
+        assert!(plain.expect("read fixture").samples.iter().all(|&s| s == 0));
+
+        // TPDF dither adds 1/6 LSB^2 of variance, rounding about 1/12 more;
+        // the signal survives as the mean
+        let samples = dithered.expect("read fixture").samples;
+        let n = samples.len() as f64;
+        let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / n;
+        let variance = samples
+            .iter()
+            .map(|&s| (s as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        assert!((mean - 0.25).abs() < 0.03, "mean {mean}");
+        assert!((0.15..0.35).contains(&variance), "variance {variance}");
+        assert!(samples.iter().all(|&s| (-1..=2).contains(&s)));
+    }
+
+    #[test]
+    fn test_read_audio_flac_fixture() {
+        // ---
+        // 1.5s of stereo 44.1kHz tone, identical in both channels
+        let path = std::env::temp_dir().join(format!("read-audio-{}.flac", std::process::id()));
+        let tone: Vec<i16> = (0..66150)
+            .flat_map(|i| {
+                let s = ((i as f32 * 2.0 * std::f32::consts::PI * 440.0 / 44100.0).sin() * 8000.0)
+                    as i16;
+                [s, s]
+            })
+            .collect();
+        std::fs::write(&path, flac_fixture(&tone, 44100, 2)).expect("write fixture");
+
+        let format = detect_format(&path);
+        let result = read_audio(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(format.unwrap(), AudioFormat::Flac);
+        let audio = result.expect("read fixture");
+
+        assert_eq!(audio.original_sample_rate, 44100);
+        assert_eq!(audio.original_channels, 2);
+        assert!((audio.duration_secs() - 1.5).abs() < 0.01);
+        assert!(audio.samples.len().abs_diff(24000) <= 2);
+
+        // Lossless source: the downmixed, resampled tone keeps its level
+        let peak = audio
+            .samples
+            .iter()
+            .map(|s| s.unsigned_abs())
+            .max()
+            .unwrap();
+        assert!((7900..=8000).contains(&peak), "peak {peak}");
+    }
+
+    #[test]
+    fn test_read_audio_mp3_fixture() {
+        // ---
+        let path = std::env::temp_dir().join(format!("read-audio-{}.mp3", std::process::id()));
+        std::fs::write(&path, mp3_fixture(50)).expect("write fixture");
+
+        let config = SilenceConfig::default();
+        let result = read_audio_with_silence(&path, &config);
+        std::fs::remove_file(&path).ok();
+        let (audio, report) = result.expect("read fixture");
+
+        // 50 frames of 1152 samples at 48kHz
+        assert_eq!(audio.original_sample_rate, 48000);
+        assert_eq!(audio.original_channels, 1);
+        assert!((audio.duration_secs() - 1.2).abs() < 0.03);
+        assert!(audio.samples.iter().all(|&s| s == 0));
+        assert_eq!(report.channels[0].silent_percent, 100.0);
+    }
+
+    #[test]
+    fn test_detect_format_and_unsupported_error() {
+        // ---
+        assert_eq!(
+            AudioFormat::detect(b"RIFF\0\0\0\0WAVEfmt ", None),
+            AudioFormat::Wav
+        );
+        assert_eq!(
+            AudioFormat::detect(b"ID3\x04", Some("bin")),
+            AudioFormat::Mp3
+        );
+        assert_eq!(
+            AudioFormat::detect(&[0xFF, 0xFB, 0x90], None),
+            AudioFormat::Mp3
+        );
+        assert_eq!(
+            AudioFormat::detect(b"\0\0\0\x20ftypM4A ", None),
+            AudioFormat::Mp4
+        );
+        assert_eq!(AudioFormat::detect(b"", Some("FLAC")), AudioFormat::Flac);
+        assert_eq!(AudioFormat::detect(b"garbage", None), AudioFormat::Unknown);
+
+        let path = std::env::temp_dir().join(format!("read-audio-{}.aiff", std::process::id()));
+        std::fs::write(&path, b"FORM\0\0\0\x04AIFF").expect("write fixture");
+        let result = read_audio(&path);
+        std::fs::remove_file(&path).ok();
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("unsupported audio format AIFF"), "{err}");
     }
 
     #[test]
@@ -289,6 +3647,7 @@ mod tests {
             samples,
             original_sample_rate: 16000,
             original_channels: 1,
+            clipped_samples: 0,
         };
 
         let frames: Vec<_> = audio.frames().collect();
@@ -305,6 +3664,7 @@ mod tests {
             samples,
             original_sample_rate: 16000,
             original_channels: 1,
+            clipped_samples: 0,
         };
 
         assert!((audio.duration_secs() - 1.0).abs() < 0.001);
@@ -318,8 +3678,140 @@ mod tests {
             samples,
             original_sample_rate: 16000,
             original_channels: 1,
+            clipped_samples: 0,
         };
 
         assert_eq!(audio.frame_count(), 2); // Rounds up
     }
+
+    /// Reads `frames` consecutive frames from `generator`.
+    async fn generate(generator: &mut SignalGenerator, frames: u64) -> Vec<i16> {
+        // ---
+        let mut samples = Vec::new();
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        for index in 0..frames {
+            assert!(generator.read_frame(index, &mut frame).await.unwrap());
+            samples.extend_from_slice(&frame);
+        }
+        samples
+    }
+
+    fn rms(samples: &[i16]) -> f64 {
+        // ---
+        let sum: f64 = samples.iter().map(|&s| (s as f64).powi(2)).sum();
+        (sum / samples.len() as f64).sqrt()
+    }
+
+    /// Power of `samples` at `hz` (Goertzel).
+    fn goertzel(samples: &[i16], hz: f64) -> f64 {
+        // ---
+        let coeff = 2.0 * (std::f64::consts::TAU * hz / SAMPLE_RATE as f64).cos();
+        let (mut s1, mut s2) = (0.0, 0.0);
+        for &sample in samples {
+            let s0 = sample as f64 + coeff * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+        }
+        s1 * s1 + s2 * s2 - coeff * s1 * s2
+    }
+
+    #[test]
+    fn test_signal_spec_parsing() {
+        // ---
+        assert_eq!("tone".parse::<Signal>().unwrap(), Signal::Tone(440.0));
+        assert_eq!("tone:1000".parse::<Signal>().unwrap(), Signal::Tone(1000.0));
+        assert_eq!(
+            "sweep:100-4000".parse::<Signal>().unwrap(),
+            Signal::Sweep {
+                start_hz: 100.0,
+                end_hz: 4000.0
+            }
+        );
+        assert_eq!("noise".parse::<Signal>().unwrap(), Signal::Noise);
+
+        for bad in [
+            "tone:9000",
+            "tone:abc",
+            "sweep:100",
+            "sweep:50-50",
+            "noise:1",
+            "hum",
+        ] {
+            assert!(bad.parse::<Signal>().is_err(), "{bad} should not parse");
+        }
+        assert!(Signal::is_spec("tone:440"));
+        assert!(!Signal::is_spec("tone.wav"));
+    }
+
+    #[tokio::test]
+    async fn test_signal_generator_rms_levels() {
+        // ---
+        let peak = DEFAULT_SIGNAL_AMPLITUDE * i16::MAX as f64;
+        let cases = [
+            ("tone:440", peak / 2f64.sqrt()),
+            ("sweep", peak / 2f64.sqrt()),
+            ("noise", peak / 3f64.sqrt()),
+        ];
+        for (spec, expected) in cases {
+            let mut generator = SignalGenerator::new(spec.parse().unwrap());
+            let samples = generate(&mut generator, 500).await;
+            let level = rms(&samples);
+            assert!(
+                (level - expected).abs() < expected * 0.02,
+                "{spec}: RMS {level:.0}, expected {expected:.0}"
+            );
+        }
+
+        // A quieter generator scales with it
+        let mut quiet = SignalGenerator::new(Signal::Tone(440.0)).with_amplitude(0.1);
+        let level = rms(&generate(&mut quiet, 50).await);
+        assert!((level - 0.1 * i16::MAX as f64 / 2f64.sqrt()).abs() < 20.0);
+    }
+
+    #[tokio::test]
+    async fn test_signal_generator_duration_and_looping() {
+        // ---
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        let mut once = SignalGenerator::new(Signal::Noise).with_duration(Duration::from_secs(1));
+        assert!(once.read_frame(49, &mut frame).await.unwrap());
+        assert!(!once.read_frame(50, &mut frame).await.unwrap());
+
+        // A looping sweep starts over after each pass
+        let mut sweep = SignalGenerator::new("sweep".parse().unwrap())
+            .with_duration(Duration::from_secs(1))
+            .looping(true);
+        let first = generate(&mut sweep, 1).await;
+        assert!(sweep.read_frame(50, &mut frame).await.unwrap());
+        assert_eq!(frame, first);
+    }
+
+    #[tokio::test]
+    async fn test_tone_survives_encode_decode_round_trip() {
+        // ---
+        let mut generator = SignalGenerator::new(Signal::Tone(440.0));
+        let mut encoder = crate::codec::OpusEncoderWrapper::new().unwrap();
+        let mut decoder = opus::Decoder::new(SAMPLE_RATE, opus::Channels::Mono).unwrap();
+
+        let mut decoded = Vec::new();
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+        for index in 0..50 {
+            assert!(generator.read_frame(index, &mut frame).await.unwrap());
+            let packet = encoder.encode(&frame).unwrap();
+            let mut out = vec![0i16; SAMPLES_PER_FRAME];
+            let len = decoder.decode(&packet, &mut out, false).unwrap();
+            decoded.extend_from_slice(&out[..len]);
+        }
+
+        // Past the codec's start-up, 440 Hz dominates its octave neighbours
+        let settled = &decoded[10 * SAMPLES_PER_FRAME..];
+        let fundamental = goertzel(settled, 440.0);
+        for other in [220.0, 880.0, 1320.0] {
+            let ratio = fundamental / goertzel(settled, other);
+            assert!(
+                ratio > 100.0,
+                "440 Hz only {ratio:.1}x the power at {other} Hz"
+            );
+        }
+        assert!(rms(settled) > 0.5 * rms(&generate(&mut generator, 10).await));
+    }
 }
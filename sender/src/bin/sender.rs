@@ -1,14 +1,31 @@
 //! RTP Opus audio sender - CLI binary.
 //!
-//! Reads a WAV file, encodes it to Opus, packetizes into RTP,
-//! and transmits via UDP to a receiver.
+//! Reads a WAV, FLAC, or MP3 file (or generates a test signal), encodes it
+//! to Opus, packetizes into RTP, and transmits via UDP to a receiver. Ogg
+//! Opus input is sent as coded, without re-encoding.
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use tracing::info;
+use tracing::{info, warn};
 
-use rtp_opus_common::{init_tracing, ColorWhen, MetricsContext, MetricsServerConfig};
-use sender::{stream_audio, OpusEncoderWrapper, RtpSender};
+#[cfg(feature = "metrics-server")]
+use rtp_opus_common::MetricsServerConfig;
+use rtp_opus_common::{
+    default_cname, handle_verbose_version, init_tracing, read_rtpdump, AnomalyLogConfig, ColorWhen,
+    MetricsContext, RtpdumpFile, SrtpKey, Transport,
+};
+use sender::network::DEFAULT_MULTICAST_TTL;
+use sender::{
+    stream_audio, stream_frames, stream_ogg_opus, stream_playlist, stream_rtpdump,
+    AdaptationConfig, AdaptationController, AgcConfig, AudioData, AudioFormat, AudioReadOptions,
+    CatchUpPolicy, ChannelMap, ConvertOptions, EncoderSettings, ErrorPolicy, GapPolicy,
+    NormalizeConfig, NormalizeMode, OggOpusFile, OpusEncoderConfig, OpusEncoderWrapper, PacketSink,
+    PcapWriter, PcmStream, RampConfig, RateLimit, RtpSender, SenderNetworkConfig, Signal,
+    SignalGenerator, SilenceConfig, StreamOptions, StreamSummary, TailPolicy, TrimConfig, VbrMode,
+    WavFrameReader,
+};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
 
 #[derive(clap::ValueEnum, Debug, Clone, Copy)]
 enum ColorArg {
@@ -27,46 +44,751 @@ impl From<ColorArg> for ColorWhen {
     }
 }
 
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum TransportArg {
+    Udp,
+    Tcp,
+}
+
+impl From<TransportArg> for Transport {
+    fn from(v: TransportArg) -> Self {
+        match v {
+            TransportArg::Udp => Transport::Udp,
+            TransportArg::Tcp => Transport::Tcp,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum CatchUpArg {
+    Burst,
+    Spread,
+    Skip,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum VbrModeArg {
+    Vbr,
+    Cvbr,
+    Cbr,
+}
+
+impl From<VbrModeArg> for VbrMode {
+    fn from(v: VbrModeArg) -> Self {
+        match v {
+            VbrModeArg::Vbr => VbrMode::Vbr,
+            VbrModeArg::Cvbr => VbrMode::ConstrainedVbr,
+            VbrModeArg::Cbr => VbrMode::Cbr,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum NormalizeArg {
+    Peak,
+    Rms,
+}
+
+impl From<NormalizeArg> for NormalizeMode {
+    fn from(v: NormalizeArg) -> Self {
+        match v {
+            NormalizeArg::Peak => NormalizeMode::Peak,
+            NormalizeArg::Rms => NormalizeMode::Rms,
+        }
+    }
+}
+
+/// Parses `--gain-db`, which must lie within [`GAIN_DB_RANGE`].
+fn parse_gain_db(value: &str) -> Result<f64, String> {
+    // ---
+    let db: f64 = value.parse().map_err(|err| format!("{err}"))?;
+    if !GAIN_DB_RANGE.contains(&db) {
+        return Err(format!(
+            "{db} dB is out of range ({} to {})",
+            GAIN_DB_RANGE.start(),
+            GAIN_DB_RANGE.end()
+        ));
+    }
+    Ok(db)
+}
+
+/// Gains accepted by `--gain-db`
+const GAIN_DB_RANGE: std::ops::RangeInclusive<f64> = -30.0..=20.0;
+
+/// Parses a time given as seconds (`90`, `1.5`), `mm:ss`, or `hh:mm:ss`;
+/// the last field may have a fraction.
+fn parse_time_secs(value: &str) -> Result<f64, String> {
+    // ---
+    let fields: Vec<&str> = value.split(':').collect();
+    if fields.len() > 3 {
+        return Err(format!("{value:?} is not seconds, mm:ss, or hh:mm:ss"));
+    }
+    let (last, whole) = fields.split_last().expect("split yields a field");
+    let mut secs: f64 = last.parse().map_err(|err| format!("{value:?}: {err}"))?;
+    if !whole.is_empty() && !(0.0..60.0).contains(&secs) {
+        return Err(format!("{value:?}: seconds must be below 60"));
+    }
+    for (place, field) in whole.iter().rev().enumerate() {
+        let units: u64 = field.parse().map_err(|err| format!("{value:?}: {err}"))?;
+        if place == 0 && fields.len() == 3 && units >= 60 {
+            return Err(format!("{value:?}: minutes must be below 60"));
+        }
+        secs += units as f64 * 60f64.powi(place as i32 + 1);
+    }
+    if !(0.0..MAX_TIME_SECS).contains(&secs) {
+        return Err(format!("{value:?} is not a time"));
+    }
+    Ok(secs)
+}
+
+/// Longest time `--start` and `--duration` accept (over 11 days)
+const MAX_TIME_SECS: f64 = 1e6;
+
+/// Parses `--highpass`, which must lie within [`HIGHPASS_HZ_RANGE`].
+fn parse_highpass_hz(value: &str) -> Result<f64, String> {
+    // ---
+    let hz: f64 = value.parse().map_err(|err| format!("{err}"))?;
+    if !HIGHPASS_HZ_RANGE.contains(&hz) {
+        return Err(format!(
+            "{hz} Hz is out of range ({} to {})",
+            HIGHPASS_HZ_RANGE.start(),
+            HIGHPASS_HZ_RANGE.end()
+        ));
+    }
+    Ok(hz)
+}
+
+/// Parses `--dns-refresh`: seconds, optionally suffixed with `s`, of at
+/// least [`MIN_DNS_REFRESH_SECS`].
+fn parse_dns_refresh(value: &str) -> Result<Duration, String> {
+    // ---
+    let secs: u64 = value
+        .strip_suffix('s')
+        .unwrap_or(value)
+        .parse()
+        .map_err(|err| format!("{value:?}: {err}"))?;
+    if secs < MIN_DNS_REFRESH_SECS {
+        return Err(format!(
+            "{secs}s is too often (at least {MIN_DNS_REFRESH_SECS}s)"
+        ));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Shortest interval `--dns-refresh` accepts
+const MIN_DNS_REFRESH_SECS: u64 = 1;
+
+/// Cutoffs accepted by `--highpass`
+const HIGHPASS_HZ_RANGE: std::ops::RangeInclusive<f64> = 20.0..=1000.0;
+
+/// Warns if `--gain-db` clipped samples of streamed input.
+fn warn_clipped(clipped: u64, gain_db: f64) {
+    // ---
+    if clipped > 0 {
+        warn!("Gain of {gain_db:+.1} dB clipped {clipped} samples");
+    }
+}
+
+/// Reads the files listed in a `--playlist`: one path per line, blank
+/// lines and lines starting with `#` skipped, relative paths taken from
+/// the playlist's directory.
+fn read_playlist(path: &std::path::Path) -> Result<Vec<String>> {
+    // ---
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read playlist {}", path.display()))?;
+    let dir = path.parent().unwrap_or(std::path::Path::new(""));
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| dir.join(line).to_string_lossy().into_owned())
+        .collect())
+}
+
+/// Audio loaded from `--input`
+enum Input {
+    // ---
+    /// PCM to encode
+    Pcm(AudioData),
+
+    /// PCM of several files to encode back to back
+    Playlist(Vec<AudioData>),
+
+    /// WAV file read and encoded as it is sent
+    Wav(Box<WavFrameReader>),
+
+    /// Opus packets to send as they are
+    Opus(OggOpusFile),
+
+    /// Raw PCM read from stdin as it arrives
+    Stdin,
+
+    /// Test signal generated as it is sent
+    Signal(SignalGenerator),
+
+    /// Captured packets to send again as recorded
+    Replay(RtpdumpFile),
+}
+
 /// RTP Opus Sender - Stream audio files over RTP
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     // ---
-    /// Input audio file (WAV format)
+    /// Input audio file (WAV, FLAC, MP3, or Ogg Opus) or test signal
     #[arg(
         short,
         long,
-        help = "Input audio file (WAV format)",
-        long_help = "Path to an input WAV file to be streamed over RTP.\n\n\
-                     The file is decoded, packetized, and transmitted in real time."
+        required_unless_present_any = ["replay", "playlist"],
+        help = "Input audio file (WAV, FLAC, MP3, or Ogg Opus), -, or a test signal",
+        long_help = "Path to an input WAV, FLAC, MP3, or Ogg Opus file to be streamed over RTP.\n\n\
+                     WAV, FLAC, and MP3 are decoded, encoded to Opus, packetized, and\n\
+                     transmitted in real time; the format is detected from the file header.\n\
+                     Use - to read raw interleaved s16le PCM from stdin (see --stdin-rate\n\
+                     and --stdin-channels); it is streamed as it arrives and the sender\n\
+                     exits at EOF.\n\
+                     tone[:HZ], sweep[:START-END], or noise generates a test signal\n\
+                     instead (default 440 Hz tone, 50-7000 Hz logarithmic sweep, white\n\
+                     noise at -6 dBFS peak); see --duration. A file of the same name\n\
+                     takes precedence.\n\
+                     An Ogg Opus file (.opus/.ogg/.oga, or any file starting with OggS)\n\
+                     is sent packet for packet without re-encoding; the encoder options\n\
+                     (--bitrate, --frame-ms, --vbr-mode, --fec, --ramp-from) then do\n\
+                     not apply and --interval-ms defaults to the file's packet duration.\n\
+                     Repeat it to stream WAV, FLAC, or MP3 files back to back (see\n\
+                     --playlist)."
+    )]
+    input: Vec<String>,
+
+    /// File listing inputs to stream back to back
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Stream the files listed in FILE back to back, after any --input",
+        long_help = "Stream the WAV, FLAC, and MP3 files listed in FILE, one path per line,\n\
+                     back to back as one stream, after any given with --input. Blank\n\
+                     lines and lines starting with # are skipped, and relative paths are\n\
+                     taken from the playlist's directory. Each file is converted on its\n\
+                     own and decoded before streaming starts; sequence numbers and\n\
+                     timestamps run on across files, so the receiver sees one\n\
+                     uninterrupted session. Looping (--no-loop, --loop-count) applies\n\
+                     to the whole playlist, and --start and --duration to each file."
+    )]
+    playlist: Option<std::path::PathBuf>,
+
+    /// Capture to replay instead of encoding input
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["input", "playlist"],
+        help = "Send the packets of an rtpdump capture with their original timing",
+        long_help = "Replay an rtpdump file, as written by receiver --rtpdump-out or\n\
+                     rtpdump, instead of encoding --input. Each packet is sent as\n\
+                     recorded (SSRC, sequence number, and timestamp included) and as\n\
+                     long after the first as it arrived after it, so the receiver sees\n\
+                     the same loss, reordering, and timing; with --interval-ms 0 they go\n\
+                     out back to back. The capture plays once. Encoder, input, and\n\
+                     packet shaping options do not apply. A capture of an SRTP stream\n\
+                     is sent still protected: leave out --srtp-key here and give the\n\
+                     receiver the original key."
+    )]
+    replay: Option<std::path::PathBuf>,
+
+    /// Sample rate of PCM read from stdin
+    #[arg(
+        long,
+        default_value_t = sender::codec::SAMPLE_RATE,
+        value_parser = clap::value_parser!(u32).range(1..=sender::source::MAX_STREAM_RATE as i64),
+        help = "Sample rate of raw PCM read with --input -",
+        long_help = "Sample rate in Hz of the s16le PCM read from stdin with --input -.\n\n\
+                     Input at other rates than 16000 is resampled as it is read, e.g.\n\
+                     ffmpeg -i talk.mkv -f s16le -ar 48000 -ac 2 - | sender --input - \\\n\
+                     --stdin-rate 48000 --stdin-channels 2"
+    )]
+    stdin_rate: u32,
+
+    /// Channels of PCM read from stdin
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u16).range(1..=sender::source::MAX_STREAM_CHANNELS as i64),
+        help = "Interleaved channels of raw PCM read with --input -",
+        long_help = "Number of interleaved channels in the s16le PCM read from stdin with\n\
+                     --input -. Channels are averaged down to mono."
+    )]
+    stdin_channels: u16,
+
+    /// Channels encoded from multichannel input
+    #[arg(
+        long,
+        default_value = "mix",
+        value_name = "MAP",
+        help = "Channels to encode: mix, left, right, or channel:N",
+        long_help = "Which input channels make up the mono signal that is encoded.\n\n\
+                     mix: Average of all channels (default).\n\
+                     left, right: The first or second channel only.\n\
+                     channel:N: Channel N only, counted from 0.\n\
+                     Useful when one channel of a recording is mostly noise. Applies to\n\
+                     file and stdin input; the sender exits with an error if the input\n\
+                     has no such channel."
+    )]
+    channel: ChannelMap,
+
+    /// Gain applied to the input before encoding
+    #[arg(
+        long,
+        default_value_t = 0.0,
+        allow_hyphen_values = true,
+        value_parser = parse_gain_db,
+        value_name = "DB",
+        help = "Gain in dB applied before encoding (-30 to +20)",
+        long_help = "Gain in dB applied to the converted 16kHz mono input before encoding\n\
+                     (-30 to +20), e.g. 12 to boost a quiet test file.\n\n\
+                     Samples pushed past full scale clip; the number clipped is logged\n\
+                     as a warning. A test signal's peak is capped at full scale instead.\n\
+                     Does not apply to Ogg Opus input."
+    )]
+    gain_db: f64,
+
+    /// Remove low-frequency rumble before encoding
+    #[arg(
+        long,
+        value_name = "HZ",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "80",
+        value_parser = parse_highpass_hz,
+        help = "High-pass filter the input below HZ (default 80) before encoding",
+        long_help = "Filter out rumble below HZ (20 to 1000, 80 if only --highpass is\n\
+                     given) before encoding, so Opus does not spend bits on handling\n\
+                     noise and fans. Fourth-order Butterworth (24 dB per octave),\n\
+                     applied to every frame of file, stdin, and signal input after\n\
+                     resampling. Does not apply to Ogg Opus input."
+    )]
+    highpass: Option<f64>,
+
+    /// Bring the input to a fixed level
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        conflicts_with = "gain_db",
+        help = "Normalize the input level: peak or rms (see --normalize-target-dbfs)",
+        long_help = "Bring the input to --normalize-target-dbfs before encoding, so fixtures\n\
+                     recorded at different levels sound alike.\n\n\
+                     peak: Set the highest sample to the target.\n\
+                     rms: Set the average (RMS) level to the target; loud peaks may clip.\n\
+                     A file is measured in a pass before streaming. Stdin input cannot\n\
+                     be measured ahead, so an automatic gain control steers each 20ms\n\
+                     frame to the target instead (fast attack, 500ms release, frames\n\
+                     below -50 dBFS hold the gain). Gain is limited to +30 dB."
+    )]
+    normalize: Option<NormalizeArg>,
+
+    /// Level --normalize brings the input to
+    #[arg(
+        long,
+        default_value_t = -3.0,
+        allow_hyphen_values = true,
+        value_name = "DBFS",
+        help = "Target level in dBFS for --normalize",
+        long_help = "Level in dBFS that --normalize brings the peak or RMS level to."
+    )]
+    normalize_target_dbfs: f64,
+
+    /// Trim leading and trailing silence from file input
+    #[arg(
+        long,
+        value_name = "THRESHOLD_DB",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "-50",
+        allow_hyphen_values = true,
+        help = "Trim silence quieter than THRESHOLD_DB (default -50 dBFS) from both ends of file input",
+        long_help = "Trim leading and trailing silence from file input before streaming: audio\n\
+                     before the first and after the last sample above THRESHOLD_DB dBFS\n\
+                     (default -50, e.g. --trim-silence=-40) is dropped, as long as it lasts\n\
+                     at least --trim-min-silence-ms. Pauses inside the audio are kept.\n\
+                     Trimming runs after conversion to 16kHz mono and before --gain-db and\n\
+                     --normalize, and applies to each file of a playlist; a WAV file is\n\
+                     then decoded before streaming instead of read from disk as it goes.\n\
+                     Does not apply to stdin, test signal, or Ogg Opus input."
+    )]
+    trim_silence: Option<f64>,
+
+    /// Shortest silence --trim-silence trims
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = sender::audio::DEFAULT_TRIM_MIN_SILENCE.as_millis() as u64,
+        help = "Keep silence at either end shorter than MS with --trim-silence",
+        long_help = "Silence at the start or end of the input shorter than this is kept by\n\
+                     --trim-silence, so a brief pause before or after speech stays."
     )]
-    input: String,
+    trim_min_silence_ms: u64,
 
-    /// Remote address (IP:port) to send to
+    /// Where in the input file to start
+    #[arg(
+        long,
+        value_name = "TIME",
+        value_parser = parse_time_secs,
+        help = "Start this far into the input file (seconds, mm:ss, or hh:mm:ss)",
+        long_help = "Skip this much of the input file before streaming; with looping, each\n\
+                     pass starts here again. Given as seconds (90 or 1.5), mm:ss, or\n\
+                     hh:mm:ss. A start past the end of the file is an error. Does not\n\
+                     apply to stdin, test signal, or Ogg Opus input."
+    )]
+    start: Option<f64>,
+
+    /// Length of the file window or generated test signal
+    #[arg(
+        long,
+        value_name = "TIME",
+        value_parser = parse_time_secs,
+        help = "Stream only this much of the input (seconds, mm:ss, or hh:mm:ss)",
+        long_help = "For a file, stream at most this much from --start; a window running\n\
+                     past the end of the file stops at the end. With looping, the\n\
+                     window is what repeats.\n\n\
+                     For a test signal given as --input (tone, sweep, or noise), the\n\
+                     length of the signal; a sweep covers its range over exactly this\n\
+                     time. Without it the signal plays until the sender is stopped, or\n\
+                     for one 10s pass with --no-loop.\n\n\
+                     Given as seconds (20 or 2.5), mm:ss, or hh:mm:ss. Does not apply\n\
+                     to stdin or Ogg Opus input."
+    )]
+    duration: Option<f64>,
+
+    /// Remote addresses (IP:port) to send to
     #[arg(
         short,
         long,
         default_value = "127.0.0.1:5004",
-        help = "Remote address (IP:port) to send to",
+        help = "Remote address (IP:port) to send to; repeat to send to several",
         long_help = "Remote address of the RTP receiver.\n\n\
-                     The sender transmits RTP packets to this address."
+                     The sender transmits RTP packets to this address. An IPv4\n\
+                     multicast group (e.g. 239.1.2.3:5004) feeds every receiver that\n\
+                     joined it; see --mcast-ttl and --mcast-if.\n\n\
+                     Repeat the option (up to 16 times) to duplicate every packet to\n\
+                     each address, e.g. to compare receivers side by side. A\n\
+                     destination that keeps failing is dropped without affecting the\n\
+                     others."
+    )]
+    remote: Vec<String>,
+
+    /// Port to wait on for the receiver
+    #[arg(
+        long,
+        value_name = "PORT",
+        conflicts_with_all = ["remote", "pcap_only"],
+        help = "Wait for a hello on PORT and stream back to where it came from",
+        long_help = "Symmetric RTP server mode: bind PORT (on the --local-addr IP if\n\
+                     given), wait for any UDP datagram, and stream to its source\n\
+                     address instead of a --remote. The receiver's --punch sends that\n\
+                     hello, and keepalives after it, from its RTP socket, so a\n\
+                     receiver behind NAT is reached at its public address. Later\n\
+                     datagrams from other addresses are ignored unless --allow-switch.\n\
+                     UDP only."
+    )]
+    listen: Option<u16>,
+
+    /// Follow the receiver to a new address
+    #[arg(
+        long,
+        requires = "listen",
+        help = "With --listen, move the stream to a new source address",
+        long_help = "With --listen, datagrams that start coming from an address other\n\
+                     than the first hello's (e.g. after the receiver's NAT rebinds)\n\
+                     move the stream there. Without it they are ignored, so nobody\n\
+                     else can redirect the stream."
+    )]
+    allow_switch: bool,
+
+    /// Transport to send over
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = TransportArg::Udp,
+        help = "Send over UDP or TCP",
+        long_help = "Transport for the RTP packets.\n\n\
+                     udp: One packet per datagram (the default).\n\
+                     tcp: Length-prefixed packets (RFC 4571) over a TCP connection to\n\
+                     each --remote, for networks that block UDP. The receiver must use\n\
+                     --transport tcp too. A lost connection is made again, at most\n\
+                     every 500ms; packets sent meanwhile count as failed. No multicast."
+    )]
+    transport: TransportArg,
+
+    /// SRTP master key and salt, base64
+    #[arg(
+        long,
+        value_name = "BASE64",
+        value_parser = SrtpKey::from_base64,
+        help = "Encrypt and authenticate packets with SRTP under this key",
+        long_help = "Protect every packet with SRTP (RFC 3711, AES_CM_128_HMAC_SHA1_80):
+                     the payload is encrypted and a 10-byte authentication tag is
+                     appended. The key is the 16-byte master key followed by the
+                     14-byte master salt, base64 encoded (as in an SDES inline: key);
+                     the receiver must be given the same one. Decoder refresh requests
+                     from the receiver are ignored, since they are not authenticated."
+    )]
+    srtp_key: Option<SrtpKey>,
+
+    /// Capture file to write
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Also record every RTP packet to FILE as a pcap capture",
+        long_help = "Write every packet sent to a libpcap capture, wrapped in synthetic\n\
+                     IPv4/UDP headers (link type raw IP) and stamped with the time the\n\
+                     pacer released it, for offline analysis in Wireshark (Telephony >\n\
+                     RTP). The packets are recorded from --local-addr (or the sending\n\
+                     socket) to the first --remote, using loopback where those are not\n\
+                     IPv4 addresses. With --srtp-key, the capture holds the packets\n\
+                     before protection."
+    )]
+    pcap_out: Option<std::path::PathBuf>,
+
+    /// Capture instead of sending
+    #[arg(
+        long,
+        requires = "pcap_out",
+        help = "Write the --pcap-out capture without sending anything",
+        long_help = "Record the stream to --pcap-out only; no socket is opened and\n\
+                     nothing goes out on the network. The stream is still paced in\n\
+                     real time unless --interval-ms 0, which stamps packets as fast\n\
+                     as they are encoded."
+    )]
+    pcap_only: bool,
+
+    /// Multicast TTL
+    #[arg(
+        long,
+        default_value_t = sender::network::DEFAULT_MULTICAST_TTL,
+        value_parser = clap::value_parser!(u32).range(0..=255),
+        value_name = "HOPS",
+        help = "Router hops multicast packets may cross (0 = this host only)",
+        long_help = "IP_MULTICAST_TTL for a multicast --remote: how many routers the\n\
+                     packets may cross. 0 keeps them on this host, 1 (the default) on\n\
+                     the local subnet. Ignored for unicast."
+    )]
+    mcast_ttl: u32,
+
+    /// Interface to send multicast from
+    #[arg(
+        long,
+        value_name = "ADDR",
+        help = "Send multicast from the interface with this IPv4 address",
+        long_help = "Outgoing interface for a multicast --remote, given by its IPv4\n\
+                     address. By default the routing table decides. Ignored for unicast."
+    )]
+    mcast_if: Option<Ipv4Addr>,
+
+    /// Local address to send from
+    #[arg(
+        long,
+        value_name = "IP:PORT",
+        help = "Send from this local address and port",
+        long_help = "Local address and port to bind the sending socket to, e.g.\n\
+                     192.168.1.10:5002. Picks the interface on a multi-homed host,\n\
+                     or a fixed source port for firewalls that expect one. Port 0\n\
+                     takes any free port. By default the socket binds every\n\
+                     interface and an ephemeral port."
+    )]
+    local_addr: Option<SocketAddr>,
+
+    /// Interval for resolving host names again
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = parse_dns_refresh,
+        help = "Resolve --remote host names again every SECS seconds (e.g. 60s)",
+        long_help = "Look up --remote host names again periodically, so a long-running\n\
+                     stream follows DNS changes. A changed address is used from the\n\
+                     next packet on, with no gap in sequence numbers; a failed lookup\n\
+                     keeps the last good address with a warning. By default names are\n\
+                     resolved once at startup."
+    )]
+    dns_refresh: Option<Duration>,
+
+    /// Socket send buffer size
+    #[arg(
+        long,
+        value_name = "BYTES",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Request a socket send buffer (SO_SNDBUF) of this many bytes",
+        long_help = "Size of the socket send buffer to request. A larger buffer holds\n\
+                     bursts (catch-up after a stall, --batch) that the default may\n\
+                     drop. The kernel may clamp the size (net.core.wmem_max on Linux);\n\
+                     the size granted is logged. By default the system default is kept."
+    )]
+    so_sndbuf: Option<u32>,
+
+    /// Bandwidth cap
+    #[arg(
+        long,
+        value_name = "KBPS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Cap outgoing bandwidth at KBPS kilobits per second",
+        long_help = "Cap the bytes sent per second (RTP headers and payloads, summed\n\
+                     over every --remote) with a token bucket. Packets that would go\n\
+                     over the cap wait for it, so a cap below the stream's bitrate\n\
+                     slows the stream and paced streams fall behind (see --catch-up).\n\
+                     A stream under the cap is not delayed at all. Throttled packets\n\
+                     and time are exported as rtp_packets_rate_limited_total and\n\
+                     rtp_rate_limit_wait_seconds_total. By default there is no cap."
+    )]
+    max_kbps: Option<u64>,
+
+    /// Burst allowed by the bandwidth cap
+    #[arg(
+        long,
+        value_name = "BYTES",
+        requires = "max_kbps",
+        default_value_t = sender::ratelimit::DEFAULT_BURST_BYTES,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Bytes --max-kbps lets through back to back",
+        long_help = "Token bucket size for --max-kbps: how many bytes may go out back\n\
+                     to back after the stream has stayed under the cap. Raise it when\n\
+                     batching (--batch) or catching up, so bursts are not split."
+    )]
+    max_burst_bytes: u64,
+
+    /// Packets kept for retransmission
+    #[arg(
+        long,
+        value_name = "PACKETS",
+        default_value_t = sender::rtx::DEFAULT_RTX_CACHE_PACKETS as u64,
+        value_parser = clap::value_parser!(u64).range(0..=sender::rtx::MAX_RTX_CACHE_PACKETS as u64),
+        help = "Recent packets kept to retransmit on the receiver's NACK (0 = off)",
+        long_help = "Keep the last PACKETS packets sent (rounded up to a power of two)\n\
+                     so the receiver's Generic NACKs (receiver --nack) can be answered:\n\
+                     a NACKed packet still kept goes out again unchanged, same SSRC and\n\
+                     sequence number, before the next frame. 0 ignores NACKs. Off with\n\
+                     --srtp-key, since NACKs are not authenticated."
+    )]
+    rtx_cache: u64,
+
+    /// Idle time before a NAT keepalive
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = sender::network::DEFAULT_KEEPALIVE_INTERVAL.as_secs(),
+        help = "Send a keepalive after SECS seconds without a packet (0 = off)",
+        long_help = "When no packet has gone out for SECS seconds, e.g. through a long\n\
+                     --loop-gap-ms with --loop-gap-skip, send an empty UDP datagram\n\
+                     (RFC 6263) so NAT bindings on the path do not expire, and again\n\
+                     every SECS seconds until the audio resumes. Keepalives carry no\n\
+                     sequence number; the receiver discards them. Not sent over TCP.\n\
+                     0 sends none."
+    )]
+    keepalive: u64,
+
+    /// Time between RTCP sender reports
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = sender::network::DEFAULT_SENDER_REPORT_INTERVAL.as_secs(),
+        help = "Send an RTCP sender report every SECS seconds (0 = off)",
+        long_help = "While streaming, send an RTCP sender report (RFC 3550) every SECS\n\
+                     seconds on the RTP socket (rtcp-mux; see --no-rtcp-mux), giving\n\
+                     the receiver the packet and octet counts sent and the mapping of\n\
+                     RTP timestamps to wallclock time. Not sent over TCP or with\n\
+                     --srtp-key. 0 sends none."
+    )]
+    rtcp_sr_interval: u64,
+
+    /// Canonical name sent with sender reports
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Name the stream by NAME in RTCP SDES (default: user@host)",
+        long_help = "Canonical name (RFC 3550 CNAME) sent in an SDES packet with every\n\
+                     sender report, so receivers can tell streams apart beyond their\n\
+                     random SSRC. Defaults to user@host from the environment. Sent\n\
+                     only with sender reports (see --rtcp-sr-interval)."
+    )]
+    cname: Option<String>,
+
+    /// RTCP on a port of its own
+    #[arg(
+        long,
+        conflicts_with = "listen",
+        help = "Send RTCP from PORT+1 to each destination's port + 1",
+        long_help = "Bind a second UDP socket on the port after the RTP socket's for\n\
+                     RTCP, as RFC 3550 has it, and send sender reports to the port\n\
+                     after each destination's, taking feedback there too, rather than\n\
+                     sharing the RTP socket (rtcp-mux, RFC 5761). Receivers must run\n\
+                     with --no-rtcp-mux too. UDP only, and not with SRTP or --listen."
+    )]
+    no_rtcp_mux: bool,
+
+    /// DiffServ code point for outgoing packets
+    #[arg(
+        long,
+        value_name = "DSCP",
+        value_parser = sender::network::parse_dscp,
+        help = "Mark packets with this DSCP (e.g. EF, AF41, or 0-63)",
+        long_help = "DiffServ code point to set in the IP header of every packet, so\n\
+                     networks that prioritize marked traffic favor the audio. Takes a\n\
+                     name (EF, AF11-AF43, CS0-CS7, VA) or a number from 0 to 63.\n\
+                     By default packets carry the system default (0). If the OS\n\
+                     refuses the marking, a warning is logged and packets go out\n\
+                     unmarked."
     )]
-    remote: String,
+    dscp: Option<u8>,
 
     /// Packet transmission interval in milliseconds
     ///
-    /// Controls pacing of packet transmission. Default 20ms matches
-    /// the frame duration for real-time streaming.
+    /// Controls pacing of packet transmission. Defaults to the frame
+    /// duration for real-time streaming.
     #[arg(
         short = 't',
         long,
-        default_value_t = 20,
-        help = "Packet transmission interval in milliseconds",
+        help = "Packet transmission interval in milliseconds (default: --frame-ms)",
         long_help = "Packet transmission interval in milliseconds.\n\n\
                      Controls the pacing of RTP packet transmission.\n\
-                     The default of 20ms matches typical Opus frame duration."
+                     Defaults to the frame duration, which is real time (for Ogg Opus\n\
+                     input, the duration of the file's packets)."
+    )]
+    interval_ms: Option<u64>,
+
+    /// Packets handed to the network per system call
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..=sender::network::MAX_BATCH as u64),
+        help = "Send N packets per system call (default: 32 with --interval-ms 0, else 1)",
+        long_help = "Queue N packets and hand them to the network together (sendmmsg on\n\
+                     Linux), for throughput when streaming faster than real time. On by\n\
+                     default with --interval-ms 0; 1 sends every packet on its own. With\n\
+                     pacing, packets wait for their batch and go out in bursts."
+    )]
+    batch: Option<u64>,
+
+    /// How overdue frames are sent after a stall
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = CatchUpArg::Burst,
+        help = "How overdue frames are sent after a stall",
+        long_help = "Policy for frames that became overdue while the sender was stalled\n\
+                     (page fault, CPU contention).\n\n\
+                     burst: Send them back-to-back as soon as possible.\n\
+                     spread: Send them faster than real time, evenly spaced over\n\
+                     --spread-over-ms, until back on schedule.\n\
+                     skip: Drop them (with a warning) to stay aligned with real time."
+    )]
+    catch_up: CatchUpArg,
+
+    /// Catch-up window for the spread policy
+    #[arg(
+        long,
+        default_value_t = 200,
+        help = "Catch-up window in milliseconds for --catch-up spread",
+        long_help = "With --catch-up spread, overdue frames are spread so the stream is\n\
+                     back on schedule after this many milliseconds."
     )]
-    interval_ms: u64,
+    spread_over_ms: u64,
 
     #[arg(
         long = "no-loop",
@@ -77,6 +799,350 @@ struct Args {
     )]
     no_loop: bool,
 
+    /// Number of passes when looping
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_name = "N",
+        conflicts_with = "no_loop",
+        help = "Play the input file N times and exit (0 = loop until stopped)",
+        long_help = "Stop after N passes through the input file instead of looping until\n\
+                     stopped. Sequence numbers and timestamps run on across passes, as\n\
+                     when looping forever. 0 loops until stopped. Applies to PCM file\n\
+                     and Ogg Opus input, not to stdin or a generated signal."
+    )]
+    loop_count: u64,
+
+    /// Fade at the loop point, in milliseconds
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u64).range(0..=1000),
+        value_name = "MS",
+        help = "Fade in and out over this many milliseconds at the loop point (0 = off)",
+        long_help = "When looping a PCM file, fade the first and last MS milliseconds of\n\
+                     every pass so the jump from the end back to the start does not\n\
+                     click. Fading happens before encoding; the frame count, sequence\n\
+                     numbers, and timestamps are unchanged. Does not apply with\n\
+                     --no-loop or to stdin, generated signal, or Ogg Opus input."
+    )]
+    loop_fade_ms: u64,
+
+    /// Silence between loop passes, in milliseconds
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u64).range(0..=60_000),
+        value_name = "MS",
+        help = "Play this many milliseconds of silence between loop passes (0 = off)",
+        long_help = "When looping a PCM file, pause for MS milliseconds (rounded up to\n\
+                     whole frames) after every pass, so passes can be told apart. The\n\
+                     silence is encoded and sent as normal frames unless --loop-gap-skip\n\
+                     is set. Does not apply with --no-loop or to stdin, generated\n\
+                     signal, or Ogg Opus input."
+    )]
+    loop_gap_ms: u64,
+
+    /// Send nothing during the loop gap
+    #[arg(
+        long,
+        help = "Send no packets during --loop-gap-ms; the timestamp jumps instead",
+        long_help = "Send no packets during the --loop-gap-ms pause instead of encoded\n\
+                     silence. The RTP timestamp jumps by the gap, sequence numbers stay\n\
+                     contiguous, and the first packet of the next pass carries the\n\
+                     marker bit as the start of a talkspurt."
+    )]
+    loop_gap_skip: bool,
+
+    /// Pad the trailing partial frame with silence instead of dropping it
+    #[arg(
+        long,
+        help = "Send the trailing partial frame padded with silence instead of dropping it",
+        long_help = "Input that does not end on a frame boundary leaves up to one frame\n\
+                     (19.9ms at 20ms frames) of samples at the end. By default they are\n\
+                     dropped; with this flag they are padded with silence and sent as a\n\
+                     full frame, on every pass when looping. Applies to PCM file and\n\
+                     stdin input."
+    )]
+    pad_tail: bool,
+
+    /// Opus target bitrate in bits per second
+    #[arg(
+        long,
+        default_value_t = sender::codec::BITRATE,
+        help = "Opus target bitrate in bits per second",
+        long_help = "Opus encoder target bitrate in bits per second (6000 to 510000).\n\n\
+                     The default of 24000 suits voice; lower values (e.g. 12000) fit\n\
+                     constrained links, higher values (e.g. 64000) improve fidelity."
+    )]
+    bitrate: i32,
+
+    /// Opus frame duration in milliseconds
+    #[arg(
+        long,
+        default_value_t = sender::codec::FRAME_DURATION_MS,
+        help = "Opus frame duration in milliseconds (10, 20, 40, or 60)",
+        long_help = "Duration of audio carried in each Opus frame and RTP packet.\n\n\
+                     20ms is the usual choice for voice. 10ms lowers latency at the cost\n\
+                     of more packets; 40ms and 60ms cut the packet rate and header\n\
+                     overhead on constrained links at the cost of added delay."
+    )]
+    frame_ms: usize,
+
+    /// Encoder rate control mode
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = VbrModeArg::Cvbr,
+        help = "Encoder rate control: vbr, cvbr, or cbr",
+        long_help = "How the encoder spends the --bitrate budget from frame to frame.\n\n\
+                     vbr: Unconstrained variable bitrate; frame sizes follow the signal.\n\
+                     cvbr: Constrained VBR (default); sizes vary within a small bound.\n\
+                     cbr: Constant bitrate; every frame is the same size, which suits\n\
+                     links provisioned for a fixed rate and hides speech activity."
+    )]
+    vbr_mode: VbrModeArg,
+
+    /// Bitrate to start from and ramp up to --bitrate
+    #[arg(
+        long,
+        value_name = "BPS",
+        help = "Trickle start: begin at this bitrate and ramp up to --bitrate",
+        long_help = "Trickle start floor in bits per second (e.g. 12000).\n\n\
+                     The stream starts at this bitrate and climbs to --bitrate in steps\n\
+                     over --ramp-secs, so a path that cannot carry the full rate shows\n\
+                     loss first. A decoder refresh request from the receiver mid-ramp\n\
+                     holds the bitrate where it is. The ramp starts over after a pause\n\
+                     in transmission of --ramp-restart-secs."
+    )]
+    ramp_from: Option<i32>,
+
+    /// Time taken to ramp from --ramp-from to --bitrate
+    #[arg(
+        long,
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds to ramp from --ramp-from to --bitrate",
+        long_help = "Duration of the trickle start ramp in seconds.\n\n\
+                     The bitrate is raised every 500ms in equal steps."
+    )]
+    ramp_secs: u64,
+
+    /// Transmission pause that restarts the ramp
+    #[arg(
+        long,
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds without sending after which the ramp starts over",
+        long_help = "With --ramp-from, a gap of this many seconds between sent packets\n\
+                     (a stall, or a long DTX silence) restarts the ramp from the floor,\n\
+                     since the path may have changed in the meantime."
+    )]
+    ramp_restart_secs: u64,
+
+    /// Enable Opus in-band forward error correction
+    #[arg(
+        long,
+        help = "Enable Opus in-band forward error correction",
+        long_help = "Embed a low-bitrate copy of each frame (LBRR) in the next packet.\n\n\
+                     A receiver that loses a single packet rebuilds it from the one that\n\
+                     follows instead of concealing it. Costs extra bitrate on speech;\n\
+                     how much is set by --expected-loss."
+    )]
+    fec: bool,
+
+    /// Packet loss the encoder plans for, in percent
+    #[arg(
+        long,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        help = "Packet loss the encoder plans for, in percent",
+        long_help = "Expected packet loss on the path, in percent (0 to 100).\n\n\
+                     With --fec this sets how much redundancy the encoder spends; Opus\n\
+                     emits no FEC while it is 0. Also makes the encoder rely less on\n\
+                     inter-frame prediction."
+    )]
+    expected_loss: u8,
+
+    /// Adapt the bitrate and expected loss to receiver reports
+    #[arg(
+        long,
+        help = "Step the bitrate down on reported loss, and back up once it clears",
+        long_help = "Adapt the encoder to the loss receivers report in RTCP receiver\n\
+                     reports. When two reports in a row say more than --adapt-loss-pct\n\
+                     was lost, step the bitrate down (e.g. 24 -> 16 -> 12 kbps) and raise\n\
+                     the expected loss to what was reported, so --fec spends more on\n\
+                     redundancy; after --adapt-recover-secs of reports at or below\n\
+                     --adapt-clean-pct, step back up. UDP only, not with SRTP, and needs\n\
+                     the encoder."
+    )]
+    adapt: bool,
+
+    /// Lowest bitrate adaptation steps down to
+    #[arg(
+        long,
+        value_name = "BPS",
+        default_value_t = sender::adapt::DEFAULT_ADAPT_MIN_BITRATE,
+        help = "Lowest bitrate --adapt steps down to, in bits per second"
+    )]
+    adapt_min_bitrate: i32,
+
+    /// Highest bitrate adaptation steps up to
+    #[arg(
+        long,
+        value_name = "BPS",
+        help = "Highest bitrate --adapt steps up to, in bits per second (default: --bitrate)"
+    )]
+    adapt_max_bitrate: Option<i32>,
+
+    /// Loss that steps the bitrate down
+    #[arg(
+        long,
+        value_name = "PCT",
+        default_value_t = 5.0,
+        help = "Reported loss above which --adapt counts a report as lossy, in percent"
+    )]
+    adapt_loss_pct: f64,
+
+    /// Loss that counts towards stepping back up
+    #[arg(
+        long,
+        value_name = "PCT",
+        default_value_t = 1.0,
+        help = "Reported loss at or below which --adapt counts a report as clean, in percent"
+    )]
+    adapt_clean_pct: f64,
+
+    /// Clean time before stepping back up
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = sender::adapt::DEFAULT_ADAPT_RECOVER_AFTER.as_secs(),
+        help = "Seconds of clean reports before --adapt steps the bitrate back up"
+    )]
+    adapt_recover_secs: u64,
+
+    /// Stop transmitting during silence
+    #[arg(
+        long,
+        help = "Discontinuous transmission: stop sending during silence",
+        long_help = "Discontinuous transmission (DTX).\n\n\
+                     After 200ms of silence the encoder emits 1-byte DTX frames, which\n\
+                     are not sent; a full comfort-noise frame still goes out every\n\
+                     400ms. The RTP timestamp keeps advancing and the sequence number\n\
+                     does not, so the receiver sees the gap as silence, not loss."
+    )]
+    dtx: bool,
+
+    /// RTP payload type
+    #[arg(
+        long,
+        value_name = "PT",
+        default_value_t = rtp_opus_common::PAYLOAD_TYPE_OPUS,
+        value_parser = clap::value_parser!(u8).range(0..=127),
+        help = "RTP payload type of the Opus packets",
+        long_help = "Payload type (0-127) to send the Opus packets with, e.g. the one\n\
+                     negotiated in SDP (often 111). The receiver must decode this type\n\
+                     as Opus (receiver --opus-payload-type). A --replay keeps its\n\
+                     recorded payload types."
+    )]
+    payload_type: u8,
+
+    /// Header extension ID for abs-send-time
+    #[arg(
+        long,
+        value_name = "ID",
+        value_parser = clap::value_parser!(u8).range(1..=14),
+        conflicts_with = "replay",
+        help = "Stamp packets with abs-send-time in this header extension ID",
+        long_help = "Stamp every packet with its send time in an abs-send-time header\n\
+                     extension (RFC 8285 one-byte element with this ID, 1-14, as mapped\n\
+                     to http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time in\n\
+                     SDP). Adds 8 bytes to every packet. A receiver given the same\n\
+                     --abs-send-time-id follows the one-way delay from the stamps."
+    )]
+    abs_send_time_id: Option<u8>,
+
+    /// Header extension ID for transport-wide sequence numbers
+    #[arg(
+        long,
+        value_name = "ID",
+        value_parser = clap::value_parser!(u8).range(1..=14),
+        conflicts_with = "replay",
+        help = "Number packets with a transport-wide sequence number in this header extension ID",
+        long_help = "Number every packet with a transport-wide sequence number, a 16-bit\n\
+                     counter independent of the RTP sequence, in a header extension (RFC\n\
+                     8285 one-byte element with this ID, 1-14, as mapped to\n\
+                     http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01\n\
+                     in SDP). Shares the extension with --abs-send-time-id, which must use\n\
+                     a different ID."
+    )]
+    transport_cc_id: Option<u8>,
+
+    /// Pad every packet to a fixed size
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help = "Pad every packet to this many bytes on the wire",
+        long_help = "Pad every RTP packet (header, payload, and padding) to exactly this\n\
+                     many bytes using RTP padding, so packet size does not reveal speech\n\
+                     activity. Must be large enough for a frame at the encoder bitrate\n\
+                     (72 bytes at 24 kbps, plus 1 byte per 400 bps more) and at most\n\
+                     268 bytes."
+    )]
+    pad_packets_to: Option<usize>,
+
+    /// Append a CRC32 trailer to every payload
+    #[arg(
+        long,
+        help = "Append a CRC32 of each payload for the receiver to check",
+        long_help = "Payload verification mode; the receiver must also run with\n\
+                     --verify-payloads.\n\n\
+                     A 4-byte CRC32 of the Opus payload is appended to every packet so the\n\
+                     receiver can prove nothing on the path corrupted it. A receiver not\n\
+                     expecting the trailer would decode it as audio."
+    )]
+    verify_payloads: bool,
+
+    /// Level below which a frame counts as silent
+    #[arg(
+        long,
+        default_value_t = -50.0,
+        allow_hyphen_values = true,
+        help = "Level (dBFS) below which a frame counts as silent",
+        long_help = "RMS level in dBFS below which a 20ms frame counts as silent.\n\n\
+                     The input is checked for silence before streaming; warnings are\n\
+                     logged if most of the file is silent, if it contains a long silent\n\
+                     run, or if one channel of a multichannel file is muted."
+    )]
+    silence_threshold_dbfs: f64,
+
+    /// Interval between aggregated anomaly log lines
+    #[arg(
+        long,
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds between aggregated log lines for one kind of packet anomaly",
+        long_help = "Rate limit for packet anomaly logging.\n\n\
+                     The first send failure is logged in full; later ones are counted and\n\
+                     summarized at most once per interval (count and sequence range).\n\
+                     Suppressed occurrences are exported as anomalies_suppressed_total."
+    )]
+    anomaly_log_interval_secs: u64,
+
+    /// Send failures in a row before giving up
+    #[arg(
+        long,
+        default_value_t = sender::network::DEFAULT_MAX_CONSECUTIVE_FAILURES,
+        value_name = "N",
+        help = "Exit with an error after N send failures in a row (0 = never)",
+        long_help = "Give up when N packets in a row fail to send, e.g. because the route\n\
+                     to the receiver went away. Failures before that are logged and the\n\
+                     stream goes on. 0 keeps sending however long the failures last.\n\
+                     Failed packets are exported as rtp_packets_failed_total."
+    )]
+    max_send_failures: u32,
+
     /// Prometheus metrics bind address (serves `GET /metrics`).
     #[arg(
         long,
@@ -99,77 +1165,908 @@ struct Args {
                      never: Disable colors."
     )]
     color: ColorArg,
+
+    /// With --version, print the full build report
+    #[arg(
+        long,
+        help = "With --version, also print crate versions, git commit, and features",
+        long_help = "Use as --version --verbose to print the versions of all linked crates,\n\
+                     the git commit, and the Cargo features this binary was built with."
+    )]
+    verbose: bool,
+}
+
+/// Port recorded as the packets' source in a capture when there is no
+/// sending socket to take it from (even, as RTP ports are, and apart from
+/// the receiver's default)
+const CAPTURE_SOURCE_PORT: u16 = 5006;
+
+/// Returns the addresses a `--pcap-out` capture records packets between:
+/// from `--local-addr` or the sending socket, to the peer learned with
+/// `--listen` or the first `--remote`. Parts that are not IPv4 addresses
+/// are recorded as loopback.
+fn capture_addrs(args: &Args, sender: Option<&RtpSender>) -> (SocketAddrV4, SocketAddrV4) {
+    // ---
+    let local = args
+        .local_addr
+        .or_else(|| sender.and_then(|sender| sender.local_addr().ok()));
+    let source = match local {
+        Some(SocketAddr::V4(addr)) if !addr.ip().is_unspecified() => addr,
+        Some(addr) if addr.port() != 0 => SocketAddrV4::new(Ipv4Addr::LOCALHOST, addr.port()),
+        _ => SocketAddrV4::new(Ipv4Addr::LOCALHOST, CAPTURE_SOURCE_PORT),
+    };
+
+    if let Some(peer) = sender.and_then(RtpSender::peer_addr) {
+        let destination = match peer {
+            SocketAddr::V4(peer) => peer,
+            SocketAddr::V6(peer) => SocketAddrV4::new(Ipv4Addr::LOCALHOST, peer.port()),
+        };
+        return (source, destination);
+    }
+    let remote = &args.remote[0];
+    let destination = remote.parse::<SocketAddrV4>().unwrap_or_else(|_| {
+        let port = remote
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse().ok())
+            .unwrap_or(0);
+        warn!("Capture records packets to --remote {remote} as going to loopback");
+        SocketAddrV4::new(Ipv4Addr::LOCALHOST, port)
+    });
+    (source, destination)
+}
+
+/// Streams `input` into `sink` with the function for its kind.
+async fn run_stream<K: PacketSink>(
+    input: &mut Input,
+    encoder: &mut OpusEncoderWrapper,
+    sink: &mut K,
+    metrics: &MetricsContext,
+    ssrc: u32,
+    options: &StreamOptions,
+    args: &Args,
+) -> Result<StreamSummary> {
+    // ---
+    let summary = match input {
+        Input::Pcm(audio) => stream_audio(audio, encoder, sink, metrics, ssrc, options).await?,
+        Input::Playlist(files) => {
+            stream_playlist(files, encoder, sink, metrics, ssrc, options).await?
+        }
+        Input::Wav(reader) => {
+            let summary =
+                stream_frames(&mut **reader, encoder, sink, metrics, ssrc, options).await?;
+            warn_clipped(reader.clipped_samples(), args.gain_db);
+            summary
+        }
+        Input::Opus(file) => stream_ogg_opus(file, sink, metrics, ssrc, options).await?,
+        Input::Stdin => {
+            // ---
+            let mut source =
+                PcmStream::new(tokio::io::stdin(), args.stdin_rate, args.stdin_channels)?
+                    .with_channel_map(args.channel)?
+                    .with_gain_db(args.gain_db)
+                    .with_tail(options.tail);
+            let summary = stream_frames(&mut source, encoder, sink, metrics, ssrc, options).await?;
+            info!("Read {} bytes of PCM from stdin", source.bytes_read());
+            warn_clipped(source.clipped_samples(), args.gain_db);
+            summary
+        }
+        Input::Signal(generator) => {
+            stream_frames(generator, encoder, sink, metrics, ssrc, options).await?
+        }
+        Input::Replay(file) => stream_rtpdump(file, sink, metrics, options).await?,
+    };
+    Ok(summary)
 }
 
-/// Capture version number from Cargo.toml
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Tells the receivers the stream is over with an RTCP BYE, the last
+/// thing sent, saying whether it ended, was interrupted, or failed.
+async fn say_goodbye(
+    sender: &mut RtpSender,
+    result: &Result<StreamSummary>,
+    options: &StreamOptions,
+) {
+    // ---
+    let reason = match result {
+        Err(_) => "stream failed",
+        Ok(_)
+            if options
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| *cancel.borrow()) =>
+        {
+            "interrupted"
+        }
+        Ok(_) => "end of stream",
+    };
+    if sender.send_bye(reason).await {
+        info!("Sent RTCP BYE ({reason})");
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // ---
+    let build = sender::build_info();
+    handle_verbose_version(&build);
     let args = Args::parse();
 
     init_tracing(args.color.into())?;
 
-    info!("Starting RTP Opus sender v{VERSION}");
-    info!("Input file: {}", args.input);
-    info!("Remote address: {}", args.remote);
-    info!("Transmission interval: {}ms", args.interval_ms);
-    info!("Loop audio: {}", !args.no_loop);
+    // Clap makes sure there is an --input unless there is a --replay or
+    // a --playlist
+    let replay = args.replay.is_some();
+    let mut inputs = args.input.clone();
+    if let Some(path) = &args.playlist {
+        inputs.extend(read_playlist(path)?);
+    }
+    let playlist = inputs.len() > 1;
+    if playlist {
+        for path in &inputs {
+            if path == "-" || !std::path::Path::new(path).exists() {
+                anyhow::bail!("playlist entries must be files, {path} is not");
+            }
+            if sender::is_ogg_opus(path) {
+                anyhow::bail!("Ogg Opus input cannot be part of a playlist: {path}");
+            }
+        }
+    } else if args.playlist.is_some() && inputs.is_empty() {
+        anyhow::bail!("playlist lists no files");
+    }
+    let input = inputs.first().map(String::as_str).unwrap_or_default();
+    let stdin = input == "-";
+    let signal = if !replay && Signal::is_spec(input) && !std::path::Path::new(input).exists() {
+        Some(input.parse::<Signal>().context("invalid test signal")?)
+    } else {
+        None
+    };
+    let passthrough = !replay && !stdin && signal.is_none() && sender::is_ogg_opus(input);
+    // Packets that arrive coded, with no encoder to apply settings to
+    let coded = if replay {
+        Some("replayed")
+    } else if passthrough {
+        Some("Ogg Opus")
+    } else {
+        None
+    };
+    let generator = match (signal, args.duration) {
+        (Some(signal), duration) => {
+            let amplitude =
+                sender::audio::DEFAULT_SIGNAL_AMPLITUDE * 10f64.powf(args.gain_db / 20.0);
+            let mut generator = SignalGenerator::new(signal)
+                .with_amplitude(amplitude)
+                .looping(!args.no_loop);
+            if let Some(secs) = duration {
+                if secs <= 0.0 {
+                    anyhow::bail!("--duration must be a positive time, got {secs}s");
+                }
+                generator = generator
+                    .with_duration(Duration::from_secs_f64(secs))
+                    .looping(false);
+            }
+            Some(generator)
+        }
+        (None, _) => None,
+    };
+    let window = !stdin && coded.is_none() && generator.is_none();
+    if !window && (args.start.is_some() || (args.duration.is_some() && generator.is_none())) {
+        warn!("--start and --duration only apply to file input, ignoring them");
+    }
+    if window && args.duration == Some(0.0) {
+        anyhow::bail!("--duration must be a positive time, got 0s");
+    }
+    if args.transport_cc_id.is_some() && args.transport_cc_id == args.abs_send_time_id {
+        anyhow::bail!("--transport-cc-id and --abs-send-time-id must be different IDs");
+    }
+    let read = AudioReadOptions {
+        start: Duration::from_secs_f64(args.start.unwrap_or(0.0)),
+        duration: args.duration.map(Duration::from_secs_f64),
+        ..Default::default()
+    };
+
+    info!("Starting {build}");
+    if let Some(path) = &args.replay {
+        info!("Input file: {} (rtpdump replay)", path.display());
+    } else if playlist {
+        info!(
+            "Input files: {} (playlist, PCM, encoded to Opus)",
+            inputs.join(", ")
+        );
+    } else {
+        info!(
+            "Input file: {} ({})",
+            input,
+            if stdin {
+                "stdin".to_string()
+            } else if let Some(signal) = signal {
+                format!("test signal, {signal}")
+            } else if passthrough {
+                "Ogg Opus passthrough".to_string()
+            } else {
+                "PCM, encoded to Opus".to_string()
+            }
+        );
+    }
+    match args.listen {
+        Some(port) => info!(
+            "Remote address: learned from a hello on port {}{}",
+            port,
+            if args.allow_switch {
+                ", following moves"
+            } else {
+                ""
+            }
+        ),
+        None => info!("Remote address: {}", args.remote.join(", ")),
+    }
+    info!("Transport: {}", Transport::from(args.transport));
+    info!(
+        "SRTP: {}",
+        if args.srtp_key.is_some() {
+            "AES_CM_128_HMAC_SHA1_80"
+        } else {
+            "off"
+        }
+    );
+    let multicast = args.remote.iter().any(|remote| {
+        matches!(
+            remote.parse::<SocketAddr>(),
+            Ok(SocketAddr::V4(addr)) if addr.ip().is_multicast()
+        )
+    });
+    if let Some(refresh) = args.dns_refresh {
+        if args
+            .remote
+            .iter()
+            .all(|remote| remote.parse::<SocketAddr>().is_ok())
+        {
+            warn!("--dns-refresh only applies to a --remote given by host name, ignoring it");
+        } else {
+            info!("DNS refresh: every {}s", refresh.as_secs());
+        }
+    }
+    if !multicast && (args.mcast_if.is_some() || args.mcast_ttl != DEFAULT_MULTICAST_TTL) {
+        warn!("--mcast-ttl and --mcast-if only apply to a multicast --remote, ignoring them");
+    }
+    if args.gain_db != 0.0 {
+        if let Some(kind) = coded {
+            warn!("--gain-db needs the encoder, ignoring it for {kind} input");
+        } else {
+            info!("Gain: {:+.1} dB", args.gain_db);
+        }
+    }
+    if let Some(hz) = args.highpass {
+        if let Some(kind) = coded {
+            warn!("--highpass needs the encoder, ignoring it for {kind} input");
+        } else {
+            info!("High-pass filter: {:.0} Hz", hz);
+        }
+    }
+    let normalize = args.normalize.map(|mode| NormalizeConfig {
+        mode: mode.into(),
+        target_dbfs: args.normalize_target_dbfs,
+    });
+    if let Some(normalize) = &normalize {
+        if coded.is_some() || signal.is_some() {
+            warn!("--normalize only applies to PCM file and stdin input, ignoring it");
+        } else if stdin {
+            info!(
+                "Automatic gain control: {} level to {:.1} dBFS",
+                normalize.mode, normalize.target_dbfs
+            );
+        } else {
+            info!(
+                "Normalize: {} level to {:.1} dBFS",
+                normalize.mode, normalize.target_dbfs
+            );
+        }
+    }
+    let trim = args.trim_silence.map(|threshold_dbfs| TrimConfig {
+        threshold_dbfs,
+        min_silence: Duration::from_millis(args.trim_min_silence_ms),
+    });
+    if let Some(trim) = &trim {
+        if stdin || coded.is_some() || signal.is_some() {
+            warn!("--trim-silence only applies to PCM file input, ignoring it");
+        } else {
+            info!(
+                "Trim silence: below {:.1} dBFS, at least {}ms at either end",
+                trim.threshold_dbfs,
+                trim.min_silence.as_millis()
+            );
+        }
+    }
+    if coded.is_some() || signal.is_some() {
+        if args.channel != ChannelMap::Mix {
+            warn!("--channel only applies to PCM file and stdin input, ignoring it");
+        }
+    } else {
+        info!("Channels: {}", args.channel);
+    }
+    if coded.is_none() {
+        info!(
+            "Transmission interval: {}ms",
+            args.interval_ms.unwrap_or(args.frame_ms as u64)
+        );
+    }
+    if stdin {
+        info!(
+            "Stdin PCM: s16le, {}Hz, {} channels",
+            args.stdin_rate, args.stdin_channels
+        );
+        if !args.no_loop {
+            info!("Stdin input cannot loop, streaming until EOF");
+        }
+        args.channel
+            .check(args.stdin_channels)
+            .context("invalid --channel for --stdin-channels")?;
+    } else if replay {
+        if args.interval_ms == Some(0) {
+            info!("Replay timing: back to back, once");
+        } else {
+            info!("Replay timing: as recorded, once");
+        }
+    } else if let Some(generator) = &generator {
+        if args.duration.is_some() || args.no_loop {
+            info!("Signal length: {:.2}s", generator.duration_secs());
+        } else {
+            info!("Signal length: until stopped");
+        }
+    } else {
+        if args.loop_count > 0 {
+            info!("Loop audio: {} passes", args.loop_count);
+        } else {
+            info!("Loop audio: {}", !args.no_loop);
+        }
+        if window && (args.start.is_some() || args.duration.is_some()) {
+            info!(
+                "Window: from {:.2}s, {}",
+                read.start.as_secs_f64(),
+                read.duration.map_or("to the end".to_string(), |d| format!(
+                    "{:.2}s",
+                    d.as_secs_f64()
+                ))
+            );
+        }
+    }
+    if args.loop_count > 0 && (replay || stdin || generator.is_some()) {
+        warn!("--loop-count only applies to a file, ignoring it");
+    }
+    if args.loop_fade_ms > 0 {
+        if args.no_loop || stdin || playlist || coded.is_some() || generator.is_some() {
+            warn!("--loop-fade-ms only applies to a looping PCM file, ignoring it");
+        } else {
+            info!("Loop fade: {}ms", args.loop_fade_ms);
+        }
+    }
+    if args.loop_gap_ms > 0 {
+        if args.no_loop || stdin || playlist || coded.is_some() || generator.is_some() {
+            warn!("--loop-gap-ms only applies to a looping PCM file, ignoring it");
+        } else {
+            if args.loop_gap_ms % args.frame_ms as u64 != 0 {
+                warn!(
+                    "--loop-gap-ms {} is not a whole number of {}ms frames, rounding up",
+                    args.loop_gap_ms, args.frame_ms
+                );
+            }
+            info!(
+                "Loop gap: {}ms ({})",
+                args.loop_gap_ms,
+                if args.loop_gap_skip {
+                    "skipped"
+                } else {
+                    "silence sent"
+                }
+            );
+        }
+    } else if args.loop_gap_skip {
+        warn!("--loop-gap-skip has no effect without --loop-gap-ms");
+    }
+    if args.pad_tail && (coded.is_some() || generator.is_some()) {
+        warn!("--pad-tail only applies to PCM file or stdin input, ignoring it");
+    }
+    let vbr_mode = VbrMode::from(args.vbr_mode);
+    if let Some(kind) = coded {
+        info!("Encoder options do not apply: packets are sent as coded in the file");
+        if args.ramp_from.is_some() {
+            warn!("--ramp-from needs the encoder, ignoring it for {kind} input");
+        }
+    } else {
+        info!("Bitrate: {} bps", args.bitrate);
+        info!("Frame duration: {}ms", args.frame_ms);
+        info!("Rate control: {vbr_mode}");
+        if let Some(floor) = args.ramp_from {
+            info!(
+                "Trickle start: {} bps to {} bps over {}s",
+                floor, args.bitrate, args.ramp_secs
+            );
+        }
+        info!(
+            "In-band FEC: {} (expected loss {}%)",
+            args.fec, args.expected_loss
+        );
+        if args.fec && args.expected_loss == 0 && !args.adapt {
+            warn!("--fec has no effect until --expected-loss is above 0");
+        }
+    }
+    info!("DTX: {}", args.dtx);
+    if args.dtx && args.pad_packets_to.is_some() {
+        warn!("--dtx stops packets during silence, so --pad-packets-to no longer hides speech activity");
+    }
+    info!("Catch-up policy: {:?}", args.catch_up);
+    if let Some(pad) = args.pad_packets_to {
+        info!("Packet padding: {pad} bytes");
+    }
+    info!("Anomaly log interval: {}s", args.anomaly_log_interval_secs);
+    if args.max_send_failures > 0 {
+        info!("Send failure budget: {} in a row", args.max_send_failures);
+    } else {
+        info!("Send failure budget: unlimited");
+    }
+    info!("Payload verification: {}", args.verify_payloads);
     info!("Metrics bind: {}", args.metrics_bind);
 
+    let (cancel, cancel_rx) = tokio::sync::watch::channel(false);
+    let mut options = StreamOptions {
+        interval_ms: args.interval_ms.unwrap_or(args.frame_ms as u64),
+        loop_audio: !args.no_loop && !stdin,
+        loop_count: args.loop_count,
+        loop_fade: Duration::from_millis(args.loop_fade_ms),
+        loop_gap: Duration::from_millis(args.loop_gap_ms),
+        gap: if args.loop_gap_skip {
+            GapPolicy::Skip
+        } else {
+            GapPolicy::SendSilence
+        },
+        tail: if args.pad_tail {
+            TailPolicy::PadWithSilence
+        } else {
+            TailPolicy::Discard
+        },
+        catch_up: match args.catch_up {
+            CatchUpArg::Burst => CatchUpPolicy::Burst,
+            CatchUpArg::Spread => CatchUpPolicy::Spread(Duration::from_millis(args.spread_over_ms)),
+            CatchUpArg::Skip => CatchUpPolicy::Skip,
+        },
+        payload_type: args.payload_type,
+        abs_send_time_id: args.abs_send_time_id,
+        transport_cc_id: args.transport_cc_id,
+        pad_packets_to: args.pad_packets_to,
+        suppress_dtx: args.dtx,
+        anomaly_log: AnomalyLogConfig {
+            interval: Duration::from_secs(args.anomaly_log_interval_secs),
+        },
+        batch: args.batch.map(|packets| packets as usize),
+        send_errors: ErrorPolicy {
+            max_consecutive_failures: Some(args.max_send_failures).filter(|&max| max > 0),
+            ..Default::default()
+        },
+        verify_payloads: args.verify_payloads,
+        highpass_hz: args.highpass,
+        agc: normalize.filter(|_| stdin).map(|normalize| AgcConfig {
+            mode: normalize.mode,
+            target_dbfs: normalize.target_dbfs,
+            ..Default::default()
+        }),
+        ramp: args
+            .ramp_from
+            .filter(|_| coded.is_none())
+            .map(|floor| RampConfig {
+                floor,
+                duration: Duration::from_secs(args.ramp_secs),
+                restart_after: Duration::from_secs(args.ramp_restart_secs),
+                ..Default::default()
+            }),
+        encoder_control: None,
+        cancel: Some(cancel_rx),
+    };
+
+    // Reject unusable encoder settings or padding target before reading any audio
+    let encoder_config = OpusEncoderConfig {
+        bitrate: args.bitrate,
+        frame_ms: args.frame_ms,
+        fec: args.fec,
+        expected_loss_pct: args.expected_loss,
+        dtx: args.dtx,
+        vbr_mode,
+    };
+    let mut encoder =
+        OpusEncoderWrapper::with_config(encoder_config).context("failed to create encoder")?;
+    if coded.is_none() {
+        options.max_payload_bytes(encoder.config())?;
+    }
+    if let Some(ramp) = &options.ramp {
+        ramp.validate().context("invalid --ramp-from")?;
+    }
+    let adaptation = match args.transport {
+        _ if !args.adapt => None,
+        _ if coded.is_some() => {
+            warn!("--adapt needs the encoder, ignoring it for coded input");
+            None
+        }
+        TransportArg::Tcp => {
+            info!("Adaptation: off (not supported over TCP)");
+            None
+        }
+        TransportArg::Udp if args.srtp_key.is_some() => {
+            info!("Adaptation: off (not supported with SRTP)");
+            None
+        }
+        TransportArg::Udp => {
+            let config = AdaptationConfig {
+                min_bitrate: args.adapt_min_bitrate,
+                max_bitrate: args.adapt_max_bitrate.unwrap_or(args.bitrate),
+                loss_threshold: args.adapt_loss_pct / 100.0,
+                clean_threshold: args.adapt_clean_pct / 100.0,
+                recover_after: Duration::from_secs(args.adapt_recover_secs),
+                ..Default::default()
+            };
+            info!(
+                "Adaptation: {} to {} bps, down on {}% loss, up after {}s at {}%",
+                config.min_bitrate,
+                config.max_bitrate,
+                args.adapt_loss_pct,
+                args.adapt_recover_secs,
+                args.adapt_clean_pct
+            );
+            let initial = EncoderSettings {
+                bitrate: args.bitrate,
+                expected_loss_pct: args.expected_loss,
+            };
+            let controller =
+                AdaptationController::new(config, initial).context("invalid --adapt settings")?;
+            Some(controller)
+        }
+    };
+
     let metrics = MetricsContext::new("sender")?;
-    let metrics_bind = args.metrics_bind.parse().context("invalid metrics bind")?;
-    let _metrics_task = metrics.spawn_metrics_server(MetricsServerConfig::new(metrics_bind));
+    metrics.set_build_info(&build);
+    #[cfg(feature = "metrics-server")]
+    {
+        let metrics_bind = args.metrics_bind.parse().context("invalid metrics bind")?;
+        let _metrics_task = metrics.spawn_metrics_server(MetricsServerConfig::new(metrics_bind));
+    }
+    #[cfg(not(feature = "metrics-server"))]
+    warn!("Built without the metrics-server feature, not serving metrics");
 
     // Read and preprocess audio in blocking task
     info!("Reading audio file...");
-    let input_path = args.input.clone();
-    let audio = match tokio::task::spawn_blocking(move || sender::read_wav(input_path))
-        .await
-        .context("audio reading task failed")?
-    {
-        Ok(audio) => audio,
+    let input_path = input.to_string();
+    let replay_path = args.replay.clone();
+    let silence_config = SilenceConfig {
+        threshold_dbfs: args.silence_threshold_dbfs,
+        ..Default::default()
+    };
+    let probe_config = silence_config.clone();
+    let loop_audio = options.loop_audio;
+    let convert = ConvertOptions {
+        channel_map: args.channel,
+        gain_db: args.gain_db,
+        normalize,
+        trim,
+        ..Default::default()
+    };
+    let loaded = tokio::task::spawn_blocking(move || {
+        // ---
+        if let Some(path) = replay_path {
+            read_rtpdump(path).map(|file| (Input::Replay(file), None))
+        } else if stdin {
+            Ok((Input::Stdin, None))
+        } else if let Some(generator) = generator {
+            Ok((Input::Signal(generator), None))
+        } else if passthrough {
+            sender::read_ogg_opus(input_path).map(|file| (Input::Opus(file), None))
+        } else if playlist {
+            // Each file is converted on its own, so rates and channel counts
+            // may differ; the silence check is logged per file
+            let mut files = Vec::with_capacity(inputs.len());
+            for path in &inputs {
+                let (audio, silence) =
+                    sender::read_audio_with_options(path, &probe_config, &read, &convert)?;
+                info!("Silence in {path}: {silence}");
+                for warning in silence.warnings(&probe_config) {
+                    warn!("Input check, {path}: {warning}");
+                }
+                files.push(audio);
+            }
+            Ok((Input::Playlist(files), None))
+        } else {
+            // WAV is streamed from disk, so long files need no more memory
+            // than short ones, unless trimming needs all of it; compressed
+            // formats are decoded up front
+            let format = sender::audio::detect_format(input_path.as_ref())?;
+            if format == AudioFormat::Wav && convert.trim.is_none() {
+                let silence = sender::analyze_wav_silence(&input_path, &probe_config, &read)?;
+                let reader = WavFrameReader::open_with(&input_path, loop_audio, &read, &convert)?
+                    .with_loop_count(options.loop_count)
+                    .with_loop_fade(options.loop_fade)
+                    .with_loop_gap(options.loop_gap)
+                    .with_tail(options.tail);
+                Ok((Input::Wav(Box::new(reader)), Some(silence)))
+            } else {
+                sender::read_audio_with_options(input_path, &probe_config, &read, &convert)
+                    .map(|(audio, silence)| (Input::Pcm(audio), Some(silence)))
+            }
+        }
+    })
+    .await
+    .context("audio reading task failed")?;
+    let (mut input, silence) = match loaded {
+        Ok(result) => result,
         Err(err) => {
-            tracing::error!("Failed to read audio file: {err}");
+            tracing::error!("Failed to read audio file: {err:#}");
             std::process::exit(1);
         }
     };
 
-    info!(
-        "Loaded {:.2}s of audio ({} frames)",
-        audio.duration_secs(),
-        audio.frame_count()
-    );
+    match &input {
+        Input::Pcm(audio) => info!(
+            "Loaded {:.2}s of audio ({} frames)",
+            audio.duration_secs(),
+            audio.frame_count()
+        ),
+        Input::Playlist(files) => info!(
+            "Loaded {} files, {:.2}s of audio in all",
+            files.len(),
+            files.iter().map(AudioData::duration_secs).sum::<f64>()
+        ),
+        Input::Wav(reader) => info!(
+            "Streaming {:.2}s of audio from disk ({}Hz, {} channels)",
+            reader.duration_secs(),
+            reader.spec().sample_rate,
+            reader.spec().channels
+        ),
+        Input::Opus(file) => {
+            // ---
+            info!(
+                "Loaded {:.2}s of Opus ({} packets, {} channels)",
+                file.duration_secs(),
+                file.packets.len(),
+                file.head.channels
+            );
+            match file.frame_duration() {
+                Some(frame) if args.interval_ms.is_none() => {
+                    options.interval_ms = frame.as_millis() as u64;
+                    if frame.subsec_micros() % 1000 != 0 {
+                        warn!(
+                            "{:?} packets cannot be paced exactly, sending every {}ms",
+                            frame, options.interval_ms
+                        );
+                    }
+                }
+                Some(_) => {}
+                None => warn!(
+                    "Packet durations vary; pacing at {}ms per packet, so transmission \
+                     drifts from real time (timestamps stay correct)",
+                    options.interval_ms
+                ),
+            }
+            info!("Transmission interval: {}ms", options.interval_ms);
+            options.check_padding(file.packets.iter().map(|p| p.data.len()))?;
+        }
+        Input::Replay(file) => info!(
+            "Loaded {} packets captured on {}, spanning {:.2}s",
+            file.packets.len(),
+            file.source,
+            file.duration().as_secs_f64()
+        ),
+        Input::Stdin | Input::Signal(_) => {}
+    }
+    if options.batch_size() > 1 {
+        info!("Batching {} packets per send", options.batch_size());
+    }
+    if let Some(kbps) = args.max_kbps {
+        info!(
+            "Bandwidth cap: {} kbps, {} byte burst",
+            kbps, args.max_burst_bytes
+        );
+    }
+    if let Some(silence) = silence {
+        info!("Silence: {silence}");
+        for warning in silence.warnings(&silence_config) {
+            warn!("Input check: {warning}");
+        }
+    }
 
-    // Create encoder and network sender
-    let mut encoder = OpusEncoderWrapper::new().context("failed to create encoder")?;
-    let mut sender = RtpSender::new(&args.remote)
-        .await
-        .context("failed to create sender")?;
+    // Create network sender
+    let network_config = SenderNetworkConfig {
+        multicast_ttl: args.mcast_ttl,
+        multicast_interface: args.mcast_if,
+        dscp: args.dscp,
+        local_addr: args.local_addr,
+        send_buffer_bytes: args.so_sndbuf.map(|bytes| bytes as usize),
+        dns_refresh: args.dns_refresh,
+        rate_limit: args.max_kbps.map(|kbps| RateLimit {
+            burst_bytes: args.max_burst_bytes,
+            ..RateLimit::from_kbps(kbps)
+        }),
+        transport: args.transport.into(),
+        srtp_key: args.srtp_key.clone(),
+        allow_peer_switch: args.allow_switch,
+        rtx_cache_packets: args.rtx_cache as usize,
+        keepalive_interval: (args.keepalive > 0).then(|| Duration::from_secs(args.keepalive)),
+        sender_report_interval: (args.rtcp_sr_interval > 0)
+            .then(|| Duration::from_secs(args.rtcp_sr_interval)),
+        rtp_clock_rate: sender::codec::SAMPLE_RATE,
+        cname: args.cname.clone().unwrap_or_else(default_cname),
+        rtcp_mux: !args.no_rtcp_mux,
+        // The encoder's budget, to keep sender reports within 5% of it
+        session_bandwidth: args.bitrate.max(0) as u64,
+    };
+    if network_config.sender_report_interval.is_some() {
+        info!("RTCP CNAME: {}", network_config.cname);
+    }
+    if args.no_rtcp_mux {
+        match args.transport {
+            TransportArg::Tcp => info!("RTCP port: off (not supported over TCP)"),
+            TransportArg::Udp if args.srtp_key.is_some() => {
+                info!("RTCP port: off (not supported with SRTP)")
+            }
+            TransportArg::Udp => {}
+        }
+    }
+    let mut sender = if args.pcap_only {
+        info!("Network: off, capturing only");
+        None
+    } else if let Some(port) = args.listen {
+        let mut sender = RtpSender::listen(port, network_config)
+            .await
+            .context("failed to create sender")?;
+        info!("Waiting for a hello on {}...", sender.local_addr()?);
+        let peer = sender.wait_for_peer().await?;
+        info!("Streaming to {}", peer);
+        Some(sender)
+    } else {
+        let sender = RtpSender::with_destinations(&args.remote, network_config)
+            .await
+            .context("failed to create sender")?;
+        if args.local_addr.is_some() {
+            info!("Sending from {}", sender.local_addr()?);
+        }
+        Some(sender)
+    };
+    // Receiver reports drive the encoder settings through the stream's
+    // control channel
+    let _adaptation = match (adaptation, sender.as_mut()) {
+        (Some(controller), Some(sender)) => {
+            let (control, control_rx) = tokio::sync::watch::channel(controller.settings());
+            options.encoder_control = Some(control_rx);
+            Some(controller.spawn(sender.subscribe_reports(), control, metrics.clone()))
+        }
+        _ => None,
+    };
+    let capture = match &args.pcap_out {
+        Some(path) => {
+            let (source, destination) = capture_addrs(&args, sender.as_ref());
+            info!(
+                "Capturing to {} as {} -> {}",
+                path.display(),
+                source,
+                destination
+            );
+            Some(PcapWriter::create(path, source, destination)?)
+        }
+        None => None,
+    };
 
-    // Generate random SSRC for this session
+    // Generate random SSRC for this session; a replay keeps the recorded ones
     let ssrc = rand::random::<u32>();
-    info!("Session SSRC: 0x{:08X}", ssrc);
+    if !replay {
+        info!("Session SSRC: 0x{:08X}", ssrc);
+    }
+
+    // Ctrl-C stops after the packet in flight so the summary still prints;
+    // a second one exits at once
+    tokio::spawn(async move {
+        // ---
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Interrupted, stopping after the current packet (Ctrl-C again to quit now)");
+            cancel.send_replace(true);
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
 
-    // Stream audio frames
+    // Stream audio frames; an error carries the counts so far as context
     info!("Starting transmission...");
-    stream_audio(
-        &audio,
-        &mut encoder,
-        &mut sender,
-        &metrics,
-        ssrc,
-        args.interval_ms,
-        args.no_loop,
-    )
-    .await?;
-
-    let (packets, bytes) = sender.stats();
+    let (summary, sender, capture) = match (sender, capture) {
+        (Some(sender), Some(capture)) => {
+            let mut sink = (sender, capture);
+            let result = run_stream(
+                &mut input,
+                &mut encoder,
+                &mut sink,
+                &metrics,
+                ssrc,
+                &options,
+                &args,
+            )
+            .await;
+            say_goodbye(&mut sink.0, &result, &options).await;
+            (result?, Some(sink.0), Some(sink.1))
+        }
+        (Some(mut sender), None) => {
+            let result = run_stream(
+                &mut input,
+                &mut encoder,
+                &mut sender,
+                &metrics,
+                ssrc,
+                &options,
+                &args,
+            )
+            .await;
+            say_goodbye(&mut sender, &result, &options).await;
+            (result?, Some(sender), None)
+        }
+        (None, Some(mut capture)) => {
+            let summary = run_stream(
+                &mut input,
+                &mut encoder,
+                &mut capture,
+                &metrics,
+                ssrc,
+                &options,
+                &args,
+            )
+            .await?;
+            (summary, None, Some(capture))
+        }
+        (None, None) => unreachable!("--pcap-only requires --pcap-out"),
+    };
+
+    info!("Stream summary: {summary}");
+    if let (Some(capture), Some(path)) = (&capture, &args.pcap_out) {
+        info!(
+            "Captured {} packets, {} bytes to {}",
+            capture.packets_written(),
+            capture.bytes_written(),
+            path.display()
+        );
+    }
+    let Some(sender) = sender else {
+        return Ok(());
+    };
+    let stats = sender.stats();
     info!(
-        "Transmission complete: {} packets, {} bytes",
-        packets, bytes
+        "Transmission complete: {} packets, {} bytes, {} failed",
+        stats.packets_sent, stats.bytes_sent, stats.packets_failed
     );
+    if stats.packets_delayed > 0 {
+        info!(
+            "Bandwidth cap delayed {} packets by {:.3}s in total",
+            stats.packets_delayed,
+            stats.throttled.as_secs_f64()
+        );
+    }
+    if stats.packets_retransmitted > 0 {
+        info!(
+            "Retransmitted {} packets on NACK",
+            stats.packets_retransmitted
+        );
+    }
+    if stats.keepalives_sent > 0 {
+        info!("Sent {} keepalives during silence", stats.keepalives_sent);
+    }
+    if stats.sender_reports_sent > 0 {
+        info!("Sent {} RTCP sender reports", stats.sender_reports_sent);
+    }
+    if stats.destinations.len() > 1 {
+        for destination in &stats.destinations {
+            info!(
+                "  {}: {} packets, {} bytes, {} failed{}",
+                destination.addr,
+                destination.packets_sent,
+                destination.bytes_sent,
+                destination.packets_failed,
+                if destination.gave_up {
+                    " (gave up)"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
 
     Ok(())
 }
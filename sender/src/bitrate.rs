@@ -0,0 +1,468 @@
+//! Encoder bitrate control.
+//!
+//! [`BitrateController`] decides the bitrate the encoder is asked for from
+//! frame to frame. Today it drives a trickle start: a session begins at a
+//! low floor and ramps up to the configured target on an explicit schedule
+//! ([`RampConfig`]), so a path that cannot carry the full rate shows loss
+//! before the sender commits to it. Delivery feedback ends the ramp early,
+//! at the target when delivery is clean or at the current step when loss
+//! appears, and a long transmission pause starts the ramp over.
+//!
+//! The controller takes the current time as an argument, so schedules can
+//! be driven over simulated time.
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::codec::{MAX_BITRATE, MIN_BITRATE};
+
+/// Default bitrate a ramp starts from, in bits per second
+pub const DEFAULT_RAMP_FLOOR: i32 = 12000;
+
+/// Default time taken to ramp from the floor to the target
+pub const DEFAULT_RAMP_DURATION: Duration = Duration::from_secs(5);
+
+/// Default time between ramp steps
+pub const DEFAULT_RAMP_STEP: Duration = Duration::from_millis(500);
+
+/// Default transmission pause after which the ramp starts over
+pub const DEFAULT_RAMP_RESTART_AFTER: Duration = Duration::from_secs(5);
+
+/// Trickle start schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RampConfig {
+    // ---
+    /// Bitrate the ramp starts from, in bits per second
+    pub floor: i32,
+
+    /// Time from the floor to the target
+    pub duration: Duration,
+
+    /// Time between bitrate increases; the bitrate is raised in equal
+    /// steps rather than every frame
+    pub step: Duration,
+
+    /// A gap this long between sent packets restarts the ramp from the floor
+    pub restart_after: Duration,
+}
+
+impl Default for RampConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            floor: DEFAULT_RAMP_FLOOR,
+            duration: DEFAULT_RAMP_DURATION,
+            step: DEFAULT_RAMP_STEP,
+            restart_after: DEFAULT_RAMP_RESTART_AFTER,
+        }
+    }
+}
+
+impl RampConfig {
+    // ---
+    /// Checks the schedule.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the floor is outside what Opus supports, or the
+    /// duration, step, or restart pause is zero.
+    pub fn validate(&self) -> Result<()> {
+        // ---
+        if !(MIN_BITRATE..=MAX_BITRATE).contains(&self.floor) {
+            anyhow::bail!(
+                "ramp floor {} bps is out of range: Opus supports {} to {} bps",
+                self.floor,
+                MIN_BITRATE,
+                MAX_BITRATE
+            );
+        }
+        if self.duration.is_zero() || self.step.is_zero() {
+            anyhow::bail!("ramp duration and step must be greater than zero");
+        }
+        if self.restart_after.is_zero() {
+            anyhow::bail!("ramp restart pause must be greater than zero");
+        }
+        Ok(())
+    }
+}
+
+/// Where the controller is in its ramp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RampPhase {
+    /// Climbing from the floor towards the target
+    Ramping,
+
+    /// Loss was reported mid-ramp; the bitrate is held where it was
+    Held,
+
+    /// At the target (ramp finished, cut short by clean delivery, or off)
+    Done,
+}
+
+impl RampPhase {
+    // ---
+    /// Returns the phase name used in logs.
+    pub fn as_str(self) -> &'static str {
+        // ---
+        match self {
+            RampPhase::Ramping => "ramping",
+            RampPhase::Held => "held",
+            RampPhase::Done => "done",
+        }
+    }
+}
+
+impl std::fmt::Display for RampPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// What the far end reported about recent delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryFeedback {
+    /// Packets are arriving without loss
+    Clean,
+
+    /// Packets are being lost
+    Loss,
+}
+
+/// Decides the encoder bitrate from frame to frame.
+#[derive(Debug)]
+pub struct BitrateController {
+    // ---
+    /// Configured bitrate, in bits per second
+    target: i32,
+
+    /// Trickle start schedule, if any
+    ramp: Option<RampConfig>,
+
+    /// Where the ramp is
+    phase: RampPhase,
+
+    /// When the current ramp started; set on the first poll
+    ramp_start: Option<Instant>,
+
+    /// Bitrate the controller currently wants
+    current: i32,
+
+    /// Bitrate last returned by [`poll`](Self::poll)
+    applied: Option<i32>,
+
+    /// When a packet was last sent
+    last_sent: Option<Instant>,
+}
+
+impl BitrateController {
+    // ---
+    /// Creates a controller for `target`, ramping up to it when `ramp` is set.
+    ///
+    /// A floor at or above the target means there is nothing to ramp.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Bitrate to reach, in bits per second
+    /// * `ramp` - Trickle start schedule, or `None` to start at the target
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the ramp schedule is invalid.
+    pub fn new(target: i32, ramp: Option<RampConfig>) -> Result<Self> {
+        // ---
+        if let Some(ramp) = &ramp {
+            ramp.validate()?;
+        }
+        let ramp = ramp.filter(|ramp| ramp.floor < target);
+
+        let mut controller = Self {
+            target,
+            ramp,
+            phase: RampPhase::Done,
+            ramp_start: None,
+            current: target,
+            applied: None,
+            last_sent: None,
+        };
+        controller.restart_ramp(None);
+        Ok(controller)
+    }
+
+    /// Returns the configured bitrate in bits per second.
+    pub fn target(&self) -> i32 {
+        // ---
+        self.target
+    }
+
+    /// Changes the bitrate to reach, as an outside controller decides.
+    ///
+    /// Outside a ramp the new target applies from the next
+    /// [`poll`](Self::poll). A ramp in progress climbs towards it instead,
+    /// and ends if its floor is already there; a held ramp stays held,
+    /// coming down to the new target if that is lower.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `target` is outside what Opus supports; the old
+    /// target stays.
+    pub fn set_target(&mut self, target: i32) -> Result<()> {
+        // ---
+        if !(MIN_BITRATE..=MAX_BITRATE).contains(&target) {
+            anyhow::bail!(
+                "bitrate {} bps is out of range: Opus supports {} to {} bps",
+                target,
+                MIN_BITRATE,
+                MAX_BITRATE
+            );
+        }
+        self.target = target;
+        let floor_reached = self.ramp.as_ref().is_some_and(|ramp| ramp.floor >= target);
+        match self.phase {
+            RampPhase::Ramping if !floor_reached => {}
+            RampPhase::Held => self.current = self.current.min(target),
+            _ => {
+                self.phase = RampPhase::Done;
+                self.current = target;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the bitrate the controller currently wants, in bits per second.
+    pub fn current(&self) -> i32 {
+        // ---
+        self.current
+    }
+
+    /// Returns where the ramp is.
+    pub fn phase(&self) -> RampPhase {
+        // ---
+        self.phase
+    }
+
+    /// Advances the schedule to `now`, before a frame is encoded.
+    ///
+    /// The first call starts the ramp. A pause since the last sent packet
+    /// of at least [`RampConfig::restart_after`] starts it over from the
+    /// floor.
+    ///
+    /// # Returns
+    ///
+    /// The bitrate to apply to the encoder when it changed since the last
+    /// call (always on the first call), `None` otherwise.
+    pub fn poll(&mut self, now: Instant) -> Option<i32> {
+        // ---
+        let paused = self
+            .ramp
+            .as_ref()
+            .zip(self.last_sent)
+            .is_some_and(|(ramp, sent)| now.saturating_duration_since(sent) >= ramp.restart_after);
+        if paused {
+            self.restart_ramp(Some(now));
+        }
+
+        if self.phase == RampPhase::Ramping {
+            let start = *self.ramp_start.get_or_insert(now);
+            self.advance_ramp(now.saturating_duration_since(start));
+        }
+
+        if self.applied == Some(self.current) {
+            return None;
+        }
+        self.applied = Some(self.current);
+        Some(self.current)
+    }
+
+    /// Records that a packet went out at `now`.
+    pub fn on_sent(&mut self, now: Instant) {
+        // ---
+        self.last_sent = Some(now);
+    }
+
+    /// Applies delivery feedback from the receiver.
+    ///
+    /// Mid-ramp, clean delivery jumps straight to the target and loss holds
+    /// the bitrate at the current step. Outside a ramp feedback is ignored.
+    pub fn on_feedback(&mut self, feedback: DeliveryFeedback) {
+        // ---
+        if self.phase != RampPhase::Ramping {
+            return;
+        }
+        match feedback {
+            DeliveryFeedback::Clean => {
+                self.phase = RampPhase::Done;
+                self.current = self.target;
+            }
+            DeliveryFeedback::Loss => self.phase = RampPhase::Held,
+        }
+    }
+
+    /// Puts the controller back at the start of its ramp, if it has one
+    /// below the target.
+    fn restart_ramp(&mut self, now: Option<Instant>) {
+        // ---
+        if let Some(ramp) = self.ramp.as_ref().filter(|ramp| ramp.floor < self.target) {
+            self.phase = RampPhase::Ramping;
+            self.ramp_start = now;
+            self.current = ramp.floor;
+        }
+    }
+
+    /// Sets the current bitrate to the ramp step reached after `elapsed`.
+    fn advance_ramp(&mut self, elapsed: Duration) {
+        // ---
+        let Some(ramp) = &self.ramp else {
+            return;
+        };
+        let steps = (elapsed.as_nanos() / ramp.step.as_nanos()) as u32;
+        let reached = ramp.step.saturating_mul(steps);
+        if reached >= ramp.duration {
+            self.phase = RampPhase::Done;
+            self.current = self.target;
+            return;
+        }
+
+        let fraction = reached.as_secs_f64() / ramp.duration.as_secs_f64();
+        self.current = ramp.floor + ((self.target - ramp.floor) as f64 * fraction) as i32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::codec::OpusEncoderWrapper;
+
+    /// Ramp from 12 to 24 kbps over 4 seconds in 1-second steps.
+    fn ramp() -> RampConfig {
+        // ---
+        RampConfig {
+            floor: 12000,
+            duration: Duration::from_secs(4),
+            step: Duration::from_secs(1),
+            restart_after: Duration::from_secs(2),
+        }
+    }
+
+    /// Streams 20ms frames from `from` to `to` milliseconds of simulated
+    /// time, applying the controller to `encoder`, and returns the encoder
+    /// bitrate at each whole second.
+    fn run(
+        controller: &mut BitrateController,
+        encoder: &mut OpusEncoderWrapper,
+        start: Instant,
+        from: u64,
+        to: u64,
+    ) -> Vec<i32> {
+        // ---
+        let mut schedule = Vec::new();
+        for ms in (from..to).step_by(20) {
+            let now = start + Duration::from_millis(ms);
+            if let Some(bits) = controller.poll(now) {
+                encoder.set_bitrate(bits).expect("set bitrate");
+            }
+            if ms.is_multiple_of(1000) {
+                schedule.push(encoder.bitrate());
+            }
+            controller.on_sent(now);
+        }
+        schedule
+    }
+
+    #[test]
+    fn test_ramp_schedule_applied_to_encoder() {
+        // ---
+        let mut controller = BitrateController::new(24000, Some(ramp())).unwrap();
+        let mut encoder = OpusEncoderWrapper::new().unwrap();
+        let start = Instant::now();
+
+        let schedule = run(&mut controller, &mut encoder, start, 0, 6000);
+        assert_eq!(schedule, [12000, 15000, 18000, 21000, 24000, 24000]);
+        assert_eq!(controller.phase(), RampPhase::Done);
+
+        // A long pause starts the ramp over; a short one does not
+        let schedule = run(&mut controller, &mut encoder, start, 7000, 8000);
+        assert_eq!(schedule, [24000]);
+        let schedule = run(&mut controller, &mut encoder, start, 10000, 12000);
+        assert_eq!(schedule, [12000, 15000]);
+        assert_eq!(controller.phase(), RampPhase::Ramping);
+    }
+
+    #[test]
+    fn test_loss_holds_ramp_and_clean_delivery_completes_it() {
+        // ---
+        let mut controller = BitrateController::new(24000, Some(ramp())).unwrap();
+        let mut encoder = OpusEncoderWrapper::new().unwrap();
+        let start = Instant::now();
+
+        run(&mut controller, &mut encoder, start, 0, 2500);
+        assert_eq!(encoder.bitrate(), 18000);
+        controller.on_feedback(DeliveryFeedback::Loss);
+        assert_eq!(controller.phase(), RampPhase::Held);
+
+        // The ramp stops climbing, and later clean reports do not resume it
+        let schedule = run(&mut controller, &mut encoder, start, 2500, 6000);
+        assert_eq!(schedule, [18000, 18000, 18000]);
+        controller.on_feedback(DeliveryFeedback::Clean);
+        assert_eq!(controller.current(), 18000);
+
+        // Clean delivery on a fresh ramp skips the rest of it
+        let mut controller = BitrateController::new(24000, Some(ramp())).unwrap();
+        run(&mut controller, &mut encoder, start, 0, 1000);
+        controller.on_feedback(DeliveryFeedback::Clean);
+        assert_eq!(controller.poll(start + Duration::from_secs(1)), Some(24000));
+        assert_eq!(controller.phase(), RampPhase::Done);
+    }
+
+    #[test]
+    fn test_set_target_mid_stream() {
+        // ---
+        let mut controller = BitrateController::new(24000, None).unwrap();
+        let mut encoder = OpusEncoderWrapper::new().unwrap();
+        let start = Instant::now();
+        run(&mut controller, &mut encoder, start, 0, 100);
+
+        controller.set_target(8000).unwrap();
+        assert_eq!(controller.poll(start), Some(8000));
+        assert!(controller.set_target(100).is_err());
+        assert_eq!(controller.target(), 8000);
+
+        // A ramp climbs to a raised target; a target at its floor ends it
+        let mut controller = BitrateController::new(24000, Some(ramp())).unwrap();
+        run(&mut controller, &mut encoder, start, 0, 1000);
+        controller.set_target(36000).unwrap();
+        let schedule = run(&mut controller, &mut encoder, start, 1000, 5000);
+        assert_eq!(schedule, [18000, 24000, 30000, 36000]);
+
+        let mut controller = BitrateController::new(24000, Some(ramp())).unwrap();
+        run(&mut controller, &mut encoder, start, 0, 1000);
+        controller.set_target(12000).unwrap();
+        assert_eq!(controller.phase(), RampPhase::Done);
+        assert_eq!(controller.current(), 12000);
+    }
+
+    #[test]
+    fn test_without_ramp_starts_at_target() {
+        // ---
+        let now = Instant::now();
+        let mut controller = BitrateController::new(24000, None).unwrap();
+        assert_eq!(controller.poll(now), Some(24000));
+        assert_eq!(controller.poll(now), None);
+
+        // A floor at or above the target leaves nothing to ramp
+        let high_floor = RampConfig {
+            floor: 32000,
+            ..ramp()
+        };
+        let mut controller = BitrateController::new(24000, Some(high_floor)).unwrap();
+        assert_eq!(controller.poll(now), Some(24000));
+        assert_eq!(controller.phase(), RampPhase::Done);
+
+        let bad_floor = RampConfig {
+            floor: MIN_BITRATE - 1,
+            ..ramp()
+        };
+        assert!(BitrateController::new(24000, Some(bad_floor)).is_err());
+    }
+}
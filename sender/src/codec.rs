@@ -13,15 +13,214 @@ pub const SAMPLE_RATE: u32 = 16000;
 #[allow(dead_code)] // Kept for consistency with receiver
 pub const CHANNELS: usize = 1;
 
-/// Frame duration in milliseconds
+/// Default frame duration in milliseconds
 pub const FRAME_DURATION_MS: usize = 20;
 
-/// Samples per frame (20ms at 16kHz)
+/// Frame durations the encoder can be configured with, in milliseconds
+pub const FRAME_DURATIONS_MS: [usize; 4] = [10, 20, 40, 60];
+
+/// Samples per frame at the default duration (20ms at 16kHz)
 pub const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE as usize * FRAME_DURATION_MS) / 1000;
 
-/// Target bitrate in bits per second
+/// Default target bitrate in bits per second
 pub const BITRATE: i32 = 24000;
 
+/// Lowest bitrate Opus supports, in bits per second
+pub const MIN_BITRATE: i32 = 6000;
+
+/// Highest bitrate Opus supports, in bits per second
+pub const MAX_BITRATE: i32 = 510_000;
+
+/// Largest Opus packet the encoder may produce
+pub const MAX_PACKET_BYTES: usize = 4000;
+
+/// Level below which a frame counts as silence for DTX, in dBFS
+pub const DTX_SILENCE_DBFS: f64 = -50.0;
+
+/// Silence that is still coded in full before DTX starts, in milliseconds
+pub const DTX_HANGOVER_MS: usize = 200;
+
+/// Interval between full comfort-noise frames during DTX, in milliseconds
+pub const DTX_REFRESH_MS: usize = 400;
+
+/// Largest payload that is a DTX frame rather than coded audio
+pub const DTX_FRAME_MAX_BYTES: usize = 2;
+
+/// Nominal encoded frame size at [`BITRATE`] and [`FRAME_DURATION_MS`], in bytes
+pub const NOMINAL_FRAME_BYTES: usize = nominal_frame_bytes(BITRATE, FRAME_DURATION_MS);
+
+/// Returns the nominal encoded size of a `frame_ms` frame at `bitrate`, in bytes.
+pub const fn nominal_frame_bytes(bitrate: i32, frame_ms: usize) -> usize {
+    // ---
+    bitrate as usize * frame_ms / 8000
+}
+
+/// Returns true if `payload` is a DTX frame: a TOC byte with no coded
+/// audio, which the decoder plays out as comfort noise.
+pub fn is_dtx_frame(payload: &[u8]) -> bool {
+    // ---
+    payload.len() <= DTX_FRAME_MAX_BYTES
+}
+
+/// How the encoder spends its bitrate from frame to frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VbrMode {
+    // ---
+    /// Unconstrained VBR: frame sizes follow signal complexity freely
+    Vbr,
+
+    /// Constrained VBR: sizes vary, but stay within about one frame's
+    /// worth of a constant-rate buffer (the libopus default)
+    #[default]
+    ConstrainedVbr,
+
+    /// Hard CBR: every frame is coded at the same size, so packet sizes do
+    /// not reveal speech activity
+    Cbr,
+}
+
+impl VbrMode {
+    // ---
+    /// All modes, in the order they are listed.
+    pub const ALL: [VbrMode; 3] = [VbrMode::Vbr, VbrMode::ConstrainedVbr, VbrMode::Cbr];
+
+    /// Returns the mode name used in logs and metric labels.
+    pub fn as_str(self) -> &'static str {
+        // ---
+        match self {
+            VbrMode::Vbr => "vbr",
+            VbrMode::ConstrainedVbr => "cvbr",
+            VbrMode::Cbr => "cbr",
+        }
+    }
+}
+
+impl std::fmt::Display for VbrMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // ---
+        f.write_str(self.as_str())
+    }
+}
+
+/// Opus encoder settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusEncoderConfig {
+    // ---
+    /// Target bitrate in bits per second
+    pub bitrate: i32,
+
+    /// Frame duration in milliseconds (one of [`FRAME_DURATIONS_MS`])
+    pub frame_ms: usize,
+
+    /// Embed in-band FEC (LBRR) so the receiver can rebuild a lost frame
+    /// from the packet that follows it
+    pub fec: bool,
+
+    /// Packet loss the encoder should plan for, in percent (0-100); with
+    /// `fec` this sets how much redundancy is spent
+    pub expected_loss_pct: u8,
+
+    /// Discontinuous transmission: replace silent frames with 1-byte DTX
+    /// frames, keeping a full frame every [`DTX_REFRESH_MS`]
+    pub dtx: bool,
+
+    /// Variable or constant bitrate
+    pub vbr_mode: VbrMode,
+}
+
+impl Default for OpusEncoderConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            bitrate: BITRATE,
+            frame_ms: FRAME_DURATION_MS,
+            fec: false,
+            expected_loss_pct: 0,
+            dtx: false,
+            vbr_mode: VbrMode::default(),
+        }
+    }
+}
+
+impl OpusEncoderConfig {
+    // ---
+    /// Returns the number of samples in one frame.
+    pub fn samples_per_frame(&self) -> usize {
+        // ---
+        SAMPLE_RATE as usize * self.frame_ms / 1000
+    }
+
+    /// Returns the nominal encoded frame size in bytes.
+    pub fn nominal_frame_bytes(&self) -> usize {
+        // ---
+        nominal_frame_bytes(self.bitrate, self.frame_ms)
+    }
+
+    /// Checks the settings against what Opus supports.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the bitrate is outside [`MIN_BITRATE`]..=[`MAX_BITRATE`],
+    /// the frame duration is not one of [`FRAME_DURATIONS_MS`], or the
+    /// expected loss is above 100%.
+    pub fn validate(&self) -> Result<()> {
+        // ---
+        if !(MIN_BITRATE..=MAX_BITRATE).contains(&self.bitrate) {
+            anyhow::bail!(
+                "bitrate {} bps is out of range: Opus supports {} to {} bps",
+                self.bitrate,
+                MIN_BITRATE,
+                MAX_BITRATE
+            );
+        }
+        if !FRAME_DURATIONS_MS.contains(&self.frame_ms) {
+            anyhow::bail!(
+                "frame duration {}ms is not supported: use one of {:?}",
+                self.frame_ms,
+                FRAME_DURATIONS_MS
+            );
+        }
+        if self.expected_loss_pct > 100 {
+            anyhow::bail!(
+                "expected loss {}% is out of range: use 0 to 100",
+                self.expected_loss_pct
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Encoder settings that can change while streaming (see
+/// [`StreamOptions::encoder_control`](crate::StreamOptions::encoder_control)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderSettings {
+    // ---
+    /// Target bitrate in bits per second
+    pub bitrate: i32,
+
+    /// Packet loss the encoder should plan for, in percent (0-100)
+    pub expected_loss_pct: u8,
+}
+
+impl EncoderSettings {
+    // ---
+    /// Checks the settings as [`OpusEncoderConfig::validate`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the bitrate is outside [`MIN_BITRATE`]..=[`MAX_BITRATE`]
+    /// or the expected loss is above 100%.
+    pub fn validate(&self) -> Result<()> {
+        // ---
+        OpusEncoderConfig {
+            bitrate: self.bitrate,
+            expected_loss_pct: self.expected_loss_pct,
+            ..Default::default()
+        }
+        .validate()
+    }
+}
+
 /// Opus encoder wrapper for audio compression.
 ///
 /// Encodes PCM audio samples (16-bit signed integers) into Opus-compressed
@@ -31,9 +230,22 @@ pub const BITRATE: i32 = 24000;
 ///
 /// - Sample Rate: 16kHz (wideband)
 /// - Channels: Mono
-/// - Bitrate: 24 kbps
-/// - Frame Size: 20ms (320 samples)
+/// - Bitrate: 24 kbps by default, configurable with [`OpusEncoderWrapper::with_bitrate`]
+/// - Frame Size: 20ms (320 samples) by default; 10, 40, or 60ms with
+///   [`OpusEncoderWrapper::with_config`]
 /// - Application: VOIP (optimized for speech)
+/// - In-band FEC: off by default, enabled with [`OpusEncoderConfig::fec`]
+/// - DTX: off by default, enabled with [`OpusEncoderWrapper::set_dtx`]
+/// - Rate control: constrained VBR by default, see [`OpusEncoderConfig::vbr_mode`]
+///
+/// # DTX
+///
+/// The `opus` crate does not expose `OPUS_SET_DTX`, so DTX is applied to
+/// the encoder output here, following libopus: after [`DTX_HANGOVER_MS`]
+/// of input below [`DTX_SILENCE_DBFS`], each frame is cut down to its TOC
+/// byte, except for one full comfort-noise frame every [`DTX_REFRESH_MS`].
+/// Every frame is still run through the encoder so its state stays current
+/// for the first frame of speech.
 ///
 /// # Example
 ///
@@ -47,6 +259,18 @@ pub const BITRATE: i32 = 24000;
 pub struct OpusEncoderWrapper {
     // ---
     encoder: Encoder,
+
+    /// Bitrate, frame duration, FEC, and DTX settings
+    config: OpusEncoderConfig,
+
+    /// Upper bound on encoded frame size in bytes
+    max_payload_bytes: usize,
+
+    /// Length of the current run of silent input, in milliseconds
+    silent_ms: usize,
+
+    /// Time since the last full frame during DTX, in milliseconds
+    dtx_ms: usize,
 }
 
 impl OpusEncoderWrapper {
@@ -60,24 +284,206 @@ impl OpusEncoderWrapper {
     /// - Bitrate setting fails
     pub fn new() -> Result<Self> {
         // ---
+        Self::with_config(OpusEncoderConfig::default())
+    }
+
+    /// Creates a new Opus encoder with the given target bitrate.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - Target bitrate in bits per second
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - `bits` is outside [`MIN_BITRATE`]..=[`MAX_BITRATE`]
+    /// - Opus encoder initialization fails
+    /// - Bitrate setting fails
+    pub fn with_bitrate(bits: i32) -> Result<Self> {
+        // ---
+        Self::with_config(OpusEncoderConfig {
+            bitrate: bits,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a new Opus encoder with explicit settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Bitrate, frame duration, FEC, and rate control settings
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - The settings are not supported (see [`OpusEncoderConfig::validate`])
+    /// - Opus encoder initialization fails
+    /// - Applying a setting fails
+    pub fn with_config(config: OpusEncoderConfig) -> Result<Self> {
+        // ---
+        config.validate()?;
+
         let mut encoder = Encoder::new(SAMPLE_RATE, Channels::Mono, Application::Voip)
             .context("failed to create Opus encoder")?;
 
         encoder
-            .set_bitrate(opus::Bitrate::Bits(BITRATE))
+            .set_bitrate(opus::Bitrate::Bits(config.bitrate))
             .context("failed to set bitrate")?;
+        encoder
+            .set_inband_fec(config.fec)
+            .context("failed to set in-band FEC")?;
+        encoder
+            .set_packet_loss_perc(config.expected_loss_pct as i32)
+            .context("failed to set expected packet loss")?;
+        encoder
+            .set_vbr(config.vbr_mode != VbrMode::Cbr)
+            .context("failed to set VBR")?;
+        encoder
+            .set_vbr_constraint(config.vbr_mode == VbrMode::ConstrainedVbr)
+            .context("failed to set VBR constraint")?;
+
+        Ok(Self {
+            encoder,
+            config,
+            max_payload_bytes: MAX_PACKET_BYTES,
+            silent_ms: 0,
+            dtx_ms: 0,
+        })
+    }
 
-        Ok(Self { encoder })
+    /// Returns the encoder settings.
+    pub fn config(&self) -> &OpusEncoderConfig {
+        // ---
+        &self.config
+    }
+
+    /// Returns the target bitrate in bits per second.
+    pub fn bitrate(&self) -> i32 {
+        // ---
+        self.config.bitrate
+    }
+
+    /// Returns the settings that can be changed while streaming.
+    pub fn settings(&self) -> EncoderSettings {
+        // ---
+        EncoderSettings {
+            bitrate: self.config.bitrate,
+            expected_loss_pct: self.config.expected_loss_pct,
+        }
+    }
+
+    /// Returns the number of samples [`encode`](Self::encode) expects.
+    pub fn samples_per_frame(&self) -> usize {
+        // ---
+        self.config.samples_per_frame()
+    }
+
+    /// Enables or disables discontinuous transmission.
+    ///
+    /// With DTX on, long silences are coded as 1-byte frames (see
+    /// [`is_dtx_frame`]) that the caller may skip sending.
+    pub fn set_dtx(&mut self, enabled: bool) {
+        // ---
+        self.config.dtx = enabled;
+        self.silent_ms = 0;
+        self.dtx_ms = 0;
+    }
+
+    /// Changes the target bitrate between frames.
+    ///
+    /// Takes effect from the next [`encode`](Self::encode); the coding
+    /// history is kept, so the change is seamless for the decoder.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - Target bitrate in bits per second
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the bitrate is outside [`MIN_BITRATE`]..=[`MAX_BITRATE`]
+    /// or Opus rejects it. The previous bitrate stays in effect.
+    pub fn set_bitrate(&mut self, bits: i32) -> Result<()> {
+        // ---
+        let config = OpusEncoderConfig {
+            bitrate: bits,
+            ..self.config.clone()
+        };
+        config.validate()?;
+        self.encoder
+            .set_bitrate(opus::Bitrate::Bits(bits))
+            .context("failed to set bitrate")?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Changes the packet loss the encoder plans for between frames.
+    ///
+    /// Like [`set_bitrate`](Self::set_bitrate), takes effect from the next
+    /// frame without disturbing the coding history.
+    ///
+    /// # Arguments
+    ///
+    /// * `pct` - Expected packet loss in percent (0-100)
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `pct` is above 100 or Opus rejects it. The previous
+    /// setting stays in effect.
+    pub fn set_expected_loss(&mut self, pct: u8) -> Result<()> {
+        // ---
+        let config = OpusEncoderConfig {
+            expected_loss_pct: pct,
+            ..self.config.clone()
+        };
+        config.validate()?;
+        self.encoder
+            .set_packet_loss_perc(pct as i32)
+            .context("failed to set expected packet loss")?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Resets the encoder's internal state.
+    ///
+    /// The next frame is coded without reference to earlier audio, so a
+    /// receiver whose decoder lost track (e.g. after a long outage) can
+    /// decode it cleanly. Settings are kept, and DTX restarts its hangover
+    /// so the frames after the reset are coded in full.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if Opus rejects the reset.
+    pub fn reset(&mut self) -> Result<()> {
+        // ---
+        self.silent_ms = 0;
+        self.dtx_ms = 0;
+        self.encoder
+            .reset_state()
+            .context("failed to reset Opus encoder")
+    }
+
+    /// Caps the size of every encoded frame.
+    ///
+    /// Opus lowers quality for a frame rather than exceed the cap, so
+    /// variable-bitrate peaks never produce a frame larger than `max_bytes`.
+    pub fn set_max_payload_bytes(&mut self, max_bytes: usize) {
+        // ---
+        self.max_payload_bytes = max_bytes.clamp(1, MAX_PACKET_BYTES);
     }
 
     /// Encodes PCM audio samples into Opus format.
     ///
-    /// Expects exactly 320 samples (20ms at 16kHz). The output size varies
-    /// depending on audio complexity but is typically 60-120 bytes at 24 kbps.
+    /// Expects exactly one frame of samples (320 for 20ms at 16kHz). The
+    /// output size varies depending on audio complexity but is typically
+    /// 60-120 bytes for a 20ms frame at 24 kbps. With DTX on, frames deep
+    /// in a silence are a single byte.
+    ///
+    /// Allocates the output for every frame; hot paths should use
+    /// [`encode_into`](Self::encode_into) with a reused buffer.
     ///
     /// # Arguments
     ///
-    /// * `pcm` - Slice of 16-bit PCM samples (must be exactly 320 samples)
+    /// * `pcm` - Slice of 16-bit PCM samples (must be exactly one frame)
     ///
     /// # Returns
     ///
@@ -86,27 +492,97 @@ impl OpusEncoderWrapper {
     /// # Errors
     ///
     /// Returns error if:
-    /// - Input size is not exactly SAMPLES_PER_FRAME (320)
+    /// - Input size is not exactly [`samples_per_frame`](Self::samples_per_frame)
     /// - Opus encoding fails
     pub fn encode(&mut self, pcm: &[i16]) -> Result<Vec<u8>> {
         // ---
-        if pcm.len() != SAMPLES_PER_FRAME {
+        let mut output = Vec::new();
+        self.encode_into(pcm, &mut output)?;
+        Ok(output)
+    }
+
+    /// Encodes PCM audio samples into a caller-owned buffer.
+    ///
+    /// Same output as [`encode`](Self::encode), but `out` is cleared and
+    /// reused: once its capacity has grown to the largest frame the encoder
+    /// may produce, encoding does not allocate.
+    ///
+    /// # Arguments
+    ///
+    /// * `pcm` - Slice of 16-bit PCM samples (must be exactly one frame)
+    /// * `out` - Buffer replaced with the encoded frame
+    ///
+    /// # Returns
+    ///
+    /// The encoded frame length in bytes (`out.len()`).
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the input size is wrong or Opus encoding fails;
+    /// `out` is left empty.
+    pub fn encode_into(&mut self, pcm: &[i16], out: &mut Vec<u8>) -> Result<usize> {
+        // ---
+        out.clear();
+        let expected = self.samples_per_frame();
+        if pcm.len() != expected {
             anyhow::bail!(
                 "invalid frame size: expected {}, got {}",
-                SAMPLES_PER_FRAME,
+                expected,
                 pcm.len()
             );
         }
 
-        let mut output = vec![0u8; 4000]; // Max Opus frame size
-        let len = self
-            .encoder
-            .encode(pcm, &mut output)
-            .context("Opus encoding failed")?;
+        out.resize(self.max_payload_bytes, 0);
+        let len = match self.encoder.encode(pcm, out) {
+            Ok(len) => len,
+            Err(e) => {
+                out.clear();
+                return Err(e).context("Opus encoding failed");
+            }
+        };
 
-        output.truncate(len);
-        Ok(output)
+        out.truncate(len);
+        if self.config.dtx {
+            self.apply_dtx(pcm, out);
+        }
+        Ok(out.len())
     }
+
+    /// Cuts `output` down to a DTX frame once `pcm` ends a long enough
+    /// silence, except where a comfort-noise refresh is due.
+    fn apply_dtx(&mut self, pcm: &[i16], output: &mut Vec<u8>) {
+        // ---
+        if rms_dbfs(pcm) >= DTX_SILENCE_DBFS {
+            self.silent_ms = 0;
+            self.dtx_ms = 0;
+            return;
+        }
+
+        self.silent_ms += self.config.frame_ms;
+        if self.silent_ms <= DTX_HANGOVER_MS || output.is_empty() {
+            return;
+        }
+
+        self.dtx_ms += self.config.frame_ms;
+        if self.dtx_ms >= DTX_REFRESH_MS {
+            self.dtx_ms = 0;
+            return;
+        }
+
+        // Keep the TOC (mode, bandwidth, duration) as a code 0 packet
+        // holding one empty frame
+        output.truncate(1);
+        output[0] &= !0x03;
+    }
+}
+
+/// Returns the RMS level of `pcm` in dBFS (negative infinity for digital
+/// silence).
+fn rms_dbfs(pcm: &[i16]) -> f64 {
+    // ---
+    let energy: f64 = pcm.iter().map(|&s| (s as f64).powi(2)).sum();
+    let rms = (energy / pcm.len().max(1) as f64).sqrt();
+    20.0 * (rms / i16::MAX as f64).log10()
 }
 
 #[cfg(test)]
@@ -166,4 +642,263 @@ mod tests {
         // Tone should be less compressible than silence
         assert!(encoded.len() > 20);
     }
+
+    #[test]
+    fn test_with_bitrate_rejects_out_of_range() {
+        // ---
+        assert!(OpusEncoderWrapper::with_bitrate(MIN_BITRATE - 1).is_err());
+        assert!(OpusEncoderWrapper::with_bitrate(MAX_BITRATE + 1).is_err());
+        assert!(OpusEncoderWrapper::with_bitrate(0).is_err());
+
+        let encoder = OpusEncoderWrapper::with_bitrate(MIN_BITRATE).expect("min bitrate");
+        assert_eq!(encoder.bitrate(), MIN_BITRATE);
+        assert!(OpusEncoderWrapper::with_bitrate(MAX_BITRATE).is_ok());
+        assert_eq!(OpusEncoderWrapper::new().unwrap().bitrate(), BITRATE);
+
+        // Runtime changes are checked the same way and leave the old rate
+        let mut encoder = OpusEncoderWrapper::new().unwrap();
+        assert!(encoder.set_bitrate(MIN_BITRATE - 1).is_err());
+        assert_eq!(encoder.bitrate(), BITRATE);
+        encoder.set_bitrate(12000).expect("set bitrate");
+        assert_eq!(encoder.bitrate(), 12000);
+        assert!(encoder.set_expected_loss(101).is_err());
+        encoder.set_expected_loss(20).expect("set expected loss");
+        assert_eq!(
+            encoder.settings(),
+            EncoderSettings {
+                bitrate: 12000,
+                expected_loss_pct: 20,
+            }
+        );
+    }
+
+    #[test]
+    fn test_frame_duration_validation_and_sizes() {
+        // ---
+        for frame_ms in [0, 5, 30, 120] {
+            let config = OpusEncoderConfig {
+                frame_ms,
+                ..Default::default()
+            };
+            assert!(
+                OpusEncoderWrapper::with_config(config).is_err(),
+                "{frame_ms}ms"
+            );
+        }
+
+        for frame_ms in FRAME_DURATIONS_MS {
+            let mut encoder = OpusEncoderWrapper::with_config(OpusEncoderConfig {
+                frame_ms,
+                ..Default::default()
+            })
+            .expect("encoder");
+            let samples = SAMPLE_RATE as usize * frame_ms / 1000;
+            assert_eq!(encoder.samples_per_frame(), samples);
+
+            let encoded = encoder.encode(&vec![0i16; samples]).expect("encode");
+            let decoder = opus::Decoder::new(SAMPLE_RATE, Channels::Mono).unwrap();
+            assert_eq!(decoder.get_nb_samples(&encoded).unwrap(), samples);
+            assert!(encoder.encode(&vec![0i16; samples + 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_bitrate_changes_payload_size() {
+        // ---
+        let tone: Vec<i16> = (0..SAMPLE_RATE as usize)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                ((t * 440.0 * 2.0 * std::f32::consts::PI).sin() * 16000.0) as i16
+            })
+            .collect();
+
+        // Total payload bytes for one second of the tone
+        let encoded_bytes = |bits: i32| -> usize {
+            let mut encoder = OpusEncoderWrapper::with_bitrate(bits).expect("encoder");
+            tone.chunks_exact(SAMPLES_PER_FRAME)
+                .map(|frame| encoder.encode(frame).expect("encode").len())
+                .sum()
+        };
+
+        let low = encoded_bytes(12000);
+        let high = encoded_bytes(64000);
+        assert!(
+            high > low * 2,
+            "12 kbps: {low} bytes, 64 kbps: {high} bytes"
+        );
+    }
+
+    #[test]
+    fn test_fec_grows_payloads() {
+        // ---
+        // Voiced, amplitude-modulated signal so SILK has something to protect
+        let speech: Vec<i16> = (0..SAMPLE_RATE as usize)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let envelope = 0.5 + 0.5 * (t * 4.0 * 2.0 * std::f32::consts::PI).sin();
+                let voice = (t * 180.0 * 2.0 * std::f32::consts::PI).sin()
+                    + 0.5 * (t * 360.0 * 2.0 * std::f32::consts::PI).sin();
+                (voice * envelope * 8000.0) as i16
+            })
+            .collect();
+
+        let encoded_bytes = |fec: bool| -> usize {
+            let mut encoder = OpusEncoderWrapper::with_config(OpusEncoderConfig {
+                fec,
+                expected_loss_pct: 10,
+                ..Default::default()
+            })
+            .expect("encoder");
+            speech
+                .chunks_exact(SAMPLES_PER_FRAME)
+                .map(|frame| encoder.encode(frame).expect("encode").len())
+                .sum()
+        };
+
+        let plain = encoded_bytes(false);
+        let protected = encoded_bytes(true);
+        assert!(
+            protected * 20 > plain * 21,
+            "without FEC: {plain} bytes, with FEC: {protected} bytes"
+        );
+
+        let invalid = OpusEncoderConfig {
+            expected_loss_pct: 101,
+            ..Default::default()
+        };
+        assert!(OpusEncoderWrapper::with_config(invalid).is_err());
+    }
+
+    #[test]
+    fn test_dtx_cuts_long_silence_to_toc_frames() {
+        // ---
+        let tone: Vec<i16> = (0..SAMPLES_PER_FRAME)
+            .map(|i| ((i as f32 * 0.3).sin() * 12000.0) as i16)
+            .collect();
+        let silence = vec![0i16; SAMPLES_PER_FRAME];
+
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        encoder.set_dtx(true);
+        assert!(encoder.config().dtx);
+
+        let frames: Vec<Vec<u8>> = std::iter::once(&tone)
+            .chain(std::iter::repeat_n(&silence, 60))
+            .chain(std::iter::once(&tone))
+            .map(|frame| encoder.encode(frame).expect("encode"))
+            .collect();
+        let sizes: Vec<usize> = frames.iter().map(Vec::len).collect();
+        let dtx: Vec<bool> = frames.iter().map(|frame| is_dtx_frame(frame)).collect();
+
+        // Hangover, then DTX with a full frame every 400ms, then speech
+        let hangover = DTX_HANGOVER_MS / FRAME_DURATION_MS;
+        let refresh = DTX_REFRESH_MS / FRAME_DURATION_MS;
+        assert!(dtx[..=hangover].iter().all(|&d| !d), "{sizes:?}");
+        for (i, &is_dtx) in dtx.iter().enumerate().take(61).skip(hangover + 1) {
+            let refresh_due = (i - hangover).is_multiple_of(refresh);
+            assert_eq!(is_dtx, !refresh_due, "frame {i}: {sizes:?}");
+        }
+        assert!(!dtx[61]);
+
+        // DTX frames decode to a full frame of comfort noise
+        let mut decoder = opus::Decoder::new(SAMPLE_RATE, Channels::Mono).unwrap();
+        let mut out = vec![0i16; SAMPLES_PER_FRAME];
+        assert_eq!(frames[hangover + 1].len(), 1);
+        assert_eq!(
+            decoder
+                .decode(&frames[hangover + 1], &mut out, false)
+                .unwrap(),
+            SAMPLES_PER_FRAME
+        );
+
+        // Off by default, and switching it off stops the cut
+        let mut plain = OpusEncoderWrapper::new().expect("encoder");
+        assert!((0..40).all(|_| !is_dtx_frame(&plain.encode(&silence).unwrap())));
+        encoder.set_dtx(false);
+        assert!((0..40).all(|_| !is_dtx_frame(&encoder.encode(&silence).unwrap())));
+    }
+
+    #[test]
+    fn test_cbr_holds_frame_size_constant() {
+        // ---
+        // Alternating loud speech-like bursts and near silence, so VBR has
+        // something to vary on
+        let varied: Vec<i16> = (0..100 * SAMPLES_PER_FRAME)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                let frame = i / SAMPLES_PER_FRAME;
+                let level = if (frame / 5).is_multiple_of(2) {
+                    12000.0
+                } else {
+                    40.0
+                };
+                let voice = (t * 180.0 * 2.0 * std::f32::consts::PI).sin()
+                    + 0.4 * (t * 2300.0 * 2.0 * std::f32::consts::PI).sin();
+                (voice * level) as i16
+            })
+            .collect();
+
+        let frame_sizes = |vbr_mode: VbrMode| -> Vec<usize> {
+            let mut encoder = OpusEncoderWrapper::with_config(OpusEncoderConfig {
+                vbr_mode,
+                ..Default::default()
+            })
+            .expect("encoder");
+            varied
+                .chunks_exact(SAMPLES_PER_FRAME)
+                .map(|frame| encoder.encode(frame).expect("encode").len())
+                .collect()
+        };
+        let spread = |sizes: &[usize]| sizes.iter().max().unwrap() - sizes.iter().min().unwrap();
+
+        let cbr = frame_sizes(VbrMode::Cbr);
+        assert!(spread(&cbr) <= 2, "CBR: {cbr:?}");
+        assert!(cbr
+            .iter()
+            .all(|&len| len.abs_diff(NOMINAL_FRAME_BYTES) <= 2));
+
+        let vbr = frame_sizes(VbrMode::Vbr);
+        assert!(spread(&vbr) > 20, "VBR: {vbr:?}");
+    }
+
+    #[test]
+    fn test_encode_into_matches_encode() {
+        // ---
+        // Speech-like bursts, noise, and silence long enough for DTX
+        let mut seed = 0x2010u32;
+        let signal: Vec<i16> = (0..80 * SAMPLES_PER_FRAME)
+            .map(|i| match (i / SAMPLES_PER_FRAME) / 20 {
+                0 => ((i as f32 * 0.07).sin() * 12000.0) as i16,
+                1 => {
+                    seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    (seed >> 17) as i16
+                }
+                2 => 0,
+                _ => ((i as f32 * 0.21).sin() * 6000.0) as i16,
+            })
+            .collect();
+
+        let config = OpusEncoderConfig {
+            dtx: true,
+            ..Default::default()
+        };
+        let mut allocating = OpusEncoderWrapper::with_config(config.clone()).unwrap();
+        let mut reusing = OpusEncoderWrapper::with_config(config).unwrap();
+        let mut out = Vec::new();
+        let mut buffer = None;
+
+        for (i, frame) in signal.chunks_exact(SAMPLES_PER_FRAME).enumerate() {
+            let expected = allocating.encode(frame).expect("encode");
+            let len = reusing.encode_into(frame, &mut out).expect("encode_into");
+            assert_eq!(len, out.len());
+            assert_eq!(out, expected, "frame {i}");
+
+            // The buffer grows once, then is reused as is
+            let current = (out.as_ptr(), out.capacity());
+            assert_eq!(*buffer.get_or_insert(current), current, "frame {i}");
+        }
+
+        // A bad frame leaves the buffer empty rather than holding stale audio
+        assert!(reusing.encode_into(&signal[..10], &mut out).is_err());
+        assert!(out.is_empty());
+    }
 }
@@ -3,95 +3,2304 @@
 //! Provides audio streaming functionality over RTP with Opus encoding.
 //! This library can be used to build custom senders or for integration testing.
 
+pub mod adapt;
 pub mod audio;
+pub mod bitrate;
 pub mod codec;
+pub mod dsp;
 pub mod network;
+pub mod ogg;
+pub mod pacing;
+pub mod pcap;
+pub mod probe;
+pub mod ratelimit;
+pub mod resolve;
+pub mod rtx;
+pub mod sink;
+pub mod source;
+pub mod tcp;
 
-pub use audio::{read_wav, AudioData};
-pub use codec::OpusEncoderWrapper;
-pub use network::RtpSender;
-pub use rtp_opus_common::RtpPacket;
+pub use adapt::{AdaptationConfig, AdaptationController, AdaptationReason};
+pub use audio::{
+    analyze_wav_silence, read_audio, read_audio_with_options, read_audio_with_silence, read_wav,
+    read_wav_with_options, read_wav_with_silence, resample, Agc, AgcConfig, AudioData, AudioFormat,
+    AudioReadOptions, ChannelMap, ConvertOptions, LevelMeter, NormalizeConfig, NormalizeMode,
+    ResampleQuality, Signal, SignalGenerator, TrimConfig, WavFrameReader,
+};
+pub use bitrate::{BitrateController, DeliveryFeedback, RampConfig, RampPhase};
+pub use codec::{EncoderSettings, OpusEncoderConfig, OpusEncoderWrapper, VbrMode};
+pub use dsp::{Biquad, HighPassFilter};
+pub use network::{DestinationStats, ErrorPolicy, RtpSender, SenderNetworkConfig, SenderStats};
+pub use ogg::{is_ogg_opus, read_ogg_opus, OggOpusFile, OpusHead, OpusPacket};
+pub use pacing::{CatchUpPolicy, Pacer, Tick};
+pub use pcap::PcapWriter;
+pub use probe::{analyze_silence, ChannelSilence, SilenceConfig, SilenceReport, SilentRun};
+pub use ratelimit::{RateLimit, TokenBucket};
+pub use resolve::{Resolver, SystemResolver};
+pub use rtp_opus_common::{BuildInfo, MetricsContext, RtpPacket, RtpdumpFile};
+pub use rtx::RtxCache;
+pub use sink::PacketSink;
+pub use source::{FrameSource, GapPolicy, PcmFrames, PcmStream, PlaylistFrames, TailPolicy};
 
 use anyhow::{Context, Result};
+use rtp_opus_common::{
+    abs_send_time_now, encode_transport_sequence, AnomalyLogConfig, SessionConfig, SessionEvent,
+    SessionTracker, ABS_SEND_TIME_LEN, MAX_PADDING, PAYLOAD_CRC_LEN, PAYLOAD_TYPE_OPUS,
+    RTP_HEADER_LEN, TRANSPORT_CC_LEN,
+};
+use std::fmt;
+use tokio::sync::watch;
 use tracing::warn;
 
-/// Streams audio frames over RTP.
+/// Returns the version, commit, and enabled features of this crate and
+/// the crates it links.
+pub fn build_info() -> BuildInfo {
+    // ---
+    BuildInfo::new("sender", env!("CARGO_PKG_VERSION"))
+        .with_feature("sender/metrics-server", cfg!(feature = "metrics-server"))
+}
+
+/// Pacing and packet shaping options for [`stream_audio`].
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    // ---
+    /// Milliseconds between packet transmissions (normally the encoder
+    /// frame duration)
+    pub interval_ms: u64,
+
+    /// Restart from the beginning when the audio ends (for
+    /// [`stream_audio`]; a [`FrameSource`] decides for itself)
+    pub loop_audio: bool,
+
+    /// When looping, stop after this many passes (0 = never stop); for
+    /// [`stream_audio`] and [`stream_ogg_opus`] (see
+    /// [`PcmFrames::with_loop_count`])
+    pub loop_count: u64,
+
+    /// When looping in [`stream_audio`], fade the first and last this much
+    /// of every pass so the wrap-around does not click (see
+    /// [`PcmFrames::with_loop_fade`])
+    pub loop_fade: std::time::Duration,
+
+    /// When looping in [`stream_audio`], play this much silence between
+    /// passes (see [`PcmFrames::with_loop_gap`])
+    pub loop_gap: std::time::Duration,
+
+    /// Whether gap frames from the source (see [`FrameSource::in_gap`])
+    /// are sent as silence or skipped
+    pub gap: GapPolicy,
+
+    /// What [`stream_audio`] does with samples after the last complete
+    /// frame (a [`FrameSource`] decides for itself)
+    pub tail: TailPolicy,
+
+    /// How frames that became overdue during a stall are sent
+    pub catch_up: CatchUpPolicy,
+
+    /// RTP payload type of every packet, e.g. the one negotiated for Opus
+    /// in SDP (at most [`rtp_opus_common::MAX_PAYLOAD_TYPE`]); a replayed
+    /// capture keeps the recorded ones
+    pub payload_type: u8,
+
+    /// Stamp every packet with its send time in an abs-send-time header
+    /// extension element with this ID (1 to 14, as negotiated in SDP), so
+    /// the receiver can follow changes in one-way delay. The stamp is
+    /// taken as each packet is released, which with batching is before it
+    /// goes out.
+    pub abs_send_time_id: Option<u8>,
+
+    /// Number every packet with a transport-wide sequence number in a
+    /// header extension element with this ID (1 to 14, as negotiated in
+    /// SDP), counting from zero at the start of the stream independently
+    /// of the RTP sequence. A retransmission repeats its packet's number.
+    pub transport_cc_id: Option<u8>,
+
+    /// Pad every packet to exactly this many bytes (RTP header, payload,
+    /// and padding) so packet size does not reveal speech activity
+    pub pad_packets_to: Option<usize>,
+
+    /// Do not transmit DTX frames (see [`codec::is_dtx_frame`]). Skipped
+    /// frames consume no sequence number, so the receiver sees a
+    /// contiguous sequence with a timestamp jump rather than a loss.
+    pub suppress_dtx: bool,
+
+    /// How often send failures are summarized in the log after the first
+    pub anomaly_log: AnomalyLogConfig,
+
+    /// Packets handed to the network per call (see
+    /// [`RtpSender::send_batch`]); `None` batches
+    /// [`network::DEFAULT_BATCH`] packets
+    /// with pacing disabled, where frames come faster than real time, and
+    /// sends each packet on its own otherwise. With pacing, a batch waits
+    /// for its last frame, so packets go out in bursts.
+    pub batch: Option<usize>,
+
+    /// How many send failures in a row are tolerated before streaming
+    /// stops with an error, and the wait after each (set on the
+    /// [`RtpSender`] when streaming starts)
+    pub send_errors: ErrorPolicy,
+
+    /// Append a CRC32 of each payload as a 4-byte trailer (see
+    /// [`RtpPacket::append_payload_crc`]); the receiver must be verifying
+    /// payloads, or it decodes the trailer as audio
+    pub verify_payloads: bool,
+
+    /// Start at a low bitrate and ramp up to the encoder's configured
+    /// bitrate (see [`BitrateController`])
+    pub ramp: Option<RampConfig>,
+
+    /// Encoder bitrate and expected loss set from outside while streaming,
+    /// e.g. from receiver feedback. The current value applies from the
+    /// first frame and every change from the next frame, without
+    /// resetting the encoder; with [`ramp`](Self::ramp) the bitrate is the
+    /// ramp's target.
+    pub encoder_control: Option<watch::Receiver<EncoderSettings>>,
+
+    /// Steer the level of each frame before encoding (see [`Agc`]); for
+    /// live input, which cannot be normalized ahead of time
+    pub agc: Option<AgcConfig>,
+
+    /// Remove rumble below this frequency in Hz before encoding (see
+    /// [`HighPassFilter`]); applied ahead of the AGC
+    pub highpass_hz: Option<f64>,
+
+    /// Stop streaming once this becomes true. Checked while waiting for
+    /// the next frame, so a packet being sent always goes out whole, and
+    /// the stream then ends normally with its summary.
+    pub cancel: Option<watch::Receiver<bool>>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        // ---
+        Self {
+            interval_ms: codec::FRAME_DURATION_MS as u64,
+            loop_audio: false,
+            loop_count: 0,
+            loop_fade: std::time::Duration::ZERO,
+            loop_gap: std::time::Duration::ZERO,
+            gap: GapPolicy::SendSilence,
+            tail: TailPolicy::Discard,
+            catch_up: CatchUpPolicy::default(),
+            payload_type: PAYLOAD_TYPE_OPUS,
+            abs_send_time_id: None,
+            transport_cc_id: None,
+            pad_packets_to: None,
+            suppress_dtx: false,
+            anomaly_log: AnomalyLogConfig::default(),
+            batch: None,
+            send_errors: ErrorPolicy::default(),
+            verify_payloads: false,
+            ramp: None,
+            encoder_control: None,
+            agc: None,
+            highpass_hz: None,
+            cancel: None,
+        }
+    }
+}
+
+impl StreamOptions {
+    // ---
+    /// Returns the largest encoded payload that fits `pad_packets_to`,
+    /// or `None` when padding is off.
+    ///
+    /// # Arguments
+    ///
+    /// * `encoder` - Encoder bitrate and frame duration
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the target cannot hold a frame at the encoder
+    /// settings, or if padding a minimal frame up to it would need more
+    /// padding than one RTP packet can carry.
+    pub fn max_payload_bytes(&self, encoder: &OpusEncoderConfig) -> Result<Option<usize>> {
+        // ---
+        let Some(target) = self.pad_packets_to else {
+            return Ok(None);
+        };
+
+        let overhead = self.packet_overhead();
+        let frame_bytes = encoder.nominal_frame_bytes();
+        let min_target = overhead + frame_bytes;
+        if target < min_target {
+            anyhow::bail!(
+                "--pad-packets-to {} is too small: {}ms frames at {} bps need up to {} bytes \
+                 (minimum {} with the RTP header)",
+                target,
+                encoder.frame_ms,
+                encoder.bitrate,
+                frame_bytes,
+                min_target
+            );
+        }
+
+        // Smallest Opus frame is one byte; the rest must fit in the padding
+        let max_target = overhead + 1 + MAX_PADDING;
+        if target > max_target {
+            anyhow::bail!(
+                "--pad-packets-to {} is too large: RTP padding allows at most {} bytes",
+                target,
+                max_target
+            );
+        }
+
+        Ok(Some(target - overhead))
+    }
+
+    /// Checks that every payload from `payload_sizes` can be padded to
+    /// `pad_packets_to`; used when packets arrive already encoded.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the largest payload does not fit the target, or if
+    /// the smallest would need more padding than one RTP packet can carry.
+    pub fn check_padding(&self, payload_sizes: impl Iterator<Item = usize>) -> Result<()> {
+        // ---
+        let Some(target) = self.pad_packets_to else {
+            return Ok(());
+        };
+
+        let (smallest, largest) = payload_sizes.fold((usize::MAX, 0), |(min, max), len| {
+            (min.min(len), max.max(len))
+        });
+        let overhead = self.packet_overhead();
+        if overhead + largest > target {
+            anyhow::bail!(
+                "--pad-packets-to {} is too small: the largest packet needs {} bytes",
+                target,
+                overhead + largest
+            );
+        }
+        if smallest != usize::MAX && target > overhead + smallest + MAX_PADDING {
+            anyhow::bail!(
+                "--pad-packets-to {} is too large: the smallest packet would need more \
+                 than {} bytes of RTP padding",
+                target,
+                MAX_PADDING
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the packets sent per call: [`batch`](Self::batch) if set,
+    /// else [`network::DEFAULT_BATCH`] with pacing disabled and 1 with it.
+    pub fn batch_size(&self) -> usize {
+        // ---
+        match self.batch {
+            Some(packets) => packets.clamp(1, network::MAX_BATCH),
+            None if self.interval_ms == 0 => network::DEFAULT_BATCH,
+            None => 1,
+        }
+    }
+
+    /// Returns the bytes every packet carries besides the codec payload.
+    fn packet_overhead(&self) -> usize {
+        // ---
+        // The CRC trailer counts as payload on the wire
+        RTP_HEADER_LEN
+            + if self.verify_payloads {
+                PAYLOAD_CRC_LEN
+            } else {
+                0
+            }
+            + self.extension_len()
+    }
+
+    /// Returns the header extension bytes every packet carries: the
+    /// profile and length, then each element's ID and length byte and
+    /// data, padded to a whole word.
+    fn extension_len(&self) -> usize {
+        // ---
+        let elements = [
+            self.abs_send_time_id.map(|_| 1 + ABS_SEND_TIME_LEN),
+            self.transport_cc_id.map(|_| 1 + TRANSPORT_CC_LEN),
+        ];
+        match elements.iter().flatten().sum::<usize>() {
+            0 => 0,
+            len => 4 + len.next_multiple_of(4),
+        }
+    }
+}
+
+/// What a stream sent, returned by [`stream_frames`], [`stream_audio`],
+/// [`stream_ogg_opus`], and [`stream_rtpdump`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StreamSummary {
+    // ---
+    /// Frames taken from the source and encoded (Ogg Opus packets for
+    /// passthrough, recorded packets for a replay), including DTX frames
+    /// that were not sent
+    pub frames_sent: u64,
+
+    /// RTP packets transmitted
+    pub packets_sent: u64,
+
+    /// Payload bytes transmitted, including any CRC trailer and padding
+    /// but not RTP headers
+    pub bytes_sent: u64,
+
+    /// Complete passes through the input (see
+    /// [`FrameSource::passes_completed`])
+    pub loops_completed: u64,
+
+    /// Time from the first frame until the stream ended
+    pub duration: std::time::Duration,
+
+    /// Trailing samples dropped for not making up a full frame, over all
+    /// passes
+    pub discarded_tail_samples: u64,
+}
+
+impl fmt::Display for StreamSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(
+            f,
+            "{} frames, {} packets, {} bytes in {:.2}s",
+            self.frames_sent,
+            self.packets_sent,
+            self.bytes_sent,
+            self.duration.as_secs_f64()
+        )?;
+        if self.loops_completed > 0 {
+            write!(f, ", passes: {}", self.loops_completed)?;
+        }
+        if self.discarded_tail_samples > 0 {
+            write!(
+                f,
+                ", {} trailing samples discarded",
+                self.discarded_tail_samples
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Context on an error that ended a stream partway, holding what was sent
+/// before it. Reach it with `err.downcast_ref::<StreamInterrupted>()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamInterrupted {
+    // ---
+    /// Counts up to the failure
+    pub summary: StreamSummary,
+}
+
+impl fmt::Display for StreamInterrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(f, "stream stopped after {}", self.summary)
+    }
+}
+
+/// Streams preloaded audio over RTP.
 ///
-/// Encodes each frame with Opus and transmits as RTP packets with
-/// proper timing and sequencing.
+/// Serves the frames of `audio` (looping with [`StreamOptions::loop_audio`],
+/// for [`StreamOptions::loop_count`] passes if set, faded at the loop point with [`StreamOptions::loop_fade`], and with
+/// [`StreamOptions::loop_gap`] of silence between passes) to
+/// [`stream_frames`]. A trailing partial frame is discarded or padded with
+/// silence as [`StreamOptions::tail`] says; padded, it is sent as a full
+/// frame on every pass and timestamps advance by a full frame for it.
+///
+/// Returns a [`StreamSummary`] counting passes through `audio` and the
+/// tail samples discarded on each.
 ///
 /// # Arguments
 ///
 /// * `audio` - Audio data to stream
 /// * `encoder` - Opus encoder instance
-/// * `sender` - RTP network sender
+/// * `sender` - Where packets go: an [`RtpSender`], a [`PcapWriter`], or
+///   a pair of sinks (see [`PacketSink`])
+/// * `metrics` - Metrics registry to report into
 /// * `ssrc` - Synchronization source identifier for this session
-/// * `interval_ms` - Milliseconds between packet transmissions
+/// * `options` - Pacing, looping, and padding options
 ///
 /// # Errors
 ///
-/// Returns error if encoding or network transmission fails.
-pub async fn stream_audio(
+/// Returns the same errors as [`stream_frames`].
+pub async fn stream_audio<K: PacketSink>(
     audio: &AudioData,
     encoder: &mut OpusEncoderWrapper,
-    sender: &mut RtpSender,
-    metrics: &rtp_opus_common::MetricsContext,
+    sender: &mut K,
+    metrics: &MetricsContext,
     ssrc: u32,
-    interval_ms: u64,
-    loop_audio: bool,
-) -> Result<()> {
+    options: &StreamOptions,
+) -> Result<StreamSummary> {
     // ---
-    let mut sequence: u16 = 0;
-    let mut timestamp: u32 = 0;
-    let mut frame_count = 0;
-
-    // Only stream complete frames. Any tail shorter than a full Opus frame
-    // is discarded to avoid partial-packet semantics at EOF.
-    let full_frames = audio.samples.chunks_exact(codec::SAMPLES_PER_FRAME);
-    let remainder = full_frames.remainder().len();
+    // Every packet carries a full Opus frame. A shorter tail is either
+    // dropped or padded out; logged once here rather than on every pass.
+    let samples_per_frame = encoder.samples_per_frame();
+    let remainder = audio.samples.len() % samples_per_frame;
     if remainder != 0 {
-        warn!(
-            "Discarding {} trailing samples at EOF (not enough for a full frame)",
-            remainder
-        );
+        match options.tail {
+            TailPolicy::Discard => warn!(
+                "Discarding {} trailing samples at EOF (not enough for a full frame)",
+                remainder
+            ),
+            TailPolicy::PadWithSilence => tracing::info!(
+                "Padding {} trailing samples with silence to a full frame",
+                remainder
+            ),
+        }
+    }
+    let padded = options.tail == TailPolicy::PadWithSilence && !audio.samples.is_empty();
+    if audio.samples.len() < samples_per_frame && !padded {
+        warn!("No complete frames to stream");
+        return Ok(StreamSummary {
+            discarded_tail_samples: remainder as u64,
+            ..Default::default()
+        });
     }
 
-    loop {
-        // ---
-        for frame in audio.samples.chunks_exact(codec::SAMPLES_PER_FRAME) {
+    let mut source = PcmFrames::new(&audio.samples, options.loop_audio)
+        .with_loop_count(options.loop_count)
+        .with_loop_fade(options.loop_fade)
+        .with_loop_gap(options.loop_gap)
+        .with_tail(options.tail);
+    stream_frames(&mut source, encoder, sender, metrics, ssrc, options).await
+}
+
+/// Streams several preloaded files back to back as one RTP stream.
+///
+/// Serves the frames of each of `files` in turn to [`stream_frames`], so
+/// sequence numbers and timestamps run on across file boundaries and the
+/// receiver hears one uninterrupted session. Each file's trailing partial
+/// frame is handled as [`StreamOptions::tail`] says. With
+/// [`StreamOptions::loop_audio`], the whole playlist loops, for
+/// [`StreamOptions::loop_count`] passes if set; loop fades and gaps do not
+/// apply.
+///
+/// # Errors
+///
+/// Returns the same errors as [`stream_frames`].
+pub async fn stream_playlist<K: PacketSink>(
+    files: &[AudioData],
+    encoder: &mut OpusEncoderWrapper,
+    sender: &mut K,
+    metrics: &MetricsContext,
+    ssrc: u32,
+    options: &StreamOptions,
+) -> Result<StreamSummary> {
+    // ---
+    let mut source = PlaylistFrames::new(files, encoder.samples_per_frame(), options.loop_audio)
+        .with_loop_count(options.loop_count)
+        .with_tail(options.tail);
+    stream_frames(&mut source, encoder, sender, metrics, ssrc, options).await
+}
+
+/// Streams audio frames from a [`FrameSource`] over RTP.
+///
+/// Encodes each frame with Opus and transmits as RTP packets with
+/// proper timing and sequencing. Frames are paced on a drift-free
+/// schedule; frames that become overdue during a stall are handled by
+/// [`StreamOptions::catch_up`], and the RTP timestamp always reflects the
+/// frame's media position (so skipped frames leave a timestamp gap).
+///
+/// With [`StreamOptions::suppress_dtx`], DTX frames the encoder emits
+/// during silence are not sent. The sequence number only advances for
+/// packets actually sent, so a DTX gap shows up at the receiver as a
+/// timestamp jump between consecutive sequence numbers, not as loss.
+///
+/// The first packet of each talkspurt carries the marker bit (RFC 3551,
+/// RFC 7587): the first of the stream and the first after frames went
+/// unsent, whether suppressed as DTX or skipped as a loop gap.
+///
+/// Decoder refresh requests from the receiver are checked before each
+/// frame; on one the encoder is reset and the next packet carries the
+/// marker bit.
+///
+/// With [`StreamOptions::verify_payloads`], every payload carries a CRC32
+/// trailer the receiver checks before decoding.
+///
+/// Silence a looping source inserts between passes is sent like any other
+/// frame, or with [`GapPolicy::Skip`] not sent at all: the timestamp then
+/// jumps across the gap and the next packet carries the marker bit.
+///
+/// The encoder's rate control mode is exported as the `encoder_vbr_mode`
+/// gauge.
+///
+/// With [`StreamOptions::ramp`], the encoder starts at the ramp floor and
+/// climbs to its configured bitrate; a refresh request mid-ramp counts as
+/// loss and holds the bitrate where it is. The bitrate in effect is
+/// exported as the `encoder_target_bitrate_bps` gauge.
+///
+/// Settings sent on [`StreamOptions::encoder_control`] are checked before
+/// each frame is encoded and logged when they change. A change that Opus
+/// does not support is logged and ignored.
+///
+/// Time in each session state (see [`SessionTracker`]) is exported as
+/// metrics and logged with the state timeline when streaming ends.
+///
+/// Setting [`StreamOptions::cancel`] stops the stream between frames: the
+/// wait for the next slot or source input is abandoned, and the summary of
+/// what was sent is returned as for a stream that ran out of frames.
+///
+/// Packets go out [`StreamOptions::batch_size`] at a time, in order;
+/// those still queued when the stream ends, for whatever reason, are sent
+/// before returning.
+///
+/// Pacing follows absolute deadlines (see [`Pacer`]), so encode time and
+/// scheduler latency do not accumulate; how late each packet went out
+/// against its deadline is exported as the `pacing_deviation_seconds`
+/// histogram.
+///
+/// Returns a [`StreamSummary`] of what was sent; the pass and discarded
+/// sample counts come from `source`.
+///
+/// Frames are pulled from `source` one at a time as the pacer releases
+/// them, and the stream ends when the source has no more. A source that
+/// waits for input (see [`PcmStream`]) holds up the pacer meanwhile; the
+/// delay is then handled as a stall by [`StreamOptions::catch_up`].
+/// [`StreamOptions::loop_audio`] is not consulted; looping is up to the
+/// source.
+///
+/// # Arguments
+///
+/// * `source` - Where the PCM frames come from
+/// * `encoder` - Opus encoder instance
+/// * `sender` - Where packets go: an [`RtpSender`], a [`PcapWriter`], or
+///   a pair of sinks (see [`PacketSink`])
+/// * `metrics` - Metrics registry to report into
+/// * `ssrc` - Synchronization source identifier for this session
+/// * `options` - Pacing and padding options
+///
+/// # Errors
+///
+/// Returns error if the options or the initial encoder settings are
+/// invalid, if reading the source or encoding fails, or if sends fail more
+/// times in a row than [`StreamOptions::send_errors`] allows. Once
+/// streaming has started, the error carries a [`StreamInterrupted`]
+/// context with the counts up to the failure.
+pub async fn stream_frames<S: FrameSource, K: PacketSink>(
+    source: &mut S,
+    encoder: &mut OpusEncoderWrapper,
+    sender: &mut K,
+    metrics: &MetricsContext,
+    ssrc: u32,
+    options: &StreamOptions,
+) -> Result<StreamSummary> {
+    // ---
+    if let Some(max_payload) = options.max_payload_bytes(encoder.config())? {
+        encoder.set_max_payload_bytes(max_payload);
+    }
+
+    sender.start(options, metrics);
+
+    let vbr_mode = encoder.config().vbr_mode;
+    for mode in VbrMode::ALL {
+        metrics
+            .encoder_vbr_mode
+            .with_label_values(&[mode.as_str()])
+            .set((mode == vbr_mode) as i64);
+    }
+
+    let mut bitrate = BitrateController::new(encoder.bitrate(), options.ramp.clone())?;
+    let mut control = options.encoder_control.clone();
+    if let Some(control) = &mut control {
+        let settings = *control.borrow_and_update();
+        apply_encoder_settings(encoder, &mut bitrate, settings)
+            .context("invalid encoder settings")?;
+    }
+
+    // Reused for every frame so encoding does not allocate
+    let mut encoded = Vec::with_capacity(codec::MAX_PACKET_BYTES);
+
+    let mut sequence: u16 = 0;
+    let mut transport_sequence: u16 = 0;
+    let mut talkspurt_start = true;
+
+    let samples_per_frame = encoder.samples_per_frame();
+    let mut frame = vec![0i16; samples_per_frame];
+    let mut highpass = options
+        .highpass_hz
+        .map(|hz| HighPassFilter::new(hz, codec::SAMPLE_RATE));
+    let mut agc = options.agc.map(Agc::new);
+
+    let interval = std::time::Duration::from_millis(options.interval_ms);
+    let mut pacer = Pacer::new(interval, options.catch_up);
+    let mut session = SessionTracker::new(SessionConfig::default(), std::time::Instant::now());
+
+    // Packets waiting to go out together, when each was released, and
+    // whether each went out
+    let batch_size = options.batch_size();
+    let mut batch = Batch::with_capacity(batch_size);
+
+    let started = tokio::time::Instant::now();
+    let mut summary = StreamSummary::default();
+    let result: Result<()> = async {
+        loop {
+            // ---
+            // Wait for the next frame's slot; skipped frames still advance
+            // the media position, and with it the RTP timestamp
+            let tick = tokio::select! {
+                tick = pacer.next_frame() => tick,
+                () = cancelled(options.cancel.as_ref()) => break,
+            };
+            if tick.catch_up_started {
+                metrics.pacing_catchup_events_total.inc();
+                session.record(SessionEvent::Disrupted, std::time::Instant::now());
+            }
+            metrics.frames_skipped_total.inc_by(tick.skipped);
+
+            // Frames skipped for DTX or a gap send nothing; a long enough
+            // silence sends a keepalive instead
+            sender.keep_alive().await;
+            sender.send_sender_report().await;
+
+            // A source waiting for input must not hold up a shutdown
+            let read = tokio::select! {
+                read = source.read_frame(tick.frame, &mut frame) => read,
+                () = cancelled(options.cancel.as_ref()) => break,
+            };
+            if !read.with_context(|| format!("failed to read frame {}", tick.frame))? {
+                break;
+            }
+            if source.in_gap() && options.gap == GapPolicy::Skip {
+                talkspurt_start = true;
+                continue;
+            }
+            if let Some(highpass) = &mut highpass {
+                highpass.process(&mut frame);
+            }
+            if let Some(agc) = &mut agc {
+                agc.process(&mut frame);
+            }
+            let timestamp = (tick.frame as u32).wrapping_mul(samples_per_frame as u32);
+
+            // A receiver that lost decoder sync asked for a fresh start: reset
+            // the encoder and flag the first frame coded without history
+            let refresh_requests = sender.poll_refresh_requests(ssrc);
+            if refresh_requests > 0 {
+                metrics
+                    .decoder_refresh_requests_received_total
+                    .inc_by(refresh_requests as u64);
+                encoder.reset()?;
+                metrics.encoder_resets_total.inc();
+                session.record(SessionEvent::Disrupted, std::time::Instant::now());
+                tracing::info!("Encoder reset on receiver request at seq={}", sequence);
+
+                // Loss mid-ramp: stop climbing rather than push into congestion
+                if bitrate.phase() == RampPhase::Ramping {
+                    bitrate.on_feedback(DeliveryFeedback::Loss);
+                    tracing::info!("Bitrate ramp held at {} bps after loss", bitrate.current());
+                }
+            }
+
+            // A closed channel leaves the last settings in place
+            if let Some(control) = control
+                .as_mut()
+                .filter(|control| control.has_changed().unwrap_or(false))
+            {
+                let settings = *control.borrow_and_update();
+                if let Err(err) = apply_encoder_settings(encoder, &mut bitrate, settings) {
+                    warn!("Ignoring encoder settings: {:#}", err);
+                }
+            }
+            if let Some(bits) = bitrate.poll(std::time::Instant::now()) {
+                encoder.set_bitrate(bits)?;
+                metrics.encoder_target_bitrate_bps.set(bits as i64);
+                tracing::debug!("Encoder bitrate {} bps (ramp {})", bits, bitrate.phase());
+            }
+
             // Encode frame (measure cold-ish but still small)
             let start = std::time::Instant::now();
-            let payload = encoder
-                .encode(frame)
-                .with_context(|| format!("failed to encode frame {}", frame_count))?;
+            encoder
+                .encode_into(&frame, &mut encoded)
+                .with_context(|| format!("failed to encode frame {}", summary.frames_sent))?;
             metrics
                 .encode_seconds
                 .observe(start.elapsed().as_secs_f64());
 
+            if options.suppress_dtx && codec::is_dtx_frame(&encoded) {
+                metrics.frames_dtx_suppressed_total.inc();
+                summary.frames_sent += 1;
+                talkspurt_start = true;
+                continue;
+            }
+
             // Create and send RTP packet
-            let packet = RtpPacket::new(sequence, timestamp, ssrc, payload);
-            sender
-                .send(&packet)
-                .await
-                .with_context(|| format!("failed to send packet {}", sequence))?;
+            // Only the encoded bytes are copied out of the scratch buffer
+            let mut packet = RtpPacket::new(sequence, timestamp, ssrc, encoded.to_vec())
+                .with_payload_type(options.payload_type)?;
+            packet.marker = refresh_requests > 0 || talkspurt_start;
+            batch.push(prepare_packet(
+                packet,
+                &mut transport_sequence,
+                metrics,
+                options,
+            )?);
+            if batch.len() >= batch_size {
+                send_batch(sender, &mut batch, metrics, &mut summary).await?;
+            }
+            record_pacing_deviation(metrics, interval, &tick);
+            talkspurt_start = false;
+            let now = std::time::Instant::now();
+            bitrate.on_sent(now);
+            session.record(SessionEvent::Packet, now);
+            session.publish(metrics, now);
 
-            metrics.packets_sent_total.inc();
-            metrics.bytes_sent_total.inc_by(packet.payload.len() as u64);
+            sequence = sequence.wrapping_add(1);
+            summary.frames_sent += 1;
+        }
+        Ok(())
+    }
+    .await;
+
+    // Packets still waiting go out even if streaming stopped on an error
+    let flushed = send_batch(sender, &mut batch, metrics, &mut summary).await;
+    let result = result.and(flushed).and_then(|()| sender.finish());
+    summary.duration = started.elapsed();
+    summary.loops_completed = source.passes_completed();
+    summary.discarded_tail_samples = source.discarded_samples();
+    result.map_err(|err| err.context(StreamInterrupted { summary }))?;
+
+    if options
+        .cancel
+        .as_ref()
+        .is_some_and(|cancel| *cancel.borrow())
+    {
+        tracing::info!("Stopped on request");
+    }
+    tracing::info!("Streamed {} frames", summary.frames_sent);
+    if let Some(agc) = &agc {
+        tracing::info!("AGC gain at end: {:+.1} dB", agc.gain_db());
+        if agc.clipped() > 0 {
+            tracing::warn!("AGC clipped {} samples", agc.clipped());
+        }
+    }
+    finish_stream(&mut session, metrics);
+    Ok(summary)
+}
+
+/// Streams the packets of an Ogg Opus file over RTP without re-encoding.
+///
+/// Each packet becomes one RTP payload as coded. The RTP timestamp
+/// advances by each packet's own duration (see [`ogg::packet_samples`]),
+/// converted to the sender clock rate, so files mixing frame sizes keep
+/// correct timing. Pacing, looping, catch-up, DTX suppression, talkspurt
+/// markers, padding, and payload verification work as in
+/// [`stream_frames`]; the caller sets
+/// [`StreamOptions::interval_ms`] to the file's packet duration.
+///
+/// Without an encoder there is nothing to reset, so decoder refresh
+/// requests are counted and logged but not acted on. A trickle start is
+/// not possible either.
+///
+/// # Arguments
+///
+/// * `file` - Packets read by [`read_ogg_opus`]
+/// * `sender` - Where packets go: an [`RtpSender`], a [`PcapWriter`], or
+///   a pair of sinks (see [`PacketSink`])
+/// * `metrics` - Metrics registry to report into
+/// * `ssrc` - Synchronization source identifier for this session
+/// * `options` - Pacing, looping, and padding options
+///
+/// # Errors
+///
+/// Returns error if [`StreamOptions::ramp`] or
+/// [`StreamOptions::encoder_control`] is set, if the packets do not
+/// fit the padding target, or if sends fail more times in a row than
+/// [`StreamOptions::send_errors`] allows; a failed transmission carries a [`StreamInterrupted`] context as for
+/// [`stream_frames`].
+pub async fn stream_ogg_opus<K: PacketSink>(
+    file: &OggOpusFile,
+    sender: &mut K,
+    metrics: &MetricsContext,
+    ssrc: u32,
+    options: &StreamOptions,
+) -> Result<StreamSummary> {
+    // ---
+    if options.ramp.is_some() {
+        anyhow::bail!("a bitrate ramp needs the encoder and cannot apply to Ogg Opus passthrough");
+    }
+    if options.encoder_control.is_some() {
+        anyhow::bail!("encoder settings cannot apply to Ogg Opus passthrough");
+    }
+    options.check_padding(file.packets.iter().map(|p| p.data.len()))?;
+
+    sender.start(options, metrics);
+
+    if file.packets.is_empty() {
+        warn!("No packets to stream");
+        return Ok(StreamSummary::default());
+    }
+
+    // RTP timestamp of each packet relative to the start of the file
+    let mut offsets = Vec::with_capacity(file.packets.len());
+    let mut position = 0u64;
+    for packet in &file.packets {
+        offsets.push(ogg::rtp_timestamp_units(position));
+        position += packet.samples as u64;
+    }
+    let file_units = ogg::rtp_timestamp_units(position);
+
+    let mut sequence: u16 = 0;
+    let mut transport_sequence: u16 = 0;
+    let mut talkspurt_start = true;
+
+    let interval = std::time::Duration::from_millis(options.interval_ms);
+    let mut pacer = Pacer::new(interval, options.catch_up);
+    let mut session = SessionTracker::new(SessionConfig::default(), std::time::Instant::now());
+
+    // Packets waiting to go out together, when each was released, and
+    // whether each went out
+    let batch_size = options.batch_size();
+    let mut batch = Batch::with_capacity(batch_size);
+
+    let started = tokio::time::Instant::now();
+    let mut summary = StreamSummary::default();
+    let result: Result<()> = async {
+        loop {
+            // ---
+            let tick = tokio::select! {
+                tick = pacer.next_frame() => tick,
+                () = cancelled(options.cancel.as_ref()) => break,
+            };
+            if tick.catch_up_started {
+                metrics.pacing_catchup_events_total.inc();
+                session.record(SessionEvent::Disrupted, std::time::Instant::now());
+            }
+            metrics.frames_skipped_total.inc_by(tick.skipped);
+
+            // Frames skipped for DTX or a gap send nothing; a long enough
+            // silence sends a keepalive instead
+            sender.keep_alive().await;
+            sender.send_sender_report().await;
+
+            let index = tick.frame as usize;
+            let passes = if options.loop_audio {
+                options.loop_count
+            } else {
+                1
+            };
+            if passes > 0 && index as u64 >= passes * file.packets.len() as u64 {
+                break;
+            }
+            let packet = &file.packets[index % file.packets.len()];
+            let passes = (index / file.packets.len()) as u64;
+            let timestamp = passes
+                .wrapping_mul(file_units)
+                .wrapping_add(offsets[index % file.packets.len()]) as u32;
+            summary.loops_completed = ((index + 1) / file.packets.len()) as u64;
+
+            let refresh_requests = sender.poll_refresh_requests(ssrc);
+            if refresh_requests > 0 {
+                metrics
+                    .decoder_refresh_requests_received_total
+                    .inc_by(refresh_requests as u64);
+                tracing::info!(
+                    "Decoder refresh requested at seq={}, but passthrough packets cannot be re-encoded",
+                    sequence
+                );
+            }
+
+            if options.suppress_dtx && codec::is_dtx_frame(&packet.data) {
+                metrics.frames_dtx_suppressed_total.inc();
+                summary.frames_sent += 1;
+                talkspurt_start = true;
+                continue;
+            }
+
+            let mut rtp = RtpPacket::new(sequence, timestamp, ssrc, packet.data.clone())
+                .with_payload_type(options.payload_type)?;
+            rtp.marker = talkspurt_start;
+            batch.push(prepare_packet(
+                rtp,
+                &mut transport_sequence,
+                metrics,
+                options,
+            )?);
+            if batch.len() >= batch_size {
+                send_batch(sender, &mut batch, metrics, &mut summary).await?;
+            }
+            talkspurt_start = false;
+            record_pacing_deviation(metrics, interval, &tick);
+            let now = std::time::Instant::now();
+            session.record(SessionEvent::Packet, now);
+            session.publish(metrics, now);
 
-            // Update sequence and timestamp
             sequence = sequence.wrapping_add(1);
-            timestamp = timestamp.wrapping_add(codec::SAMPLES_PER_FRAME as u32);
-            frame_count += 1;
+            summary.frames_sent += 1;
+        }
+        Ok(())
+    }
+    .await;
+
+    // Packets still waiting go out even if streaming stopped on an error
+    let flushed = send_batch(sender, &mut batch, metrics, &mut summary).await;
+    let result = result.and(flushed).and_then(|()| sender.finish());
+    summary.duration = started.elapsed();
+    result.map_err(|err| err.context(StreamInterrupted { summary }))?;
+
+    if options
+        .cancel
+        .as_ref()
+        .is_some_and(|cancel| *cancel.borrow())
+    {
+        tracing::info!("Stopped on request");
+    }
+    tracing::info!("Streamed {} Ogg Opus packets", summary.frames_sent);
+    finish_stream(&mut session, metrics);
+    Ok(summary)
+}
+
+/// Replays the packets of an rtpdump capture with their original timing.
+///
+/// Each packet goes out as it was recorded, with its SSRC, sequence number,
+/// and timestamp, as long after the first packet as it arrived after it in
+/// the capture, so a receiver sees the same gaps, reordering, and timing
+/// as the one that made the capture. With pacing disabled
+/// ([`StreamOptions::interval_ms`] of 0) the packets go out back to back.
+/// Records that do not hold a whole RTP packet are skipped, as RTCP or a
+/// packet cut short by the capture would be. The capture is played once,
+/// whatever the looping options say.
+///
+/// Packets captured with SRTP are replayed still protected, so they need
+/// a sender without a key and a receiver with the key they were sent
+/// under. Decoder refresh requests are counted and logged but not acted
+/// on.
+///
+/// # Arguments
+///
+/// * `file` - Capture read by [`read_rtpdump`](rtp_opus_common::read_rtpdump)
+/// * `sender` - Where packets go: an [`RtpSender`], a [`PcapWriter`], or
+///   a pair of sinks (see [`PacketSink`])
+/// * `metrics` - Metrics registry to report into
+/// * `options` - Pacing, batching, and send error options
+///
+/// # Errors
+///
+/// Returns error if an option that needs the encoder or changes packets
+/// is set ([`StreamOptions::ramp`], [`StreamOptions::encoder_control`],
+/// [`StreamOptions::pad_packets_to`], [`StreamOptions::abs_send_time_id`],
+/// [`StreamOptions::transport_cc_id`], or [`StreamOptions::verify_payloads`]), or if sends fail more times in a
+/// row than [`StreamOptions::send_errors`] allows; a failed transmission
+/// carries a [`StreamInterrupted`] context as for [`stream_frames`].
+pub async fn stream_rtpdump<K: PacketSink>(
+    file: &RtpdumpFile,
+    sender: &mut K,
+    metrics: &MetricsContext,
+    options: &StreamOptions,
+) -> Result<StreamSummary> {
+    // ---
+    if options.ramp.is_some() || options.encoder_control.is_some() {
+        anyhow::bail!("encoder settings cannot apply to a replayed capture");
+    }
+    if options.pad_packets_to.is_some()
+        || options.abs_send_time_id.is_some()
+        || options.transport_cc_id.is_some()
+        || options.verify_payloads
+    {
+        anyhow::bail!(
+            "a replayed capture is sent as recorded and cannot be padded, stamped, or given CRCs"
+        );
+    }
+
+    sender.start(options, metrics);
+
+    let Some(first) = file.packets.first() else {
+        warn!("No packets to replay");
+        return Ok(StreamSummary::default());
+    };
+    let paced = options.interval_ms > 0;
+    let mut session = SessionTracker::new(SessionConfig::default(), std::time::Instant::now());
+    let mut skipped = 0u64;
+
+    let batch_size = options.batch_size();
+    let mut batch = Batch::with_capacity(batch_size);
+
+    let started = tokio::time::Instant::now();
+    let mut summary = StreamSummary::default();
+    let result: Result<()> = async {
+        for record in &file.packets {
+            // ---
+            let due = if paced {
+                started + record.offset.saturating_sub(first.offset)
+            } else {
+                started
+            };
+            tokio::select! {
+                () = tokio::time::sleep_until(due) => {}
+                () = cancelled(options.cancel.as_ref()) => break,
+            }
+
+            let packet = match record
+                .is_complete()
+                .then(|| RtpPacket::deserialize(&record.data))
+            {
+                Some(Ok(packet)) => packet,
+                Some(Err(_)) | None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
 
-            // Pace transmission (real-time simulation)
-            tokio::time::sleep(tokio::time::Duration::from_millis(interval_ms)).await;
+            sender.send_sender_report().await;
+            let refresh_requests = sender.poll_refresh_requests(packet.ssrc);
+            if refresh_requests > 0 {
+                metrics
+                    .decoder_refresh_requests_received_total
+                    .inc_by(refresh_requests as u64);
+                tracing::info!(
+                    "Decoder refresh requested at seq={}, but replayed packets cannot be re-encoded",
+                    packet.sequence
+                );
+            }
+
+            batch.push(packet);
+            if batch.len() >= batch_size {
+                send_batch(sender, &mut batch, metrics, &mut summary).await?;
+            }
+            if paced {
+                let late = tokio::time::Instant::now().saturating_duration_since(due);
+                metrics.pacing_deviation_seconds.observe(late.as_secs_f64());
+            }
+            let now = std::time::Instant::now();
+            session.record(SessionEvent::Packet, now);
+            session.publish(metrics, now);
+            summary.frames_sent += 1;
         }
+        Ok(())
+    }
+    .await;
+
+    // Packets still waiting go out even if streaming stopped on an error
+    let flushed = send_batch(sender, &mut batch, metrics, &mut summary).await;
+    let result = result.and(flushed).and_then(|()| sender.finish());
+    summary.duration = started.elapsed();
+    result.map_err(|err| err.context(StreamInterrupted { summary }))?;
+
+    if options
+        .cancel
+        .as_ref()
+        .is_some_and(|cancel| *cancel.borrow())
+    {
+        tracing::info!("Stopped on request");
+    }
+    if skipped > 0 {
+        warn!(
+            "Skipped {} records that were not whole RTP packets",
+            skipped
+        );
+    }
+    tracing::info!("Replayed {} packets", summary.frames_sent);
+    finish_stream(&mut session, metrics);
+    Ok(summary)
+}
 
-        if !loop_audio {
-            break;
+/// Applies the payload trailer, header extension, and padding options to
+/// `packet`, numbering it with `transport_sequence` (then advanced) if
+/// transport-wide sequence numbers are on.
+fn prepare_packet(
+    mut packet: RtpPacket,
+    transport_sequence: &mut u16,
+    metrics: &MetricsContext,
+    options: &StreamOptions,
+) -> Result<RtpPacket> {
+    // ---
+    if options.verify_payloads {
+        packet.append_payload_crc();
+    }
+    if let Some(id) = options.abs_send_time_id {
+        packet.set_extension_element(id, &abs_send_time_now())?;
+    }
+    if let Some(id) = options.transport_cc_id {
+        packet.set_extension_element(id, &encode_transport_sequence(*transport_sequence))?;
+        *transport_sequence = transport_sequence.wrapping_add(1);
+    }
+    if let Some(target) = options.pad_packets_to {
+        packet.pad_to(target)?;
+        metrics
+            .padding_bytes_sent_total
+            .inc_by(packet.padding as u64);
+    }
+    Ok(packet)
+}
+
+/// Packets waiting to go out together.
+struct Batch {
+    // ---
+    packets: Vec<RtpPacket>,
+
+    /// When each packet was released, on the pacing clock
+    released: Vec<tokio::time::Instant>,
+
+    /// Whether each packet of the last send went out
+    sent: Vec<bool>,
+}
+
+impl Batch {
+    // ---
+    fn with_capacity(capacity: usize) -> Self {
+        // ---
+        Self {
+            packets: Vec::with_capacity(capacity),
+            released: Vec::with_capacity(capacity),
+            sent: Vec::with_capacity(capacity),
         }
     }
 
-    tracing::info!("Streamed {} frames", frame_count);
+    /// Queues `packet`, released now.
+    fn push(&mut self, packet: RtpPacket) {
+        // ---
+        self.packets.push(packet);
+        self.released.push(tokio::time::Instant::now());
+    }
+
+    fn len(&self) -> usize {
+        // ---
+        self.packets.len()
+    }
+}
+
+/// Sends the packets in `batch`, leaving it empty, and counts those that
+/// went out in `summary`. Packets that failed within the
+/// [`StreamOptions::send_errors`] budget are counted as failed.
+///
+/// # Errors
+///
+/// Returns error once sends have failed more times in a row than the
+/// budget allows; the packets sent before then are still counted, and
+/// those after it in the batch are dropped uncounted.
+async fn send_batch<K: PacketSink>(
+    sender: &mut K,
+    batch: &mut Batch,
+    metrics: &MetricsContext,
+    summary: &mut StreamSummary,
+) -> Result<()> {
+    // ---
+    if batch.packets.is_empty() {
+        return Ok(());
+    }
+    let result = sender
+        .send_packets(&batch.packets, &batch.released, &mut batch.sent)
+        .await;
+    for (packet, &sent) in batch.packets.iter().zip(batch.sent.iter()) {
+        if sent {
+            let bytes = packet.payload.len() as u64;
+            metrics.packets_sent_total.inc();
+            metrics.bytes_sent_total.inc_by(bytes);
+            summary.bytes_sent += bytes;
+            summary.packets_sent += 1;
+        } else {
+            metrics.packets_failed_total.inc();
+        }
+    }
+
+    // On an error, the last packet tried is the one the sender gave up on
+    let sequence = batch.packets[batch.sent.len().saturating_sub(1)].sequence;
+    batch.packets.clear();
+    batch.released.clear();
+    result.with_context(|| format!("failed to send packet {}", sequence))
+}
+
+/// Applies `settings` from [`StreamOptions::encoder_control`]: the expected
+/// loss to `encoder` directly, the bitrate through `bitrate`, which hands
+/// it to the encoder on its next poll.
+fn apply_encoder_settings(
+    encoder: &mut OpusEncoderWrapper,
+    bitrate: &mut BitrateController,
+    settings: EncoderSettings,
+) -> Result<()> {
+    // ---
+    settings.validate()?;
+    encoder.set_expected_loss(settings.expected_loss_pct)?;
+    bitrate.set_target(settings.bitrate)?;
+    tracing::info!(
+        "Encoder settings: {} bps, {}% expected loss",
+        settings.bitrate,
+        settings.expected_loss_pct
+    );
     Ok(())
 }
+
+/// Resolves once `cancel` is set; never without one, or if its sender is
+/// dropped without setting it.
+async fn cancelled(cancel: Option<&watch::Receiver<bool>>) {
+    // ---
+    if let Some(cancel) = cancel {
+        if cancel.clone().wait_for(|&stop| stop).await.is_ok() {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+/// Records how long after its nominal send time the packet for `tick` went
+/// out in the `pacing_deviation_seconds` histogram. Nothing is recorded
+/// with pacing disabled, where there is no schedule to deviate from.
+fn record_pacing_deviation(metrics: &MetricsContext, interval: std::time::Duration, tick: &Tick) {
+    // ---
+    if interval.is_zero() {
+        return;
+    }
+    let late = tokio::time::Instant::now().saturating_duration_since(tick.nominal);
+    metrics.pacing_deviation_seconds.observe(late.as_secs_f64());
+}
+
+/// Logs the session state timeline at the end of a stream.
+fn finish_stream(session: &mut SessionTracker, metrics: &MetricsContext) {
+    // ---
+    let now = std::time::Instant::now();
+    session.record(SessionEvent::Draining, now);
+    session.publish(metrics, now);
+    tracing::info!("Session states: {}", session);
+    if session.timeline_dropped() > 0 {
+        tracing::info!(
+            "  ({} earlier transitions not kept)",
+            session.timeline_dropped()
+        );
+    }
+    for transition in session.timeline() {
+        tracing::info!("  {}", transition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use rtp_opus_common::is_rtcp;
+    use tokio::net::UdpSocket;
+
+    /// Receives the next datagram that is not RTCP (the sender reports
+    /// sharing the socket), returning its length.
+    async fn recv_media(socket: &UdpSocket, buf: &mut [u8]) -> usize {
+        // ---
+        loop {
+            let len = socket.recv(buf).await.expect("recv");
+            if !is_rtcp(&buf[..len]) {
+                return len;
+            }
+        }
+    }
+
+    /// One frame each of silence, a tone, and full-scale noise.
+    fn varying_audio() -> AudioData {
+        // ---
+        let n = codec::SAMPLES_PER_FRAME;
+        let mut samples = vec![0i16; n];
+        samples.extend((0..n).map(|i| ((i as f32 * 0.3).sin() * 12000.0) as i16));
+        let mut seed = 0x1234_5678u32;
+        samples.extend((0..n).map(|_| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 16) as i16
+        }));
+
+        AudioData::from_samples(samples)
+    }
+
+    #[test]
+    fn test_pad_target_validation() {
+        // ---
+        let options = |pad| StreamOptions {
+            pad_packets_to: Some(pad),
+            ..Default::default()
+        };
+
+        let config = OpusEncoderConfig::default();
+        assert_eq!(
+            StreamOptions::default().max_payload_bytes(&config).unwrap(),
+            None
+        );
+        assert_eq!(options(200).max_payload_bytes(&config).unwrap(), Some(188));
+        assert!(options(RTP_HEADER_LEN + codec::NOMINAL_FRAME_BYTES - 1)
+            .max_payload_bytes(&config)
+            .is_err());
+        assert!(options(RTP_HEADER_LEN + MAX_PADDING + 2)
+            .max_payload_bytes(&config)
+            .is_err());
+
+        // Higher bitrates and longer frames need larger packets
+        assert!(options(120).max_payload_bytes(&config).is_ok());
+        let high_bitrate = OpusEncoderConfig {
+            bitrate: 64000,
+            ..Default::default()
+        };
+        assert!(options(120).max_payload_bytes(&high_bitrate).is_err());
+        let long_frames = OpusEncoderConfig {
+            frame_ms: 60,
+            ..Default::default()
+        };
+        assert!(options(120).max_payload_bytes(&long_frames).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_padded_stream_has_constant_wire_size() {
+        // ---
+        const PAD_TO: usize = 120;
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            pad_packets_to: Some(PAD_TO),
+            ..Default::default()
+        };
+
+        let audio = varying_audio();
+        stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+
+        let mut buf = [0u8; 2048];
+        let mut payload_bytes = Vec::new();
+        for _ in 0..audio.frame_count() {
+            let len = recv_media(&socket, &mut buf).await;
+            assert_eq!(len, PAD_TO);
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            payload_bytes.push(packet.payload.len());
+        }
+
+        // Content varied even though the wire size did not
+        assert!(payload_bytes.iter().min() < payload_bytes.iter().max());
+        let expected_padding: usize = payload_bytes
+            .iter()
+            .map(|net| PAD_TO - RTP_HEADER_LEN - net)
+            .sum();
+        assert_eq!(
+            metrics.padding_bytes_sent_total.get(),
+            expected_padding as u64
+        );
+        assert_eq!(
+            metrics.bytes_sent_total.get(),
+            payload_bytes.iter().sum::<usize>() as u64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_frames_advance_timestamp_by_frame_size() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::with_config(OpusEncoderConfig {
+            frame_ms: 40,
+            ..Default::default()
+        })
+        .expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            ..Default::default()
+        };
+
+        // Three 40ms frames plus a tail that is dropped
+        let audio = AudioData::from_samples(vec![0i16; 3 * 640 + 100]);
+        stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+
+        let mut buf = [0u8; 2048];
+        for frame in 0..3u32 {
+            let len = recv_media(&socket, &mut buf).await;
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.timestamp, frame * 640);
+        }
+        assert_eq!(metrics.packets_sent_total.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stream_sends_configured_payload_type() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let audio = AudioData::from_samples(vec![0i16; 2 * 320]);
+
+        let options = StreamOptions {
+            interval_ms: 0,
+            payload_type: 111,
+            ..Default::default()
+        };
+        stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+        let mut buf = [0u8; 2048];
+        for _ in 0..2 {
+            let len = recv_media(&socket, &mut buf).await;
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.payload_type, 111);
+        }
+
+        // Out of the 7-bit range, nothing is sent
+        let options = StreamOptions {
+            payload_type: 128,
+            ..options
+        };
+        assert!(
+            stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+                .await
+                .is_err()
+        );
+        assert_eq!(metrics.packets_sent_total.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_header_extensions_stamped_within_padded_size() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            abs_send_time_id: Some(3),
+            transport_cc_id: Some(5),
+            pad_packets_to: Some(100),
+            ..Default::default()
+        };
+        let audio = AudioData::from_samples(vec![0i16; 3 * 320]);
+        stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+
+        let mut buf = [0u8; 2048];
+        for transport_sequence in 0..3u16 {
+            let len = recv_media(&socket, &mut buf).await;
+            assert_eq!(len, 100);
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+
+            // Both elements share one word-padded extension
+            assert_eq!(packet.header_len(), RTP_HEADER_LEN + 4 + 8);
+            let stamp = packet.extension_element(3).expect("stamped");
+            assert!(rtp_opus_common::decode_abs_send_time(&stamp).is_ok());
+            let numbered = packet.extension_element(5).expect("numbered");
+            assert_eq!(
+                rtp_opus_common::decode_transport_sequence(&numbered).unwrap(),
+                transport_sequence
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tail_policy_packets_per_pass() {
+        // ---
+        // 500 samples: one full 320-sample frame and a 180-sample tail
+        let audio = AudioData::from_samples(vec![1000i16; 500]);
+
+        for (tail, per_pass) in [(TailPolicy::Discard, 1), (TailPolicy::PadWithSilence, 2)] {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+            let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+                .await
+                .expect("sender");
+            let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+            let metrics = MetricsContext::new("test").expect("metrics");
+
+            // Once through
+            let options = StreamOptions {
+                interval_ms: 0,
+                tail,
+                ..Default::default()
+            };
+            stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+                .await
+                .expect("stream");
+            assert_eq!(metrics.packets_sent_total.get(), per_pass, "{tail:?}");
+
+            let mut buf = [0u8; 2048];
+            for _ in 0..per_pass {
+                recv_media(&socket, &mut buf).await;
+            }
+
+            // Looping: the second pass continues the timestamps by a full
+            // frame per packet, padded or not
+            let options = StreamOptions {
+                interval_ms: 1,
+                loop_audio: true,
+                tail,
+                ..Default::default()
+            };
+            let stream = stream_audio(&audio, &mut encoder, &mut sender, &metrics, 2, &options);
+            let receive = async {
+                let mut packets = Vec::new();
+                for _ in 0..2 * per_pass {
+                    let len = recv_media(&socket, &mut buf).await;
+                    packets.push(RtpPacket::deserialize(&buf[..len]).expect("parse"));
+                }
+                packets
+            };
+            let packets = tokio::select! {
+                result = stream => panic!("looping stream ended: {result:?}"),
+                packets = receive => packets,
+            };
+            for (frame, packet) in packets.iter().enumerate() {
+                assert_eq!(packet.sequence, frame as u16, "{tail:?}");
+                assert_eq!(packet.timestamp, frame as u32 * 320, "{tail:?}");
+            }
+        }
+    }
+
+    /// Serves silence, then fails to read frame `fail_at`.
+    struct FailingSource {
+        // ---
+        fail_at: u64,
+    }
+
+    impl FrameSource for FailingSource {
+        async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+            // ---
+            if index == self.fail_at {
+                anyhow::bail!("input went away");
+            }
+            frame.fill(0);
+            Ok(true)
+        }
+    }
+
+    /// Serves `frames` frames of tone, then ends.
+    struct CountedSource {
+        // ---
+        frames: u64,
+    }
+
+    impl FrameSource for CountedSource {
+        async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+            // ---
+            if index >= self.frames {
+                return Ok(false);
+            }
+            for (i, sample) in frame.iter_mut().enumerate() {
+                *sample = ((i as f32 * 0.05).sin() * 8000.0) as i16;
+            }
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_batch_size() {
+        // ---
+        let options = |interval_ms, batch| StreamOptions {
+            interval_ms,
+            batch,
+            ..Default::default()
+        };
+        assert_eq!(options(20, None).batch_size(), 1);
+        assert_eq!(options(0, None).batch_size(), network::DEFAULT_BATCH);
+        assert_eq!(options(20, Some(8)).batch_size(), 8);
+        assert_eq!(options(0, Some(1)).batch_size(), 1);
+        assert_eq!(options(0, Some(0)).batch_size(), 1);
+        assert_eq!(options(0, Some(1000)).batch_size(), network::MAX_BATCH);
+    }
+
+    #[tokio::test]
+    async fn test_batched_stream_sends_every_frame_in_order() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            batch: Some(4),
+            ..Default::default()
+        };
+
+        // Two full batches and a partial one sent at the end
+        let mut source = CountedSource { frames: 10 };
+        let summary = stream_frames(
+            &mut source,
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            1,
+            &options,
+        )
+        .await
+        .expect("stream");
+        assert_eq!(summary.packets_sent, 10);
+        assert_eq!(metrics.packets_sent_total.get(), 10);
+        assert_eq!(metrics.bytes_sent_total.get(), summary.bytes_sent);
+        assert_eq!(sender.stats().packets_sent, 10);
+
+        let mut buf = [0u8; 2048];
+        let mut payload_bytes = 0;
+        for frame in 0..10u16 {
+            let len = recv_media(&socket, &mut buf).await;
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.sequence, frame);
+            payload_bytes += packet.payload.len() as u64;
+        }
+        assert_eq!(summary.bytes_sent, payload_bytes);
+    }
+
+    #[tokio::test]
+    async fn test_custom_source_streams_in_order() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            ..Default::default()
+        };
+
+        let mut source = CountedSource { frames: 7 };
+        let summary = stream_frames(
+            &mut source,
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            1,
+            &options,
+        )
+        .await
+        .expect("stream");
+        assert_eq!(summary.packets_sent, 7);
+
+        let mut buf = [0u8; 2048];
+        for frame in 0..7u16 {
+            let len = recv_media(&socket, &mut buf).await;
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.sequence, frame);
+            assert_eq!(packet.timestamp, frame as u32 * 320);
+        }
+    }
+
+    /// Serves a tone, sending `settings` on `control` when frame `at` is
+    /// read.
+    struct SwitchingSource {
+        // ---
+        control: watch::Sender<EncoderSettings>,
+        settings: EncoderSettings,
+        at: u64,
+    }
+
+    impl FrameSource for SwitchingSource {
+        async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+            // ---
+            if index == self.at {
+                self.control.send_replace(self.settings);
+            }
+            let start = index as usize * frame.len();
+            for (i, sample) in frame.iter_mut().enumerate() {
+                let n = start + i;
+                *sample = ((n as f32 * 0.05).sin() * 8000.0) as i16;
+            }
+            Ok(index < 50)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_encoder_control_changes_bitrate_mid_stream() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::with_bitrate(24000).expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let (control, control_rx) = watch::channel(encoder.settings());
+        let options = StreamOptions {
+            interval_ms: 0,
+            encoder_control: Some(control_rx),
+            ..Default::default()
+        };
+
+        let settings = EncoderSettings {
+            bitrate: 8000,
+            expected_loss_pct: 10,
+        };
+        let mut source = SwitchingSource {
+            control,
+            settings,
+            at: 25,
+        };
+        let summary = stream_frames(
+            &mut source,
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            1,
+            &options,
+        )
+        .await
+        .expect("stream");
+        assert_eq!(summary.packets_sent, 50);
+        assert_eq!(encoder.settings(), settings);
+        assert_eq!(metrics.encoder_target_bitrate_bps.get(), 8000);
+
+        // Same sequence on, smaller payloads from frame 25
+        let mut buf = [0u8; 2048];
+        let mut sizes = Vec::new();
+        for frame in 0..50u16 {
+            let len = recv_media(&socket, &mut buf).await;
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.sequence, frame);
+            assert!(!packet.marker || frame == 0);
+            sizes.push(packet.payload.len());
+        }
+        let mean = |sizes: &[usize]| sizes.iter().sum::<usize>() as f64 / sizes.len() as f64;
+        let (before, after) = (mean(&sizes[..25]), mean(&sizes[25..]));
+        assert!(after < before * 0.6, "{before:.1} -> {after:.1} bytes");
+    }
+
+    #[tokio::test]
+    async fn test_loop_count_stops_after_passes() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            loop_audio: true,
+            loop_count: 3,
+            ..Default::default()
+        };
+
+        // Two frames, played three times
+        let audio = AudioData::from_samples(
+            (0..2 * 320)
+                .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+                .collect(),
+        );
+        let summary = stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+        assert_eq!(summary.frames_sent, 6);
+        assert_eq!(summary.packets_sent, 6);
+        assert_eq!(summary.loops_completed, 3);
+
+        // Sequence and timestamp run on across passes
+        let mut buf = [0u8; 2048];
+        for frame in 0..6u16 {
+            let len = recv_media(&socket, &mut buf).await;
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.sequence, frame);
+            assert_eq!(packet.timestamp, frame as u32 * 320);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stream_summary_counts() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+
+        // Three frames and a 100-sample tail, paced at 20ms
+        let audio = AudioData::from_samples(
+            (0..3 * 320 + 100)
+                .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+                .collect(),
+        );
+        let summary = stream_audio(
+            &audio,
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            1,
+            &StreamOptions::default(),
+        )
+        .await
+        .expect("stream");
+
+        let mut buf = [0u8; 2048];
+        let mut payload_bytes = 0;
+        for _ in 0..3 {
+            let len = recv_media(&socket, &mut buf).await;
+            payload_bytes += RtpPacket::deserialize(&buf[..len])
+                .expect("parse")
+                .payload
+                .len() as u64;
+        }
+        assert_eq!(
+            summary,
+            StreamSummary {
+                frames_sent: 3,
+                packets_sent: 3,
+                bytes_sent: payload_bytes,
+                loops_completed: 1,
+                duration: std::time::Duration::from_millis(60),
+                discarded_tail_samples: 100,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_summary_on_error() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            ..Default::default()
+        };
+
+        let mut source = FailingSource { fail_at: 4 };
+        let err = stream_frames(
+            &mut source,
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            1,
+            &options,
+        )
+        .await
+        .expect_err("read failure");
+
+        // The counts up to the failure ride along with the error
+        let interrupted = err
+            .downcast_ref::<StreamInterrupted>()
+            .expect("summary context");
+        assert_eq!(interrupted.summary.frames_sent, 4);
+        assert_eq!(interrupted.summary.packets_sent, 4);
+        assert_eq!(
+            interrupted.summary.bytes_sent,
+            metrics.bytes_sent_total.get()
+        );
+        assert!(format!("{err:#}").contains("input went away"));
+    }
+
+    #[tokio::test]
+    async fn test_send_failure_budget_stops_stream() {
+        // ---
+        // Every send fails: an IPv4 socket cannot reach an IPv6 address
+        let mut sender = RtpSender::new("[::1]:5004").await.expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            loop_audio: true,
+            batch: Some(1),
+            send_errors: ErrorPolicy {
+                max_consecutive_failures: Some(5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let err = stream_audio(
+            &varying_audio(),
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            1,
+            &options,
+        )
+        .await
+        .expect_err("route is dead");
+        let interrupted = err
+            .downcast_ref::<StreamInterrupted>()
+            .expect("summary context");
+        assert_eq!(interrupted.summary.frames_sent, 5);
+        assert_eq!(interrupted.summary.packets_sent, 0);
+        assert!(format!("{err:#}").contains("failed 6 times in a row"));
+        assert_eq!(metrics.packets_failed_total.get(), 6);
+        assert_eq!(metrics.packets_sent_total.get(), 0);
+        assert_eq!(sender.stats().packets_failed, 6);
+
+        // Batched, the frames queued behind the sixth failure are dropped
+        // without being counted as failed
+        let mut sender = RtpSender::new("[::1]:5004").await.expect("sender");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            batch: None,
+            ..options
+        };
+        let err = stream_audio(
+            &varying_audio(),
+            &mut encoder,
+            &mut sender,
+            &metrics,
+            1,
+            &options,
+        )
+        .await
+        .expect_err("route is dead");
+        assert!(
+            format!("{err:#}").contains("failed to send packet 5"),
+            "{err:#}"
+        );
+        assert_eq!(metrics.packets_failed_total.get(), 6);
+        assert_eq!(sender.stats().packets_failed, 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_stops_between_frames() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let (cancel, cancel_rx) = watch::channel(false);
+        let options = StreamOptions {
+            loop_audio: true,
+            cancel: Some(cancel_rx),
+            ..Default::default()
+        };
+
+        // Frames go out at 0, 20, ..., 200ms; cancel while waiting for 220ms
+        let audio = AudioData::from_samples(vec![0i16; 4 * codec::SAMPLES_PER_FRAME]);
+        let start = tokio::time::Instant::now();
+        let stream = stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options);
+        let stop = async {
+            tokio::time::sleep(std::time::Duration::from_millis(210)).await;
+            cancel.send(true).expect("cancel");
+        };
+        let (summary, ()) = tokio::join!(stream, stop);
+        let summary = summary.expect("stream");
+
+        assert_eq!(start.elapsed(), std::time::Duration::from_millis(210));
+        assert_eq!(summary.frames_sent, 11);
+        assert_eq!(summary.packets_sent, 11);
+        assert_eq!(summary.loops_completed, 2);
+        assert_eq!(metrics.packets_sent_total.get(), 11);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pacing_does_not_drift() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions::default();
+
+        // 1000 frames of 20ms: the last is due 19.98s after the first
+        let frames = 1000;
+        let audio = AudioData::from_samples(vec![0i16; frames * codec::SAMPLES_PER_FRAME]);
+        let start = tokio::time::Instant::now();
+        stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+        let elapsed = start.elapsed();
+
+        let ideal = std::time::Duration::from_secs(20);
+        let interval = std::time::Duration::from_millis(codec::FRAME_DURATION_MS as u64);
+        assert!(
+            elapsed <= ideal && ideal - elapsed <= interval,
+            "1000 frames took {elapsed:?}"
+        );
+        assert_eq!(metrics.packets_sent_total.get(), frames as u64);
+        assert_eq!(
+            metrics.pacing_deviation_seconds.get_sample_count(),
+            frames as u64
+        );
+        assert!(metrics.pacing_deviation_seconds.get_sample_sum() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_loop_gap_timestamps() {
+        // ---
+        // Two frames per pass, then a 100ms (five frame) gap
+        let audio = AudioData::from_samples(vec![1000i16; 640]);
+        let gap_ms = 100;
+        let gap_samples = gap_ms * codec::SAMPLE_RATE / 1000;
+
+        // (policy, packets received, index of the first packet after the gap)
+        for (gap, count, after_gap) in [(GapPolicy::SendSilence, 9, 7), (GapPolicy::Skip, 4, 2)] {
+            let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+            let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+                .await
+                .expect("sender");
+            let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+            let metrics = MetricsContext::new("test").expect("metrics");
+            let options = StreamOptions {
+                interval_ms: 1,
+                loop_audio: true,
+                loop_gap: std::time::Duration::from_millis(gap_ms as u64),
+                gap,
+                ..Default::default()
+            };
+
+            let stream = stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options);
+            let receive = async {
+                let mut buf = [0u8; 2048];
+                let mut packets = Vec::new();
+                for _ in 0..count {
+                    let len = recv_media(&socket, &mut buf).await;
+                    packets.push(RtpPacket::deserialize(&buf[..len]).expect("parse"));
+                }
+                packets
+            };
+            let packets = tokio::select! {
+                result = stream => panic!("looping stream ended: {result:?}"),
+                packets = receive => packets,
+            };
+
+            // Sequence numbers are contiguous either way
+            for (index, packet) in packets.iter().enumerate() {
+                assert_eq!(packet.sequence, index as u16, "{gap:?}");
+            }
+
+            // The second pass starts one frame plus the gap after the end
+            // of the first
+            let delta = packets[after_gap].timestamp - packets[1].timestamp;
+            assert_eq!(delta, 320 + gap_samples, "{gap:?}");
+
+            // The first packet starts a talkspurt, and so does the first
+            // after a skipped gap
+            let markers: Vec<usize> = (0..packets.len()).filter(|&i| packets[i].marker).collect();
+            match gap {
+                GapPolicy::SendSilence => assert_eq!(markers, [0]),
+                GapPolicy::Skip => assert_eq!(markers, [0, after_gap]),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_playlist_runs_on_across_files() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            ..Default::default()
+        };
+
+        // Three frames and a tail, then two frames
+        let tone = |len: usize| -> Vec<i16> {
+            (0..len)
+                .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+                .collect()
+        };
+        let files = [
+            AudioData::from_samples(tone(3 * 320 + 100)),
+            AudioData::from_samples(tone(2 * 320)),
+        ];
+        let summary = stream_playlist(&files, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+        let per_file: usize = files.iter().map(|file| file.samples.len() / 320).sum();
+        assert_eq!(summary.frames_sent, per_file as u64);
+        assert_eq!(summary.discarded_tail_samples, 100);
+
+        // No discontinuity at the file boundary
+        let mut buf = [0u8; 2048];
+        for frame in 0..per_file as u16 {
+            let len = socket.recv(&mut buf).await.expect("recv");
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.sequence, frame);
+            assert_eq!(packet.timestamp, frame as u32 * 320);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ogg_passthrough_timestamps_follow_packet_durations() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            ..Default::default()
+        };
+
+        // SILK 10ms, 20ms, and 40ms packets, sent as they are
+        let packets: Vec<OpusPacket> = [(0u8, 480), (1, 960), (2, 1920), (1, 960)]
+            .into_iter()
+            .map(|(config, samples)| OpusPacket {
+                data: vec![config << 3, 0xA5, config],
+                samples,
+            })
+            .collect();
+        let file = OggOpusFile {
+            head: OpusHead::new(1, 16000),
+            packets: packets.clone(),
+        };
+        stream_ogg_opus(&file, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+
+        let mut buf = [0u8; 2048];
+        for (sequence, (packet, timestamp)) in packets.iter().zip([0, 160, 480, 1120]).enumerate() {
+            let len = recv_media(&socket, &mut buf).await;
+            let rtp = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(rtp.sequence, sequence as u16);
+            assert_eq!(rtp.timestamp, timestamp);
+            assert_eq!(rtp.payload, packet.data);
+        }
+        assert_eq!(metrics.packets_sent_total.get(), 4);
+
+        // Encoder-only options are refused
+        let ramp = StreamOptions {
+            ramp: Some(RampConfig::default()),
+            ..options
+        };
+        assert!(stream_ogg_opus(&file, &mut sender, &metrics, 1, &ramp)
+            .await
+            .is_err());
+    }
+
+    /// Records what it is given, and when, on the pacing clock.
+    #[derive(Default)]
+    struct RecordingSink {
+        // ---
+        packets: Vec<(RtpPacket, tokio::time::Instant)>,
+    }
+
+    impl PacketSink for RecordingSink {
+        async fn send_packets(
+            &mut self,
+            packets: &[RtpPacket],
+            released: &[tokio::time::Instant],
+            sent: &mut Vec<bool>,
+        ) -> Result<()> {
+            // ---
+            sent.clear();
+            for (packet, &at) in packets.iter().zip(released) {
+                self.packets.push((packet.clone(), at));
+                sent.push(true);
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rtpdump_replay_keeps_timing() {
+        // ---
+        use rtp_opus_common::RtpdumpPacket;
+
+        let record = |sequence: u16, ms: u64| {
+            let data = RtpPacket::new(sequence, sequence as u32 * 320, 0xD0D0, vec![1; 20])
+                .serialize()
+                .unwrap();
+            RtpdumpPacket {
+                offset: std::time::Duration::from_millis(ms),
+                wire_len: data.len(),
+                data,
+            }
+        };
+        let mut cut_short = record(3, 70);
+        cut_short.data.truncate(16);
+        let file = RtpdumpFile {
+            source: "127.0.0.1:5004".parse().unwrap(),
+            start: std::time::SystemTime::now(),
+            packets: vec![
+                record(10, 500),
+                record(11, 520),
+                record(13, 561),
+                cut_short,
+                RtpdumpPacket {
+                    offset: std::time::Duration::from_millis(575),
+                    data: vec![0x80, 200, 0, 6],
+                    wire_len: 0,
+                },
+                record(12, 590),
+            ],
+        };
+
+        let mut sink = RecordingSink::default();
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let start = tokio::time::Instant::now();
+        let summary = stream_rtpdump(&file, &mut sink, &metrics, &StreamOptions::default())
+            .await
+            .expect("replay");
+
+        // Timed from the first packet, as recorded, without the RTCP record
+        // and the packet cut short
+        let replayed: Vec<(u16, u128)> = sink
+            .packets
+            .iter()
+            .map(|(packet, at)| (packet.sequence, (*at - start).as_millis()))
+            .collect();
+        assert_eq!(replayed, [(10, 0), (11, 20), (13, 61), (12, 90)]);
+        assert!(sink.packets.iter().all(|(packet, _)| packet.ssrc == 0xD0D0));
+        assert_eq!(summary.packets_sent, 4);
+        assert_eq!(summary.duration, std::time::Duration::from_millis(90));
+
+        // Options that would change the packets are refused
+        let padded = StreamOptions {
+            pad_packets_to: Some(100),
+            ..Default::default()
+        };
+        assert!(stream_rtpdump(&file, &mut sink, &metrics, &padded)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dtx_frames_skipped_without_sequence_gap() {
+        // ---
+        let socket = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(socket.local_addr().unwrap().to_string())
+            .await
+            .expect("sender");
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        encoder.set_dtx(true);
+        let metrics = MetricsContext::new("test").expect("metrics");
+        let options = StreamOptions {
+            interval_ms: 0,
+            suppress_dtx: true,
+            ..Default::default()
+        };
+
+        // Half a second of tone either side of 1.2s of silence
+        let n = codec::SAMPLES_PER_FRAME;
+        let tone =
+            |frames: usize| (0..frames * n).map(|i| ((i as f32 * 0.3).sin() * 12000.0) as i16);
+        let mut samples: Vec<i16> = tone(25).collect();
+        samples.extend(std::iter::repeat_n(0, 60 * n));
+        samples.extend(tone(25));
+        let audio = AudioData::from_samples(samples);
+        stream_audio(&audio, &mut encoder, &mut sender, &metrics, 1, &options)
+            .await
+            .expect("stream");
+
+        let sent = metrics.packets_sent_total.get();
+        let suppressed = metrics.frames_dtx_suppressed_total.get();
+        assert!(suppressed >= 40, "{suppressed} frames suppressed");
+        assert_eq!(sent + suppressed, audio.frame_count() as u64);
+
+        let mut buf = [0u8; 2048];
+        let mut timestamp_jumps = 0;
+        let mut previous: Option<RtpPacket> = None;
+        for expected_sequence in 0..sent as u16 {
+            let len = recv_media(&socket, &mut buf).await;
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.sequence, expected_sequence);
+            assert!(!codec::is_dtx_frame(&packet.payload));
+            let mut talkspurt_start = true;
+            if let Some(previous) = previous {
+                let step = packet.timestamp - previous.timestamp;
+                assert!(step.is_multiple_of(n as u32));
+                talkspurt_start = step > n as u32;
+                if talkspurt_start {
+                    timestamp_jumps += (step / n as u32 - 1) as u64;
+                }
+            }
+            // Marked exactly when the packet starts a talkspurt
+            assert_eq!(packet.marker, talkspurt_start, "seq {expected_sequence}");
+            previous = Some(packet);
+        }
+
+        // Every suppressed frame shows up as timestamp, not sequence, advance
+        assert_eq!(timestamp_jumps, suppressed);
+    }
+}
@@ -1,17 +1,361 @@
 //! UDP network transmission for RTP packets.
 //!
 //! Provides async UDP socket handling for sending RTP packets
-//! to the receiver.
+//! to the receiver, or TCP connections (see [`crate::tcp`]).
 
 use anyhow::{Context, Result};
-use rtp_opus_common::RtpPacket;
+use rtp_opus_common::{
+    default_cname, is_rtcp, ntp_timestamp, AnomalyClass, AnomalyLogger, Bye, CompoundPacket,
+    DecoderRefreshRequest, ExtendedReport, Keepalive, MetricsContext, Nack, ReceiverReport,
+    ReportBlock, RtcpInterval, RtcpSocket, RtpPacket, Sdes, SdesChunk, SenderReport, SrtpContext,
+    SrtpKey, Transport, VoipMetrics, XrBlock, DEFAULT_SESSION_BANDWIDTH, RTCP_PT_PSFB, RTCP_PT_RR,
+    RTCP_PT_RTPFB, RTCP_PT_XR,
+};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::net::UdpSocket;
-use tracing::{debug, error, warn};
+use tokio::sync::{mpsc, watch};
+
+use crate::codec::SAMPLE_RATE;
+use crate::ratelimit::{RateLimit, TokenBucket};
+use crate::resolve::{self, resolve, Resolver, SystemResolver};
+use crate::rtx::{RtxCache, DEFAULT_RTX_CACHE_PACKETS};
+use crate::tcp::{TcpLink, TcpOptions};
+use tracing::{debug, info, warn};
+
+/// Multicast TTL used unless configured: packets stay on the local subnet
+pub const DEFAULT_MULTICAST_TTL: u32 = 1;
+
+/// Highest DSCP value: the code point is the top six bits of the ToS byte
+pub const MAX_DSCP: u8 = 63;
+
+/// Standard DSCP names accepted by [`parse_dscp`], with their values
+/// (RFC 2474, RFC 2597, RFC 3246, RFC 5865)
+pub const DSCP_NAMES: &[(&str, u8)] = &[
+    ("CS0", 0),
+    ("CS1", 8),
+    ("AF11", 10),
+    ("AF12", 12),
+    ("AF13", 14),
+    ("CS2", 16),
+    ("AF21", 18),
+    ("AF22", 20),
+    ("AF23", 22),
+    ("CS3", 24),
+    ("AF31", 26),
+    ("AF32", 28),
+    ("AF33", 30),
+    ("CS4", 32),
+    ("AF41", 34),
+    ("AF42", 36),
+    ("AF43", 38),
+    ("CS5", 40),
+    ("VA", 44),
+    ("EF", 46),
+    ("CS6", 48),
+    ("CS7", 56),
+];
+
+/// Parses a DSCP given by name (`EF`, `AF41`, `CS5`, any case) or as a
+/// number from 0 to [`MAX_DSCP`], decimal or `0x` hex.
+///
+/// # Errors
+///
+/// Returns error for an unknown name or a number out of range.
+pub fn parse_dscp(value: &str) -> Result<u8> {
+    // ---
+    if let Some(&(_, dscp)) = DSCP_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+    {
+        return Ok(dscp);
+    }
+    let number = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|_| anyhow::anyhow!("{value:?} is not a DSCP name (e.g. EF, AF41, CS5) or number"))?;
+    if number > MAX_DSCP {
+        anyhow::bail!("DSCP {number} is out of range (0 to {MAX_DSCP})");
+    }
+    Ok(number)
+}
+
+/// Idle time after which an [`RtpSender`] keeps NAT bindings open unless
+/// configured: half the 30 s some NATs allow a UDP mapping
+pub const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Time between RTCP sender reports unless configured: the RFC 3550
+/// minimum reporting interval
+pub const DEFAULT_SENDER_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Consecutive send failures after which [`RtpSender::send`] gives up by
+/// default: five seconds of 20ms frames
+pub const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 250;
+
+/// How [`RtpSender::send`] handles packets the socket fails to send.
+///
+/// Failures are logged and streaming goes on, so a brief outage costs only
+/// the packets sent during it. A route that stays dead (e.g. unreachable
+/// host after a VPN drop) uses up the failure budget, and the send then
+/// returns an error instead of streaming into the void.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorPolicy {
+    // ---
+    /// Failures in a row tolerated before giving up; `None` never gives up
+    pub max_consecutive_failures: Option<u32>,
+
+    /// Wait this long after a failed send, so a dead route is not retried
+    /// at the full packet rate
+    pub backoff: Duration,
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        // ---
+        Self {
+            max_consecutive_failures: Some(DEFAULT_MAX_CONSECUTIVE_FAILURES),
+            backoff: Duration::ZERO,
+        }
+    }
+}
+
+/// Network-side sender configuration.
+///
+/// The DSCP marking applies to every destination. The multicast settings
+/// apply only when the remote address is an IPv4 multicast group; they are
+/// ignored for unicast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderNetworkConfig {
+    // ---
+    /// Router hops multicast packets may cross (`IP_MULTICAST_TTL`); 0
+    /// keeps them on this host
+    pub multicast_ttl: u32,
+
+    /// Interface to send multicast from, by address; `None` follows the
+    /// routing table
+    pub multicast_interface: Option<Ipv4Addr>,
+
+    /// DiffServ code point to mark outgoing packets with (`IP_TOS`), so
+    /// networks that prioritize e.g. EF traffic favor the audio; `None`
+    /// leaves the system default
+    pub dscp: Option<u8>,
+
+    /// Local address and port to send from, e.g. to pick the interface on
+    /// a multi-homed host or a source port a firewall expects; `None`
+    /// binds any interface and an ephemeral port
+    pub local_addr: Option<SocketAddr>,
+
+    /// Socket send buffer to request (`SO_SNDBUF`), in bytes, so a burst
+    /// of packets (catch-up, batches) is queued rather than dropped;
+    /// `None` keeps the system default. The kernel may grant a different
+    /// size.
+    pub send_buffer_bytes: Option<usize>,
+
+    /// Resolve destinations given by host name again this often, so a
+    /// long-running stream follows DNS changes; `None` resolves them once
+    pub dns_refresh: Option<Duration>,
+
+    /// Cap on the bytes sent per second, summed over destinations; sends
+    /// wait for the token bucket to refill past it. `None` sends as fast
+    /// as packets come.
+    pub rate_limit: Option<RateLimit>,
+
+    /// UDP datagrams, or RFC 4571 frames over a TCP connection to each
+    /// destination (unicast only). Over TCP, the DSCP, send buffer, and
+    /// local address apply to every connection made.
+    pub transport: Transport,
+
+    /// Pre-shared SRTP master key: every packet is encrypted and
+    /// authenticated under it. `None` sends plain RTP.
+    pub srtp_key: Option<SrtpKey>,
+
+    /// In listen mode ([`RtpSender::listen`]), follow the receiver when
+    /// its datagrams start coming from a new address, e.g. after a NAT
+    /// rebinding; otherwise datagrams from anyone but the first peer are
+    /// ignored
+    pub allow_peer_switch: bool,
+
+    /// Recent packets kept to answer NACKs with (see [`RtxCache`]); 0
+    /// ignores NACKs. Off with SRTP, whose feedback is not authenticated.
+    pub rtx_cache_packets: usize,
+
+    /// Send a keepalive when no packet has gone out for this long, e.g.
+    /// through DTX suppression or a loop gap, so NAT bindings on the path
+    /// stay open (see [`RtpSender::keep_alive`]); `None` sends none
+    pub keepalive_interval: Option<Duration>,
+
+    /// Send an RTCP sender report to every UDP destination this often
+    /// while streaming, or less often if the session bandwidth calls for
+    /// it (see [`RtpSender::send_sender_report`]); `None` sends none
+    pub sender_report_interval: Option<Duration>,
+
+    /// Carry RTCP on the RTP socket (RFC 5761) rather than on a socket of
+    /// its own on the next port, sent to the port after each
+    /// destination's (RFC 3550 §11); the receivers must do the same. Not
+    /// in listen mode, which learns the receiver's address from its RTP.
+    pub rtcp_mux: bool,
+
+    /// Session bandwidth in bits per second, headers included, that
+    /// sender reports keep within 5% of (see [`RtcpInterval`])
+    pub session_bandwidth: u64,
+
+    /// RTP clock rate of the packets sent, in Hz, which sender reports
+    /// map wallclock time to RTP timestamps with
+    pub rtp_clock_rate: u32,
+
+    /// Canonical name sent with every sender report in an SDES CNAME
+    /// item, identifying the stream beyond its random SSRC; `user@host`
+    /// by default (see [`default_cname`])
+    pub cname: String,
+}
+
+impl Default for SenderNetworkConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
+            multicast_interface: None,
+            dscp: None,
+            local_addr: None,
+            send_buffer_bytes: None,
+            dns_refresh: None,
+            rate_limit: None,
+            transport: Transport::Udp,
+            srtp_key: None,
+            allow_peer_switch: false,
+            rtx_cache_packets: DEFAULT_RTX_CACHE_PACKETS,
+            keepalive_interval: Some(DEFAULT_KEEPALIVE_INTERVAL),
+            sender_report_interval: Some(DEFAULT_SENDER_REPORT_INTERVAL),
+            rtcp_mux: true,
+            session_bandwidth: DEFAULT_SESSION_BANDWIDTH,
+            rtp_clock_rate: SAMPLE_RATE,
+            cname: default_cname(),
+        }
+    }
+}
+
+/// Most packets [`RtpSender::send_batch`] takes at once
+pub const MAX_BATCH: usize = 64;
+
+/// Packets per batch when frames come faster than real time (pacing
+/// disabled)
+pub const DEFAULT_BATCH: usize = 32;
+
+/// Most destinations one [`RtpSender`] fans out to; keeps the
+/// `destination` metric label bounded
+pub const MAX_DESTINATIONS: usize = 16;
+
+/// Transmission counts for one destination of an [`RtpSender`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DestinationStats {
+    // ---
+    /// Destination address as given
+    pub addr: String,
+
+    /// Packets handed to the network for this destination
+    pub packets_sent: u64,
+
+    /// Bytes handed to the network, RTP headers and SRTP tags included
+    pub bytes_sent: u64,
+
+    /// Packets the socket failed to send to this destination
+    pub packets_failed: u64,
+
+    /// The destination used up its failure budget and is no longer sent to
+    pub gave_up: bool,
+}
+
+/// Transmission counts of an [`RtpSender`], summed over its destinations.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SenderStats {
+    // ---
+    /// Packets handed to the network
+    pub packets_sent: u64,
+
+    /// Bytes handed to the network, RTP headers and SRTP tags included
+    pub bytes_sent: u64,
+
+    /// Packets the socket failed to send
+    pub packets_failed: u64,
+
+    /// Packets held back by the bandwidth cap
+    pub packets_delayed: u64,
+
+    /// Time spent waiting for the bandwidth cap
+    pub throttled: Duration,
+
+    /// Packets sent again on a receiver's NACK, counted once however
+    /// many destinations they went to
+    pub packets_retransmitted: u64,
+
+    /// Keepalives sent while the stream was silent, counted once however
+    /// many destinations they went to
+    pub keepalives_sent: u64,
+
+    /// RTCP sender reports sent, counted once however many destinations
+    /// they went to
+    pub sender_reports_sent: u64,
+
+    /// RTCP receiver reports received on the stream
+    pub receiver_reports_received: u64,
+
+    /// Counts for each destination, in the order given
+    pub destinations: Vec<DestinationStats>,
+}
+
+/// The stream as sent so far, for sender reports.
+#[derive(Debug, Clone, Copy)]
+struct MediaSent {
+    // ---
+    /// SSRC of the last packet sent
+    ssrc: u32,
+
+    /// RTP timestamp of the last packet sent, and when it went out
+    timestamp: u32,
+    sent_at: SystemTime,
+
+    /// Packets and payload octets sent, each counted once however many
+    /// destinations it went to
+    packets: u64,
+    octets: u64,
+}
+
+/// One remote endpoint of an [`RtpSender`].
+#[derive(Debug)]
+struct Destination {
+    // ---
+    /// Address and counts
+    stats: DestinationStats,
+
+    /// Address resolved when the sender was created, or since
+    target: SocketAddr,
+
+    /// New addresses for a host name resolved again periodically
+    updates: Option<watch::Receiver<SocketAddr>>,
+
+    /// Sends failed since the last one that went out
+    consecutive_failures: u32,
+
+    /// Connection carrying the packets when sending over TCP
+    tcp: Option<TcpLink>,
+}
 
 /// UDP sender for RTP packet transmission.
 ///
-/// Wraps a tokio UDP socket for async transmission of RTP packets.
-/// Handles network errors gracefully by logging but continuing operation.
+/// Wraps a tokio UDP socket for async transmission of RTP packets to one
+/// or more destinations; with several, every packet is duplicated to each.
+/// Send failures are logged and tolerated per destination as its
+/// [`ErrorPolicy`] says.
+///
+/// With [`Transport::Tcp`], packets go over a TCP connection to each
+/// destination instead, which is made again after a failure (see
+/// [`crate::tcp`]). Feedback from receivers still arrives on the UDP
+/// socket.
 ///
 /// # Example
 ///
@@ -25,16 +369,92 @@ use tracing::{debug, error, warn};
 pub struct RtpSender {
     // ---
     socket: UdpSocket,
-    remote_addr: String,
-    packets_sent: u64,
-    bytes_sent: u64,
+
+    /// The socket is connected to the only destination, so sends use
+    /// `send` rather than `send_to`
+    connected: bool,
+
+    destinations: Vec<Destination>,
+    anomalies: AnomalyLogger,
+
+    /// Failure budget and backoff
+    policy: ErrorPolicy,
+
+    /// Registry for the per-destination counters, once set
+    metrics: Option<MetricsContext>,
+
+    /// Bandwidth cap, if configured
+    bucket: Option<TokenBucket>,
+
+    /// Packets held back by the bandwidth cap
+    packets_delayed: u64,
+
+    /// Time spent waiting for the bandwidth cap
+    throttled: Duration,
+
+    /// Session keys and rollover counters, with SRTP
+    srtp: Option<SrtpContext>,
+
+    /// Created by [`listen`](Self::listen): the destination is learned
+    /// from the receiver's datagrams
+    listening: bool,
+
+    /// In listen mode, whether a new source address moves the stream
+    allow_peer_switch: bool,
+
+    /// Recently sent datagrams, if NACKs are answered
+    rtx: Option<RtxCache>,
+
+    /// Sequence numbers NACKed since the last retransmission
+    nacked: Vec<u16>,
+
+    /// Packets sent again on request
+    packets_retransmitted: u64,
+
+    /// Idle time before a keepalive, if sent
+    keepalive_interval: Option<Duration>,
+
+    /// When a packet or keepalive last went out, once one has
+    last_sent: Option<tokio::time::Instant>,
+
+    /// Keepalives sent
+    keepalives_sent: u64,
+
+    /// RTCP transport and sender report schedule, and the CNAME sent with
+    /// the reports
+    rtcp: RtcpSocket<Feedback>,
+    cname: String,
+
+    /// RTP timestamp units per second
+    rtp_clock_rate: u32,
+
+    /// What has been sent of the stream, once a packet has
+    media: Option<MediaSent>,
+
+    /// Sender reports sent
+    sender_reports_sent: u64,
+
+    /// The stream's block of the last receiver report, and reports received
+    remote_report: Option<ReportBlock>,
+    receiver_reports_received: u64,
+
+    /// The stream's VoIP Metrics block of the last extended report
+    remote_voip_metrics: Option<VoipMetrics>,
+
+    /// Where the stream's receiver report blocks are published, if
+    /// subscribed
+    report_events: Option<mpsc::UnboundedSender<ReportBlock>>,
+
+    /// Datagram buffers reused by every batch, one per packet
+    send_buffers: Vec<Vec<u8>>,
 }
 
 impl RtpSender {
     // ---
     /// Creates a new RTP sender bound to any available port.
     ///
-    /// The socket will send packets to the specified remote address.
+    /// The socket will send packets to the specified remote address, using
+    /// the default [`SenderNetworkConfig`].
     ///
     /// # Arguments
     ///
@@ -45,87 +465,1666 @@ impl RtpSender {
     /// Returns error if socket binding fails.
     pub async fn new(remote_addr: impl Into<String>) -> Result<Self> {
         // ---
-        let remote_addr = remote_addr.into();
+        Self::with_config(remote_addr, SenderNetworkConfig::default()).await
+    }
+
+    /// Creates a new RTP sender with explicit network configuration.
+    ///
+    /// A remote address that is an IPv4 multicast group is detected, and
+    /// the socket is set up to send to it with the configured TTL and
+    /// interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `remote_addr` - Destination address in "IP:port" format
+    /// * `config` - Local address, multicast TTL and interface, DSCP
+    ///   marking, send buffer size, DNS refresh interval
+    ///
+    /// # Errors
+    ///
+    /// Returns error if socket binding fails, the multicast settings are
+    /// rejected, or the DSCP is out of range.
+    pub async fn with_config(
+        remote_addr: impl Into<String>,
+        config: SenderNetworkConfig,
+    ) -> Result<Self> {
+        // ---
+        Self::with_destinations([remote_addr.into()], config).await
+    }
+
+    /// Creates a new RTP sender that duplicates every packet to each of
+    /// `remote_addrs`.
+    ///
+    /// Multicast groups among them are detected as for
+    /// [`with_config`](Self::with_config).
+    ///
+    /// # Arguments
+    ///
+    /// * `remote_addrs` - Destination addresses in "IP:port" format
+    /// * `config` - Local address, multicast TTL and interface, DSCP
+    ///   marking, send buffer size, DNS refresh interval
+    ///
+    /// # Errors
+    ///
+    /// Returns error if there are no destinations or more than
+    /// [`MAX_DESTINATIONS`], if socket binding fails, if the multicast
+    /// settings are rejected, or if the DSCP is out of range.
+    pub async fn with_destinations<I>(remote_addrs: I, config: SenderNetworkConfig) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        // ---
+        Self::with_resolver(remote_addrs, config, Arc::new(SystemResolver)).await
+    }
+
+    /// Creates a new RTP sender that looks up destinations given by host
+    /// name with `resolver`; otherwise as
+    /// [`with_destinations`](Self::with_destinations).
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as
+    /// [`with_destinations`](Self::with_destinations).
+    pub async fn with_resolver<I>(
+        remote_addrs: I,
+        config: SenderNetworkConfig,
+        resolver: Arc<dyn Resolver>,
+    ) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        // ---
+        if let Some(limit) = &config.rate_limit {
+            limit.validate()?;
+        }
+        let addrs: Vec<String> = remote_addrs.into_iter().map(Into::into).collect();
+        if addrs.is_empty() || addrs.len() > MAX_DESTINATIONS {
+            anyhow::bail!(
+                "{} destinations given: use 1 to {}",
+                addrs.len(),
+                MAX_DESTINATIONS
+            );
+        }
+
+        let local_addr = config
+            .local_addr
+            .unwrap_or(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)));
+        let socket = UdpSocket::bind(local_addr)
+            .await
+            .with_context(|| format!("failed to bind UDP socket to {}", local_addr))?;
+
+        let bound = socket.local_addr()?;
+        debug!("UDP socket bound to {}", bound);
+
+        // Host names are looked up once here, and again every
+        // `dns_refresh` if set; literal addresses never change
+        let mut targets = Vec::with_capacity(addrs.len());
+        let mut updates = Vec::with_capacity(addrs.len());
+        for addr in &addrs {
+            if let Ok(target) = addr.parse::<SocketAddr>() {
+                targets.push(target);
+                updates.push(None);
+                continue;
+            }
+            let target = resolve(&resolver, addr, bound.is_ipv4()).await?;
+            info!("Resolved {} to {}", addr, target);
+            targets.push(target);
+            updates.push(config.dns_refresh.map(|interval| {
+                resolve::spawn_refresh(
+                    resolver.clone(),
+                    addr.clone(),
+                    bound.is_ipv4(),
+                    interval,
+                    target,
+                )
+            }));
+        }
+
+        let multicast: Vec<(&String, &SocketAddr)> = addrs
+            .iter()
+            .zip(&targets)
+            .filter(|(_, target)| target.is_ipv4() && target.ip().is_multicast())
+            .collect();
+        if let (Transport::Tcp, Some((addr, _))) = (config.transport, multicast.first()) {
+            anyhow::bail!("cannot send to multicast group {} over TCP", addr);
+        }
+        if !multicast.is_empty() {
+            socket
+                .set_multicast_ttl_v4(config.multicast_ttl)
+                .context("failed to set multicast TTL")?;
+            if let Some(interface) = config.multicast_interface {
+                socket2::SockRef::from(&socket)
+                    .set_multicast_if_v4(&interface)
+                    .with_context(|| format!("failed to send multicast from {}", interface))?;
+            }
+        }
+        if let Some(dscp) = config.dscp {
+            set_dscp(&socket, dscp)?;
+        }
+        if let Some(bytes) = config.send_buffer_bytes {
+            set_send_buffer(&socket, bytes);
+        }
+        for (addr, _) in &multicast {
+            info!(
+                "Multicast to {} with TTL {}{}",
+                addr,
+                config.multicast_ttl,
+                config
+                    .multicast_interface
+                    .map_or(String::new(), |interface| format!(" from {interface}"))
+            );
+        }
+
+        // A single unicast destination gets a connected socket: the kernel
+        // skips the route lookup on each send, and ICMP errors from the
+        // destination are reported. Multicast stays unconnected so feedback
+        // from receivers' own addresses still arrives.
+        let mut connected = false;
+        if let ([target], true, Transport::Udp) =
+            (targets.as_slice(), multicast.is_empty(), config.transport)
+        {
+            match socket.connect(target).await {
+                Ok(()) => connected = true,
+                Err(e) => debug!("Not connecting to {}, sending unconnected: {}", target, e),
+            }
+        }
+
+        let tcp = TcpOptions {
+            local_addr: config.local_addr,
+            tos: config.dscp.map(|dscp| (dscp as u32) << 2),
+            send_buffer_bytes: config.send_buffer_bytes,
+        };
+        let rtcp = rtcp_socket(&config, &socket).await?;
+        let destinations = addrs
+            .into_iter()
+            .zip(targets)
+            .zip(updates)
+            .map(|((addr, target), updates)| Destination {
+                stats: DestinationStats {
+                    addr,
+                    ..Default::default()
+                },
+                target,
+                updates,
+                consecutive_failures: 0,
+                tcp: (config.transport == Transport::Tcp).then(|| TcpLink::new(tcp)),
+            })
+            .collect();
+
+        Ok(Self {
+            socket,
+            connected,
+            destinations,
+            anomalies: AnomalyLogger::default(),
+            policy: ErrorPolicy::default(),
+            metrics: None,
+            bucket: config
+                .rate_limit
+                .map(|limit| TokenBucket::new(limit, tokio::time::Instant::now())),
+            packets_delayed: 0,
+            throttled: Duration::ZERO,
+            srtp: config.srtp_key.as_ref().map(SrtpContext::new),
+            listening: false,
+            allow_peer_switch: false,
+            rtx: rtx_cache(&config),
+            nacked: Vec::new(),
+            packets_retransmitted: 0,
+            keepalive_interval: config.keepalive_interval,
+            last_sent: None,
+            keepalives_sent: 0,
+            rtcp,
+            cname: config.cname.clone(),
+            rtp_clock_rate: config.rtp_clock_rate,
+            media: None,
+            sender_reports_sent: 0,
+            remote_report: None,
+            receiver_reports_received: 0,
+            remote_voip_metrics: None,
+            report_events: None,
+            send_buffers: Vec::new(),
+        })
+    }
+
+    /// Creates a sender that waits to be contacted instead of being told
+    /// where to send (symmetric RTP).
+    ///
+    /// Binds `port` and has no destination until
+    /// [`wait_for_peer`](Self::wait_for_peer) latches the source of the
+    /// first datagram to arrive, so a receiver behind NAT can be reached
+    /// at the address its hello came from. The socket stays unconnected
+    /// to keep hearing from the receiver; see
+    /// [`SenderNetworkConfig::allow_peer_switch`] for datagrams from
+    /// elsewhere.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - Port to listen on, 0 for an ephemeral one
+    /// * `config` - Network settings; the IP of `local_addr`, if given,
+    ///   picks the interface
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the transport is TCP, or socket binding or
+    /// configuration fails.
+    pub async fn listen(port: u16, config: SenderNetworkConfig) -> Result<Self> {
+        // ---
+        if config.transport == Transport::Tcp {
+            anyhow::bail!("listen mode works over UDP only");
+        }
+        if !config.rtcp_mux {
+            anyhow::bail!("listen mode needs RTCP on the RTP socket");
+        }
+        if let Some(limit) = &config.rate_limit {
+            limit.validate()?;
+        }
 
-        let socket = UdpSocket::bind("0.0.0.0:0")
+        let ip = config
+            .local_addr
+            .map_or(Ipv4Addr::UNSPECIFIED.into(), |addr| addr.ip());
+        let local_addr = SocketAddr::new(ip, port);
+        let socket = UdpSocket::bind(local_addr)
             .await
-            .context("failed to bind UDP socket")?;
+            .with_context(|| format!("failed to bind UDP socket to {}", local_addr))?;
+        debug!("UDP socket listening on {}", socket.local_addr()?);
 
-        debug!("UDP socket bound to {}", socket.local_addr()?);
+        if let Some(dscp) = config.dscp {
+            set_dscp(&socket, dscp)?;
+        }
+        if let Some(bytes) = config.send_buffer_bytes {
+            set_send_buffer(&socket, bytes);
+        }
+        let rtcp = rtcp_socket(&config, &socket).await?;
 
         Ok(Self {
             socket,
-            remote_addr,
-            packets_sent: 0,
-            bytes_sent: 0,
+            connected: false,
+            destinations: Vec::new(),
+            anomalies: AnomalyLogger::default(),
+            policy: ErrorPolicy::default(),
+            metrics: None,
+            bucket: config
+                .rate_limit
+                .map(|limit| TokenBucket::new(limit, tokio::time::Instant::now())),
+            packets_delayed: 0,
+            throttled: Duration::ZERO,
+            srtp: config.srtp_key.as_ref().map(SrtpContext::new),
+            listening: true,
+            allow_peer_switch: config.allow_peer_switch,
+            rtx: rtx_cache(&config),
+            nacked: Vec::new(),
+            packets_retransmitted: 0,
+            keepalive_interval: config.keepalive_interval,
+            last_sent: None,
+            keepalives_sent: 0,
+            rtcp,
+            cname: config.cname.clone(),
+            rtp_clock_rate: config.rtp_clock_rate,
+            media: None,
+            sender_reports_sent: 0,
+            remote_report: None,
+            receiver_reports_received: 0,
+            remote_voip_metrics: None,
+            report_events: None,
+            send_buffers: Vec::new(),
         })
     }
 
-    /// Sends an RTP packet to the remote endpoint.
+    /// Waits for a datagram on a listening socket and makes its source
+    /// the destination. Returns at once if a peer is already known.
+    ///
+    /// # Returns
+    ///
+    /// The address packets will be sent to.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the sender was not created by
+    /// [`listen`](Self::listen) or receiving fails.
+    pub async fn wait_for_peer(&mut self) -> Result<SocketAddr> {
+        // ---
+        if !self.listening {
+            anyhow::bail!("sender is not listening for a peer");
+        }
+        if let Some(peer) = self.peer_addr() {
+            return Ok(peer);
+        }
+
+        let mut buf = [0u8; 1500];
+        let (len, peer) = self
+            .socket
+            .recv_from(&mut buf)
+            .await
+            .context("failed to wait for a peer")?;
+        info!("Peer {} said hello ({} bytes)", peer, len);
+        self.destinations.push(Destination {
+            stats: DestinationStats {
+                addr: peer.to_string(),
+                ..Default::default()
+            },
+            target: peer,
+            updates: None,
+            consecutive_failures: 0,
+            tcp: None,
+        });
+        Ok(peer)
+    }
+
+    /// Returns the address learned by [`wait_for_peer`](Self::wait_for_peer)
+    /// in listen mode, following any switch since; `None` before a peer
+    /// is known or when the destinations were given.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        // ---
+        self.destinations
+            .first()
+            .filter(|_| self.listening)
+            .map(|destination| destination.target)
+    }
+
+    /// Replaces the logger for send failures, e.g. with one that exports
+    /// suppression metrics.
+    pub fn set_anomaly_logger(&mut self, logger: AnomalyLogger) {
+        // ---
+        self.anomalies = logger;
+    }
+
+    /// Replaces the policy for send failures. The counts of failures in a
+    /// row carry over.
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy) {
+        // ---
+        self.policy = policy;
+    }
+
+    /// Exports per-destination counts to `metrics`, labelled by
+    /// destination address.
+    pub fn set_metrics(&mut self, metrics: &MetricsContext) {
+        // ---
+        self.metrics = Some(metrics.clone());
+    }
+
+    /// Logs send failures counted since the last aggregate line.
+    pub fn flush_anomalies(&mut self) {
+        // ---
+        self.anomalies.flush(std::time::Instant::now());
+    }
+
+    /// Sends an RTP packet to every destination.
+    ///
+    /// Serializes the packet once, protects it with SRTP if configured,
+    /// and transmits it via UDP to each destination in turn. Network errors are logged and, within the
+    /// [`ErrorPolicy`] budget, do not stop operation; the policy's backoff
+    /// is waited out after a send with failures. A destination that uses
+    /// up its budget is given up on, and the others carry on without it.
     ///
-    /// Serializes the packet and transmits it via UDP. Network errors
-    /// are logged but do not stop operation (resilient behavior).
+    /// With a bandwidth cap ([`SenderNetworkConfig::rate_limit`]), the
+    /// send first waits until the cap lets the packet out to every
+    /// destination. A stream that stays under the cap never waits, not
+    /// even for a yield.
     ///
     /// # Arguments
     ///
     /// * `packet` - RTP packet to transmit
     ///
+    /// # Returns
+    ///
+    /// Whether the packet went out to at least one destination.
+    ///
     /// # Errors
     ///
     /// Returns error if:
     /// - Packet serialization fails
-    /// - Network transmission fails persistently
-    pub async fn send(&mut self, packet: &RtpPacket) -> Result<()> {
+    /// - Network transmission has failed more times in a row than the
+    ///   policy allows for every destination
+    pub async fn send(&mut self, packet: &RtpPacket) -> Result<bool> {
         // ---
-        let data = packet
-            .serialize()
-            .context("failed to serialize RTP packet")?;
+        let mut sent = Vec::with_capacity(1);
+        self.send_batch(std::slice::from_ref(packet), &mut sent)
+            .await?;
+        Ok(sent[0])
+    }
+
+    /// Sends several RTP packets, in order, to every destination.
+    ///
+    /// Behaves as [`send`](Self::send) for each packet in turn, but hands
+    /// the whole batch to the kernel in one `sendmmsg` call per
+    /// destination on Linux (a call per packet elsewhere). Failures are
+    /// counted per packet, so the counts come out as for separate sends;
+    /// the backoff is waited out once per batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `packets` - RTP packets to transmit, at most [`MAX_BATCH`]
+    /// * `sent` - Cleared, then set to whether each packet went out to at
+    ///   least one destination. On an error, packets after the last one
+    ///   tried have no entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`send`](Self::send), or an error if
+    /// there are more than [`MAX_BATCH`] packets.
+    pub async fn send_batch(&mut self, packets: &[RtpPacket], sent: &mut Vec<bool>) -> Result<()> {
+        // ---
+        sent.clear();
+        if packets.len() > MAX_BATCH {
+            anyhow::bail!(
+                "{} packets in one batch (at most {})",
+                packets.len(),
+                MAX_BATCH
+            );
+        }
 
-        match self.socket.send_to(&data, &self.remote_addr).await {
-            Ok(bytes) => {
-                self.packets_sent += 1;
-                self.bytes_sent += bytes as u64;
+        // Taken out for the send, so they can be borrowed alongside self
+        let mut buffers = std::mem::take(&mut self.send_buffers);
+        let result = self.send_serialized(packets, &mut buffers, sent).await;
+        self.send_buffers = buffers;
+        result
+    }
 
-                if self.packets_sent.is_multiple_of(100) {
-                    debug!(
-                        "Sent {} packets ({} bytes) - seq={}",
-                        self.packets_sent, self.bytes_sent, packet.sequence
+    /// Serializes `packets` into `buffers`, once each whatever the number
+    /// of destinations, and sends them as [`send_batch`](Self::send_batch)
+    /// describes. The buffers keep their capacity for the next batch, so
+    /// a steady stream sends without allocating.
+    async fn send_serialized(
+        &mut self,
+        packets: &[RtpPacket],
+        buffers: &mut Vec<Vec<u8>>,
+        sent: &mut Vec<bool>,
+    ) -> Result<()> {
+        // ---
+        if buffers.len() < packets.len() {
+            buffers.resize_with(packets.len(), Vec::new);
+        }
+        let datagrams = &mut buffers[..packets.len()];
+        for (packet, datagram) in packets.iter().zip(datagrams.iter_mut()) {
+            packet
+                .serialize_into(datagram)
+                .context("failed to serialize RTP packet")?;
+        }
+        if let Some(srtp) = &mut self.srtp {
+            for datagram in datagrams.iter_mut() {
+                srtp.protect(datagram)?;
+            }
+        }
+        sent.resize(packets.len(), false);
+
+        self.follow_dns_changes().await;
+        self.wait_for_bandwidth(datagrams).await;
+
+        let fan_out = self.destinations.len() > 1;
+        let mut failed = false;
+        let mut last_error = None;
+        let mut tried = 0;
+        for destination in &mut self.destinations {
+            let mut next = 0;
+            while next < datagrams.len() && !destination.stats.gave_up {
+                let stats = &mut destination.stats;
+                let target = destination.target;
+                let sequence = packets[next].sequence;
+                let mut result = match &mut destination.tcp {
+                    Some(link) => link.send(&datagrams[next..], target).await,
+                    None => {
+                        transmit(&self.socket, self.connected, &datagrams[next..], target).await
+                    }
+                };
+                if destination.tcp.is_none()
+                    && matches!(&result, Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused)
+                {
+                    // The connected socket reports an ICMP port unreachable
+                    // for an earlier packet on this send, which did not go
+                    // out; the error is consumed, so the retry normally
+                    // succeeds
+                    self.anomalies.record(
+                        AnomalyClass::DestinationUnreachable,
+                        Some(sequence),
+                        format_args!(
+                            "Nothing listening at {}, earlier packet refused (continuing)",
+                            stats.addr
+                        ),
+                        std::time::Instant::now(),
                     );
+                    result =
+                        transmit(&self.socket, self.connected, &datagrams[next..], target).await;
+                }
+                match result {
+                    Ok(count) => {
+                        destination.consecutive_failures = 0;
+                        for index in next..next + count {
+                            sent[index] = true;
+                            stats.packets_sent += 1;
+                            stats.bytes_sent += datagrams[index].len() as u64;
+                            if stats.packets_sent.is_multiple_of(100) {
+                                debug!(
+                                    "Sent {} packets ({} bytes) to {} - seq={}",
+                                    stats.packets_sent,
+                                    stats.bytes_sent,
+                                    stats.addr,
+                                    packets[index].sequence
+                                );
+                            }
+                        }
+                        if let Some(metrics) = &self.metrics {
+                            metrics
+                                .destination_packets_sent_total
+                                .with_label_values(&[&stats.addr])
+                                .inc_by(count as u64);
+                        }
+                        next += count;
+                    }
+                    Err(e) => {
+                        failed = true;
+                        next += 1;
+                        stats.packets_failed += 1;
+                        destination.consecutive_failures += 1;
+                        if let Some(metrics) = &self.metrics {
+                            metrics
+                                .destination_packets_failed_total
+                                .with_label_values(&[&stats.addr])
+                                .inc();
+                        }
+                        if self
+                            .policy
+                            .max_consecutive_failures
+                            .is_some_and(|max| destination.consecutive_failures > max)
+                        {
+                            stats.gave_up = true;
+                            let err = anyhow::Error::new(e).context(format!(
+                                "sending to {} failed {} times in a row",
+                                stats.addr, destination.consecutive_failures
+                            ));
+                            if fan_out {
+                                warn!("Giving up on destination: {:#}", err);
+                            }
+                            last_error = Some(err);
+                            continue;
+                        }
+
+                        self.anomalies.record(
+                            AnomalyClass::SendFailed,
+                            Some(sequence),
+                            format_args!(
+                                "Failed to send packet seq={} to {}: {} (continuing)",
+                                sequence, stats.addr, e
+                            ),
+                            std::time::Instant::now(),
+                        );
+                    }
                 }
             }
-            Err(e) => {
-                error!("Failed to send packet seq={}: {}", packet.sequence, e);
-                // Don't bail - continue sending to demonstrate resilience
-                warn!("Continuing despite network error");
-            }
+            tried = tried.max(next);
         }
 
+        if self.destinations.iter().all(|d| d.stats.gave_up) {
+            sent.truncate(tried);
+            self.anomalies.flush(std::time::Instant::now());
+            return Err(last_error.unwrap_or_else(|| anyhow::anyhow!("no destinations left")));
+        }
+        if sent.contains(&true) {
+            self.last_sent = Some(tokio::time::Instant::now());
+            self.record_media(packets, sent);
+        }
+        if let Some(rtx) = &mut self.rtx {
+            for ((packet, datagram), _) in packets
+                .iter()
+                .zip(datagrams.iter())
+                .zip(sent.iter())
+                .filter(|(_, &sent)| sent)
+            {
+                rtx.insert(packet.sequence, datagram);
+            }
+        }
+        if failed && !self.policy.backoff.is_zero() {
+            tokio::time::sleep(self.policy.backoff).await;
+        }
         Ok(())
     }
 
-    /// Returns statistics about packets sent.
-    pub fn stats(&self) -> (u64, u64) {
+    /// Waits until the bandwidth cap allows `datagrams` to go out to every
+    /// destination still being sent to. Returns at once, without yielding,
+    /// when there is no cap or the bucket holds enough.
+    async fn wait_for_bandwidth(&mut self, datagrams: &[Vec<u8>]) {
         // ---
-        (self.packets_sent, self.bytes_sent)
-    }
-}
+        let Some(bucket) = &mut self.bucket else {
+            return;
+        };
+        let active = self
+            .destinations
+            .iter()
+            .filter(|d| !d.stats.gave_up)
+            .count();
+        let bytes = datagrams.iter().map(Vec::len).sum::<usize>() * active;
+        let wait = bucket.take(bytes, tokio::time::Instant::now());
+        if wait.is_zero() {
+            return;
+        }
 
-#[cfg(test)]
-mod tests {
-    // ---
-    use super::*;
+        self.packets_delayed += datagrams.len() as u64;
+        self.throttled += wait;
+        if let Some(metrics) = &self.metrics {
+            metrics
+                .packets_rate_limited_total
+                .inc_by(datagrams.len() as u64);
+            metrics
+                .rate_limit_wait_seconds_total
+                .inc_by(wait.as_secs_f64());
+        }
+        tokio::time::sleep(wait).await;
+    }
 
-    #[tokio::test]
-    async fn test_sender_creation() {
+    /// Switches destinations whose host name now resolves to a different
+    /// address, reconnecting a connected socket or TCP connection. A UDP
+    /// reconnect that fails leaves the socket unconnected, sending with
+    /// `send_to`.
+    async fn follow_dns_changes(&mut self) {
         // ---
-        let sender = RtpSender::new("127.0.0.1:5004").await;
-        assert!(sender.is_ok());
+        for destination in &mut self.destinations {
+            let Some(updates) = destination
+                .updates
+                .as_mut()
+                .filter(|updates| updates.has_changed().unwrap_or(false))
+            else {
+                continue;
+            };
+            destination.target = *updates.borrow_and_update();
+            debug!(
+                "Sending to {} at {}",
+                destination.stats.addr, destination.target
+            );
+            if let Some(link) = &mut destination.tcp {
+                link.disconnect();
+            }
+            if self.connected {
+                if let Err(e) = self.socket.connect(destination.target).await {
+                    warn!(
+                        "Failed to connect to {}, sending unconnected: {}",
+                        destination.target, e
+                    );
+                    self.connected = false;
+                }
+            }
+        }
     }
 
-    #[tokio::test]
-    async fn test_sender_send_packet() {
-        // ---
-        let mut sender = RtpSender::new("127.0.0.1:5004")
+    /// Drains feedback that arrived on the sending socket, or on the RTCP
+    /// socket if RTCP is not muxed.
+    ///
+    /// Receivers reply to the address packets come from, so feedback lands
+    /// on these sockets. Returns the number of decoder refresh requests for
+    /// `ssrc`. NACKs for `ssrc` are queued for
+    /// [`retransmit_requested`](Self::retransmit_requested), and receiver
+    /// reports on `ssrc` kept for [`remote_report`](Self::remote_report)
+    /// and extended reports logged, their VoIP Metrics kept for
+    /// [`remote_voip_metrics`](Self::remote_voip_metrics); other datagrams, and malformed compound packets, are ignored. Never
+    /// waits.
+    ///
+    /// With SRTP, feedback is drained but not acted on: it is not
+    /// authenticated, so anyone could force encoder resets.
+    ///
+    /// In listen mode, datagrams from anyone but the peer are ignored,
+    /// or with [`SenderNetworkConfig::allow_peer_switch`] move the stream
+    /// to their source. The peer's keepalives are dropped quietly.
+    pub fn poll_refresh_requests(&mut self, ssrc: u32) -> usize {
+        // ---
+        let mut buf = [0u8; 1500];
+        let mut feedback = Feedback::default();
+
+        loop {
+            match self.socket.try_recv_from(&mut buf) {
+                Ok((len, from)) if !self.accept_from(from) => {
+                    debug!("Ignoring {}-byte datagram from {}", len, from);
+                }
+                Ok((len, _)) if Keepalive::deserialize(&buf[..len]).is_ok() => {}
+                Ok((len, from)) if !is_rtcp(&buf[..len]) => {
+                    debug!("Ignoring {}-byte datagram from {}", len, from);
+                }
+                Ok((len, from)) => {
+                    self.rtcp.dispatch(&buf[..len], from, &mut feedback);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    // ICMP errors from an unreachable receiver surface here
+                    debug!("Feedback receive failed: {}", e);
+                    break;
+                }
+            }
+        }
+        self.rtcp.try_recv(&mut feedback);
+
+        for (report, from) in feedback.receiver_reports {
+            self.record_receiver_report(report, ssrc, from);
+        }
+        for (report, from) in feedback.extended_reports {
+            self.record_extended_report(report, ssrc, from);
+        }
+        for (nack, from) in feedback.nacks {
+            self.queue_nack(nack, ssrc, from);
+        }
+        let mut requests = 0;
+        for (request, from) in feedback.refresh_requests {
+            if self.srtp.is_some() {
+                debug!("Ignoring unauthenticated refresh request from {}", from);
+            } else if request.media_ssrc == ssrc {
+                debug!("Decoder refresh request from {}", from);
+                requests += 1;
+            } else {
+                debug!(
+                    "Ignoring refresh request for SSRC 0x{:08X} from {}",
+                    request.media_ssrc, from
+                );
+            }
+        }
+
+        requests
+    }
+
+    /// Returns how many RTCP datagrams were dropped as malformed compound
+    /// packets.
+    pub fn rtcp_malformed(&self) -> u64 {
+        // ---
+        self.rtcp.malformed()
+    }
+
+    /// Keeps the block of `report` on `ssrc` and exports what it says of
+    /// the receiver's reception.
+    fn record_receiver_report(&mut self, report: ReceiverReport, ssrc: u32, from: SocketAddr) {
+        // ---
+        if self.srtp.is_some() {
+            debug!("Ignoring unauthenticated receiver report from {}", from);
+            return;
+        }
+        let Some(block) = report.blocks.into_iter().find(|block| block.ssrc == ssrc) else {
+            debug!(
+                "Ignoring receiver report without SSRC 0x{:08X} from {}",
+                ssrc, from
+            );
+            return;
+        };
+        let jitter = block.jitter as f64 / self.rtp_clock_rate as f64;
+        debug!(
+            "Receiver report from {}: {:.1}% lost, {} cumulative, jitter {:.1}ms",
+            from,
+            block.fraction_lost_ratio() * 100.0,
+            block.cumulative_lost,
+            jitter * 1000.0
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.receiver_reports_received_total.inc();
+            metrics
+                .remote_fraction_lost
+                .set(block.fraction_lost_ratio());
+            metrics.remote_jitter_seconds.set(jitter);
+        }
+        self.receiver_reports_received += 1;
+        self.remote_report = Some(block);
+        if let Some(events) = &self.report_events {
+            if events.send(block).is_err() {
+                self.report_events = None;
+            }
+        }
+    }
+
+    /// Publishes the block on the stream of every receiver report taken
+    /// in from now on to the returned channel, e.g. for an
+    /// [`AdaptationController`](crate::AdaptationController). Replaces
+    /// any earlier subscription.
+    pub fn subscribe_reports(&mut self) -> mpsc::UnboundedReceiver<ReportBlock> {
+        // ---
+        let (events, reports) = mpsc::unbounded_channel();
+        self.report_events = Some(events);
+        reports
+    }
+
+    /// Returns the block on this stream of the last receiver report
+    /// taken in by [`poll_refresh_requests`](Self::poll_refresh_requests).
+    pub fn remote_report(&self) -> Option<ReportBlock> {
+        // ---
+        self.remote_report
+    }
+
+    /// Logs the blocks of `report` on `ssrc`, keeping its VoIP Metrics
+    /// and exporting the listening quality they estimate.
+    fn record_extended_report(&mut self, report: ExtendedReport, ssrc: u32, from: SocketAddr) {
+        // ---
+        if self.srtp.is_some() {
+            debug!("Ignoring unauthenticated extended report from {}", from);
+            return;
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.extended_reports_received_total.inc();
+        }
+        for block in report.blocks {
+            match block {
+                XrBlock::LossRle(loss) if loss.ssrc == ssrc => {
+                    let received = loss.received();
+                    let lost = received.iter().filter(|&&received| !received).count();
+                    debug!(
+                        "Loss RLE from {}: {} of {} lost in seq {}..{}",
+                        from,
+                        lost,
+                        received.len(),
+                        loss.begin_sequence,
+                        loss.end_sequence
+                    );
+                }
+                XrBlock::VoipMetrics(voip) if voip.ssrc == ssrc => {
+                    let mos =
+                        |mos: Option<f64>| mos.map_or("n/a".to_string(), |m| format!("{m:.1}"));
+                    debug!(
+                        "VoIP metrics from {}: {:.1}% lost, {:.1}% discarded, R {}, MOS-LQ {}, MOS-CQ {}, jitter buffer {}ms",
+                        from,
+                        voip.loss_ratio() * 100.0,
+                        voip.discard_ratio() * 100.0,
+                        voip.r_factor,
+                        mos(voip.mos_lq()),
+                        mos(voip.mos_cq()),
+                        voip.jb_nominal
+                    );
+                    if let (Some(metrics), Some(mos)) = (&self.metrics, voip.mos_lq()) {
+                        metrics.remote_mos_lq.set(mos);
+                    }
+                    self.remote_voip_metrics = Some(voip);
+                }
+                XrBlock::LossRle(_) | XrBlock::VoipMetrics(_) => {
+                    debug!(
+                        "Ignoring extended report block on another SSRC from {}",
+                        from
+                    );
+                }
+                XrBlock::Other { block_type, .. } => {
+                    debug!(
+                        "Ignoring extended report block type {} from {}",
+                        block_type, from
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns the VoIP Metrics block on this stream of the last extended
+    /// report taken in by
+    /// [`poll_refresh_requests`](Self::poll_refresh_requests).
+    pub fn remote_voip_metrics(&self) -> Option<VoipMetrics> {
+        // ---
+        self.remote_voip_metrics
+    }
+
+    /// Queues the packets of `nack` for retransmission if it is for
+    /// `ssrc` and can be acted on.
+    fn queue_nack(&mut self, nack: Nack, ssrc: u32, from: SocketAddr) {
+        // ---
+        if self.srtp.is_some() {
+            debug!("Ignoring unauthenticated NACK from {}", from);
+        } else if nack.media_ssrc != ssrc {
+            debug!(
+                "Ignoring NACK for SSRC 0x{:08X} from {}",
+                nack.media_ssrc, from
+            );
+        } else if self.rtx.is_none() {
+            debug!("Ignoring NACK from {}: no retransmission cache", from);
+        } else {
+            debug!("NACK from {} for {:?}", from, nack.lost);
+            if let Some(metrics) = &self.metrics {
+                metrics
+                    .nack_requests_received_total
+                    .inc_by(nack.lost.len() as u64);
+            }
+            self.nacked.extend(nack.lost);
+        }
+    }
+
+    /// Retransmits the packets NACKed since the last call (see
+    /// [`poll_refresh_requests`](Self::poll_refresh_requests)); returns
+    /// how many went out again.
+    pub async fn retransmit_requested(&mut self) -> usize {
+        // ---
+        if self.nacked.is_empty() {
+            return 0;
+        }
+        let nacked = std::mem::take(&mut self.nacked);
+        self.handle_nack(&nacked).await
+    }
+
+    /// Sends the packets with `sequences` again, as they first went out,
+    /// to every UDP destination still being sent to.
+    ///
+    /// Packets no longer (or never) in the retransmission cache are
+    /// skipped, as are repeats within `sequences`. Retransmissions count
+    /// against the bandwidth cap; a failed one is logged and not retried.
+    ///
+    /// # Returns
+    ///
+    /// The number of packets that went out again to at least one
+    /// destination.
+    pub async fn handle_nack(&mut self, sequences: &[u16]) -> usize {
+        // ---
+        let Some(rtx) = &self.rtx else {
+            return 0;
+        };
+        let mut wanted: Vec<u16> = Vec::with_capacity(sequences.len());
+        let mut datagrams = Vec::with_capacity(sequences.len());
+        for &sequence in sequences {
+            if wanted.contains(&sequence) {
+                continue;
+            }
+            match rtx.get(sequence) {
+                Some(datagram) => {
+                    wanted.push(sequence);
+                    datagrams.push(datagram.to_vec());
+                }
+                None => debug!("Cannot retransmit seq={}: no longer cached", sequence),
+            }
+        }
+        if datagrams.is_empty() {
+            return 0;
+        }
+        self.wait_for_bandwidth(&datagrams).await;
+
+        let mut resent = vec![false; datagrams.len()];
+        for destination in &self.destinations {
+            if destination.stats.gave_up || destination.tcp.is_some() {
+                continue;
+            }
+            for (index, datagram) in datagrams.iter().enumerate() {
+                let result = transmit(
+                    &self.socket,
+                    self.connected,
+                    std::slice::from_ref(datagram),
+                    destination.target,
+                )
+                .await;
+                match result {
+                    Ok(_) => resent[index] = true,
+                    Err(e) => debug!(
+                        "Failed to retransmit seq={} to {}: {}",
+                        wanted[index], destination.stats.addr, e
+                    ),
+                }
+            }
+        }
+
+        let count = resent.iter().filter(|&&resent| resent).count();
+        if count > 0 {
+            self.last_sent = Some(tokio::time::Instant::now());
+        }
+        self.packets_retransmitted += count as u64;
+        if let Some(metrics) = &self.metrics {
+            metrics.packets_retransmitted_total.inc_by(count as u64);
+        }
+        count
+    }
+
+    /// Sends a keepalive to every UDP destination still being sent to if
+    /// nothing has gone out for [`SenderNetworkConfig::keepalive_interval`].
+    ///
+    /// The keepalive is an empty UDP datagram (RFC 6263 §4.1): it carries
+    /// no RTP header, so it uses up no sequence number and the receiver
+    /// sees no gap, and it is not SRTP-protected. Nothing is sent before
+    /// the first packet, or over TCP, whose connections need no help. A
+    /// failed keepalive is logged and made up by the next one. Never
+    /// waits for the bandwidth cap: the datagram has no payload.
+    ///
+    /// # Returns
+    ///
+    /// Whether a keepalive went out to at least one destination.
+    pub async fn keep_alive(&mut self) -> bool {
+        // ---
+        let (Some(interval), Some(last_sent)) = (self.keepalive_interval, self.last_sent) else {
+            return false;
+        };
+        let now = tokio::time::Instant::now();
+        if now.saturating_duration_since(last_sent) < interval {
+            return false;
+        }
+        self.last_sent = Some(now);
+
+        let mut sent = false;
+        for destination in &self.destinations {
+            if destination.stats.gave_up || destination.tcp.is_some() {
+                continue;
+            }
+            match transmit(
+                &self.socket,
+                self.connected,
+                &[Vec::new()],
+                destination.target,
+            )
+            .await
+            {
+                Ok(_) => sent = true,
+                Err(e) => debug!("Keepalive to {} failed: {}", destination.stats.addr, e),
+            }
+        }
+        if sent {
+            self.keepalives_sent += 1;
+            if let Some(metrics) = &self.metrics {
+                metrics.keepalives_sent_total.inc();
+            }
+        }
+        sent
+    }
+
+    /// Sends an RTCP sender report to every UDP destination still being
+    /// sent to if the report interval has passed since the last one:
+    /// [`SenderNetworkConfig::sender_report_interval`], or longer if
+    /// reports would otherwise take more than 5% of
+    /// [`SenderNetworkConfig::session_bandwidth`].
+    ///
+    /// The first report goes out with the first call after a packet has,
+    /// and maps the time of sending to the RTP timestamp the stream has
+    /// reached, extrapolated from the last packet at
+    /// [`SenderNetworkConfig::rtp_clock_rate`]. Each goes out compound with
+    /// an SDES packet carrying [`SenderNetworkConfig::cname`]. Reports share the RTP
+    /// socket (RFC 5761) unless [`SenderNetworkConfig::rtcp_mux`] is off.
+    /// None are sent with SRTP, whose RTCP would need
+    /// SRTCP protection, or over TCP. A failed report is logged and made
+    /// up by the next one.
+    ///
+    /// # Returns
+    ///
+    /// Whether a report went out to at least one destination.
+    pub async fn send_sender_report(&mut self) -> bool {
+        // ---
+        let Some(media) = self.media else {
+            return false;
+        };
+        if self.srtp.is_some() || !self.rtcp.report_due(tokio::time::Instant::now()) {
+            return false;
+        }
+
+        let (report, compound) = self.sender_report(media);
+        let sent = self.send_rtcp(&compound.serialize(), "Sender report").await;
+        if sent {
+            self.sender_reports_sent += 1;
+            if let Some(metrics) = &self.metrics {
+                metrics.sender_reports_sent_total.inc();
+            }
+            debug!(
+                "Sender report: {} packets, {} octets, RTP timestamp {}",
+                report.packet_count, report.octet_count, report.rtp_timestamp
+            );
+        }
+        sent
+    }
+
+    /// Says goodbye to every UDP destination still being sent to: an RTCP
+    /// BYE for the stream with `reason`, compound after a last sender
+    /// report and SDES (RFC 3550 §6.6), so receivers can end the stream
+    /// at once rather than wait out an idle timeout. Meant as the
+    /// sender's last action. Nothing is sent before the first packet has
+    /// gone out, with SRTP, or over TCP.
+    ///
+    /// # Returns
+    ///
+    /// Whether the goodbye went out to at least one destination.
+    pub async fn send_bye(&mut self, reason: &str) -> bool {
+        // ---
+        let Some(media) = self.media else {
+            return false;
+        };
+        if self.srtp.is_some() {
+            return false;
+        }
+        let (_, report) = self.sender_report(media);
+        let bye = Bye {
+            ssrcs: vec![media.ssrc],
+            reason: Some(reason.to_string()),
+        };
+        let sent = self
+            .send_rtcp(&report.with_bye(&bye).serialize(), "BYE")
+            .await;
+        if sent {
+            debug!("BYE for SSRC 0x{:08X}: {}", media.ssrc, reason);
+        }
+        sent
+    }
+
+    /// Returns a sender report on the stream as of now, and the compound
+    /// packet carrying it with the SDES naming the stream.
+    fn sender_report(&self, media: MediaSent) -> (SenderReport, CompoundPacket) {
+        // ---
+        let wallclock = SystemTime::now();
+        let elapsed = wallclock
+            .duration_since(media.sent_at)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let report = SenderReport {
+            ssrc: media.ssrc,
+            ntp_timestamp: ntp_timestamp(wallclock),
+            rtp_timestamp: media
+                .timestamp
+                .wrapping_add((elapsed * self.rtp_clock_rate as f64) as u32),
+            packet_count: media.packets as u32,
+            octet_count: media.octets as u32,
+        };
+        let sdes = Sdes {
+            chunks: vec![SdesChunk::with_cname(media.ssrc, self.cname.as_str())],
+        };
+        let compound = CompoundPacket::sender_report(&report).with_sdes(&sdes);
+        (report, compound)
+    }
+
+    /// Sends an RTCP `datagram` to every UDP destination still being sent
+    /// to, logging failures as `what`; whether it reached at least one.
+    async fn send_rtcp(&mut self, datagram: &[u8], what: &str) -> bool {
+        // ---
+        let mut sent = false;
+        for destination in &self.destinations {
+            if destination.stats.gave_up || destination.tcp.is_some() {
+                continue;
+            }
+            match self
+                .rtcp
+                .send(&self.socket, datagram, destination.target)
+                .await
+            {
+                Ok(_) => sent = true,
+                Err(e) => debug!("{} to {} failed: {}", what, destination.stats.addr, e),
+            }
+        }
+        sent
+    }
+
+    /// Counts the packets of a batch that went out toward sender reports.
+    fn record_media(&mut self, packets: &[RtpPacket], sent: &[bool]) {
+        // ---
+        let sent_at = SystemTime::now();
+        for (packet, _) in packets.iter().zip(sent).filter(|(_, &sent)| sent) {
+            let media = self.media.get_or_insert(MediaSent {
+                ssrc: packet.ssrc,
+                timestamp: packet.timestamp,
+                sent_at,
+                packets: 0,
+                octets: 0,
+            });
+            media.ssrc = packet.ssrc;
+            media.timestamp = packet.timestamp;
+            media.sent_at = sent_at;
+            media.packets += 1;
+            media.octets += packet.payload.len() as u64;
+        }
+    }
+
+    /// Decides whether a datagram from `from` comes from the receiver,
+    /// moving the stream to `from` in listen mode if switching is allowed.
+    fn accept_from(&mut self, from: SocketAddr) -> bool {
+        // ---
+        let Some(peer) = self.peer_addr().filter(|&peer| peer != from) else {
+            return true;
+        };
+        if !self.allow_peer_switch {
+            return false;
+        }
+
+        // The destination keeps its first address as its name, so its
+        // counts and metric labels carry on
+        info!("Peer moved from {} to {}", peer, from);
+        let destination = &mut self.destinations[0];
+        destination.target = from;
+        destination.consecutive_failures = 0;
+        true
+    }
+
+    /// Returns the socket send buffer size the kernel reports.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the size cannot be queried.
+    pub fn send_buffer_size(&self) -> Result<usize> {
+        // ---
+        socket2::SockRef::from(&self.socket)
+            .send_buffer_size()
+            .context("failed to query send buffer size")
+    }
+
+    /// Returns the local address the socket is bound to.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the socket address cannot be queried.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        // ---
+        self.socket
+            .local_addr()
+            .context("failed to query local address")
+    }
+
+    /// Returns statistics about packets sent.
+    pub fn stats(&self) -> SenderStats {
+        // ---
+        let destinations: Vec<DestinationStats> =
+            self.destinations.iter().map(|d| d.stats.clone()).collect();
+        SenderStats {
+            packets_sent: destinations.iter().map(|d| d.packets_sent).sum(),
+            bytes_sent: destinations.iter().map(|d| d.bytes_sent).sum(),
+            packets_failed: destinations.iter().map(|d| d.packets_failed).sum(),
+            packets_delayed: self.packets_delayed,
+            throttled: self.throttled,
+            packets_retransmitted: self.packets_retransmitted,
+            keepalives_sent: self.keepalives_sent,
+            sender_reports_sent: self.sender_reports_sent,
+            receiver_reports_received: self.receiver_reports_received,
+            destinations,
+        }
+    }
+}
+
+/// Returns the RTCP transport `config` asks for beside the RTP `socket`,
+/// with its handlers registered and sender reports scheduled.
+///
+/// RTCP gets a socket of its own only over UDP without SRTP, the only
+/// case it is sent in.
+async fn rtcp_socket(
+    config: &SenderNetworkConfig,
+    socket: &UdpSocket,
+) -> Result<RtcpSocket<Feedback>> {
+    // ---
+    let mut rtcp =
+        if config.rtcp_mux || config.transport == Transport::Tcp || config.srtp_key.is_some() {
+            RtcpSocket::muxed()
+        } else {
+            let rtcp = RtcpSocket::bind(socket.local_addr()?).await?;
+            if let Some(addr) = rtcp.local_addr() {
+                info!("RTCP socket bound to {}", addr);
+            }
+            rtcp
+        }
+        .with_handler(RTCP_PT_RR, Feedback::on_receiver_report)
+        .with_handler(RTCP_PT_XR, Feedback::on_extended_report)
+        .with_handler(RTCP_PT_RTPFB, Feedback::on_nack)
+        .with_handler(RTCP_PT_PSFB, Feedback::on_refresh_request);
+    let interval = config
+        .sender_report_interval
+        .map(|interval| RtcpInterval::new(config.session_bandwidth, interval));
+    rtcp.schedule_reports(interval, true);
+    Ok(rtcp)
+}
+
+/// Feedback from receivers taken in by the sender's RTCP handlers during
+/// one [`RtpSender::poll_refresh_requests`], with where each came from.
+#[derive(Default)]
+struct Feedback {
+    // ---
+    receiver_reports: Vec<(ReceiverReport, SocketAddr)>,
+    extended_reports: Vec<(ExtendedReport, SocketAddr)>,
+    nacks: Vec<(Nack, SocketAddr)>,
+    refresh_requests: Vec<(DecoderRefreshRequest, SocketAddr)>,
+}
+
+impl Feedback {
+    // ---
+    /// Keeps a receiver report.
+    fn on_receiver_report(&mut self, packet: &[u8], from: SocketAddr) {
+        // ---
+        match ReceiverReport::deserialize(packet) {
+            Ok(report) => self.receiver_reports.push((report, from)),
+            Err(e) => debug!("Ignoring receiver report from {}: {}", from, e),
+        }
+    }
+
+    /// Keeps an extended report.
+    fn on_extended_report(&mut self, packet: &[u8], from: SocketAddr) {
+        // ---
+        match ExtendedReport::deserialize(packet) {
+            Ok(report) => self.extended_reports.push((report, from)),
+            Err(e) => debug!("Ignoring extended report from {}: {}", from, e),
+        }
+    }
+
+    /// Keeps a NACK.
+    fn on_nack(&mut self, packet: &[u8], from: SocketAddr) {
+        // ---
+        match Nack::deserialize(packet) {
+            Ok(nack) => self.nacks.push((nack, from)),
+            Err(e) => debug!("Ignoring NACK from {}: {}", from, e),
+        }
+    }
+
+    /// Keeps a decoder refresh request.
+    fn on_refresh_request(&mut self, packet: &[u8], from: SocketAddr) {
+        // ---
+        match DecoderRefreshRequest::deserialize(packet) {
+            Ok(request) => self.refresh_requests.push((request, from)),
+            Err(e) => debug!("Ignoring feedback from {}: {}", from, e),
+        }
+    }
+}
+
+/// Returns the retransmission cache `config` asks for, if any.
+fn rtx_cache(config: &SenderNetworkConfig) -> Option<RtxCache> {
+    // ---
+    (config.rtx_cache_packets > 0 && config.srtp_key.is_none())
+        .then(|| RtxCache::new(config.rtx_cache_packets))
+}
+
+/// Sends `datagrams` in order to `target`, or to the peer of a connected
+/// `socket`, and returns how many went out before the first failure.
+///
+/// # Errors
+///
+/// Returns the error of the first datagram if it could not be sent.
+async fn transmit(
+    socket: &UdpSocket,
+    connected: bool,
+    datagrams: &[Vec<u8>],
+    target: SocketAddr,
+) -> std::io::Result<usize> {
+    // ---
+    let target = (!connected).then_some(target);
+    match datagrams {
+        [] => Ok(0),
+        [data] => match target {
+            Some(target) => socket.send_to(data, target).await.map(|_| 1),
+            None => socket.send(data).await.map(|_| 1),
+        },
+        #[cfg(target_os = "linux")]
+        _ => {
+            socket
+                .async_io(tokio::io::Interest::WRITABLE, || {
+                    sendmmsg(socket, datagrams, target)
+                })
+                .await
+        }
+        #[cfg(not(target_os = "linux"))]
+        _ => {
+            for (count, data) in datagrams.iter().enumerate() {
+                let result = match target {
+                    Some(target) => socket.send_to(data, target).await,
+                    None => socket.send(data).await,
+                };
+                if let Err(e) = result {
+                    return if count == 0 { Err(e) } else { Ok(count) };
+                }
+            }
+            Ok(datagrams.len())
+        }
+    }
+}
+
+/// Hands `datagrams` to the kernel in one `sendmmsg` call, addressed to
+/// `target` or, with `None`, to the peer of the connected `socket`.
+/// Returns how many were sent; never waits.
+#[cfg(target_os = "linux")]
+fn sendmmsg(
+    socket: &UdpSocket,
+    datagrams: &[Vec<u8>],
+    target: Option<SocketAddr>,
+) -> std::io::Result<usize> {
+    // ---
+    use std::os::fd::AsRawFd;
+
+    let addr = target.map(socket2::SockAddr::from);
+    let mut iovecs: Vec<libc::iovec> = datagrams
+        .iter()
+        .map(|data| libc::iovec {
+            iov_base: data.as_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        })
+        .collect();
+    let mut messages: Vec<libc::mmsghdr> = iovecs
+        .iter_mut()
+        .map(|iovec| {
+            // SAFETY: msghdr is plain old data; all zeroes is a valid value
+            let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
+            header.msg_iov = iovec;
+            header.msg_iovlen = 1;
+            if let Some(addr) = &addr {
+                header.msg_name = addr.as_ptr() as *mut libc::c_void;
+                header.msg_namelen = addr.len();
+            }
+            libc::mmsghdr {
+                msg_hdr: header,
+                msg_len: 0,
+            }
+        })
+        .collect();
+
+    // SAFETY: every header points at an iovec and address that outlive
+    // the call, and each iovec at a live datagram buffer
+    let sent = unsafe {
+        libc::sendmmsg(
+            socket.as_raw_fd(),
+            messages.as_mut_ptr(),
+            messages.len() as libc::c_uint,
+            0,
+        )
+    };
+    if sent < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(sent as usize)
+    }
+}
+
+/// Requests a send buffer of `bytes` on `socket` and logs what the kernel
+/// granted, which may be clamped (or, on Linux, doubled for bookkeeping).
+/// A refusal is logged and the default kept.
+fn set_send_buffer(socket: &UdpSocket, bytes: usize) {
+    // ---
+    let socket = socket2::SockRef::from(socket);
+    if let Err(e) = socket.set_send_buffer_size(bytes) {
+        warn!("Failed to set send buffer to {} bytes: {}", bytes, e);
+        return;
+    }
+    match socket.send_buffer_size() {
+        Ok(granted) => info!(
+            "Send buffer: {} bytes requested, {} granted",
+            bytes, granted
+        ),
+        Err(e) => warn!("Failed to read back send buffer size: {}", e),
+    }
+}
+
+/// Marks packets sent from `socket` with `dscp`.
+///
+/// Refusal by the OS (e.g. a value that needs privileges) is logged and
+/// the packets go out unmarked; only a value out of range is an error.
+fn set_dscp(socket: &UdpSocket, dscp: u8) -> Result<()> {
+    // ---
+    if dscp > MAX_DSCP {
+        anyhow::bail!("DSCP {dscp} is out of range (0 to {MAX_DSCP})");
+    }
+
+    // The socket is IPv4, so the marking goes in IP_TOS; the low two bits
+    // belong to ECN and stay clear
+    let tos = (dscp as u32) << 2;
+    match socket2::SockRef::from(socket).set_tos_v4(tos) {
+        Ok(()) => info!("Marking packets with DSCP {} (ToS 0x{:02X})", dscp, tos),
+        Err(e) => warn!(
+            "Failed to mark packets with DSCP {}, sending unmarked: {}",
+            dscp, e
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sender_creation() {
+        // ---
+        let sender = RtpSender::new("127.0.0.1:5004").await;
+        assert!(sender.is_ok());
+    }
+
+    #[test]
+    fn test_parse_dscp() {
+        // ---
+        assert_eq!(parse_dscp("EF").unwrap(), 46);
+        assert_eq!(parse_dscp("ef").unwrap(), 46);
+        assert_eq!(parse_dscp("AF41").unwrap(), 34);
+        assert_eq!(parse_dscp("AF11").unwrap(), 10);
+        assert_eq!(parse_dscp("CS5").unwrap(), 40);
+        assert_eq!(parse_dscp("CS0").unwrap(), 0);
+        assert_eq!(parse_dscp("46").unwrap(), 46);
+        assert_eq!(parse_dscp("0x2e").unwrap(), 46);
+        assert_eq!(parse_dscp("63").unwrap(), MAX_DSCP);
+
+        // AFxy is 8x + 2y, CSx is 8x
+        for &(name, dscp) in DSCP_NAMES {
+            let digits: Vec<u8> = name
+                .bytes()
+                .filter(u8::is_ascii_digit)
+                .map(|d| d - b'0')
+                .collect();
+            match &name[..2] {
+                "AF" => assert_eq!(dscp, 8 * digits[0] + 2 * digits[1], "{name}"),
+                "CS" => assert_eq!(dscp, 8 * digits[0], "{name}"),
+                _ => {}
+            }
+        }
+
+        assert!(parse_dscp("64").is_err());
+        assert!(parse_dscp("AF44").is_err());
+        assert!(parse_dscp("-1").is_err());
+        assert!(parse_dscp("").is_err());
+    }
+
+    /// Resolves every name to the address `target` holds at the time.
+    struct SwitchingResolver {
+        // ---
+        target: std::sync::Mutex<SocketAddr>,
+    }
+
+    impl Resolver for SwitchingResolver {
+        fn lookup(&self, _addr: &str) -> std::io::Result<Vec<SocketAddr>> {
+            // ---
+            Ok(vec![*self.target.lock().unwrap()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dns_refresh_switches_destination_between_packets() {
+        // ---
+        let first = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let resolver = Arc::new(SwitchingResolver {
+            target: std::sync::Mutex::new(first.local_addr().unwrap()),
+        });
+        let config = SenderNetworkConfig {
+            dns_refresh: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let mut sender = RtpSender::with_resolver(["media.test:5004"], config, resolver.clone())
+            .await
+            .expect("sender creation failed");
+
+        let packet = |sequence: u16| RtpPacket::new(sequence, sequence as u32 * 320, 7, vec![1]);
+        assert!(sender.send(&packet(0)).await.unwrap());
+        *resolver.target.lock().unwrap() = second.local_addr().unwrap();
+
+        // Keep sending until the refresh moves the stream over
+        let mut buf = [0u8; 64];
+        let mut sequence = 1;
+        let switched_at = loop {
+            assert!(sequence < 200, "never switched");
+            assert!(sender.send(&packet(sequence)).await.unwrap());
+            if let Ok(len) = second.try_recv(&mut buf) {
+                break RtpPacket::deserialize(&buf[..len]).unwrap().sequence;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            sequence += 1;
+        };
+
+        // The first destination got everything before the switch, in order
+        for expected in 0..switched_at {
+            let len = first.recv(&mut buf).await.unwrap();
+            assert_eq!(
+                RtpPacket::deserialize(&buf[..len]).unwrap().sequence,
+                expected
+            );
+        }
+        assert!(first.try_recv(&mut buf).is_err());
+        assert_eq!(sender.stats().packets_sent, sequence as u64 + 1);
+        assert_eq!(sender.stats().destinations[0].addr, "media.test:5004");
+    }
+
+    #[tokio::test]
+    async fn test_sender_send_buffer_size() {
+        // ---
+        let default = RtpSender::new("127.0.0.1:5004")
+            .await
+            .unwrap()
+            .send_buffer_size()
+            .unwrap();
+        let config = SenderNetworkConfig {
+            send_buffer_bytes: Some(default * 2),
+            ..Default::default()
+        };
+        let sender = RtpSender::with_config("127.0.0.1:5004", config)
+            .await
+            .unwrap();
+        assert!(sender.send_buffer_size().unwrap() >= default);
+    }
+
+    #[tokio::test]
+    async fn test_sender_binds_local_addr() {
+        // ---
+        // Two free ports, released for the senders to take
+        let ports: Vec<u16> = (0..2)
+            .map(|_| std::net::UdpSocket::bind("127.0.0.1:0").unwrap())
+            .collect::<Vec<_>>()
+            .iter()
+            .map(|socket| socket.local_addr().unwrap().port())
+            .collect();
+        assert_ne!(ports[0], ports[1]);
+
+        let mut senders = Vec::new();
+        for &port in &ports {
+            let local_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+            let config = SenderNetworkConfig {
+                local_addr: Some(local_addr),
+                ..Default::default()
+            };
+            let sender = RtpSender::with_config("127.0.0.1:5004", config)
+                .await
+                .expect("sender creation failed");
+            assert_eq!(sender.local_addr().unwrap(), local_addr);
+            senders.push(sender);
+        }
+
+        // A port in use is reported with the address
+        let local_addr = SocketAddr::from((Ipv4Addr::LOCALHOST, ports[0]));
+        let config = SenderNetworkConfig {
+            local_addr: Some(local_addr),
+            ..Default::default()
+        };
+        let err = RtpSender::with_config("127.0.0.1:5004", config)
+            .await
+            .err()
+            .expect("port in use");
+        assert!(err.to_string().contains(&local_addr.to_string()), "{err}");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_sender_marks_packets_with_dscp() {
+        // ---
+        let config = SenderNetworkConfig {
+            dscp: Some(parse_dscp("EF").unwrap()),
+            ..Default::default()
+        };
+        let sender = RtpSender::with_config("127.0.0.1:5004", config)
+            .await
+            .expect("sender creation failed");
+        let tos = socket2::SockRef::from(&sender.socket).tos_v4().unwrap();
+        assert_eq!(tos, 46 << 2);
+
+        let config = SenderNetworkConfig {
+            dscp: Some(MAX_DSCP + 1),
+            ..Default::default()
+        };
+        assert!(RtpSender::with_config("127.0.0.1:5004", config)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sender_send_packet() {
+        // ---
+        let mut sender = RtpSender::new("127.0.0.1:5004")
             .await
             .expect("sender creation failed");
 
@@ -133,10 +2132,584 @@ mod tests {
         let result = sender.send(&packet).await;
 
         // Should succeed even if no receiver (UDP is fire-and-forget)
-        assert!(result.is_ok());
+        assert!(result.expect("send"));
+
+        let stats = sender.stats();
+        assert_eq!(stats.packets_sent, 1);
+        assert!(stats.bytes_sent > 0);
+        assert_eq!(stats.packets_failed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_connected_send_reaches_receiver() {
+        // ---
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").expect("bind");
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .expect("timeout");
+        let addr = receiver.local_addr().unwrap();
+        let mut sender = RtpSender::new(addr.to_string())
+            .await
+            .expect("sender creation failed");
+        assert!(sender.connected);
+        assert_eq!(sender.socket.peer_addr().unwrap(), addr);
+
+        for sequence in 0..3u16 {
+            let packet = RtpPacket::new(sequence, sequence as u32 * 320, 7, vec![1, 2, 3]);
+            assert!(sender.send(&packet).await.expect("send"));
+        }
+        let mut buf = [0u8; 64];
+        for sequence in 0..3u16 {
+            let (len, from) = receiver.recv_from(&mut buf).expect("packet");
+            assert_eq!(from, sender.local_addr().unwrap());
+            let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+            assert_eq!(packet.sequence, sequence);
+        }
+    }
 
-        let (packets, bytes) = sender.stats();
-        assert_eq!(packets, 1);
-        assert!(bytes > 0);
+    #[tokio::test]
+    async fn test_connected_send_survives_nothing_listening() {
+        // ---
+        // Bind and release a port so nothing listens there
+        let addr = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut sender = RtpSender::new(addr.to_string())
+            .await
+            .expect("sender creation failed");
+        sender.set_error_policy(ErrorPolicy {
+            max_consecutive_failures: Some(0),
+            backoff: Duration::ZERO,
+        });
+
+        // Each send after the first meets the ICMP error for the one
+        // before; none may fail, even with no failure budget at all
+        for sequence in 0..20u16 {
+            let packet = RtpPacket::new(sequence, sequence as u32 * 320, 7, vec![1, 2, 3]);
+            assert!(sender.send(&packet).await.expect("send"));
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let stats = sender.stats();
+        assert_eq!(stats.packets_sent, 20);
+        assert_eq!(stats.packets_failed, 0);
+        assert!(!stats.destinations[0].gave_up);
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_delivers_every_sequence() {
+        // ---
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::new(receiver.local_addr().unwrap().to_string())
+            .await
+            .expect("sender creation failed");
+
+        // Each batch is read back before the next so none overflows the
+        // receive buffer
+        let mut sent = Vec::new();
+        let mut bytes = 0;
+        let mut buf = [0u8; 256];
+        for first in (0..10_000u16).step_by(DEFAULT_BATCH) {
+            let packets: Vec<RtpPacket> = (first..10_000.min(first + DEFAULT_BATCH as u16))
+                .map(|sequence| {
+                    RtpPacket::new(
+                        sequence,
+                        sequence as u32 * 320,
+                        7,
+                        vec![0; sequence as usize % 100],
+                    )
+                })
+                .collect();
+            sender.send_batch(&packets, &mut sent).await.expect("send");
+            assert_eq!(sent, vec![true; packets.len()]);
+            for packet in &packets {
+                let len = receiver.recv(&mut buf).await.expect("recv");
+                let received = RtpPacket::deserialize(&buf[..len]).expect("parse");
+                assert_eq!(received.sequence, packet.sequence);
+                assert_eq!(received.payload, packet.payload);
+                bytes += len as u64;
+            }
+        }
+
+        let stats = sender.stats();
+        assert_eq!(stats.packets_sent, 10_000);
+        assert_eq!(stats.bytes_sent, bytes);
+        assert_eq!(stats.packets_failed, 0);
+
+        let too_many = vec![RtpPacket::new(0, 0, 7, vec![1]); MAX_BATCH + 1];
+        assert!(sender.send_batch(&too_many, &mut sent).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_send_batch_counts_failures_per_packet() {
+        // ---
+        // An IPv4 socket cannot reach an IPv6 address, so every send fails
+        let mut sender = RtpSender::new("[::1]:5004")
+            .await
+            .expect("sender creation failed");
+        sender.set_error_policy(ErrorPolicy {
+            max_consecutive_failures: Some(5),
+            ..Default::default()
+        });
+
+        let packets: Vec<RtpPacket> = (0..4u16)
+            .map(|sequence| RtpPacket::new(sequence, 0, 7, vec![1, 2, 3]))
+            .collect();
+        let mut sent = Vec::new();
+        sender
+            .send_batch(&packets, &mut sent)
+            .await
+            .expect("within budget");
+        assert_eq!(sent, [false; 4]);
+        assert_eq!(sender.stats().packets_failed, 4);
+
+        // The sixth failure in a row uses up the budget partway through
+        let err = sender.send_batch(&packets, &mut sent).await.unwrap_err();
+        assert!(format!("{err:#}").contains("6 times in a row"), "{err:#}");
+        assert_eq!(sent, [false; 2]);
+        assert_eq!(sender.stats().packets_failed, 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_send_failures_use_up_budget() {
+        // ---
+        // An IPv4 socket cannot reach an IPv6 address, so every send fails
+        let mut sender = RtpSender::new("[::1]:5004")
+            .await
+            .expect("sender creation failed");
+        sender.set_error_policy(ErrorPolicy {
+            max_consecutive_failures: Some(3),
+            backoff: Duration::from_millis(100),
+        });
+
+        let packet = RtpPacket::new(1, 320, 0x12345678, vec![1, 2, 3]);
+        let start = tokio::time::Instant::now();
+        for _ in 0..3 {
+            assert!(!sender.send(&packet).await.expect("within budget"));
+        }
+        assert_eq!(start.elapsed(), Duration::from_millis(300));
+
+        let err = sender.send(&packet).await.expect_err("budget used up");
+        assert!(err.to_string().contains("failed 4 times in a row"), "{err}");
+        let stats = sender.stats();
+        assert_eq!((stats.packets_sent, stats.packets_failed), (0, 4));
+        assert!(stats.destinations[0].gave_up);
+
+        // Without a budget it never gives up
+        let mut sender = RtpSender::new("[::1]:5004")
+            .await
+            .expect("sender creation failed");
+        sender.set_error_policy(ErrorPolicy {
+            max_consecutive_failures: None,
+            backoff: Duration::ZERO,
+        });
+        for _ in 0..10 {
+            assert!(!sender.send(&packet).await.expect("no budget"));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_holds_configured_rate() {
+        // ---
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let limit = RateLimit::from_kbps(64);
+        let mut sender = RtpSender::with_config(
+            receiver.local_addr().unwrap().to_string(),
+            SenderNetworkConfig {
+                rate_limit: Some(limit),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("sender creation failed");
+        let metrics = MetricsContext::new("test").unwrap();
+        sender.set_metrics(&metrics);
+
+        let payload = vec![0; crate::codec::MAX_PACKET_BYTES];
+        let start = tokio::time::Instant::now();
+        for sequence in 0..100u16 {
+            let packet = RtpPacket::new(sequence, sequence as u32 * 320, 7, payload.clone());
+            assert!(sender.send(&packet).await.expect("send"));
+        }
+
+        // The burst goes out at once, the rest at the configured rate
+        let stats = sender.stats();
+        let expected = (stats.bytes_sent - limit.burst_bytes) as f64 / limit.bytes_per_sec as f64;
+        let elapsed = start.elapsed().as_secs_f64();
+        assert!(
+            (elapsed - expected).abs() < expected * 0.02,
+            "took {elapsed}s, expected {expected}s"
+        );
+
+        // Each packet is larger than the burst, so even the first waited
+        assert_eq!(stats.packets_delayed, 100);
+        // Timers round up to the millisecond, so the sleeps run a little
+        // past the waits counted
+        let throttled = stats.throttled.as_secs_f64();
+        assert!(throttled <= elapsed && throttled > elapsed * 0.99);
+        assert_eq!(metrics.packets_rate_limited_total.get(), 100);
+        assert!((metrics.rate_limit_wait_seconds_total.get() - throttled).abs() < 1e-6);
+
+        let zero = RateLimit {
+            bytes_per_sec: 0,
+            ..limit
+        };
+        let config = SenderNetworkConfig {
+            rate_limit: Some(zero),
+            ..Default::default()
+        };
+        assert!(RtpSender::with_config("127.0.0.1:5004", config)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_idle_rate_limit_adds_no_latency() {
+        // ---
+        let receiver = UdpSocket::bind("127.0.0.1:0").await.expect("bind");
+        let mut sender = RtpSender::with_config(
+            receiver.local_addr().unwrap().to_string(),
+            SenderNetworkConfig {
+                rate_limit: Some(RateLimit::from_kbps(64)),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("sender creation failed");
+
+        // 100-byte packets every 20ms are 40 kbps, under the cap
+        for sequence in 0..100u16 {
+            let packet = RtpPacket::new(sequence, sequence as u32 * 320, 7, vec![0; 88]);
+            let start = tokio::time::Instant::now();
+            assert!(sender.send(&packet).await.expect("send"));
+            assert_eq!(start.elapsed(), Duration::ZERO);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let stats = sender.stats();
+        assert_eq!(stats.packets_delayed, 0);
+        assert_eq!(stats.throttled, Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_serializes_each_packet_once_into_reused_buffers() {
+        // ---
+        let mut sockets = Vec::new();
+        for _ in 0..3 {
+            sockets.push(tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        }
+        let addrs = sockets.iter().map(|s| s.local_addr().unwrap().to_string());
+        let mut sender = RtpSender::with_destinations(addrs, SenderNetworkConfig::default())
+            .await
+            .expect("sender creation failed");
+
+        let batch = |first: u16| -> Vec<RtpPacket> {
+            (first..first + 4)
+                .map(|sequence| RtpPacket::new(sequence, 0, 7, vec![sequence as u8; 40]))
+                .collect()
+        };
+        let mut sent = Vec::new();
+        sender.send_batch(&batch(0), &mut sent).await.unwrap();
+
+        // One buffer per packet, not per destination, kept for the next
+        // batch and written without reallocating
+        assert_eq!(sender.send_buffers.len(), 4);
+        let pointers: Vec<_> = sender.send_buffers.iter().map(|b| b.as_ptr()).collect();
+        let packets = batch(4);
+        sender.send_batch(&packets[..2], &mut sent).await.unwrap();
+        assert_eq!(sent, [true, true]);
+        let reused: Vec<_> = sender.send_buffers.iter().map(|b| b.as_ptr()).collect();
+        assert_eq!(reused, pointers);
+
+        let mut buf = [0u8; 128];
+        for socket in &sockets {
+            for expected in batch(0).iter().chain(&packets[..2]) {
+                let len = socket.recv(&mut buf).await.unwrap();
+                assert_eq!(buf[..len], expected.serialize().unwrap());
+            }
+        }
+        assert_eq!(sender.stats().packets_sent, 18);
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_to_every_destination() {
+        // ---
+        let first = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let second = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addrs = [
+            first.local_addr().unwrap().to_string(),
+            "[::1]:5004".to_string(),
+            second.local_addr().unwrap().to_string(),
+        ];
+        let mut sender =
+            RtpSender::with_destinations(addrs.clone(), SenderNetworkConfig::default())
+                .await
+                .expect("sender creation failed");
+        assert!(!sender.connected, "fan-out needs an unconnected socket");
+        sender.set_error_policy(ErrorPolicy {
+            max_consecutive_failures: Some(2),
+            ..Default::default()
+        });
+        let metrics = MetricsContext::new("test").unwrap();
+        sender.set_metrics(&metrics);
+
+        // The unreachable destination is given up on; the others go on
+        for sequence in 0..5u16 {
+            let packet = RtpPacket::new(sequence, sequence as u32 * 320, 7, vec![1, 2, 3]);
+            assert!(sender.send(&packet).await.expect("send"));
+        }
+        let mut buf = [0u8; 64];
+        for socket in [&first, &second] {
+            for sequence in 0..5u16 {
+                let len = socket.recv(&mut buf).await.unwrap();
+                let packet = RtpPacket::deserialize(&buf[..len]).unwrap();
+                assert_eq!(packet.sequence, sequence);
+            }
+        }
+
+        let stats = sender.stats();
+        assert_eq!((stats.packets_sent, stats.packets_failed), (10, 3));
+        let per_destination: Vec<_> = stats
+            .destinations
+            .iter()
+            .map(|d| (d.addr.as_str(), d.packets_sent, d.packets_failed, d.gave_up))
+            .collect();
+        assert_eq!(
+            per_destination,
+            [
+                (addrs[0].as_str(), 5, 0, false),
+                (addrs[1].as_str(), 0, 3, true),
+                (addrs[2].as_str(), 5, 0, false),
+            ]
+        );
+        let sent = &metrics.destination_packets_sent_total;
+        let failed = &metrics.destination_packets_failed_total;
+        assert_eq!(sent.with_label_values(&[&addrs[2]]).get(), 5);
+        assert_eq!(failed.with_label_values(&[&addrs[1]]).get(), 3);
+
+        assert!(
+            RtpSender::with_destinations(Vec::<String>::new(), SenderNetworkConfig::default())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poll_refresh_requests() {
+        // ---
+        let receiver = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut sender = RtpSender::new(receiver.local_addr().unwrap().to_string())
+            .await
+            .expect("sender creation failed");
+        assert_eq!(sender.poll_refresh_requests(7), 0);
+
+        sender
+            .send(&RtpPacket::new(1, 320, 7, vec![1, 2, 3]))
+            .await
+            .unwrap();
+        let mut buf = [0u8; 64];
+        let (_, sender_addr) = receiver.recv_from(&mut buf).await.unwrap();
+
+        let request = |media_ssrc| {
+            DecoderRefreshRequest {
+                sender_ssrc: 0,
+                media_ssrc,
+            }
+            .serialize()
+        };
+        for datagram in [request(7), request(8), vec![0xFF; 4], request(7)] {
+            receiver.send_to(&datagram, sender_addr).await.unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        // Only the two requests for our SSRC count
+        assert_eq!(sender.poll_refresh_requests(7), 2);
+        assert_eq!(sender.poll_refresh_requests(7), 0);
+    }
+
+    #[tokio::test]
+    async fn test_listening_sender_follows_peer_only_when_allowed() {
+        // ---
+        let request = DecoderRefreshRequest {
+            sender_ssrc: 0,
+            media_ssrc: 7,
+        }
+        .serialize();
+        let hello = Keepalive { sender_ssrc: 0 }.serialize();
+        let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let stranger = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut buf = [0u8; 64];
+
+        for allow_peer_switch in [false, true] {
+            let config = SenderNetworkConfig {
+                local_addr: Some("127.0.0.1:0".parse().unwrap()),
+                allow_peer_switch,
+                ..Default::default()
+            };
+            let mut sender = RtpSender::listen(0, config).await.unwrap();
+            let addr = sender.local_addr().unwrap();
+            assert_eq!(sender.peer_addr(), None);
+
+            peer.send_to(&hello, addr).await.unwrap();
+            assert_eq!(
+                sender.wait_for_peer().await.unwrap(),
+                peer.local_addr().unwrap()
+            );
+
+            // The stranger's request counts only if it moves the stream
+            for socket in [&peer, &stranger] {
+                socket.send_to(&hello, addr).await.unwrap();
+            }
+            stranger.send_to(&request, addr).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            assert_eq!(
+                sender.poll_refresh_requests(7),
+                usize::from(allow_peer_switch)
+            );
+
+            let target = if allow_peer_switch { &stranger } else { &peer };
+            assert_eq!(sender.peer_addr(), Some(target.local_addr().unwrap()));
+            sender
+                .send(&RtpPacket::new(1, 320, 7, vec![1, 2, 3]))
+                .await
+                .unwrap();
+            let (_, from) = target.recv_from(&mut buf).await.unwrap();
+            assert_eq!(from, addr);
+        }
+
+        let mut sender = RtpSender::new(peer.local_addr().unwrap().to_string())
+            .await
+            .unwrap();
+        assert!(sender.wait_for_peer().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_nacked_packets_are_sent_again_unchanged() {
+        // ---
+        let receiver = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut sender = RtpSender::new(receiver.local_addr().unwrap().to_string())
+            .await
+            .unwrap();
+        let mut buf = [0u8; 64];
+        let mut originals = Vec::new();
+        for sequence in 0..5 {
+            sender
+                .send(&RtpPacket::new(sequence, 320, 7, vec![sequence as u8; 3]))
+                .await
+                .unwrap();
+            let (len, _) = receiver.recv_from(&mut buf).await.unwrap();
+            originals.push(buf[..len].to_vec());
+        }
+        let sender_addr = sender.local_addr().unwrap();
+
+        // Another stream's NACK, and one for packets never sent or repeated
+        for (media_ssrc, lost) in [(8, vec![0]), (7, vec![3, 1, 3, 9])] {
+            let nack = Nack {
+                sender_ssrc: 0,
+                media_ssrc,
+                lost,
+            };
+            receiver
+                .send_to(&nack.serialize(), sender_addr)
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(sender.poll_refresh_requests(7), 0);
+        assert_eq!(sender.retransmit_requested().await, 2);
+        assert_eq!(sender.retransmit_requested().await, 0);
+
+        for sequence in [3, 1] {
+            let (len, _) = receiver.recv_from(&mut buf).await.unwrap();
+            assert_eq!(buf[..len], originals[sequence]);
+        }
+        let stats = sender.stats();
+        assert_eq!(stats.packets_retransmitted, 2);
+        assert_eq!(stats.packets_sent, 5);
+
+        // Without a cache, NACKs are not acted on
+        let config = SenderNetworkConfig {
+            rtx_cache_packets: 0,
+            ..Default::default()
+        };
+        let mut sender = RtpSender::with_config(receiver.local_addr().unwrap().to_string(), config)
+            .await
+            .unwrap();
+        sender
+            .send(&RtpPacket::new(0, 0, 7, vec![0; 3]))
+            .await
+            .unwrap();
+        assert_eq!(sender.handle_nack(&[0]).await, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_keepalives_fill_a_silent_gap_at_the_interval() {
+        // ---
+        let receiver = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut sender = RtpSender::new(receiver.local_addr().unwrap().to_string())
+            .await
+            .unwrap();
+
+        // Nothing to keep open before the first packet
+        assert!(!sender.keep_alive().await);
+        sender
+            .send(&RtpPacket::new(0, 0, 7, vec![0; 3]))
+            .await
+            .unwrap();
+
+        // A minute of 20ms frame slots with nothing to send
+        let start = tokio::time::Instant::now();
+        let mut sent_at = Vec::new();
+        for _ in 0..3000 {
+            tokio::time::advance(Duration::from_millis(20)).await;
+            if sender.keep_alive().await {
+                sent_at.push(start.elapsed());
+            }
+        }
+        assert_eq!(
+            sent_at,
+            [15, 30, 45, 60].map(Duration::from_secs),
+            "one keepalive per interval of silence"
+        );
+
+        // The packet, then empty datagrams
+        let mut buf = [0u8; 64];
+        let (len, _) = receiver.recv_from(&mut buf).await.unwrap();
+        assert_eq!(
+            len,
+            RtpPacket::new(0, 0, 7, vec![0; 3])
+                .serialize()
+                .unwrap()
+                .len()
+        );
+        for _ in 0..4 {
+            let (len, _) = receiver.recv_from(&mut buf).await.unwrap();
+            assert_eq!(len, 0);
+        }
+        let stats = sender.stats();
+        assert_eq!(stats.keepalives_sent, 4);
+        assert_eq!(stats.packets_sent, 1);
+
+        // A packet restarts the interval; none are sent when turned off
+        sender
+            .send(&RtpPacket::new(1, 320, 7, vec![0; 3]))
+            .await
+            .unwrap();
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(!sender.keep_alive().await);
+
+        let config = SenderNetworkConfig {
+            keepalive_interval: None,
+            ..Default::default()
+        };
+        let mut sender = RtpSender::with_config(receiver.local_addr().unwrap().to_string(), config)
+            .await
+            .unwrap();
+        sender
+            .send(&RtpPacket::new(0, 0, 7, vec![0; 3]))
+            .await
+            .unwrap();
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert!(!sender.keep_alive().await);
     }
 }
@@ -0,0 +1,741 @@
+//! Ogg Opus file input (RFC 7845).
+//!
+//! An `.opus` file already holds Opus packets, so the sender can put them on
+//! the wire as they are instead of decoding and re-encoding them, which
+//! costs CPU and a generation of quality. [`read_ogg_opus`] extracts the
+//! packets of the first Opus stream in a file together with the number of
+//! samples each one carries, which sets the RTP timestamp step. Only what
+//! passthrough needs is parsed: page framing and checksums, the `OpusHead`
+//! header, and each packet's TOC byte. Tags are skipped.
+//!
+//! [`write_ogg_opus`] produces files the reader accepts, for test fixtures
+//! and for saving encoder output.
+
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::Duration;
+use tracing::info;
+
+use crate::codec::SAMPLE_RATE;
+
+/// Capture pattern starting every Ogg page
+pub const OGG_CAPTURE: &[u8; 4] = b"OggS";
+
+/// Rate of Ogg Opus granule positions and packet sample counts, whatever
+/// the rate of the original input
+pub const OPUS_GRANULE_RATE: u32 = 48000;
+
+/// Most audio one Opus packet can carry (120ms at 48kHz)
+pub const MAX_PACKET_SAMPLES: u32 = 5760;
+
+/// Magic starting the identification header packet
+const OPUS_HEAD: &[u8; 8] = b"OpusHead";
+
+/// Magic starting the comment header packet
+const OPUS_TAGS: &[u8; 8] = b"OpusTags";
+
+/// Vendor string written into the comment header
+const VENDOR: &str = concat!("rtp-opus-streamer ", env!("CARGO_PKG_VERSION"));
+
+/// Fixed part of an Ogg page header, before the lacing table
+const PAGE_HEADER_LEN: usize = 27;
+
+/// Offset of the page checksum within the header
+const PAGE_CRC_OFFSET: usize = 22;
+
+/// Page header flag: the first packet continues one from the previous page
+const FLAG_CONTINUED: u8 = 0x01;
+
+/// Page header flag: first page of a logical stream
+const FLAG_BOS: u8 = 0x02;
+
+/// Page header flag: last page of a logical stream
+const FLAG_EOS: u8 = 0x04;
+
+/// Most audio packets [`write_ogg_opus`] puts on one page (a second of
+/// 20ms frames)
+const PACKETS_PER_PAGE: usize = 50;
+
+/// Identification header of an Ogg Opus stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusHead {
+    // ---
+    /// Channels coded in the stream (1 or 2)
+    pub channels: u8,
+
+    /// Samples at 48kHz to discard from the start of decoded output
+    /// (the encoder lookahead)
+    pub pre_skip: u16,
+
+    /// Sample rate of the original input, for information only
+    pub input_sample_rate: u32,
+
+    /// Gain to apply on output, in Q7.8 dB
+    pub output_gain: i16,
+}
+
+impl OpusHead {
+    // ---
+    /// Describes a stream of `channels` channels encoded from input at
+    /// `input_sample_rate`, with no pre-skip or gain.
+    pub fn new(channels: u8, input_sample_rate: u32) -> Self {
+        // ---
+        Self {
+            channels,
+            pre_skip: 0,
+            input_sample_rate,
+            output_gain: 0,
+        }
+    }
+
+    /// Parses an `OpusHead` packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the packet is not an `OpusHead`, is truncated, has
+    /// an unsupported major version, or uses a multistream channel mapping
+    /// (which cannot be sent as a single RTP Opus stream).
+    fn parse(packet: &[u8]) -> Result<Self> {
+        // ---
+        if !packet.starts_with(OPUS_HEAD) {
+            anyhow::bail!("first packet is not an OpusHead header");
+        }
+        if packet.len() < 19 {
+            anyhow::bail!("OpusHead header truncated ({} bytes)", packet.len());
+        }
+        let version = packet[8];
+        if version >> 4 != 0 {
+            anyhow::bail!("unsupported Ogg Opus version {}", version);
+        }
+        let channels = packet[9];
+        if channels == 0 {
+            anyhow::bail!("OpusHead declares zero channels");
+        }
+        let mapping_family = packet[18];
+        if mapping_family != 0 {
+            anyhow::bail!(
+                "channel mapping family {} (multistream) is not supported; \
+                 only mono and stereo streams can be passed through",
+                mapping_family
+            );
+        }
+
+        Ok(Self {
+            channels,
+            pre_skip: u16::from_le_bytes([packet[10], packet[11]]),
+            input_sample_rate: u32::from_le_bytes([packet[12], packet[13], packet[14], packet[15]]),
+            output_gain: i16::from_le_bytes([packet[16], packet[17]]),
+        })
+    }
+
+    /// Serializes the header as an `OpusHead` packet (version 1, mapping
+    /// family 0).
+    fn to_packet(&self) -> Vec<u8> {
+        // ---
+        let mut packet = Vec::with_capacity(19);
+        packet.extend_from_slice(OPUS_HEAD);
+        packet.push(1);
+        packet.push(self.channels);
+        packet.extend_from_slice(&self.pre_skip.to_le_bytes());
+        packet.extend_from_slice(&self.input_sample_rate.to_le_bytes());
+        packet.extend_from_slice(&self.output_gain.to_le_bytes());
+        packet.push(0);
+        packet
+    }
+}
+
+/// One Opus packet from an Ogg Opus file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusPacket {
+    // ---
+    /// Packet as coded, ready to be an RTP payload
+    pub data: Vec<u8>,
+
+    /// Audio carried, in samples at 48kHz (see [`packet_samples`])
+    pub samples: u32,
+}
+
+/// The Opus packets of an Ogg Opus file, in stream order.
+#[derive(Debug, Clone)]
+pub struct OggOpusFile {
+    // ---
+    /// Identification header
+    pub head: OpusHead,
+
+    /// Audio packets
+    pub packets: Vec<OpusPacket>,
+}
+
+impl OggOpusFile {
+    // ---
+    /// Returns the audio carried by all packets, in samples at 48kHz.
+    pub fn total_samples(&self) -> u64 {
+        // ---
+        self.packets.iter().map(|p| p.samples as u64).sum()
+    }
+
+    /// Returns the total duration in seconds.
+    pub fn duration_secs(&self) -> f64 {
+        // ---
+        self.total_samples() as f64 / OPUS_GRANULE_RATE as f64
+    }
+
+    /// Returns the packet duration if every packet has the same one.
+    pub fn frame_duration(&self) -> Option<Duration> {
+        // ---
+        let first = self.packets.first()?.samples;
+        if self.packets.iter().any(|p| p.samples != first) {
+            return None;
+        }
+        Some(Duration::from_micros(
+            first as u64 * 1_000_000 / OPUS_GRANULE_RATE as u64,
+        ))
+    }
+}
+
+/// Converts a sample count at the Ogg Opus rate of 48kHz to RTP timestamp
+/// units at the sender clock rate ([`SAMPLE_RATE`]).
+///
+/// Opus durations are multiples of 2.5ms, so the conversion is exact.
+pub fn rtp_timestamp_units(samples: u64) -> u64 {
+    // ---
+    samples * SAMPLE_RATE as u64 / OPUS_GRANULE_RATE as u64
+}
+
+/// Returns the number of samples at 48kHz an Opus packet decodes to.
+///
+/// Read from the TOC byte (and, for code 3 packets, the frame count byte)
+/// as laid out in RFC 6716 section 3.1.
+///
+/// # Errors
+///
+/// Returns error if the packet is empty, a code 3 packet lacks its frame
+/// count, or the packet would exceed 120ms.
+pub fn packet_samples(packet: &[u8]) -> Result<u32> {
+    // ---
+    let Some(&toc) = packet.first() else {
+        anyhow::bail!("empty Opus packet");
+    };
+
+    // Frame duration by configuration: SILK 10/20/40/60ms, hybrid 10/20ms,
+    // CELT 2.5/5/10/20ms
+    let config = (toc >> 3) as usize;
+    let frame_samples = match config {
+        0..=11 => [480, 960, 1920, 2880][config % 4],
+        12..=15 => [480, 960][config % 2],
+        _ => [120, 240, 480, 960][config % 4],
+    };
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => match packet.get(1) {
+            Some(count) => (count & 0x3F) as u32,
+            None => anyhow::bail!("code 3 Opus packet is missing its frame count"),
+        },
+    };
+
+    let samples = frame_samples * frames;
+    if frames == 0 || samples > MAX_PACKET_SAMPLES {
+        anyhow::bail!(
+            "invalid Opus packet: {} frames of {} samples",
+            frames,
+            frame_samples
+        );
+    }
+    Ok(samples)
+}
+
+/// Returns true if `path` looks like an Ogg file: by extension (`.opus`,
+/// `.ogg`, `.oga`) or, failing that, by the `OggS` capture pattern at the
+/// start of the file.
+///
+/// A file that cannot be read is reported as not Ogg, leaving the error to
+/// whichever reader opens it.
+pub fn is_ogg_opus<P: AsRef<Path>>(path: P) -> bool {
+    // ---
+    let path = path.as_ref();
+    let by_extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["opus", "ogg", "oga"].contains(&ext.to_ascii_lowercase().as_str()));
+    if by_extension {
+        return true;
+    }
+
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|()| &magic == OGG_CAPTURE)
+}
+
+/// Reads the Opus packets of an Ogg Opus file for passthrough streaming.
+///
+/// # Arguments
+///
+/// * `path` - Path to the `.opus` file
+///
+/// # Errors
+///
+/// Returns error if the file cannot be read or is not a valid Ogg Opus
+/// stream (see [`parse_ogg_opus`]).
+///
+/// # Example
+///
+/// ```no_run
+/// use sender::ogg::read_ogg_opus;
+///
+/// let file = read_ogg_opus("voice.opus").unwrap();
+/// println!("{} packets, {:.2}s", file.packets.len(), file.duration_secs());
+/// ```
+pub fn read_ogg_opus<P: AsRef<Path>>(path: P) -> Result<OggOpusFile> {
+    // ---
+    let path = path.as_ref();
+    info!("Reading Ogg Opus file: {}", path.display());
+
+    let data = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let file = parse_ogg_opus(&data)
+        .with_context(|| format!("invalid Ogg Opus file {}", path.display()))?;
+
+    info!(
+        "Ogg Opus: {} channels, {}Hz input, pre-skip {}, {} packets",
+        file.head.channels,
+        file.head.input_sample_rate,
+        file.head.pre_skip,
+        file.packets.len()
+    );
+    Ok(file)
+}
+
+/// Extracts the Opus packets of the first logical stream in Ogg data.
+///
+/// Pages of other multiplexed streams are ignored, as is anything after
+/// the first stream ends (a chained file's later links).
+///
+/// # Errors
+///
+/// Returns error if a page is malformed or fails its checksum, a page is
+/// missing, the stream does not open with `OpusHead` and `OpusTags`
+/// headers, or an audio packet is invalid.
+pub fn parse_ogg_opus(data: &[u8]) -> Result<OggOpusFile> {
+    // ---
+    let mut serial = None;
+    let mut next_sequence = 0u32;
+    let mut head = None;
+    let mut tags_seen = false;
+    let mut packets = Vec::new();
+
+    // Packet continuing onto the next page
+    let mut partial = Vec::new();
+
+    let mut offset = 0;
+    while offset < data.len() {
+        let page =
+            Page::parse(&data[offset..]).with_context(|| format!("bad page at byte {offset}"))?;
+        let page_offset = offset;
+        offset += page.len;
+
+        match serial {
+            None if page.flags & FLAG_BOS != 0 => serial = Some(page.serial),
+            None => anyhow::bail!("stream does not start with a beginning-of-stream page"),
+            Some(s) if s != page.serial => continue,
+            Some(_) => {}
+        }
+
+        if page.sequence != next_sequence {
+            anyhow::bail!(
+                "page {} missing (found page {} at byte {})",
+                next_sequence,
+                page.sequence,
+                page_offset
+            );
+        }
+        next_sequence = page.sequence.wrapping_add(1);
+
+        // A continued page must follow an unfinished packet, and only then
+        let continued = page.flags & FLAG_CONTINUED != 0;
+        let unfinished = !partial.is_empty();
+        if continued != unfinished {
+            anyhow::bail!("packet continuation mismatch at page {}", page.sequence);
+        }
+
+        let mut body = page.body;
+        for &lacing in page.lacing {
+            let (segment, rest) = body.split_at(lacing as usize);
+            partial.extend_from_slice(segment);
+            body = rest;
+
+            // A lacing value under 255 ends the packet
+            if lacing < 255 {
+                let packet = std::mem::take(&mut partial);
+                if head.is_none() {
+                    head = Some(OpusHead::parse(&packet)?);
+                } else if !tags_seen {
+                    if !packet.starts_with(OPUS_TAGS) {
+                        anyhow::bail!("second packet is not an OpusTags header");
+                    }
+                    tags_seen = true;
+                } else {
+                    let samples = packet_samples(&packet)
+                        .with_context(|| format!("audio packet {}", packets.len()))?;
+                    packets.push(OpusPacket {
+                        data: packet,
+                        samples,
+                    });
+                }
+            }
+        }
+
+        if page.flags & FLAG_EOS != 0 {
+            break;
+        }
+    }
+
+    if !partial.is_empty() {
+        anyhow::bail!("stream ends in the middle of a packet");
+    }
+    let Some(head) = head else {
+        anyhow::bail!("no Opus stream found");
+    };
+    if !tags_seen {
+        anyhow::bail!("stream ends before its OpusTags header");
+    }
+    Ok(OggOpusFile { head, packets })
+}
+
+/// Writes Opus packets as an Ogg Opus file.
+///
+/// Each packet must be a complete Opus packet; granule positions are
+/// derived from their sample counts plus the header's pre-skip.
+///
+/// # Arguments
+///
+/// * `out` - Destination
+/// * `head` - Identification header
+/// * `packets` - Opus packets in stream order
+/// * `serial` - Logical stream serial number
+///
+/// # Errors
+///
+/// Returns error if a packet is invalid or writing fails.
+pub fn write_ogg_opus<W: Write>(
+    mut out: W,
+    head: &OpusHead,
+    packets: &[Vec<u8>],
+    serial: u32,
+) -> Result<()> {
+    // ---
+    let mut tags = Vec::new();
+    tags.extend_from_slice(OPUS_TAGS);
+    tags.extend_from_slice(&(VENDOR.len() as u32).to_le_bytes());
+    tags.extend_from_slice(VENDOR.as_bytes());
+    tags.extend_from_slice(&0u32.to_le_bytes());
+
+    let mut writer = PageWriter {
+        out: &mut out,
+        serial,
+        sequence: 0,
+    };
+    writer.write(FLAG_BOS, 0, &[&head.to_packet()])?;
+    let tags_flags = if packets.is_empty() { FLAG_EOS } else { 0 };
+    writer.write(tags_flags, 0, &[&tags])?;
+
+    let mut granule = head.pre_skip as u64;
+    let mut page: Vec<&[u8]> = Vec::new();
+    let mut segments = 0;
+    for (index, packet) in packets.iter().enumerate() {
+        // ---
+        granule += packet_samples(packet).with_context(|| format!("packet {index}"))? as u64;
+        page.push(packet);
+        segments += packet.len() / 255 + 1;
+
+        let last = index + 1 == packets.len();
+        let next_segments = packets.get(index + 1).map_or(0, |p| p.len() / 255 + 1);
+        if last || page.len() == PACKETS_PER_PAGE || segments + next_segments > 255 {
+            let flags = if last { FLAG_EOS } else { 0 };
+            writer.write(flags, granule, &page)?;
+            page.clear();
+            segments = 0;
+        }
+    }
+
+    out.flush().context("failed to flush Ogg output")
+}
+
+/// One Ogg page, borrowed from the input.
+struct Page<'a> {
+    // ---
+    /// Header flags (`FLAG_*`)
+    flags: u8,
+
+    /// Logical stream the page belongs to
+    serial: u32,
+
+    /// Page sequence number within its stream
+    sequence: u32,
+
+    /// Segment lengths
+    lacing: &'a [u8],
+
+    /// Concatenated segments
+    body: &'a [u8],
+
+    /// Bytes the whole page occupies
+    len: usize,
+}
+
+impl<'a> Page<'a> {
+    // ---
+    /// Parses and checksums the page at the start of `data`.
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        // ---
+        if data.len() < PAGE_HEADER_LEN || &data[..4] != OGG_CAPTURE {
+            anyhow::bail!("missing OggS capture pattern");
+        }
+        if data[4] != 0 {
+            anyhow::bail!("unsupported Ogg version {}", data[4]);
+        }
+
+        let header_len = PAGE_HEADER_LEN + data[26] as usize;
+        let lacing = data
+            .get(PAGE_HEADER_LEN..header_len)
+            .context("page header truncated")?;
+        let len = header_len + lacing.iter().map(|&l| l as usize).sum::<usize>();
+        let page = data.get(..len).context("page truncated")?;
+
+        let stored = u32::from_le_bytes(page[PAGE_CRC_OFFSET..PAGE_CRC_OFFSET + 4].try_into()?);
+        let computed = ogg_crc32(page);
+        if stored != computed {
+            anyhow::bail!(
+                "page checksum mismatch (stored {:08X}, computed {:08X})",
+                stored,
+                computed
+            );
+        }
+
+        Ok(Self {
+            flags: data[5],
+            serial: u32::from_le_bytes(data[14..18].try_into()?),
+            sequence: u32::from_le_bytes(data[18..22].try_into()?),
+            lacing,
+            body: &page[header_len..],
+            len,
+        })
+    }
+}
+
+/// Writes the pages of one logical stream.
+struct PageWriter<'w, W: Write> {
+    // ---
+    out: &'w mut W,
+    serial: u32,
+    sequence: u32,
+}
+
+impl<W: Write> PageWriter<'_, W> {
+    // ---
+    /// Writes complete `packets` as the next page.
+    fn write(&mut self, flags: u8, granule: u64, packets: &[&[u8]]) -> Result<()> {
+        // ---
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+        let body = packets.concat();
+        let page = build_page(flags, granule, self.serial, self.sequence, &lacing, &body);
+        self.sequence += 1;
+        self.out
+            .write_all(&page)
+            .context("failed to write Ogg page")
+    }
+}
+
+/// Assembles a page from its lacing table and body, with its checksum.
+fn build_page(
+    flags: u8,
+    granule: u64,
+    serial: u32,
+    sequence: u32,
+    lacing: &[u8],
+    body: &[u8],
+) -> Vec<u8> {
+    // ---
+    let mut page = Vec::with_capacity(PAGE_HEADER_LEN + lacing.len() + body.len());
+    page.extend_from_slice(OGG_CAPTURE);
+    page.push(0);
+    page.push(flags);
+    page.extend_from_slice(&granule.to_le_bytes());
+    page.extend_from_slice(&serial.to_le_bytes());
+    page.extend_from_slice(&sequence.to_le_bytes());
+    page.extend_from_slice(&[0; 4]);
+    page.push(lacing.len() as u8);
+    page.extend_from_slice(lacing);
+    page.extend_from_slice(body);
+
+    let crc = ogg_crc32(&page);
+    page[PAGE_CRC_OFFSET..PAGE_CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+    page
+}
+
+/// Computes the Ogg page checksum: CRC-32 with polynomial 0x04C11DB7, no
+/// reflection, zero initial value, and the checksum field read as zero.
+fn ogg_crc32(page: &[u8]) -> u32 {
+    // ---
+    let mut crc = 0u32;
+    for (i, &byte) in page.iter().enumerate() {
+        let byte = if (PAGE_CRC_OFFSET..PAGE_CRC_OFFSET + 4).contains(&i) {
+            0
+        } else {
+            byte
+        };
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            let mask = (crc >> 31).wrapping_neg();
+            crc = (crc << 1) ^ (0x04C1_1DB7 & mask);
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::codec::{OpusEncoderWrapper, SAMPLES_PER_FRAME};
+
+    /// Encodes `frames` 20ms frames of tone.
+    fn encoded_tone(frames: usize) -> Vec<Vec<u8>> {
+        // ---
+        let mut encoder = OpusEncoderWrapper::new().expect("encoder");
+        (0..frames)
+            .map(|frame| {
+                let pcm: Vec<i16> = (0..SAMPLES_PER_FRAME)
+                    .map(|i| {
+                        (((frame * SAMPLES_PER_FRAME + i) as f32 * 0.05).sin() * 8000.0) as i16
+                    })
+                    .collect();
+                encoder.encode(&pcm).expect("encode")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_write_then_read_round_trip() {
+        // ---
+        let packets = encoded_tone(120);
+        let head = OpusHead {
+            pre_skip: 312,
+            ..OpusHead::new(1, SAMPLE_RATE)
+        };
+        let mut data = Vec::new();
+        write_ogg_opus(&mut data, &head, &packets, 0x2012).expect("write");
+
+        let file = parse_ogg_opus(&data).expect("parse");
+        assert_eq!(file.head, head);
+        assert_eq!(file.packets.len(), packets.len());
+        for (read, written) in file.packets.iter().zip(&packets) {
+            assert_eq!(&read.data, written);
+            assert_eq!(read.samples, 960);
+        }
+        assert_eq!(file.frame_duration(), Some(Duration::from_millis(20)));
+        assert!((file.duration_secs() - 2.4).abs() < 1e-9);
+        assert_eq!(rtp_timestamp_units(960), SAMPLES_PER_FRAME as u64);
+
+        // Last page granule: pre-skip plus every sample
+        let last_page = data.len()
+            - data
+                .windows(4)
+                .rev()
+                .position(|w| w == OGG_CAPTURE)
+                .unwrap()
+            - 4;
+        let granule = u64::from_le_bytes(data[last_page + 6..last_page + 14].try_into().unwrap());
+        assert_eq!(granule, 312 + 120 * 960);
+
+        // Any flipped bit fails a page checksum
+        data[last_page + PAGE_HEADER_LEN + 10] ^= 0x01;
+        let err = parse_ogg_opus(&data).unwrap_err();
+        assert!(format!("{err:#}").contains("checksum"), "{err:#}");
+    }
+
+    #[test]
+    fn test_packet_spanning_pages_and_other_streams() {
+        // ---
+        let head = OpusHead::new(2, 48000).to_packet();
+        let mut tags = OPUS_TAGS.to_vec();
+        tags.extend_from_slice(&[0; 8]);
+
+        // A 600-byte code 0 CELT 20ms packet split 510 + 90 across pages,
+        // with a page of another stream in between
+        let mut long = vec![0xFC; 600];
+        long[0] = 31 << 3;
+        let mut data = Vec::new();
+        data.extend(build_page(FLAG_BOS, 0, 7, 0, &[19], &head));
+        data.extend(build_page(FLAG_BOS, 0, 9, 0, &[3], b"foo"));
+        data.extend(build_page(0, 0, 7, 1, &[16], &tags));
+        data.extend(build_page(0, 0, 7, 2, &[255, 255], &long[..510]));
+        data.extend(build_page(
+            FLAG_CONTINUED | FLAG_EOS,
+            960,
+            7,
+            3,
+            &[90, 1],
+            &[&long[510..], &[0x00][..]].concat(),
+        ));
+
+        let file = parse_ogg_opus(&data).expect("parse");
+        assert_eq!(file.head.channels, 2);
+        assert_eq!(file.packets.len(), 2);
+        assert_eq!(file.packets[0].data, long);
+        assert_eq!(file.packets[0].samples, 960);
+        assert_eq!(file.packets[1].samples, 480);
+        assert_eq!(file.frame_duration(), None);
+
+        // Dropping a page is detected rather than splicing packets together
+        let mut missing = Vec::new();
+        missing.extend(build_page(FLAG_BOS, 0, 7, 0, &[19], &head));
+        missing.extend(build_page(0, 0, 7, 1, &[16], &tags));
+        missing.extend(build_page(FLAG_CONTINUED, 0, 7, 3, &[90], &long[510..]));
+        let err = parse_ogg_opus(&missing).unwrap_err();
+        assert!(format!("{err:#}").contains("page 2 missing"), "{err:#}");
+    }
+
+    #[test]
+    fn test_packet_samples_from_toc() {
+        // ---
+        // Hybrid fullband 20ms, one frame
+        assert_eq!(packet_samples(&[15 << 3]).unwrap(), 960);
+        // SILK wideband 60ms, two frames: the 120ms maximum
+        assert_eq!(packet_samples(&[(11 << 3) | 1]).unwrap(), 5760);
+        // CELT 2.5ms, code 3 with three frames
+        assert_eq!(packet_samples(&[(16 << 3) | 3, 3]).unwrap(), 360);
+
+        assert!(packet_samples(&[]).is_err());
+        assert!(packet_samples(&[(16 << 3) | 3]).is_err());
+        assert!(packet_samples(&[(16 << 3) | 3, 0]).is_err());
+        assert!(packet_samples(&[(3 << 3) | 3, 3]).is_err());
+    }
+
+    #[test]
+    fn test_detects_ogg_by_extension_or_magic() {
+        // ---
+        let dir = std::env::temp_dir();
+        let disguised = dir.join(format!("ogg-magic-{}.bin", std::process::id()));
+        let mut data = Vec::new();
+        write_ogg_opus(&mut data, &OpusHead::new(1, 16000), &[], 1).expect("write");
+        std::fs::write(&disguised, &data).expect("write fixture");
+        let wav = dir.join(format!("ogg-magic-{}.wav", std::process::id()));
+        std::fs::write(&wav, b"RIFF....WAVE").expect("write fixture");
+
+        let detected = (is_ogg_opus(&disguised), is_ogg_opus(&wav));
+        std::fs::remove_file(&disguised).ok();
+        std::fs::remove_file(&wav).ok();
+        assert_eq!(detected, (true, false));
+        assert!(is_ogg_opus("missing.OPUS"));
+        assert!(!is_ogg_opus("missing.wav"));
+
+        // No audio packets is still a valid stream
+        assert!(parse_ogg_opus(&data).unwrap().packets.is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+//! Drift-free packet pacing with a configurable catch-up policy.
+//!
+//! Frame `n` is due at `start + n × interval`, so per-packet processing
+//! time never accumulates into drift. After a stall (page fault, CPU
+//! contention) several frames are overdue at once, and the
+//! [`CatchUpPolicy`] decides how they go out.
+
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// What the pacer does with frames that became overdue during a stall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CatchUpPolicy {
+    /// Send every overdue frame back-to-back, as soon as possible.
+    #[default]
+    Burst,
+
+    /// Send the overdue frames faster than real time, evenly spaced, so the
+    /// stream is back on schedule after this long.
+    Spread(Duration),
+
+    /// Drop the overdue frames and resume at the current media position.
+    /// Keeps real-time alignment at the cost of lost media.
+    Skip,
+}
+
+/// The next frame to send, as decided by [`Pacer::next_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tick {
+    // ---
+    /// Media frame index to send (its RTP timestamp is this many frames in)
+    pub frame: u64,
+
+    /// Frames dropped before this one by [`CatchUpPolicy::Skip`]
+    pub skipped: u64,
+
+    /// Whether this tick detected a new stall
+    pub catch_up_started: bool,
+
+    /// When the frame was due on the nominal schedule, ignoring catch-up
+    /// (the start of the schedule when pacing is disabled)
+    pub nominal: Instant,
+}
+
+/// Catch-up schedule while spreading overdue frames.
+#[derive(Debug, Clone, Copy)]
+struct Spread {
+    // ---
+    /// When the first overdue frame was released
+    start: Instant,
+
+    /// First overdue frame
+    first_frame: u64,
+
+    /// Spacing between frames until the schedule is caught up
+    spacing: Duration,
+}
+
+/// Paces frames on a fixed, drift-free schedule.
+#[derive(Debug)]
+pub struct Pacer {
+    // ---
+    /// Nominal spacing between frames
+    interval: Duration,
+
+    /// How overdue frames are handled
+    policy: CatchUpPolicy,
+
+    /// When frame 0 was due; set on the first tick
+    start: Option<Instant>,
+
+    /// Next media frame index
+    frame: u64,
+
+    /// Whether the previous tick was already behind schedule
+    behind: bool,
+
+    /// Active spread schedule, if catching up with [`CatchUpPolicy::Spread`]
+    spread: Option<Spread>,
+}
+
+impl Pacer {
+    // ---
+    /// Creates a pacer releasing one frame per `interval`.
+    ///
+    /// A zero interval disables pacing: every frame is released immediately.
+    pub fn new(interval: Duration, policy: CatchUpPolicy) -> Self {
+        // ---
+        Self {
+            interval,
+            policy,
+            start: None,
+            frame: 0,
+            behind: false,
+            spread: None,
+        }
+    }
+
+    /// Waits until the next frame is due and returns it.
+    ///
+    /// The first call returns immediately and starts the schedule.
+    pub async fn next_frame(&mut self) -> Tick {
+        // ---
+        let now = Instant::now();
+        let start = *self.start.get_or_insert(now);
+        let mut tick = Tick {
+            frame: self.frame,
+            skipped: 0,
+            catch_up_started: false,
+            nominal: start,
+        };
+
+        if self.interval.is_zero() {
+            self.frame += 1;
+            return tick;
+        }
+
+        // A stall is at least one whole tick missed beyond the current frame
+        let nominal = start + self.interval * self.frame as u32;
+        let missed = now.saturating_duration_since(nominal).as_nanos() / self.interval.as_nanos();
+        if missed == 0 {
+            self.behind = false;
+        } else if !self.behind && self.spread.is_none() {
+            self.behind = true;
+            tick.catch_up_started = true;
+
+            match self.policy {
+                CatchUpPolicy::Burst => {}
+                CatchUpPolicy::Skip => {
+                    warn!(
+                        "Pacing stall: skipping {} overdue frames to stay real-time",
+                        missed
+                    );
+                    tick.skipped = missed as u64;
+                    self.frame += missed as u64;
+                    tick.frame = self.frame;
+                    self.behind = false;
+                }
+                CatchUpPolicy::Spread(window) => {
+                    // Send the backlog plus the frames due during the window
+                    // within the window: spacing = W·I / (missed·I + W)
+                    let interval = self.interval.as_nanos();
+                    let window_ns = window.as_nanos();
+                    let spacing = window_ns * interval / (missed * interval + window_ns);
+                    self.spread = Some(Spread {
+                        start: now,
+                        first_frame: self.frame,
+                        spacing: Duration::from_nanos(spacing as u64),
+                    });
+                }
+            }
+        }
+
+        let nominal = start + self.interval * self.frame as u32;
+        tick.nominal = nominal;
+        let due = match self.spread {
+            Some(spread) => {
+                let spread_due =
+                    spread.start + spread.spacing * (self.frame - spread.first_frame) as u32;
+                if spread_due <= nominal {
+                    // Caught up: back on the nominal schedule
+                    self.spread = None;
+                    self.behind = false;
+                    nominal
+                } else {
+                    spread_due
+                }
+            }
+            None => nominal,
+        };
+
+        tokio::time::sleep_until(due).await;
+        self.frame += 1;
+        tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+    const STALL: Duration = Duration::from_millis(200);
+
+    /// Emits `count` frames with a 200ms stall after frame 4, returning
+    /// each frame's index and emission time relative to the start.
+    async fn run_with_stall(policy: CatchUpPolicy, count: usize) -> Vec<(Tick, Duration)> {
+        // ---
+        let mut pacer = Pacer::new(INTERVAL, policy);
+        let start = Instant::now();
+        let mut emitted = Vec::new();
+
+        for i in 0..count {
+            let tick = pacer.next_frame().await;
+            emitted.push((tick, start.elapsed()));
+            if i == 4 {
+                tokio::time::advance(STALL).await;
+            }
+        }
+        emitted
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_on_schedule_without_stall() {
+        // ---
+        let mut pacer = Pacer::new(INTERVAL, CatchUpPolicy::Burst);
+        let start = Instant::now();
+
+        for n in 0..5u64 {
+            let tick = pacer.next_frame().await;
+            assert_eq!(tick.frame, n);
+            assert!(!tick.catch_up_started);
+            assert_eq!(start.elapsed(), INTERVAL * n as u32);
+            assert_eq!(tick.nominal, start + INTERVAL * n as u32);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_sends_overdue_frames_back_to_back() {
+        // ---
+        let emitted = run_with_stall(CatchUpPolicy::Burst, 20).await;
+
+        // Frame 4 went out at 80ms, then the stall ran to 280ms:
+        // frames 5..=14 (due 100..=280ms) all go out at 280ms
+        for (n, (tick, at)) in emitted.iter().enumerate() {
+            assert_eq!(tick.frame, n as u64, "no frames dropped");
+            assert_eq!(tick.skipped, 0);
+            let expected = if (5..=14).contains(&n) {
+                ms(280)
+            } else {
+                INTERVAL * n as u32
+            };
+            assert_eq!(*at, expected, "frame {n}");
+        }
+
+        let events = emitted.iter().filter(|(t, _)| t.catch_up_started).count();
+        assert_eq!(events, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_skip_drops_overdue_frames_and_keeps_alignment() {
+        // ---
+        let emitted = run_with_stall(CatchUpPolicy::Skip, 10).await;
+
+        // Frames 5..=13 are dropped; frame 14 (due at 280ms) goes out on time
+        let (tick, at) = emitted[5];
+        assert_eq!(tick.frame, 14);
+        assert_eq!(tick.skipped, 9);
+        assert!(tick.catch_up_started);
+        assert_eq!(at, ms(280));
+
+        // Every later frame is on its nominal schedule
+        for (tick, at) in &emitted[5..] {
+            assert_eq!(*at, INTERVAL * tick.frame as u32);
+        }
+        let skipped: u64 = emitted.iter().map(|(t, _)| t.skipped).sum();
+        assert_eq!(skipped, 9);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_spread_catches_up_within_window() {
+        // ---
+        let window = ms(100);
+        let emitted = run_with_stall(CatchUpPolicy::Spread(window), 40).await;
+
+        for (n, (tick, _)) in emitted.iter().enumerate() {
+            assert_eq!(tick.frame, n as u64, "no frames dropped");
+        }
+
+        // Overdue frames are spaced out rather than sent back-to-back
+        let catch_up: Vec<Duration> = emitted[5..12].iter().map(|(_, at)| *at).collect();
+        assert_eq!(catch_up[0], ms(280));
+        for pair in catch_up.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap > Duration::ZERO && gap < INTERVAL, "gap {gap:?}");
+        }
+
+        // Back on the nominal schedule once the window has passed
+        for (tick, at) in &emitted[25..] {
+            assert_eq!(*at, INTERVAL * tick.frame as u32);
+        }
+        let first_on_time = emitted
+            .iter()
+            .skip(5)
+            .position(|(tick, at)| *at == INTERVAL * tick.frame as u32)
+            .expect("caught up");
+        assert!(emitted[5 + first_on_time].1 <= ms(280) + window + INTERVAL);
+    }
+}
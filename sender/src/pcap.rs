@@ -0,0 +1,356 @@
+//! Packet capture output for offline analysis.
+//!
+//! [`PcapWriter`] records each RTP packet of a stream to a classic libpcap
+//! file, wrapped in synthetic IPv4 and UDP headers, so Wireshark's RTP
+//! analysis (Telephony > RTP) can take it like a capture from the wire.
+//! The link type is raw IP; the UDP checksum is left out, which IPv4
+//! allows. Packets are stamped with the time the stream released them,
+//! on the pacing clock, so the capture shows the pacing the receiver
+//! would see.
+
+use anyhow::{Context, Result};
+use rtp_opus_common::RtpPacket;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::SocketAddrV4;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+use crate::sink::PacketSink;
+
+/// Magic number of a libpcap file with microsecond timestamps
+pub const PCAP_MAGIC: u32 = 0xA1B2_C3D4;
+
+/// Link type of packets starting at the IPv4 header (`LINKTYPE_RAW`)
+pub const LINKTYPE_RAW: u32 = 101;
+
+/// Size of the libpcap file header
+pub const PCAP_FILE_HEADER_LEN: usize = 24;
+
+/// Size of the header before each recorded packet
+pub const PCAP_RECORD_HEADER_LEN: usize = 16;
+
+/// IPv4 header (no options) plus UDP header wrapped around each packet
+pub const IP_UDP_HEADER_LEN: usize = 28;
+
+/// Largest packet recorded whole
+const SNAPLEN: u32 = 65_535;
+
+/// Hop limit written in the synthetic IPv4 headers
+const TTL: u8 = 64;
+
+/// Writes RTP packets to a libpcap capture.
+///
+/// Every packet is recorded as a UDP datagram from `source` to
+/// `destination`, the addresses given at creation. With SRTP on the
+/// network side, a capture paired with an [`RtpSender`](crate::RtpSender)
+/// holds the packets before protection.
+pub struct PcapWriter<W: Write> {
+    // ---
+    out: W,
+    source: SocketAddrV4,
+    destination: SocketAddrV4,
+
+    /// Wall-clock time matching `clock` at creation, to turn pacing clock
+    /// instants into capture timestamps
+    epoch: SystemTime,
+    clock: Instant,
+
+    /// IPv4 identification of the next packet
+    ip_id: u16,
+
+    packets_written: u64,
+    bytes_written: u64,
+}
+
+impl PcapWriter<BufWriter<File>> {
+    // ---
+    /// Creates the capture file at `path`, replacing any file there.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be created or its header written.
+    pub fn create(
+        path: impl AsRef<Path>,
+        source: SocketAddrV4,
+        destination: SocketAddrV4,
+    ) -> Result<Self> {
+        // ---
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("failed to create capture file {}", path.display()))?;
+        Self::new(BufWriter::new(file), source, destination)
+            .with_context(|| format!("failed to write capture file {}", path.display()))
+    }
+}
+
+impl<W: Write> PcapWriter<W> {
+    // ---
+    /// Starts a capture on `out` by writing the file header.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - Where the capture goes
+    /// * `source` - Address the packets are recorded as coming from
+    /// * `destination` - Address the packets are recorded as going to
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the header cannot be written.
+    pub fn new(mut out: W, source: SocketAddrV4, destination: SocketAddrV4) -> io::Result<Self> {
+        // ---
+        let mut header = Vec::with_capacity(PCAP_FILE_HEADER_LEN);
+        header.extend_from_slice(&PCAP_MAGIC.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes()); // Version 2.4
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&0i32.to_le_bytes()); // Timestamps in UTC
+        header.extend_from_slice(&0u32.to_le_bytes()); // Accuracy, unused
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        out.write_all(&header)?;
+
+        Ok(Self {
+            out,
+            source,
+            destination,
+            epoch: SystemTime::now(),
+            clock: Instant::now(),
+            ip_id: 0,
+            packets_written: 0,
+            bytes_written: 0,
+        })
+    }
+
+    /// Records one serialized RTP packet, released at `at`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the packet is too long for a UDP datagram or the
+    /// write fails.
+    pub fn write_packet(&mut self, rtp: &[u8], at: Instant) -> io::Result<()> {
+        // ---
+        let total_len = u16::try_from(IP_UDP_HEADER_LEN + rtp.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} byte packet does not fit in a datagram", rtp.len()),
+            )
+        })?;
+
+        let time = if at >= self.clock {
+            self.epoch + (at - self.clock)
+        } else {
+            self.epoch - (self.clock - at)
+        };
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+
+        let mut record = Vec::with_capacity(PCAP_RECORD_HEADER_LEN + total_len as usize);
+        record.extend_from_slice(&(since_epoch.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&since_epoch.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&u32::from(total_len).to_le_bytes()); // Bytes recorded
+        record.extend_from_slice(&u32::from(total_len).to_le_bytes()); // Bytes on the wire
+
+        // IPv4: version 4, 5-word header, don't fragment
+        let ip_start = record.len();
+        record.extend_from_slice(&[0x45, 0]);
+        record.extend_from_slice(&total_len.to_be_bytes());
+        record.extend_from_slice(&self.ip_id.to_be_bytes());
+        record.extend_from_slice(&[0x40, 0, TTL, 17, 0, 0]);
+        record.extend_from_slice(&self.source.ip().octets());
+        record.extend_from_slice(&self.destination.ip().octets());
+        let checksum = ipv4_checksum(&record[ip_start..]);
+        record[ip_start + 10..ip_start + 12].copy_from_slice(&checksum.to_be_bytes());
+
+        // UDP, without a checksum
+        record.extend_from_slice(&self.source.port().to_be_bytes());
+        record.extend_from_slice(&self.destination.port().to_be_bytes());
+        record.extend_from_slice(&(total_len - 20).to_be_bytes());
+        record.extend_from_slice(&[0, 0]);
+        record.extend_from_slice(rtp);
+        self.out.write_all(&record)?;
+
+        self.ip_id = self.ip_id.wrapping_add(1);
+        self.packets_written += 1;
+        self.bytes_written += rtp.len() as u64;
+        Ok(())
+    }
+
+    /// Returns how many packets have been recorded.
+    pub fn packets_written(&self) -> u64 {
+        // ---
+        self.packets_written
+    }
+
+    /// Returns the RTP bytes recorded, headers included but not the
+    /// synthetic IP and UDP headers.
+    pub fn bytes_written(&self) -> u64 {
+        // ---
+        self.bytes_written
+    }
+
+    /// Flushes the capture and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the flush fails.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        // ---
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: Write + Send> PacketSink for PcapWriter<W> {
+    async fn send_packets(
+        &mut self,
+        packets: &[RtpPacket],
+        released: &[Instant],
+        sent: &mut Vec<bool>,
+    ) -> Result<()> {
+        // ---
+        sent.clear();
+        for (packet, &at) in packets.iter().zip(released) {
+            let rtp = packet
+                .serialize()
+                .context("failed to serialize RTP packet")?;
+            self.write_packet(&rtp, at)
+                .context("failed to write to the capture")?;
+            sent.push(true);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        // ---
+        self.out.flush().context("failed to flush the capture")
+    }
+}
+
+/// Returns the Internet checksum of an IPv4 header whose checksum field
+/// is zero (RFC 791).
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    // ---
+    let sum = header
+        .chunks(2)
+        .map(|word| u32::from(u16::from_be_bytes([word[0], word[1]])))
+        .sum::<u32>();
+    let folded = (sum & 0xFFFF) + (sum >> 16);
+    !((folded & 0xFFFF) + (folded >> 16)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::{stream_audio, AudioData, OpusEncoderWrapper, StreamOptions};
+    use rtp_opus_common::MetricsContext;
+    use std::net::Ipv4Addr;
+
+    /// One packet read back from a capture.
+    struct Record {
+        // ---
+        time: Duration,
+        ip: Vec<u8>,
+    }
+
+    /// Reads back a capture written with [`PcapWriter`], checking the file
+    /// header.
+    fn read_capture(data: &[u8]) -> Vec<Record> {
+        // ---
+        let word = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+        assert_eq!(word(0), PCAP_MAGIC);
+        assert_eq!(word(16), SNAPLEN);
+        assert_eq!(word(20), LINKTYPE_RAW);
+
+        let mut records = Vec::new();
+        let mut at = PCAP_FILE_HEADER_LEN;
+        while at < data.len() {
+            let time = Duration::new(word(at) as u64, word(at + 4) * 1000);
+            let (recorded, original) = (word(at + 8) as usize, word(at + 12));
+            assert_eq!(recorded as u32, original);
+            at += PCAP_RECORD_HEADER_LEN;
+            records.push(Record {
+                time,
+                ip: data[at..at + recorded].to_vec(),
+            });
+            at += recorded;
+        }
+        assert_eq!(at, data.len());
+        records
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_capture_of_a_stream_reads_back() {
+        // ---
+        let source = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 40000);
+        let destination = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 2), 5004);
+        let mut writer = PcapWriter::new(Vec::new(), source, destination).unwrap();
+
+        let tone: Vec<i16> = (0..10 * crate::codec::SAMPLES_PER_FRAME)
+            .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+            .collect();
+        let audio = AudioData::from_samples(tone);
+        let mut encoder = OpusEncoderWrapper::new().unwrap();
+        let metrics = MetricsContext::new("test_pcap").unwrap();
+        let summary = stream_audio(
+            &audio,
+            &mut encoder,
+            &mut writer,
+            &metrics,
+            0x0BAD_CAFE,
+            &StreamOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(writer.packets_written(), 10);
+        let rtp_bytes = writer.bytes_written();
+        let records = read_capture(&writer.into_inner().unwrap());
+
+        assert_eq!(records.len(), 10);
+        let mut payload_bytes = 0;
+        for (index, record) in records.iter().enumerate() {
+            // ---
+            let ip = &record.ip;
+            assert_eq!(ip[0], 0x45);
+            assert_eq!(u16::from_be_bytes([ip[2], ip[3]]) as usize, ip.len());
+            assert_eq!(ip[9], 17);
+            assert_eq!(ipv4_checksum(&ip[..20]), 0, "header checksum");
+            assert_eq!(ip[12..16], source.ip().octets());
+            assert_eq!(ip[16..20], destination.ip().octets());
+            assert_eq!(u16::from_be_bytes([ip[22], ip[23]]), 5004);
+            assert_eq!(u16::from_be_bytes([ip[24], ip[25]]) as usize, ip.len() - 20);
+
+            let packet = RtpPacket::deserialize(&ip[IP_UDP_HEADER_LEN..]).unwrap();
+            assert_eq!(packet.sequence, index as u16);
+            assert_eq!(packet.ssrc, 0x0BAD_CAFE);
+            payload_bytes += packet.payload.len() as u64;
+        }
+        assert_eq!(payload_bytes, summary.bytes_sent);
+        assert_eq!(
+            rtp_bytes,
+            records
+                .iter()
+                .map(|r| (r.ip.len() - IP_UDP_HEADER_LEN) as u64)
+                .sum::<u64>()
+        );
+
+        // Stamped 20ms apart on the (paused) pacing clock
+        for pair in records.windows(2) {
+            assert!(pair[1].time > pair[0].time);
+            assert_eq!(pair[1].time - pair[0].time, Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_oversized_packet_is_rejected() {
+        // ---
+        let any = SocketAddrV4::new(Ipv4Addr::LOCALHOST, 5004);
+        let mut writer = PcapWriter::new(Vec::new(), any, any).unwrap();
+        let err = writer
+            .write_packet(&vec![0; 65_535], Instant::now())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(writer.packets_written(), 0);
+        assert_eq!(writer.into_inner().unwrap().len(), PCAP_FILE_HEADER_LEN);
+    }
+}
@@ -0,0 +1,449 @@
+//! Input file probing.
+//!
+//! Analyzes source audio before streaming so problems with the input (a
+//! wrong export, a muted channel, long dead air) are caught up front rather
+//! than discovered by a listener. Analysis runs on the original interleaved
+//! samples, before downmix and resampling.
+
+use std::fmt;
+
+use crate::codec::FRAME_DURATION_MS;
+
+/// Thresholds for silence analysis.
+#[derive(Debug, Clone)]
+pub struct SilenceConfig {
+    // ---
+    /// Frames with RMS level below this (dBFS) count as silent
+    pub threshold_dbfs: f64,
+
+    /// Warn when more than this percentage of frames is silent
+    pub max_silent_percent: f64,
+
+    /// Warn when a single silent run lasts at least this many seconds
+    pub max_silent_run_secs: f64,
+}
+
+impl Default for SilenceConfig {
+    fn default() -> Self {
+        // ---
+        Self {
+            threshold_dbfs: -50.0,
+            max_silent_percent: 50.0,
+            max_silent_run_secs: 2.0,
+        }
+    }
+}
+
+/// Longest stretch of consecutive silent frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilentRun {
+    // ---
+    /// Index of the first silent frame
+    pub start_frame: usize,
+
+    /// Number of consecutive silent frames
+    pub frames: usize,
+
+    /// Start position in seconds
+    pub start_secs: f64,
+
+    /// Duration in seconds
+    pub duration_secs: f64,
+}
+
+/// Silence figures for one source channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelSilence {
+    // ---
+    /// Zero-based channel index
+    pub channel: usize,
+
+    /// Percentage of frames silent on this channel
+    pub silent_percent: f64,
+
+    /// Overall RMS level of the channel in dBFS
+    pub rms_dbfs: f64,
+}
+
+/// Result of silence analysis over a whole file.
+#[derive(Debug, Clone)]
+pub struct SilenceReport {
+    // ---
+    /// Analysis frames (20ms windows at the source rate)
+    pub frames: usize,
+
+    /// Frames silent on every channel
+    pub silent_frames: usize,
+
+    /// Longest run of frames silent on every channel
+    pub longest_run: Option<SilentRun>,
+
+    /// Per-channel figures, in channel order
+    pub channels: Vec<ChannelSilence>,
+}
+
+impl SilenceReport {
+    // ---
+    /// Percentage of frames that are silent on every channel.
+    pub fn silent_percent(&self) -> f64 {
+        // ---
+        if self.frames == 0 {
+            0.0
+        } else {
+            self.silent_frames as f64 / self.frames as f64 * 100.0
+        }
+    }
+
+    /// Returns a warning for every threshold in `config` that is exceeded.
+    pub fn warnings(&self, config: &SilenceConfig) -> Vec<String> {
+        // ---
+        let mut warnings = Vec::new();
+
+        if self.silent_percent() > config.max_silent_percent {
+            warnings.push(format!(
+                "{:.1}% of frames are silent (below {} dBFS)",
+                self.silent_percent(),
+                config.threshold_dbfs
+            ));
+        }
+
+        if let Some(run) = self.longest_run {
+            if run.duration_secs >= config.max_silent_run_secs {
+                warnings.push(format!(
+                    "silent for {:.2}s starting at {:.2}s",
+                    run.duration_secs, run.start_secs
+                ));
+            }
+        }
+
+        // A channel that is silent while the file is not is likely muted;
+        // the mono downmix will be at half level.
+        if self.channels.len() > 1 && self.silent_percent() < 100.0 {
+            for ch in &self.channels {
+                if ch.silent_percent >= 100.0 {
+                    warnings.push(format!(
+                        "channel {} is silent; downmix to mono will lower the level",
+                        ch.channel
+                    ));
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+impl fmt::Display for SilenceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // ---
+        write!(
+            f,
+            "{} of {} frames silent ({:.1}%)",
+            self.silent_frames,
+            self.frames,
+            self.silent_percent()
+        )?;
+        if let Some(run) = self.longest_run {
+            write!(
+                f,
+                ", longest silent run {:.2}s at {:.2}s",
+                run.duration_secs, run.start_secs
+            )?;
+        }
+        for ch in &self.channels {
+            write!(
+                f,
+                ", ch{} {:.1}% silent ({:.1} dBFS)",
+                ch.channel, ch.silent_percent, ch.rms_dbfs
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Analyzes interleaved PCM for silence.
+///
+/// # Arguments
+///
+/// * `samples` - Interleaved samples as read from the file
+/// * `channels` - Number of interleaved channels
+/// * `sample_rate` - Source sample rate in Hz
+/// * `config` - Silence threshold
+pub fn analyze_silence(
+    samples: &[i16],
+    channels: usize,
+    sample_rate: u32,
+    config: &SilenceConfig,
+) -> SilenceReport {
+    // ---
+    let mut analyzer = SilenceAnalyzer::new(channels, sample_rate, config);
+    analyzer.push(samples);
+    analyzer.finish()
+}
+
+/// Incremental form of [`analyze_silence`] for a file read in chunks.
+///
+/// Chunks may split analysis frames anywhere (on sample frame boundaries);
+/// the report is the same as for all samples at once.
+#[derive(Debug)]
+pub(crate) struct SilenceAnalyzer {
+    // ---
+    /// Frames below this RMS level (dBFS) are silent
+    threshold_dbfs: f64,
+
+    /// Number of interleaved channels
+    channels: usize,
+
+    /// Interleaved samples per analysis frame
+    frame_len: usize,
+
+    /// Samples of an analysis frame split across chunks
+    partial: Vec<i16>,
+
+    /// Interleaved samples pushed so far
+    samples: u64,
+
+    /// Analysis frames seen
+    frames: usize,
+
+    /// Frames silent on every channel
+    silent_frames: usize,
+
+    /// Silent frames per channel
+    channel_silent: Vec<usize>,
+
+    /// Summed squared samples per channel
+    channel_energy: Vec<f64>,
+
+    /// Start frame and length of the silent run in progress
+    current_run: Option<(usize, usize)>,
+
+    /// Start frame and length of the longest silent run so far
+    longest: Option<(usize, usize)>,
+}
+
+impl SilenceAnalyzer {
+    // ---
+    /// Creates an analyzer for `channels` interleaved channels at
+    /// `sample_rate`.
+    pub(crate) fn new(channels: usize, sample_rate: u32, config: &SilenceConfig) -> Self {
+        // ---
+        let channels = channels.max(1);
+        Self {
+            threshold_dbfs: config.threshold_dbfs,
+            channels,
+            frame_len: (sample_rate as usize * FRAME_DURATION_MS / 1000).max(1) * channels,
+            partial: Vec::new(),
+            samples: 0,
+            frames: 0,
+            silent_frames: 0,
+            channel_silent: vec![0; channels],
+            channel_energy: vec![0.0; channels],
+            current_run: None,
+            longest: None,
+        }
+    }
+
+    /// Analyzes the next interleaved samples.
+    pub(crate) fn push(&mut self, mut samples: &[i16]) {
+        // ---
+        self.samples += samples.len() as u64;
+
+        // Complete a frame left over from the previous chunk first
+        if !self.partial.is_empty() {
+            let needed = (self.frame_len - self.partial.len()).min(samples.len());
+            self.partial.extend_from_slice(&samples[..needed]);
+            samples = &samples[needed..];
+            if self.partial.len() < self.frame_len {
+                return;
+            }
+            let frame = std::mem::take(&mut self.partial);
+            self.analyze_frame(&frame);
+        }
+
+        let mut frames = samples.chunks_exact(self.frame_len);
+        for frame in &mut frames {
+            self.analyze_frame(frame);
+        }
+        self.partial.extend_from_slice(frames.remainder());
+    }
+
+    /// Analyzes a final partial frame, if any, and returns the report.
+    pub(crate) fn finish(mut self) -> SilenceReport {
+        // ---
+        if !self.partial.is_empty() {
+            let frame = std::mem::take(&mut self.partial);
+            self.analyze_frame(&frame);
+        }
+
+        let frame_secs = FRAME_DURATION_MS as f64 / 1000.0;
+        let frames = self.frames;
+        let samples_per_channel = (self.samples / self.channels as u64) as usize;
+        SilenceReport {
+            frames,
+            silent_frames: self.silent_frames,
+            longest_run: self.longest.map(|(start, len)| SilentRun {
+                start_frame: start,
+                frames: len,
+                start_secs: start as f64 * frame_secs,
+                duration_secs: len as f64 * frame_secs,
+            }),
+            channels: (0..self.channels)
+                .map(|ch| ChannelSilence {
+                    channel: ch,
+                    silent_percent: if frames == 0 {
+                        0.0
+                    } else {
+                        self.channel_silent[ch] as f64 / frames as f64 * 100.0
+                    },
+                    rms_dbfs: rms_dbfs(self.channel_energy[ch], samples_per_channel.max(1)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds one analysis frame (possibly short, at the end) to the totals.
+    fn analyze_frame(&mut self, frame: &[i16]) {
+        // ---
+        let channels = self.channels;
+        let mut all_silent = true;
+        for (ch, energy_total) in self.channel_energy.iter_mut().enumerate() {
+            let energy: f64 = frame
+                .iter()
+                .skip(ch)
+                .step_by(channels)
+                .map(|&s| (s as f64) * (s as f64))
+                .sum();
+            *energy_total += energy;
+
+            let count = frame.len().div_ceil(channels).max(1);
+            if rms_dbfs(energy, count) < self.threshold_dbfs {
+                self.channel_silent[ch] += 1;
+            } else {
+                all_silent = false;
+            }
+        }
+
+        if all_silent {
+            self.silent_frames += 1;
+            let run = self.current_run.get_or_insert((self.frames, 0));
+            run.1 += 1;
+            if self.longest.is_none_or(|(_, len)| run.1 > len) {
+                self.longest = Some(*run);
+            }
+        } else {
+            self.current_run = None;
+        }
+        self.frames += 1;
+    }
+}
+
+/// Converts summed squared samples to an RMS level in dBFS.
+fn rms_dbfs(energy: f64, count: usize) -> f64 {
+    // ---
+    let rms = (energy / count as f64).sqrt();
+    20.0 * (rms / i16::MAX as f64).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn tone(len: usize) -> impl Iterator<Item = i16> {
+        // ---
+        (0..len).map(|i| ((i as f32 * 0.2).sin() * 8000.0) as i16)
+    }
+
+    #[test]
+    fn test_tone_is_not_silent() {
+        // ---
+        let samples: Vec<i16> = tone(RATE as usize * 2).collect();
+        let config = SilenceConfig::default();
+        let report = analyze_silence(&samples, 1, RATE, &config);
+
+        assert_eq!(report.frames, 100);
+        assert_eq!(report.silent_frames, 0);
+        assert!(report.longest_run.is_none());
+        assert!(report.warnings(&config).is_empty());
+    }
+
+    #[test]
+    fn test_three_second_gap_detected() {
+        // ---
+        // 1s tone, 3s silence, 1s tone
+        let second = RATE as usize;
+        let mut samples: Vec<i16> = tone(second).collect();
+        samples.extend(std::iter::repeat_n(0, 3 * second));
+        samples.extend(tone(second));
+
+        let config = SilenceConfig::default();
+        let report = analyze_silence(&samples, 1, RATE, &config);
+
+        let run = report.longest_run.expect("silent run");
+        assert_eq!(run.start_frame, 50);
+        assert_eq!(run.frames, 150);
+        assert!((run.start_secs - 1.0).abs() < 1e-9);
+        assert!((run.duration_secs - 3.0).abs() < 1e-9);
+        assert!((report.silent_percent() - 60.0).abs() < 1e-9);
+
+        let warnings = report.warnings(&config);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(warnings[1].contains("3.00s starting at 1.00s"));
+    }
+
+    #[test]
+    fn test_silent_right_channel_detected() {
+        // ---
+        let samples: Vec<i16> = tone(RATE as usize).flat_map(|left| [left, 0]).collect();
+
+        let config = SilenceConfig::default();
+        let report = analyze_silence(&samples, 2, RATE, &config);
+
+        assert_eq!(report.silent_frames, 0);
+        assert_eq!(report.channels[0].silent_percent, 0.0);
+        assert_eq!(report.channels[1].silent_percent, 100.0);
+
+        let warnings = report.warnings(&config);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("channel 1 is silent"));
+    }
+
+    #[test]
+    fn test_fully_silent_file() {
+        // ---
+        let samples = vec![0i16; RATE as usize * 2 * 2];
+        let config = SilenceConfig::default();
+        let report = analyze_silence(&samples, 2, RATE, &config);
+
+        assert_eq!(report.silent_percent(), 100.0);
+        // Whole-file silence is reported once, not per channel
+        let warnings = report.warnings(&config);
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+    }
+
+    #[test]
+    fn test_chunked_analysis_matches_whole() {
+        // ---
+        // Gap and muted channel, pushed in chunks that split frames
+        let mut samples: Vec<i16> = tone(RATE as usize).flat_map(|s| [s, 0]).collect();
+        samples.extend(std::iter::repeat_n(0, 2 * RATE as usize + 6));
+        samples.extend(tone(RATE as usize / 2).flat_map(|s| [s, s]));
+
+        let config = SilenceConfig::default();
+        let whole = analyze_silence(&samples, 2, RATE, &config);
+
+        let mut analyzer = SilenceAnalyzer::new(2, RATE, &config);
+        for chunk in samples.chunks(2 * 173) {
+            analyzer.push(chunk);
+        }
+        let chunked = analyzer.finish();
+
+        assert_eq!(chunked.frames, whole.frames);
+        assert_eq!(chunked.silent_frames, whole.silent_frames);
+        assert_eq!(chunked.longest_run, whole.longest_run);
+        assert_eq!(chunked.channels, whole.channels);
+    }
+}
@@ -0,0 +1,174 @@
+//! Token-bucket bandwidth cap for [`RtpSender`](crate::RtpSender).
+//!
+//! The bucket fills at the configured rate up to its burst size, and each
+//! packet takes its size in bytes out of it. A packet that finds enough
+//! tokens goes out at once, so a cap the stream stays under adds no
+//! latency; otherwise the sender waits until the bucket has refilled.
+
+use anyhow::Result;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Burst used by `--max-kbps` unless configured: one full-size datagram
+pub const DEFAULT_BURST_BYTES: u64 = 1500;
+
+/// Bandwidth cap settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    // ---
+    /// Long-run rate in bytes per second, counting RTP headers and
+    /// payloads (not UDP/IP headers)
+    pub bytes_per_sec: u64,
+
+    /// Bytes that may go out back to back after an idle spell
+    pub burst_bytes: u64,
+}
+
+impl RateLimit {
+    // ---
+    /// Cap of `kbps` kilobits per second with the default burst.
+    pub fn from_kbps(kbps: u64) -> Self {
+        // ---
+        Self {
+            bytes_per_sec: kbps * 1000 / 8,
+            burst_bytes: DEFAULT_BURST_BYTES,
+        }
+    }
+
+    /// Checks that the rate and burst are nonzero.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if either is zero.
+    pub fn validate(&self) -> Result<()> {
+        // ---
+        if self.bytes_per_sec == 0 {
+            anyhow::bail!("bandwidth cap must be above 0 bytes/s");
+        }
+        if self.burst_bytes == 0 {
+            anyhow::bail!("bandwidth cap burst must be above 0 bytes");
+        }
+        Ok(())
+    }
+}
+
+/// Token bucket enforcing a [`RateLimit`].
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    // ---
+    /// Rate and burst
+    limit: RateLimit,
+
+    /// Bytes available at `updated`
+    tokens: f64,
+
+    /// When `tokens` was last brought up to date; in the future while a
+    /// take is waiting for the bucket to refill
+    updated: Instant,
+}
+
+impl TokenBucket {
+    // ---
+    /// Creates a full bucket for `limit`.
+    pub fn new(limit: RateLimit, now: Instant) -> Self {
+        // ---
+        Self {
+            limit,
+            tokens: limit.burst_bytes as f64,
+            updated: now,
+        }
+    }
+
+    /// Returns the rate and burst.
+    pub fn limit(&self) -> RateLimit {
+        // ---
+        self.limit
+    }
+
+    /// Takes `bytes` from the bucket and returns how long to wait before
+    /// sending them: zero if the bucket held enough.
+    ///
+    /// Takes queue behind each other: one made while an earlier one is
+    /// still waiting counts from the end of that wait. A take larger than
+    /// the burst is allowed and waits for the difference, so the long-run
+    /// rate holds either way.
+    pub fn take(&mut self, bytes: usize, now: Instant) -> Duration {
+        // ---
+        let rate = self.limit.bytes_per_sec as f64;
+        let start = now.max(self.updated);
+        let refilled = start.duration_since(self.updated).as_secs_f64() * rate;
+        self.tokens = (self.tokens + refilled).min(self.limit.burst_bytes as f64);
+
+        let bytes = bytes as f64;
+        let refill = if self.tokens >= bytes {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((bytes - self.tokens) / rate)
+        };
+
+        // Tokens refilled during the wait are paid out with this take
+        self.tokens = (self.tokens - bytes).max(0.0);
+        self.updated = start + refill;
+        self.updated - now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_full_bucket_sends_at_once() {
+        // ---
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(
+            RateLimit {
+                bytes_per_sec: 1000,
+                burst_bytes: 300,
+            },
+            now,
+        );
+        assert_eq!(bucket.take(100, now), Duration::ZERO);
+        assert_eq!(bucket.take(200, now), Duration::ZERO);
+
+        // Empty: the next 100 bytes take 100ms to refill
+        assert_eq!(bucket.take(100, now), Duration::from_millis(100));
+
+        // After an idle second the bucket is full again, but no fuller
+        let later = now + Duration::from_secs(1);
+        assert_eq!(bucket.take(300, later), Duration::ZERO);
+        assert!(bucket.take(1, later) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_take_larger_than_burst() {
+        // ---
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(
+            RateLimit {
+                bytes_per_sec: 1000,
+                burst_bytes: 100,
+            },
+            now,
+        );
+
+        // 500 bytes against 100 in the bucket: wait for the other 400
+        assert_eq!(bucket.take(500, now), Duration::from_millis(400));
+        let sent = now + Duration::from_millis(400);
+        assert_eq!(bucket.take(100, sent), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_rate_limit_validation() {
+        // ---
+        assert_eq!(RateLimit::from_kbps(40).bytes_per_sec, 5000);
+        assert!(RateLimit::from_kbps(40).validate().is_ok());
+        assert!(RateLimit::from_kbps(0).validate().is_err());
+        let no_burst = RateLimit {
+            burst_bytes: 0,
+            ..RateLimit::from_kbps(40)
+        };
+        assert!(no_burst.validate().is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! Destination name resolution for [`RtpSender`](crate::RtpSender).
+//!
+//! A destination given by host name is resolved once when the sender is
+//! created. With a refresh interval, a background task resolves it again
+//! periodically, and the sender switches to a changed address between
+//! packets; a lookup that fails keeps the last good address.
+
+use anyhow::{Context, Result};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+/// Looks up the socket addresses a destination name stands for.
+///
+/// Implemented by [`SystemResolver`]; tests substitute their own.
+pub trait Resolver: Send + Sync + 'static {
+    /// Returns the addresses `addr` ("host:port") resolves to, in the
+    /// resolver's order of preference. May block; it is called off the
+    /// async runtime's worker threads.
+    fn lookup(&self, addr: &str) -> std::io::Result<Vec<SocketAddr>>;
+}
+
+/// Resolves names with the operating system's resolver (`/etc/hosts`,
+/// DNS).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn lookup(&self, addr: &str) -> std::io::Result<Vec<SocketAddr>> {
+        // ---
+        Ok(addr.to_socket_addrs()?.collect())
+    }
+}
+
+/// Picks the first of `resolved` in the socket's family (IPv4 when
+/// `ipv4`), or the first of any family if none match.
+///
+/// A socket cannot send to the other family, but falling back keeps the
+/// mismatch visible as send failures rather than a startup error.
+pub fn pick_address(resolved: &[SocketAddr], ipv4: bool) -> Option<SocketAddr> {
+    // ---
+    resolved
+        .iter()
+        .find(|target| target.is_ipv4() == ipv4)
+        .or(resolved.first())
+        .copied()
+}
+
+/// Resolves `addr` with `resolver`, preferring an address of the socket's
+/// family (see [`pick_address`]).
+///
+/// # Errors
+///
+/// Returns error if the lookup fails or finds no addresses.
+pub async fn resolve(resolver: &Arc<dyn Resolver>, addr: &str, ipv4: bool) -> Result<SocketAddr> {
+    // ---
+    let lookup = {
+        let resolver = resolver.clone();
+        let addr = addr.to_string();
+        tokio::task::spawn_blocking(move || resolver.lookup(&addr))
+    };
+    let resolved = lookup
+        .await
+        .context("resolver task failed")?
+        .with_context(|| format!("failed to resolve {}", addr))?;
+    pick_address(&resolved, ipv4).with_context(|| format!("{} resolved to no addresses", addr))
+}
+
+/// Resolves `addr` again every `interval` in a background task, and
+/// publishes each change on the returned channel, starting from `current`.
+///
+/// A failed lookup is logged and leaves the channel at the last good
+/// address. The task ends once the receiver is dropped.
+pub(crate) fn spawn_refresh(
+    resolver: Arc<dyn Resolver>,
+    addr: String,
+    ipv4: bool,
+    interval: Duration,
+    current: SocketAddr,
+) -> watch::Receiver<SocketAddr> {
+    // ---
+    let (updates, receiver) = watch::channel(current);
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now() + interval;
+        let mut ticker = tokio::time::interval_at(start, interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                () = updates.closed() => break,
+            }
+            let last = *updates.borrow();
+            match resolve(&resolver, &addr, ipv4).await {
+                Ok(target) if target != last => {
+                    info!("{} now resolves to {} (was {})", addr, target, last);
+                    if updates.send(target).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => debug!("{} still resolves to {}", addr, last),
+                Err(e) => warn!("Keeping {} for {}: {:#}", last, addr, e),
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers every lookup with whatever `answer` holds at the time.
+    struct MockResolver {
+        // ---
+        answer: Mutex<std::io::Result<Vec<SocketAddr>>>,
+    }
+
+    impl MockResolver {
+        // ---
+        fn set(&self, answer: std::io::Result<Vec<SocketAddr>>) {
+            // ---
+            *self.answer.lock().unwrap() = answer;
+        }
+    }
+
+    impl Resolver for MockResolver {
+        fn lookup(&self, _addr: &str) -> std::io::Result<Vec<SocketAddr>> {
+            // ---
+            match &*self.answer.lock().unwrap() {
+                Ok(addrs) => Ok(addrs.clone()),
+                Err(e) => Err(std::io::Error::new(e.kind(), e.to_string())),
+            }
+        }
+    }
+
+    fn addr(s: &str) -> SocketAddr {
+        // ---
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_pick_address_prefers_socket_family() {
+        // ---
+        let both = [addr("[::1]:5004"), addr("127.0.0.1:5004")];
+        assert_eq!(pick_address(&both, true), Some(addr("127.0.0.1:5004")));
+        assert_eq!(pick_address(&both, false), Some(addr("[::1]:5004")));
+
+        // The other family only if nothing matches
+        assert_eq!(
+            pick_address(&[addr("[::1]:5004")], true),
+            Some(addr("[::1]:5004"))
+        );
+        assert_eq!(pick_address(&[], true), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_localhost() {
+        // ---
+        let resolver: Arc<dyn Resolver> = Arc::new(SystemResolver);
+        let target = resolve(&resolver, "localhost:5004", true).await.unwrap();
+        assert_eq!(target, addr("127.0.0.1:5004"));
+
+        assert!(resolve(&resolver, "localhost", true).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_refresh_follows_changes_and_keeps_last_good() {
+        // ---
+        let mock = Arc::new(MockResolver {
+            answer: Mutex::new(Ok(vec![addr("10.0.0.1:5004")])),
+        });
+        let resolver: Arc<dyn Resolver> = mock.clone();
+        let interval = Duration::from_secs(60);
+        let mut updates = spawn_refresh(
+            resolver,
+            "media.example:5004".to_string(),
+            true,
+            interval,
+            addr("10.0.0.1:5004"),
+        );
+
+        // Nothing changes before the first refresh
+        mock.set(Ok(vec![addr("[::1]:5004"), addr("10.0.0.2:5004")]));
+        tokio::time::sleep(interval / 2).await;
+        assert!(!updates.has_changed().unwrap());
+
+        // The refresh picks up the new IPv4 address
+        tokio::time::timeout(interval, updates.changed())
+            .await
+            .expect("refreshed in time")
+            .unwrap();
+        assert_eq!(*updates.borrow_and_update(), addr("10.0.0.2:5004"));
+
+        // A failed lookup keeps it
+        mock.set(Err(std::io::ErrorKind::TimedOut.into()));
+        tokio::time::sleep(interval * 3).await;
+        assert!(!updates.has_changed().unwrap());
+        assert_eq!(*updates.borrow(), addr("10.0.0.2:5004"));
+
+        // And lookups resume once the resolver recovers
+        mock.set(Ok(vec![addr("10.0.0.3:5004")]));
+        tokio::time::timeout(interval * 2, updates.changed())
+            .await
+            .expect("refreshed in time")
+            .unwrap();
+        assert_eq!(*updates.borrow(), addr("10.0.0.3:5004"));
+    }
+}
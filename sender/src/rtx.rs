@@ -0,0 +1,100 @@
+//! Retransmission cache for [`RtpSender`](crate::RtpSender).
+//!
+//! Keeps the datagrams of the last packets sent, so a receiver's Generic
+//! NACK (RFC 4585 §6.2.1) can be answered by sending them again. Packets
+//! are retransmitted as they first went out, same SSRC and sequence
+//! number, rather than wrapped in an RFC 4588 RTX stream: the receiver's
+//! jitter buffer slots a late copy into its gap, and there is no second
+//! payload type or SSRC to negotiate.
+
+/// Packets kept unless configured: 2.56 s of 20 ms frames, well past any
+/// jitter buffer depth a NACK could still help
+pub const DEFAULT_RTX_CACHE_PACKETS: usize = 128;
+
+/// Most packets a cache holds (half the sequence number space)
+pub const MAX_RTX_CACHE_PACKETS: usize = 32768;
+
+/// Ring buffer of sent datagrams, indexed by sequence number.
+///
+/// Holds the most recent packets; each new one overwrites the packet
+/// `capacity` sequence numbers before it. The capacity is a power of two
+/// dividing the sequence space, so a lookup never finds a packet from an
+/// earlier wrap of the sequence numbers.
+#[derive(Debug, Clone)]
+pub struct RtxCache {
+    // ---
+    /// Sequence number and datagram in each slot
+    slots: Vec<Option<(u16, Vec<u8>)>>,
+}
+
+impl RtxCache {
+    // ---
+    /// Creates an empty cache for at least `packets` packets, rounded up
+    /// to a power of two and capped at [`MAX_RTX_CACHE_PACKETS`].
+    pub fn new(packets: usize) -> Self {
+        // ---
+        let capacity = packets.clamp(1, MAX_RTX_CACHE_PACKETS).next_power_of_two();
+        Self {
+            slots: vec![None; capacity],
+        }
+    }
+
+    /// Returns how many packets the cache holds at most.
+    pub fn capacity(&self) -> usize {
+        // ---
+        self.slots.len()
+    }
+
+    /// Keeps a copy of `datagram`, the packet with `sequence` as it went
+    /// out, in the buffer of the packet it replaces.
+    pub fn insert(&mut self, sequence: u16, datagram: &[u8]) {
+        // ---
+        let slot = self.slot(sequence);
+        let (kept, data) = self.slots[slot].get_or_insert_with(Default::default);
+        *kept = sequence;
+        data.clear();
+        data.extend_from_slice(datagram);
+    }
+
+    /// Returns the datagram of packet `sequence`, if it is still kept.
+    pub fn get(&self, sequence: u16) -> Option<&[u8]> {
+        // ---
+        match &self.slots[self.slot(sequence)] {
+            Some((kept, datagram)) if *kept == sequence => Some(datagram),
+            _ => None,
+        }
+    }
+
+    fn slot(&self, sequence: u16) -> usize {
+        // ---
+        sequence as usize & (self.slots.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+
+    #[test]
+    fn test_keeps_the_latest_packets_across_wraparound() {
+        // ---
+        let mut cache = RtxCache::new(100);
+        assert_eq!(cache.capacity(), 128);
+
+        for sequence in (65_500..=u16::MAX).chain(0..100) {
+            cache.insert(sequence, &sequence.to_be_bytes());
+        }
+        assert_eq!(cache.get(99), Some(&99u16.to_be_bytes()[..]));
+        assert_eq!(cache.get(65_535), Some(&u16::MAX.to_be_bytes()[..]));
+        assert!(cache.get(65_500).is_none(), "overwritten by 65500 + 128");
+        assert!(cache.get(100).is_none(), "not sent yet");
+    }
+
+    #[test]
+    fn test_capacity_is_bounded() {
+        // ---
+        assert_eq!(RtxCache::new(0).capacity(), 1);
+        assert_eq!(RtxCache::new(usize::MAX).capacity(), MAX_RTX_CACHE_PACKETS);
+    }
+}
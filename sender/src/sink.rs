@@ -0,0 +1,187 @@
+//! Where [`stream_frames`](crate::stream_frames) puts its packets.
+//!
+//! A [`PacketSink`] takes the packets of a stream in order, a batch at a
+//! time. [`RtpSender`] transmits them; [`PcapWriter`](crate::PcapWriter)
+//! records them to a capture file instead. A pair `(A, B)` does both: the
+//! packets go to `A`, and `B` gets a copy of the ones `A` sent, e.g. to
+//! capture a live stream.
+
+use anyhow::Result;
+use rtp_opus_common::{AnomalyLogger, MetricsContext, RtpPacket};
+use std::future::Future;
+use tokio::time::Instant;
+
+use crate::network::RtpSender;
+use crate::StreamOptions;
+
+/// Takes the RTP packets a stream produces.
+pub trait PacketSink {
+    /// Prepares for a stream with `options`, reporting into `metrics`.
+    /// Called once before the first packet; sinks without settings keep
+    /// the default of doing nothing.
+    fn start(&mut self, options: &StreamOptions, metrics: &MetricsContext) {
+        // ---
+        let _ = (options, metrics);
+    }
+
+    /// Hands `packets` on, in order. `released[i]` is when the stream
+    /// released `packets[i]`, on the pacing clock.
+    ///
+    /// `sent` is cleared, then set to whether each packet went out; on an
+    /// error, packets after the last one tried have no entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the packets cannot be handed on and the stream
+    /// should stop.
+    fn send_packets(
+        &mut self,
+        packets: &[RtpPacket],
+        released: &[Instant],
+        sent: &mut Vec<bool>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Returns the number of decoder refresh requests for `ssrc` received
+    /// since the last call. Never waits; sinks with no receiver to hear
+    /// from keep the default of 0.
+    fn poll_refresh_requests(&mut self, ssrc: u32) -> usize {
+        // ---
+        let _ = ssrc;
+        0
+    }
+
+    /// Keeps the path to the receiver open while no packets are being
+    /// handed on, e.g. through DTX suppression or a loop gap. Called once
+    /// per frame slot; returns whether anything was sent. Sinks with no
+    /// network path keep the default of doing nothing.
+    fn keep_alive(&mut self) -> impl Future<Output = bool> + Send {
+        // ---
+        async { false }
+    }
+
+    /// Tells the receiver what has been sent so far, when a report is
+    /// due. Called once per frame slot; returns whether anything was
+    /// sent. Sinks with no receiver keep the default of doing nothing.
+    fn send_sender_report(&mut self) -> impl Future<Output = bool> + Send {
+        // ---
+        async { false }
+    }
+
+    /// Completes the stream once every packet has been handed on, e.g. by
+    /// flushing buffered output.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the output cannot be completed.
+    fn finish(&mut self) -> Result<()> {
+        // ---
+        Ok(())
+    }
+}
+
+impl PacketSink for RtpSender {
+    fn start(&mut self, options: &StreamOptions, metrics: &MetricsContext) {
+        // ---
+        self.set_anomaly_logger(
+            AnomalyLogger::new(options.anomaly_log.clone()).with_metrics(metrics),
+        );
+        self.set_error_policy(options.send_errors.clone());
+        self.set_metrics(metrics);
+    }
+
+    /// Retransmits any packets the receiver NACKed, then sends `packets`.
+    async fn send_packets(
+        &mut self,
+        packets: &[RtpPacket],
+        _released: &[Instant],
+        sent: &mut Vec<bool>,
+    ) -> Result<()> {
+        // ---
+        self.retransmit_requested().await;
+        self.send_batch(packets, sent).await
+    }
+
+    fn poll_refresh_requests(&mut self, ssrc: u32) -> usize {
+        // ---
+        RtpSender::poll_refresh_requests(self, ssrc)
+    }
+
+    async fn keep_alive(&mut self) -> bool {
+        // ---
+        RtpSender::keep_alive(self).await
+    }
+
+    async fn send_sender_report(&mut self) -> bool {
+        // ---
+        RtpSender::send_sender_report(self).await
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        // ---
+        self.flush_anomalies();
+        Ok(())
+    }
+}
+
+impl<A, B> PacketSink for (A, B)
+where
+    A: PacketSink + Send,
+    B: PacketSink + Send,
+{
+    fn start(&mut self, options: &StreamOptions, metrics: &MetricsContext) {
+        // ---
+        self.0.start(options, metrics);
+        self.1.start(options, metrics);
+    }
+
+    /// Sends `packets` to the first sink, whose results are the pair's,
+    /// then hands the ones it sent to the second. A failure of the second
+    /// stops the stream too.
+    async fn send_packets(
+        &mut self,
+        packets: &[RtpPacket],
+        released: &[Instant],
+        sent: &mut Vec<bool>,
+    ) -> Result<()> {
+        // ---
+        let result = self.0.send_packets(packets, released, sent).await;
+        let (copies, copy_released): (Vec<RtpPacket>, Vec<Instant>) = packets
+            .iter()
+            .zip(released)
+            .zip(sent.iter())
+            .filter(|(_, &sent)| sent)
+            .map(|((packet, &at), _)| (packet.clone(), at))
+            .unzip();
+        if !copies.is_empty() {
+            let mut copied = Vec::with_capacity(copies.len());
+            self.1
+                .send_packets(&copies, &copy_released, &mut copied)
+                .await?;
+        }
+        result
+    }
+
+    fn poll_refresh_requests(&mut self, ssrc: u32) -> usize {
+        // ---
+        self.0.poll_refresh_requests(ssrc) + self.1.poll_refresh_requests(ssrc)
+    }
+
+    /// Keeps the first sink's path open; a keepalive is not a packet, so
+    /// the second gets no copy.
+    async fn keep_alive(&mut self) -> bool {
+        // ---
+        self.0.keep_alive().await
+    }
+
+    /// Reports on what the first sink sent; the second gets no copy.
+    async fn send_sender_report(&mut self) -> bool {
+        // ---
+        self.0.send_sender_report().await
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        // ---
+        let first = self.0.finish();
+        self.1.finish().and(first)
+    }
+}
@@ -0,0 +1,822 @@
+//! Where [`stream_frames`](crate::stream_frames) gets its PCM.
+//!
+//! A [`FrameSource`] hands out 16kHz mono frames by media position, one at a
+//! time as the pacer calls for them. [`PcmFrames`] serves preloaded samples
+//! (a decoded file) and can loop; [`PlaylistFrames`] serves several decoded
+//! files back to back; [`PcmStream`] converts interleaved s16le
+//! PCM from any async reader, such as stdin, as it arrives. Nothing is read
+//! ahead of the frame being sent, so a producer writing into a pipe faster
+//! than real time blocks on the pipe instead of filling sender memory.
+
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{info, warn};
+
+use crate::audio::{AudioData, ChannelMap, Gain, ResampleQuality, Resampler};
+use crate::codec::SAMPLE_RATE;
+
+/// Bytes read from a [`PcmStream`] reader at a time
+const READ_CHUNK_BYTES: usize = 4096;
+
+/// Highest input rate [`PcmStream`] accepts
+pub const MAX_STREAM_RATE: u32 = 192_000;
+
+/// Most interleaved channels [`PcmStream`] accepts
+pub const MAX_STREAM_CHANNELS: u16 = 8;
+
+/// What happens to input left over after the last complete frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TailPolicy {
+    /// Drop the partial frame; only complete frames are sent.
+    #[default]
+    Discard,
+
+    /// Send the partial frame padded with silence to a full frame, so no
+    /// input is lost. When looping, the padding is part of every pass.
+    PadWithSilence,
+}
+
+/// How [`stream_frames`](crate::stream_frames) handles the silent frames a
+/// looping source inserts between passes (see [`FrameSource::in_gap`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GapPolicy {
+    /// Encode and send the gap as frames of silence; sequence numbers and
+    /// timestamps stay contiguous.
+    #[default]
+    SendSilence,
+
+    /// Send nothing during the gap. The timestamp jumps by the gap, and
+    /// the first packet after it carries the marker bit as the start of a
+    /// talkspurt.
+    Skip,
+}
+
+/// Supplies 16kHz mono PCM frames to the streaming loop.
+pub trait FrameSource {
+    /// Fills `frame` with the frame at media position `index` (counted in
+    /// frames of `frame.len()` samples).
+    ///
+    /// Indices only increase but may jump: frames the pacer skipped are
+    /// never asked for, and a source that cannot seek reads and discards
+    /// them. Returns `Ok(false)` once there is no frame at `index`, which
+    /// ends the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if reading the underlying input fails.
+    fn read_frame(
+        &mut self,
+        index: u64,
+        frame: &mut [i16],
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Returns true if the frame last read is silence inserted between
+    /// loop passes rather than media, which
+    /// [`stream_frames`](crate::stream_frames) sends or skips as
+    /// [`StreamOptions::gap`](crate::StreamOptions::gap) says. Sources
+    /// that insert no gap keep the default of false.
+    fn in_gap(&self) -> bool {
+        // ---
+        false
+    }
+
+    /// Returns the number of complete passes through the input so far.
+    /// Sources without an end keep the default of 0.
+    fn passes_completed(&self) -> u64 {
+        // ---
+        0
+    }
+
+    /// Returns the number of trailing samples dropped so far for not
+    /// making up a full frame (see [`TailPolicy`]), counting every pass.
+    fn discarded_samples(&self) -> u64 {
+        // ---
+        0
+    }
+}
+
+/// Returns the number of 16kHz samples in `duration`.
+pub(crate) fn duration_samples(duration: Duration) -> usize {
+    // ---
+    (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as usize
+}
+
+/// Fades `frame` in over the first `fade` samples of a loop pass and out
+/// over the last `fade`, so the jump from the end back to the start does
+/// not click. Both ramps are linear and reach zero at the pass edges.
+///
+/// # Arguments
+///
+/// * `frame` - Samples to fade in place
+/// * `start` - Position of `frame[0]` in the pass, in samples
+/// * `remaining` - Samples from `frame[0]` to the end of the pass, if known
+/// * `fade` - Length of each ramp in samples
+pub(crate) fn apply_loop_fade(
+    frame: &mut [i16],
+    start: usize,
+    remaining: Option<usize>,
+    fade: usize,
+) {
+    // ---
+    let in_fade_out = remaining.is_some_and(|r| r < frame.len() + fade);
+    if fade == 0 || (start >= fade && !in_fade_out) {
+        return;
+    }
+    for (i, sample) in frame.iter_mut().enumerate() {
+        let mut gain = ((start + i) as f64 / fade as f64).min(1.0);
+        if let Some(remaining) = remaining {
+            gain = gain.min(remaining.saturating_sub(i + 1) as f64 / fade as f64);
+        }
+        *sample = (*sample as f64 * gain).round() as i16;
+    }
+}
+
+/// Frames served from samples already in memory.
+///
+/// By default only complete frames are served and a shorter tail is never
+/// sent; [`with_tail`](Self::with_tail) pads it to a frame instead.
+#[derive(Debug)]
+pub struct PcmFrames<'a> {
+    // ---
+    /// 16kHz mono samples
+    samples: &'a [i16],
+
+    /// Start over from the first frame after the last one
+    looping: bool,
+
+    /// Passes served before the end when looping (0 = no end)
+    loop_count: u64,
+
+    /// Samples faded in and out at each end of a pass when looping
+    fade: usize,
+
+    /// Whether a trailing partial frame is served
+    tail: TailPolicy,
+
+    /// Samples of silence served between passes when looping
+    gap: usize,
+
+    /// The frame last served was part of the gap
+    in_gap: bool,
+
+    /// Passes served to the end so far
+    passes: u64,
+
+    /// Trailing samples left out of the passes so far
+    discarded: u64,
+}
+
+impl<'a> PcmFrames<'a> {
+    // ---
+    /// Serves `samples` (16kHz mono), once or `looping` forever.
+    pub fn new(samples: &'a [i16], looping: bool) -> Self {
+        // ---
+        Self {
+            samples,
+            looping,
+            loop_count: 0,
+            fade: 0,
+            tail: TailPolicy::Discard,
+            gap: 0,
+            in_gap: false,
+            passes: 0,
+            discarded: 0,
+        }
+    }
+
+    /// Handles a trailing partial frame as `tail` says. Padded, it is the
+    /// last frame of every pass, and frame indices (so timestamps) count it
+    /// like any other.
+    pub fn with_tail(mut self, tail: TailPolicy) -> Self {
+        // ---
+        self.tail = tail;
+        self
+    }
+
+    /// When looping, ends after `count` passes rather than going on forever
+    /// (0 keeps looping). No loop gap follows the last pass.
+    pub fn with_loop_count(mut self, count: u64) -> Self {
+        // ---
+        self.loop_count = count;
+        self
+    }
+
+    /// When looping, fades the first and last `fade` of every pass so the
+    /// wrap-around does not click. The frame count is unchanged.
+    pub fn with_loop_fade(mut self, fade: Duration) -> Self {
+        // ---
+        self.fade = duration_samples(fade);
+        self
+    }
+
+    /// When looping, serves `gap` of silence (rounded up to whole frames)
+    /// after every pass before starting over. Frame indices, and with them
+    /// timestamps, run on through the gap.
+    pub fn with_loop_gap(mut self, gap: Duration) -> Self {
+        // ---
+        self.gap = duration_samples(gap);
+        self
+    }
+}
+
+impl FrameSource for PcmFrames<'_> {
+    async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+        // ---
+        let frames = match self.tail {
+            TailPolicy::Discard => self.samples.len() / frame.len(),
+            TailPolicy::PadWithSilence => self.samples.len().div_ceil(frame.len()),
+        } as u64;
+        if frames == 0 || (!self.looping && index >= frames) {
+            return Ok(false);
+        }
+
+        let gap = if self.looping {
+            self.gap.div_ceil(frame.len()) as u64
+        } else {
+            0
+        };
+        if self.looping && self.loop_count > 0 && index >= self.loop_count * (frames + gap) - gap {
+            return Ok(false);
+        }
+        let position = index % (frames + gap);
+        self.in_gap = position >= frames;
+        self.passes = index / (frames + gap) + (position + 1 >= frames) as u64;
+        if self.tail == TailPolicy::Discard {
+            self.discarded = self.passes * (self.samples.len() % frame.len()) as u64;
+        }
+        if self.in_gap {
+            frame.fill(0);
+            return Ok(true);
+        }
+
+        let start = position as usize * frame.len();
+        let end = self.samples.len().min(start + frame.len());
+        frame[..end - start].copy_from_slice(&self.samples[start..end]);
+        frame[end - start..].fill(0);
+        if self.looping {
+            let pass_len = frames as usize * frame.len();
+            apply_loop_fade(frame, start, Some(pass_len - start), self.fade);
+        }
+        Ok(true)
+    }
+
+    fn in_gap(&self) -> bool {
+        // ---
+        self.in_gap
+    }
+
+    fn passes_completed(&self) -> u64 {
+        // ---
+        self.passes
+    }
+
+    fn discarded_samples(&self) -> u64 {
+        // ---
+        self.discarded
+    }
+}
+
+/// Frames served from several decoded files in turn, as one stream.
+///
+/// Each file is framed on its own: its trailing partial frame is dropped or
+/// padded as [`with_tail`](Self::with_tail) says, and the next file starts
+/// on a frame boundary. Frame indices, so sequence numbers and timestamps,
+/// run on across files. Looping starts over at the first file.
+#[derive(Debug)]
+pub struct PlaylistFrames<'a> {
+    // ---
+    /// 16kHz mono audio of each file, in play order
+    files: &'a [AudioData],
+
+    /// Samples in each frame served
+    frame_len: usize,
+
+    /// Frames each file serves, and trailing samples it leaves out
+    file_frames: Vec<(u64, u64)>,
+
+    /// Frames in one pass through the playlist
+    frames: u64,
+
+    /// Trailing samples left out of one pass through the playlist
+    pass_discarded: u64,
+
+    /// Start over from the first file after the last one
+    looping: bool,
+
+    /// Passes served before the end when looping (0 = no end)
+    loop_count: u64,
+
+    /// Whether each file's trailing partial frame is served
+    tail: TailPolicy,
+
+    /// Index of the file the last frame came from
+    current: usize,
+
+    /// Passes served to the end so far
+    passes: u64,
+
+    /// Trailing samples left out of the files so far
+    discarded: u64,
+}
+
+impl<'a> PlaylistFrames<'a> {
+    // ---
+    /// Serves `files` one after another in frames of `frame_len` samples,
+    /// once or `looping` forever.
+    pub fn new(files: &'a [AudioData], frame_len: usize, looping: bool) -> Self {
+        // ---
+        let mut playlist = Self {
+            files,
+            frame_len,
+            file_frames: Vec::with_capacity(files.len()),
+            frames: 0,
+            pass_discarded: 0,
+            looping,
+            loop_count: 0,
+            tail: TailPolicy::Discard,
+            current: 0,
+            passes: 0,
+            discarded: 0,
+        };
+        playlist.count_frames();
+        playlist
+    }
+
+    /// Handles each file's trailing partial frame as `tail` says.
+    pub fn with_tail(mut self, tail: TailPolicy) -> Self {
+        // ---
+        self.tail = tail;
+        self.count_frames();
+        self
+    }
+
+    /// When looping, ends after `count` passes through the playlist rather
+    /// than going on forever (0 keeps looping).
+    pub fn with_loop_count(mut self, count: u64) -> Self {
+        // ---
+        self.loop_count = count;
+        self
+    }
+
+    /// Works out how many frames each file serves under the tail policy,
+    /// and what a pass through the playlist adds up to.
+    fn count_frames(&mut self) {
+        // ---
+        let frame_len = self.frame_len.max(1);
+        self.file_frames.clear();
+        self.file_frames
+            .extend(self.files.iter().map(|file| match self.tail {
+                TailPolicy::Discard => (
+                    (file.samples.len() / frame_len) as u64,
+                    (file.samples.len() % frame_len) as u64,
+                ),
+                TailPolicy::PadWithSilence => (file.samples.len().div_ceil(frame_len) as u64, 0),
+            }));
+        self.frames = self.file_frames.iter().map(|&(frames, _)| frames).sum();
+        self.pass_discarded = self.file_frames.iter().map(|&(_, tail)| tail).sum();
+    }
+}
+
+impl FrameSource for PlaylistFrames<'_> {
+    async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+        // ---
+        anyhow::ensure!(
+            frame.len() == self.frame_len,
+            "playlist framed in {} samples, asked for {}",
+            self.frame_len,
+            frame.len()
+        );
+        let frames = self.frames;
+        if frames == 0 || (!self.looping && index >= frames) {
+            return Ok(false);
+        }
+        if self.looping && self.loop_count > 0 && index >= self.loop_count * frames {
+            return Ok(false);
+        }
+
+        let pass = index / frames;
+        let mut position = index % frames;
+        self.discarded = pass * self.pass_discarded;
+        for (current, &(count, tail)) in self.file_frames.iter().enumerate() {
+            if position >= count {
+                // Already played through this pass
+                self.discarded += tail;
+                position -= count;
+                continue;
+            }
+            self.current = current;
+            if position + 1 == count {
+                self.discarded += tail;
+            }
+            break;
+        }
+        self.passes = pass + (index % frames + 1 == frames) as u64;
+
+        let samples = &self.files[self.current].samples;
+        let start = position as usize * frame.len();
+        let end = samples.len().min(start + frame.len());
+        frame[..end - start].copy_from_slice(&samples[start..end]);
+        frame[end - start..].fill(0);
+        Ok(true)
+    }
+
+    fn passes_completed(&self) -> u64 {
+        // ---
+        self.passes
+    }
+
+    fn discarded_samples(&self) -> u64 {
+        // ---
+        self.discarded
+    }
+}
+
+/// Frames converted incrementally from interleaved signed 16-bit
+/// little-endian PCM read from `R`.
+///
+/// Input at any rate and channel count is downmixed and resampled to 16kHz
+/// mono on the fly, with the same helpers used for files. The stream ends
+/// at EOF; a final partial frame is dropped unless
+/// [`with_tail`](Self::with_tail) pads it.
+#[derive(Debug)]
+pub struct PcmStream<R> {
+    // ---
+    /// PCM input
+    reader: R,
+
+    /// Interleaved channels in the input
+    channels: usize,
+
+    /// Sample rate of the input
+    sample_rate: u32,
+
+    /// Channels that make up the mono signal
+    channel_map: ChannelMap,
+
+    /// Converts the downmixed input to 16kHz
+    resampler: Resampler,
+
+    /// Applied to converted samples
+    gain: Gain,
+
+    /// Bytes read but not yet making up a whole sample frame
+    raw: Vec<u8>,
+
+    /// Converted samples not yet handed out
+    pending: Vec<i16>,
+
+    /// Media position of the next frame in `pending`
+    next_index: u64,
+
+    /// Input bytes consumed so far
+    bytes_read: u64,
+
+    /// Whether a final partial frame is sent
+    tail: TailPolicy,
+
+    /// Trailing samples dropped at EOF
+    discarded: u64,
+
+    /// The reader reported EOF
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin + Send> PcmStream<R> {
+    // ---
+    /// Reads `channels` interleaved channels at `sample_rate` from `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the rate or channel count is zero or above
+    /// [`MAX_STREAM_RATE`] or [`MAX_STREAM_CHANNELS`].
+    pub fn new(reader: R, sample_rate: u32, channels: u16) -> Result<Self> {
+        // ---
+        if sample_rate == 0 || sample_rate > MAX_STREAM_RATE {
+            anyhow::bail!(
+                "PCM stream sample rate {} out of range (1 to {})",
+                sample_rate,
+                MAX_STREAM_RATE
+            );
+        }
+        if channels == 0 || channels > MAX_STREAM_CHANNELS {
+            anyhow::bail!(
+                "PCM stream channel count {} out of range (1 to {})",
+                channels,
+                MAX_STREAM_CHANNELS
+            );
+        }
+
+        Ok(Self {
+            reader,
+            channels: channels as usize,
+            sample_rate,
+            channel_map: ChannelMap::default(),
+            resampler: Resampler::new(sample_rate, SAMPLE_RATE, ResampleQuality::default()),
+            gain: Gain::new(0.0),
+            raw: Vec::with_capacity(READ_CHUNK_BYTES),
+            pending: Vec::new(),
+            next_index: 0,
+            bytes_read: 0,
+            tail: TailPolicy::Discard,
+            discarded: 0,
+            eof: false,
+        })
+    }
+
+    /// Handles a final partial frame at EOF as `tail` says.
+    pub fn with_tail(mut self, tail: TailPolicy) -> Self {
+        // ---
+        self.tail = tail;
+        self
+    }
+
+    /// Converts the input rate with `quality` instead of the default
+    /// [`ResampleQuality::High`].
+    pub fn with_quality(mut self, quality: ResampleQuality) -> Self {
+        // ---
+        self.resampler = Resampler::new(self.sample_rate, SAMPLE_RATE, quality);
+        self
+    }
+
+    /// Keeps the channels selected by `channel_map` instead of mixing all
+    /// of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the input does not have the selected channel.
+    pub fn with_channel_map(mut self, channel_map: ChannelMap) -> Result<Self> {
+        // ---
+        channel_map.check(self.channels as u16)?;
+        self.channel_map = channel_map;
+        Ok(self)
+    }
+
+    /// Applies `gain_db` to the converted samples, clipping at full scale.
+    pub fn with_gain_db(mut self, gain_db: f64) -> Self {
+        // ---
+        self.gain = Gain::new(gain_db);
+        self
+    }
+
+    /// Returns the number of samples the gain has clipped so far.
+    pub fn clipped_samples(&self) -> u64 {
+        // ---
+        self.gain.clipped()
+    }
+
+    /// Returns the number of input bytes consumed so far.
+    pub fn bytes_read(&self) -> u64 {
+        // ---
+        self.bytes_read
+    }
+
+    /// Reads until `pending` holds at least `len` samples; returns false if
+    /// the input ended first.
+    async fn fill(&mut self, len: usize) -> Result<bool> {
+        // ---
+        let mut chunk = [0u8; READ_CHUNK_BYTES];
+        while self.pending.len() < len {
+            if self.eof {
+                return Ok(false);
+            }
+            let read = self
+                .reader
+                .read(&mut chunk)
+                .await
+                .context("failed to read PCM input")?;
+            if read == 0 {
+                let converted = self.pending.len();
+                self.resampler.finish(&mut self.pending);
+                self.gain.apply(&mut self.pending[converted..]);
+                self.eof = true;
+                let tail = self.pending.len() % len;
+                if tail != 0 && self.tail == TailPolicy::PadWithSilence {
+                    info!("Padding {} trailing samples at EOF with silence", tail);
+                    self.pending.resize(self.pending.len() + len - tail, 0);
+                } else if tail != 0 || !self.raw.is_empty() {
+                    self.discarded = tail as u64;
+                    warn!(
+                        "Discarding {} trailing samples at EOF (not enough for a full frame)",
+                        tail
+                    );
+                }
+                continue;
+            }
+            self.bytes_read += read as u64;
+            self.raw.extend_from_slice(&chunk[..read]);
+
+            // Convert whole sample frames; a split one waits for the next read
+            let frame_bytes = 2 * self.channels;
+            let usable = self.raw.len() - self.raw.len() % frame_bytes;
+            let interleaved: Vec<i16> = self.raw[..usable]
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            self.raw.drain(..usable);
+
+            let mono = if self.channels == 1 {
+                interleaved
+            } else {
+                self.channel_map.apply(&interleaved, self.channels)
+            };
+            let converted = self.pending.len();
+            self.resampler.process(&mono, &mut self.pending);
+            self.gain.apply(&mut self.pending[converted..]);
+        }
+        Ok(true)
+    }
+}
+
+impl<R: AsyncRead + Unpin + Send> FrameSource for PcmStream<R> {
+    async fn read_frame(&mut self, index: u64, frame: &mut [i16]) -> Result<bool> {
+        // ---
+        // Frames the pacer skipped still pass through the input
+        while self.next_index < index {
+            if !self.fill(frame.len()).await? {
+                return Ok(false);
+            }
+            self.pending.drain(..frame.len());
+            self.next_index += 1;
+        }
+
+        if !self.fill(frame.len()).await? {
+            return Ok(false);
+        }
+        frame.copy_from_slice(&self.pending[..frame.len()]);
+        self.pending.drain(..frame.len());
+        self.next_index += 1;
+        Ok(true)
+    }
+    fn passes_completed(&self) -> u64 {
+        // ---
+        self.eof as u64
+    }
+
+    fn discarded_samples(&self) -> u64 {
+        // ---
+        self.discarded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use crate::codec::SAMPLES_PER_FRAME;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_pcm_frames_loop_and_end() {
+        // ---
+        let samples: Vec<i16> = (0..2 * SAMPLES_PER_FRAME as i16 + 7).collect();
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+
+        let mut once = PcmFrames::new(&samples, false);
+        assert!(once.read_frame(1, &mut frame).await.unwrap());
+        assert_eq!(frame[0], SAMPLES_PER_FRAME as i16);
+        assert!(!once.read_frame(2, &mut frame).await.unwrap());
+
+        let mut looping = PcmFrames::new(&samples, true);
+        assert!(looping.read_frame(4, &mut frame).await.unwrap());
+        assert_eq!(frame[0], 0);
+    }
+
+    #[tokio::test]
+    async fn test_pcm_frames_pad_tail() {
+        // ---
+        let samples: Vec<i16> = (1..=2 * SAMPLES_PER_FRAME as i16 + 7).collect();
+        let mut frame = vec![-1i16; SAMPLES_PER_FRAME];
+
+        let mut once = PcmFrames::new(&samples, false).with_tail(TailPolicy::PadWithSilence);
+        assert!(once.read_frame(2, &mut frame).await.unwrap());
+        assert_eq!(&frame[..7], &samples[2 * SAMPLES_PER_FRAME..]);
+        assert!(frame[7..].iter().all(|&s| s == 0));
+        assert!(!once.read_frame(3, &mut frame).await.unwrap());
+
+        // The padded frame is the last of each pass
+        let mut looping = PcmFrames::new(&samples, true).with_tail(TailPolicy::PadWithSilence);
+        assert!(looping.read_frame(3, &mut frame).await.unwrap());
+        assert_eq!(frame[0], 1);
+        assert!(looping.read_frame(5, &mut frame).await.unwrap());
+        assert_eq!(frame[0], samples[2 * SAMPLES_PER_FRAME]);
+    }
+
+    #[tokio::test]
+    async fn test_pcm_frames_loop_fade() {
+        // ---
+        // Three frames of a constant level, faded over half a frame
+        let samples = vec![1000i16; 3 * SAMPLES_PER_FRAME];
+        let fade = SAMPLES_PER_FRAME / 2;
+        let mut source = PcmFrames::new(&samples, true)
+            .with_loop_fade(Duration::from_micros(fade as u64 * 1_000_000 / 16_000));
+        let mut frame = vec![0i16; SAMPLES_PER_FRAME];
+
+        // Same number of frames per pass, the first and last ramped
+        let mut pass = Vec::new();
+        for index in 3..6 {
+            assert!(source.read_frame(index, &mut frame).await.unwrap());
+            pass.push(frame.clone());
+        }
+        let first = &pass[0];
+        assert_eq!(first[0], 0);
+        assert_eq!(first[fade / 2], 500);
+        assert!(first.windows(2).take(fade).all(|w| w[0] <= w[1]));
+        assert!(first[fade..].iter().all(|&s| s == 1000));
+        assert!(pass[1].iter().all(|&s| s == 1000));
+        let last = &pass[2];
+        assert_eq!(last[SAMPLES_PER_FRAME - 1], 0);
+        assert_eq!(last[SAMPLES_PER_FRAME - 1 - fade / 2], 500);
+        assert!(last[..SAMPLES_PER_FRAME - fade].iter().all(|&s| s == 1000));
+
+        // Played once, nothing is faded
+        let mut once = PcmFrames::new(&samples, false).with_loop_fade(Duration::from_millis(10));
+        assert!(once.read_frame(0, &mut frame).await.unwrap());
+        assert!(frame.iter().all(|&s| s == 1000));
+    }
+
+    #[tokio::test]
+    async fn test_playlist_frames_run_on_across_files() {
+        // ---
+        // 2 frames and a 7-sample tail, then 3 frames
+        let n = SAMPLES_PER_FRAME;
+        let files = [
+            AudioData::from_samples(vec![1; 2 * n + 7]),
+            AudioData::from_samples(vec![2; 3 * n]),
+        ];
+        let mut frame = vec![0i16; n];
+
+        let mut once = PlaylistFrames::new(&files, n, false);
+        let mut served = Vec::new();
+        for index in 0.. {
+            if !once.read_frame(index, &mut frame).await.unwrap() {
+                break;
+            }
+            assert!(frame.iter().all(|&s| s == frame[0]));
+            served.push(frame[0]);
+        }
+        assert_eq!(served, [1, 1, 2, 2, 2]);
+        assert_eq!(once.passes_completed(), 1);
+        assert_eq!(once.discarded_samples(), 7);
+        assert!(once.read_frame(0, &mut frame[..n / 2]).await.is_err());
+
+        // Padded, the first file's tail is a frame of its own; looping
+        // starts over at the first file
+        let mut looped = PlaylistFrames::new(&files, n, true)
+            .with_tail(TailPolicy::PadWithSilence)
+            .with_loop_count(2);
+        assert!(looped.read_frame(2, &mut frame).await.unwrap());
+        assert_eq!(frame[6], 1);
+        assert_eq!(frame[7], 0);
+        assert!(looped.read_frame(6, &mut frame).await.unwrap());
+        assert_eq!(frame[0], 1);
+        assert_eq!(looped.passes_completed(), 1);
+        assert!(looped.read_frame(11, &mut frame).await.unwrap());
+        assert_eq!(looped.passes_completed(), 2);
+        assert!(!looped.read_frame(12, &mut frame).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pcm_stream_converts_through_small_pipe() {
+        // ---
+        // 0.25s of 48kHz stereo through a pipe smaller than one read, with
+        // left and right at +/-1000 so the downmix is silent
+        let (mut writer, reader) = tokio::io::duplex(100);
+        let frames_48k = 12000;
+        let producer = tokio::spawn(async move {
+            // ---
+            for _ in 0..frames_48k {
+                writer.write_all(&1000i16.to_le_bytes()).await.unwrap();
+                writer.write_all(&(-1000i16).to_le_bytes()).await.unwrap();
+            }
+        });
+
+        let mut stream = PcmStream::new(reader, 48000, 2).expect("stream");
+        let mut frame = vec![7i16; SAMPLES_PER_FRAME];
+        let mut frames = 0;
+
+        // Frame 3 is skipped: read and discarded
+        for index in [0, 1, 2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13] {
+            if !stream.read_frame(index, &mut frame).await.unwrap() {
+                break;
+            }
+            assert!(frame.iter().all(|&s| s == 0));
+            frames += 1;
+        }
+        producer.await.unwrap();
+
+        // 4000 samples at 16kHz make 12 full frames, one of them skipped
+        assert_eq!(frames, 11);
+        assert_eq!(stream.bytes_read(), frames_48k * 4);
+    }
+
+    #[test]
+    fn test_pcm_stream_rejects_bad_format() {
+        // ---
+        assert!(PcmStream::new(tokio::io::empty(), 0, 1).is_err());
+        assert!(PcmStream::new(tokio::io::empty(), 16000, 0).is_err());
+        assert!(PcmStream::new(tokio::io::empty(), MAX_STREAM_RATE + 1, 1).is_err());
+        assert!(PcmStream::new(tokio::io::empty(), 16000, MAX_STREAM_CHANNELS).is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+//! RTP over TCP for [`RtpSender`](crate::RtpSender).
+//!
+//! Each destination gets its own connection, and each packet goes out as
+//! an RFC 4571 frame (see [`append_frame`]). The connection is made on the
+//! first send. A connection the receiver closed is noticed before the
+//! next write, and a write that fails drops it; a later send connects
+//! again, at most once per [`RECONNECT_INTERVAL`], so a receiver that is
+//! down costs the packets sent meanwhile rather than stalling the stream.
+
+use rtp_opus_common::append_frame;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// How long a connection attempt may take before the send fails
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Least time between connection attempts; sends in between fail at once
+pub const RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Socket settings applied to every connection.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TcpOptions {
+    // ---
+    /// Local address to connect from
+    pub local_addr: Option<SocketAddr>,
+
+    /// ToS byte to mark packets with
+    pub tos: Option<u32>,
+
+    /// Socket send buffer to request, in bytes
+    pub send_buffer_bytes: Option<usize>,
+}
+
+/// Connection to one destination.
+#[derive(Debug)]
+pub(crate) struct TcpLink {
+    // ---
+    options: TcpOptions,
+    stream: Option<TcpStream>,
+
+    /// No connection attempt before this
+    next_attempt: Option<Instant>,
+
+    /// Connections made so far
+    connections: u64,
+
+    /// Frames of the batch being written, kept to reuse the allocation
+    frames: Vec<u8>,
+}
+
+impl TcpLink {
+    // ---
+    /// Creates an unconnected link.
+    pub(crate) fn new(options: TcpOptions) -> Self {
+        // ---
+        Self {
+            options,
+            stream: None,
+            next_attempt: None,
+            connections: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Drops the connection, e.g. because the destination moved; the
+    /// next send connects again without waiting.
+    pub(crate) fn disconnect(&mut self) {
+        // ---
+        self.stream = None;
+        self.next_attempt = None;
+    }
+
+    /// Frames `datagrams` and writes them to `target`, connecting first
+    /// if needed. Returns how many were written.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if there is no connection and one cannot be made
+    /// (yet), if the first datagram is too long for a frame, or if the
+    /// write fails; the connection is then dropped.
+    pub(crate) async fn send(
+        &mut self,
+        datagrams: &[Vec<u8>],
+        target: SocketAddr,
+    ) -> io::Result<usize> {
+        // ---
+        let Some(first) = datagrams.first() else {
+            return Ok(0);
+        };
+        self.frames.clear();
+        let count = datagrams
+            .iter()
+            .take_while(|data| append_frame(data, &mut self.frames).is_ok())
+            .count();
+        if count == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} byte packet does not fit in a frame", first.len()),
+            ));
+        }
+
+        if self.stream.as_ref().is_some_and(peer_closed) {
+            info!("TCP connection to {} closed by the receiver", target);
+            self.stream = None;
+        }
+        if self.stream.is_none() {
+            self.connect(target).await?;
+        }
+        let stream = self.stream.as_mut().expect("connected");
+        if let Err(e) = stream.write_all(&self.frames).await {
+            warn!("Lost TCP connection to {}: {}", target, e);
+            self.stream = None;
+            return Err(e);
+        }
+        Ok(count)
+    }
+
+    /// Connects to `target`, unless the last attempt was too recent.
+    async fn connect(&mut self, target: SocketAddr) -> io::Result<()> {
+        // ---
+        let now = Instant::now();
+        if self.next_attempt.is_some_and(|at| now < at) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("not connected to {}, waiting to reconnect", target),
+            ));
+        }
+        self.next_attempt = Some(now + RECONNECT_INTERVAL);
+
+        let socket = if target.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+        if let Some(local_addr) = self.options.local_addr {
+            // The fixed port is reused from one connection to the next
+            socket.set_reuseaddr(true)?;
+            socket.bind(local_addr)?;
+        }
+        if let Some(bytes) = self.options.send_buffer_bytes {
+            socket.set_send_buffer_size(bytes.try_into().unwrap_or(u32::MAX))?;
+        }
+        let stream = tokio::time::timeout(CONNECT_TIMEOUT, socket.connect(target))
+            .await
+            .map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("connecting to {} timed out", target),
+                )
+            })??;
+
+        // Every frame is a packet that should go out now
+        stream.set_nodelay(true)?;
+        if let Some(tos) = self.options.tos {
+            if let Err(e) = socket2::SockRef::from(&stream).set_tos_v4(tos) {
+                debug!("Failed to mark TCP packets to {}: {}", target, e);
+            }
+        }
+
+        self.connections += 1;
+        if self.connections == 1 {
+            info!("Connected to {} over TCP", target);
+        } else {
+            info!("Reconnected to {} over TCP", target);
+        }
+        self.stream = Some(stream);
+        Ok(())
+    }
+}
+
+/// Whether the peer closed or reset `stream`. Receivers send nothing
+/// back, so the only thing to read is the end of the connection.
+fn peer_closed(stream: &TcpStream) -> bool {
+    // ---
+    let mut byte = [0u8; 1];
+    match stream.try_read(&mut byte) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(e) => e.kind() != io::ErrorKind::WouldBlock,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // ---
+    use super::*;
+    use rtp_opus_common::complete_frame;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_link_frames_packets_and_reconnects() {
+        // ---
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = listener.local_addr().unwrap();
+        let mut link = TcpLink::new(TcpOptions::default());
+
+        let datagrams = vec![vec![1, 2, 3], vec![4; 5]];
+        assert_eq!(link.send(&datagrams, target).await.unwrap(), 2);
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = vec![0; 2 + 3 + 2 + 5];
+        stream.read_exact(&mut received).await.unwrap();
+        assert_eq!(complete_frame(&received), Some(3));
+        assert_eq!(received[..5], [0, 3, 1, 2, 3]);
+        assert_eq!(complete_frame(&received[5..]), Some(5));
+
+        // The receiver closes the connection: the next send notices and,
+        // once the reconnect interval has passed, goes over a new one
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let err = link.send(&[vec![9]], target).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+        assert_eq!(link.send(&[vec![9]], target).await.unwrap(), 1);
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = [0u8; 3];
+        stream.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, [0, 1, 9]);
+        assert_eq!(link.connections, 2);
+
+        // Too long for a frame
+        let err = link
+            .send(&[vec![0; u16::MAX as usize + 1]], target)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
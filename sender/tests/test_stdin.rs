@@ -0,0 +1,82 @@
+//! Integration test for raw PCM on stdin.
+//!
+//! Pipes a known s16le byte stream into the sender binary with `--input -`
+//! and checks that every complete frame arrives as one RTP packet and that
+//! the sender exits on its own at EOF.
+
+use std::io::Write;
+use std::net::UdpSocket;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use rtp_opus_common::{compound_packets, is_rtcp, RtpPacket, RTCP_PT_BYE};
+use sender::codec::SAMPLES_PER_FRAME;
+
+/// Input rate, converted to 16kHz by the sender
+const STDIN_RATE: u32 = 48000;
+
+/// Interleaved input channels, downmixed by the sender
+const STDIN_CHANNELS: usize = 2;
+
+/// One second of input makes this many 20ms frames
+const EXPECTED_FRAMES: u16 = 50;
+
+#[test]
+fn test_stdin_pcm_streams_every_frame_then_exits() {
+    // ---
+    let socket = UdpSocket::bind("127.0.0.1:0").expect("bind");
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .expect("timeout");
+
+    // One second of stereo tone, plus a tail too short for another frame
+    let mut pcm = Vec::new();
+    for i in 0..STDIN_RATE as usize + 25 {
+        let sample = ((i as f32 * 0.02).sin() * 8000.0) as i16;
+        for _ in 0..STDIN_CHANNELS {
+            pcm.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sender"))
+        .args(["--input", "-", "--interval-ms", "0", "--color", "never"])
+        .args(["--stdin-rate", &STDIN_RATE.to_string()])
+        .args(["--stdin-channels", &STDIN_CHANNELS.to_string()])
+        .args(["--remote", &socket.local_addr().unwrap().to_string()])
+        .args(["--metrics-bind", "127.0.0.1:0"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn sender");
+
+    // Written from another thread: the pipe fills long before the input ends
+    let mut stdin = child.stdin.take().expect("stdin");
+    let writer = std::thread::spawn(move || stdin.write_all(&pcm));
+    writer.join().unwrap().expect("write stdin");
+    let status = child.wait().expect("wait for sender");
+    assert!(status.success(), "sender exited with {status}");
+
+    let mut buf = [0u8; 2048];
+    for sequence in 0..EXPECTED_FRAMES {
+        // Sender reports share the socket
+        let mut len = socket.recv(&mut buf).expect("packet");
+        while is_rtcp(&buf[..len]) {
+            len = socket.recv(&mut buf).expect("packet");
+        }
+        let packet = RtpPacket::deserialize(&buf[..len]).expect("parse");
+        assert_eq!(packet.sequence, sequence);
+        assert_eq!(packet.timestamp, sequence as u32 * SAMPLES_PER_FRAME as u32);
+    }
+
+    // Nothing beyond the complete frames but RTCP, ending with the BYE
+    socket
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .expect("timeout");
+    let mut said_goodbye = false;
+    while let Ok(len) = socket.recv(&mut buf) {
+        assert!(is_rtcp(&buf[..len]), "media after the last frame");
+        said_goodbye = compound_packets(&buf[..len]).any(|packet| packet[1] == RTCP_PT_BYE);
+    }
+    assert!(said_goodbye);
+}
@@ -3,11 +3,8 @@
 //! Tests the complete sender → receiver pipeline with simulated
 //! network conditions: packet loss, jitter, and reordering.
 
-mod network_simulator;
-
-use network_simulator::{NetworkSimulator, NetworkSimulatorConfig};
 use receiver::{JitterBufferConfig, OpusDecoderWrapper};
-use rtp_opus_common::RtpPacket;
+use rtp_opus_common::{NetworkSimulator, NetworkSimulatorConfig, RtpPacket};
 use sender::OpusEncoderWrapper;
 
 /// Test helper to create a simple audio frame
@@ -60,6 +57,7 @@ fn test_jitter_buffer_in_order() {
     let config = JitterBufferConfig {
         depth_ms: 0, // No delay for testing
         max_packets: 10,
+        ..Default::default()
     };
     
     let mut buffer = JitterBuffer::new(config);
@@ -88,6 +86,7 @@ fn test_jitter_buffer_reordering() {
     let config = JitterBufferConfig {
         depth_ms: 0,
         max_packets: 10,
+        ..Default::default()
     };
     
     let mut buffer = JitterBuffer::new(config);
@@ -116,6 +115,7 @@ fn test_network_simulator_loss() {
         loss_rate: 0.5, // 50% loss
         jitter_ms: 0,
         reorder_rate: 0.0,
+        corrupt_rate: 0.0,
         seed: Some(42), // Deterministic
     };
     
@@ -149,6 +149,7 @@ fn test_network_simulator_jitter() {
         loss_rate: 0.0,
         jitter_ms: 50, // Up to 100ms jitter
         reorder_rate: 0.0,
+        corrupt_rate: 0.0,
         seed: Some(42),
     };
     
@@ -178,6 +179,7 @@ fn test_network_simulator_reordering() {
         loss_rate: 0.0,
         jitter_ms: 0,
         reorder_rate: 0.3, // 30% reordering
+        corrupt_rate: 0.0,
         seed: Some(42),
     };
     
@@ -244,6 +246,7 @@ fn test_end_to_end_with_loss() {
         loss_rate: 0.1, // 10% loss
         jitter_ms: 10,
         reorder_rate: 0.05,
+        corrupt_rate: 0.0,
         seed: Some(42),
     };
     
@@ -298,12 +301,14 @@ fn test_end_to_end_with_jitter_buffer() {
         loss_rate: 0.05,
         jitter_ms: 20,
         reorder_rate: 0.2, // 20% reordering
+        corrupt_rate: 0.0,
         seed: Some(42),
     };
     
     let jitter_config = JitterBufferConfig {
         depth_ms: 0, // No delay for testing
         max_packets: 50,
+        ..Default::default()
     };
     
     let mut sim = NetworkSimulator::new(sim_config);
@@ -361,9 +366,10 @@ fn test_receiver_stats() {
     stats.record_packet(5, false); // Gap: lost 2, 3, 4
     stats.record_packet(4, true);  // Reordered
     
-    assert_eq!(stats.packets_received, 4);
-    assert_eq!(stats.packets_lost, 3);
-    assert_eq!(stats.packets_reordered, 1);
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.packets_received, 4);
+    assert_eq!(snapshot.packets_lost, 3);
+    assert_eq!(snapshot.packets_reordered, 1);
     
     let loss_pct = stats.loss_percentage();
     assert!((loss_pct - 42.86).abs() < 0.1); // 3 lost out of 7 total